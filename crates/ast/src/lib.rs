@@ -92,12 +92,18 @@ impl Root {
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Def {
     Function(Function),
+    TyAlias(TyAlias),
+    Import(Import),
+    Const(Const),
 }
 
 impl AstNode for Def {
     fn cast(node: SyntaxNode, tree: &SyntaxTree) -> Option<Self> {
         match node.kind(tree) {
             NodeKind::Function => Some(Self::Function(Function(node))),
+            NodeKind::TyAlias => Some(Self::TyAlias(TyAlias(node))),
+            NodeKind::Import => Some(Self::Import(Import(node))),
+            NodeKind::Const => Some(Self::Const(Const(node))),
 
             _ => None,
         }
@@ -106,6 +112,9 @@ impl AstNode for Def {
     fn syntax(self) -> SyntaxNode {
         match self {
             Self::Function(function) => function.syntax(),
+            Self::TyAlias(ty_alias) => ty_alias.syntax(),
+            Self::Import(import) => import.syntax(),
+            Self::Const(constant) => constant.syntax(),
         }
     }
 }
@@ -117,10 +126,26 @@ impl Function {
         node(self, tree)
     }
 
+    pub fn attrs(self, tree: &SyntaxTree) -> impl Iterator<Item = Attr> + '_ {
+        nodes(self, tree)
+    }
+
+    pub fn pub_kw(self, tree: &SyntaxTree) -> Option<PubKw> {
+        token(self, tree)
+    }
+
+    pub fn extern_kw(self, tree: &SyntaxTree) -> Option<ExternKw> {
+        token(self, tree)
+    }
+
     pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
         token(self, tree)
     }
 
+    pub fn ty_param_list(self, tree: &SyntaxTree) -> Option<TyParamList> {
+        node(self, tree)
+    }
+
     pub fn param_list(self, tree: &SyntaxTree) -> Option<ParamList> {
         node(self, tree)
     }
@@ -134,10 +159,77 @@ impl Function {
     }
 }
 
+def_ast_node!(TyParamList);
+
+impl TyParamList {
+    pub fn names(self, tree: &SyntaxTree) -> impl Iterator<Item = Ident> + '_ {
+        tokens(self, tree)
+    }
+}
+
+def_ast_node!(TyAlias);
+
+impl TyAlias {
+    pub fn docs(self, tree: &SyntaxTree) -> Option<Docs> {
+        node(self, tree)
+    }
+
+    pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
+        token(self, tree)
+    }
+
+    pub fn ty(self, tree: &SyntaxTree) -> Option<Ty> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(Import);
+
+impl Import {
+    pub fn module_name(self, tree: &SyntaxTree) -> Option<Ident> {
+        self.syntax()
+            .child_tokens(tree)
+            .take_while(|t| t.kind(tree) != TokenKind::Dot)
+            .find_map(|t| Ident::cast(t, tree))
+    }
+
+    pub fn function_name(self, tree: &SyntaxTree) -> Option<Ident> {
+        self.syntax()
+            .child_tokens(tree)
+            .skip_while(|t| t.kind(tree) != TokenKind::Dot)
+            .find_map(|t| Ident::cast(t, tree))
+    }
+}
+
+def_ast_node!(Const);
+
+impl Const {
+    pub fn docs(self, tree: &SyntaxTree) -> Option<Docs> {
+        node(self, tree)
+    }
+
+    pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
+        token(self, tree)
+    }
+
+    pub fn ty(self, tree: &SyntaxTree) -> Option<Ty> {
+        node(self, tree)
+    }
+
+    pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Statement {
     LocalDef(LocalDef),
     ExprStatement(ExprStatement),
+    Assign(Assign),
+    Break(Break),
+    Continue(Continue),
+    Return(Return),
+    Defer(Defer),
 }
 
 impl AstNode for Statement {
@@ -145,6 +237,11 @@ impl AstNode for Statement {
         match node.kind(tree) {
             NodeKind::LocalDef => Some(Self::LocalDef(LocalDef(node))),
             NodeKind::ExprStatement => Some(Self::ExprStatement(ExprStatement(node))),
+            NodeKind::Assign => Some(Self::Assign(Assign(node))),
+            NodeKind::Break => Some(Self::Break(Break(node))),
+            NodeKind::Continue => Some(Self::Continue(Continue(node))),
+            NodeKind::Return => Some(Self::Return(Return(node))),
+            NodeKind::Defer => Some(Self::Defer(Defer(node))),
             _ => None,
         }
     }
@@ -153,6 +250,11 @@ impl AstNode for Statement {
         match self {
             Self::LocalDef(local_def) => local_def.syntax(),
             Self::ExprStatement(expr) => expr.syntax(),
+            Self::Assign(assign) => assign.syntax(),
+            Self::Break(break_statement) => break_statement.syntax(),
+            Self::Continue(continue_statement) => continue_statement.syntax(),
+            Self::Return(return_statement) => return_statement.syntax(),
+            Self::Defer(defer) => defer.syntax(),
         }
     }
 }
@@ -160,13 +262,147 @@ impl AstNode for Statement {
 def_ast_node!(LocalDef);
 
 impl LocalDef {
+    pub fn mut_kw(self, tree: &SyntaxTree) -> Option<MutKw> {
+        token(self, tree)
+    }
+
     pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
         token(self, tree)
     }
 
+    pub fn tuple_pattern(self, tree: &SyntaxTree) -> Option<TuplePattern> {
+        node(self, tree)
+    }
+
+    pub fn ty(self, tree: &SyntaxTree) -> Option<Ty> {
+        node(self, tree)
+    }
+
     pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
         node(self, tree)
     }
+
+    pub fn semicolon(self, tree: &SyntaxTree) -> Option<Semicolon> {
+        token(self, tree)
+    }
+}
+
+def_ast_node!(TuplePattern);
+
+impl TuplePattern {
+    pub fn patterns(self, tree: &SyntaxTree) -> impl Iterator<Item = Pattern> + '_ {
+        nodes(self, tree)
+    }
+}
+
+def_ast_node!(VariantPattern);
+
+impl VariantPattern {
+    pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
+        token(self, tree)
+    }
+
+    pub fn pattern(self, tree: &SyntaxTree) -> Option<Pattern> {
+        node(self, tree)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    Binding(BindingPattern),
+    Literal(LiteralPattern),
+    Tuple(TuplePattern),
+    Variant(VariantPattern),
+}
+
+impl AstNode for Pattern {
+    fn cast(node: SyntaxNode, tree: &SyntaxTree) -> Option<Self> {
+        match node.kind(tree) {
+            NodeKind::BindingPattern => Some(Self::Binding(BindingPattern(node))),
+            NodeKind::LiteralPattern => Some(Self::Literal(LiteralPattern(node))),
+            NodeKind::TuplePattern => Some(Self::Tuple(TuplePattern(node))),
+            NodeKind::VariantPattern => Some(Self::Variant(VariantPattern(node))),
+            _ => None,
+        }
+    }
+
+    fn syntax(self) -> SyntaxNode {
+        match self {
+            Self::Binding(binding_pattern) => binding_pattern.syntax(),
+            Self::Literal(literal_pattern) => literal_pattern.syntax(),
+            Self::Tuple(tuple_pattern) => tuple_pattern.syntax(),
+            Self::Variant(variant_pattern) => variant_pattern.syntax(),
+        }
+    }
+}
+
+def_ast_node!(BindingPattern);
+
+impl BindingPattern {
+    pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
+        token(self, tree)
+    }
+}
+
+def_ast_node!(LiteralPattern);
+
+impl LiteralPattern {
+    /// The `-` in front of a negative integer.
+    pub fn hyphen(self, tree: &SyntaxTree) -> Option<Hyphen> {
+        token(self, tree)
+    }
+
+    pub fn literal(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(Assign);
+
+impl Assign {
+    pub fn target(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+
+    pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
+        nodes(self, tree).nth(1)
+    }
+}
+
+def_ast_node!(Break);
+
+impl Break {
+    pub fn label(self, tree: &SyntaxTree) -> Option<Label> {
+        token(self, tree)
+    }
+
+    pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(Continue);
+
+impl Continue {
+    pub fn label(self, tree: &SyntaxTree) -> Option<Label> {
+        token(self, tree)
+    }
+}
+
+def_ast_node!(Return);
+
+impl Return {
+    pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(Defer);
+
+impl Defer {
+    pub fn expr(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
 }
 
 def_ast_node!(ParamList);
@@ -195,6 +431,11 @@ impl Param {
     pub fn ty(self, tree: &SyntaxTree) -> Option<Ty> {
         node(self, tree)
     }
+
+    /// The value the parameter takes on when a call leaves it out.
+    pub fn default_value(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
 }
 
 def_ast_node!(Ty);
@@ -203,6 +444,35 @@ impl Ty {
     pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
         token(self, tree)
     }
+
+    /// The `(` of a tuple type.
+    pub fn l_paren(self, tree: &SyntaxTree) -> Option<LParen> {
+        token(self, tree)
+    }
+
+    /// The `[` of an array type.
+    pub fn l_bracket(self, tree: &SyntaxTree) -> Option<LBracket> {
+        token(self, tree)
+    }
+
+    /// The element types of a tuple type,
+    /// or the single element type of an array type.
+    pub fn elements(self, tree: &SyntaxTree) -> impl Iterator<Item = Ty> + '_ {
+        nodes(self, tree)
+    }
+
+    /// The `[T, E]` after the name of a generic type.
+    pub fn arg_list(self, tree: &SyntaxTree) -> Option<TyArgList> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(TyArgList);
+
+impl TyArgList {
+    pub fn tys(self, tree: &SyntaxTree) -> impl Iterator<Item = Ty> + '_ {
+        nodes(self, tree)
+    }
 }
 
 def_ast_node!(Docs);
@@ -221,6 +491,26 @@ impl DocComment {
     }
 }
 
+def_ast_node!(Attr);
+
+impl Attr {
+    pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
+        token(self, tree)
+    }
+
+    pub fn arg_list(self, tree: &SyntaxTree) -> Option<AttrArgList> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(AttrArgList);
+
+impl AttrArgList {
+    pub fn args(self, tree: &SyntaxTree) -> impl Iterator<Item = Ident> + '_ {
+        tokens(self, tree)
+    }
+}
+
 def_ast_node!(ExprStatement);
 
 impl ExprStatement {
@@ -232,20 +522,46 @@ impl ExprStatement {
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Expr {
     Binary(BinaryExpr),
+    Unary(UnaryExpr),
+    Cast(CastExpr),
+    Tuple(TupleExpr),
+    Field(FieldExpr),
+    MethodCall(MethodCall),
+    Array(ArrayExpr),
+    Index(IndexExpr),
+    Try(TryExpr),
+    Match(MatchExpr),
     Block(Block),
     Call(Call),
     IntLiteral(IntLiteral),
+    BoolLiteral(BoolLiteral),
     StringLiteral(StringLiteral),
+    CharLiteral(CharLiteral),
+    Loop(Loop),
+    For(For),
 }
 
 impl AstNode for Expr {
     fn cast(node: SyntaxNode, tree: &SyntaxTree) -> Option<Self> {
         match node.kind(tree) {
             NodeKind::BinaryExpr => Some(Self::Binary(BinaryExpr(node))),
+            NodeKind::UnaryExpr => Some(Self::Unary(UnaryExpr(node))),
+            NodeKind::CastExpr => Some(Self::Cast(CastExpr(node))),
+            NodeKind::TupleExpr => Some(Self::Tuple(TupleExpr(node))),
+            NodeKind::FieldExpr => Some(Self::Field(FieldExpr(node))),
+            NodeKind::MethodCall => Some(Self::MethodCall(MethodCall(node))),
+            NodeKind::ArrayExpr => Some(Self::Array(ArrayExpr(node))),
+            NodeKind::IndexExpr => Some(Self::Index(IndexExpr(node))),
+            NodeKind::TryExpr => Some(Self::Try(TryExpr(node))),
+            NodeKind::MatchExpr => Some(Self::Match(MatchExpr(node))),
             NodeKind::Block => Some(Self::Block(Block(node))),
             NodeKind::Call => Some(Self::Call(Call(node))),
             NodeKind::IntLiteral => Some(Self::IntLiteral(IntLiteral(node))),
+            NodeKind::BoolLiteral => Some(Self::BoolLiteral(BoolLiteral(node))),
             NodeKind::StringLiteral => Some(Self::StringLiteral(StringLiteral(node))),
+            NodeKind::CharLiteral => Some(Self::CharLiteral(CharLiteral(node))),
+            NodeKind::Loop => Some(Self::Loop(Loop(node))),
+            NodeKind::For => Some(Self::For(For(node))),
             _ => None,
         }
     }
@@ -253,33 +569,216 @@ impl AstNode for Expr {
     fn syntax(self) -> SyntaxNode {
         match self {
             Self::Binary(binary_expr) => binary_expr.syntax(),
+            Self::Unary(unary_expr) => unary_expr.syntax(),
+            Self::Cast(cast_expr) => cast_expr.syntax(),
+            Self::Tuple(tuple_expr) => tuple_expr.syntax(),
+            Self::Field(field_expr) => field_expr.syntax(),
+            Self::MethodCall(method_call) => method_call.syntax(),
+            Self::Array(array_expr) => array_expr.syntax(),
+            Self::Index(index_expr) => index_expr.syntax(),
+            Self::Try(try_expr) => try_expr.syntax(),
+            Self::Match(match_expr) => match_expr.syntax(),
             Self::Block(block) => block.syntax(),
             Self::Call(call) => call.syntax(),
             Self::IntLiteral(int_literal) => int_literal.syntax(),
+            Self::BoolLiteral(bool_literal) => bool_literal.syntax(),
             Self::StringLiteral(string_literal) => string_literal.syntax(),
+            Self::CharLiteral(char_literal) => char_literal.syntax(),
+            Self::Loop(loop_expr) => loop_expr.syntax(),
+            Self::For(for_expr) => for_expr.syntax(),
         }
     }
 }
 
-def_ast_node!(BinaryExpr);
+def_ast_node!(Loop);
+
+impl Loop {
+    pub fn label(self, tree: &SyntaxTree) -> Option<Label> {
+        token(self, tree)
+    }
+
+    pub fn body(self, tree: &SyntaxTree) -> Option<Block> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(For);
+
+impl For {
+    pub fn label(self, tree: &SyntaxTree) -> Option<Label> {
+        token(self, tree)
+    }
+
+    pub fn variable(self, tree: &SyntaxTree) -> Option<Ident> {
+        token(self, tree)
+    }
+
+    pub fn range_expr(self, tree: &SyntaxTree) -> Option<RangeExpr> {
+        node(self, tree)
+    }
+
+    pub fn body(self, tree: &SyntaxTree) -> Option<Block> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(RangeExpr);
+
+impl RangeExpr {
+    pub fn start(self, tree: &SyntaxTree) -> Option<Expr> {
+        let dot_dot = self.dot_dot(tree)?;
+        nodes(self, tree).find(|expr: &Expr| expr.range(tree).end() <= dot_dot.range(tree).start())
+    }
+
+    pub fn end(self, tree: &SyntaxTree) -> Option<Expr> {
+        let dot_dot = self.dot_dot(tree)?;
+        nodes(self, tree).find(|expr: &Expr| expr.range(tree).start() >= dot_dot.range(tree).end())
+    }
+
+    pub fn dot_dot(self, tree: &SyntaxTree) -> Option<DotDot> {
+        token(self, tree)
+    }
+}
+
+def_ast_node!(BinaryExpr);
+
+impl BinaryExpr {
+    pub fn lhs(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+
+    pub fn rhs(self, tree: &SyntaxTree) -> Option<Expr> {
+        nodes(self, tree).nth(1)
+    }
+
+    pub fn operator(self, tree: &SyntaxTree) -> Option<BinaryOperator> {
+        token(self, tree)
+    }
+}
+
+def_ast_node!(UnaryExpr);
+
+impl UnaryExpr {
+    pub fn expr(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+
+    pub fn operator(self, tree: &SyntaxTree) -> Option<UnaryOperator> {
+        token(self, tree)
+    }
+}
+
+def_ast_node!(CastExpr);
+
+impl CastExpr {
+    pub fn expr(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+
+    pub fn ty(self, tree: &SyntaxTree) -> Option<Ty> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(TupleExpr);
+
+impl TupleExpr {
+    pub fn elements(self, tree: &SyntaxTree) -> impl Iterator<Item = Expr> + '_ {
+        nodes(self, tree)
+    }
+}
+
+def_ast_node!(FieldExpr);
+
+impl FieldExpr {
+    pub fn tuple(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+
+    pub fn index(self, tree: &SyntaxTree) -> Option<Int> {
+        token(self, tree)
+    }
+}
+
+def_ast_node!(MethodCall);
+
+impl MethodCall {
+    pub fn receiver(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+
+    pub fn name(self, tree: &SyntaxTree) -> Option<Ident> {
+        token(self, tree)
+    }
+
+    pub fn arg_list(self, tree: &SyntaxTree) -> Option<ArgList> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(ArrayExpr);
+
+impl ArrayExpr {
+    pub fn elements(self, tree: &SyntaxTree) -> impl Iterator<Item = Expr> + '_ {
+        nodes(self, tree)
+    }
+}
+
+def_ast_node!(IndexExpr);
+
+impl IndexExpr {
+    pub fn array(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+
+    pub fn index(self, tree: &SyntaxTree) -> Option<Expr> {
+        nodes(self, tree).nth(1)
+    }
+}
+
+def_ast_node!(TryExpr);
+
+impl TryExpr {
+    pub fn expr(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
+    }
+}
+
+def_ast_node!(MatchExpr);
 
-impl BinaryExpr {
-    pub fn lhs(self, tree: &SyntaxTree) -> Option<Expr> {
+impl MatchExpr {
+    pub fn value(self, tree: &SyntaxTree) -> Option<Expr> {
         node(self, tree)
     }
 
-    pub fn rhs(self, tree: &SyntaxTree) -> Option<Expr> {
-        nodes(self, tree).nth(1)
+    pub fn arms(self, tree: &SyntaxTree) -> impl Iterator<Item = MatchArm> + '_ {
+        nodes(self, tree)
     }
+}
 
-    pub fn operator(self, tree: &SyntaxTree) -> Option<BinaryOperator> {
-        token(self, tree)
+def_ast_node!(MatchArm);
+
+impl MatchArm {
+    pub fn pattern(self, tree: &SyntaxTree) -> Option<Pattern> {
+        node(self, tree)
+    }
+
+    pub fn body(self, tree: &SyntaxTree) -> Option<Expr> {
+        node(self, tree)
     }
 }
 
 def_ast_node!(Block);
 
 impl Block {
+    pub fn label(self, tree: &SyntaxTree) -> Option<Label> {
+        token(self, tree)
+    }
+
+    pub fn defs(self, tree: &SyntaxTree) -> impl Iterator<Item = Def> + '_ {
+        nodes(self, tree)
+    }
+
     pub fn statements(self, tree: &SyntaxTree) -> impl Iterator<Item = Statement> + '_ {
         nodes(self, tree)
     }
@@ -329,6 +828,18 @@ impl IntLiteral {
     }
 }
 
+def_ast_node!(BoolLiteral);
+
+impl BoolLiteral {
+    pub fn value(self, tree: &SyntaxTree) -> Option<bool> {
+        self.syntax().child_tokens(tree).find_map(|token| match token.kind(tree) {
+            TokenKind::TrueKw => Some(true),
+            TokenKind::FalseKw => Some(false),
+            _ => None,
+        })
+    }
+}
+
 def_ast_node!(StringLiteral);
 
 impl StringLiteral {
@@ -337,12 +848,28 @@ impl StringLiteral {
     }
 }
 
+def_ast_node!(CharLiteral);
+
+impl CharLiteral {
+    pub fn value(self, tree: &SyntaxTree) -> Option<Char> {
+        token(self, tree)
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BinaryOperator {
     Add(Plus),
     Sub(Hyphen),
     Mul(Asterisk),
     Div(Slash),
+    Eq(EqEq),
+    NotEq(BangEq),
+    Lt(Less),
+    LtEq(LessEq),
+    Gt(Greater),
+    GtEq(GreaterEq),
+    And(AmpAmp),
+    Or(PipePipe),
 }
 
 impl AstToken for BinaryOperator {
@@ -352,6 +879,14 @@ impl AstToken for BinaryOperator {
             TokenKind::Hyphen => Some(Self::Sub(Hyphen(token))),
             TokenKind::Asterisk => Some(Self::Mul(Asterisk(token))),
             TokenKind::Slash => Some(Self::Div(Slash(token))),
+            TokenKind::EqEq => Some(Self::Eq(EqEq(token))),
+            TokenKind::BangEq => Some(Self::NotEq(BangEq(token))),
+            TokenKind::Less => Some(Self::Lt(Less(token))),
+            TokenKind::LessEq => Some(Self::LtEq(LessEq(token))),
+            TokenKind::Greater => Some(Self::Gt(Greater(token))),
+            TokenKind::GreaterEq => Some(Self::GtEq(GreaterEq(token))),
+            TokenKind::AmpAmp => Some(Self::And(AmpAmp(token))),
+            TokenKind::PipePipe => Some(Self::Or(PipePipe(token))),
             _ => None,
         }
     }
@@ -362,17 +897,87 @@ impl AstToken for BinaryOperator {
             Self::Sub(hyphen) => hyphen.syntax(),
             Self::Mul(asterisk) => asterisk.syntax(),
             Self::Div(slash) => slash.syntax(),
+            Self::Eq(eq_eq) => eq_eq.syntax(),
+            Self::NotEq(bang_eq) => bang_eq.syntax(),
+            Self::Lt(less) => less.syntax(),
+            Self::LtEq(less_eq) => less_eq.syntax(),
+            Self::Gt(greater) => greater.syntax(),
+            Self::GtEq(greater_eq) => greater_eq.syntax(),
+            Self::And(amp_amp) => amp_amp.syntax(),
+            Self::Or(pipe_pipe) => pipe_pipe.syntax(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOperator {
+    Neg(Hyphen),
+    Not(Bang),
+}
+
+impl AstToken for UnaryOperator {
+    fn cast(token: SyntaxToken, tree: &SyntaxTree) -> Option<Self> {
+        match token.kind(tree) {
+            TokenKind::Hyphen => Some(Self::Neg(Hyphen(token))),
+            TokenKind::Bang => Some(Self::Not(Bang(token))),
+            _ => None,
+        }
+    }
+
+    fn syntax(self) -> SyntaxToken {
+        match self {
+            Self::Neg(hyphen) => hyphen.syntax(),
+            Self::Not(bang) => bang.syntax(),
         }
     }
 }
 
+def_ast_token!(PubKw);
+def_ast_token!(ExternKw);
+def_ast_token!(MutKw);
+def_ast_token!(DotDot);
 def_ast_token!(Plus);
 def_ast_token!(Hyphen);
 def_ast_token!(Asterisk);
 def_ast_token!(Slash);
+def_ast_token!(EqEq);
+def_ast_token!(BangEq);
+def_ast_token!(Less);
+def_ast_token!(LessEq);
+def_ast_token!(Greater);
+def_ast_token!(GreaterEq);
+def_ast_token!(AmpAmp);
+def_ast_token!(PipePipe);
+def_ast_token!(Bang);
+def_ast_token!(Semicolon);
+def_ast_token!(LParen);
+def_ast_token!(LBracket);
 def_ast_token!(Ident);
 def_ast_token!(Int);
 
+impl Int {
+    /// Splits the literal into its radix and the digits after the `0x`, `0o` or `0b` prefix.
+    pub fn radix_and_digits(self, tree: &SyntaxTree) -> (u32, &str) {
+        let text = self.text(tree);
+
+        match text.get(..2) {
+            Some("0x") => (16, &text[2..]),
+            Some("0o") => (8, &text[2..]),
+            Some("0b") => (2, &text[2..]),
+            _ => (10, text),
+        }
+    }
+}
+def_ast_token!(Char);
+def_ast_token!(Label);
+
+impl Label {
+    /// The name of the label, without the `'` in front of it.
+    pub fn name(self, tree: &SyntaxTree) -> &str {
+        &self.text(tree)[1..]
+    }
+}
+
 pub enum StringComponent {
     Escape(Escape),
     Contents(StringContents),
@@ -467,6 +1072,20 @@ mod tests {
         assert_eq!(local_def.name(&tree).unwrap().text(&tree), "a");
     }
 
+    #[test]
+    fn get_ty_of_local_def() {
+        let (tree, root) = parse("let a: s64 = 10;");
+        let statement = root.statements(&tree).next().unwrap();
+
+        let local_def = match statement {
+            Statement::LocalDef(local_def) => local_def,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(local_def.ty(&tree).unwrap().name(&tree).unwrap().text(&tree), "s64");
+        assert!(matches!(local_def.value(&tree), Some(Expr::IntLiteral(_))));
+    }
+
     #[test]
     fn get_value_of_local_def() {
         let (tree, root) = parse("let foo = 5;");
@@ -609,22 +1228,330 @@ mod tests {
         assert!(matches!(block.tail_expr(&tree), Some(Expr::Binary(_))));
     }
 
+    #[test]
+    fn get_block_defs() {
+        let (tree, root) = parse("{ let a = 1; fnc f -> {}; f }");
+
+        let block = match root.tail_expr(&tree) {
+            Some(Expr::Block(block)) => block,
+            _ => unreachable!(),
+        };
+
+        let mut defs = block.defs(&tree);
+        assert!(matches!(defs.next(), Some(Def::Function(_))));
+        assert!(defs.next().is_none());
+
+        assert_eq!(block.statements(&tree).count(), 1);
+        assert!(matches!(block.tail_expr(&tree), Some(Expr::Call(_))));
+    }
+
+    #[test]
+    fn get_cast_expr_expr_and_ty() {
+        let (tree, root) = parse("n as s64");
+
+        let cast_expr = match root.tail_expr(&tree) {
+            Some(Expr::Cast(cast_expr)) => cast_expr,
+            _ => unreachable!(),
+        };
+
+        assert!(matches!(cast_expr.expr(&tree), Some(Expr::Call(_))));
+        assert_eq!(cast_expr.ty(&tree).unwrap().name(&tree).unwrap().text(&tree), "s64");
+    }
+
+    #[test]
+    fn get_tuple_expr_elements() {
+        let (tree, root) = parse("(1, a)");
+
+        let tuple_expr = match root.tail_expr(&tree) {
+            Some(Expr::Tuple(tuple_expr)) => tuple_expr,
+            _ => unreachable!(),
+        };
+
+        let mut elements = tuple_expr.elements(&tree);
+        assert!(matches!(elements.next(), Some(Expr::IntLiteral(_))));
+        assert!(matches!(elements.next(), Some(Expr::Call(_))));
+        assert!(elements.next().is_none());
+    }
+
+    #[test]
+    fn get_field_expr_tuple_and_index() {
+        let (tree, root) = parse("pair.1");
+
+        let field_expr = match root.tail_expr(&tree) {
+            Some(Expr::Field(field_expr)) => field_expr,
+            _ => unreachable!(),
+        };
+
+        assert!(matches!(field_expr.tuple(&tree), Some(Expr::Call(_))));
+        assert_eq!(field_expr.index(&tree).unwrap().text(&tree), "1");
+    }
+
+    #[test]
+    fn get_array_expr_elements() {
+        let (tree, root) = parse("[1, 2]");
+
+        let array_expr = match root.tail_expr(&tree) {
+            Some(Expr::Array(array_expr)) => array_expr,
+            _ => unreachable!(),
+        };
+
+        let elements: Vec<_> = array_expr.elements(&tree).collect();
+        assert_eq!(elements.len(), 2);
+        assert!(matches!(elements[0], Expr::IntLiteral(_)));
+        assert!(matches!(elements[1], Expr::IntLiteral(_)));
+    }
+
+    #[test]
+    fn get_match_expr_value_and_arms() {
+        let (tree, root) = parse("match n { -1 -> a; (_, \"s\") -> b }");
+
+        let match_expr = match root.tail_expr(&tree) {
+            Some(Expr::Match(match_expr)) => match_expr,
+            _ => unreachable!(),
+        };
+
+        assert!(matches!(match_expr.value(&tree), Some(Expr::Call(_))));
+
+        let arms: Vec<_> = match_expr.arms(&tree).collect();
+        assert_eq!(arms.len(), 2);
+
+        let literal_pattern = match arms[0].pattern(&tree) {
+            Some(Pattern::Literal(literal_pattern)) => literal_pattern,
+            _ => unreachable!(),
+        };
+        assert!(literal_pattern.hyphen(&tree).is_some());
+        assert!(matches!(literal_pattern.literal(&tree), Some(Expr::IntLiteral(_))));
+        assert_eq!(arms[0].body(&tree).unwrap().text(&tree), "a");
+
+        let tuple_pattern = match arms[1].pattern(&tree) {
+            Some(Pattern::Tuple(tuple_pattern)) => tuple_pattern,
+            _ => unreachable!(),
+        };
+        let patterns: Vec<_> = tuple_pattern.patterns(&tree).collect();
+        assert!(matches!(patterns[0], Pattern::Binding(_)));
+        assert!(matches!(patterns[1], Pattern::Literal(_)));
+        assert_eq!(arms[1].body(&tree).unwrap().text(&tree), "b");
+    }
+
+    #[test]
+    fn get_index_expr_array_and_index() {
+        let (tree, root) = parse("xs[i]");
+
+        let index_expr = match root.tail_expr(&tree) {
+            Some(Expr::Index(index_expr)) => index_expr,
+            _ => unreachable!(),
+        };
+
+        let array = match index_expr.array(&tree) {
+            Some(Expr::Call(call)) => call,
+            _ => unreachable!(),
+        };
+        assert_eq!(array.top_level_name(&tree).unwrap().text(&tree), "xs");
+
+        let index = match index_expr.index(&tree) {
+            Some(Expr::Call(call)) => call,
+            _ => unreachable!(),
+        };
+        assert_eq!(index.top_level_name(&tree).unwrap().text(&tree), "i");
+    }
+
+    #[test]
+    fn get_try_expr_expr() {
+        let (tree, root) = parse("{f x}?");
+
+        let try_expr = match root.tail_expr(&tree) {
+            Some(Expr::Try(try_expr)) => try_expr,
+            _ => unreachable!(),
+        };
+        assert!(matches!(try_expr.expr(&tree), Some(Expr::Block(_))));
+    }
+
+    #[test]
+    fn get_loop_and_jump_labels() {
+        let (tree, root) = parse("'outer: loop { break 'outer; continue; }");
+
+        let loop_expr = match root.tail_expr(&tree) {
+            Some(Expr::Loop(loop_expr)) => loop_expr,
+            _ => unreachable!(),
+        };
+        assert_eq!(loop_expr.label(&tree).unwrap().name(&tree), "outer");
+
+        let mut statements = loop_expr.body(&tree).unwrap().statements(&tree);
+
+        let Some(Statement::Break(break_statement)) = statements.next() else { unreachable!() };
+        assert_eq!(break_statement.label(&tree).unwrap().name(&tree), "outer");
+
+        let Some(Statement::Continue(continue_statement)) = statements.next() else {
+            unreachable!()
+        };
+        assert!(continue_statement.label(&tree).is_none());
+    }
+
+    #[test]
+    fn get_variant_pattern_name_and_pattern() {
+        let (tree, root) = parse("match o { Some x -> x; None -> 0 }");
+
+        let match_expr = match root.tail_expr(&tree) {
+            Some(Expr::Match(match_expr)) => match_expr,
+            _ => unreachable!(),
+        };
+        let arms: Vec<_> = match_expr.arms(&tree).collect();
+
+        let variant_pattern = match arms[0].pattern(&tree) {
+            Some(Pattern::Variant(variant_pattern)) => variant_pattern,
+            _ => unreachable!(),
+        };
+        assert_eq!(variant_pattern.name(&tree).unwrap().text(&tree), "Some");
+        assert!(matches!(variant_pattern.pattern(&tree), Some(Pattern::Binding(_))));
+
+        assert!(matches!(arms[1].pattern(&tree), Some(Pattern::Binding(_))));
+    }
+
+    #[test]
+    fn get_ty_arg_list() {
+        let (tree, root) = parse("let r: Result[s32, string] = x;");
+
+        let local_def = match root.statements(&tree).next() {
+            Some(Statement::LocalDef(local_def)) => local_def,
+            _ => unreachable!(),
+        };
+
+        let ty = local_def.ty(&tree).unwrap();
+        assert_eq!(ty.name(&tree).unwrap().text(&tree), "Result");
+        assert!(ty.l_bracket(&tree).is_none());
+
+        let args: Vec<_> = ty.arg_list(&tree).unwrap().tys(&tree).collect();
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[0].name(&tree).unwrap().text(&tree), "s32");
+        assert_eq!(args[1].name(&tree).unwrap().text(&tree), "string");
+    }
+
+    #[test]
+    fn get_array_ty_element() {
+        let (tree, root) = parse("let xs: [s32] = [];");
+
+        let local_def = match root.statements(&tree).next() {
+            Some(Statement::LocalDef(local_def)) => local_def,
+            _ => unreachable!(),
+        };
+
+        let ty = local_def.ty(&tree).unwrap();
+        assert!(ty.l_bracket(&tree).is_some());
+
+        let element = ty.elements(&tree).next().unwrap();
+        assert_eq!(element.name(&tree).unwrap().text(&tree), "s32");
+    }
+
+    #[test]
+    fn get_tuple_pattern_and_ty_of_local_def() {
+        let (tree, root) = parse("let (a, b): (s32, bool) = pair;");
+
+        let local_def = match root.statements(&tree).next() {
+            Some(Statement::LocalDef(local_def)) => local_def,
+            _ => unreachable!(),
+        };
+
+        assert!(local_def.name(&tree).is_none());
+
+        let names: Vec<_> = local_def
+            .tuple_pattern(&tree)
+            .unwrap()
+            .patterns(&tree)
+            .map(|pattern| match pattern {
+                Pattern::Binding(binding_pattern) => {
+                    binding_pattern.name(&tree).unwrap().text(&tree)
+                }
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(names, ["a", "b"]);
+
+        let ty = local_def.ty(&tree).unwrap();
+        assert!(ty.name(&tree).is_none());
+        assert!(ty.l_paren(&tree).is_some());
+
+        let elements: Vec<_> =
+            ty.elements(&tree).map(|ty| ty.name(&tree).unwrap().text(&tree)).collect();
+        assert_eq!(elements, ["s32", "bool"]);
+    }
+
     #[test]
     fn get_function_name() {
         let (tree, root) = parse("fnc a -> {};");
         let def = root.defs(&tree).next().unwrap();
 
-        let Def::Function(function) = def;
+        let Def::Function(function) = def else { unreachable!() };
 
         assert_eq!(function.name(&tree).unwrap().text(&tree), "a");
     }
 
+    #[test]
+    fn get_ty_alias_name_and_ty() {
+        let (tree, root) = parse("type Meters = s32;");
+        let def = root.defs(&tree).next().unwrap();
+
+        let Def::TyAlias(ty_alias) = def else { unreachable!() };
+
+        assert_eq!(ty_alias.name(&tree).unwrap().text(&tree), "Meters");
+        assert_eq!(ty_alias.ty(&tree).unwrap().name(&tree).unwrap().text(&tree), "s32");
+    }
+
+    #[test]
+    fn get_import_module_and_function_name() {
+        let (tree, root) = parse("use io.print;");
+        let def = root.defs(&tree).next().unwrap();
+
+        let Def::Import(import) = def else { unreachable!() };
+
+        assert_eq!(import.module_name(&tree).unwrap().text(&tree), "io");
+        assert_eq!(import.function_name(&tree).unwrap().text(&tree), "print");
+    }
+
+    #[test]
+    fn get_import_without_module_name() {
+        let (tree, root) = parse("use .print;");
+        let def = root.defs(&tree).next().unwrap();
+
+        let Def::Import(import) = def else { unreachable!() };
+
+        assert!(import.module_name(&tree).is_none());
+        assert_eq!(import.function_name(&tree).unwrap().text(&tree), "print");
+    }
+
+    #[test]
+    fn get_const_name_ty_and_value() {
+        let (tree, root) = parse("const MAX: s64 = 100;");
+        let def = root.defs(&tree).next().unwrap();
+
+        let Def::Const(constant) = def else { unreachable!() };
+
+        assert_eq!(constant.name(&tree).unwrap().text(&tree), "MAX");
+        assert_eq!(constant.ty(&tree).unwrap().name(&tree).unwrap().text(&tree), "s64");
+        assert!(matches!(constant.value(&tree), Some(Expr::IntLiteral(_))));
+    }
+
+    #[test]
+    fn get_function_ty_params() {
+        let (tree, root) = parse("fnc pair[A, B](a: A, b: B): (A, B) -> (a, b);");
+        let def = root.defs(&tree).next().unwrap();
+
+        let Def::Function(function) = def else { unreachable!() };
+
+        let names: Vec<_> = function
+            .ty_param_list(&tree)
+            .unwrap()
+            .names(&tree)
+            .map(|name| name.text(&tree))
+            .collect();
+        assert_eq!(names, ["A", "B"]);
+    }
+
     #[test]
     fn get_function_params() {
         let (tree, root) = parse("fnc add(x: s32, y: s32) -> {};");
         let def = root.defs(&tree).next().unwrap();
 
-        let Def::Function(function) = def;
+        let Def::Function(function) = def else { unreachable!() };
 
         let mut params = function.param_list(&tree).unwrap().params(&tree);
 
@@ -639,12 +1566,28 @@ mod tests {
         assert!(params.next().is_none());
     }
 
+    #[test]
+    fn get_param_default_value() {
+        let (tree, root) = parse("fnc greet(name: string = \"world\", times: s32) -> {};");
+        let def = root.defs(&tree).next().unwrap();
+
+        let Def::Function(function) = def else { unreachable!() };
+
+        let mut params = function.param_list(&tree).unwrap().params(&tree);
+
+        let param = params.next().unwrap();
+        let Some(Expr::StringLiteral(_)) = param.default_value(&tree) else { unreachable!() };
+
+        let param = params.next().unwrap();
+        assert!(param.default_value(&tree).is_none());
+    }
+
     #[test]
     fn get_function_return_ty() {
         let (tree, root) = parse("fnc four: s32 -> 4;");
         let def = root.defs(&tree).next().unwrap();
 
-        let Def::Function(function) = def;
+        let Def::Function(function) = def else { unreachable!() };
 
         assert_eq!(
             function.return_ty(&tree).unwrap().ty(&tree).unwrap().name(&tree).unwrap().text(&tree),
@@ -657,7 +1600,7 @@ mod tests {
         let (tree, root) = parse("fnc nothing -> {};");
         let def = root.defs(&tree).next().unwrap();
 
-        let Def::Function(function) = def;
+        let Def::Function(function) = def else { unreachable!() };
 
         let block = match function.body(&tree).unwrap() {
             Expr::Block(block) => block,
@@ -679,7 +1622,7 @@ mod tests {
         );
         let def = root.defs(&tree).next().unwrap();
 
-        let Def::Function(function) = def;
+        let Def::Function(function) = def else { unreachable!() };
 
         let docs = function.docs(&tree).unwrap();
         let mut doc_comments = docs.doc_comments(&tree);
@@ -694,4 +1637,64 @@ mod tests {
         );
         assert!(doc_comments.next().is_none());
     }
+
+    #[test]
+    fn get_ty_alias_and_const_docs() {
+        let (tree, root) = parse(
+            "
+                ## A point on the screen.
+                type Point = (s32, s32);
+                ## The width of the screen.
+                const WIDTH = 80;
+                const HEIGHT = 24;
+            ",
+        );
+        let mut defs = root.defs(&tree);
+
+        let Some(Def::TyAlias(ty_alias)) = defs.next() else { unreachable!() };
+        let docs = ty_alias.docs(&tree).unwrap();
+        let mut doc_comments = docs.doc_comments(&tree);
+        assert_eq!(
+            doc_comments.next().unwrap().contents(&tree).unwrap().text(&tree),
+            " A point on the screen."
+        );
+        assert!(doc_comments.next().is_none());
+
+        let Some(Def::Const(constant)) = defs.next() else { unreachable!() };
+        let docs = constant.docs(&tree).unwrap();
+        let mut doc_comments = docs.doc_comments(&tree);
+        assert_eq!(
+            doc_comments.next().unwrap().contents(&tree).unwrap().text(&tree),
+            " The width of the screen."
+        );
+        assert!(doc_comments.next().is_none());
+
+        let Some(Def::Const(constant)) = defs.next() else { unreachable!() };
+        assert!(constant.docs(&tree).is_none());
+    }
+
+    #[test]
+    fn get_function_attrs() {
+        let (tree, root) = parse(
+            "
+                #[test]
+                #[allow(unneeded_parens, unused)]
+                fnc check -> {};
+            ",
+        );
+        let Some(Def::Function(function)) = root.defs(&tree).next() else { unreachable!() };
+        let mut attrs = function.attrs(&tree);
+
+        let attr = attrs.next().unwrap();
+        assert_eq!(attr.name(&tree).unwrap().text(&tree), "test");
+        assert!(attr.arg_list(&tree).is_none());
+
+        let attr = attrs.next().unwrap();
+        assert_eq!(attr.name(&tree).unwrap().text(&tree), "allow");
+        let args: Vec<_> =
+            attr.arg_list(&tree).unwrap().args(&tree).map(|arg| arg.text(&tree)).collect();
+        assert_eq!(args, ["unneeded_parens", "unused"]);
+
+        assert!(attrs.next().is_none());
+    }
 }
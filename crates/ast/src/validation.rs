@@ -1,7 +1,13 @@
-use crate::{AstNode, Function};
-use syntax::SyntaxTree;
+use crate::{
+    AstNode, AstToken, Attr, CharLiteral, Const, FieldExpr, Function, Import, IntLiteral, TyAlias,
+};
+use syntax::{SyntaxTree, TokenKind};
 use text_size::TextRange;
 
+/// Words which aren’t keywords yet but might become ones,
+/// so they can’t be used as names.
+pub const RESERVED_KEYWORDS: [&str; 7] = ["if", "else", "while", "enum", "struct", "trait", "impl"];
+
 pub fn validate(ast: impl AstNode, tree: &SyntaxTree) -> Vec<ValidationDiagnostic> {
     let mut diagnostics = Vec::new();
 
@@ -16,11 +22,98 @@ pub fn validate(ast: impl AstNode, tree: &SyntaxTree) -> Vec<ValidationDiagnosti
                 }
             }
         }
+
+        // the parser accepts attributes before any definition
+        if TyAlias::cast(node, tree).is_some()
+            || Import::cast(node, tree).is_some()
+            || Const::cast(node, tree).is_some()
+        {
+            for attr in node.child_nodes(tree).filter_map(|child| Attr::cast(child, tree)) {
+                diagnostics.push(ValidationDiagnostic {
+                    kind: ValidationDiagnosticKind::MisplacedAttr,
+                    range: attr.range(tree),
+                });
+            }
+        }
+
+        if let Some(int_literal) = IntLiteral::cast(node, tree) {
+            if let Some(kind) = int_literal.value(tree).and_then(|int| {
+                let (radix, digits) = int.radix_and_digits(tree);
+                validate_int(radix, digits)
+            }) {
+                diagnostics.push(ValidationDiagnostic { kind, range: int_literal.range(tree) });
+            }
+        }
+
+        // tuple indices are plain decimal numbers, like in `pair.0`
+        if let Some(index) = FieldExpr::cast(node, tree).and_then(|f| f.index(tree)) {
+            if index.text(tree).parse::<u32>().is_err() || index.radix_and_digits(tree).0 != 10 {
+                diagnostics.push(ValidationDiagnostic {
+                    kind: ValidationDiagnosticKind::InvalidTupleIndex,
+                    range: index.range(tree),
+                });
+            }
+        }
+
+        if let Some(char_literal) = CharLiteral::cast(node, tree) {
+            if let Some(kind) = char_literal.value(tree).and_then(|c| validate_char(c.text(tree))) {
+                diagnostics.push(ValidationDiagnostic { kind, range: char_literal.range(tree) });
+            }
+        }
+    }
+
+    for token in ast.syntax().descendant_tokens(tree) {
+        if token.kind(tree) != TokenKind::Ident {
+            continue;
+        }
+
+        let text = token.text(tree);
+        if let Some(keyword) = RESERVED_KEYWORDS.iter().find(|keyword| **keyword == text) {
+            diagnostics.push(ValidationDiagnostic {
+                kind: ValidationDiagnosticKind::ReservedKeyword { name: keyword },
+                range: token.range(tree),
+            });
+        }
     }
 
     diagnostics
 }
 
+fn validate_int(radix: u32, digits: &str) -> Option<ValidationDiagnosticKind> {
+    if digits.is_empty() {
+        return Some(ValidationDiagnosticKind::MissingDigitsInIntLiteral);
+    }
+
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return Some(ValidationDiagnosticKind::InvalidDigitInIntLiteral { radix });
+    }
+
+    None
+}
+
+fn validate_char(text: &str) -> Option<ValidationDiagnosticKind> {
+    let contents = &text[1..];
+    let Some(contents) = contents.strip_suffix('\'') else {
+        return Some(ValidationDiagnosticKind::UnclosedCharLiteral);
+    };
+
+    // an escape is made up of two characters but stands for one
+    let mut len = 0;
+    let mut chars = contents.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        }
+        len += 1;
+    }
+
+    match len {
+        0 => Some(ValidationDiagnosticKind::EmptyCharLiteral),
+        1 => None,
+        _ => Some(ValidationDiagnosticKind::MultipleCharsInCharLiteral),
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ValidationDiagnostic {
     pub kind: ValidationDiagnosticKind,
@@ -30,6 +123,14 @@ pub struct ValidationDiagnostic {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ValidationDiagnosticKind {
     UnneededParens,
+    MissingDigitsInIntLiteral,
+    InvalidDigitInIntLiteral { radix: u32 },
+    EmptyCharLiteral,
+    MultipleCharsInCharLiteral,
+    UnclosedCharLiteral,
+    InvalidTupleIndex,
+    ReservedKeyword { name: &'static str },
+    MisplacedAttr,
 }
 
 #[cfg(test)]
@@ -84,6 +185,92 @@ mod validation_tests {
         check_repl_line("4294967295", []);
     }
 
+    #[test]
+    fn validate_prefixed_int_literals() {
+        check_repl_line("0xFFFFFFFF + 0o17 + 0b101 + 0x0", []);
+    }
+
+    #[test]
+    fn validate_prefixed_int_literal_without_digits() {
+        check_repl_line("0x", [(ValidationDiagnosticKind::MissingDigitsInIntLiteral, 0..2)]);
+    }
+
+    #[test]
+    fn validate_prefixed_int_literals_with_invalid_digits() {
+        check_repl_line(
+            "0b102 + 0o8 + 0xfg",
+            [
+                (ValidationDiagnosticKind::InvalidDigitInIntLiteral { radix: 2 }, 0..5),
+                (ValidationDiagnosticKind::InvalidDigitInIntLiteral { radix: 8 }, 8..11),
+                (ValidationDiagnosticKind::InvalidDigitInIntLiteral { radix: 16 }, 14..18),
+            ],
+        );
+    }
+
+    #[test]
+    fn validate_char_literals() {
+        check_repl_line(r"'a'; 'é'; '\n'; '\''", []);
+    }
+
+    #[test]
+    fn validate_empty_char_literal() {
+        check_repl_line("''", [(ValidationDiagnosticKind::EmptyCharLiteral, 0..2)]);
+    }
+
+    #[test]
+    fn validate_char_literal_with_multiple_chars() {
+        check_repl_line(
+            r"'ab'; '\n\n'",
+            [
+                (ValidationDiagnosticKind::MultipleCharsInCharLiteral, 0..4),
+                (ValidationDiagnosticKind::MultipleCharsInCharLiteral, 6..12),
+            ],
+        );
+    }
+
+    #[test]
+    fn validate_unclosed_char_literal() {
+        check_repl_line("'1", [(ValidationDiagnosticKind::UnclosedCharLiteral, 0..2)]);
+    }
+
+    #[test]
+    fn validate_tuple_indices() {
+        check_repl_line(
+            "pair.0; pair.12; pair.0x1; pair.99999999999",
+            [
+                (ValidationDiagnosticKind::InvalidTupleIndex, 22..25),
+                (ValidationDiagnosticKind::InvalidTupleIndex, 32..43),
+            ],
+        );
+    }
+
+    #[test]
+    fn validate_reserved_keywords() {
+        check_source_file(
+            "fnc if(while: s32): s32 -> { let enum = while; enum };",
+            [
+                (ValidationDiagnosticKind::ReservedKeyword { name: "if" }, 4..6),
+                (ValidationDiagnosticKind::ReservedKeyword { name: "while" }, 7..12),
+                (ValidationDiagnosticKind::ReservedKeyword { name: "enum" }, 33..37),
+                (ValidationDiagnosticKind::ReservedKeyword { name: "while" }, 40..45),
+                (ValidationDiagnosticKind::ReservedKeyword { name: "enum" }, 47..51),
+            ],
+        );
+    }
+
+    #[test]
+    fn validate_misplaced_attrs() {
+        check_source_file(
+            "#[test] fnc f -> {}; #[a] type T = s32; #[b] use m.f; #[c] #[d] const C = 1;",
+            [
+                (ValidationDiagnosticKind::MisplacedAttr, 21..25),
+                (ValidationDiagnosticKind::MisplacedAttr, 40..44),
+                (ValidationDiagnosticKind::MisplacedAttr, 54..58),
+                (ValidationDiagnosticKind::MisplacedAttr, 59..63),
+            ],
+        );
+    }
+
     #[test]
     fn validate_unneeded_parens_on_function() {
         check_source_file(
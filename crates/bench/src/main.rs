@@ -55,14 +55,53 @@ fn main() {
                     fnc add(x: s32, y: s32): s32 -> x + y;
                     fnc main: s32 -> add 10, 20 + 30;
                 "#,
-                BenchOptions { should_compile: true, runs: 5_000_000 },
+                BenchOptions { should_compile: true, should_eval: false, runs: 5_000_000 },
             )
             .run();
         }
 
         Some("long") => {
             let input = gen::gen(16 << 10 << 10); // 16 MiB
-            Bench::new(&input, BenchOptions { should_compile: false, runs: 20 }).run();
+            Bench::new(
+                &input,
+                BenchOptions { should_compile: false, should_eval: false, runs: 20 },
+            )
+            .run();
+        }
+
+        // copying strings around should be as cheap as copying numbers,
+        // however long they are
+        Some("strings") => {
+            let input = format!(
+                r#"
+                    fnc main: s32 -> {{
+                        let s = "{}";
+                        let mut copied = 0;
+                        for i in 0..100000 {{
+                            let copies = [s, s, s, s, s, s, s, s];
+                            let pair = (copies[i - i], copies[7]);
+                            copied = copied + 10;
+                        }};
+                        copied
+                    }};
+                "#,
+                "a".repeat(1000)
+            );
+            Bench::new(&input, BenchOptions { should_compile: false, should_eval: true, runs: 20 })
+                .run();
+        }
+
+        // every line is evaluated against all the ones before it,
+        // which shouldn’t get slower the more lines there were
+        Some("repl") => {
+            let times = repl(20_000);
+            let tenth = times.len() / 10;
+            let average = |times: &[Duration]| times.iter().sum::<Duration>() / times.len() as u32;
+
+            println!("{} lines", times.len());
+            println!("first tenth {:>15?} per line", average(&times[..tenth]));
+            println!("last tenth  {:>15?} per line", average(&times[times.len() - tenth..]));
+            println!("total       {:>15?}", times.iter().sum::<Duration>());
         }
 
         Some(_) => eprintln!("Unrecognized benchmark name"),
@@ -71,8 +110,53 @@ fn main() {
     }
 }
 
+// Runs `lines` lines like a REPL would,
+// with each line in a module of its own so that it’s checked on its own,
+// and the program they make up so far borrowed for evaluating it.
+// Returns how long each line took.
+fn repl(lines: usize) -> Vec<Duration> {
+    let mut interner = Interner::default();
+    let mut world_index = hir::WorldIndex::default();
+    let mut bodies_map = FxHashMap::default();
+    let mut evaluator = eval::Evaluator::default();
+    let value = hir::Name(interner.intern("value"));
+
+    (0..lines)
+        .map(|n| {
+            let line = match n {
+                0 => "pub fnc answer: s32 -> 0; pub fnc value: s32 -> answer;".to_string(),
+                _ => format!(
+                    "pub fnc answer: s32 -> {n}; pub fnc value: s32 -> line{}.answer;",
+                    n - 1
+                ),
+            };
+            let now = Instant::now();
+
+            let tokens = lexer::lex(&line);
+            let tree = parser::parse_repl_line(&tokens, &line).into_syntax_tree();
+            let root = ast::Root::cast(tree.root(), &tree).unwrap();
+
+            let module = hir::Name(interner.intern(&format!("line{n}")));
+            let (index, _diagnostics) = hir::index(root, &tree, &world_index, &mut interner);
+            world_index.add_module(module, index.clone());
+
+            let (bodies, _diagnostics) =
+                hir::lower(root, &tree, &index, &world_index, &mut interner);
+            let (inference, _diagnostics) = hir_ty::infer_all(&bodies, &index, &world_index);
+            bodies_map.insert(module, hir_ty::thir::lower(bodies, inference));
+
+            let fqn = hir::Fqn { module, function: value };
+            let val = evaluator.eval(fqn, &bodies_map, &world_index);
+            assert_eq!(val, Ok(eval::Val::S32(n.saturating_sub(1) as i32)));
+
+            now.elapsed()
+        })
+        .collect()
+}
+
 struct BenchOptions {
     should_compile: bool,
+    should_eval: bool,
     runs: usize,
 }
 
@@ -122,23 +206,22 @@ impl<'a> Bench<'a> {
         let (inference, _diagnostics) =
             self.stage("infer", || hir_ty::infer_all(&bodies, &index, &world_index));
 
-        if self.options.should_compile {
+        if self.options.should_compile || self.options.should_eval {
             let main = hir::Name(interner.intern("main"));
-            world_index.add_module(main, index);
+            let main = hir::Fqn { module: main, function: main };
+            world_index.add_module(main.module, index);
 
             let mut bodies_map = FxHashMap::default();
-            let mut tys_map = FxHashMap::default();
-            bodies_map.insert(main, bodies);
-            tys_map.insert(main, inference);
-
-            let _wasm = self.stage("compile", || {
-                eval::compile(
-                    hir::Fqn { module: main, function: main },
-                    bodies_map.clone(),
-                    tys_map.clone(),
-                    &world_index,
-                )
-            });
+            bodies_map.insert(main.module, hir_ty::thir::lower(bodies, inference));
+
+            if self.options.should_compile {
+                let _wasm = self
+                    .stage("compile", || eval::compile(main, &bodies_map, &world_index, &interner));
+            }
+
+            if self.options.should_eval {
+                let _val = self.stage("eval", || eval::eval(main, &bodies_map, &world_index));
+            }
         }
 
         // MB/s == B/µs
@@ -178,3 +261,64 @@ impl<'a> Bench<'a> {
         result.unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: usize = 100_000;
+
+    // Both budgets are far more than the front end needs,
+    // even in a debug build on a slow machine,
+    // but far less than a pass that is quadratic in the input would take.
+    const TIME_BUDGET: Duration = Duration::from_secs(if cfg!(debug_assertions) { 60 } else { 10 });
+    const MEMORY_BUDGET: usize = 1 << 30; // 1 GiB
+
+    #[test]
+    fn repl_scales_linearly() {
+        let times = repl(LINES / 5);
+        let elapsed = times.iter().sum::<Duration>();
+
+        assert!(
+            elapsed <= TIME_BUDGET,
+            "running {} lines took {elapsed:?}, which is over the budget of {TIME_BUDGET:?}",
+            times.len()
+        );
+    }
+
+    #[test]
+    fn front_end_scales_linearly() {
+        // generated programs average well under 32 bytes per line
+        let input = gen::gen(LINES * 32);
+        let lines = input.lines().count();
+        assert!(lines >= LINES, "only generated {lines} lines");
+
+        let initial_mem_usage = GLOBAL.total_size.load(Ordering::SeqCst);
+        let now = Instant::now();
+
+        let tokens = lexer::lex(&input);
+        let tree = parser::parse_source_file(&tokens, &input).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let _diagnostics = ast::validation::validate(root, &tree);
+
+        let world_index = hir::WorldIndex::default();
+        let mut interner = Interner::default();
+        let (index, _diagnostics) = hir::index(root, &tree, &world_index, &mut interner);
+        let (bodies, _diagnostics) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+        let (_inference, _diagnostics) = hir_ty::infer_all(&bodies, &index, &world_index);
+
+        let elapsed = now.elapsed();
+        let mem_usage = GLOBAL.total_size.load(Ordering::SeqCst) - initial_mem_usage;
+
+        assert!(
+            elapsed <= TIME_BUDGET,
+            "checking {lines} lines took {elapsed:?}, which is over the budget of {TIME_BUDGET:?}"
+        );
+        assert!(
+            mem_usage <= MEMORY_BUDGET,
+            "checking {lines} lines used {} MB, which is over the budget of {} MB",
+            mem_usage / 1_000_000,
+            MEMORY_BUDGET / 1_000_000
+        );
+    }
+}
@@ -0,0 +1,977 @@
+//! Lowers the typed HIR of a program into bytecode
+//! for the stack machine in `eval` to run.
+//!
+//! Every function becomes a flat list of [`Instruction`]s
+//! working on a stack of values and on numbered slots
+//! holding the function’s parameters and locals.
+//! Where every jump goes is worked out here,
+//! including which deferred expressions it runs on its way,
+//! so that running the bytecode never has to search for it.
+
+use arena::{ArenaMap, Id};
+use hir_ty::thir;
+use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+use text_size::TextRange;
+
+/// The functions reachable from an entry point.
+#[derive(Debug, Clone)]
+pub struct Program {
+    /// The entry point comes first,
+    /// followed by the functions it calls in the order they were first called.
+    pub functions: Vec<Function>,
+    /// The strings [`Instruction::PushString`] refers to.
+    pub strings: Vec<String>,
+    /// The patterns [`Instruction::Match`] refers to.
+    pub patterns: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub fqn: hir::Fqn,
+    /// The parameters take up the first slots,
+    /// followed by the locals and the counters of `for` loops.
+    pub slot_count: u32,
+    pub code: Vec<Instruction>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    PushS32(i32),
+    PushS64(i64),
+    PushU32(u32),
+    PushBool(bool),
+    PushChar(char),
+    PushString(u32),
+    PushNil,
+    /// A variant without a field.
+    PushVariant(hir::Variant),
+    /// Throws away the given number of values.
+    Pop(u32),
+    /// Throws away the given number of values below the one on top.
+    Slide(u32),
+    Load(u32),
+    Store(u32),
+    Unary {
+        operator: hir::UnaryOperator,
+        range: TextRange,
+    },
+    /// Integer arithmetic, which fails at `range` if it overflows or divides by zero.
+    Arithmetic {
+        operator: hir::BinaryOperator,
+        range: TextRange,
+    },
+    Compare(hir::BinaryOperator),
+    Cast(hir::Ty),
+    /// Collects the given number of values into a tuple.
+    Tuple(u32),
+    Field(u32),
+    /// Collects the given number of values into an array.
+    Array(u32),
+    /// Wraps the value on top of the stack in a variant.
+    Variant(hir::Variant),
+    /// Picks the element at the index on top of the stack out of the array below it.
+    Index,
+    /// Stores the elements of the tuple on top of the stack in the given slots.
+    Destructure(Vec<Option<u32>>),
+    Jump(u32),
+    /// Jumps if the bool on top of the stack is `value`, keeping it,
+    /// and throws it away otherwise.
+    JumpIfKeep {
+        value: bool,
+        target: u32,
+    },
+    /// Starts the next iteration of a `for` loop
+    /// by storing the number in the `counter` slot in `local` and incrementing it,
+    /// or jumps to `exit` once it has reached the number in the `end` slot.
+    ForNext {
+        counter: u32,
+        end: u32,
+        local: u32,
+        exit: u32,
+    },
+    /// Takes the value on top of the stack and binds the locals of the pattern
+    /// if it matches, and otherwise jumps to `otherwise`, keeping the value.
+    Match {
+        pattern: u32,
+        otherwise: u32,
+    },
+    /// Skips over the default of a parameter the call passed.
+    SkipIfPassed {
+        param: u32,
+        target: u32,
+    },
+    Call {
+        function: u32,
+        arg_count: u32,
+        range: TextRange,
+    },
+    /// Leaves the function to call another one in its place,
+    /// which returns straight to the caller.
+    TailCall {
+        function: u32,
+        arg_count: u32,
+        range: TextRange,
+    },
+    CallExtern {
+        fqn: hir::Fqn,
+        arg_count: u32,
+        range: TextRange,
+    },
+    CallBuiltin {
+        builtin: hir::Builtin,
+        arg_count: u32,
+    },
+    /// Fails at `range` with the message on top of the stack
+    /// if the condition below it is false.
+    Assert {
+        range: TextRange,
+    },
+    /// Leaves the function with the value on top of the stack.
+    Return,
+    /// Reports the value on top of the stack as the value of the expression at `range`.
+    Trace {
+        range: TextRange,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Bind(u32),
+    Int(i128),
+    Bool(bool),
+    String(String),
+    Char(char),
+    Tuple(Vec<Pattern>),
+    Variant { variant: hir::Variant, field: Option<Box<Pattern>> },
+}
+
+/// Lowers `entry` and every function it can end up calling.
+///
+/// With `trace` every expression is followed by [`Instruction::Trace`],
+/// which calls in tail position have to return for,
+/// so they don’t become [`Instruction::TailCall`].
+pub fn lower(
+    entry: hir::Fqn,
+    bodies_map: &FxHashMap<hir::Name, thir::Bodies>,
+    world_index: &hir::WorldIndex,
+    trace: bool,
+) -> Program {
+    let mut ctx = Ctx {
+        bodies_map,
+        world_index,
+        trace,
+        program: Program { functions: Vec::new(), strings: Vec::new(), patterns: Vec::new() },
+        function_idxs: FxHashMap::default(),
+        functions_to_lower: VecDeque::new(),
+        module: entry.module,
+        code: Vec::new(),
+        slots: ArenaMap::default(),
+        slot_count: 0,
+        depth: 0,
+        scopes: Vec::new(),
+    };
+
+    ctx.function_idx(entry);
+    while let Some(fqn) = ctx.functions_to_lower.pop_front() {
+        ctx.lower_function(fqn);
+    }
+
+    ctx.program
+}
+
+struct Ctx<'a> {
+    bodies_map: &'a FxHashMap<hir::Name, thir::Bodies>,
+    world_index: &'a hir::WorldIndex,
+    trace: bool,
+    program: Program,
+    function_idxs: FxHashMap<hir::Fqn, u32>,
+    functions_to_lower: VecDeque<hir::Fqn>,
+    // the module of the function being lowered
+    module: hir::Name,
+    code: Vec<Instruction>,
+    slots: ArenaMap<Id<hir::LocalDef>, u32>,
+    slot_count: u32,
+    // how many values the function has on the stack at this point,
+    // which tells jumps how many to throw away
+    depth: u32,
+    scopes: Vec<Scope>,
+}
+
+// what jumps inside of the function being lowered can leave
+enum Scope {
+    // the deferred expressions of the statements lowered so far
+    Block { deferred: Vec<Id<hir::Expr>> },
+    Loop { label: Option<hir::Name>, depth: u32, continue_target: u32, breaks: Vec<usize> },
+    LabeledBlock { label: hir::Name, depth: u32, breaks: Vec<usize> },
+}
+
+impl<'a> Ctx<'a> {
+    fn function_idx(&mut self, fqn: hir::Fqn) -> u32 {
+        if let Some(idx) = self.function_idxs.get(&fqn) {
+            return *idx;
+        }
+
+        // functions are lowered in the order they are first referenced,
+        // which keeps `Program::functions` in sync with these indexes
+        let idx = self.function_idxs.len() as u32;
+        self.function_idxs.insert(fqn, idx);
+        self.functions_to_lower.push_back(fqn);
+
+        idx
+    }
+
+    fn lower_function(&mut self, fqn: hir::Fqn) {
+        self.module = fqn.module;
+        self.slots = ArenaMap::default();
+        self.depth = 0;
+
+        let param_count = self.world_index.get_function(fqn).unwrap().params.len() as u32;
+        self.slot_count = param_count;
+
+        // the parameters the call left out take on their defaults
+        for param in 0..param_count {
+            if let Some(default) = self.bodies().param_default(fqn.function, param) {
+                let skip = self.emit(Instruction::SkipIfPassed { param, target: 0 });
+                self.lower_expr(default);
+                self.emit(Instruction::Store(param));
+                self.patch(skip);
+            }
+        }
+
+        self.lower_expr(self.bodies().function_body(fqn.function));
+        self.emit(Instruction::Return);
+
+        self.program.functions.push(Function {
+            fqn,
+            slot_count: self.slot_count,
+            code: std::mem::take(&mut self.code),
+        });
+    }
+
+    fn lower_expr(&mut self, expr: Id<hir::Expr>) {
+        self.lower_expr_value(expr);
+
+        if self.trace {
+            let range = self.bodies().range_for_expr(expr);
+            self.emit(Instruction::Trace { range });
+        }
+    }
+
+    fn lower_expr_value(&mut self, expr: Id<hir::Expr>) {
+        let bodies = self.bodies();
+        let thir::Expr { kind, ty } = &bodies[expr];
+
+        match kind {
+            hir::Expr::Missing => unreachable!("tried to lower a program with errors"),
+
+            hir::Expr::IntLiteral(_) => self.push_int_literal(expr),
+
+            hir::Expr::BoolLiteral(b) => {
+                self.emit(Instruction::PushBool(*b));
+            }
+
+            hir::Expr::StringLiteral(s) => self.push_string(s.clone()),
+
+            hir::Expr::CharLiteral(c) => {
+                self.emit(Instruction::PushChar(*c));
+            }
+
+            hir::Expr::Unit => {
+                self.emit(Instruction::PushNil);
+            }
+
+            hir::Expr::Binary { lhs, rhs, operator } => match operator {
+                hir::BinaryOperator::And | hir::BinaryOperator::Or => {
+                    self.lower_expr(*lhs);
+                    let value = matches!(operator, hir::BinaryOperator::Or);
+                    let jump = self.emit(Instruction::JumpIfKeep { value, target: 0 });
+                    self.lower_expr(*rhs);
+                    self.patch(jump);
+                }
+                _ => {
+                    self.lower_expr(*lhs);
+                    self.lower_expr(*rhs);
+
+                    if operator.is_comparison() {
+                        self.emit(Instruction::Compare(*operator));
+                    } else {
+                        let range = bodies.range_for_expr(expr);
+                        self.emit(Instruction::Arithmetic { operator: *operator, range });
+                    }
+                }
+            },
+
+            hir::Expr::Unary { .. } if bodies.int_literal(expr).is_some() => {
+                self.push_int_literal(expr);
+            }
+
+            hir::Expr::Unary { expr: operand, operator } => {
+                self.lower_expr(*operand);
+                let range = bodies.range_for_expr(expr);
+                self.emit(Instruction::Unary { operator: *operator, range });
+            }
+
+            hir::Expr::Cast { expr, ty } => {
+                self.lower_expr(*expr);
+                self.emit(Instruction::Cast(ty.expand_aliases()));
+            }
+
+            hir::Expr::Tuple(elements) => {
+                for element in elements {
+                    self.lower_expr(*element);
+                }
+                self.emit(Instruction::Tuple(elements.len() as u32));
+            }
+
+            hir::Expr::Field { tuple, idx } => {
+                self.lower_expr(*tuple);
+                self.emit(Instruction::Field(*idx));
+            }
+
+            hir::Expr::Array(elements) => {
+                for element in elements {
+                    self.lower_expr(*element);
+                }
+                self.emit(Instruction::Array(elements.len() as u32));
+            }
+
+            hir::Expr::Index { array, index } => {
+                self.lower_expr(*array);
+                self.lower_expr(*index);
+                self.emit(Instruction::Index);
+            }
+
+            hir::Expr::Block { statements, tail_expr } => {
+                self.scopes.push(Scope::Block { deferred: Vec::new() });
+
+                for statement in statements {
+                    self.lower_statement(*statement);
+                }
+
+                match tail_expr {
+                    Some(tail_expr) => self.lower_expr(*tail_expr),
+                    None => {
+                        self.emit(Instruction::PushNil);
+                    }
+                }
+
+                let Some(Scope::Block { deferred }) = self.scopes.pop() else { unreachable!() };
+                self.lower_deferred(deferred.into_iter().rev());
+            }
+
+            hir::Expr::Local(local_def) => {
+                let slot = self.slot(*local_def);
+                self.emit(Instruction::Load(slot));
+            }
+
+            hir::Expr::Param { idx } => {
+                self.emit(Instruction::Load(*idx));
+            }
+
+            // type checking has already worked out the value
+            hir::Expr::Const(name) => match bodies.const_value(*name) {
+                Some(hir_ty::ConstValue::Int(n)) => {
+                    self.emit(match ty {
+                        hir::Ty::S64 => Instruction::PushS64(*n),
+                        hir::Ty::U32 => Instruction::PushU32(*n as u32),
+                        _ => Instruction::PushS32(*n as i32),
+                    });
+                }
+                Some(hir_ty::ConstValue::Bool(b)) => {
+                    self.emit(Instruction::PushBool(*b));
+                }
+                Some(hir_ty::ConstValue::String(s)) => self.push_string(s.clone()),
+                Some(hir_ty::ConstValue::Char(c)) => {
+                    self.emit(Instruction::PushChar(*c));
+                }
+                None => unreachable!("tried to lower a program with errors"),
+            },
+
+            hir::Expr::Loop { label, body } => {
+                let depth = self.depth;
+                let continue_target = self.code.len() as u32;
+                self.scopes.push(Scope::Loop {
+                    label: *label,
+                    depth,
+                    continue_target,
+                    breaks: Vec::new(),
+                });
+
+                self.lower_expr(*body);
+                self.emit(Instruction::Pop(1));
+                self.emit(Instruction::Jump(continue_target));
+
+                self.finish_scope(depth);
+            }
+
+            hir::Expr::For { label, local_def, start, end, body } => {
+                self.lower_expr(*start);
+                self.lower_expr(*end);
+
+                let end = self.new_slot();
+                let counter = self.new_slot();
+                self.emit(Instruction::Store(end));
+                self.emit(Instruction::Store(counter));
+
+                let depth = self.depth;
+                let continue_target = self.code.len() as u32;
+                let local = self.slot(*local_def);
+                let next = self.emit(Instruction::ForNext { counter, end, local, exit: 0 });
+                self.scopes.push(Scope::Loop {
+                    label: *label,
+                    depth,
+                    continue_target,
+                    breaks: Vec::new(),
+                });
+
+                self.lower_expr(*body);
+                self.emit(Instruction::Pop(1));
+                self.emit(Instruction::Jump(continue_target));
+
+                self.patch(next);
+                self.emit(Instruction::PushNil);
+
+                self.finish_scope(depth);
+            }
+
+            hir::Expr::LabeledBlock { label, body } => {
+                let depth = self.depth;
+                self.scopes.push(Scope::LabeledBlock { label: *label, depth, breaks: Vec::new() });
+
+                self.lower_expr(*body);
+
+                self.finish_scope(depth);
+            }
+
+            hir::Expr::Call { path, args } => {
+                for arg in args {
+                    self.lower_expr(*arg);
+                }
+                let arg_count = args.len() as u32;
+                let range = bodies.range_for_expr(expr);
+
+                let fqn = match path {
+                    hir::Path::Builtin(hir::Builtin::Assert) => {
+                        self.emit(Instruction::Assert { range });
+                        return;
+                    }
+                    hir::Path::Builtin(builtin) => {
+                        self.emit(Instruction::CallBuiltin { builtin: *builtin, arg_count });
+                        return;
+                    }
+                    hir::Path::ThisModule(function) => {
+                        hir::Fqn { module: self.module, function: *function }
+                    }
+                    hir::Path::OtherModule(fqn) => *fqn,
+                };
+
+                if self.world_index.get_function(fqn).unwrap().is_extern {
+                    self.emit(Instruction::CallExtern { fqn, arg_count, range });
+                } else if bodies.is_tail_call(expr) && !self.trace {
+                    let function = self.function_idx(fqn);
+                    self.emit(Instruction::TailCall { function, arg_count, range });
+                } else {
+                    let function = self.function_idx(fqn);
+                    self.emit(Instruction::Call { function, arg_count, range });
+                }
+            }
+
+            hir::Expr::Variant { variant, value: Some(value) } => {
+                self.lower_expr(*value);
+                self.emit(Instruction::Variant(*variant));
+            }
+
+            hir::Expr::Variant { variant, value: None } => {
+                self.emit(Instruction::PushVariant(*variant));
+            }
+
+            hir::Expr::Match { value, arms } => {
+                self.lower_expr(*value);
+                let depth = self.depth;
+                let mut ends = Vec::new();
+
+                // exhaustiveness checking guarantees one of the arms matches,
+                // so the last one never jumps
+                for arm in arms {
+                    let pattern = self.lower_pattern(arm.pattern);
+                    self.program.patterns.push(pattern);
+                    let pattern = self.program.patterns.len() as u32 - 1;
+
+                    let test = self.emit(Instruction::Match { pattern, otherwise: 0 });
+                    self.lower_expr(arm.body);
+                    ends.push(self.emit(Instruction::Jump(0)));
+
+                    self.patch(test);
+                    self.depth = depth;
+                }
+
+                for end in ends {
+                    self.patch(end);
+                }
+            }
+        }
+    }
+
+    fn lower_statement(&mut self, statement: Id<hir::Statement>) {
+        let bodies = self.bodies();
+        let depth = self.depth;
+
+        match &bodies[statement] {
+            hir::Statement::Expr(expr) => {
+                self.lower_expr(*expr);
+                self.emit(Instruction::Pop(1));
+            }
+            hir::Statement::LocalDef(local_def) => {
+                self.lower_expr(bodies[*local_def].value);
+                let slot = self.slot(*local_def);
+                self.emit(Instruction::Store(slot));
+            }
+            hir::Statement::Destructure { value, local_defs, .. } => {
+                self.lower_expr(*value);
+                let slots =
+                    local_defs.iter().map(|local_def| local_def.map(|l| self.slot(l))).collect();
+                self.emit(Instruction::Destructure(slots));
+            }
+            hir::Statement::Assign { local_def, value } => {
+                self.lower_expr(*value);
+                let slot = self.slot(*local_def);
+                self.emit(Instruction::Store(slot));
+            }
+            hir::Statement::Break { label, value } => {
+                match value {
+                    Some(value) => self.lower_expr(*value),
+                    None => {
+                        self.emit(Instruction::PushNil);
+                    }
+                }
+                self.lower_break(*label);
+            }
+            hir::Statement::Continue { label } => self.lower_continue(*label),
+            hir::Statement::Return { value } => {
+                match value {
+                    Some(value) => self.lower_expr(*value),
+                    None => {
+                        self.emit(Instruction::PushNil);
+                    }
+                }
+                self.lower_deferred_of_scopes(0);
+                self.emit(Instruction::Return);
+            }
+            hir::Statement::Defer(expr) => match self.scopes.last_mut() {
+                Some(Scope::Block { deferred }) => deferred.push(*expr),
+                _ => unreachable!("statements are always inside of a block"),
+            },
+        }
+
+        // whatever comes after a jump is never reached,
+        // so it can carry on as if the jump wasn’t there
+        self.depth = depth;
+    }
+
+    fn lower_break(&mut self, label: Option<hir::Name>) {
+        let idx = self
+            .scopes
+            .iter()
+            .rposition(|scope| match scope {
+                Scope::Loop { label: loop_label, .. } => label.is_none() || *loop_label == label,
+                Scope::LabeledBlock { label: block_label, .. } => label == Some(*block_label),
+                Scope::Block { .. } => false,
+            })
+            .expect("lowering only allows jumps inside of loops");
+        self.lower_deferred_of_scopes(idx + 1);
+
+        let depth = match &self.scopes[idx] {
+            Scope::Loop { depth, .. } | Scope::LabeledBlock { depth, .. } => *depth,
+            Scope::Block { .. } => unreachable!(),
+        };
+        // the value being broken with stays on top
+        let extra = self.depth - depth - 1;
+        if extra != 0 {
+            self.emit(Instruction::Slide(extra));
+        }
+
+        let jump = self.emit(Instruction::Jump(0));
+        match &mut self.scopes[idx] {
+            Scope::Loop { breaks, .. } | Scope::LabeledBlock { breaks, .. } => breaks.push(jump),
+            Scope::Block { .. } => unreachable!(),
+        }
+    }
+
+    fn lower_continue(&mut self, label: Option<hir::Name>) {
+        let idx = self
+            .scopes
+            .iter()
+            .rposition(|scope| match scope {
+                Scope::Loop { label: loop_label, .. } => label.is_none() || *loop_label == label,
+                Scope::LabeledBlock { .. } | Scope::Block { .. } => false,
+            })
+            .expect("lowering only allows continuing loops");
+        self.lower_deferred_of_scopes(idx + 1);
+
+        let Scope::Loop { depth, continue_target, .. } = self.scopes[idx] else { unreachable!() };
+        let extra = self.depth - depth;
+        if extra != 0 {
+            self.emit(Instruction::Pop(extra));
+        }
+        self.emit(Instruction::Jump(continue_target));
+    }
+
+    // runs what the blocks from `scopes[start]` inwards deferred so far,
+    // innermost block first
+    fn lower_deferred_of_scopes(&mut self, start: usize) {
+        let deferred: Vec<_> = self.scopes[start..]
+            .iter()
+            .rev()
+            .flat_map(|scope| match scope {
+                Scope::Block { deferred } => deferred.iter().rev().copied().collect(),
+                Scope::Loop { .. } | Scope::LabeledBlock { .. } => Vec::new(),
+            })
+            .collect();
+
+        self.lower_deferred(deferred);
+    }
+
+    fn lower_deferred(&mut self, deferred: impl IntoIterator<Item = Id<hir::Expr>>) {
+        for expr in deferred {
+            self.lower_expr(expr);
+            self.emit(Instruction::Pop(1));
+        }
+    }
+
+    // finishes the loop or labeled block that started with `depth` values on the stack,
+    // which leaves its value on top of them
+    fn finish_scope(&mut self, depth: u32) {
+        let breaks = match self.scopes.pop() {
+            Some(Scope::Loop { breaks, .. } | Scope::LabeledBlock { breaks, .. }) => breaks,
+            _ => unreachable!(),
+        };
+
+        for jump in breaks {
+            self.patch(jump);
+        }
+        self.depth = depth + 1;
+    }
+
+    // a negated literal is pushed as a literal of its own,
+    // which type checking has made sure fits its type
+    fn push_int_literal(&mut self, expr: Id<hir::Expr>) {
+        let bodies = self.bodies();
+        let n = bodies.int_literal(expr).unwrap();
+        self.emit(match bodies[expr].ty {
+            hir::Ty::S64 => Instruction::PushS64(n as i64),
+            hir::Ty::U32 => Instruction::PushU32(n as u32),
+            _ => Instruction::PushS32(n as i32),
+        });
+    }
+
+    fn lower_pattern(&mut self, pattern: Id<hir::Pattern>) -> Pattern {
+        match &self.bodies()[pattern] {
+            hir::Pattern::Missing => unreachable!("type checking rejects missing patterns"),
+            hir::Pattern::Wildcard => Pattern::Wildcard,
+            hir::Pattern::Bind(local_def) => Pattern::Bind(self.slot(*local_def)),
+            hir::Pattern::IntLiteral { value, is_negated } => {
+                let value = *value as i128;
+                Pattern::Int(if *is_negated { -value } else { value })
+            }
+            hir::Pattern::BoolLiteral(b) => Pattern::Bool(*b),
+            hir::Pattern::StringLiteral(s) => Pattern::String(s.clone()),
+            hir::Pattern::CharLiteral(c) => Pattern::Char(*c),
+            hir::Pattern::Tuple(patterns) => Pattern::Tuple(
+                patterns.iter().map(|pattern| self.lower_pattern(*pattern)).collect(),
+            ),
+            hir::Pattern::Variant { variant, field } => Pattern::Variant {
+                variant: *variant,
+                field: field.map(|field| Box::new(self.lower_pattern(field))),
+            },
+        }
+    }
+
+    fn push_string(&mut self, s: String) {
+        self.program.strings.push(s);
+        let idx = self.program.strings.len() as u32 - 1;
+        self.emit(Instruction::PushString(idx));
+    }
+
+    fn slot(&mut self, local_def: Id<hir::LocalDef>) -> u32 {
+        if let Some(slot) = self.slots.get(local_def) {
+            return *slot;
+        }
+
+        let slot = self.new_slot();
+        self.slots.insert(local_def, slot);
+        slot
+    }
+
+    fn new_slot(&mut self) -> u32 {
+        self.slot_count += 1;
+        self.slot_count - 1
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        let effect = match &instruction {
+            Instruction::PushS32(_)
+            | Instruction::PushS64(_)
+            | Instruction::PushU32(_)
+            | Instruction::PushBool(_)
+            | Instruction::PushChar(_)
+            | Instruction::PushString(_)
+            | Instruction::PushNil
+            | Instruction::PushVariant(_)
+            | Instruction::Load(_) => 1,
+            Instruction::Pop(count) | Instruction::Slide(count) => -(*count as i32),
+            Instruction::Tuple(len) | Instruction::Array(len) => 1 - *len as i32,
+            Instruction::Call { arg_count, .. }
+            | Instruction::TailCall { arg_count, .. }
+            | Instruction::CallExtern { arg_count, .. }
+            | Instruction::CallBuiltin { arg_count, .. } => 1 - *arg_count as i32,
+            Instruction::Unary { .. }
+            | Instruction::Cast(_)
+            | Instruction::Field(_)
+            | Instruction::Variant(_)
+            | Instruction::Jump(_)
+            | Instruction::ForNext { .. }
+            | Instruction::SkipIfPassed { .. }
+            | Instruction::Trace { .. } => 0,
+            // when they carry on to the next instruction
+            Instruction::JumpIfKeep { .. } | Instruction::Match { .. } => -1,
+            Instruction::Store(_)
+            | Instruction::Arithmetic { .. }
+            | Instruction::Compare(_)
+            | Instruction::Index
+            | Instruction::Destructure(_)
+            | Instruction::Assert { .. }
+            | Instruction::Return => -1,
+        };
+        self.depth = self.depth.checked_add_signed(effect).unwrap();
+
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    // points the jump at `idx` at the next instruction
+    fn patch(&mut self, idx: usize) {
+        let next = self.code.len() as u32;
+
+        match &mut self.code[idx] {
+            Instruction::Jump(target)
+            | Instruction::JumpIfKeep { target, .. }
+            | Instruction::ForNext { exit: target, .. }
+            | Instruction::Match { otherwise: target, .. }
+            | Instruction::SkipIfPassed { target, .. } => *target = next,
+            _ => unreachable!("only jumps can be patched"),
+        }
+    }
+
+    fn bodies(&self) -> &'a thir::Bodies {
+        &self.bodies_map[&self.module]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+    use expect_test::{expect, Expect};
+    use interner::Interner;
+    use std::fmt::Write;
+
+    fn check(input: &str, expect: Expect) {
+        let mut interner = Interner::default();
+        let mut world_index = hir::WorldIndex::default();
+
+        let tokens = lexer::lex(input);
+        let tree = parser::parse_source_file(&tokens, input).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = hir::index(root, &tree, &world_index, &mut interner);
+
+        let main = hir::Name(interner.intern("main"));
+        world_index.add_module(main, index.clone());
+
+        let (bodies, diagnostics) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+        assert!(diagnostics.is_empty());
+        let (inference, diagnostics) = hir_ty::infer_all(&bodies, &index, &world_index);
+        assert!(diagnostics.is_empty());
+
+        let bodies_map = [(main, thir::lower(bodies, inference))].into_iter().collect();
+        let main = hir::Fqn { module: main, function: main };
+        let program = lower(main, &bodies_map, &world_index, false);
+
+        let mut actual = String::new();
+        for function in &program.functions {
+            writeln!(actual, "{}:", interner.lookup(function.fqn.function.0)).unwrap();
+            for (idx, instruction) in function.code.iter().enumerate() {
+                writeln!(actual, "  {idx}: {instruction:?}").unwrap();
+            }
+        }
+        expect.assert_eq(&actual);
+    }
+
+    #[test]
+    fn calls_and_params() {
+        check(
+            r#"
+                fnc main: s32 -> add 1, 2;
+                fnc add(a: s32, b: s32): s32 -> a + b;
+            "#,
+            expect![[r#"
+                main:
+                  0: PushS32(1)
+                  1: PushS32(2)
+                  2: TailCall { function: 1, arg_count: 2, range: 34..42 }
+                  3: Return
+                add:
+                  0: Load(0)
+                  1: Load(1)
+                  2: Arithmetic { operator: Add, range: 92..97 }
+                  3: Return
+            "#]],
+        );
+    }
+
+    #[test]
+    fn tail_calls() {
+        check(
+            r#"
+                fnc main: s32 -> {
+                    let n = count 3;
+                    count n
+                };
+                fnc count(n: s32): s32 -> {
+                    n == 0 && { return 0; };
+                    defer n;
+                    count n - 1
+                };
+            "#,
+            expect![[r#"
+                main:
+                  0: PushS32(3)
+                  1: Call { function: 1, arg_count: 1, range: 64..71 }
+                  2: Store(0)
+                  3: Load(0)
+                  4: TailCall { function: 1, arg_count: 1, range: 93..100 }
+                  5: Return
+                count:
+                  0: Load(0)
+                  1: PushS32(0)
+                  2: Compare(Eq)
+                  3: JumpIfKeep { value: false, target: 7 }
+                  4: PushS32(0)
+                  5: Return
+                  6: PushNil
+                  7: Pop(1)
+                  8: Load(0)
+                  9: PushS32(1)
+                  10: Arithmetic { operator: Sub, range: 264..269 }
+                  11: Call { function: 1, arg_count: 1, range: 258..269 }
+                  12: Load(0)
+                  13: Pop(1)
+                  14: Return
+            "#]],
+        );
+    }
+
+    #[test]
+    fn param_defaults() {
+        check(
+            r#"
+                fnc main: s32 -> inc 1;
+                fnc inc(n: s32, by: s32 = 1): s32 -> n + by;
+            "#,
+            expect![[r#"
+                main:
+                  0: PushS32(1)
+                  1: TailCall { function: 1, arg_count: 1, range: 34..39 }
+                  2: Return
+                inc:
+                  0: SkipIfPassed { param: 1, target: 3 }
+                  1: PushS32(1)
+                  2: Store(1)
+                  3: Load(0)
+                  4: Load(1)
+                  5: Arithmetic { operator: Add, range: 94..100 }
+                  6: Return
+            "#]],
+        );
+    }
+
+    #[test]
+    fn break_runs_deferred_and_drops_values() {
+        check(
+            r#"
+                fnc main: s32 -> 1 + loop {
+                    defer 2;
+                    3 + { break 4; };
+                };
+            "#,
+            expect![[r#"
+                main:
+                  0: PushS32(1)
+                  1: PushS32(3)
+                  2: PushS32(4)
+                  3: PushS32(2)
+                  4: Pop(1)
+                  5: Slide(1)
+                  6: Jump(15)
+                  7: PushNil
+                  8: Arithmetic { operator: Add, range: 94..110 }
+                  9: Pop(1)
+                  10: PushNil
+                  11: PushS32(2)
+                  12: Pop(1)
+                  13: Pop(1)
+                  14: Jump(1)
+                  15: Arithmetic { operator: Add, range: 34..129 }
+                  16: Return
+            "#]],
+        );
+    }
+
+    #[test]
+    fn for_loop() {
+        check(
+            r#"
+                fnc main -> for i in 0..3 { continue; };
+            "#,
+            expect![[r#"
+                main:
+                  0: PushS32(0)
+                  1: PushS32(3)
+                  2: Store(0)
+                  3: Store(1)
+                  4: ForNext { counter: 1, end: 0, local: 2, exit: 9 }
+                  5: Jump(4)
+                  6: PushNil
+                  7: Pop(1)
+                  8: Jump(4)
+                  9: PushNil
+                  10: Return
+            "#]],
+        );
+    }
+
+    #[test]
+    fn match_arms() {
+        check(
+            r#"
+                fnc main: s32 -> match Some 1 {
+                    Some n -> n;
+                    None -> 0;
+                };
+            "#,
+            expect![[r#"
+                main:
+                  0: PushS32(1)
+                  1: Variant(Some)
+                  2: Match { pattern: 0, otherwise: 5 }
+                  3: Load(0)
+                  4: Jump(8)
+                  5: Match { pattern: 1, otherwise: 8 }
+                  6: PushS32(0)
+                  7: Jump(8)
+                  8: Return
+            "#]],
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use hir::{Backtrace, CallSite};
+use interner::Interner;
+use line_index::LineIndex;
+
+/// One line for every frame, like `  0: main.count, called at main:3:17`,
+/// with `line_index` giving the line index of the module the call is in.
+pub fn display_backtrace<'a>(
+    backtrace: &Backtrace,
+    interner: &Interner,
+    line_index: impl Fn(hir::Name) -> &'a LineIndex,
+) -> Vec<String> {
+    let mut lines = vec!["backtrace:".to_string()];
+
+    for (idx, frame) in backtrace.frames.iter().enumerate() {
+        let function = format!(
+            "{}.{}",
+            interner.lookup(frame.function.module.0),
+            interner.lookup(frame.function.function.0)
+        );
+
+        let line = match frame.call_site {
+            Some(CallSite { module, range }) => {
+                let (line, col) = line_index(module).line_col(range.start());
+                let module = interner.lookup(module.0);
+                format!("  {idx}: {function}, called at {module}:{}:{}", line.0 + 1, col.0 + 1)
+            }
+            None => format!("  {idx}: {function}"),
+        };
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use expect_test::expect;
+    use hir::BacktraceFrame;
+    use text_size::TextRange;
+
+    #[test]
+    fn display() {
+        let mut interner = Interner::default();
+        let main = hir::Name(interner.intern("main"));
+        let math = hir::Name(interner.intern("math"));
+        let fqn = |module, function| hir::Fqn { module, function: hir::Name(function) };
+
+        let main_text = "fnc main -> {\n    math.divide 1, 0\n};\n";
+        let math_text = "fnc divide(a: s32, b: s32) -> a / b;\n";
+        let main_index = LineIndex::new(main_text);
+        let math_index = LineIndex::new(math_text);
+
+        let divide = interner.intern("divide");
+        let start = main_text.find("math.divide").unwrap() as u32;
+
+        let backtrace = Backtrace {
+            frames: vec![
+                BacktraceFrame {
+                    function: fqn(math, divide),
+                    call_site: Some(CallSite {
+                        module: main,
+                        range: TextRange::at(start.into(), 16.into()),
+                    }),
+                },
+                BacktraceFrame { function: fqn(main, main.0), call_site: None },
+            ],
+        };
+
+        let line_index = |module| if module == main { &main_index } else { &math_index };
+        let lines = display_backtrace(&backtrace, &interner, line_index);
+
+        expect![[r#"
+            backtrace:
+              0: math.divide, called at main:2:5
+              1: main.main
+        "#]]
+        .assert_eq(&format!("{}\n", lines.join("\n")));
+    }
+}
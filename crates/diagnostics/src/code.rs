@@ -0,0 +1,136 @@
+use std::fmt;
+
+/// Identifies a kind of diagnostic, like `E0001`.
+///
+/// Codes never change once they’re given out,
+/// so they can be looked up even when messages are reworded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Code(u16);
+
+// the explanation of each code, in order;
+// new codes go at the end, so that existing ones keep their numbers
+const EXPLANATIONS: [&str; 61] = [
+    include_str!("explanations/E0001.md"),
+    include_str!("explanations/E0002.md"),
+    include_str!("explanations/E0003.md"),
+    include_str!("explanations/E0004.md"),
+    include_str!("explanations/E0005.md"),
+    include_str!("explanations/E0006.md"),
+    include_str!("explanations/E0007.md"),
+    include_str!("explanations/E0008.md"),
+    include_str!("explanations/E0009.md"),
+    include_str!("explanations/E0010.md"),
+    include_str!("explanations/E0011.md"),
+    include_str!("explanations/E0012.md"),
+    include_str!("explanations/E0013.md"),
+    include_str!("explanations/E0014.md"),
+    include_str!("explanations/E0015.md"),
+    include_str!("explanations/E0016.md"),
+    include_str!("explanations/E0017.md"),
+    include_str!("explanations/E0018.md"),
+    include_str!("explanations/E0019.md"),
+    include_str!("explanations/E0020.md"),
+    include_str!("explanations/E0021.md"),
+    include_str!("explanations/E0022.md"),
+    include_str!("explanations/E0023.md"),
+    include_str!("explanations/E0024.md"),
+    include_str!("explanations/E0025.md"),
+    include_str!("explanations/E0026.md"),
+    include_str!("explanations/E0027.md"),
+    include_str!("explanations/E0028.md"),
+    include_str!("explanations/E0029.md"),
+    include_str!("explanations/E0030.md"),
+    include_str!("explanations/E0031.md"),
+    include_str!("explanations/E0032.md"),
+    include_str!("explanations/E0033.md"),
+    include_str!("explanations/E0034.md"),
+    include_str!("explanations/E0035.md"),
+    include_str!("explanations/E0036.md"),
+    include_str!("explanations/E0037.md"),
+    include_str!("explanations/E0038.md"),
+    include_str!("explanations/E0039.md"),
+    include_str!("explanations/E0040.md"),
+    include_str!("explanations/E0041.md"),
+    include_str!("explanations/E0042.md"),
+    include_str!("explanations/E0043.md"),
+    include_str!("explanations/E0044.md"),
+    include_str!("explanations/E0045.md"),
+    include_str!("explanations/E0046.md"),
+    include_str!("explanations/E0047.md"),
+    include_str!("explanations/E0048.md"),
+    include_str!("explanations/E0049.md"),
+    include_str!("explanations/E0050.md"),
+    include_str!("explanations/E0051.md"),
+    include_str!("explanations/E0052.md"),
+    include_str!("explanations/E0053.md"),
+    include_str!("explanations/E0054.md"),
+    include_str!("explanations/E0055.md"),
+    include_str!("explanations/E0056.md"),
+    include_str!("explanations/E0057.md"),
+    include_str!("explanations/E0058.md"),
+    include_str!("explanations/E0059.md"),
+    include_str!("explanations/E0060.md"),
+    include_str!("explanations/E0061.md"),
+];
+
+impl Code {
+    pub(crate) fn new(n: u16) -> Self {
+        debug_assert!(n >= 1 && n as usize <= EXPLANATIONS.len());
+        Self(n)
+    }
+
+    /// Parses a code written like `E0001`, returning `None` if there’s no such code.
+    pub fn parse(s: &str) -> Option<Self> {
+        let digits = s.strip_prefix('E')?;
+        if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let n: u16 = digits.parse().ok()?;
+        if n == 0 || n as usize > EXPLANATIONS.len() {
+            return None;
+        }
+
+        Some(Self(n))
+    }
+
+    /// A longer explanation of what diagnostics with this code mean,
+    /// usually with an example of code which causes them.
+    pub fn explanation(self) -> &'static str {
+        EXPLANATIONS[self.0 as usize - 1]
+    }
+
+    pub fn all() -> impl Iterator<Item = Self> {
+        (1..=EXPLANATIONS.len() as u16).map(Self)
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "E{:04}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_codes() {
+        assert_eq!(Code::parse("E0001"), Some(Code(1)));
+        assert_eq!(Code::parse("E0056"), Some(Code(56)));
+        assert_eq!(Code::parse("E0000"), None);
+        assert_eq!(Code::parse("E9999"), None);
+        assert_eq!(Code::parse("E1"), None);
+        assert_eq!(Code::parse("e0001"), None);
+        assert_eq!(Code::parse("E+001"), None);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for code in Code::all() {
+            assert_eq!(Code::parse(&code.to_string()), Some(code));
+            assert!(!code.explanation().is_empty());
+        }
+    }
+}
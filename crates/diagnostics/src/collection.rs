@@ -0,0 +1,255 @@
+use crate::{Diagnostic, Repr, Severity};
+
+/// The diagnostics of a module in the order they were found,
+/// leaving out any which say the same thing about the same place as an earlier one,
+/// such as an undefined type reported by both indexing and lowering.
+#[derive(Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        if !self.diagnostics.iter().any(|d| d.is_duplicate_of(&diagnostic)) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+}
+
+impl Extend<Diagnostic> for Diagnostics {
+    fn extend<T: IntoIterator<Item = Diagnostic>>(&mut self, iter: T) {
+        for diagnostic in iter {
+            self.push(diagnostic);
+        }
+    }
+}
+
+impl FromIterator<Diagnostic> for Diagnostics {
+    fn from_iter<T: IntoIterator<Item = Diagnostic>>(iter: T) -> Self {
+        let mut diagnostics = Self::default();
+        diagnostics.extend(iter);
+        diagnostics
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+/// Decides which diagnostics get shown when rendering them,
+/// leaving out any past a maximum,
+/// since pathological inputs can produce one for nearly every token.
+///
+/// Warnings count towards the maximum as well as errors,
+/// so that they can’t flood the output either.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayLimit {
+    max: usize,
+    shown: usize,
+    hidden_errors: usize,
+    hidden_warnings: usize,
+}
+
+impl DisplayLimit {
+    pub const DEFAULT_MAX: usize = 20;
+
+    /// Like with C compilers, a `max` of 0 means there’s no limit.
+    pub fn new(max: usize) -> Self {
+        let max = if max == 0 { usize::MAX } else { max };
+        Self { max, shown: 0, hidden_errors: 0, hidden_warnings: 0 }
+    }
+
+    /// Whether to show `diagnostic`,
+    /// which counts it towards the maximum if so.
+    pub fn allows(&mut self, diagnostic: &Diagnostic) -> bool {
+        if self.shown < self.max {
+            self.shown += 1;
+            return true;
+        }
+
+        match diagnostic.severity() {
+            Severity::Error => self.hidden_errors += 1,
+            Severity::Warning => self.hidden_warnings += 1,
+        }
+        false
+    }
+
+    /// A line like `3 more errors and 1 more warning not shown`,
+    /// if any diagnostics were left out.
+    pub fn summary(&self) -> Option<String> {
+        let count = |n: usize, what: &str| match n {
+            0 => None,
+            1 => Some(format!("1 more {what}")),
+            n => Some(format!("{n} more {what}s")),
+        };
+
+        let counts =
+            match (count(self.hidden_errors, "error"), count(self.hidden_warnings, "warning")) {
+                (None, None) => return None,
+                (Some(errors), None) => errors,
+                (None, Some(warnings)) => warnings,
+                (Some(errors), Some(warnings)) => format!("{errors} and {warnings}"),
+            };
+
+        Some(format!("{counts} not shown"))
+    }
+}
+
+impl Default for DisplayLimit {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX)
+    }
+}
+
+impl Diagnostic {
+    fn is_duplicate_of(&self, other: &Self) -> bool {
+        if self.range() != other.range() || self.code() != other.code() {
+            return false;
+        }
+
+        match (&self.repr, &other.repr) {
+            (Repr::Syntax(a), Repr::Syntax(b)) => a == b,
+            (Repr::Validation(a), Repr::Validation(b)) => a == b,
+            (Repr::Indexing(a), Repr::Indexing(b)) => a == b,
+            (Repr::Lowering(a), Repr::Lowering(b)) => a == b,
+            (Repr::Ty(a), Repr::Ty(b)) => a == b,
+            // stages only share codes for kinds which mean the same thing,
+            // and what they’re about is the text at the range
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hir::{
+        IndexingDiagnostic, IndexingDiagnosticKind, LoweringDiagnostic, LoweringDiagnosticKind,
+    };
+    use interner::Interner;
+    use text_size::TextRange;
+
+    fn range(start: u32, end: u32) -> TextRange {
+        TextRange::new(start.into(), end.into())
+    }
+
+    #[test]
+    fn same_diagnostic_from_different_stages() {
+        let name = Interner::default().intern("Foo");
+        let diagnostics: Diagnostics = [
+            Diagnostic::from_indexing(IndexingDiagnostic {
+                kind: IndexingDiagnosticKind::UndefinedTy { name },
+                range: range(7, 10),
+            }),
+            Diagnostic::from_lowering(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::UndefinedTy { name },
+                range: range(7, 10),
+            }),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn same_kind_at_different_ranges() {
+        let diagnostics: Diagnostics = [range(0, 1), range(2, 3)]
+            .into_iter()
+            .map(|range| {
+                Diagnostic::from_lowering(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::InvalidEscape,
+                    range,
+                })
+            })
+            .collect();
+
+        assert_eq!(diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn same_kind_with_different_details() {
+        let mut interner = Interner::default();
+        let diagnostics: Diagnostics = ["a", "b"]
+            .into_iter()
+            .map(|name| {
+                Diagnostic::from_lowering(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::UndefinedLocal { name: interner.intern(name) },
+                    range: range(0, 1),
+                })
+            })
+            .collect();
+
+        assert_eq!(diagnostics.len(), 2);
+    }
+
+    fn unused_local() -> Diagnostic {
+        Diagnostic::from_lowering(LoweringDiagnostic {
+            kind: LoweringDiagnosticKind::UnusedLocal { name: Interner::default().intern("a") },
+            range: range(0, 1),
+        })
+    }
+
+    fn invalid_escape() -> Diagnostic {
+        Diagnostic::from_lowering(LoweringDiagnostic {
+            kind: LoweringDiagnosticKind::InvalidEscape,
+            range: range(0, 1),
+        })
+    }
+
+    #[test]
+    fn default_limit() {
+        let mut limit = DisplayLimit::default();
+        let shown = (0..25).filter(|_| limit.allows(&invalid_escape())).count();
+
+        assert_eq!(shown, DisplayLimit::DEFAULT_MAX);
+        assert_eq!(limit.summary().as_deref(), Some("5 more errors not shown"));
+    }
+
+    #[test]
+    fn zero_means_no_limit() {
+        let mut limit = DisplayLimit::new(0);
+        assert!((0..1000).all(|_| limit.allows(&invalid_escape())));
+        assert_eq!(limit.summary(), None);
+    }
+
+    #[test]
+    fn warnings_count_towards_limit() {
+        let mut limit = DisplayLimit::new(1);
+        assert!(limit.allows(&unused_local()));
+        assert!(!limit.allows(&unused_local()));
+        assert_eq!(limit.summary().as_deref(), Some("1 more warning not shown"));
+
+        assert!(!limit.allows(&invalid_escape()));
+        assert!(!limit.allows(&unused_local()));
+        assert_eq!(limit.summary().as_deref(), Some("1 more error and 2 more warnings not shown"));
+    }
+
+    #[test]
+    fn nothing_hidden_without_summary() {
+        let mut limit = DisplayLimit::new(2);
+        assert!(limit.allows(&invalid_escape()));
+        assert_eq!(limit.summary(), None);
+    }
+}
@@ -1,14 +1,28 @@
+mod backtrace;
+mod code;
+mod collection;
+mod lint;
+
+pub use self::backtrace::display_backtrace;
+pub use self::code::Code;
+pub use self::collection::{Diagnostics, DisplayLimit};
+pub use self::lint::{Level, Lint, LintLevels};
+
 use ast::validation::{ValidationDiagnostic, ValidationDiagnosticKind};
 use hir::{IndexingDiagnostic, IndexingDiagnosticKind, LoweringDiagnostic, LoweringDiagnosticKind};
 use hir_ty::{TyDiagnostic, TyDiagnosticKind};
 use interner::Interner;
-use line_index::{ColNr, LineIndex, LineNr};
+use line_index::{ColNr, LineIndex};
+use owo_colors::Style;
 use parser::{ExpectedSyntax, SyntaxError, SyntaxErrorKind};
-use std::convert::TryInto;
 use syntax::TokenKind;
 use text_size::{TextRange, TextSize};
+use unicode_width::UnicodeWidthChar;
 
-pub struct Diagnostic(Repr);
+pub struct Diagnostic {
+    repr: Repr,
+    severity: Severity,
+}
 
 enum Repr {
     Syntax(SyntaxError),
@@ -16,8 +30,96 @@ enum Repr {
     Indexing(IndexingDiagnostic),
     Lowering(LoweringDiagnostic),
     Ty(TyDiagnostic),
+    #[cfg(feature = "eval")]
+    Eval(EvalDiagnostic),
+}
+
+// a runtime error which happened at a particular expression,
+// with the backtrace rendered up front
+// since its frames can be in any module
+#[cfg(feature = "eval")]
+struct EvalDiagnostic {
+    error: eval::EvalError,
+    range: TextRange,
+    backtrace: Option<Vec<String>>,
+}
+
+/// An edit which resolves a diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub message: String,
+    pub range: TextRange,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+/// How sure a [`Suggestion`] is to be what the user meant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applicability {
+    /// The edit can be applied without asking the user for anything.
+    MachineApplicable,
+    /// The edit makes the diagnostic go away,
+    /// but the user should check it does what they meant.
+    MaybeIncorrect,
+}
+
+/// Another place a diagnostic is about, such as the definition it refers to,
+/// which can be in a different module from the diagnostic itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelatedLocation {
+    /// `None` if it’s in the same module as the diagnostic.
+    pub module: Option<hir::Name>,
+    pub range: TextRange,
+    pub message: String,
+}
+
+/// How [`Diagnostic::display_with`] renders a diagnostic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisplayConfig {
+    /// Whether to color the output with ANSI escape codes.
+    /// Checking whether it’s going to a terminal is left to the caller.
+    pub color: bool,
+    pub format: Format,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// A header followed by the source the diagnostic is about
+    /// and any notes on it.
+    #[default]
+    Full,
+    /// Just `line:col: error[E0001]: message` on one line,
+    /// for scripts, logs and editors which show the source themselves.
+    Short,
 }
 
+impl DisplayConfig {
+    fn paint(self, style: Style, text: &str) -> String {
+        if self.color {
+            style.style(text).to_string()
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Another part of the module a diagnostic is about,
+/// which is shown along with the diagnostic’s own range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub range: TextRange,
+    pub message: String,
+}
+
+/// A line shown under a diagnostic’s snippet
+/// with more to say about why it happened or how to resolve it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trailer {
+    Note(String),
+    Help(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Severity {
     Warning,
     Error,
@@ -25,54 +127,144 @@ pub enum Severity {
 
 impl Diagnostic {
     pub fn from_syntax(error: SyntaxError) -> Self {
-        Self(Repr::Syntax(error))
+        Self::new(Repr::Syntax(error))
     }
 
     pub fn from_validation(diagnostic: ValidationDiagnostic) -> Self {
-        Self(Repr::Validation(diagnostic))
+        Self::new(Repr::Validation(diagnostic))
     }
 
     pub fn from_indexing(diagnostic: IndexingDiagnostic) -> Self {
-        Self(Repr::Indexing(diagnostic))
+        Self::new(Repr::Indexing(diagnostic))
     }
 
     pub fn from_lowering(diagnostic: LoweringDiagnostic) -> Self {
-        Self(Repr::Lowering(diagnostic))
+        Self::new(Repr::Lowering(diagnostic))
     }
 
     pub fn from_ty(diagnostic: TyDiagnostic) -> Self {
-        Self(Repr::Ty(diagnostic))
+        Self::new(Repr::Ty(diagnostic))
+    }
+
+    /// A runtime error, with the backtrace the evaluator captured for it as a note.
+    /// `line_index` gives the line index of each module the backtrace goes through.
+    ///
+    /// Returns the diagnostic along with the module it’s in,
+    /// or `None` for errors which didn’t come from a particular expression.
+    #[cfg(feature = "eval")]
+    pub fn from_eval<'a>(
+        error: eval::EvalError,
+        backtrace: Option<&hir::Backtrace>,
+        interner: &Interner,
+        line_index: impl Fn(hir::Name) -> &'a LineIndex,
+    ) -> Option<(hir::Name, Self)> {
+        let (module, range) = error.location()?;
+        let backtrace =
+            backtrace.map(|backtrace| display_backtrace(backtrace, interner, line_index));
+
+        Some((module, Self::new(Repr::Eval(EvalDiagnostic { error, range, backtrace }))))
+    }
+
+    fn new(repr: Repr) -> Self {
+        let severity = match &repr {
+            Repr::Syntax(_) => Severity::Error,
+            Repr::Validation(d) => match d.kind {
+                ValidationDiagnosticKind::UnneededParens => Severity::Warning,
+                ValidationDiagnosticKind::MissingDigitsInIntLiteral
+                | ValidationDiagnosticKind::InvalidDigitInIntLiteral { .. }
+                | ValidationDiagnosticKind::EmptyCharLiteral
+                | ValidationDiagnosticKind::MultipleCharsInCharLiteral
+                | ValidationDiagnosticKind::UnclosedCharLiteral
+                | ValidationDiagnosticKind::InvalidTupleIndex
+                | ValidationDiagnosticKind::ReservedKeyword { .. }
+                | ValidationDiagnosticKind::MisplacedAttr => Severity::Error,
+            },
+            Repr::Indexing(_) => Severity::Error,
+            Repr::Lowering(d) => match d.kind {
+                LoweringDiagnosticKind::UnusedLocal { .. } => Severity::Warning,
+                _ => Severity::Error,
+            },
+            Repr::Ty(d) => match d.kind {
+                TyDiagnosticKind::UnreachableArm | TyDiagnosticKind::UnreachableCode => {
+                    Severity::Warning
+                }
+                _ => Severity::Error,
+            },
+            #[cfg(feature = "eval")]
+            Repr::Eval(_) => Severity::Error,
+        };
+
+        Self { repr, severity }
     }
 
     pub fn display(&self, input: &str, interner: &Interner, line_index: &LineIndex) -> Vec<String> {
-        let range = self.range();
+        self.display_with(input, interner, line_index, DisplayConfig::default())
+    }
 
+    pub fn display_with(
+        &self,
+        input: &str,
+        interner: &Interner,
+        line_index: &LineIndex,
+        config: DisplayConfig,
+    ) -> Vec<String> {
+        let range = self.range();
         let (start_line, start_col) = line_index.line_col(range.start());
 
-        // we subtract 1 since end_line_column is inclusive,
-        // unlike TextRange which is always exclusive
-        let (end_line, end_col) = line_index.line_col(range.end() - TextSize::from(1));
+        // the column the snippet shows the start at,
+        // which tabs and wide chars before it push further right
+        let line_start = usize::from(line_index[start_line]);
+        let start_col = display_width(&input[line_start..line_start + start_col.0 as usize]);
 
-        let severity = match self.severity() {
-            Severity::Warning => "warning",
-            Severity::Error => "error",
+        let (severity, severity_style) = match self.severity() {
+            Severity::Warning => ("warning", Style::new().yellow().bold()),
+            Severity::Error => ("error", Style::new().red().bold()),
         };
 
-        let mut lines = vec![format!(
-            "{} at {}:{}: {}",
-            severity,
+        if config.format == Format::Short {
+            return vec![format!(
+                "{}:{}: {}{}",
+                start_line.0 + 1,
+                start_col + 1,
+                config.paint(severity_style, severity),
+                config.paint(
+                    Style::new().bold(),
+                    &format!("[{}]: {}", self.code(), self.message(interner))
+                )
+            )];
+        }
+
+        let header = format!(
+            "[{}] at {}:{}: {}",
+            self.code(),
             start_line.0 + 1,
-            start_col.0 + 1,
+            start_col + 1,
             self.message(interner)
+        );
+        let mut lines = vec![format!(
+            "{}{}",
+            config.paint(severity_style, severity),
+            config.paint(Style::new().bold(), &header)
         )];
 
-        input_snippet(input, start_line, start_col, end_line, end_col, range, &mut lines);
+        let labels = self.labels();
+        let mut spans = vec![Span { range, label: None }];
+        spans.extend(labels.iter().map(|label| Span { range: label.range, label: Some(label) }));
+        input_snippet(input, line_index, &spans, config, &mut lines);
+
+        for trailer in self.trailers(interner) {
+            let (kind, message) = match &trailer {
+                Trailer::Note(message) => ("note", message),
+                Trailer::Help(message) => ("help", message),
+            };
+            lines.push(format!("{}: {}", config.paint(Style::new().bold(), kind), message));
+        }
 
         lines
     }
 
     pub fn range(&self) -> TextRange {
-        match self.0 {
+        match self.repr {
             Repr::Syntax(SyntaxError { kind: SyntaxErrorKind::Missing { offset }, .. }) => {
                 TextRange::new(offset, offset + TextSize::from(1))
             }
@@ -83,81 +275,376 @@ impl Diagnostic {
             Repr::Indexing(IndexingDiagnostic { range, .. }) => range,
             Repr::Lowering(LoweringDiagnostic { range, .. }) => range,
             Repr::Ty(TyDiagnostic { range, .. }) => range,
+            #[cfg(feature = "eval")]
+            Repr::Eval(EvalDiagnostic { range, .. }) => range,
         }
     }
 
     pub fn severity(&self) -> Severity {
-        match &self.0 {
-            Repr::Syntax(_) => Severity::Error,
-            Repr::Validation(_) => Severity::Warning,
-            Repr::Indexing(_) => Severity::Error,
-            Repr::Lowering(_) => Severity::Error,
-            Repr::Ty(_) => Severity::Error,
+        self.severity
+    }
+
+    pub fn code(&self) -> Code {
+        let n = match &self.repr {
+            Repr::Syntax(e) => match e.kind {
+                SyntaxErrorKind::Missing { .. } => 1,
+                SyntaxErrorKind::Unexpected { .. } => 2,
+            },
+            Repr::Validation(d) => match d.kind {
+                ValidationDiagnosticKind::UnneededParens => 3,
+                ValidationDiagnosticKind::MissingDigitsInIntLiteral => 4,
+                ValidationDiagnosticKind::InvalidDigitInIntLiteral { .. } => 5,
+                ValidationDiagnosticKind::EmptyCharLiteral => 6,
+                ValidationDiagnosticKind::MultipleCharsInCharLiteral => 7,
+                ValidationDiagnosticKind::UnclosedCharLiteral => 8,
+                ValidationDiagnosticKind::InvalidTupleIndex => 9,
+                ValidationDiagnosticKind::ReservedKeyword { .. } => 10,
+                ValidationDiagnosticKind::MisplacedAttr => 11,
+            },
+            Repr::Indexing(d) => match d.kind {
+                IndexingDiagnosticKind::FunctionAlreadyDefined { .. } => 12,
+                IndexingDiagnosticKind::UndefinedTy { .. } => 13,
+                IndexingDiagnosticKind::MismatchedTyArgCount { .. } => 14,
+                IndexingDiagnosticKind::TyAliasAlreadyDefined { .. } => 15,
+                IndexingDiagnosticKind::RecursiveTyAlias { .. } => 16,
+                IndexingDiagnosticKind::TyParamAlreadyDefined { .. } => 17,
+                IndexingDiagnosticKind::ParamWithoutDefaultAfterDefault => 18,
+                IndexingDiagnosticKind::DefaultInExternFunction => 19,
+            },
+            Repr::Lowering(d) => match d.kind {
+                LoweringDiagnosticKind::OutOfRangeIntLiteral => 20,
+                LoweringDiagnosticKind::UndefinedLocal { .. } => 21,
+                LoweringDiagnosticKind::UndefinedModule { .. } => 22,
+                LoweringDiagnosticKind::UndefinedFunction { .. }
+                | LoweringDiagnosticKind::UndefinedMethod { .. } => 23,
+                LoweringDiagnosticKind::PrivateFunction { .. } => 24,
+                LoweringDiagnosticKind::MismatchedArgCount { .. } => 25,
+                LoweringDiagnosticKind::CalledLocal { .. } => 26,
+                LoweringDiagnosticKind::MissingSemicolonAfterLocal { .. } => 27,
+                LoweringDiagnosticKind::InvalidEscape => 28,
+                LoweringDiagnosticKind::BreakOutsideLoop => 29,
+                LoweringDiagnosticKind::ContinueOutsideLoop => 30,
+                LoweringDiagnosticKind::UndefinedLabel { .. } => 31,
+                LoweringDiagnosticKind::ContinueToBlock { .. } => 32,
+                LoweringDiagnosticKind::ReturnOutsideFunction => 33,
+                LoweringDiagnosticKind::JumpOutOfDefer => 34,
+                LoweringDiagnosticKind::AssignToImmutable { .. } => 35,
+                LoweringDiagnosticKind::InvalidAssignTarget => 36,
+                LoweringDiagnosticKind::UndefinedTy { .. } => 13,
+                LoweringDiagnosticKind::MismatchedTyArgCount { .. } => 14,
+                LoweringDiagnosticKind::UndefinedVariant { .. } => 37,
+                LoweringDiagnosticKind::MismatchedVariantPattern { .. } => 38,
+                LoweringDiagnosticKind::TryInInfallibleFunction => 39,
+                LoweringDiagnosticKind::NestedTyAlias => 40,
+                LoweringDiagnosticKind::NestedImport => 41,
+                LoweringDiagnosticKind::NestedConst => 42,
+                LoweringDiagnosticKind::ConstAlreadyDefined { .. } => 43,
+                LoweringDiagnosticKind::NonConstExpr => 44,
+                LoweringDiagnosticKind::UnusedLocal { .. } => 57,
+            },
+            Repr::Ty(d) => match d.kind {
+                TyDiagnosticKind::OutOfRangeIntLiteral { .. } => 20,
+                TyDiagnosticKind::Mismatch { .. }
+                | TyDiagnosticKind::AnnotationMismatch { .. }
+                | TyDiagnosticKind::ArgMismatch { .. }
+                | TyDiagnosticKind::ReturnTyMismatch { .. }
+                | TyDiagnosticKind::InferredReturnTyMismatch { .. } => 45,
+                TyDiagnosticKind::InvalidCast { .. } => 46,
+                TyDiagnosticKind::NoSuchField { .. } => 47,
+                TyDiagnosticKind::MismatchedTuplePattern { .. } => 48,
+                TyDiagnosticKind::NotIndexable { .. } => 49,
+                TyDiagnosticKind::NonExhaustiveMatch { .. } => 50,
+                TyDiagnosticKind::UnreachableArm => 51,
+                TyDiagnosticKind::UnreachableCode => 52,
+                TyDiagnosticKind::AnnotationNeeded => 53,
+                TyDiagnosticKind::RecursiveConst => 54,
+                TyDiagnosticKind::ConstOverflow { .. } => 55,
+                TyDiagnosticKind::ConstDivisionByZero => 56,
+            },
+            #[cfg(feature = "eval")]
+            Repr::Eval(d) => match d.error {
+                eval::EvalError::AssertionFailed { .. } => 58,
+                eval::EvalError::DivisionByZero { .. } => 59,
+                eval::EvalError::Overflow { .. } => 60,
+                eval::EvalError::StackOverflow { .. } => 61,
+                _ => unreachable!("only errors with a location become diagnostics"),
+            },
+        };
+
+        Code::new(n)
+    }
+
+    /// The lint this diagnostic belongs to,
+    /// if it’s a warning whose level can be configured.
+    pub fn lint(&self) -> Option<Lint> {
+        match &self.repr {
+            Repr::Validation(d) => match d.kind {
+                ValidationDiagnosticKind::UnneededParens => Some(Lint::UnneededParens),
+                ValidationDiagnosticKind::MissingDigitsInIntLiteral
+                | ValidationDiagnosticKind::InvalidDigitInIntLiteral { .. }
+                | ValidationDiagnosticKind::EmptyCharLiteral
+                | ValidationDiagnosticKind::MultipleCharsInCharLiteral
+                | ValidationDiagnosticKind::UnclosedCharLiteral
+                | ValidationDiagnosticKind::InvalidTupleIndex
+                | ValidationDiagnosticKind::ReservedKeyword { .. }
+                | ValidationDiagnosticKind::MisplacedAttr => None,
+            },
+            Repr::Lowering(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::UnusedLocal { .. },
+                ..
+            }) => Some(Lint::UnusedVariables),
+            Repr::Ty(TyDiagnostic { kind: TyDiagnosticKind::UnreachableArm, .. }) => {
+                Some(Lint::UnreachableArms)
+            }
+            Repr::Ty(TyDiagnostic { kind: TyDiagnosticKind::UnreachableCode, .. }) => {
+                Some(Lint::UnreachableCode)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn suggestion(&self) -> Option<Suggestion> {
+        let insert_semicolon = |offset, applicability| Suggestion {
+            message: "insert `;`".to_string(),
+            range: TextRange::empty(offset),
+            replacement: ";".to_string(),
+            applicability,
+        };
+
+        match &self.repr {
+            // when something else could have gone there instead,
+            // the `;` might not be what was missing
+            Repr::Syntax(SyntaxError {
+                expected_syntax,
+                kind: SyntaxErrorKind::Missing { offset },
+            }) if expected_syntax.allows(TokenKind::Semicolon) => {
+                let applicability = if expected_syntax.alternatives().len() == 1 {
+                    Applicability::MachineApplicable
+                } else {
+                    Applicability::MaybeIncorrect
+                };
+                Some(insert_semicolon(*offset, applicability))
+            }
+            // the unexpected token is probably the value the `=` should come before,
+            // but it might have been meant as something else entirely
+            Repr::Syntax(SyntaxError {
+                expected_syntax,
+                kind: SyntaxErrorKind::Unexpected { range, .. },
+            }) if expected_syntax.allows(TokenKind::Eq) => Some(Suggestion {
+                message: "insert `=`".to_string(),
+                range: TextRange::empty(range.start()),
+                replacement: "= ".to_string(),
+                applicability: Applicability::MaybeIncorrect,
+            }),
+            Repr::Lowering(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::MissingSemicolonAfterLocal { .. },
+                range,
+            }) => Some(insert_semicolon(range.end(), Applicability::MachineApplicable)),
+            _ => None,
+        }
+    }
+
+    /// The parts of the module besides [`Diagnostic::range`] the diagnostic points at.
+    pub fn labels(&self) -> Vec<Label> {
+        match &self.repr {
+            Repr::Ty(TyDiagnostic {
+                kind: TyDiagnosticKind::AnnotationMismatch { annotation: Some(range), .. },
+                ..
+            }) => vec![Label {
+                range: *range,
+                message: "expected because of this annotation".to_string(),
+            }],
+            Repr::Ty(TyDiagnostic {
+                kind: TyDiagnosticKind::ReturnTyMismatch { return_ty: Some(range), .. },
+                ..
+            }) => vec![Label {
+                range: *range,
+                message: "expected because of this return type".to_string(),
+            }],
+            Repr::Lowering(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::CalledLocal { definition: Some(range), .. },
+                ..
+            }) => vec![Label { range: *range, message: "defined here".to_string() }],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn trailers(&self, interner: &Interner) -> Vec<Trailer> {
+        match &self.repr {
+            Repr::Syntax(_) | Repr::Indexing(_) => Vec::new(),
+            Repr::Validation(d) => validation_diagnostic_trailers(d),
+            Repr::Lowering(d) => lowering_diagnostic_trailers(d, interner),
+            Repr::Ty(d) => ty_diagnostic_trailers(d),
+            #[cfg(feature = "eval")]
+            Repr::Eval(d) => match &d.backtrace {
+                Some(backtrace) => vec![Trailer::Note(backtrace.join("\n"))],
+                None => Vec::new(),
+            },
+        }
+    }
+
+    pub fn related_location(&self, interner: &Interner) -> Option<RelatedLocation> {
+        match &self.repr {
+            Repr::Lowering(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::PrivateFunction { module, function, definition },
+                ..
+            }) => Some(RelatedLocation {
+                module: Some(hir::Name(*module)),
+                range: *definition,
+                message: format!("`{}` is defined here", interner.lookup(*function)),
+            }),
+            Repr::Lowering(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::MismatchedArgCount { name, params: Some(params), .. },
+                ..
+            }) => Some(RelatedLocation {
+                module: params.module,
+                range: params.range,
+                message: format!("the parameters of `{}` are defined here", interner.lookup(*name)),
+            }),
+            Repr::Ty(TyDiagnostic {
+                kind: TyDiagnosticKind::ArgMismatch { params: Some(params), .. },
+                ..
+            }) => Some(RelatedLocation {
+                module: params.module,
+                range: params.range,
+                message: "the parameters are defined here".to_string(),
+            }),
+            _ => None,
         }
     }
 
     pub fn message(&self, interner: &Interner) -> String {
-        match &self.0 {
+        match &self.repr {
             Repr::Syntax(e) => syntax_error_message(e),
             Repr::Validation(d) => validation_diagnostic_message(d),
             Repr::Indexing(d) => indexing_diagnostic_message(d, interner),
             Repr::Lowering(d) => lowering_diagnostic_message(d, interner),
             Repr::Ty(d) => ty_diagnostic_message(d),
+            #[cfg(feature = "eval")]
+            Repr::Eval(d) => d.error.display(interner),
         }
     }
 }
 
+// the primary range of a diagnostic, or one of its labels
+struct Span<'a> {
+    range: TextRange,
+    label: Option<&'a Label>,
+}
+
+/// Shows each line the spans are on in turn,
+/// with the spans which start on a line marked above it
+/// if they go on past it, and the spans which end on a line marked below it.
 fn input_snippet(
     input: &str,
-    start_line: LineNr,
-    start_col: ColNr,
-    end_line: LineNr,
-    end_col: ColNr,
-    range: TextRange,
+    line_index: &LineIndex,
+    spans: &[Span<'_>],
+    config: DisplayConfig,
     lines: &mut Vec<String>,
 ) {
     const PADDING: &str = "  ";
     const POINTER_UP: &str = "^";
     const POINTER_DOWN: &str = "v";
+    const UNDERLINE: &str = "-";
 
     let file_lines: Vec<_> = input.lines().collect();
 
-    let is_single_line = start_line == end_line;
-    if is_single_line {
-        lines.push(format!("{}{}", PADDING, file_lines[start_line.0 as usize]));
+    // we subtract 1 to find the line the span ends on,
+    // since TextRange’s end is exclusive and could be at the start of the next line
+    let positions: Vec<_> = spans
+        .iter()
+        .map(|span| {
+            let start = line_index.line_col(span.range.start());
+            let (end_line, _) = line_index.line_col(span.range.end() - TextSize::from(1));
+            let end_col = ColNr(u32::from(span.range.end() - line_index[end_line]));
+            (start, (end_line, end_col))
+        })
+        .collect();
+
+    let mut shown_lines: Vec<_> = positions
+        .iter()
+        .flat_map(|((start_line, _), (end_line, _))| start_line.0..=end_line.0)
+        .collect();
+    shown_lines.sort_unstable();
+    shown_lines.dedup();
+
+    let pointers = |span: &Span<'_>, indent: usize, pointer: &str, count: usize| {
+        let (style, pointer) = match span.label {
+            None => (Style::new().cyan(), pointer),
+            Some(_) => (Style::new().blue(), UNDERLINE),
+        };
+        let mut pointers = pointer.repeat(count);
+        if let Some(label) = span.label {
+            pointers.push(' ');
+            pointers.push_str(&label.message);
+        }
+        format!("{}{}{}", PADDING, " ".repeat(indent), config.paint(style, &pointers))
+    };
+
+    let mut previous_line = None;
+    for line_nr in shown_lines {
+        if previous_line.is_some_and(|previous| line_nr > previous + 1) {
+            lines.push(format!("{PADDING}..."));
+        }
+        previous_line = Some(line_nr);
+
+        let line = file_lines.get(line_nr as usize).copied().unwrap_or_default();
+
+        // columns can be past the end of the line
+        // when a span points at the newline or the end of the input
+        let width_up_to = |col: ColNr| display_width(&line[..line.len().min(col.0 as usize)]);
+
+        for (span, ((start_line, start_col), (end_line, _))) in spans.iter().zip(&positions) {
+            if start_line.0 == line_nr && end_line.0 > line_nr {
+                let indent = width_up_to(*start_col);
+                let count = display_width(line).saturating_sub(indent).max(1);
+                lines.push(pointers(span, indent, POINTER_DOWN, count));
+            }
+        }
 
         lines.push(format!(
-            "{}{}{}",
+            "{}{}",
             PADDING,
-            " ".repeat(start_col.0 as usize),
-            POINTER_UP.repeat(range.len().try_into().unwrap())
+            config.paint(Style::new().dimmed(), &line.replace('\t', TAB))
         ));
 
-        return;
-    }
-
-    let first_line = file_lines[start_line.0 as usize];
-    lines.push(format!(
-        "{}{}{}",
-        PADDING,
-        " ".repeat(start_col.0 as usize),
-        POINTER_DOWN.repeat(first_line.len() - start_col.0 as usize)
-    ));
-    lines.push(format!("{}{}", PADDING, first_line));
+        for (span, ((start_line, start_col), (end_line, end_col))) in spans.iter().zip(&positions) {
+            if end_line.0 != line_nr {
+                continue;
+            }
 
-    for line in &file_lines[start_line.0 as usize + 1..end_line.0 as usize] {
-        lines.push(format!("{}{}", PADDING, line));
+            let indent = if start_line.0 == line_nr { width_up_to(*start_col) } else { 0 };
+            let count = width_up_to(*end_col).saturating_sub(indent).max(1);
+            lines.push(pointers(span, indent, POINTER_UP, count));
+        }
     }
+}
+
+// what tabs are shown as,
+// so that they line up the same in the source and under it
+const TAB: &str = "    ";
 
-    let last_line = file_lines[end_line.0 as usize];
-    lines.push(format!("{}{}", PADDING, last_line));
-    lines.push(format!("{}{}", PADDING, POINTER_UP.repeat(end_col.0 as usize + 1)));
+/// How many columns `text` takes up on screen.
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c == '\t' { TAB.len() } else { c.width().unwrap_or(0) }).sum()
 }
 
 fn syntax_error_message(e: &SyntaxError) -> String {
-    let write_expected_syntax = |buf: &mut String| match e.expected_syntax {
-        ExpectedSyntax::Named(name) => buf.push_str(name),
-        ExpectedSyntax::Unnamed(kind) => buf.push_str(format_kind(kind)),
+    // like “expected `:`, `;` or `=`”
+    let write_expected_syntax = |buf: &mut String| {
+        let alternatives = e.expected_syntax.alternatives();
+        for (idx, expected_syntax) in alternatives.iter().enumerate() {
+            match idx {
+                0 => {}
+                _ if idx == alternatives.len() - 1 => buf.push_str(" or "),
+                _ => buf.push_str(", "),
+            }
+            match expected_syntax {
+                ExpectedSyntax::Named(name) => buf.push_str(name),
+                ExpectedSyntax::Unnamed(kind) => buf.push_str(format_kind(*kind)),
+                ExpectedSyntax::OneOf(_) => unreachable!(),
+            }
+        }
     };
 
     let mut message = String::new();
@@ -180,6 +667,24 @@ fn syntax_error_message(e: &SyntaxError) -> String {
 fn validation_diagnostic_message(d: &ValidationDiagnostic) -> String {
     match d.kind {
         ValidationDiagnosticKind::UnneededParens => "unneeded parentheses".to_string(),
+        ValidationDiagnosticKind::MissingDigitsInIntLiteral => {
+            "missing digits after integer prefix".to_string()
+        }
+        ValidationDiagnosticKind::InvalidDigitInIntLiteral { radix } => {
+            format!("invalid digit for a base {radix} literal")
+        }
+        ValidationDiagnosticKind::EmptyCharLiteral => "empty character literal".to_string(),
+        ValidationDiagnosticKind::MultipleCharsInCharLiteral => {
+            "character literal may only contain one character".to_string()
+        }
+        ValidationDiagnosticKind::UnclosedCharLiteral => "missing closing `'`".to_string(),
+        ValidationDiagnosticKind::InvalidTupleIndex => "invalid tuple index".to_string(),
+        ValidationDiagnosticKind::ReservedKeyword { name } => {
+            format!("`{name}` is a reserved keyword and can’t be used as a name")
+        }
+        ValidationDiagnosticKind::MisplacedAttr => {
+            "attributes can only be put on functions".to_string()
+        }
     }
 }
 
@@ -191,6 +696,24 @@ fn indexing_diagnostic_message(d: &IndexingDiagnostic, interner: &Interner) -> S
         IndexingDiagnosticKind::UndefinedTy { name } => {
             format!("undefined type `{}`", interner.lookup(*name))
         }
+        IndexingDiagnosticKind::MismatchedTyArgCount { name, expected, got } => {
+            mismatched_ty_arg_count_message(interner.lookup(*name), *expected, *got)
+        }
+        IndexingDiagnosticKind::TyAliasAlreadyDefined { name } => {
+            format!("type `{}` already defined", interner.lookup(*name))
+        }
+        IndexingDiagnosticKind::RecursiveTyAlias { name } => {
+            format!("type `{}` is defined in terms of itself", interner.lookup(*name))
+        }
+        IndexingDiagnosticKind::TyParamAlreadyDefined { name } => {
+            format!("type parameter `{}` already defined", interner.lookup(*name))
+        }
+        IndexingDiagnosticKind::ParamWithoutDefaultAfterDefault => {
+            "parameters after one with a default value need default values too".to_string()
+        }
+        IndexingDiagnosticKind::DefaultInExternFunction => {
+            "parameters of extern functions can’t have default values".to_string()
+        }
     }
 }
 
@@ -203,24 +726,263 @@ fn lowering_diagnostic_message(d: &LoweringDiagnostic, interner: &Interner) -> S
         LoweringDiagnosticKind::UndefinedModule { name } => {
             format!("undefined module `{}`", interner.lookup(*name))
         }
-        LoweringDiagnosticKind::MismatchedArgCount { name, expected, got } => {
+        LoweringDiagnosticKind::UndefinedFunction { module, function } => {
+            format!(
+                "undefined function `{}.{}`",
+                interner.lookup(*module),
+                interner.lookup(*function)
+            )
+        }
+        LoweringDiagnosticKind::UndefinedMethod { name } => {
+            format!("undefined function `{}`", interner.lookup(*name))
+        }
+        LoweringDiagnosticKind::PrivateFunction { module, function, .. } => {
+            format!(
+                "function `{}.{}` is private",
+                interner.lookup(*module),
+                interner.lookup(*function)
+            )
+        }
+        LoweringDiagnosticKind::MismatchedArgCount { name, expected, got, .. } => {
             format!("`{}` expected {} arguments, but got {}", interner.lookup(*name), expected, got)
         }
-        LoweringDiagnosticKind::CalledLocal { name } => {
+        LoweringDiagnosticKind::CalledLocal { name, .. } => {
             format!(
                 "tried to call `{}`, which is a variable, not a function",
                 interner.lookup(*name)
             )
         }
+        LoweringDiagnosticKind::MissingSemicolonAfterLocal { name, .. } => {
+            format!(
+                "missing `;` after `{}`, which is a variable, not a function",
+                interner.lookup(*name)
+            )
+        }
         LoweringDiagnosticKind::InvalidEscape => "invalid escape".to_string(),
+        LoweringDiagnosticKind::BreakOutsideLoop => "`break` outside of a loop".to_string(),
+        LoweringDiagnosticKind::ContinueOutsideLoop => "`continue` outside of a loop".to_string(),
+        LoweringDiagnosticKind::UndefinedLabel { name } => {
+            format!("undefined label `'{}`", interner.lookup(*name))
+        }
+        LoweringDiagnosticKind::ContinueToBlock { name } => {
+            format!("cannot `continue` the block labeled `'{}`", interner.lookup(*name))
+        }
+        LoweringDiagnosticKind::ReturnOutsideFunction => {
+            "`return` outside of a function".to_string()
+        }
+        LoweringDiagnosticKind::JumpOutOfDefer => {
+            "cannot jump out of a deferred expression".to_string()
+        }
+        LoweringDiagnosticKind::AssignToImmutable { name, .. } => {
+            format!("cannot assign to `{}`, which is not mutable", interner.lookup(*name))
+        }
+        LoweringDiagnosticKind::InvalidAssignTarget => "can only assign to variables".to_string(),
+        LoweringDiagnosticKind::UndefinedTy { name } => {
+            format!("undefined type `{}`", interner.lookup(*name))
+        }
+        LoweringDiagnosticKind::MismatchedTyArgCount { name, expected, got } => {
+            mismatched_ty_arg_count_message(interner.lookup(*name), *expected, *got)
+        }
+        LoweringDiagnosticKind::UndefinedVariant { name } => {
+            format!("undefined variant `{}`", interner.lookup(*name))
+        }
+        LoweringDiagnosticKind::MismatchedVariantPattern { variant } if variant.has_field() => {
+            format!("`{}` holds a value, which the pattern has to match", variant.name())
+        }
+        LoweringDiagnosticKind::MismatchedVariantPattern { variant } => {
+            format!("`{}` holds no value to match", variant.name())
+        }
+        LoweringDiagnosticKind::TryInInfallibleFunction => {
+            "`?` can only be used in functions returning `Option` or `Result`".to_string()
+        }
+        LoweringDiagnosticKind::NestedTyAlias => {
+            "type aliases can only be defined at the top level".to_string()
+        }
+        LoweringDiagnosticKind::NestedImport => {
+            "`use` can only be used at the top level".to_string()
+        }
+        LoweringDiagnosticKind::NestedConst => {
+            "constants can only be defined at the top level".to_string()
+        }
+        LoweringDiagnosticKind::ConstAlreadyDefined { name } => {
+            format!("constant `{}` already defined", interner.lookup(*name))
+        }
+        LoweringDiagnosticKind::NonConstExpr => {
+            "constants can only be made of literals, other constants and arithmetic".to_string()
+        }
+        LoweringDiagnosticKind::UnusedLocal { name } => {
+            format!("unused variable `{}`", interner.lookup(*name))
+        }
     }
 }
 
+fn mismatched_ty_arg_count_message(name: &str, expected: u32, got: u32) -> String {
+    format!("`{name}` expected {expected} type arguments, but got {got}")
+}
+
 fn ty_diagnostic_message(d: &TyDiagnostic) -> String {
     match &d.kind {
         TyDiagnosticKind::Mismatch { expected, found } => {
             format!("expected `{}` but found `{}`", expected, found)
         }
+        TyDiagnosticKind::AnnotationMismatch { expected, found, .. } => {
+            format!("expected `{}` because of the type annotation but found `{}`", expected, found)
+        }
+        TyDiagnosticKind::ArgMismatch { expected, found, .. } => {
+            format!("expected `{}` because of the parameter’s type but found `{}`", expected, found)
+        }
+        TyDiagnosticKind::ReturnTyMismatch { expected, found, .. } => {
+            format!("expected `{}` because of the return type but found `{}`", expected, found)
+        }
+        TyDiagnosticKind::InferredReturnTyMismatch { expected, found } => {
+            format!("expected the inferred return type `{}` but found `{}`", expected, found)
+        }
+        TyDiagnosticKind::OutOfRangeIntLiteral { ty } => {
+            format!("integer literal out of range for `{}`", ty)
+        }
+        TyDiagnosticKind::InvalidCast { from, to } => {
+            format!("cannot cast `{}` as `{}`", from, to)
+        }
+        TyDiagnosticKind::NoSuchField { ty, idx } => {
+            format!("no field `{}` on type `{}`", idx, ty)
+        }
+        TyDiagnosticKind::MismatchedTuplePattern { len, found } => {
+            let elements = if *len == 1 { "element" } else { "elements" };
+            format!("expected a tuple of {} {} but found `{}`", len, elements, found)
+        }
+        TyDiagnosticKind::NotIndexable { ty } => {
+            format!("cannot index into a value of type `{}`", ty)
+        }
+        TyDiagnosticKind::NonExhaustiveMatch { missing } => {
+            // long lists of missing values aren’t any more helpful than their start
+            const MAX_SHOWN: usize = 3;
+
+            let arms = if missing.len() == 1 { "arm" } else { "arms" };
+            let mut shown: Vec<_> =
+                missing.iter().take(MAX_SHOWN).map(|witness| format!("`{}`", witness)).collect();
+
+            let list = if missing.len() > MAX_SHOWN {
+                format!("{} and {} more", shown.join(", "), missing.len() - MAX_SHOWN)
+            } else {
+                let last = shown.pop().unwrap();
+                if shown.is_empty() {
+                    last
+                } else {
+                    format!("{} and {}", shown.join(", "), last)
+                }
+            };
+
+            format!("missing match {} for {}", arms, list)
+        }
+        TyDiagnosticKind::UnreachableArm => "unreachable match arm".to_string(),
+        TyDiagnosticKind::UnreachableCode => "unreachable code".to_string(),
+        TyDiagnosticKind::AnnotationNeeded => {
+            "the type of this has to be known here, so it needs a type annotation".to_string()
+        }
+        TyDiagnosticKind::RecursiveConst => "constant depends on its own value".to_string(),
+        TyDiagnosticKind::ConstOverflow { ty } => {
+            format!("value of constant overflows `{}`", ty)
+        }
+        TyDiagnosticKind::ConstDivisionByZero => "constant divides by zero".to_string(),
+    }
+}
+
+fn validation_diagnostic_trailers(d: &ValidationDiagnostic) -> Vec<Trailer> {
+    match d.kind {
+        ValidationDiagnosticKind::UnneededParens => {
+            vec![Trailer::Help("remove the parentheses".to_string())]
+        }
+        ValidationDiagnosticKind::MultipleCharsInCharLiteral => {
+            vec![Trailer::Help(
+                "use a string literal (`\"…\"`) for more than one character".to_string(),
+            )]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn lowering_diagnostic_trailers(d: &LoweringDiagnostic, interner: &Interner) -> Vec<Trailer> {
+    match &d.kind {
+        LoweringDiagnosticKind::UndefinedMethod { name } => vec![Trailer::Note(format!(
+            "`a.{}` calls the function `{}` with `a` as its first argument",
+            interner.lookup(*name),
+            interner.lookup(*name)
+        ))],
+        LoweringDiagnosticKind::PrivateFunction { module, function, .. } => {
+            vec![Trailer::Help(format!(
+                "mark `{}` as `pub` in `{}` to use it from other modules",
+                interner.lookup(*function),
+                interner.lookup(*module)
+            ))]
+        }
+        LoweringDiagnosticKind::InvalidEscape => vec![Trailer::Note(
+            r#"the valid escapes are `\n`, `\r`, `\t`, `\\`, `\"` and `\'`"#.to_string(),
+        )],
+        LoweringDiagnosticKind::ContinueToBlock { name } => vec![Trailer::Help(format!(
+            "use `break '{}` to leave the block",
+            interner.lookup(*name)
+        ))],
+        LoweringDiagnosticKind::AssignToImmutable { name, is_param: false } => {
+            vec![Trailer::Help(format!(
+                "declare `{}` with `let mut` to be able to assign to it",
+                interner.lookup(*name)
+            ))]
+        }
+        LoweringDiagnosticKind::AssignToImmutable { name, is_param: true } => vec![
+            Trailer::Note("parameters are never mutable".to_string()),
+            Trailer::Help(format!(
+                "copy `{}` into a variable with `let mut {} = {};`",
+                interner.lookup(*name),
+                interner.lookup(*name),
+                interner.lookup(*name)
+            )),
+        ],
+        LoweringDiagnosticKind::TryInInfallibleFunction => vec![Trailer::Note(
+            "`?` returns the `None` or `Err` it finds from the function".to_string(),
+        )],
+        LoweringDiagnosticKind::NestedConst => {
+            vec![Trailer::Help("use `let` for a value only this function needs".to_string())]
+        }
+        LoweringDiagnosticKind::UnusedLocal { name } => vec![Trailer::Help(format!(
+            "if it’s meant to be unused, name it `_{}` instead",
+            interner.lookup(*name)
+        ))],
+        _ => Vec::new(),
+    }
+}
+
+fn ty_diagnostic_trailers(d: &TyDiagnostic) -> Vec<Trailer> {
+    match &d.kind {
+        TyDiagnosticKind::Mismatch { expected, found }
+        | TyDiagnosticKind::AnnotationMismatch { expected, found, .. }
+        | TyDiagnosticKind::ArgMismatch { expected, found, .. }
+        | TyDiagnosticKind::ReturnTyMismatch { expected, found, .. }
+        | TyDiagnosticKind::InferredReturnTyMismatch { expected, found }
+            if expected.expand_aliases().is_int() && found.expand_aliases().is_int() =>
+        {
+            vec![Trailer::Help(format!("convert it with `as {}`", expected))]
+        }
+        TyDiagnosticKind::InvalidCast { .. } => vec![Trailer::Note(
+            "only integers, `bool` and `char` can be cast, and only to integer types and `string`"
+                .to_string(),
+        )],
+        TyDiagnosticKind::NoSuchField { ty, .. } => match ty.expand_aliases() {
+            hir::Ty::Tuple(elements) => {
+                let fields = if elements.len() == 1 { "field" } else { "fields" };
+                vec![Trailer::Note(format!("`{}` has {} {}", ty, elements.len(), fields))]
+            }
+            _ => vec![Trailer::Note("only tuples have fields".to_string())],
+        },
+        TyDiagnosticKind::NonExhaustiveMatch { .. } => vec![Trailer::Help(
+            "add arms for them, or a `_` arm to match everything else".to_string(),
+        )],
+        TyDiagnosticKind::UnreachableArm => {
+            vec![Trailer::Note("the arms before it already match every value it does".to_string())]
+        }
+        TyDiagnosticKind::ConstOverflow { ty } if ty.expand_aliases() != hir::Ty::S64 => {
+            vec![Trailer::Help("give the constant a bigger type, such as `s64`".to_string())]
+        }
+        _ => Vec::new(),
     }
 }
 
@@ -228,8 +990,27 @@ fn format_kind(kind: TokenKind) -> &'static str {
     match kind {
         TokenKind::LetKw => "`let`",
         TokenKind::FncKw => "`fnc`",
+        TokenKind::ExternKw => "`extern`",
+        TokenKind::TrueKw => "`true`",
+        TokenKind::FalseKw => "`false`",
+        TokenKind::LoopKw => "`loop`",
+        TokenKind::BreakKw => "`break`",
+        TokenKind::ContinueKw => "`continue`",
+        TokenKind::MutKw => "`mut`",
+        TokenKind::ForKw => "`for`",
+        TokenKind::InKw => "`in`",
+        TokenKind::ReturnKw => "`return`",
+        TokenKind::AsKw => "`as`",
+        TokenKind::MatchKw => "`match`",
+        TokenKind::TypeKw => "`type`",
+        TokenKind::UseKw => "`use`",
+        TokenKind::PubKw => "`pub`",
+        TokenKind::ConstKw => "`const`",
+        TokenKind::DeferKw => "`defer`",
         TokenKind::Ident => "identifier",
         TokenKind::Int => "integer literal",
+        TokenKind::Char => "character literal",
+        TokenKind::Label => "label",
         TokenKind::Quote => "`\"`",
         TokenKind::Escape => "escape sequence",
         TokenKind::StringContents => "string literal",
@@ -237,16 +1018,30 @@ fn format_kind(kind: TokenKind) -> &'static str {
         TokenKind::Hyphen => "`-`",
         TokenKind::Asterisk => "`*`",
         TokenKind::Slash => "`/`",
+        TokenKind::EqEq => "`==`",
+        TokenKind::BangEq => "`!=`",
+        TokenKind::Less => "`<`",
+        TokenKind::LessEq => "`<=`",
+        TokenKind::Greater => "`>`",
+        TokenKind::GreaterEq => "`>=`",
+        TokenKind::AmpAmp => "`&&`",
+        TokenKind::PipePipe => "`||`",
+        TokenKind::Bang => "`!`",
+        TokenKind::Question => "`?`",
         TokenKind::Eq => "`=`",
         TokenKind::Dot => "`.`",
+        TokenKind::DotDot => "`..`",
         TokenKind::Colon => "`:`",
         TokenKind::Comma => "`,`",
         TokenKind::Semicolon => "`;`",
         TokenKind::Arrow => "`->`",
         TokenKind::LParen => "`(`",
         TokenKind::RParen => "`)`",
+        TokenKind::LBracket => "`[`",
+        TokenKind::RBracket => "`]`",
         TokenKind::LBrace => "`{`",
         TokenKind::RBrace => "`}`",
+        TokenKind::Hash => "`#`",
         TokenKind::Whitespace => "whitespace",
         TokenKind::CommentContents | TokenKind::CommentLeader => "comment",
         TokenKind::DocCommentContents | TokenKind::DocCommentLeader => "doc comment",
@@ -340,6 +1135,53 @@ mod tests {
         ));
     }
 
+    #[cfg(feature = "eval")]
+    #[test]
+    fn eval_error_with_backtrace() {
+        let mut interner = Interner::default();
+        let main = hir::Name(interner.intern("main"));
+        let divide = hir::Name(interner.intern("divide"));
+
+        let input = "fnc divide(a: s32, b: s32): s32 -> a / b;\nfnc main: s32 -> divide 1, 0;\n";
+        let line_index = LineIndex::new(input);
+        let at = |text: &str| {
+            let start = input.find(text).unwrap() as u32;
+            TextRange::at(start.into(), (text.len() as u32).into())
+        };
+
+        let error = eval::EvalError::DivisionByZero { module: main, range: at("a / b") };
+        let backtrace = hir::Backtrace {
+            frames: vec![
+                hir::BacktraceFrame {
+                    function: hir::Fqn { module: main, function: divide },
+                    call_site: Some(hir::CallSite { module: main, range: at("divide 1, 0") }),
+                },
+                hir::BacktraceFrame {
+                    function: hir::Fqn { module: main, function: main },
+                    call_site: None,
+                },
+            ],
+        };
+
+        let (module, diagnostic) =
+            Diagnostic::from_eval(error, Some(&backtrace), &interner, |_| &line_index).unwrap();
+        assert_eq!(module, main);
+
+        expect![[r#"
+            error[E0059] at 1:36: attempted to divide by zero
+              fnc divide(a: s32, b: s32): s32 -> a / b;
+                                                 ^^^^^
+            note: backtrace:
+              0: main.divide, called at main:2:18
+              1: main.main
+        "#]]
+        .assert_eq(&format!("{}\n", diagnostic.display(input, &interner, &line_index).join("\n")));
+
+        // errors which didn’t come from a particular expression have nothing to point at
+        let error = eval::EvalError::FuelExhausted;
+        assert!(Diagnostic::from_eval(error, None, &interner, |_| &line_index).is_none());
+    }
+
     #[test]
     fn syntax_unexpected() {
         check_syntax(
@@ -350,13 +1192,34 @@ mod tests {
                 range: TextRange::new(4.into(), 5.into()),
             },
             expect![[r#"
-                error at 1:5: expected identifier but found `*`
+                error[E0002] at 1:5: expected identifier but found `*`
                   let *
                       ^
             "#]],
         );
     }
 
+    #[test]
+    fn syntax_unexpected_one_of() {
+        check_syntax(
+            "let a 5;",
+            ExpectedSyntax::OneOf(vec![
+                ExpectedSyntax::Unnamed(TokenKind::Colon),
+                ExpectedSyntax::Unnamed(TokenKind::Semicolon),
+                ExpectedSyntax::Unnamed(TokenKind::Eq),
+            ]),
+            SyntaxErrorKind::Unexpected {
+                found: TokenKind::Int,
+                range: TextRange::new(6.into(), 7.into()),
+            },
+            expect![[r#"
+                error[E0002] at 1:7: expected `:`, `;` or `=` but found integer literal
+                  let a 5;
+                        ^
+            "#]],
+        );
+    }
+
     #[test]
     fn syntax_missing() {
         check_syntax(
@@ -364,7 +1227,7 @@ mod tests {
             ExpectedSyntax::Named("variable name"),
             SyntaxErrorKind::Missing { offset: 3.into() },
             expect![[r#"
-                error at 1:4: missing variable name
+                error[E0001] at 1:4: missing variable name
                   let = 10;
                      ^
             "#]],
@@ -378,7 +1241,7 @@ mod tests {
             ExpectedSyntax::Named("expression"),
             SyntaxErrorKind::Missing { offset: 7.into() },
             expect![[r#"
-                error at 1:8: missing expression
+                error[E0001] at 1:8: missing expression
                   let a =
                          ^
             "#]],
@@ -392,9 +1255,10 @@ mod tests {
             ValidationDiagnosticKind::UnneededParens,
             8..10,
             expect![[r#"
-                warning at 1:9: unneeded parentheses
+                warning[E0003] at 1:9: unneeded parentheses
                   fnc five(): s32 -> 5;
                           ^^
+                help: remove the parentheses
             "#]],
         );
     }
@@ -406,15 +1270,44 @@ mod tests {
             ValidationDiagnosticKind::UnneededParens,
             8..11,
             expect![[r#"
-                warning at 1:9: unneeded parentheses
+                warning[E0003] at 1:9: unneeded parentheses
                           v
                   fnc main(
                   ) -> {};
                   ^
+                help: remove the parentheses
+            "#]],
+        );
+    }
+
+    #[test]
+    fn validation_reserved_keyword() {
+        check_validation(
+            "let if = 1;",
+            ValidationDiagnosticKind::ReservedKeyword { name: "if" },
+            4..6,
+            expect![[r#"
+                error[E0010] at 1:5: `if` is a reserved keyword and can’t be used as a name
+                  let if = 1;
+                      ^^
             "#]],
         );
     }
 
+    #[test]
+    fn validation_misplaced_attr() {
+        check_validation(
+            "#[test] type T = s32;",
+            ValidationDiagnosticKind::MisplacedAttr,
+            0..7,
+            expect![[r##"
+                error[E0011] at 1:1: attributes can only be put on functions
+                  #[test] type T = s32;
+                  ^^^^^^^
+            "##]],
+        );
+    }
+
     #[test]
     fn indexing_function_already_defined() {
         check_indexing(
@@ -422,13 +1315,83 @@ mod tests {
             |i| IndexingDiagnosticKind::FunctionAlreadyDefined { name: i.intern("do_thing") },
             0..19,
             expect![[r#"
-                error at 1:1: function `do_thing` already defined
+                error[E0012] at 1:1: function `do_thing` already defined
                   fnc do_thing -> {};
                   ^^^^^^^^^^^^^^^^^^^
             "#]],
         );
     }
 
+    #[test]
+    fn indexing_ty_alias_already_defined() {
+        check_indexing(
+            "type Id = s32;",
+            |i| IndexingDiagnosticKind::TyAliasAlreadyDefined { name: i.intern("Id") },
+            0..14,
+            expect![[r#"
+                error[E0015] at 1:1: type `Id` already defined
+                  type Id = s32;
+                  ^^^^^^^^^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn indexing_recursive_ty_alias() {
+        check_indexing(
+            "type Tree = [Tree];",
+            |i| IndexingDiagnosticKind::RecursiveTyAlias { name: i.intern("Tree") },
+            13..17,
+            expect![[r#"
+                error[E0016] at 1:14: type `Tree` is defined in terms of itself
+                  type Tree = [Tree];
+                               ^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn indexing_ty_param_already_defined() {
+        check_indexing(
+            "fnc pair[T, T](a: T, b: T) -> {};",
+            |i| IndexingDiagnosticKind::TyParamAlreadyDefined { name: i.intern("T") },
+            12..13,
+            expect![[r#"
+                error[E0017] at 1:13: type parameter `T` already defined
+                  fnc pair[T, T](a: T, b: T) -> {};
+                              ^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn indexing_param_without_default_after_default() {
+        check_indexing(
+            "fnc clamp(min: s32 = 0, n: s32) -> {};",
+            |_| IndexingDiagnosticKind::ParamWithoutDefaultAfterDefault,
+            24..30,
+            expect![[r#"
+                error[E0018] at 1:25: parameters after one with a default value need default values too
+                  fnc clamp(min: s32 = 0, n: s32) -> {};
+                                          ^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn indexing_default_in_extern_function() {
+        check_indexing(
+            "extern fnc log(message: string = \"\");",
+            |_| IndexingDiagnosticKind::DefaultInExternFunction,
+            15..35,
+            expect![[r#"
+                error[E0019] at 1:16: parameters of extern functions can’t have default values
+                  extern fnc log(message: string = "");
+                                 ^^^^^^^^^^^^^^^^^^^^
+            "#]],
+        );
+    }
+
     #[test]
     fn indexing_function_undefined_ty() {
         check_indexing(
@@ -436,7 +1399,7 @@ mod tests {
             |i| IndexingDiagnosticKind::UndefinedTy { name: i.intern("sring") },
             12..17,
             expect![[r#"
-                error at 1:13: undefined type `sring`
+                error[E0013] at 1:13: undefined type `sring`
                   fnc header: sring -> "=====";
                               ^^^^^
             "#]],
@@ -450,7 +1413,7 @@ mod tests {
             |_| LoweringDiagnosticKind::OutOfRangeIntLiteral,
             0..16,
             expect![[r#"
-                error at 1:1: integer literal out of range
+                error[E0020] at 1:1: integer literal out of range
                   1000000000000000;
                   ^^^^^^^^^^^^^^^^
             "#]],
@@ -464,7 +1427,7 @@ mod tests {
             |i| LoweringDiagnosticKind::UndefinedLocal { name: i.intern("foo") },
             0..3,
             expect![[r#"
-                error at 1:1: undefined variable `foo`
+                error[E0021] at 1:1: undefined variable `foo`
                   foo + 1;
                   ^^^
             "#]],
@@ -478,7 +1441,7 @@ mod tests {
             |i| LoweringDiagnosticKind::UndefinedModule { name: i.intern("io") },
             0..2,
             expect![[r#"
-                error at 1:1: undefined module `io`
+                error[E0022] at 1:1: undefined module `io`
                   io.print "10";
                   ^^
             "#]],
@@ -486,17 +1449,129 @@ mod tests {
     }
 
     #[test]
-    fn lowering_mismatched_arg_count() {
+    fn lowering_undefined_function() {
         check_lowering(
-            "add 1, 2, 3",
+            "io.pritn \"10\";",
+            |i| LoweringDiagnosticKind::UndefinedFunction {
+                module: i.intern("io"),
+                function: i.intern("pritn"),
+            },
+            3..8,
+            expect![[r#"
+                error[E0023] at 1:4: undefined function `io.pritn`
+                  io.pritn "10";
+                     ^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_private_function() {
+        check_lowering(
+            "use io.flush;",
+            |i| LoweringDiagnosticKind::PrivateFunction {
+                module: i.intern("io"),
+                function: i.intern("flush"),
+                definition: TextRange::new(0.into(), 20.into()),
+            },
+            7..12,
+            expect![[r#"
+                error[E0024] at 1:8: function `io.flush` is private
+                  use io.flush;
+                         ^^^^^
+                help: mark `flush` as `pub` in `io` to use it from other modules
+            "#]],
+        );
+    }
+
+    #[test]
+    fn related_location_of_private_function() {
+        let mut interner = Interner::default();
+        let module = interner.intern("io");
+        let function = interner.intern("flush");
+        let definition = TextRange::new(0.into(), 20.into());
+
+        let diagnostic = Diagnostic::from_lowering(LoweringDiagnostic {
+            kind: LoweringDiagnosticKind::PrivateFunction { module, function, definition },
+            range: TextRange::new(7.into(), 12.into()),
+        });
+
+        assert_eq!(
+            diagnostic.related_location(&interner),
+            Some(RelatedLocation {
+                module: Some(hir::Name(module)),
+                range: definition,
+                message: "`flush` is defined here".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn related_location_of_mismatched_arg_count() {
+        let mut interner = Interner::default();
+        let module = hir::Name(interner.intern("math"));
+        let params = hir::ParamsLocation {
+            module: Some(module),
+            range: TextRange::new(7.into(), 23.into()),
+        };
+
+        let diagnostic = Diagnostic::from_lowering(LoweringDiagnostic {
+            kind: LoweringDiagnosticKind::MismatchedArgCount {
+                name: interner.intern("math.add"),
+                expected: 2,
+                got: 1,
+                params: Some(params),
+            },
+            range: TextRange::new(0.into(), 8.into()),
+        });
+
+        assert_eq!(
+            diagnostic.related_location(&interner),
+            Some(RelatedLocation {
+                module: Some(module),
+                range: params.range,
+                message: "the parameters of `math.add` are defined here".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn related_location_of_arg_mismatch() {
+        let params =
+            hir::ParamsLocation { module: None, range: TextRange::new(5.into(), 13.into()) };
+
+        let diagnostic = Diagnostic::from_ty(TyDiagnostic {
+            kind: TyDiagnosticKind::ArgMismatch {
+                expected: hir::Ty::S32,
+                found: hir::Ty::String,
+                params: Some(params),
+            },
+            range: TextRange::new(30.into(), 33.into()),
+        });
+
+        assert_eq!(
+            diagnostic.related_location(&Interner::default()),
+            Some(RelatedLocation {
+                module: None,
+                range: params.range,
+                message: "the parameters are defined here".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn lowering_mismatched_arg_count() {
+        check_lowering(
+            "add 1, 2, 3",
             |i| LoweringDiagnosticKind::MismatchedArgCount {
                 name: i.intern("add"),
                 expected: 2,
                 got: 3,
+                params: None,
             },
             0..3,
             expect![[r#"
-                error at 1:1: `add` expected 2 arguments, but got 3
+                error[E0025] at 1:1: `add` expected 2 arguments, but got 3
                   add 1, 2, 3
                   ^^^
             "#]],
@@ -506,27 +1581,629 @@ mod tests {
     #[test]
     fn lowering_called_local() {
         check_lowering(
-            "frobnicate a, b",
-            |i| LoweringDiagnosticKind::CalledLocal { name: i.intern("frobnicate") },
-            0..10,
+            "let frobnicate = 1;\nfrobnicate a, b",
+            |i| LoweringDiagnosticKind::CalledLocal {
+                name: i.intern("frobnicate"),
+                definition: Some(TextRange::new(4.into(), 14.into())),
+            },
+            20..30,
             expect![[r#"
-                error at 1:1: tried to call `frobnicate`, which is a variable, not a function
+                error[E0026] at 2:1: tried to call `frobnicate`, which is a variable, not a function
+                  let frobnicate = 1;
+                      ---------- defined here
                   frobnicate a, b
                   ^^^^^^^^^^
             "#]],
         );
     }
 
+    #[test]
+    fn lowering_missing_semicolon_after_local() {
+        check_lowering(
+            "let x = a x + 1",
+            |i| LoweringDiagnosticKind::MissingSemicolonAfterLocal {
+                name: i.intern("a"),
+                statement_end: 15.into(),
+            },
+            8..9,
+            expect![[r#"
+                error[E0027] at 1:9: missing `;` after `a`, which is a variable, not a function
+                  let x = a x + 1
+                          ^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_assign_to_immutable() {
+        check_lowering(
+            "x = 5;",
+            |i| LoweringDiagnosticKind::AssignToImmutable { name: i.intern("x"), is_param: false },
+            0..1,
+            expect![[r#"
+                error[E0035] at 1:1: cannot assign to `x`, which is not mutable
+                  x = 5;
+                  ^
+                help: declare `x` with `let mut` to be able to assign to it
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_assign_to_param() {
+        check_lowering(
+            "n = 5;",
+            |i| LoweringDiagnosticKind::AssignToImmutable { name: i.intern("n"), is_param: true },
+            0..1,
+            expect![[r#"
+                error[E0035] at 1:1: cannot assign to `n`, which is not mutable
+                  n = 5;
+                  ^
+                note: parameters are never mutable
+                help: copy `n` into a variable with `let mut n = n;`
+            "#]],
+        );
+    }
+
+    #[test]
+    fn insert_missing_semicolon() {
+        let syntax_error = Diagnostic::from_syntax(SyntaxError {
+            expected_syntax: ExpectedSyntax::Unnamed(TokenKind::Semicolon),
+            kind: SyntaxErrorKind::Missing { offset: 9.into() },
+        });
+
+        let lowering_diagnostic = Diagnostic::from_lowering(LoweringDiagnostic {
+            kind: LoweringDiagnosticKind::MissingSemicolonAfterLocal {
+                name: Interner::default().intern("a"),
+                statement_end: 15.into(),
+            },
+            range: TextRange::new(8.into(), 9.into()),
+        });
+
+        let suggestion = |offset: u32| Suggestion {
+            message: "insert `;`".to_string(),
+            range: TextRange::empty(offset.into()),
+            replacement: ";".to_string(),
+            applicability: Applicability::MachineApplicable,
+        };
+
+        assert_eq!(syntax_error.suggestion(), Some(suggestion(9)));
+        assert_eq!(lowering_diagnostic.suggestion(), Some(suggestion(9)));
+    }
+
+    #[test]
+    fn insert_semicolon_among_alternatives() {
+        let diagnostic = Diagnostic::from_syntax(SyntaxError {
+            expected_syntax: ExpectedSyntax::OneOf(vec![
+                ExpectedSyntax::Unnamed(TokenKind::RBrace),
+                ExpectedSyntax::Unnamed(TokenKind::Semicolon),
+            ]),
+            kind: SyntaxErrorKind::Missing { offset: 24.into() },
+        });
+
+        assert_eq!(
+            diagnostic.suggestion(),
+            Some(Suggestion {
+                message: "insert `;`".to_string(),
+                range: TextRange::empty(24.into()),
+                replacement: ";".to_string(),
+                applicability: Applicability::MaybeIncorrect,
+            })
+        );
+    }
+
+    #[test]
+    fn insert_missing_eq() {
+        let diagnostic = Diagnostic::from_syntax(SyntaxError {
+            expected_syntax: ExpectedSyntax::Unnamed(TokenKind::Eq),
+            kind: SyntaxErrorKind::Unexpected {
+                found: TokenKind::Int,
+                range: TextRange::new(6.into(), 7.into()),
+            },
+        });
+
+        assert_eq!(
+            diagnostic.suggestion(),
+            Some(Suggestion {
+                message: "insert `=`".to_string(),
+                range: TextRange::empty(6.into()),
+                replacement: "= ".to_string(),
+                applicability: Applicability::MaybeIncorrect,
+            })
+        );
+    }
+
     #[test]
     fn lowering_invalid_escape() {
         check_lowering(
             "\\#",
             |_| LoweringDiagnosticKind::InvalidEscape,
             0..2,
-            expect![[r#"
-                error at 1:1: invalid escape
+            expect![[r##"
+                error[E0028] at 1:1: invalid escape
                   \#
                   ^^
+                note: the valid escapes are `\n`, `\r`, `\t`, `\\`, `\"` and `\'`
+            "##]],
+        );
+    }
+
+    #[test]
+    fn lowering_break_outside_loop() {
+        check_lowering(
+            "break;",
+            |_| LoweringDiagnosticKind::BreakOutsideLoop,
+            0..6,
+            expect![[r#"
+                error[E0029] at 1:1: `break` outside of a loop
+                  break;
+                  ^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_jump_out_of_defer() {
+        check_lowering(
+            "loop { defer { break; }; }",
+            |_| LoweringDiagnosticKind::JumpOutOfDefer,
+            15..21,
+            expect![[r#"
+                error[E0034] at 1:16: cannot jump out of a deferred expression
+                  loop { defer { break; }; }
+                                 ^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_undefined_method() {
+        check_lowering(
+            "\"abc\".lenght",
+            |i| LoweringDiagnosticKind::UndefinedMethod { name: i.intern("lenght") },
+            6..12,
+            expect![[r#"
+                error[E0023] at 1:7: undefined function `lenght`
+                  "abc".lenght
+                        ^^^^^^
+                note: `a.lenght` calls the function `lenght` with `a` as its first argument
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_undefined_label() {
+        check_lowering(
+            "loop { break 'outer; }",
+            |i| LoweringDiagnosticKind::UndefinedLabel { name: i.intern("outer") },
+            13..19,
+            expect![[r#"
+                error[E0031] at 1:14: undefined label `'outer`
+                  loop { break 'outer; }
+                               ^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_continue_to_block() {
+        check_lowering(
+            "'a: { continue 'a; }",
+            |i| LoweringDiagnosticKind::ContinueToBlock { name: i.intern("a") },
+            15..17,
+            expect![[r#"
+                error[E0032] at 1:16: cannot `continue` the block labeled `'a`
+                  'a: { continue 'a; }
+                                 ^^
+                help: use `break 'a` to leave the block
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_mismatched_ty_arg_count() {
+        check_lowering(
+            "let r: Result[s32] = Ok 1;",
+            |i| LoweringDiagnosticKind::MismatchedTyArgCount {
+                name: i.intern("Result"),
+                expected: 2,
+                got: 1,
+            },
+            7..18,
+            expect![[r#"
+                error[E0014] at 1:8: `Result` expected 2 type arguments, but got 1
+                  let r: Result[s32] = Ok 1;
+                         ^^^^^^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_undefined_variant() {
+        check_lowering(
+            "match o { Many n -> n; _ -> 0 }",
+            |i| LoweringDiagnosticKind::UndefinedVariant { name: i.intern("Many") },
+            10..14,
+            expect![[r#"
+                error[E0037] at 1:11: undefined variant `Many`
+                  match o { Many n -> n; _ -> 0 }
+                            ^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_variant_pattern_without_field() {
+        check_lowering(
+            "match o { Some -> 1; None -> 0 }",
+            |_| LoweringDiagnosticKind::MismatchedVariantPattern { variant: hir::Variant::Some },
+            10..14,
+            expect![[r#"
+                error[E0038] at 1:11: `Some` holds a value, which the pattern has to match
+                  match o { Some -> 1; None -> 0 }
+                            ^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_variant_pattern_with_extra_field() {
+        check_lowering(
+            "match o { Some n -> n; None m -> 0 }",
+            |_| LoweringDiagnosticKind::MismatchedVariantPattern { variant: hir::Variant::None },
+            23..27,
+            expect![[r#"
+                error[E0038] at 1:24: `None` holds no value to match
+                  match o { Some n -> n; None m -> 0 }
+                                         ^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_try_in_infallible_function() {
+        check_lowering(
+            "fnc f: s32 -> {parse_int \"1\"}?;",
+            |_| LoweringDiagnosticKind::TryInInfallibleFunction,
+            14..30,
+            expect![[r#"
+                error[E0039] at 1:15: `?` can only be used in functions returning `Option` or `Result`
+                  fnc f: s32 -> {parse_int "1"}?;
+                                ^^^^^^^^^^^^^^^^
+                note: `?` returns the `None` or `Err` it finds from the function
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_nested_ty_alias() {
+        check_lowering(
+            "{ type Id = s32; }",
+            |_| LoweringDiagnosticKind::NestedTyAlias,
+            2..16,
+            expect![[r#"
+                error[E0040] at 1:3: type aliases can only be defined at the top level
+                  { type Id = s32; }
+                    ^^^^^^^^^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_nested_import() {
+        check_lowering(
+            "{ use io.print; }",
+            |_| LoweringDiagnosticKind::NestedImport,
+            2..15,
+            expect![[r#"
+                error[E0041] at 1:3: `use` can only be used at the top level
+                  { use io.print; }
+                    ^^^^^^^^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_nested_const() {
+        check_lowering(
+            "{ const MAX = 3; }",
+            |_| LoweringDiagnosticKind::NestedConst,
+            2..16,
+            expect![[r#"
+                error[E0042] at 1:3: constants can only be defined at the top level
+                  { const MAX = 3; }
+                    ^^^^^^^^^^^^^^
+                help: use `let` for a value only this function needs
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_const_already_defined() {
+        check_lowering(
+            "const MAX = 3;",
+            |i| LoweringDiagnosticKind::ConstAlreadyDefined { name: i.intern("MAX") },
+            0..14,
+            expect![[r#"
+                error[E0043] at 1:1: constant `MAX` already defined
+                  const MAX = 3;
+                  ^^^^^^^^^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_non_const_expr() {
+        check_lowering(
+            "const MAX = limit;",
+            |_| LoweringDiagnosticKind::NonConstExpr,
+            12..17,
+            expect![[r#"
+                error[E0044] at 1:13: constants can only be made of literals, other constants and arithmetic
+                  const MAX = limit;
+                              ^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lowering_unused_local() {
+        check_lowering(
+            "let count = 0;",
+            |i| LoweringDiagnosticKind::UnusedLocal { name: i.intern("count") },
+            4..9,
+            expect![[r#"
+                warning[E0057] at 1:5: unused variable `count`
+                  let count = 0;
+                      ^^^^^
+                help: if it’s meant to be unused, name it `_count` instead
+            "#]],
+        );
+    }
+
+    #[test]
+    fn ty_mismatch_of_alias() {
+        check_ty(
+            "let id: Id = true;",
+            TyDiagnosticKind::Mismatch {
+                expected: hir::Ty::Alias { name: "Id".to_string(), ty: Box::new(hir::Ty::S32) },
+                found: hir::Ty::Bool,
+            },
+            13..17,
+            expect![[r#"
+                error[E0045] at 1:14: expected `Id` but found `bool`
+                  let id: Id = true;
+                               ^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn ty_mismatch_of_ints() {
+        check_ty(
+            "let a: s64 = 1; let b: s32 = a;",
+            TyDiagnosticKind::AnnotationMismatch {
+                expected: hir::Ty::S32,
+                found: hir::Ty::S64,
+                annotation: None,
+            },
+            29..30,
+            expect![[r#"
+                error[E0045] at 1:30: expected `s32` because of the type annotation but found `s64`
+                  let a: s64 = 1; let b: s32 = a;
+                                               ^
+                help: convert it with `as s32`
+            "#]],
+        );
+    }
+
+    #[test]
+    fn caret_after_tab() {
+        check_ty(
+            "\tlet a: s32 = true;",
+            TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Bool },
+            14..18,
+            expect![[r#"
+                error[E0045] at 1:18: expected `s32` but found `bool`
+                      let a: s32 = true;
+                                   ^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn caret_after_wide_chars() {
+        check_ty(
+            "let 名前: s32 = \"🎉\";",
+            TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
+            18..24,
+            expect![[r#"
+                error[E0045] at 1:17: expected `s32` but found `string`
+                  let 名前: s32 = "🎉";
+                                  ^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        check_ty(
+            "let a: s32 = 1;\r\nlet b: s32 = {\r\n    true\r\n};\r\n",
+            TyDiagnosticKind::AnnotationMismatch {
+                expected: hir::Ty::S32,
+                found: hir::Ty::Bool,
+                annotation: Some(TextRange::new(24.into(), 27.into())),
+            },
+            30..44,
+            expect![[r#"
+                error[E0045] at 2:14: expected `s32` because of the type annotation but found `bool`
+                               v
+                  let b: s32 = {
+                         --- expected because of this annotation
+                      true
+                  };
+                  ^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn ty_mismatch_of_option() {
+        check_ty(
+            "let o: Option[s32] = Some true;",
+            TyDiagnosticKind::Mismatch {
+                expected: hir::Ty::Option(Box::new(hir::Ty::S32)),
+                found: hir::Ty::Option(Box::new(hir::Ty::Bool)),
+            },
+            21..30,
+            expect![[r#"
+                error[E0045] at 1:22: expected `Option[s32]` but found `Option[bool]`
+                  let o: Option[s32] = Some true;
+                                       ^^^^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn out_of_range_int_literal_for_ty() {
+        check_ty(
+            "4294967296",
+            TyDiagnosticKind::OutOfRangeIntLiteral { ty: hir::Ty::U32 },
+            0..10,
+            expect![[r#"
+                error[E0020] at 1:1: integer literal out of range for `u32`
+                  4294967296
+                  ^^^^^^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn invalid_cast() {
+        check_ty(
+            "\"1\" as s32",
+            TyDiagnosticKind::InvalidCast { from: hir::Ty::String, to: hir::Ty::S32 },
+            0..10,
+            expect![[r#"
+                error[E0046] at 1:1: cannot cast `string` as `s32`
+                  "1" as s32
+                  ^^^^^^^^^^
+                note: only integers, `bool` and `char` can be cast, and only to integer types and `string`
+            "#]],
+        );
+    }
+
+    #[test]
+    fn no_such_field() {
+        check_ty(
+            "(1, 2).2",
+            TyDiagnosticKind::NoSuchField {
+                ty: hir::Ty::Tuple(vec![hir::Ty::S32, hir::Ty::S32]),
+                idx: 2,
+            },
+            0..8,
+            expect![[r#"
+                error[E0047] at 1:1: no field `2` on type `(s32, s32)`
+                  (1, 2).2
+                  ^^^^^^^^
+                note: `(s32, s32)` has 2 fields
+            "#]],
+        );
+    }
+
+    #[test]
+    fn not_indexable() {
+        check_ty(
+            "true[0]",
+            TyDiagnosticKind::NotIndexable { ty: hir::Ty::Bool },
+            0..4,
+            expect![[r#"
+                error[E0049] at 1:1: cannot index into a value of type `bool`
+                  true[0]
+                  ^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn mismatched_tuple_pattern() {
+        check_ty(
+            "let (a, b) = 1;",
+            TyDiagnosticKind::MismatchedTuplePattern { len: 2, found: hir::Ty::S32 },
+            13..14,
+            expect![[r#"
+                error[E0048] at 1:14: expected a tuple of 2 elements but found `s32`
+                  let (a, b) = 1;
+                               ^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn non_exhaustive_match() {
+        check_ty(
+            "match b { true -> 1 }",
+            TyDiagnosticKind::NonExhaustiveMatch { missing: vec![hir_ty::Witness::Bool(false)] },
+            6..7,
+            expect![[r#"
+                error[E0050] at 1:7: missing match arm for `false`
+                  match b { true -> 1 }
+                        ^
+                help: add arms for them, or a `_` arm to match everything else
+            "#]],
+        );
+    }
+
+    #[test]
+    fn non_exhaustive_match_with_many_missing_values() {
+        let pair =
+            |a, b| hir_ty::Witness::Tuple(vec![hir_ty::Witness::Bool(a), hir_ty::Witness::Bool(b)]);
+
+        check_ty(
+            "match p {}",
+            TyDiagnosticKind::NonExhaustiveMatch {
+                missing: vec![
+                    pair(true, true),
+                    pair(true, false),
+                    pair(false, true),
+                    pair(false, false),
+                ],
+            },
+            6..7,
+            expect![[r#"
+                error[E0050] at 1:7: missing match arms for `(true, true)`, `(true, false)`, `(false, true)` and 1 more
+                  match p {}
+                        ^
+                help: add arms for them, or a `_` arm to match everything else
+            "#]],
+        );
+    }
+
+    #[test]
+    fn unreachable_arm() {
+        check_ty(
+            "match b { _ -> 1; true -> 2 }",
+            TyDiagnosticKind::UnreachableArm,
+            18..22,
+            expect![[r#"
+                warning[E0051] at 1:19: unreachable match arm
+                  match b { _ -> 1; true -> 2 }
+                                    ^^^^
+                note: the arms before it already match every value it does
+            "#]],
+        );
+    }
+
+    #[test]
+    fn unreachable_code() {
+        check_ty(
+            "{ return 1; 2 }",
+            TyDiagnosticKind::UnreachableCode,
+            12..13,
+            expect![[r#"
+                warning[E0052] at 1:13: unreachable code
+                  { return 1; 2 }
+                              ^
             "#]],
         );
     }
@@ -538,10 +2215,213 @@ mod tests {
             TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
             4..9,
             expect![[r#"
-                error at 1:5: expected `s32` but found `string`
+                error[E0045] at 1:5: expected `s32` but found `string`
                   1 + "foo"
                       ^^^^^
             "#]],
         );
     }
+
+    #[test]
+    fn annotation_mismatch() {
+        check_ty(
+            "let s: string = 1;",
+            TyDiagnosticKind::AnnotationMismatch {
+                expected: hir::Ty::String,
+                found: hir::Ty::S32,
+                annotation: Some(TextRange::new(7.into(), 13.into())),
+            },
+            16..17,
+            expect![[r#"
+                error[E0045] at 1:17: expected `string` because of the type annotation but found `s32`
+                  let s: string = 1;
+                                  ^
+                         ------ expected because of this annotation
+            "#]],
+        );
+    }
+
+    #[test]
+    fn annotation_mismatch_on_other_line() {
+        check_ty(
+            "let s: string = {\n    let a = 1;\n    a\n};",
+            TyDiagnosticKind::AnnotationMismatch {
+                expected: hir::Ty::String,
+                found: hir::Ty::S32,
+                annotation: Some(TextRange::new(7.into(), 13.into())),
+            },
+            37..38,
+            expect![[r#"
+                error[E0045] at 3:5: expected `string` because of the type annotation but found `s32`
+                  let s: string = {
+                         ------ expected because of this annotation
+                  ...
+                      a
+                      ^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn arg_mismatch() {
+        check_ty(
+            "f (1, \"a\")",
+            TyDiagnosticKind::ArgMismatch {
+                expected: hir::Ty::Bool,
+                found: hir::Ty::String,
+                params: None,
+            },
+            6..9,
+            expect![[r#"
+                error[E0045] at 1:7: expected `bool` because of the parameter’s type but found `string`
+                  f (1, "a")
+                        ^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn return_ty_mismatch() {
+        check_ty(
+            "fnc f: s32 -> true;",
+            TyDiagnosticKind::ReturnTyMismatch {
+                expected: hir::Ty::S32,
+                found: hir::Ty::Bool,
+                return_ty: Some(TextRange::new(7.into(), 10.into())),
+            },
+            14..18,
+            expect![[r#"
+                error[E0045] at 1:15: expected `s32` because of the return type but found `bool`
+                  fnc f: s32 -> true;
+                                ^^^^
+                         --- expected because of this return type
+            "#]],
+        );
+    }
+
+    #[test]
+    fn inferred_return_ty_mismatch() {
+        check_ty(
+            "fnc f(b: bool) -> { b && { return 1; }; \"a\" };",
+            TyDiagnosticKind::InferredReturnTyMismatch {
+                expected: hir::Ty::S32,
+                found: hir::Ty::String,
+            },
+            40..43,
+            expect![[r#"
+                error[E0045] at 1:41: expected the inferred return type `s32` but found `string`
+                  fnc f(b: bool) -> { b && { return 1; }; "a" };
+                                                          ^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn annotation_needed() {
+        check_ty(
+            "fnc f -> { let a = []; a[0].1 };",
+            TyDiagnosticKind::AnnotationNeeded,
+            23..27,
+            expect![[r#"
+                error[E0053] at 1:24: the type of this has to be known here, so it needs a type annotation
+                  fnc f -> { let a = []; a[0].1 };
+                                         ^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn recursive_const() {
+        check_ty(
+            "const A = A + 1;",
+            TyDiagnosticKind::RecursiveConst,
+            10..11,
+            expect![[r#"
+                error[E0054] at 1:11: constant depends on its own value
+                  const A = A + 1;
+                            ^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn const_overflow() {
+        check_ty(
+            "const A: u32 = 1 - 2;",
+            TyDiagnosticKind::ConstOverflow { ty: hir::Ty::U32 },
+            15..20,
+            expect![[r#"
+                error[E0055] at 1:16: value of constant overflows `u32`
+                  const A: u32 = 1 - 2;
+                                 ^^^^^
+                help: give the constant a bigger type, such as `s64`
+            "#]],
+        );
+    }
+
+    #[test]
+    fn const_division_by_zero() {
+        check_ty(
+            "const A = 1 / 0;",
+            TyDiagnosticKind::ConstDivisionByZero,
+            10..15,
+            expect![[r#"
+                error[E0056] at 1:11: constant divides by zero
+                  const A = 1 / 0;
+                            ^^^^^
+            "#]],
+        );
+    }
+
+    #[test]
+    fn colored() {
+        let input = "fnc f: s32 -> {\n    a\n};";
+        let diagnostic = Diagnostic::from_ty(TyDiagnostic {
+            kind: TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
+            range: TextRange::new(14.into(), 23.into()),
+        });
+
+        let lines = diagnostic.display_with(
+            input,
+            &Interner::default(),
+            &LineIndex::new(input),
+            DisplayConfig { color: true, ..DisplayConfig::default() },
+        );
+        let escaped: Vec<_> = lines.iter().map(|line| line.replace('\x1b', "\\e")).collect();
+
+        expect![[r#"
+            \e[31;1merror\e[0m\e[1m[E0045] at 1:15: expected `s32` but found `string`\e[0m
+                            \e[36mv\e[0m
+              \e[2mfnc f: s32 -> {\e[0m
+              \e[2m    a\e[0m
+              \e[2m};\e[0m
+              \e[36m^\e[0m
+        "#]]
+        .assert_eq(&format!("{}\n", escaped.join("\n")));
+    }
+
+    #[test]
+    fn short() {
+        let input = "let a: s32 = 1;\nlet b: s32 = true;";
+        let diagnostic = Diagnostic::from_ty(TyDiagnostic {
+            kind: TyDiagnosticKind::AnnotationMismatch {
+                expected: hir::Ty::S32,
+                found: hir::Ty::Bool,
+                annotation: Some(TextRange::new(23.into(), 26.into())),
+            },
+            range: TextRange::new(29.into(), 33.into()),
+        });
+
+        let lines = diagnostic.display_with(
+            input,
+            &Interner::default(),
+            &LineIndex::new(input),
+            DisplayConfig { format: Format::Short, ..DisplayConfig::default() },
+        );
+
+        expect![[r#"
+            2:14: error[E0045]: expected `s32` because of the type annotation but found `bool`
+        "#]]
+        .assert_eq(&format!("{}\n", lines.join("\n")));
+    }
 }
@@ -0,0 +1,145 @@
+use crate::{Diagnostic, Severity};
+use std::collections::HashMap;
+
+/// A kind of warning that can be allowed, kept as a warning or denied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnneededParens,
+    UnreachableArms,
+    UnreachableCode,
+    UnusedVariables,
+}
+
+impl Lint {
+    pub const ALL: [Self; 4] =
+        [Self::UnneededParens, Self::UnreachableArms, Self::UnreachableCode, Self::UnusedVariables];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UnneededParens => "unneeded_parens",
+            Self::UnreachableArms => "unreachable_arms",
+            Self::UnreachableCode => "unreachable_code",
+            Self::UnusedVariables => "unused_variables",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+/// Decides what happens to the warnings every stage produces.
+///
+/// `deny_warnings` turns all warnings into errors,
+/// which is what CI usually wants.
+/// Levels set for individual lints take precedence over it.
+#[derive(Debug, Clone, Default)]
+pub struct LintLevels {
+    pub deny_warnings: bool,
+    overrides: HashMap<Lint, Level>,
+}
+
+impl LintLevels {
+    pub fn set(&mut self, lint: Lint, level: Level) {
+        self.overrides.insert(lint, level);
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        match self.overrides.get(&lint) {
+            Some(level) => *level,
+            None if self.deny_warnings => Level::Deny,
+            None => Level::Warn,
+        }
+    }
+
+    /// Adjusts the severity of a diagnostic,
+    /// returning `None` if its lint is allowed.
+    pub fn apply(&self, mut diagnostic: Diagnostic) -> Option<Diagnostic> {
+        let lint = match diagnostic.lint() {
+            Some(lint) => lint,
+            None => return Some(diagnostic),
+        };
+
+        diagnostic.severity = match self.level(lint) {
+            Level::Allow => return None,
+            Level::Warn => Severity::Warning,
+            Level::Deny => Severity::Error,
+        };
+
+        Some(diagnostic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::validation::{ValidationDiagnostic, ValidationDiagnosticKind};
+    use text_size::TextRange;
+
+    fn unneeded_parens() -> Diagnostic {
+        Diagnostic::from_validation(ValidationDiagnostic {
+            kind: ValidationDiagnosticKind::UnneededParens,
+            range: TextRange::new(0.into(), 2.into()),
+        })
+    }
+
+    fn severity(levels: &LintLevels) -> Option<Severity> {
+        levels.apply(unneeded_parens()).map(|d| d.severity())
+    }
+
+    #[test]
+    fn warnings_stay_warnings_by_default() {
+        assert_eq!(severity(&LintLevels::default()), Some(Severity::Warning));
+    }
+
+    #[test]
+    fn deny_warnings() {
+        let levels = LintLevels { deny_warnings: true, ..LintLevels::default() };
+        assert_eq!(severity(&levels), Some(Severity::Error));
+    }
+
+    #[test]
+    fn allow_lint_despite_deny_warnings() {
+        let mut levels = LintLevels { deny_warnings: true, ..LintLevels::default() };
+        levels.set(Lint::UnneededParens, Level::Allow);
+        assert_eq!(severity(&levels), None);
+    }
+
+    #[test]
+    fn warn_lint_despite_deny_warnings() {
+        let mut levels = LintLevels { deny_warnings: true, ..LintLevels::default() };
+        levels.set(Lint::UnneededParens, Level::Warn);
+        assert_eq!(severity(&levels), Some(Severity::Warning));
+    }
+
+    #[test]
+    fn deny_single_lint() {
+        let mut levels = LintLevels::default();
+        levels.set(Lint::UnneededParens, Level::Deny);
+        assert_eq!(severity(&levels), Some(Severity::Error));
+    }
+
+    #[test]
+    fn errors_are_unaffected() {
+        let levels = LintLevels::default();
+        let error = Diagnostic::from_syntax(parser::SyntaxError {
+            expected_syntax: parser::ExpectedSyntax::Named("expression"),
+            kind: parser::SyntaxErrorKind::Missing { offset: 0.into() },
+        });
+        assert_eq!(levels.apply(error).map(|d| d.severity()), Some(Severity::Error));
+    }
+
+    #[test]
+    fn lint_names_round_trip() {
+        for lint in Lint::ALL {
+            assert_eq!(Lint::from_name(lint.name()), Some(lint));
+        }
+    }
+}
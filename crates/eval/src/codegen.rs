@@ -1,65 +1,90 @@
 use arena::{ArenaMap, Id};
+use interner::Interner;
 use rustc_hash::FxHashMap;
+use std::collections::VecDeque;
+use std::fmt;
 use wasm_encoder::{
-    CodeSection, DataSection, Export, ExportSection, Function, FunctionSection, Instruction,
-    MemorySection, MemoryType, Module, TypeSection, ValType,
+    BlockType, CodeSection, DataSection, EntityType, Export, ExportSection, Function,
+    FunctionSection, ImportSection, Instruction, MemorySection, MemoryType, Module, TypeSection,
+    ValType,
 };
 
 pub(crate) struct Ctx<'a> {
     type_section: TypeSection,
+    import_section: ImportSection,
     function_section: FunctionSection,
     export_section: ExportSection,
     code_section: CodeSection,
     data_section: DataSection,
     instructions: Vec<Instruction<'static>>,
     function_idxs: FxHashMap<hir::Fqn, u32>,
-    function_idx: u32,
-    functions_to_compile: Vec<hir::Fqn>,
+    functions_to_compile: VecDeque<hir::Fqn>,
     local_idxs: ArenaMap<Id<hir::LocalDef>, u32>,
     local_idx: u32,
     local_tys: Vec<(u32, ValType)>,
     constant_idx: i32,
-    bodies_map: FxHashMap<hir::Name, hir::Bodies>,
-    tys_map: FxHashMap<hir::Name, hir_ty::InferenceResult>,
+    open_labels: u32,
+    loop_labels: Vec<LoopLabels>,
+    bodies_map: &'a FxHashMap<hir::Name, hir_ty::thir::Bodies>,
     world_index: &'a hir::WorldIndex,
+    interner: &'a Interner,
+}
+
+// the labels `break` and `continue` branch to,
+// counted from the outermost label of the function
+#[derive(Clone, Copy)]
+struct LoopLabels {
+    // the label the loop was given in the source, if any
+    name: Option<hir::Name>,
+    break_label: u32,
+    continue_label: u32,
 }
 
 impl<'a> Ctx<'a> {
     pub(crate) fn new(
-        bodies_map: FxHashMap<hir::Name, hir::Bodies>,
-        tys_map: FxHashMap<hir::Name, hir_ty::InferenceResult>,
+        bodies_map: &'a FxHashMap<hir::Name, hir_ty::thir::Bodies>,
         world_index: &'a hir::WorldIndex,
+        interner: &'a Interner,
         entry_point: hir::Fqn,
-    ) -> Self {
+    ) -> Result<Self, CompileError> {
         let mut ctx = Self {
             type_section: TypeSection::new(),
+            import_section: ImportSection::new(),
             function_section: FunctionSection::new(),
             export_section: ExportSection::new(),
             code_section: CodeSection::new(),
             data_section: DataSection::new(),
             instructions: Vec::new(),
             function_idxs: FxHashMap::default(),
-            function_idx: 0,
-            functions_to_compile: vec![entry_point],
+            functions_to_compile: VecDeque::new(),
             local_idxs: ArenaMap::default(),
             local_idx: 0,
             local_tys: Vec::new(),
             constant_idx: 0,
+            open_labels: 0,
+            loop_labels: Vec::new(),
             bodies_map,
-            tys_map,
             world_index,
+            interner,
         };
-        ctx.export_section.export("main", Export::Function(ctx.function_idx));
+
+        // imported functions come before all other functions in the index space,
+        // so they have to be added before the entry point gets its index
+        ctx.import_extern_functions()?;
+
+        let entry_point_idx = ctx.function_idx(entry_point);
+        ctx.export_section.export("main", Export::Function(entry_point_idx));
         ctx.export_section.export("memory", Export::Memory(0));
 
-        ctx
+        Ok(ctx)
     }
 
-    pub(crate) fn finish(mut self) -> Vec<u8> {
-        self.compile_queued_functions();
+    pub(crate) fn finish(mut self) -> Result<Vec<u8>, CompileError> {
+        self.compile_queued_functions()?;
 
         let mut module = Module::new();
         module.section(&self.type_section);
+        module.section(&self.import_section);
         module.section(&self.function_section);
         module.section(MemorySection::new().memory(MemoryType {
             minimum: 1,
@@ -70,35 +95,69 @@ impl<'a> Ctx<'a> {
         module.section(&self.code_section);
         module.section(&self.data_section);
 
-        module.finish()
+        Ok(module.finish())
     }
 
-    fn compile_function(&mut self, fqn: hir::Fqn) {
-        let function = self.world_index.get_function(fqn).unwrap();
-
-        let params: Vec<_> = function
-            .params
+    fn import_extern_functions(&mut self) -> Result<(), CompileError> {
+        let mut extern_functions: Vec<_> = self
+            .world_index
             .iter()
-            .filter_map(|param| match param.ty {
-                hir::Ty::Unknown => unreachable!(),
-                hir::Ty::S32 => Some(ValType::I32),
-                hir::Ty::String => todo!(),
-                hir::Ty::Unit => None,
-            })
+            .map(|(fqn, _)| fqn)
+            .filter(|fqn| self.world_index.get_function(*fqn).unwrap().is_extern)
             .collect();
+        extern_functions.sort_unstable();
 
-        let results = match function.return_ty {
-            hir::Ty::Unknown => unreachable!(),
-            hir::Ty::S32 => vec![ValType::I32],
-            hir::Ty::String => vec![ValType::I32],
-            hir::Ty::Unit => Vec::new(),
-        };
+        for fqn in extern_functions {
+            let ty_idx = self.add_function_ty(fqn)?;
+
+            self.import_section.import(
+                self.interner.lookup(fqn.module.0),
+                Some(self.interner.lookup(fqn.function.0)),
+                EntityType::Function(ty_idx),
+            );
+
+            let idx = self.function_idxs.len() as u32;
+            self.function_idxs.insert(fqn, idx);
+        }
+
+        Ok(())
+    }
+
+    fn function_idx(&mut self, fqn: hir::Fqn) -> u32 {
+        if let Some(idx) = self.function_idxs.get(&fqn) {
+            return *idx;
+        }
+
+        // functions are compiled in the order they are first referenced,
+        // which keeps the function section in sync with these indexes
+        let idx = self.function_idxs.len() as u32;
+        self.function_idxs.insert(fqn, idx);
+        self.functions_to_compile.push_back(fqn);
+
+        idx
+    }
+
+    fn add_function_ty(&mut self, fqn: hir::Fqn) -> Result<u32, CompileError> {
+        let function = self.world_index.get_function(fqn).unwrap();
+
+        let mut params = Vec::new();
+        for param in &function.params {
+            params.extend(val_ty(&param.ty.expand_aliases())?);
+        }
+
+        let return_ty = self.bodies_map[&fqn.module].return_ty(fqn.function);
+        let results: Vec<_> = val_ty(&return_ty.expand_aliases())?.into_iter().collect();
 
         self.type_section.function(params, results);
 
-        self.function_section.function(self.function_idx);
+        Ok(self.type_section.len() - 1)
+    }
+
+    fn compile_function(&mut self, fqn: hir::Fqn) -> Result<(), CompileError> {
+        let ty_idx = self.add_function_ty(fqn)?;
+        self.function_section.function(ty_idx);
 
-        self.compile_expr(fqn.module, self.bodies_map[&fqn.module].function_body(fqn.function));
+        self.compile_expr(fqn.module, self.bodies_map[&fqn.module].function_body(fqn.function))?;
         self.push(Instruction::End);
 
         let mut f = Function::new(self.local_tys.drain(..));
@@ -108,18 +167,39 @@ impl<'a> Ctx<'a> {
         self.instructions.clear();
         self.code_section.function(&f);
 
-        self.compile_queued_functions();
+        Ok(())
     }
 
-    fn compile_queued_functions(&mut self) {
-        while let Some(name) = self.functions_to_compile.pop() {
-            self.compile_function(name);
+    fn alloc_i32_local(&mut self) -> u32 {
+        let idx = self.local_idx;
+        self.local_idx += 1;
+        self.local_tys.push((1, ValType::I32));
+
+        idx
+    }
+
+    fn compile_queued_functions(&mut self) -> Result<(), CompileError> {
+        while let Some(fqn) = self.functions_to_compile.pop_front() {
+            self.compile_function(fqn)?;
         }
+
+        Ok(())
     }
 
-    fn compile_statement(&mut self, module: hir::Name, statement: Id<hir::Statement>) {
+    fn compile_statement(
+        &mut self,
+        module: hir::Name,
+        statement: Id<hir::Statement>,
+    ) -> Result<(), CompileError> {
         match self.bodies_map[&module][statement] {
-            hir::Statement::Expr(expr) => self.compile_expr(module, expr),
+            hir::Statement::Expr(expr) => {
+                self.compile_expr(module, expr)?;
+
+                match self.bodies_map[&module][expr].ty {
+                    hir::Ty::Unit | hir::Ty::Never => {}
+                    _ => self.push(Instruction::Drop),
+                }
+            }
             hir::Statement::LocalDef(local_def) => {
                 let idx = self.local_idx;
                 self.local_idx += 1;
@@ -127,60 +207,196 @@ impl<'a> Ctx<'a> {
                 self.local_idxs.insert(local_def, idx);
 
                 let value = self.bodies_map[&module][local_def].value;
-                self.compile_expr(module, value);
+                self.compile_expr(module, value)?;
                 self.push(Instruction::LocalSet(idx));
 
-                let ty = match self.tys_map[&module][local_def] {
-                    hir::Ty::Unknown => unreachable!(),
-                    hir::Ty::S32 => ValType::I32,
-                    hir::Ty::String => ValType::I32,
-                    hir::Ty::Unit => return,
+                // type checking expands aliases in the types it infers;
+                // a local of type `!` is never reached,
+                // but the `local.set` above still needs it to exist
+                let ty = match &self.bodies_map[&module][local_def].ty {
+                    hir::Ty::Never => ValType::I32,
+                    ty => match val_ty(ty)? {
+                        Some(ty) => ty,
+                        None => return Ok(()),
+                    },
                 };
                 self.local_tys.push((1, ty));
             }
+            hir::Statement::Destructure { .. } => return Err(CompileError::Unsupported("tuples")),
+            hir::Statement::Defer(_) => return Err(CompileError::Unsupported("`defer`")),
+            hir::Statement::Assign { local_def, value } => {
+                self.compile_expr(module, value)?;
+                self.push(Instruction::LocalSet(self.local_idxs[local_def]));
+            }
+
+            // branch targets are relative to the innermost open label
+            hir::Statement::Break { label, value } => {
+                if let Some(value) = value {
+                    self.compile_expr(module, value)?;
+                }
+
+                let labels = self.loop_labels(label);
+                self.push(Instruction::Br(self.open_labels - labels.break_label));
+            }
+            hir::Statement::Continue { label } => {
+                let labels = self.loop_labels(label);
+                self.push(Instruction::Br(self.open_labels - labels.continue_label));
+            }
+            hir::Statement::Return { value } => {
+                if let Some(value) = value {
+                    self.compile_expr(module, value)?;
+                }
+
+                self.push(Instruction::Return);
+            }
         }
+
+        Ok(())
     }
 
-    fn compile_expr(&mut self, module: hir::Name, expr: Id<hir::Expr>) {
-        match self.bodies_map[&module][expr].clone() {
+    // the labels of the loop named by `label`, or of the innermost loop
+    fn loop_labels(&self, label: Option<hir::Name>) -> LoopLabels {
+        let labels = match label {
+            Some(label) => self.loop_labels.iter().rev().find(|labels| labels.name == Some(label)),
+            None => self.loop_labels.last(),
+        };
+        *labels.unwrap()
+    }
+
+    // strings are stored in the data section, prefixed with their length
+    fn compile_string(&mut self, s: String) {
+        let instruction = Instruction::I32Const(self.constant_idx);
+
+        let len = s.len() as i32;
+        let mut bytes = len.to_le_bytes().to_vec();
+        bytes.append(&mut s.into_bytes());
+
+        self.constant_idx += bytes.len() as i32;
+
+        self.data_section.active(0, &instruction, bytes);
+        self.push(instruction);
+    }
+
+    fn compile_expr(&mut self, module: hir::Name, expr: Id<hir::Expr>) -> Result<(), CompileError> {
+        match self.bodies_map[&module][expr].kind.clone() {
             hir::Expr::Missing => unreachable!(),
 
             hir::Expr::IntLiteral(n) => {
-                self.push(Instruction::I32Const(n as i32));
+                if self.bodies_map[&module][expr].ty == hir::Ty::S64 {
+                    self.push(Instruction::I64Const(n as i64));
+                } else {
+                    self.push(Instruction::I32Const(n as i32));
+                }
+            }
+
+            hir::Expr::BoolLiteral(b) => {
+                self.push(Instruction::I32Const(b as i32));
+            }
+
+            // chars are represented by their code point
+            hir::Expr::CharLiteral(c) => {
+                self.push(Instruction::I32Const(c as i32));
+            }
+
+            hir::Expr::StringLiteral(s) => self.compile_string(s),
+
+            // unit has no runtime representation
+            hir::Expr::Unit => {}
+
+            hir::Expr::Unary { expr, operator: hir::UnaryOperator::Neg } => {
+                if self.bodies_map[&module][expr].ty == hir::Ty::S64 {
+                    self.push(Instruction::I64Const(0));
+                    self.compile_expr(module, expr)?;
+                    self.push(Instruction::I64Sub);
+                } else {
+                    self.push(Instruction::I32Const(0));
+                    self.compile_expr(module, expr)?;
+                    self.push(Instruction::I32Sub);
+                }
+            }
+
+            hir::Expr::Cast { expr, ty } => {
+                let from = self.bodies_map[&module][expr].ty.clone();
+                let ty = ty.expand_aliases();
+
+                if ty == hir::Ty::String && from != hir::Ty::String {
+                    return Err(CompileError::Unsupported("casts to strings"));
+                }
+
+                self.compile_expr(module, expr)?;
+
+                // all other types share an `i32`,
+                // so only casts to and from `s64` need an instruction
+                match (from, ty) {
+                    (hir::Ty::S64, hir::Ty::S64) => {}
+                    (hir::Ty::S32, hir::Ty::S64) => self.push(Instruction::I64ExtendI32S),
+                    (_, hir::Ty::S64) => self.push(Instruction::I64ExtendI32U),
+                    (hir::Ty::S64, _) => self.push(Instruction::I32WrapI64),
+                    _ => {}
+                }
             }
 
-            hir::Expr::StringLiteral(s) => {
-                let instruction = Instruction::I32Const(self.constant_idx);
+            hir::Expr::Tuple(_) | hir::Expr::Field { .. } => {
+                return Err(CompileError::Unsupported("tuples"))
+            }
+            hir::Expr::Array(_) | hir::Expr::Index { .. } => {
+                return Err(CompileError::Unsupported("arrays"))
+            }
+            hir::Expr::Match { .. } => return Err(CompileError::Unsupported("`match`")),
+            hir::Expr::Variant { .. } => {
+                return Err(CompileError::Unsupported("options and results"))
+            }
 
-                let len = s.len() as i32;
-                let mut bytes = len.to_le_bytes().to_vec();
-                bytes.append(&mut s.into_bytes());
+            hir::Expr::Unary { expr, operator: hir::UnaryOperator::Not } => {
+                self.compile_expr(module, expr)?;
+                self.push(Instruction::I32Eqz);
+            }
 
-                self.constant_idx += bytes.len() as i32;
+            hir::Expr::Binary {
+                lhs,
+                rhs,
+                operator: operator @ (hir::BinaryOperator::And | hir::BinaryOperator::Or),
+            } => {
+                self.compile_expr(module, lhs)?;
+
+                // the `if` opens a label of its own
+                self.push(Instruction::If(BlockType::Result(ValType::I32)));
+                self.open_labels += 1;
+
+                if let hir::BinaryOperator::And = operator {
+                    self.compile_expr(module, rhs)?;
+                    self.push(Instruction::Else);
+                    self.push(Instruction::I32Const(0));
+                } else {
+                    self.push(Instruction::I32Const(1));
+                    self.push(Instruction::Else);
+                    self.compile_expr(module, rhs)?;
+                }
 
-                self.data_section.active(0, &instruction, bytes);
-                self.push(instruction);
+                self.open_labels -= 1;
+                self.push(Instruction::End);
             }
 
             hir::Expr::Binary { lhs, rhs, operator } => {
-                self.compile_expr(module, lhs);
-                self.compile_expr(module, rhs);
-
-                match operator {
-                    hir::BinaryOperator::Add => self.push(Instruction::I32Add),
-                    hir::BinaryOperator::Sub => self.push(Instruction::I32Sub),
-                    hir::BinaryOperator::Mul => self.push(Instruction::I32Mul),
-                    hir::BinaryOperator::Div => self.push(Instruction::I32DivU),
-                };
+                let ty = self.bodies_map[&module][lhs].ty.clone();
+
+                // strings are pointers, so comparing them like numbers would be wrong
+                if ty == hir::Ty::String {
+                    return Err(CompileError::Unsupported("string comparisons"));
+                }
+
+                self.compile_expr(module, lhs)?;
+                self.compile_expr(module, rhs)?;
+                self.push(binary_instruction(operator, ty));
             }
 
             hir::Expr::Block { statements, tail_expr } => {
                 for statement in statements {
-                    self.compile_statement(module, statement);
+                    self.compile_statement(module, statement)?;
                 }
 
                 if let Some(tail_expr) = tail_expr {
-                    self.compile_expr(module, tail_expr);
+                    self.compile_expr(module, tail_expr)?;
                 }
             }
 
@@ -192,26 +408,221 @@ impl<'a> Ctx<'a> {
                 self.push(Instruction::LocalGet(idx));
             }
 
+            hir::Expr::Const(name) => match self.bodies_map[&module].const_value(name).cloned() {
+                Some(hir_ty::ConstValue::Int(n)) => {
+                    if self.bodies_map[&module][expr].ty == hir::Ty::S64 {
+                        self.push(Instruction::I64Const(n));
+                    } else {
+                        self.push(Instruction::I32Const(n as i32));
+                    }
+                }
+                Some(hir_ty::ConstValue::Bool(b)) => self.push(Instruction::I32Const(b as i32)),
+                Some(hir_ty::ConstValue::Char(c)) => self.push(Instruction::I32Const(c as i32)),
+                Some(hir_ty::ConstValue::String(s)) => self.compile_string(s),
+                None => unreachable!(),
+            },
+
+            hir::Expr::Loop { label, body } => {
+                let block_ty = match &self.bodies_map[&module][expr].ty {
+                    hir::Ty::Never => BlockType::Empty,
+                    ty => match val_ty(ty)? {
+                        Some(ty) => BlockType::Result(ty),
+                        None => BlockType::Empty,
+                    },
+                };
+
+                // a `block` to break out of and a `loop` inside of it to continue with
+                self.push(Instruction::Block(block_ty));
+                self.push(Instruction::Loop(BlockType::Empty));
+                self.open_labels += 2;
+                self.loop_labels.push(LoopLabels {
+                    name: label,
+                    break_label: self.open_labels - 1,
+                    continue_label: self.open_labels,
+                });
+
+                self.compile_expr(module, body)?;
+                if self.bodies_map[&module][body].ty != hir::Ty::Unit {
+                    self.push(Instruction::Drop);
+                }
+                self.push(Instruction::Br(0));
+
+                self.loop_labels.pop();
+                self.open_labels -= 2;
+                self.push(Instruction::End);
+
+                // the `loop` never falls through,
+                // so the `block` can only be exited by a `break` carrying its value
+                self.push(Instruction::Unreachable);
+                self.push(Instruction::End);
+
+                // without a `break` the `block` is never exited either,
+                // which has to be spelled out for code expecting a value after it
+                if self.bodies_map[&module][expr].ty == hir::Ty::Never {
+                    self.push(Instruction::Unreachable);
+                }
+            }
+
+            hir::Expr::For { label, local_def, start, end, body } => {
+                let counter_idx = self.alloc_i32_local();
+                self.local_idxs.insert(local_def, counter_idx);
+                let end_idx = self.alloc_i32_local();
+
+                self.compile_expr(module, start)?;
+                self.push(Instruction::LocalSet(counter_idx));
+                self.compile_expr(module, end)?;
+                self.push(Instruction::LocalSet(end_idx));
+
+                // a `block` to break out of,
+                // a `loop` which checks the counter and runs the body,
+                // and a `block` around the body,
+                // which `continue` exits to reach the increment
+                self.push(Instruction::Block(BlockType::Empty));
+                self.push(Instruction::Loop(BlockType::Empty));
+
+                self.push(Instruction::LocalGet(counter_idx));
+                self.push(Instruction::LocalGet(end_idx));
+                self.push(Instruction::I32GeS);
+                self.push(Instruction::BrIf(1));
+
+                self.push(Instruction::Block(BlockType::Empty));
+                self.open_labels += 3;
+                self.loop_labels.push(LoopLabels {
+                    name: label,
+                    break_label: self.open_labels - 2,
+                    continue_label: self.open_labels,
+                });
+
+                self.compile_expr(module, body)?;
+                if self.bodies_map[&module][body].ty != hir::Ty::Unit {
+                    self.push(Instruction::Drop);
+                }
+
+                self.loop_labels.pop();
+                self.open_labels -= 3;
+                self.push(Instruction::End);
+
+                self.push(Instruction::LocalGet(counter_idx));
+                self.push(Instruction::I32Const(1));
+                self.push(Instruction::I32Add);
+                self.push(Instruction::LocalSet(counter_idx));
+                self.push(Instruction::Br(0));
+
+                self.push(Instruction::End);
+                self.push(Instruction::End);
+            }
+
+            hir::Expr::LabeledBlock { .. } => {
+                return Err(CompileError::Unsupported("labeled blocks"))
+            }
+
             hir::Expr::Call { path, args } => {
                 let fqn = match path {
                     hir::Path::ThisModule(function) => hir::Fqn { module, function },
                     hir::Path::OtherModule(fqn) => fqn,
+                    hir::Path::Builtin(_) => return Err(CompileError::Unsupported("builtins")),
                 };
 
-                self.functions_to_compile.push(fqn);
-                self.function_idxs.insert(fqn, self.function_idx);
-                self.function_idx += 1;
+                let idx = self.function_idx(fqn);
+                let param_count = self.world_index.get_function(fqn).unwrap().params.len();
 
-                for arg in args {
-                    self.compile_expr(module, arg);
+                for arg in &args {
+                    self.compile_expr(module, *arg)?;
                 }
 
-                self.push(Instruction::Call(self.function_idx));
+                // the callee only takes the full list of arguments,
+                // so the caller fills in the defaults from the callee’s module
+                for idx in args.len()..param_count {
+                    let default =
+                        self.bodies_map[&fqn.module].param_default(fqn.function, idx as u32);
+                    self.compile_expr(fqn.module, default.unwrap())?;
+                }
+
+                self.push(Instruction::Call(idx));
             }
         }
+
+        Ok(())
     }
 
     fn push(&mut self, instruction: Instruction<'static>) {
         self.instructions.push(instruction);
     }
 }
+
+/// Something [`crate::compile`] can’t turn into WebAssembly yet,
+/// though the tree-walker and the bytecode VM can run it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompileError {
+    /// What isn’t supported, like `tuples`.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported(what) => write!(f, "compiled mode doesn’t support {what} yet"),
+        }
+    }
+}
+
+// the type a value of `ty` is represented with, which is nothing for unit;
+// `!` has to be handled by the caller, since how depends on what it’s the type of
+fn val_ty(ty: &hir::Ty) -> Result<Option<ValType>, CompileError> {
+    let val_ty = match ty {
+        hir::Ty::Unknown | hir::Ty::Never | hir::Ty::Alias { .. } | hir::Ty::Var(_) => {
+            unreachable!()
+        }
+        hir::Ty::S32 => ValType::I32,
+        hir::Ty::S64 => ValType::I64,
+        hir::Ty::U32 => ValType::I32,
+        hir::Ty::String => ValType::I32,
+        hir::Ty::Bool => ValType::I32,
+        hir::Ty::Char => ValType::I32,
+        hir::Ty::Unit => return Ok(None),
+        hir::Ty::Tuple(_) => return Err(CompileError::Unsupported("tuples")),
+        hir::Ty::Array(_) => return Err(CompileError::Unsupported("arrays")),
+        hir::Ty::Option(_) | hir::Ty::Result { .. } => {
+            return Err(CompileError::Unsupported("options and results"))
+        }
+        hir::Ty::Param { .. } => return Err(CompileError::Unsupported("generic functions")),
+    };
+
+    Ok(Some(val_ty))
+}
+
+/// Picks the instruction for a binary operator applied to two operands of type `ty`.
+fn binary_instruction(operator: hir::BinaryOperator, ty: hir::Ty) -> Instruction<'static> {
+    match (ty, operator) {
+        (_, hir::BinaryOperator::And | hir::BinaryOperator::Or) => unreachable!(),
+
+        (hir::Ty::S64, hir::BinaryOperator::Add) => Instruction::I64Add,
+        (hir::Ty::S64, hir::BinaryOperator::Sub) => Instruction::I64Sub,
+        (hir::Ty::S64, hir::BinaryOperator::Mul) => Instruction::I64Mul,
+        (hir::Ty::S64, hir::BinaryOperator::Div) => Instruction::I64DivS,
+        (hir::Ty::S64, hir::BinaryOperator::Eq) => Instruction::I64Eq,
+        (hir::Ty::S64, hir::BinaryOperator::NotEq) => Instruction::I64Ne,
+        (hir::Ty::S64, hir::BinaryOperator::Lt) => Instruction::I64LtS,
+        (hir::Ty::S64, hir::BinaryOperator::LtEq) => Instruction::I64LeS,
+        (hir::Ty::S64, hir::BinaryOperator::Gt) => Instruction::I64GtS,
+        (hir::Ty::S64, hir::BinaryOperator::GtEq) => Instruction::I64GeS,
+
+        (hir::Ty::U32, hir::BinaryOperator::Div) => Instruction::I32DivU,
+        (hir::Ty::U32, hir::BinaryOperator::Lt) => Instruction::I32LtU,
+        (hir::Ty::U32, hir::BinaryOperator::LtEq) => Instruction::I32LeU,
+        (hir::Ty::U32, hir::BinaryOperator::Gt) => Instruction::I32GtU,
+        (hir::Ty::U32, hir::BinaryOperator::GtEq) => Instruction::I32GeU,
+
+        // everything else, including bools and chars, is a signed 32-bit integer
+        (_, hir::BinaryOperator::Add) => Instruction::I32Add,
+        (_, hir::BinaryOperator::Sub) => Instruction::I32Sub,
+        (_, hir::BinaryOperator::Mul) => Instruction::I32Mul,
+        (_, hir::BinaryOperator::Div) => Instruction::I32DivS,
+        (_, hir::BinaryOperator::Eq) => Instruction::I32Eq,
+        (_, hir::BinaryOperator::NotEq) => Instruction::I32Ne,
+        (_, hir::BinaryOperator::Lt) => Instruction::I32LtS,
+        (_, hir::BinaryOperator::LtEq) => Instruction::I32LeS,
+        (_, hir::BinaryOperator::Gt) => Instruction::I32GtS,
+        (_, hir::BinaryOperator::GtEq) => Instruction::I32GeS,
+    }
+}
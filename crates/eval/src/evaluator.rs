@@ -0,0 +1,1809 @@
+use crate::rand::Rng;
+use crate::vm::{self, Vm};
+use crate::{EvalError, Val};
+use arena::{ArenaMap, Id};
+use hir::{Backtrace, BacktraceFrame, CallSite};
+use rustc_hash::FxHashMap;
+use std::cmp::Ordering;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::rc::Rc;
+use std::task::Poll;
+use std::time::Instant;
+use text_size::TextRange;
+
+/// An evaluation session.
+///
+/// State that outlives a single evaluation,
+/// such as the random number generator,
+/// lives here so that consecutive evaluations can share it.
+pub struct Evaluator {
+    permissions: Permissions,
+    rng: Rng,
+    host_functions: FxHashMap<hir::Fqn, HostFunction>,
+    host_config: HostConfig,
+    file_system: Option<Box<dyn FileSystem>>,
+    clock: Option<Box<dyn Clock>>,
+    input: Option<Box<dyn BufRead>>,
+    output: Box<dyn Write>,
+    overflow_mode: OverflowMode,
+    fuel: Option<u64>,
+    heap_limit: Option<u64>,
+    max_call_depth: usize,
+    backend: Backend,
+    tracer: Option<Tracer>,
+    debugger: Option<Box<dyn Debugger>>,
+    breakpoints: Vec<(hir::Name, TextRange)>,
+    backtrace: Option<Backtrace>,
+}
+
+// deep enough for any reasonable recursion,
+// while still catching runaway recursion before it eats all memory
+const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+type HostFunction = Box<dyn FnMut(Vec<Val>) -> Poll<Val>>;
+
+type Tracer = Box<dyn FnMut(hir::Name, TextRange, &Val)>;
+
+/// Controls which builtins that interact with the outside world
+/// programs are allowed to use,
+/// so that untrusted programs can be run in a sandbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permissions {
+    pub random: bool,
+    /// Reading lines with `read_line` and printing,
+    /// wherever the host has pointed them.
+    pub io: bool,
+}
+
+impl Permissions {
+    pub const ALL: Self = Self { random: true, io: true };
+    pub const NONE: Self = Self { random: false, io: false };
+}
+
+/// How programs are run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Lowers the program to bytecode and runs that.
+    #[default]
+    Bytecode,
+    /// Walks the HIR directly,
+    /// which is slower but simple enough to check the bytecode against.
+    TreeWalker,
+}
+
+/// What happens when integer arithmetic overflows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowMode {
+    /// Fails with [`EvalError::Overflow`].
+    #[default]
+    Checked,
+    /// Wraps around to the other end of the type’s range.
+    Wrapping,
+}
+
+/// What programs see of the process running them
+/// through the `args` and `env` builtins.
+///
+/// The host decides what goes in here,
+/// so by default programs get no arguments and no environment variables.
+#[derive(Debug, Clone, Default)]
+pub struct HostConfig {
+    pub args: Vec<String>,
+    pub env: FxHashMap<String, String>,
+}
+
+/// Where the `read_file` and `write_file` builtins go.
+///
+/// Programs can only use files once the host has provided one
+/// with [`Evaluator::set_file_system`].
+pub trait FileSystem {
+    fn read_file(&mut self, path: &str) -> io::Result<String>;
+    fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()>;
+}
+
+/// The file system of the machine the evaluator runs on.
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn read_file(&mut self, path: &str) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+}
+
+/// Where the `clock_ms` builtin gets the time from.
+///
+/// Programs can only read the time once the host has provided one
+/// with [`Evaluator::set_clock`],
+/// which keeps evaluation deterministic otherwise.
+pub trait Clock {
+    /// Milliseconds since some fixed point in the past.
+    /// This must never decrease.
+    fn now_ms(&mut self) -> i64;
+}
+
+/// A monotonic clock counting from when it was created.
+pub struct StdClock {
+    start: Instant,
+}
+
+impl StdClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for StdClock {
+    fn now_ms(&mut self) -> i64 {
+        self.start.elapsed().as_millis() as i64
+    }
+}
+
+/// Follows along as a program runs,
+/// so that a REPL or an editor can step through it
+/// and look at its parameters and locals on the way.
+///
+/// The callbacks run before the program continues,
+/// so a debugger that waits for input in them pauses the program.
+pub trait Debugger {
+    fn before_statement(&mut self, _location: Location, _scope: &Scope<'_>) {}
+
+    /// Called before a statement within a breakpoint,
+    /// instead of [`Debugger::before_statement`].
+    fn on_breakpoint(&mut self, location: Location, scope: &Scope<'_>) {
+        self.before_statement(location, scope);
+    }
+
+    /// Called before a function defined in a program or by the host runs,
+    /// once its arguments have been evaluated.
+    fn before_call(&mut self, _function: hir::Fqn, _args: &[Val]) {}
+}
+
+/// Where in a program a statement is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub module: hir::Name,
+    pub range: TextRange,
+}
+
+/// What a [`Debugger`] can see of the function being run.
+pub struct Scope<'a> {
+    pub function: hir::Fqn,
+    /// The parameters of the function, including those left out which took on their defaults.
+    pub params: &'a [Val],
+    /// The locals which have been given a value so far.
+    pub locals: Vec<(Id<hir::LocalDef>, &'a Val)>,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new(Permissions::ALL)
+    }
+}
+
+impl Evaluator {
+    pub fn new(permissions: Permissions) -> Self {
+        Self {
+            permissions,
+            rng: Rng::new(0),
+            host_functions: FxHashMap::default(),
+            host_config: HostConfig::default(),
+            file_system: None,
+            clock: None,
+            input: None,
+            output: Box::new(io::stdout()),
+            overflow_mode: OverflowMode::default(),
+            fuel: None,
+            heap_limit: None,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            backend: Backend::default(),
+            tracer: None,
+            debugger: None,
+            breakpoints: Vec::new(),
+            backtrace: None,
+        }
+    }
+
+    /// Calls `f` with the module and range of every expression
+    /// as soon as it has been evaluated, along with its value,
+    /// which helps with debugging programs as well as the evaluator itself.
+    ///
+    /// Expressions which jump elsewhere, like `return`, have no value
+    /// and aren’t traced.
+    pub fn set_tracer(&mut self, f: impl FnMut(hir::Name, TextRange, &Val) + 'static) {
+        self.tracer = Some(Box::new(f));
+    }
+
+    /// Calls into `debugger` as programs run.
+    ///
+    /// Debugging needs to know which statement is running,
+    /// which bytecode doesn’t keep track of,
+    /// so programs are evaluated with [`Backend::TreeWalker`] while a debugger is set.
+    pub fn set_debugger(&mut self, debugger: impl Debugger + 'static) {
+        self.debugger = Some(Box::new(debugger));
+    }
+
+    pub fn clear_debugger(&mut self) {
+        self.debugger = None;
+    }
+
+    /// Makes the debugger stop at every statement which starts within `range`
+    /// with [`Debugger::on_breakpoint`].
+    pub fn add_breakpoint(&mut self, module: hir::Name, range: TextRange) {
+        self.breakpoints.push((module, range));
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    /// How many calls can be in progress at once
+    /// before evaluation fails with [`EvalError::StackOverflow`].
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// Limits how many more steps evaluations can take in total
+    /// before failing with [`EvalError::FuelExhausted`],
+    /// so that a program which never finishes can’t hang the host.
+    ///
+    /// Evaluations are unlimited until this is called.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = Some(fuel);
+    }
+
+    /// How many steps are left before evaluations run out of fuel.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Limits how many more bytes of strings and arrays evaluations can create in total
+    /// before failing with [`EvalError::HeapExhausted`],
+    /// so that a program can’t use up all of the host’s memory.
+    ///
+    /// Strings written in the program don’t count, since they come with it.
+    /// Evaluations are unlimited until this is called.
+    pub fn set_heap_limit(&mut self, bytes: u64) {
+        self.heap_limit = Some(bytes);
+    }
+
+    /// How many more bytes evaluations can create before running out.
+    pub fn heap_limit(&self) -> Option<u64> {
+        self.heap_limit
+    }
+
+    pub fn set_overflow_mode(&mut self, overflow_mode: OverflowMode) {
+        self.overflow_mode = overflow_mode;
+    }
+
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Some(Box::new(clock));
+    }
+
+    /// Where `read_line` reads from,
+    /// which is stdin until this is called.
+    pub fn set_input(&mut self, input: impl BufRead + 'static) {
+        self.input = Some(Box::new(input));
+    }
+
+    /// Where `print` and `println` write to,
+    /// which is stdout until this is called.
+    pub fn set_output(&mut self, output: impl Write + 'static) {
+        self.output = Box::new(output);
+    }
+
+    pub fn set_file_system(&mut self, file_system: impl FileSystem + 'static) {
+        self.file_system = Some(Box::new(file_system));
+    }
+
+    pub fn set_host_config(&mut self, host_config: HostConfig) {
+        self.host_config = host_config;
+    }
+
+    /// Provides the implementation of an extern function.
+    ///
+    /// A host function that can’t produce its result right away
+    /// returns [`Poll::Pending`],
+    /// which suspends evaluation until the host supplies the result
+    /// with [`PendingHostCall::complete`].
+    pub fn register_host_function(
+        &mut self,
+        fqn: hir::Fqn,
+        f: impl FnMut(Vec<Val>) -> Poll<Val> + 'static,
+    ) {
+        self.host_functions.insert(fqn, Box::new(f));
+    }
+
+    /// Provides a function programs can call by name without declaring it,
+    /// like a host function for an extern function they didn’t have to write.
+    ///
+    /// This declares it in `world_index` with `signature`,
+    /// so it has to happen before the program is lowered and type checked.
+    pub fn register_fnc(
+        &mut self,
+        world_index: &mut hir::WorldIndex,
+        fqn: hir::Fqn,
+        signature: hir::Function,
+        f: impl FnMut(Vec<Val>) -> Poll<Val> + 'static,
+    ) {
+        world_index.add_native_function(fqn, signature);
+        self.register_host_function(fqn, f);
+    }
+
+    /// Captures the state evaluations leave behind for later ones,
+    /// so that it can be put back with [`Evaluator::restore`],
+    /// for example to undo an evaluation
+    /// or to try one out without affecting the rest of the session.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot { rng: self.rng.clone(), fuel: self.fuel, heap_limit: self.heap_limit }
+    }
+
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.rng = snapshot.rng;
+        self.fuel = snapshot.fuel;
+        self.heap_limit = snapshot.heap_limit;
+    }
+
+    /// The calls in progress when the last evaluation failed,
+    /// or `None` if it didn’t.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_ref()
+    }
+
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    pub fn eval(
+        &mut self,
+        fqn: hir::Fqn,
+        bodies_map: &FxHashMap<hir::Name, hir_ty::thir::Bodies>,
+        world_index: &hir::WorldIndex,
+    ) -> Result<Val, EvalError> {
+        match self.start(fqn, bodies_map, world_index, None)? {
+            Progress::Finished(val) => Ok(val),
+            Progress::Paused(_) => unreachable!(),
+            Progress::Pending(call) => Err(EvalError::HostFunctionPending(call.function)),
+        }
+    }
+
+    /// Like [`Evaluator::eval`],
+    /// but pauses once `budget` steps have been taken
+    /// so the host can do other work before resuming.
+    pub fn eval_with_budget(
+        &mut self,
+        fqn: hir::Fqn,
+        bodies_map: &FxHashMap<hir::Name, hir_ty::thir::Bodies>,
+        world_index: &hir::WorldIndex,
+        budget: u64,
+    ) -> Result<Progress, EvalError> {
+        self.start(fqn, bodies_map, world_index, Some(budget))
+    }
+
+    /// Continues a paused evaluation for at most `budget` more steps.
+    ///
+    /// The program must be the same one the evaluation was started with.
+    pub fn resume(
+        &mut self,
+        paused: EvalPaused,
+        bodies_map: &FxHashMap<hir::Name, hir_ty::thir::Bodies>,
+        world_index: &hir::WorldIndex,
+        budget: u64,
+    ) -> Result<Progress, EvalError> {
+        self.backtrace = None;
+        let EvalPaused { values, state } = paused;
+
+        match state {
+            PausedState::TreeWalker { tasks, frames } => {
+                let ctx = Ctx {
+                    evaluator: self,
+                    bodies_map,
+                    world_index,
+                    tasks,
+                    values,
+                    frames,
+                    waiting_on: None,
+                };
+                ctx.run(Some(budget))
+            }
+            PausedState::Bytecode(state) => Vm::resume(self, values, state).run(Some(budget)),
+        }
+    }
+
+    fn start(
+        &mut self,
+        fqn: hir::Fqn,
+        bodies_map: &FxHashMap<hir::Name, hir_ty::thir::Bodies>,
+        world_index: &hir::WorldIndex,
+        budget: Option<u64>,
+    ) -> Result<Progress, EvalError> {
+        self.backtrace = None;
+
+        if self.backend == Backend::Bytecode && self.debugger.is_none() {
+            let trace = self.tracer.is_some();
+            let program = codegen_bytecode::lower(fqn, bodies_map, world_index, trace);
+            return Vm::new(self, Rc::new(program)).run(budget);
+        }
+
+        let mut ctx = Ctx {
+            evaluator: self,
+            bodies_map,
+            world_index,
+            tasks: Vec::new(),
+            values: Vec::new(),
+            frames: Vec::new(),
+            waiting_on: None,
+        };
+
+        ctx.call(fqn, Vec::new(), None)?;
+        ctx.run(budget)
+    }
+}
+
+/// What [`Evaluator::snapshot`] captured:
+/// where the random number generator is and how much fuel and heap is left.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    rng: Rng,
+    fuel: Option<u64>,
+    heap_limit: Option<u64>,
+}
+
+/// How far an evaluation with a step budget got.
+pub enum Progress {
+    Finished(Val),
+    Paused(EvalPaused),
+    Pending(PendingHostCall),
+}
+
+/// An evaluation that ran out of steps,
+/// which can be continued with [`Evaluator::resume`].
+pub struct EvalPaused {
+    values: Vec<Val>,
+    state: PausedState,
+}
+
+enum PausedState {
+    TreeWalker { tasks: Vec<Task>, frames: Vec<Frame> },
+    Bytecode(vm::State),
+}
+
+impl EvalPaused {
+    pub(crate) fn bytecode(values: Vec<Val>, state: vm::State) -> Self {
+        Self { values, state: PausedState::Bytecode(state) }
+    }
+}
+
+/// An evaluation waiting for a host function to return.
+pub struct PendingHostCall {
+    pub(crate) function: hir::Fqn,
+    pub(crate) paused: EvalPaused,
+}
+
+impl PendingHostCall {
+    pub fn function(&self) -> hir::Fqn {
+        self.function
+    }
+
+    /// Supplies the value the host function returned,
+    /// after which the evaluation can be resumed.
+    pub fn complete(self, val: Val) -> EvalPaused {
+        let mut paused = self.paused;
+        paused.values.push(val);
+        paused
+    }
+}
+
+// Rather than recursing over the HIR,
+// evaluation works through an explicit stack of tasks.
+// Evaluating an expression pushes the work needed to finish it,
+// and every finished expression leaves its value on the value stack.
+struct Ctx<'a> {
+    evaluator: &'a mut Evaluator,
+    bodies_map: &'a FxHashMap<hir::Name, hir_ty::thir::Bodies>,
+    world_index: &'a hir::WorldIndex,
+    tasks: Vec<Task>,
+    values: Vec<Val>,
+    frames: Vec<Frame>,
+    waiting_on: Option<hir::Fqn>,
+}
+
+enum Task {
+    Expr(Id<hir::Expr>),
+    Statement(Id<hir::Statement>),
+    // Combines the two values on top of the value stack
+    // with the operator of the binary expression.
+    Binary { operator: hir::BinaryOperator, expr: Id<hir::Expr> },
+    Unary { operator: hir::UnaryOperator, expr: Id<hir::Expr> },
+    Cast(hir::Ty),
+    // Collects the given number of values into a tuple.
+    Tuple(usize),
+    Field(u32),
+    // Collects the given number of values into an array.
+    Array(usize),
+    // Wraps the value on top of the value stack in a variant.
+    Variant(hir::Variant),
+    // Picks the element at the index on top of the value stack
+    // out of the array below it.
+    Index,
+    Destructure(Vec<Option<Id<hir::LocalDef>>>),
+    // Runs the first arm of the match whose pattern fits the value on top of the value stack.
+    Match(Id<hir::Expr>),
+    // Evaluates the right-hand side of `&&` or `||`
+    // only if the left-hand side doesn’t already decide the result.
+    ShortCircuit { operator: hir::BinaryOperator, rhs: Id<hir::Expr> },
+    SetLocal(Id<hir::LocalDef>),
+    // Fails with the message on top of the value stack
+    // if the condition below it is false.
+    Assert(Id<hir::Expr>),
+    // Exits the loop or block with the label,
+    // or the innermost loop without one,
+    // with the value on top of the value stack.
+    Break { label: Option<hir::Name> },
+    // Starts the next iteration of the loop with the label,
+    // or the innermost loop without one.
+    Continue { label: Option<hir::Name> },
+    // Exits the current function with the value on top of the value stack.
+    ExitFunction,
+    // Runs what the block being left deferred,
+    // which is everything deferred in the current frame past `deferred_len`.
+    ExitBlock { deferred_len: usize },
+    // Starts the next iteration of a loop,
+    // throwing away whatever the previous iteration left on the value stack.
+    Loop(LoopState),
+    // Starts a `for` loop once both bounds of its range are on the value stack.
+    StartFor { label: Option<hir::Name>, local_def: Id<hir::LocalDef>, body: Id<hir::Expr> },
+    // Marks where a `break` to a labeled block continues,
+    // which does nothing if the block finishes normally.
+    LabeledBlock { label: hir::Name, values_len: usize },
+    Call { path: hir::Path, arg_count: usize, expr: Id<hir::Expr> },
+    // Passes the value on top of the value stack
+    // as the next parameter of the current function.
+    PushParam,
+    Nil,
+    Discard,
+    Return,
+    // Reports the value on top of the value stack as the value of the expression.
+    Trace(Id<hir::Expr>),
+}
+
+#[derive(Clone, Copy)]
+struct LoopState {
+    label: Option<hir::Name>,
+    body: Id<hir::Expr>,
+    values_len: usize,
+    // only `for` loops count their iterations
+    counter: Option<Counter>,
+}
+
+#[derive(Clone, Copy)]
+struct Counter {
+    local_def: Id<hir::LocalDef>,
+    next: i32,
+    end: i32,
+}
+
+struct Frame {
+    function: hir::Fqn,
+    call_site: Option<CallSite>,
+    params: Vec<Val>,
+    locals: ArenaMap<Id<hir::LocalDef>, Val>,
+    // the height of the value stack when the function was called
+    values_len: usize,
+    // the expressions `defer` statements in blocks that haven’t been left yet deferred
+    deferred: Vec<Id<hir::Expr>>,
+}
+
+impl<'a> Ctx<'a> {
+    fn run(mut self, budget: Option<u64>) -> Result<Progress, EvalError> {
+        let mut steps = 0;
+
+        loop {
+            if let Some(function) = self.waiting_on.take() {
+                let paused = self.pause();
+                return Ok(Progress::Pending(PendingHostCall { function, paused }));
+            }
+
+            let Some(task) = self.tasks.pop() else { break };
+
+            if budget == Some(steps) {
+                self.tasks.push(task);
+                return Ok(Progress::Paused(self.pause()));
+            }
+
+            if let Err(error) = self.evaluator.burn_fuel().and_then(|()| self.step(task)) {
+                self.evaluator.failed_with(self.backtrace());
+                return Err(error);
+            }
+            steps += 1;
+        }
+
+        assert_eq!(self.values.len(), 1);
+        Ok(Progress::Finished(self.values.pop().unwrap()))
+    }
+
+    fn backtrace(&self) -> Backtrace {
+        let frames = self
+            .frames
+            .iter()
+            .rev()
+            .map(|frame| BacktraceFrame { function: frame.function, call_site: frame.call_site });
+        Backtrace { frames: frames.collect() }
+    }
+
+    fn pause(self) -> EvalPaused {
+        let Self { tasks, values, frames, .. } = self;
+        EvalPaused { values, state: PausedState::TreeWalker { tasks, frames } }
+    }
+
+    fn step(&mut self, task: Task) -> Result<(), EvalError> {
+        match task {
+            Task::Expr(expr) => self.eval_expr(expr),
+
+            Task::Statement(statement) => {
+                self.debug_statement(statement);
+
+                match self.bodies()[statement] {
+                    hir::Statement::Expr(expr) => {
+                        self.tasks.push(Task::Discard);
+                        self.tasks.push(Task::Expr(expr));
+                    }
+                    hir::Statement::LocalDef(local_def) => {
+                        self.tasks.push(Task::SetLocal(local_def));
+                        self.tasks.push(Task::Expr(self.bodies()[local_def].value));
+                    }
+                    hir::Statement::Destructure { value, ref local_defs, .. } => {
+                        self.tasks.push(Task::Destructure(local_defs.clone()));
+                        self.tasks.push(Task::Expr(value));
+                    }
+                    hir::Statement::Assign { local_def, value } => {
+                        self.tasks.push(Task::SetLocal(local_def));
+                        self.tasks.push(Task::Expr(value));
+                    }
+                    hir::Statement::Break { label, value: Some(value) } => {
+                        self.tasks.push(Task::Break { label });
+                        self.tasks.push(Task::Expr(value));
+                    }
+                    hir::Statement::Break { label, value: None } => {
+                        self.values.push(Val::Nil);
+                        self.step(Task::Break { label })?;
+                    }
+                    hir::Statement::Continue { label } => self.step(Task::Continue { label })?,
+                    hir::Statement::Return { value: Some(value) } => {
+                        self.tasks.push(Task::ExitFunction);
+                        self.tasks.push(Task::Expr(value));
+                    }
+                    hir::Statement::Return { value: None } => {
+                        self.values.push(Val::Nil);
+                        self.step(Task::ExitFunction)?;
+                    }
+                    hir::Statement::Defer(expr) => self.frame().deferred.push(expr),
+                }
+            }
+
+            Task::Unary { operator, expr } => {
+                let operand = self.values.pop().unwrap();
+                match self.evaluator.unary(operator, operand) {
+                    Ok(result) => self.values.push(result),
+                    Err(error) => return Err(self.arithmetic_error(error, expr)),
+                }
+            }
+
+            Task::Cast(ty) => {
+                let val = self.evaluator.cast(self.values.pop().unwrap(), ty)?;
+                self.values.push(val);
+            }
+
+            Task::Tuple(len) => {
+                let elements = self.values.split_off(self.values.len() - len);
+                self.values.push(Val::Tuple(elements));
+            }
+
+            Task::Field(idx) => {
+                let Val::Tuple(mut elements) = self.values.pop().unwrap() else {
+                    unreachable!("type checking guarantees this is a tuple")
+                };
+                self.values.push(elements.swap_remove(idx as usize));
+            }
+
+            Task::Array(len) => {
+                let elements = self.values.split_off(self.values.len() - len);
+                let array = self.evaluator.array(elements)?;
+                self.values.push(array);
+            }
+
+            Task::Variant(variant) => {
+                let field = self.values.pop().unwrap();
+                self.values.push(Val::Variant(variant, Some(Box::new(field))));
+            }
+
+            Task::Index => {
+                let index = self.values.pop().unwrap();
+                let array = self.values.pop().unwrap();
+                self.values.push(index_array(array, index)?);
+            }
+
+            Task::Destructure(local_defs) => {
+                let Val::Tuple(elements) = self.values.pop().unwrap() else {
+                    unreachable!("type checking guarantees this is a tuple")
+                };
+
+                for (local_def, element) in local_defs.into_iter().zip(elements) {
+                    if let Some(local_def) = local_def {
+                        self.frame().locals.insert(local_def, element);
+                    }
+                }
+            }
+
+            Task::Match(expr) => {
+                let hir::Expr::Match { arms, .. } = &self.bodies()[expr].kind else {
+                    unreachable!()
+                };
+                let value = self.values.pop().unwrap();
+
+                let mut bindings = Vec::new();
+                let arm = arms
+                    .iter()
+                    .find(|arm| {
+                        bindings.clear();
+                        self.match_pattern(arm.pattern, &value, &mut bindings)
+                    })
+                    .expect("exhaustiveness checking guarantees one of the arms matches");
+
+                for (local_def, val) in bindings {
+                    self.frame().locals.insert(local_def, val);
+                }
+                self.tasks.push(Task::Expr(arm.body));
+            }
+
+            Task::ShortCircuit { operator, rhs } => {
+                let lhs = match self.values.last() {
+                    Some(Val::Bool(b)) => *b,
+                    _ => unreachable!("type checking guarantees this is a bool"),
+                };
+
+                let is_decided = match operator {
+                    hir::BinaryOperator::And => !lhs,
+                    hir::BinaryOperator::Or => lhs,
+                    _ => unreachable!(),
+                };
+
+                if !is_decided {
+                    self.values.pop();
+                    self.tasks.push(Task::Expr(rhs));
+                }
+            }
+
+            Task::Binary { operator, .. } if operator.is_comparison() => {
+                let rhs = self.values.pop().unwrap();
+                let lhs = self.values.pop().unwrap();
+                self.values.push(Val::Bool(compare_with(operator, &lhs, &rhs)));
+            }
+
+            Task::Binary { operator, expr } => {
+                let rhs = self.values.pop().unwrap();
+                let lhs = self.values.pop().unwrap();
+                match self.evaluator.arithmetic(operator, lhs, rhs) {
+                    Ok(result) => self.values.push(result),
+                    Err(error) => return Err(self.arithmetic_error(error, expr)),
+                }
+            }
+
+            Task::SetLocal(local_def) => {
+                let value = self.values.pop().unwrap();
+                self.frame().locals.insert(local_def, value);
+            }
+
+            Task::Call { path, arg_count, expr } => {
+                let args = self.values.split_off(self.values.len() - arg_count);
+
+                let module = self.frame().function.module;
+                let call_site = CallSite { module, range: self.bodies().range_for_expr(expr) };
+
+                // tracing needs calls to come back to trace their value
+                if self.evaluator.tracer.is_none() && self.bodies().is_tail_call(expr) {
+                    let fqn = match path {
+                        hir::Path::ThisModule(function) => hir::Fqn { module, function },
+                        hir::Path::OtherModule(fqn) => fqn,
+                        hir::Path::Builtin(_) => unreachable!("builtins are never tail calls"),
+                    };
+                    return self.tail_call(fqn, args, call_site);
+                }
+
+                match path {
+                    hir::Path::ThisModule(_) | hir::Path::OtherModule(_)
+                        if self.frames.len() >= self.evaluator.max_call_depth =>
+                    {
+                        return Err(EvalError::StackOverflow { module, range: call_site.range });
+                    }
+                    hir::Path::ThisModule(function) => {
+                        self.call(hir::Fqn { module, function }, args, Some(call_site))?;
+                    }
+                    hir::Path::OtherModule(fqn) => self.call(fqn, args, Some(call_site))?,
+                    hir::Path::Builtin(builtin) => {
+                        let value = self.evaluator.call_builtin(builtin, args)?;
+                        self.values.push(value);
+                    }
+                }
+            }
+
+            Task::Assert(expr) => {
+                let Some(Val::String(message)) = self.values.pop() else { unreachable!() };
+                let Some(Val::Bool(condition)) = self.values.pop() else { unreachable!() };
+
+                if !condition {
+                    return Err(EvalError::AssertionFailed {
+                        message: message.to_string(),
+                        module: self.frame().function.module,
+                        range: self.bodies().range_for_expr(expr),
+                    });
+                }
+
+                self.values.push(Val::Nil);
+            }
+
+            // the value stays on the value stack
+            // while deferred expressions run during unwinding
+            Task::Break { label } => {
+                let values_len = match self.unwind_to_loop(label, Task::Break { label }) {
+                    Some(Task::Loop(LoopState { values_len, .. }))
+                    | Some(Task::LabeledBlock { values_len, .. }) => values_len,
+                    Some(_) => unreachable!("only loops and labeled blocks can be left"),
+                    None => return Ok(()),
+                };
+                let value = self.values.pop().unwrap();
+                self.values.truncate(values_len);
+                self.values.push(value);
+            }
+
+            Task::Continue { label } => {
+                match self.unwind_to_loop(label, Task::Continue { label }) {
+                    Some(Task::Loop(state)) => self.tasks.push(Task::Loop(state)),
+                    Some(_) => unreachable!("lowering only allows continuing loops"),
+                    None => {}
+                }
+            }
+
+            // the block’s value is already on top of the value stack
+            Task::LabeledBlock { .. } => {}
+
+            Task::ExitFunction => {
+                if !self.unwind_to_return(Task::ExitFunction) {
+                    return Ok(());
+                }
+                let value = self.values.pop().unwrap();
+                let frame = self.frames.pop().unwrap();
+                self.values.truncate(frame.values_len);
+                self.values.push(value);
+            }
+
+            Task::ExitBlock { deferred_len } => self.run_deferred(deferred_len),
+
+            Task::Loop(state) => {
+                self.values.truncate(state.values_len);
+
+                let mut next_state = state;
+                if let Some(counter) = state.counter {
+                    if counter.next >= counter.end {
+                        self.values.push(Val::Nil);
+                        return Ok(());
+                    }
+
+                    self.frame().locals.insert(counter.local_def, Val::S32(counter.next));
+                    next_state.counter = Some(Counter { next: counter.next + 1, ..counter });
+                }
+
+                self.tasks.push(Task::Loop(next_state));
+                self.tasks.push(Task::Expr(state.body));
+            }
+
+            Task::StartFor { label, local_def, body } => {
+                let end = self.pop_s32();
+                let start = self.pop_s32();
+
+                self.tasks.push(Task::Loop(LoopState {
+                    label,
+                    body,
+                    values_len: self.values.len(),
+                    counter: Some(Counter { local_def, next: start, end }),
+                }));
+            }
+
+            Task::PushParam => {
+                let value = self.values.pop().unwrap();
+                self.frame().params.push(value);
+            }
+
+            Task::Nil => self.values.push(Val::Nil),
+
+            Task::Discard => {
+                self.values.pop();
+            }
+
+            Task::Return => {
+                self.frames.pop();
+            }
+
+            Task::Trace(expr) => {
+                let module = self.frame().function.module;
+                let range = self.bodies().range_for_expr(expr);
+                self.evaluator.trace(module, range, self.values.last().unwrap());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn eval_expr(&mut self, expr: Id<hir::Expr>) {
+        // everything the expression pushes runs before this
+        if self.evaluator.tracer.is_some() {
+            self.tasks.push(Task::Trace(expr));
+        }
+
+        let bodies = self.bodies();
+        let hir_ty::thir::Expr { kind, ty } = &bodies[expr];
+
+        match kind {
+            hir::Expr::Missing => unreachable!("tried to evaluate a program with errors"),
+
+            hir::Expr::IntLiteral(_) => {
+                self.values.push(int_literal(bodies.int_literal(expr).unwrap(), ty));
+            }
+
+            hir::Expr::BoolLiteral(b) => self.values.push(Val::Bool(*b)),
+
+            hir::Expr::StringLiteral(s) => self.values.push(Val::String(s.as_str().into())),
+
+            hir::Expr::Unit => self.values.push(Val::Nil),
+
+            hir::Expr::CharLiteral(c) => self.values.push(Val::Char(*c)),
+
+            hir::Expr::Unary { expr: operand, operator } => match bodies.int_literal(expr) {
+                Some(n) => self.values.push(int_literal(n, ty)),
+                None => {
+                    self.tasks.push(Task::Unary { operator: *operator, expr });
+                    self.tasks.push(Task::Expr(*operand));
+                }
+            },
+
+            hir::Expr::Cast { expr, ty } => {
+                self.tasks.push(Task::Cast(ty.expand_aliases()));
+                self.tasks.push(Task::Expr(*expr));
+            }
+
+            hir::Expr::Tuple(elements) => {
+                self.tasks.push(Task::Tuple(elements.len()));
+
+                for element in elements.iter().rev() {
+                    self.tasks.push(Task::Expr(*element));
+                }
+            }
+
+            hir::Expr::Field { tuple, idx } => {
+                self.tasks.push(Task::Field(*idx));
+                self.tasks.push(Task::Expr(*tuple));
+            }
+
+            hir::Expr::Array(elements) => {
+                self.tasks.push(Task::Array(elements.len()));
+
+                for element in elements.iter().rev() {
+                    self.tasks.push(Task::Expr(*element));
+                }
+            }
+
+            hir::Expr::Index { array, index } => {
+                self.tasks.push(Task::Index);
+                self.tasks.push(Task::Expr(*index));
+                self.tasks.push(Task::Expr(*array));
+            }
+
+            hir::Expr::Variant { variant, value: Some(value) } => {
+                self.tasks.push(Task::Variant(*variant));
+                self.tasks.push(Task::Expr(*value));
+            }
+
+            hir::Expr::Variant { variant, value: None } => {
+                self.values.push(Val::Variant(*variant, None));
+            }
+
+            hir::Expr::Binary {
+                lhs,
+                rhs,
+                operator: operator @ (hir::BinaryOperator::And | hir::BinaryOperator::Or),
+            } => {
+                self.tasks.push(Task::ShortCircuit { operator: *operator, rhs: *rhs });
+                self.tasks.push(Task::Expr(*lhs));
+            }
+
+            hir::Expr::Binary { lhs, rhs, operator } => {
+                self.tasks.push(Task::Binary { operator: *operator, expr });
+                self.tasks.push(Task::Expr(*rhs));
+                self.tasks.push(Task::Expr(*lhs));
+            }
+
+            hir::Expr::Block { statements, tail_expr } => {
+                let has_defer = statements
+                    .iter()
+                    .any(|statement| matches!(bodies[*statement], hir::Statement::Defer(_)));
+                if has_defer {
+                    let deferred_len = self.frame().deferred.len();
+                    self.tasks.push(Task::ExitBlock { deferred_len });
+                }
+
+                match tail_expr {
+                    Some(tail_expr) => self.tasks.push(Task::Expr(*tail_expr)),
+                    None => self.tasks.push(Task::Nil),
+                }
+
+                for statement in statements.iter().rev() {
+                    self.tasks.push(Task::Statement(*statement));
+                }
+            }
+
+            hir::Expr::Local(local_def) => {
+                let value = self.frame().locals[*local_def].clone();
+                self.values.push(value);
+            }
+
+            hir::Expr::Param { idx } => {
+                let value = self.frame().params[*idx as usize].clone();
+                self.values.push(value);
+            }
+
+            // type checking has already worked out the value
+            hir::Expr::Const(name) => {
+                let val = match bodies.const_value(*name) {
+                    Some(hir_ty::ConstValue::Int(n)) => match ty {
+                        hir::Ty::S64 => Val::S64(*n),
+                        hir::Ty::U32 => Val::U32(*n as u32),
+                        _ => Val::S32(*n as i32),
+                    },
+                    Some(hir_ty::ConstValue::Bool(b)) => Val::Bool(*b),
+                    Some(hir_ty::ConstValue::String(s)) => Val::String(s.as_str().into()),
+                    Some(hir_ty::ConstValue::Char(c)) => Val::Char(*c),
+                    None => unreachable!("tried to evaluate a program with errors"),
+                };
+                self.values.push(val);
+            }
+
+            hir::Expr::Loop { label, body } => {
+                self.tasks.push(Task::Loop(LoopState {
+                    label: *label,
+                    body: *body,
+                    values_len: self.values.len(),
+                    counter: None,
+                }));
+            }
+
+            hir::Expr::For { label, local_def, start, end, body } => {
+                self.tasks.push(Task::StartFor {
+                    label: *label,
+                    local_def: *local_def,
+                    body: *body,
+                });
+                self.tasks.push(Task::Expr(*end));
+                self.tasks.push(Task::Expr(*start));
+            }
+
+            hir::Expr::LabeledBlock { label, body } => {
+                self.tasks
+                    .push(Task::LabeledBlock { label: *label, values_len: self.values.len() });
+                self.tasks.push(Task::Expr(*body));
+            }
+
+            hir::Expr::Call { path, args } => {
+                // a failed assertion or overflowing stack needs to know where it is
+                let task = match path {
+                    hir::Path::Builtin(hir::Builtin::Assert) => Task::Assert(expr),
+                    _ => Task::Call { path: *path, arg_count: args.len(), expr },
+                };
+                self.tasks.push(task);
+
+                for arg in args.iter().rev() {
+                    self.tasks.push(Task::Expr(*arg));
+                }
+            }
+
+            hir::Expr::Match { value, .. } => {
+                self.tasks.push(Task::Match(expr));
+                self.tasks.push(Task::Expr(*value));
+            }
+        }
+    }
+
+    /// Returns whether the pattern matches `val`,
+    /// collecting the values of the locals it binds into `bindings`.
+    fn match_pattern(
+        &self,
+        pattern: Id<hir::Pattern>,
+        val: &Val,
+        bindings: &mut Vec<(Id<hir::LocalDef>, Val)>,
+    ) -> bool {
+        match (&self.bodies()[pattern], val) {
+            (hir::Pattern::Missing, _) => unreachable!("type checking rejects missing patterns"),
+            (hir::Pattern::Wildcard, _) => true,
+            (hir::Pattern::Bind(local_def), _) => {
+                bindings.push((*local_def, val.clone()));
+                true
+            }
+            (hir::Pattern::IntLiteral { value, is_negated }, _) => {
+                let value = *value as i128;
+                let n = match val {
+                    Val::S32(n) => i128::from(*n),
+                    Val::S64(n) => i128::from(*n),
+                    Val::U32(n) => i128::from(*n),
+                    _ => unreachable!("type checking guarantees this is an integer"),
+                };
+                n == if *is_negated { -value } else { value }
+            }
+            (hir::Pattern::BoolLiteral(expected), Val::Bool(b)) => b == expected,
+            (hir::Pattern::StringLiteral(expected), Val::String(s)) => **s == **expected,
+            (hir::Pattern::CharLiteral(expected), Val::Char(c)) => c == expected,
+            (hir::Pattern::Tuple(patterns), Val::Tuple(elements)) => patterns
+                .iter()
+                .zip(elements)
+                .all(|(pattern, element)| self.match_pattern(*pattern, element, bindings)),
+            (hir::Pattern::Variant { variant, field }, Val::Variant(val_variant, val_field)) => {
+                if variant != val_variant {
+                    return false;
+                }
+
+                match (field, val_field) {
+                    (Some(field), Some(val_field)) => {
+                        self.match_pattern(*field, val_field, bindings)
+                    }
+                    _ => true,
+                }
+            }
+            _ => unreachable!("type checking guarantees the pattern fits the value"),
+        }
+    }
+
+    /// Drops the remaining work of the current iteration of the loop with `label`
+    /// (or the innermost loop without one) or of the labeled block with `label`,
+    /// returning the task of that loop or block,
+    /// or `None` if the blocks being left have deferred expressions to run first.
+    fn unwind_to_loop(&mut self, label: Option<hir::Name>, resume: Task) -> Option<Task> {
+        loop {
+            match self.tasks.pop() {
+                Some(Task::Loop(state)) if label.is_none() || state.label == label => {
+                    return Some(Task::Loop(state));
+                }
+                Some(Task::LabeledBlock { label: block_label, values_len })
+                    if label == Some(block_label) =>
+                {
+                    return Some(Task::LabeledBlock { label: block_label, values_len });
+                }
+                Some(Task::ExitBlock { deferred_len }) if self.has_deferred(deferred_len) => {
+                    self.tasks.push(resume);
+                    self.run_deferred(deferred_len);
+                    return None;
+                }
+                Some(_) => {}
+                None => unreachable!("lowering only allows jumps inside of loops"),
+            }
+        }
+    }
+
+    /// Drops the remaining work of the current function,
+    /// including the `Task::Return` which would have finished it normally.
+    /// Returns `false` if the blocks being left have deferred expressions to run first.
+    fn unwind_to_return(&mut self, resume: Task) -> bool {
+        loop {
+            match self.tasks.pop() {
+                Some(Task::Return) => return true,
+                Some(Task::ExitBlock { deferred_len }) if self.has_deferred(deferred_len) => {
+                    self.tasks.push(resume);
+                    self.run_deferred(deferred_len);
+                    return false;
+                }
+                Some(_) => {}
+                None => unreachable!("every function body runs inside a call"),
+            }
+        }
+    }
+
+    // A block being unwound out of runs what it deferred before the jump carries on,
+    // so the jump is suspended as `resume` until they’ve finished.
+    fn has_deferred(&self, deferred_len: usize) -> bool {
+        self.frames.last().unwrap().deferred.len() != deferred_len
+    }
+
+    // runs everything deferred since there were `deferred_len` deferred expressions,
+    // starting with the one deferred last
+    fn run_deferred(&mut self, deferred_len: usize) {
+        let deferred = self.frame().deferred.split_off(deferred_len);
+
+        for expr in deferred {
+            self.tasks.push(Task::Discard);
+            self.tasks.push(Task::Expr(expr));
+        }
+    }
+
+    fn call(
+        &mut self,
+        fqn: hir::Fqn,
+        args: Vec<Val>,
+        call_site: Option<CallSite>,
+    ) -> Result<(), EvalError> {
+        if let Some(debugger) = &mut self.evaluator.debugger {
+            debugger.before_call(fqn, &args);
+        }
+
+        let function = self.world_index.get_function(fqn).unwrap();
+        if function.is_extern {
+            return self.call_host_function(fqn, args);
+        }
+
+        let bodies = &self.bodies_map[&fqn.module];
+        let body = bodies.function_body(fqn.function);
+        let arg_count = args.len();
+
+        self.frames.push(Frame {
+            function: fqn,
+            call_site,
+            params: args,
+            locals: ArenaMap::default(),
+            values_len: self.values.len(),
+            deferred: Vec::new(),
+        });
+        self.tasks.push(Task::Return);
+        self.tasks.push(Task::Expr(body));
+
+        // the parameters the call left out take on their defaults,
+        // which are evaluated in the module of the function
+        for idx in (arg_count..function.params.len()).rev() {
+            let default = bodies.param_default(fqn.function, idx as u32).unwrap();
+            self.tasks.push(Task::PushParam);
+            self.tasks.push(Task::Expr(default));
+        }
+
+        Ok(())
+    }
+
+    // replaces the current frame with the one of the call,
+    // which is fine since the current function has nothing left to do
+    // other than returning what the call does
+    fn tail_call(
+        &mut self,
+        fqn: hir::Fqn,
+        args: Vec<Val>,
+        call_site: CallSite,
+    ) -> Result<(), EvalError> {
+        while !matches!(self.tasks.pop(), Some(Task::Return)) {}
+        let frame = self.frames.pop().unwrap();
+        self.values.truncate(frame.values_len);
+
+        self.call(fqn, args, Some(call_site))
+    }
+
+    fn call_host_function(&mut self, fqn: hir::Fqn, args: Vec<Val>) -> Result<(), EvalError> {
+        match self.evaluator.call_host_function(fqn, args)? {
+            Poll::Ready(value) => self.values.push(value),
+            Poll::Pending => self.waiting_on = Some(fqn),
+        }
+
+        Ok(())
+    }
+
+    fn pop_s32(&mut self) -> i32 {
+        s32_arg(&self.values.pop().unwrap())
+    }
+
+    fn arithmetic_error(&mut self, error: ArithmeticError, expr: Id<hir::Expr>) -> EvalError {
+        let module = self.frame().function.module;
+        error.at(module, self.bodies().range_for_expr(expr))
+    }
+
+    fn debug_statement(&mut self, statement: Id<hir::Statement>) {
+        let Some(debugger) = &mut self.evaluator.debugger else { return };
+
+        let frame = self.frames.last().unwrap();
+        let module = frame.function.module;
+        let range = self.bodies_map[&module].range_for_statement(statement);
+        let location = Location { module, range };
+        let scope = Scope {
+            function: frame.function,
+            params: &frame.params,
+            locals: frame.locals.iter().collect(),
+        };
+
+        let at_breakpoint =
+            self.evaluator.breakpoints.iter().any(|(breakpoint_module, breakpoint)| {
+                *breakpoint_module == module && breakpoint.contains(range.start())
+            });
+
+        if at_breakpoint {
+            debugger.on_breakpoint(location, &scope);
+        } else {
+            debugger.before_statement(location, &scope);
+        }
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn bodies(&self) -> &'a hir_ty::thir::Bodies {
+        &self.bodies_map[&self.frames.last().unwrap().function.module]
+    }
+}
+
+// What both backends share of running a program.
+impl Evaluator {
+    pub(crate) fn failed_with(&mut self, backtrace: Backtrace) {
+        self.backtrace = Some(backtrace);
+    }
+
+    pub(crate) fn trace(&mut self, module: hir::Name, range: TextRange, val: &Val) {
+        if let Some(tracer) = &mut self.tracer {
+            tracer(module, range, val);
+        }
+    }
+
+    pub(crate) fn max_call_depth(&self) -> usize {
+        self.max_call_depth
+    }
+
+    pub(crate) fn burn_fuel(&mut self) -> Result<(), EvalError> {
+        if let Some(fuel) = &mut self.fuel {
+            if *fuel == 0 {
+                return Err(EvalError::FuelExhausted);
+            }
+            *fuel -= 1;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn allocate(&mut self, bytes: usize) -> Result<(), EvalError> {
+        if let Some(heap_limit) = &mut self.heap_limit {
+            let bytes = bytes as u64;
+            if bytes > *heap_limit {
+                *heap_limit = 0;
+                return Err(EvalError::HeapExhausted);
+            }
+            *heap_limit -= bytes;
+        }
+
+        Ok(())
+    }
+
+    // the elements are already accounted for, having been created beforehand
+    pub(crate) fn array(&mut self, elements: Vec<Val>) -> Result<Val, EvalError> {
+        self.allocate(elements.len() * mem::size_of::<Val>())?;
+        Ok(Val::Array(elements))
+    }
+
+    pub(crate) fn cast(&mut self, val: Val, ty: hir::Ty) -> Result<Val, EvalError> {
+        // a string cast to a string stays the same string
+        let allocates = ty == hir::Ty::String && !matches!(val, Val::String(_));
+        let val = cast(val, ty);
+        if allocates {
+            self.allocate(heap_size(&val))?;
+        }
+        Ok(val)
+    }
+
+    pub(crate) fn unary(
+        &self,
+        operator: hir::UnaryOperator,
+        operand: Val,
+    ) -> Result<Val, ArithmeticError> {
+        let is_wrapping = self.overflow_mode == OverflowMode::Wrapping;
+        let result = match (operator, operand) {
+            (hir::UnaryOperator::Neg, Val::S32(n)) if is_wrapping => {
+                Some(Val::S32(n.wrapping_neg()))
+            }
+            (hir::UnaryOperator::Neg, Val::S64(n)) if is_wrapping => {
+                Some(Val::S64(n.wrapping_neg()))
+            }
+            (hir::UnaryOperator::Neg, Val::S32(n)) => n.checked_neg().map(Val::S32),
+            (hir::UnaryOperator::Neg, Val::S64(n)) => n.checked_neg().map(Val::S64),
+            (hir::UnaryOperator::Not, Val::Bool(b)) => Some(Val::Bool(!b)),
+            _ => unreachable!("type checking guarantees the operand fits the operator"),
+        };
+
+        result.ok_or(ArithmeticError::Overflow)
+    }
+
+    pub(crate) fn arithmetic(
+        &self,
+        operator: hir::BinaryOperator,
+        lhs: Val,
+        rhs: Val,
+    ) -> Result<Val, ArithmeticError> {
+        if matches!(operator, hir::BinaryOperator::Div)
+            && matches!(rhs, Val::S32(0) | Val::S64(0) | Val::U32(0))
+        {
+            return Err(ArithmeticError::DivisionByZero);
+        }
+
+        // division by zero was ruled out above,
+        // so checked division only fails when it overflows
+        macro_rules! arithmetic {
+            ($lhs:ident, $rhs:ident) => {
+                match (self.overflow_mode, operator) {
+                    (OverflowMode::Checked, hir::BinaryOperator::Add) => $lhs.checked_add($rhs),
+                    (OverflowMode::Checked, hir::BinaryOperator::Sub) => $lhs.checked_sub($rhs),
+                    (OverflowMode::Checked, hir::BinaryOperator::Mul) => $lhs.checked_mul($rhs),
+                    (OverflowMode::Checked, hir::BinaryOperator::Div) => $lhs.checked_div($rhs),
+                    (OverflowMode::Wrapping, hir::BinaryOperator::Add) => {
+                        Some($lhs.wrapping_add($rhs))
+                    }
+                    (OverflowMode::Wrapping, hir::BinaryOperator::Sub) => {
+                        Some($lhs.wrapping_sub($rhs))
+                    }
+                    (OverflowMode::Wrapping, hir::BinaryOperator::Mul) => {
+                        Some($lhs.wrapping_mul($rhs))
+                    }
+                    (OverflowMode::Wrapping, hir::BinaryOperator::Div) => {
+                        Some($lhs.wrapping_div($rhs))
+                    }
+                    _ => unreachable!(),
+                }
+            };
+        }
+
+        let result = match (lhs, rhs) {
+            (Val::S32(lhs), Val::S32(rhs)) => arithmetic!(lhs, rhs).map(Val::S32),
+            (Val::S64(lhs), Val::S64(rhs)) => arithmetic!(lhs, rhs).map(Val::S64),
+            (Val::U32(lhs), Val::U32(rhs)) => arithmetic!(lhs, rhs).map(Val::U32),
+            _ => unreachable!("type checking guarantees both sides have the same integer type"),
+        };
+
+        result.ok_or(ArithmeticError::Overflow)
+    }
+
+    pub(crate) fn call_host_function(
+        &mut self,
+        fqn: hir::Fqn,
+        args: Vec<Val>,
+    ) -> Result<Poll<Val>, EvalError> {
+        match self.host_functions.get_mut(&fqn) {
+            Some(f) => Ok(f(args)),
+            None => Err(EvalError::MissingHostFunction(fqn)),
+        }
+    }
+
+    pub(crate) fn call_builtin(
+        &mut self,
+        builtin: hir::Builtin,
+        args: Vec<Val>,
+    ) -> Result<Val, EvalError> {
+        // builtins never give back anything they were passed,
+        // so everything in the result is new
+        let val = self.builtin(builtin, args)?;
+        self.allocate(heap_size(&val))?;
+        Ok(val)
+    }
+
+    fn builtin(&mut self, builtin: hir::Builtin, args: Vec<Val>) -> Result<Val, EvalError> {
+        match builtin {
+            hir::Builtin::RandSeed | hir::Builtin::RandInt if !self.permissions.random => {
+                Err(EvalError::NotPermitted(builtin))
+            }
+
+            hir::Builtin::Print | hir::Builtin::Println | hir::Builtin::ReadLine
+                if !self.permissions.io =>
+            {
+                Err(EvalError::NotPermitted(builtin))
+            }
+
+            hir::Builtin::ReadFile | hir::Builtin::WriteFile if self.file_system.is_none() => {
+                Err(EvalError::NotPermitted(builtin))
+            }
+
+            hir::Builtin::ClockMs => match &mut self.clock {
+                Some(clock) => Ok(Val::S64(clock.now_ms())),
+                None => Err(EvalError::NotPermitted(builtin)),
+            },
+
+            hir::Builtin::RandSeed => {
+                let seed = s32_arg(&args[0]);
+                self.seed_rng(seed as u32 as u64);
+                Ok(Val::Nil)
+            }
+
+            hir::Builtin::RandInt => {
+                let lo = s32_arg(&args[0]);
+                let hi = s32_arg(&args[1]);
+
+                if lo > hi {
+                    return Err(EvalError::EmptyRandRange { lo, hi });
+                }
+
+                Ok(Val::S32(self.rng.range_inclusive(lo, hi)))
+            }
+
+            hir::Builtin::ToString => Ok(Val::String(s32_arg(&args[0]).to_string().into())),
+
+            hir::Builtin::ParseInt => {
+                let parsed = match string_arg(&args[0]).parse() {
+                    Ok(n) => Val::Variant(hir::Variant::Some, Some(Box::new(Val::S32(n)))),
+                    Err(_) => Val::Variant(hir::Variant::None, None),
+                };
+                Ok(parsed)
+            }
+
+            hir::Builtin::Len => Ok(Val::S32(string_arg(&args[0]).chars().count() as i32)),
+
+            hir::Builtin::ArrayLen => match &args[0] {
+                Val::Array(elements) => Ok(Val::S32(elements.len() as i32)),
+                _ => unreachable!("type checking guarantees this is an array"),
+            },
+
+            hir::Builtin::Substring => {
+                let s = string_arg(&args[0]);
+                let start = s32_arg(&args[1]);
+                let end = s32_arg(&args[2]);
+
+                let start_idx = char_boundary(s, start)?;
+                let end_idx = char_boundary(s, end)?;
+                if start > end {
+                    return Err(EvalError::InvalidSubstring { start, end });
+                }
+
+                Ok(Val::String(s[start_idx..end_idx].into()))
+            }
+
+            hir::Builtin::Contains => {
+                Ok(Val::Bool(string_arg(&args[0]).contains(string_arg(&args[1]))))
+            }
+
+            hir::Builtin::CharAt => {
+                let s = string_arg(&args[0]);
+                let index = s32_arg(&args[1]);
+
+                let c = usize::try_from(index).ok().and_then(|index| s.chars().nth(index));
+                match c {
+                    Some(c) => Ok(Val::Char(c)),
+                    None => {
+                        Err(EvalError::StringIndexOutOfBounds { index, len: s.chars().count() })
+                    }
+                }
+            }
+
+            hir::Builtin::Args => {
+                let args = self.host_config.args.iter().map(|arg| Val::String(arg.as_str().into()));
+                Ok(Val::Array(args.collect()))
+            }
+
+            hir::Builtin::Env => match self.host_config.env.get(string_arg(&args[0])) {
+                Some(value) => Ok(Val::Variant(
+                    hir::Variant::Some,
+                    Some(Box::new(Val::String(value.as_str().into()))),
+                )),
+                None => Ok(Val::Variant(hir::Variant::None, None)),
+            },
+
+            hir::Builtin::ReadFile => {
+                let file_system = self.file_system.as_mut().unwrap();
+                match file_system.read_file(string_arg(&args[0])) {
+                    Ok(contents) => Ok(Val::String(contents.into())),
+                    Err(error) => Err(EvalError::Io(error.to_string())),
+                }
+            }
+
+            hir::Builtin::WriteFile => {
+                let file_system = self.file_system.as_mut().unwrap();
+                match file_system.write_file(string_arg(&args[0]), string_arg(&args[1])) {
+                    Ok(()) => Ok(Val::Nil),
+                    Err(error) => Err(EvalError::Io(error.to_string())),
+                }
+            }
+
+            hir::Builtin::Assert => unreachable!("assertions are evaluated separately"),
+
+            hir::Builtin::Print => {
+                // without a newline the output would otherwise sit in the buffer
+                let written = write!(self.output, "{}", string_arg(&args[0]))
+                    .and_then(|()| self.output.flush());
+                match written {
+                    Ok(()) => Ok(Val::Nil),
+                    Err(error) => Err(EvalError::Io(error.to_string())),
+                }
+            }
+
+            hir::Builtin::Println => match writeln!(self.output, "{}", string_arg(&args[0])) {
+                Ok(()) => Ok(Val::Nil),
+                Err(error) => Err(EvalError::Io(error.to_string())),
+            },
+
+            hir::Builtin::ReadLine => {
+                let mut line = String::new();
+                // stdin is only locked while reading,
+                // so that the host can read from it between evaluations
+                let read = match &mut self.input {
+                    Some(input) => input.read_line(&mut line),
+                    None => io::stdin().lock().read_line(&mut line),
+                };
+
+                let line = match read {
+                    Ok(0) => return Ok(Val::Variant(hir::Variant::None, None)),
+                    Ok(_) => line.trim_end_matches(['\r', '\n']).to_string(),
+                    Err(error) => return Err(EvalError::Io(error.to_string())),
+                };
+                Ok(Val::Variant(hir::Variant::Some, Some(Box::new(Val::String(line.into())))))
+            }
+        }
+    }
+}
+
+/// Arithmetic that failed,
+/// which becomes an [`EvalError`] once it’s known where it happened.
+pub(crate) enum ArithmeticError {
+    DivisionByZero,
+    Overflow,
+}
+
+impl ArithmeticError {
+    pub(crate) fn at(self, module: hir::Name, range: TextRange) -> EvalError {
+        match self {
+            Self::DivisionByZero => EvalError::DivisionByZero { module, range },
+            Self::Overflow => EvalError::Overflow { module, range },
+        }
+    }
+}
+
+// how many bytes of strings and arrays `val` is made up of
+fn heap_size(val: &Val) -> usize {
+    match val {
+        Val::String(s) => s.len(),
+        Val::Array(elements) => {
+            elements.len() * mem::size_of::<Val>() + elements.iter().map(heap_size).sum::<usize>()
+        }
+        Val::Tuple(elements) => elements.iter().map(heap_size).sum(),
+        Val::Variant(_, Some(field)) => heap_size(field),
+        Val::S32(_)
+        | Val::S64(_)
+        | Val::U32(_)
+        | Val::Bool(_)
+        | Val::Char(_)
+        | Val::Nil
+        | Val::Variant(_, None) => 0,
+    }
+}
+
+// type checking has made sure the literal fits its type
+fn int_literal(n: i128, ty: &hir::Ty) -> Val {
+    match ty {
+        hir::Ty::S64 => Val::S64(n as i64),
+        hir::Ty::U32 => Val::U32(n as u32),
+        _ => Val::S32(n as i32),
+    }
+}
+
+// integers are truncated or sign-extended like in Rust,
+// and chars become their code point
+pub(crate) fn cast(val: Val, ty: hir::Ty) -> Val {
+    // a value can only be cast to a type parameter if it already has that type
+    if let hir::Ty::Param { .. } = ty {
+        return val;
+    }
+
+    if ty == hir::Ty::String {
+        let s = match val {
+            Val::S32(n) => n.to_string(),
+            Val::S64(n) => n.to_string(),
+            Val::U32(n) => n.to_string(),
+            Val::Bool(b) => b.to_string(),
+            Val::Char(c) => c.to_string(),
+            Val::String(s) => return Val::String(s),
+            Val::Nil | Val::Tuple(_) | Val::Array(_) | Val::Variant(..) => {
+                unreachable!("type checking guarantees the cast is valid")
+            }
+        };
+
+        return Val::String(s.into());
+    }
+
+    let n = match val {
+        Val::S32(n) => i64::from(n),
+        Val::S64(n) => n,
+        Val::U32(n) => i64::from(n),
+        Val::Bool(b) => i64::from(b),
+        Val::Char(c) => i64::from(u32::from(c)),
+        Val::String(_) | Val::Nil | Val::Tuple(_) | Val::Array(_) | Val::Variant(..) => {
+            unreachable!("type checking guarantees the cast is valid")
+        }
+    };
+
+    match ty {
+        hir::Ty::S32 => Val::S32(n as i32),
+        hir::Ty::S64 => Val::S64(n),
+        hir::Ty::U32 => Val::U32(n as u32),
+        hir::Ty::Bool => Val::Bool(n != 0),
+        hir::Ty::Char => Val::Char(char::from_u32(n as u32).unwrap()),
+        _ => unreachable!("type checking guarantees the cast is valid"),
+    }
+}
+
+pub(crate) fn compare_with(operator: hir::BinaryOperator, lhs: &Val, rhs: &Val) -> bool {
+    let ordering = compare(lhs, rhs);
+
+    match operator {
+        hir::BinaryOperator::Eq => ordering.is_eq(),
+        hir::BinaryOperator::NotEq => ordering.is_ne(),
+        hir::BinaryOperator::Lt => ordering.is_lt(),
+        hir::BinaryOperator::LtEq => ordering.is_le(),
+        hir::BinaryOperator::Gt => ordering.is_gt(),
+        hir::BinaryOperator::GtEq => ordering.is_ge(),
+        _ => unreachable!(),
+    }
+}
+
+fn compare(lhs: &Val, rhs: &Val) -> Ordering {
+    match (lhs, rhs) {
+        (Val::S32(lhs), Val::S32(rhs)) => lhs.cmp(rhs),
+        (Val::S64(lhs), Val::S64(rhs)) => lhs.cmp(rhs),
+        (Val::U32(lhs), Val::U32(rhs)) => lhs.cmp(rhs),
+        (Val::Bool(lhs), Val::Bool(rhs)) => lhs.cmp(rhs),
+        // byte-wise, which for UTF-8 is the same as comparing code points
+        (Val::String(lhs), Val::String(rhs)) => lhs.cmp(rhs),
+        (Val::Char(lhs), Val::Char(rhs)) => lhs.cmp(rhs),
+        _ => unreachable!("type checking guarantees both sides have the same comparable type"),
+    }
+}
+
+pub(crate) fn index_array(array: Val, index: Val) -> Result<Val, EvalError> {
+    let Val::S32(index) = index else {
+        unreachable!("type checking guarantees the index is an s32")
+    };
+    let Val::Array(mut elements) = array else {
+        unreachable!("type checking guarantees this is an array")
+    };
+
+    let len = elements.len();
+    if index < 0 || index as usize >= len {
+        return Err(EvalError::IndexOutOfBounds { index, len });
+    }
+
+    Ok(elements.swap_remove(index as usize))
+}
+
+pub(crate) fn s32_arg(val: &Val) -> i32 {
+    match val {
+        Val::S32(n) => *n,
+        _ => unreachable!("type checking guarantees this is an s32"),
+    }
+}
+
+// the byte offset of the `index`th char of `s`,
+// where the end of `s` counts as a char boundary too
+fn char_boundary(s: &str, index: i32) -> Result<usize, EvalError> {
+    let len = s.chars().count();
+    let out_of_bounds = EvalError::StringIndexOutOfBounds { index, len };
+
+    let Ok(index) = usize::try_from(index) else {
+        return Err(out_of_bounds);
+    };
+
+    s.char_indices()
+        .map(|(idx, _)| idx)
+        .chain(std::iter::once(s.len()))
+        .nth(index)
+        .ok_or(out_of_bounds)
+}
+
+fn string_arg(val: &Val) -> &str {
+    match val {
+        Val::String(s) => s,
+        _ => unreachable!("type checking guarantees this is a string"),
+    }
+}
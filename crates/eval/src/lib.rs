@@ -1,78 +1,287 @@
+#[cfg(feature = "wasm")]
 mod codegen;
-use self::codegen::Ctx;
+mod evaluator;
+mod rand;
+mod vm;
 
+#[cfg(feature = "wasm")]
+pub use self::codegen::CompileError;
+pub use self::evaluator::{
+    Backend, Clock, Debugger, EvalPaused, Evaluator, FileSystem, HostConfig, Location,
+    OverflowMode, PendingHostCall, Permissions, Progress, Scope, Snapshot, StdClock, StdFileSystem,
+};
+
+use interner::Interner;
 use rustc_hash::FxHashMap;
+use std::fmt;
+use std::rc::Rc;
+use text_size::TextRange;
 
+/// Evaluates a function in a fresh [`Evaluator`] with all permissions.
 pub fn eval(
     fqn: hir::Fqn,
-    bodies_map: FxHashMap<hir::Name, hir::Bodies>,
-    tys_map: FxHashMap<hir::Name, hir_ty::InferenceResult>,
+    bodies_map: &FxHashMap<hir::Name, hir_ty::thir::Bodies>,
     world_index: &hir::WorldIndex,
-) -> Val {
-    let entry_point_return_ty = {
-        let function = world_index.get_function(fqn).unwrap();
-        function.return_ty
-    };
-
-    let ctx = Ctx::new(bodies_map, tys_map, world_index, fqn);
-
-    let mut store = wasmtime::Store::<()>::default();
-    let module = wasmtime::Module::new(store.engine(), ctx.finish()).unwrap();
-    let instance = wasmtime::Instance::new(&mut store, &module, &[]).unwrap();
-
-    let main = instance.get_func(&mut store, "main").unwrap();
-
-    let num_results = if entry_point_return_ty == hir::Ty::Unit { 0 } else { 1 };
-    let mut results = vec![wasmtime::Val::I32(0); num_results];
-    main.call(&mut store, &[], &mut results).unwrap();
-
-    match results.get(0).cloned() {
-        Some(wasmtime::Val::I32(n)) => match entry_point_return_ty {
-            hir::Ty::S32 => Val::S32(n),
-            hir::Ty::String => {
-                let mut len = [0; std::mem::size_of::<i32>()];
-                instance
-                    .get_memory(&mut store, "memory")
-                    .unwrap()
-                    .read(&mut store, n as usize, &mut len)
-                    .unwrap();
-
-                let len = i32::from_le_bytes(len);
-                let len = len.try_into().unwrap();
-                let mut string = vec![0; len];
-                instance
-                    .get_memory(&mut store, "memory")
-                    .unwrap()
-                    .read(&mut store, n as usize + std::mem::size_of::<i32>(), &mut string)
-                    .unwrap();
-
-                Val::String(String::from_utf8(string).unwrap())
-            }
-            _ => unreachable!(),
-        },
-
-        None => {
-            assert_eq!(entry_point_return_ty, hir::Ty::Unit);
-            Val::Nil
-        }
-        _ => unreachable!(),
-    }
+) -> Result<Val, EvalError> {
+    Evaluator::default().eval(fqn, bodies_map, world_index)
 }
 
+/// Compiles a program to a WebAssembly module.
+///
+/// Every extern function in `world_index` becomes an import
+/// named after its module and function.
+#[cfg(feature = "wasm")]
 pub fn compile(
     fqn: hir::Fqn,
-    bodies_map: FxHashMap<hir::Name, hir::Bodies>,
-    tys_map: FxHashMap<hir::Name, hir_ty::InferenceResult>,
+    bodies_map: &FxHashMap<hir::Name, hir_ty::thir::Bodies>,
     world_index: &hir::WorldIndex,
-) -> Vec<u8> {
-    Ctx::new(bodies_map, tys_map, world_index, fqn).finish()
+    interner: &Interner,
+) -> Result<Vec<u8>, CompileError> {
+    codegen::Ctx::new(bodies_map, world_index, interner, fqn)?.finish()
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Val {
     Nil,
     S32(i32),
-    String(String),
+    S64(i64),
+    U32(u32),
+    Bool(bool),
+    /// Shared rather than copied whenever the value is,
+    /// since strings are never changed in place.
+    String(Rc<str>),
+    Char(char),
+    Tuple(Vec<Val>),
+    Array(Vec<Val>),
+    Variant(hir::Variant, Option<Box<Val>>),
+}
+
+impl Val {
+    /// Renders the value the way it would be written in source code,
+    /// eliding whatever goes beyond `limits` with `…`.
+    pub fn display(&self, limits: PrintLimits) -> String {
+        self.display_at_depth(limits, 0)
+    }
+
+    fn display_at_depth(&self, limits: PrintLimits, depth: usize) -> String {
+        match self {
+            Self::Nil => "{}".to_string(),
+            Self::S32(n) => n.to_string(),
+            Self::S64(n) => n.to_string(),
+            Self::U32(n) => n.to_string(),
+            Self::Bool(b) => b.to_string(),
+            Self::String(s) => {
+                let mut displayed = String::from('"');
+
+                for (idx, c) in s.chars().enumerate() {
+                    if idx == limits.max_string_len {
+                        displayed.push('…');
+                        break;
+                    }
+
+                    push_escaped(&mut displayed, c, '"');
+                }
+
+                displayed.push('"');
+                displayed
+            }
+            Self::Char(c) => {
+                let mut displayed = String::from('\'');
+                push_escaped(&mut displayed, *c, '\'');
+                displayed.push('\'');
+                displayed
+            }
+            Self::Tuple(elements) => display_elements(elements, ('(', ')'), limits, depth),
+            Self::Array(elements) => display_elements(elements, ('[', ']'), limits, depth),
+            Self::Variant(variant, None) => variant.name().to_string(),
+            Self::Variant(variant, Some(field)) => {
+                format!("{} {}", variant.name(), field.display_at_depth(limits, depth))
+            }
+        }
+    }
+}
+
+/// Uses the default [`PrintLimits`].
+impl fmt::Display for Val {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.display(PrintLimits::default()))
+    }
+}
+
+fn display_elements(
+    elements: &[Val],
+    (open, close): (char, char),
+    limits: PrintLimits,
+    depth: usize,
+) -> String {
+    let mut displayed = String::from(open);
+
+    if depth == limits.max_depth {
+        displayed.push('…');
+        displayed.push(close);
+        return displayed;
+    }
+
+    for (idx, element) in elements.iter().enumerate() {
+        if idx != 0 {
+            displayed.push_str(", ");
+        }
+
+        if idx == limits.max_elements {
+            displayed.push('…');
+            break;
+        }
+
+        displayed.push_str(&element.display_at_depth(limits, depth + 1));
+    }
+
+    displayed.push(close);
+    displayed
+}
+
+fn push_escaped(s: &mut String, c: char, quote: char) {
+    match c {
+        '\\' => s.push_str("\\\\"),
+        '\n' => s.push_str("\\n"),
+        '\r' => s.push_str("\\r"),
+        '\t' => s.push_str("\\t"),
+        _ if c == quote => {
+            s.push('\\');
+            s.push(c);
+        }
+        _ => s.push(c),
+    }
+}
+
+/// How much of a value [`Val::display`] prints
+/// before eliding the rest,
+/// so that showing a huge value doesn’t flood the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintLimits {
+    /// The number of characters of a string to print.
+    pub max_string_len: usize,
+    /// The number of elements of a tuple or array to print.
+    pub max_elements: usize,
+    /// How deeply nested a tuple or array can be and still have its elements printed.
+    pub max_depth: usize,
+}
+
+impl PrintLimits {
+    pub const UNLIMITED: Self =
+        Self { max_string_len: usize::MAX, max_elements: usize::MAX, max_depth: usize::MAX };
+}
+
+impl Default for PrintLimits {
+    fn default() -> Self {
+        Self { max_string_len: 1000, max_elements: 100, max_depth: 10 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalError {
+    MissingHostFunction(hir::Fqn),
+    HostFunctionPending(hir::Fqn),
+    NotPermitted(hir::Builtin),
+    EmptyRandRange {
+        lo: i32,
+        hi: i32,
+    },
+    IndexOutOfBounds {
+        index: i32,
+        len: usize,
+    },
+    /// A char index past the end of a string.
+    StringIndexOutOfBounds {
+        index: i32,
+        len: usize,
+    },
+    /// A `substring` which starts after it ends.
+    InvalidSubstring {
+        start: i32,
+        end: i32,
+    },
+    Io(String),
+    /// An `assert` whose condition was false,
+    /// with `range` pointing at the call in `module`.
+    AssertionFailed {
+        message: String,
+        module: hir::Name,
+        range: TextRange,
+    },
+    /// A division whose divisor was zero,
+    /// with `range` pointing at the division in `module`.
+    DivisionByZero {
+        module: hir::Name,
+        range: TextRange,
+    },
+    /// Arithmetic whose result doesn’t fit in its type
+    /// while overflow is checked,
+    /// with `range` pointing at the expression in `module`.
+    Overflow {
+        module: hir::Name,
+        range: TextRange,
+    },
+    /// See [`Evaluator::set_fuel`].
+    FuelExhausted,
+    /// See [`Evaluator::set_heap_limit`].
+    HeapExhausted,
+    /// A call past [`Evaluator::set_max_call_depth`],
+    /// with `range` pointing at the call in `module`.
+    StackOverflow {
+        module: hir::Name,
+        range: TextRange,
+    },
+}
+
+impl EvalError {
+    pub fn display(&self, interner: &Interner) -> String {
+        match self {
+            Self::MissingHostFunction(fqn) => format!(
+                "cannot call `{}.{}`: no host function was registered for it",
+                interner.lookup(fqn.module.0),
+                interner.lookup(fqn.function.0)
+            ),
+            Self::HostFunctionPending(fqn) => format!(
+                "`{}.{}` did not return right away: only resumable evaluations can wait for it",
+                interner.lookup(fqn.module.0),
+                interner.lookup(fqn.function.0)
+            ),
+            Self::NotPermitted(builtin) => {
+                format!("`{}` is not permitted in this evaluator", builtin.name())
+            }
+            Self::EmptyRandRange { lo, hi } => {
+                format!("cannot pick a random number between {lo} and {hi}: the range is empty")
+            }
+            Self::IndexOutOfBounds { index, len } => {
+                format!("index {index} is out of bounds for an array of length {len}")
+            }
+            Self::StringIndexOutOfBounds { index, len } => {
+                format!("index {index} is out of bounds for a string of length {len}")
+            }
+            Self::InvalidSubstring { start, end } => {
+                format!("cannot take the substring from {start} to {end}: it starts after it ends")
+            }
+            Self::Io(error) => format!("input/output error: {error}"),
+            Self::AssertionFailed { message, .. } => format!("assertion failed: {message}"),
+            Self::DivisionByZero { .. } => "attempted to divide by zero".to_string(),
+            Self::Overflow { .. } => "arithmetic overflowed".to_string(),
+            Self::FuelExhausted => "evaluation ran out of fuel".to_string(),
+            Self::HeapExhausted => "evaluation ran out of memory".to_string(),
+            Self::StackOverflow { .. } => "too many nested calls".to_string(),
+        }
+    }
+
+    /// The module and range of the expression which failed,
+    /// if the error came from a particular one.
+    pub fn location(&self) -> Option<(hir::Name, TextRange)> {
+        match self {
+            Self::AssertionFailed { module, range, .. }
+            | Self::DivisionByZero { module, range }
+            | Self::Overflow { module, range }
+            | Self::StackOverflow { module, range } => Some((*module, *range)),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -81,52 +290,130 @@ mod tests {
     use ast::AstNode;
     use expect_test::{expect, Expect};
     use interner::Interner;
+    use line_index::LineIndex;
+    use std::cell::{Cell, RefCell};
+    use std::io;
+    use std::rc::Rc;
+    use std::task::Poll;
 
+    // runs the program with both backends,
+    // using the tree-walker as the reference for the bytecode
     fn check<const N: usize>(modules: [(&str, &str); N], expect: Expect) {
-        let mut analysis_results = FxHashMap::default();
-        let mut interner = Interner::default();
-        let mut world_index = hir::WorldIndex::default();
+        let program = Program::new(modules);
+
+        let mut tree_walker = Evaluator::default();
+        tree_walker.set_backend(Backend::TreeWalker);
+        let expected = program.eval(tree_walker);
 
-        for (module, text) in &modules {
-            let tokens = lexer::lex(text);
-            let parse = parser::parse_source_file(&tokens, text);
-            assert!(parse.errors().is_empty());
+        let actual = program.eval(Evaluator::default());
+        assert_eq!(actual, expected, "the bytecode disagrees with the tree-walker");
+        expect.assert_eq(&actual);
+    }
 
-            let tree = parse.into_syntax_tree();
-            let root = ast::Root::cast(tree.root(), &tree).unwrap();
-            assert!(ast::validation::validate(root, &tree).is_empty());
+    fn check_with_evaluator<const N: usize>(
+        evaluator: Evaluator,
+        modules: [(&str, &str); N],
+        expect: Expect,
+    ) {
+        expect.assert_eq(&Program::new(modules).eval(evaluator));
+    }
 
-            let (index, d) = hir::index(root, &tree, &world_index, &mut interner);
-            assert!(d.is_empty());
+    struct Program {
+        main: hir::Fqn,
+        bodies_map: FxHashMap<hir::Name, hir_ty::thir::Bodies>,
+        world_index: hir::WorldIndex,
+        interner: Interner,
+    }
 
-            world_index.add_module(hir::Name(interner.intern(module)), index.clone());
-            analysis_results.insert(module, (tree, root, index));
+    impl Program {
+        fn new<const N: usize>(modules: [(&str, &str); N]) -> Self {
+            Self::with_setup(modules, |_, _| {})
         }
 
-        let mut bodies_map = FxHashMap::default();
-        let mut tys_map = FxHashMap::default();
+        // `setup` runs before any of the modules are analyzed
+        fn with_setup<const N: usize>(
+            modules: [(&str, &str); N],
+            setup: impl FnOnce(&mut hir::WorldIndex, &mut Interner),
+        ) -> Self {
+            let mut analysis_results = FxHashMap::default();
+            let mut interner = Interner::default();
+            let mut world_index = hir::WorldIndex::default();
+            setup(&mut world_index, &mut interner);
+
+            let std = (stdlib::MODULE_NAME, stdlib::SOURCE);
+
+            for (module, text) in modules.iter().chain([&std]) {
+                let tokens = lexer::lex(text);
+                let parse = parser::parse_source_file(&tokens, text);
+                assert!(parse.errors().is_empty());
+
+                let tree = parse.into_syntax_tree();
+                let root = ast::Root::cast(tree.root(), &tree).unwrap();
+                assert!(ast::validation::validate(root, &tree).is_empty());
+
+                let (index, d) = hir::index(root, &tree, &world_index, &mut interner);
+                assert!(d.is_empty());
+
+                world_index.add_module(hir::Name(interner.intern(module)), index.clone());
+                analysis_results.insert(*module, (tree, root, index));
+            }
+
+            let mut bodies_map = FxHashMap::default();
+
+            for (module, (tree, root, index)) in analysis_results {
+                let (bodies, _) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+
+                let (inference, d) = hir_ty::infer_all(&bodies, &index, &world_index);
+                // some tests check that code after a jump is skipped,
+                // which only gets a warning
+                assert!(d.iter().all(|d| d.kind == hir_ty::TyDiagnosticKind::UnreachableCode));
+
+                let module = hir::Name(interner.intern(module));
+                bodies_map.insert(module, hir_ty::thir::lower(bodies, inference));
+            }
+
+            let main = hir::Name(interner.intern("main"));
+            let main = hir::Fqn { module: main, function: main };
+
+            Self { main, bodies_map, world_index, interner }
+        }
 
-        for (module, (tree, root, index)) in analysis_results {
-            let (bodies, _) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+        fn eval(&self, mut evaluator: Evaluator) -> String {
+            let output = Output::default();
+            evaluator.set_output(output.clone());
 
-            let (inference, d) = hir_ty::infer_all(&bodies, &index, &world_index);
-            assert!(d.is_empty());
+            let result = match evaluator.eval(self.main, &self.bodies_map, &self.world_index) {
+                Ok(val) => format!("{val:?}"),
+                Err(error) => format!("error: {}", error.display(&self.interner)),
+            };
 
-            bodies_map.insert(hir::Name(interner.intern(module)), bodies);
-            tys_map.insert(hir::Name(interner.intern(module)), inference);
+            let output = output.0.take();
+            if output.is_empty() {
+                return result;
+            }
+            format!("{result} after printing {:?}", String::from_utf8(output).unwrap())
         }
 
-        let result = eval(
+        fn fqn(&mut self, module: &str, function: &str) -> hir::Fqn {
             hir::Fqn {
-                module: hir::Name(interner.intern("main")),
-                function: hir::Name(interner.intern("main")),
-            },
-            bodies_map,
-            tys_map,
-            &world_index,
-        );
+                module: hir::Name(self.interner.intern(module)),
+                function: hir::Name(self.interner.intern(function)),
+            }
+        }
+    }
 
-        expect.assert_eq(&format!("{:?}", result));
+    // what the program printed, shared with the evaluator
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<u8>>>);
+
+    impl io::Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
     }
 
     #[test]
@@ -142,6 +429,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unit_literal() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc nothing: unit -> {};
+
+                    fnc main: unit -> {
+                        let nil = unit;
+                        nothing;
+                        nil
+                    };
+                "#,
+            )],
+            expect![["Nil"]],
+        );
+    }
+
     #[test]
     fn return_constant_s32() {
         check(
@@ -169,136 +475,2701 @@ mod tests {
     }
 
     #[test]
-    fn local() {
+    fn comparisons() {
         check(
             [(
                 "main",
                 r#"
-                    fnc main: s32 -> {
-                        let foo = 5;
-                        foo
+                    fnc main: bool -> {1 + 1 == 2} == {3 * 2 < 6};
+                "#,
+            )],
+            expect![["Bool(false)"]],
+        );
+    }
+
+    #[test]
+    fn compare_bools() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: bool -> false < true;
+                "#,
+            )],
+            expect![["Bool(true)"]],
+        );
+    }
+
+    #[test]
+    fn return_char() {
+        check([("main", "fnc main: char -> '\\n';")], expect![[r#"Char('\n')"#]]);
+    }
+
+    #[test]
+    fn compare_chars() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc is_digit(c: char): bool -> c >= '0' && c <= '9';
+                    fnc main: bool -> {is_digit '7'} && !{is_digit 'x'} && {'a' != 'b'};
+                "#,
+            )],
+            expect![["Bool(true)"]],
+        );
+    }
+
+    #[test]
+    fn prefixed_int_literals() {
+        check([("main", "fnc main: s32 -> 0xff - 0o10 - 0b11;")], expect![["S32(244)"]]);
+    }
+
+    #[test]
+    fn s64_arithmetic() {
+        let mut evaluator = Evaluator::default();
+        evaluator.set_overflow_mode(OverflowMode::Wrapping);
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc square(n: s64): s64 -> n * n;
+                    fnc main: s64 -> square 3000000000 - -9223372036854775808;
+                "#,
+            )],
+            expect![["S64(9000000000000000000)"]],
+        );
+    }
+
+    #[test]
+    fn u32_arithmetic() {
+        let mut evaluator = Evaluator::default();
+        evaluator.set_overflow_mode(OverflowMode::Wrapping);
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc half(n: u32): u32 -> n / 2;
+                    fnc main: bool -> {half 4000000000} > 1000000000 && {half 1} - 1 == 4294967295;
+                "#,
+            )],
+            expect![["Bool(true)"]],
+        );
+    }
+
+    #[test]
+    fn local_def_with_ty() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s64 -> {
+                        let seconds_per_year: s64 = 60 * 60 * 24 * 365;
+                        seconds_per_year * 1000
                     };
                 "#,
             )],
-            expect![["S32(5)"]],
+            expect![["S64(31536000000)"]],
         );
     }
 
     #[test]
-    fn nested_blocks() {
+    fn wildcard_local_def() {
         check(
             [(
                 "main",
                 r#"
                     fnc main: s32 -> {
-                        let d = {
-                            let c = {
-                                let b = {
-                                    let a = 10;
-                                    a - 5
-                                };
-                                b + 22
-                            };
-                            c / 2
+                        let mut calls = 0;
+                        let _ = loop {
+                            calls = calls + 1;
+                            break calls;
                         };
-                        d * 5
+                        let _ = "ignored";
+                        calls
                     };
                 "#,
             )],
-            expect![["S32(65)"]],
+            expect![["S32(1)"]],
         );
     }
 
     #[test]
-    fn call_with_no_params_or_return_ty() {
+    fn casts() {
         check(
             [(
                 "main",
                 r#"
-                    fnc main: s32 -> {
-                        nil;
-                        1
+                    fnc main: bool -> {-1 as u32 == 4294967295}
+                        && {4294967297 as s64 as s32 == 1}
+                        && {-5 as s64 == -5}
+                        && {'a' as s32 == 97}
+                        && {true as u32 == 1};
+                "#,
+            )],
+            expect![["Bool(true)"]],
+        );
+    }
+
+    #[test]
+    fn casts_to_string() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: bool -> {-5 as string == "-5"}
+                        && {'x' as string == "x"}
+                        && {true as string == "true"};
+                "#,
+            )],
+            expect![["Bool(true)"]],
+        );
+    }
+
+    #[test]
+    fn generic_functions() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc id[T](x: T): T -> x as T;
+                    fnc swap[A, B](pair: (A, B)): (B, A) -> (pair.1, pair.0);
+                    fnc first_or[T](xs: [T], default: T): T -> {
+                        for i in 0..1 {
+                            return xs[i];
+                        };
+                        default
                     };
-                    fnc nil -> {};
+                    fnc main: (string, s32) -> swap ({first_or [id 1], 0}, {id "x"});
                 "#,
             )],
-            expect![["S32(1)"]],
+            expect![[r#"Tuple([String("x"), S32(1)])"#]],
         );
     }
 
     #[test]
-    fn call_with_no_params_but_with_return_ty() {
+    fn ty_aliases() {
         check(
             [(
                 "main",
                 r#"
-                    fnc main: s32 -> n;
-                    fnc n: s32 -> 5;
+                    type Wide = s64;
+                    type Label = string;
+                    fnc widen(n: s32): Wide -> n as Wide;
+                    fnc main: Label -> {{widen 2147483647} + 1} as Label;
                 "#,
             )],
-            expect![["S32(5)"]],
+            expect![[r#"String("2147483648")"#]],
         );
     }
 
     #[test]
-    fn call_with_params_and_return_ty() {
+    fn consts() {
         check(
             [(
                 "main",
                 r#"
-                    fnc main: s32 -> add 10, 20;
-                    fnc add(x: s32, y: s32): s32 -> x + y;
+                    const AREA: s64 = WIDTH * HEIGHT;
+                    const WIDTH: s64 = HEIGHT + 2;
+                    const HEIGHT: s64 = -{1 - 4};
+                    const UNIT = "m";
+                    const SQUARED = '²';
+                    fnc main: (s64, string, char) -> (AREA, UNIT, SQUARED);
                 "#,
             )],
-            expect![["S32(30)"]],
+            expect![[r#"Tuple([S64(15), String("m"), Char('²')])"#]],
         );
     }
 
     #[test]
-    fn create_string() {
+    fn param_default_values() {
+        check(
+            [
+                (
+                    "main",
+                    r#"
+                        use greetings.greet;
+                        fnc main: (string, string, string) -> {
+                            let (greeting, _, _) = greet;
+                            let (_, name, _) = greet "hi";
+                            let (_, _, end) = greet "hi", "there", "?";
+                            (greeting, name, end)
+                        };
+                    "#,
+                ),
+                (
+                    "greetings",
+                    r#"
+                        const NAME = "world";
+                        fnc punctuation: string -> "!";
+                        pub fnc greet(greeting: string = "hello", name: string = NAME, end: string = punctuation): (string, string, string) ->
+                            (greeting, name, end);
+                    "#,
+                ),
+            ],
+            expect![[r#"Tuple([String("hello"), String("world"), String("?")])"#]],
+        );
+    }
+
+    #[test]
+    fn tuples() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc swap(pair: (s32, string)): (string, s32) -> (pair.1, pair.0);
+                    fnc main: (s32, (string, s32), ()) -> {
+                        let pair = swap (1, "one");
+                        (pair.1 + 1, {swap (2, pair.0)}, ())
+                    };
+                "#,
+            )],
+            expect![[r#"Tuple([S32(2), Tuple([String("one"), S32(2)]), Tuple([])])"#]],
+        );
+    }
+
+    #[test]
+    fn destructure_tuple() {
         check(
             [(
                 "main",
                 r#"
+                    fnc div_rem(a: s32, b: s32): (s32, s32) -> (a / b, a - a / b * b);
                     fnc main: s32 -> {
-                        let s = "foo";
-                        0
+                        let (q, _) = div_rem 17, 5;
+                        let mut (a, b) = div_rem 17, 5;
+                        a = a * 100;
+                        a + q * 10 + b
                     };
                 "#,
             )],
-            expect![["S32(0)"]],
+            expect![["S32(332)"]],
         );
     }
 
     #[test]
-    fn return_string() {
+    fn match_expr() {
         check(
             [(
                 "main",
                 r#"
-                    fnc main: string -> "hello";
+                    fnc describe(n: s64): string -> match n {
+                        -1 -> "minus one";
+                        0 -> "zero";
+                        _ -> "many";
+                    };
+                    fnc classify(p: (bool, char)): s32 -> match p {
+                        (true, 'a') -> 1;
+                        (true, c) -> c as s32;
+                        (false, _) -> 0;
+                    };
+                    fnc main: (string, string, s32, s32) -> {
+                        let minus_one: s64 = -1;
+                        (
+                            {describe minus_one},
+                            {describe 5},
+                            {classify (true, 'a')},
+                            {classify (true, 'b')},
+                        )
+                    };
                 "#,
             )],
-            expect![[r#"String("hello")"#]],
+            expect![[r#"Tuple([String("minus one"), String("many"), S32(1), S32(98)])"#]],
         );
     }
 
     #[test]
-    fn multiple_strings() {
+    fn nested_match_bindings() {
         check(
             [(
                 "main",
                 r#"
-                    fnc main: string -> {
-                        let a = "foo";
-                        let b = "bar";
-                        let c = "baz";
-                        let d = "quux";
-                        c
+                    fnc main: s32 -> {
+                        let n = 10;
+                        match (1, (2, "three")) {
+                            (a, (b, "four")) -> 0;
+                            (a, (n, s)) -> match s { "three" -> a + n * 100; _ -> 0 };
+                        } + n
                     };
                 "#,
             )],
-            expect![[r#"String("baz")"#]],
+            expect![[r#"S32(211)"#]],
         );
     }
+
+    #[test]
+    fn options_and_results() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc or_zero(o: Option[s32]): s32 -> match o { Some n -> n; None -> 0 };
+                    fnc check(n: s32): Result[s32, string] -> match n {
+                        0 -> Err "zero";
+                        _ -> Ok n;
+                    };
+                    fnc main: (s32, s32, Result[s32, string], Option[Option[bool]]) -> (
+                        {or_zero {parse_int "-12"}},
+                        {or_zero {parse_int "twelve"}},
+                        {check 0},
+                        Some None,
+                    );
+                "#,
+            )],
+            expect![[
+                r#"Tuple([S32(-12), S32(0), Variant(Err, Some(String("zero"))), Variant(Some, Some(Variant(None, None)))])"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn try_operator() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc add(a: string, b: string): Option[s32] ->
+                        Some {parse_int a}? + {parse_int b}?;
+                    fnc main: (Option[s32], Option[s32]) ->
+                        ({add "1", "2"}, {add "1", "two"});
+                "#,
+            )],
+            expect![[r#"Tuple([Variant(Some, Some(S32(3))), Variant(None, None)])"#]],
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc sum(xs: [s32]): s32 -> xs[0] + xs[1] + xs[2];
+                    fnc main: ([[char]], s32) -> {
+                        let grid = [['a', 'b'], ['c']];
+                        let i = 1;
+                        ([grid[i], grid[0]], sum [1, 2, 3])
+                    };
+                "#,
+            )],
+            expect![[
+                r#"Tuple([Array([Array([Char('c')]), Array([Char('a'), Char('b')])]), S32(6)])"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn index_out_of_bounds() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let xs = [1, 2, 3];
+                        xs[3]
+                    };
+                "#,
+            )],
+            expect![["error: index 3 is out of bounds for an array of length 3"]],
+        );
+    }
+
+    #[test]
+    fn negative_index() {
+        check(
+            [("main", "fnc main: bool -> [true][-1];")],
+            expect![["error: index -1 is out of bounds for an array of length 1"]],
+        );
+    }
+
+    #[test]
+    fn compare_strings() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: bool -> {"apple" < "banana"}
+                        && {"app" < "apple"}
+                        && {"Zebra" < "apple"}
+                        && {"pear" == "pear"}
+                        && {"pear" != "pears"}
+                        && {"é" > "z"};
+                "#,
+            )],
+            expect![["Bool(true)"]],
+        );
+    }
+
+    #[test]
+    fn logical_operators() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: bool -> 1 < 2 && 2 < 3 || false;
+                "#,
+            )],
+            expect![["Bool(true)"]],
+        );
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: bool -> {false && fail} || {true || fail};
+                    extern fnc fail: bool;
+                "#,
+            )],
+            expect![["Bool(true)"]],
+        );
+    }
+
+    #[test]
+    fn unary_operators() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> -{1 - -2} * 2;
+                "#,
+            )],
+            expect![[r#"S32(-6)"#]],
+        );
+    }
+
+    #[test]
+    fn not() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: bool -> !{1 < 2} || !!false;
+                "#,
+            )],
+            expect![[r#"Bool(false)"#]],
+        );
+    }
+
+    #[test]
+    fn assign() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let mut x = 1;
+                        x = x + 2;
+                        loop {
+                            x = x * 3;
+                            break;
+                        };
+                        x
+                    };
+                "#,
+            )],
+            expect![[r#"S32(9)"#]],
+        );
+    }
+
+    #[test]
+    fn for_loop() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let mut sum = 0;
+                        for i in 0..10 {
+                            sum = sum + i;
+                        };
+                        for i in 5..5 {
+                            sum = 1000;
+                        };
+                        sum
+                    };
+                "#,
+            )],
+            expect![[r#"S32(45)"#]],
+        );
+    }
+
+    #[test]
+    fn early_return() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc first_square_above(n: s32): s32 -> {
+                        for i in 0..n {
+                            loop {
+                                if_above n, i * i;
+                                break;
+                            };
+                            let square = i * i;
+                            square > n && { return square; };
+                        };
+                        return -1;
+                    };
+
+                    fnc if_above(n: s32, x: s32) -> {
+                        return;
+                    };
+
+                    fnc partial_sum: s32 -> 1 + 2 + { return 10; };
+
+                    fnc main: s32 -> {
+                        let square = first_square_above 50;
+                        square + partial_sum
+                    };
+                "#,
+            )],
+            expect![[r#"S32(74)"#]],
+        );
+    }
+
+    #[test]
+    fn recursion() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc factorial(n: s32): s32 -> {
+                        n == 0 && { return 1; };
+                        n * factorial {n - 1}
+                    };
+
+                    fnc main: s32 -> factorial 10;
+                "#,
+            )],
+            expect![[r#"S32(3628800)"#]],
+        );
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: bool -> is_even 7;
+
+                    fnc is_even(n: s32): bool -> {
+                        n == 0 && { return true; };
+                        is_odd n - 1
+                    };
+
+                    fnc is_odd(n: s32): bool -> {
+                        n == 0 && { return false; };
+                        is_even n - 1
+                    };
+                "#,
+            )],
+            expect![[r#"Bool(false)"#]],
+        );
+    }
+
+    #[test]
+    fn nested_functions() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let a = { fnc value: s32 -> 1; value };
+                        let b = { fnc value: s32 -> 2; value };
+                        fnc sum_to(n: s32): s32 -> {
+                            n == 0 && { return 0; };
+                            n + sum_to n - 1
+                        };
+                        a * 100 + b * 10 + sum_to 3
+                    };
+                "#,
+            )],
+            expect![[r#"S32(126)"#]],
+        );
+    }
+
+    #[test]
+    fn nested_functions_in_tail_blocks() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        {fnc h: s32 -> 2; h};
+                        { {fnc k: s32 -> 3; k} * {fnc k: s32 -> 4; k} }
+                    };
+                "#,
+            )],
+            expect![[r#"S32(12)"#]],
+        );
+    }
+
+    #[test]
+    fn for_loop_with_break_and_continue() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let mut sum = 0;
+                        for i in -3..100 {
+                            sum = sum + i;
+                            for j in 0..i {
+                                continue;
+                                sum = 1000;
+                            };
+                            loop {
+                                break;
+                            };
+                            for _j in 0..1 {
+                                break;
+                            };
+                        };
+                        sum
+                    };
+                "#,
+            )],
+            expect![[r#"S32(4944)"#]],
+        );
+    }
+
+    #[test]
+    fn local() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let foo = 5;
+                        foo
+                    };
+                "#,
+            )],
+            expect![["S32(5)"]],
+        );
+    }
+
+    #[test]
+    fn nested_blocks() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let d = {
+                            let c = {
+                                let b = {
+                                    let a = 10;
+                                    a - 5
+                                };
+                                b + 22
+                            };
+                            c / 2
+                        };
+                        d * 5
+                    };
+                "#,
+            )],
+            expect![["S32(65)"]],
+        );
+    }
+
+    #[test]
+    fn call_with_inferred_return_ty() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> add 1, 2;
+                    fnc add(a: s32, b: s32) -> a + b;
+                "#,
+            )],
+            expect![[r#"S32(3)"#]],
+        );
+    }
+
+    #[test]
+    fn call_with_no_params_or_return_ty() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        nil;
+                        1
+                    };
+                    fnc nil -> {};
+                "#,
+            )],
+            expect![["S32(1)"]],
+        );
+    }
+
+    #[test]
+    fn call_with_no_params_but_with_return_ty() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> n;
+                    fnc n: s32 -> 5;
+                "#,
+            )],
+            expect![["S32(5)"]],
+        );
+    }
+
+    #[test]
+    fn call_with_params_and_return_ty() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> add 10, 20;
+                    fnc add(x: s32, y: s32): s32 -> x + y;
+                "#,
+            )],
+            expect![["S32(30)"]],
+        );
+    }
+
+    #[test]
+    fn create_string() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let s = "foo";
+                        0
+                    };
+                "#,
+            )],
+            expect![["S32(0)"]],
+        );
+    }
+
+    #[test]
+    fn return_string() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: string -> "hello";
+                "#,
+            )],
+            expect![[r#"String("hello")"#]],
+        );
+    }
+
+    #[test]
+    fn return_bool() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: bool -> pass false;
+                    fnc pass(b: bool): bool -> b;
+                "#,
+            )],
+            expect![["Bool(false)"]],
+        );
+    }
+
+    #[test]
+    fn multiple_strings() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: string -> {
+                        let a = "foo";
+                        let b = "bar";
+                        let c = "baz";
+                        let d = "quux";
+                        c
+                    };
+                "#,
+            )],
+            expect![[r#"String("baz")"#]],
+        );
+    }
+
+    #[test]
+    fn break_out_of_loop() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        loop { break; };
+                        42
+                    };
+                "#,
+            )],
+            expect![["S32(42)"]],
+        );
+    }
+
+    #[test]
+    fn break_with_value() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> loop {
+                        let n = loop { break 40; };
+                        break n + 2;
+                    };
+                "#,
+            )],
+            expect![["S32(42)"]],
+        );
+    }
+
+    #[test]
+    fn break_out_of_nested_loop() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        loop {
+                            loop { break; };
+                            break;
+                        };
+                        42
+                    };
+                "#,
+            )],
+            expect![["S32(42)"]],
+        );
+    }
+
+    #[test]
+    fn break_in_the_middle_of_an_expression() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let a = 1;
+                        loop {
+                            let b = 2;
+                            a + b + { break; 3 };
+                        };
+                        a
+                    };
+                "#,
+            )],
+            expect![["S32(1)"]],
+        );
+    }
+
+    #[test]
+    fn continue_keeps_looping() {
+        let program = Program::new([(
+            "main",
+            r#"
+                fnc main -> loop {
+                    1 + 2;
+                    continue;
+                };
+            "#,
+        )]);
+
+        let progress = Evaluator::default()
+            .eval_with_budget(program.main, &program.bodies_map, &program.world_index, 1000)
+            .unwrap();
+        assert!(matches!(progress, Progress::Paused(_)));
+    }
+
+    #[test]
+    fn defer_runs_in_reverse_order_when_leaving_block() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let mut n = 0;
+                        {
+                            defer { n = n * 10 + 1; };
+                            defer { n = n * 10 + 2; };
+                            n = 5;
+                        };
+                        n
+                    };
+                "#,
+            )],
+            expect![["S32(521)"]],
+        );
+    }
+
+    #[test]
+    fn defer_runs_after_tail_expr() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let mut n = 1;
+                        let m = {
+                            defer { n = 2; };
+                            n
+                        };
+                        m * 10 + n
+                    };
+                "#,
+            )],
+            expect![["S32(12)"]],
+        );
+    }
+
+    #[test]
+    fn defer_runs_on_early_return() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        defer assert false, "deferred";
+                        return 1;
+                        2
+                    };
+                "#,
+            )],
+            expect![[r#"error: assertion failed: deferred"#]],
+        );
+    }
+
+    #[test]
+    fn defer_runs_on_break_and_continue() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let mut n = 0;
+                        let m = loop {
+                            defer { n = n + 1; };
+                            n < 3 && { continue; };
+                            break n * 100;
+                        };
+                        m * 100 + n
+                    };
+                "#,
+            )],
+            expect![[r#"S32(30004)"#]],
+        );
+    }
+
+    #[test]
+    fn labeled_break_and_continue() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        let mut sum = 0;
+                        'rows: for i in 0..10 {
+                            for j in 0..10 {
+                                j > i && { continue 'rows; };
+                                i == 5 && { break 'rows; };
+                                sum = sum + 1;
+                            };
+                        };
+                        sum
+                    };
+                "#,
+            )],
+            expect![[r#"S32(15)"#]],
+        );
+    }
+
+    #[test]
+    fn break_out_of_labeled_block() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: (s32, s32, s32) -> {
+                        let a = 'block: {
+                            loop { break 'block 1; };
+                            2
+                        };
+                        let mut n = 0;
+                        let b = 'block: {
+                            defer { n = 10; };
+                            break 'block n + 3;
+                            4
+                        };
+                        (a, b, n)
+                    };
+                "#,
+            )],
+            expect![[r#"Tuple([S32(1), S32(3), S32(10)])"#]],
+        );
+    }
+
+    #[test]
+    fn call_std_function() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> std.square 7;
+                "#,
+            )],
+            expect![["S32(49)"]],
+        );
+    }
+
+    #[test]
+    fn call_generic_std_function() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: (s32, string) -> ({std.id 1}, {std.id "x"});
+                "#,
+            )],
+            expect![[r#"Tuple([S32(1), String("x")])"#]],
+        );
+    }
+
+    #[test]
+    fn call_std_function_with_multiple_params() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> std.average 10, 20;
+                "#,
+            )],
+            expect![["S32(15)"]],
+        );
+    }
+
+    #[test]
+    fn std_array_helpers() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: (s32, bool, bool, Option[string], Option[s32], bool, Option[s32], s32) -> {
+                        let xs = [3, 1, 4, 1, 5];
+                        let none: [string] = [];
+                        (
+                            {array_len xs},
+                            {std.is_empty none},
+                            {std.is_empty xs},
+                            {std.first ["a", "b"]},
+                            {std.last xs},
+                            {std.contains xs, 4},
+                            {std.index_of xs, 1},
+                            {std.sum xs},
+                        )
+                    };
+                "#,
+            )],
+            expect![[
+                r#"Tuple([S32(5), Bool(true), Bool(false), Variant(Some, Some(String("a"))), Variant(Some, Some(S32(5))), Bool(true), Variant(Some, Some(S32(1))), S32(14)])"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn std_string_helpers() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: (bool, bool, bool, bool, s32) -> (
+                        {std.starts_with "gingerbread", "ginger"},
+                        {std.starts_with "gin", "ginger"},
+                        {std.ends_with "gingerbread", "bread"},
+                        {std.ends_with "gingerbread", "ginger"},
+                        {std.count_char "gingerbread", 'g'},
+                    );
+                "#,
+            )],
+            expect![[r#"Tuple([Bool(true), Bool(false), Bool(true), Bool(false), S32(2)])"#]],
+        );
+    }
+
+    #[test]
+    fn call_imported_functions() {
+        check(
+            [
+                (
+                    "main",
+                    r#"
+                        use geometry.area;
+                        use geometry.perimeter;
+                        use std.square;
+                        fnc main: s32 -> area {square 3}, {perimeter 1, 2};
+                    "#,
+                ),
+                (
+                    "geometry",
+                    r#"
+                        use std.double;
+                        pub fnc area(width: s32, height: s32): s32 -> width * height;
+                        pub fnc perimeter(width: s32, height: s32): s32 -> double {width + height};
+                    "#,
+                ),
+            ],
+            expect![["S32(54)"]],
+        );
+    }
+
+    #[test]
+    fn call_extern_function() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> puts "hello";
+                    extern fnc puts(s: string): s32;
+                "#,
+            )],
+            expect![["error: cannot call `main.puts`: no host function was registered for it"]],
+        );
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_unsupported() {
+        let error = |text| compile_main(text).unwrap_err().to_string();
+
+        assert_eq!(
+            error("fnc main: s32 -> { let t = (1, 2); t.0 };"),
+            "compiled mode doesn’t support tuples yet"
+        );
+        assert_eq!(error("fnc main -> { defer 1; };"), "compiled mode doesn’t support `defer` yet");
+        assert_eq!(
+            error(r#"fnc main -> println "hi";"#),
+            "compiled mode doesn’t support builtins yet"
+        );
+        assert_eq!(
+            error("fnc id[T](x: T): T -> x; fnc main: s32 -> id 1;"),
+            "compiled mode doesn’t support generic functions yet"
+        );
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_extern_function_to_import() {
+        let text = r#"
+            fnc main: s32 -> add_one 41;
+            extern fnc add_one(n: s32): s32;
+        "#;
+
+        let wasm = compile_main(text).unwrap();
+
+        let mut store = wasmtime::Store::<()>::default();
+        let module = wasmtime::Module::new(store.engine(), wasm).unwrap();
+
+        let imports: Vec<_> =
+            module.imports().map(|i| format!("{}.{}", i.module(), i.name().unwrap())).collect();
+        assert_eq!(imports, ["main.add_one"]);
+
+        let add_one = wasmtime::Func::wrap(&mut store, |n: i32| n + 1);
+        let instance = wasmtime::Instance::new(&mut store, &module, &[add_one.into()]).unwrap();
+        let main = instance.get_typed_func::<(), i32, _>(&mut store, "main").unwrap();
+        assert_eq!(main.call(&mut store, ()).unwrap(), 42);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_loops() {
+        let text = r#"
+            fnc main: s32 -> {
+                loop {
+                    loop { break; };
+                    break;
+                };
+                let n = loop { break 5; };
+                n + 2
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 7);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_labeled_loops() {
+        let text = r#"
+            fnc main: s32 -> {
+                let mut sum = 0;
+                'rows: for i in 0..10 {
+                    'columns: loop {
+                        i == 5 && { break 'rows; };
+                        sum = sum + i;
+                        loop { continue 'rows; };
+                    };
+                };
+                sum
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 10);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_comparisons() {
+        let text = r#"
+            fnc main: bool -> {1 + 2 <= 3} == {5 > 4};
+        "#;
+
+        assert_eq!(run_compiled_main(text), 1);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_s64_and_u32() {
+        let text = r#"
+            fnc big(n: s64): s64 -> n * 1000000000000 / -2;
+            fnc halve(n: u32): u32 -> n / 2;
+            fnc main: bool -> {big 3} < -1000000000000 && {halve 4000000000} > 1000000000;
+        "#;
+
+        assert_eq!(run_compiled_main(text), 1);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_casts() {
+        let text = r#"
+            fnc widen(n: s32): s64 -> n as s64 * 1000000000;
+            fnc main: s32 -> {
+                let n = -3;
+                {{widen n} / 1000000000} as s32 + 'a' as s32
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 94);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_chars() {
+        let text = r#"
+            fnc next(c: char): char -> c;
+            fnc main: bool -> {next 'a'} < 'b';
+        "#;
+
+        assert_eq!(run_compiled_main(text), 1);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_assign() {
+        let text = r#"
+            fnc main: s32 -> {
+                let mut total = 0;
+                let mut i = 0;
+                loop {
+                    i = i + 1;
+                    total = total + i;
+                    break;
+                };
+                total = total * 10;
+                total
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 10);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_for_loop() {
+        let text = r#"
+            fnc main: s32 -> {
+                let mut sum = 0;
+                for i in 0..10 {
+                    for j in 0..i {
+                        continue;
+                    };
+                    sum = sum + i;
+                    for k in 0..1 {
+                        break;
+                    };
+                };
+                sum
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 45);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_return() {
+        let text = r#"
+            fnc first_square_above(n: s32): s32 -> {
+                for i in 0..n {
+                    let square = i * i;
+                    square > n && { return square; };
+                };
+                return -1;
+            };
+
+            fnc partial_sum: s32 -> 1 + 2 + { return 10; };
+
+            fnc forever: s32 -> loop { return 3; };
+
+            fnc main: s32 -> {
+                let square = first_square_above 50;
+                square + partial_sum + forever
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 77);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_recursion() {
+        let text = r#"
+            fnc fib(n: s32): s32 -> {
+                n < 2 && { return n; };
+                {fib {n - 1}} + fib {n - 2}
+            };
+
+            fnc main: s32 -> fib 20;
+        "#;
+
+        assert_eq!(run_compiled_main(text), 6765);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_mutual_recursion() {
+        let text = r#"
+            fnc main: s32 -> ping 10;
+
+            fnc ping(n: s32): s32 -> {
+                n == 0 && { return 0; };
+                1 + pong n - 1
+            };
+
+            fnc pong(n: s32): s32 -> {
+                n == 0 && { return 0; };
+                2 + ping n - 1
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 15);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_nested_functions() {
+        let text = r#"
+            fnc main: s32 -> {
+                fnc square(n: s32): s32 -> n * n;
+                let mut total = 0;
+                for i in 0..4 {
+                    fnc add(a: s32, b: s32): s32 -> a + b;
+                    total = add total, square i;
+                };
+                total
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 14);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_unary_operators() {
+        let text = r#"
+            fnc main: s32 -> {
+                let negative = -{5 - 2};
+                let yes = !{negative > 0};
+                yes == true && negative == -3
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 1);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn compile_logical_operators() {
+        let text = r#"
+            fnc main: bool -> loop {
+                break {false || 1 < 2} && loop { break true && 3 < 2; };
+            };
+        "#;
+
+        assert_eq!(run_compiled_main(text), 0);
+    }
+
+    #[cfg(feature = "wasm")]
+    fn compile_main(text: &str) -> Result<Vec<u8>, CompileError> {
+        let mut interner = Interner::default();
+        let mut world_index = hir::WorldIndex::default();
+
+        let tokens = lexer::lex(text);
+        let tree = parser::parse_source_file(&tokens, text).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = hir::index(root, &tree, &world_index, &mut interner);
+        let main = hir::Name(interner.intern("main"));
+        world_index.add_module(main, index.clone());
+        let (bodies, _) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+        let (inference, _) = hir_ty::infer_all(&bodies, &index, &world_index);
+
+        compile(
+            hir::Fqn { module: main, function: main },
+            &FxHashMap::from_iter([(main, hir_ty::thir::lower(bodies, inference))]),
+            &world_index,
+            &interner,
+        )
+    }
+
+    #[cfg(feature = "wasm")]
+    fn run_compiled_main(text: &str) -> i32 {
+        let wasm = compile_main(text).unwrap();
+
+        let mut store = wasmtime::Store::<()>::default();
+        let module = wasmtime::Module::new(store.engine(), wasm).unwrap();
+        let instance = wasmtime::Instance::new(&mut store, &module, &[]).unwrap();
+        let main = instance.get_typed_func::<(), i32, _>(&mut store, "main").unwrap();
+        main.call(&mut store, ()).unwrap()
+    }
+
+    #[test]
+    fn unused_extern_function() {
+        check(
+            [(
+                "main",
+                r#"
+                    extern fnc exit(code: s32);
+                    fnc main: s32 -> 0;
+                "#,
+            )],
+            expect![["S32(0)"]],
+        );
+    }
+
+    #[test]
+    fn rand_int_is_reproducible() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        rand_seed 7;
+                        let a = rand_int 0, 1000000;
+                        rand_seed 7;
+                        let b = rand_int 0, 1000000;
+                        a - b
+                    };
+                "#,
+            )],
+            expect![["S32(0)"]],
+        );
+    }
+
+    #[test]
+    fn rand_int_with_seed() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        rand_seed 42;
+                        rand_int 1, 100
+                    };
+                "#,
+            )],
+            expect![["S32(14)"]],
+        );
+    }
+
+    #[test]
+    fn rand_int_with_single_value_range() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> rand_int 5, 5;
+                "#,
+            )],
+            expect![["S32(5)"]],
+        );
+    }
+
+    #[test]
+    fn rand_int_with_empty_range() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> rand_int 10, 1;
+                "#,
+            )],
+            expect![["error: cannot pick a random number between 10 and 1: the range is empty"]],
+        );
+    }
+
+    #[test]
+    fn rand_int_seeded_by_host() {
+        let mut evaluator = Evaluator::default();
+        evaluator.seed_rng(42);
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> rand_int 1, 100;
+                "#,
+            )],
+            expect![["S32(14)"]],
+        );
+    }
+
+    #[test]
+    fn trace_expressions() {
+        let text = r#"
+            fnc main: s32 -> {
+                let x = double 2;
+                defer x;
+                {x > 1} || {x / 0 > 0};
+                match Some x {
+                    Some n -> n + 1;
+                    None -> 0;
+                }
+            };
+            fnc double(n: s32): s32 -> n * 2;
+        "#;
+        let program = Program::new([("main", text)]);
+
+        let trace = |backend| {
+            let lines = Rc::new(RefCell::new(String::new()));
+            let mut evaluator = Evaluator::default();
+            evaluator.set_backend(backend);
+            evaluator.set_tracer({
+                let lines = Rc::clone(&lines);
+                move |_, range, val| {
+                    let source = text[range].split_whitespace().collect::<Vec<_>>().join(" ");
+                    let line = format!("{source} = {val}\n");
+                    lines.borrow_mut().push_str(&line);
+                }
+            });
+
+            evaluator.eval(program.main, &program.bodies_map, &program.world_index).unwrap();
+            lines.take()
+        };
+
+        let actual = trace(Backend::Bytecode);
+        assert_eq!(actual, trace(Backend::TreeWalker));
+        expect![[r#"
+            2 = 2
+            n = 2
+            2 = 2
+            n * 2 = 4
+            double 2 = 4
+            x = 4
+            1 = 1
+            x > 1 = true
+            {x > 1} = true
+            {x > 1} || {x / 0 > 0} = true
+            x = 4
+            Some x = Some 4
+            n = 4
+            1 = 1
+            n + 1 = 5
+            match Some x { Some n -> n + 1; None -> 0; } = 5
+            x = 4
+            { let x = double 2; defer x; {x > 1} || {x / 0 > 0}; match Some x { Some n -> n + 1; None -> 0; } } = 5
+        "#]].assert_eq(&actual);
+    }
+
+    #[test]
+    fn debugger_steps_through_program() {
+        #[derive(Default)]
+        struct Recorder(Rc<RefCell<Vec<Event>>>);
+
+        enum Event {
+            Statement { range: TextRange, scope: Vec<Val>, breakpoint: bool },
+            Call { function: hir::Fqn, args: Vec<Val> },
+        }
+
+        impl Recorder {
+            fn record_statement(
+                &mut self,
+                location: Location,
+                scope: &Scope<'_>,
+                breakpoint: bool,
+            ) {
+                let scope = scope.params.iter().chain(scope.locals.iter().map(|(_, val)| *val));
+                let scope = scope.cloned().collect();
+                self.0.borrow_mut().push(Event::Statement {
+                    range: location.range,
+                    scope,
+                    breakpoint,
+                });
+            }
+        }
+
+        impl Debugger for Recorder {
+            fn before_statement(&mut self, location: Location, scope: &Scope<'_>) {
+                self.record_statement(location, scope, false);
+            }
+
+            fn on_breakpoint(&mut self, location: Location, scope: &Scope<'_>) {
+                self.record_statement(location, scope, true);
+            }
+
+            fn before_call(&mut self, function: hir::Fqn, args: &[Val]) {
+                self.0.borrow_mut().push(Event::Call { function, args: args.to_vec() });
+            }
+        }
+
+        let text = r#"
+            fnc main: s32 -> {
+                let x = double 2;
+                let y = x + 1;
+                y
+            };
+            fnc double(n: s32, m: s32 = 3): s32 -> {
+                let product = n * 2;
+                product
+            };
+        "#;
+        let program = Program::new([("main", text)]);
+
+        let recorder = Recorder::default();
+        let events = Rc::clone(&recorder.0);
+        let mut evaluator = Evaluator::default();
+        evaluator.set_debugger(recorder);
+
+        let start = text.find("let y").unwrap();
+        let range = TextRange::at((start as u32).into(), 1.into());
+        evaluator.add_breakpoint(program.main.module, range);
+
+        evaluator.eval(program.main, &program.bodies_map, &program.world_index).unwrap();
+
+        let mut actual = String::new();
+        for event in events.take() {
+            match event {
+                Event::Statement { range, scope, breakpoint } => {
+                    let marker = if breakpoint { "break" } else { "step" };
+                    let scope = scope.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+                    let line = format!("{marker} `{}` [{}]\n", &text[range], scope.join(", "));
+                    actual.push_str(&line);
+                }
+                Event::Call { function, args } => {
+                    let function = program.interner.lookup(function.function.0);
+                    let args = args.iter().map(|val| val.to_string()).collect::<Vec<_>>();
+                    actual.push_str(&format!("call {function} [{}]\n", args.join(", ")));
+                }
+            }
+        }
+
+        expect![[r#"
+            call main []
+            step `let x = double 2;` []
+            call double [2]
+            step `let product = n * 2;` [2, 3]
+            break `let y = x + 1;` [4]
+        "#]]
+        .assert_eq(&actual);
+    }
+
+    #[test]
+    fn restore_snapshot() {
+        let program = Program::new([(
+            "main",
+            r#"
+                fnc main: s32 -> rand_int 1, 100;
+            "#,
+        )]);
+        let mut evaluator = Evaluator::default();
+        evaluator.set_fuel(1000);
+        let eval = |evaluator: &mut Evaluator| {
+            let val = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+            format!("{:?} with {:?} fuel left", val.unwrap(), evaluator.fuel().unwrap())
+        };
+
+        let snapshot = evaluator.snapshot();
+        let first = eval(&mut evaluator);
+        let second = eval(&mut evaluator);
+        evaluator.restore(snapshot);
+        let first_again = eval(&mut evaluator);
+
+        expect![[r#"
+            S32(36) with 996 fuel left
+            S32(1) with 992 fuel left
+            S32(36) with 996 fuel left
+        "#]]
+        .assert_eq(&format!("{first}\n{second}\n{first_again}\n"));
+    }
+
+    #[test]
+    fn rand_int_without_permission() {
+        check_with_evaluator(
+            Evaluator::new(Permissions::NONE),
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> rand_int 1, 6;
+                "#,
+            )],
+            expect![["error: `rand_int` is not permitted in this evaluator"]],
+        );
+    }
+
+    #[test]
+    fn args_and_env_from_host_config() {
+        let mut evaluator = Evaluator::default();
+        evaluator.set_host_config(HostConfig {
+            args: vec!["prog".to_string(), "--verbose".to_string()],
+            env: [("HOME".to_string(), "/home/gb".to_string())].into_iter().collect(),
+        });
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc main: ([string], Option[string], Option[string]) ->
+                        (args, {env "HOME"}, {env "PATH"});
+                "#,
+            )],
+            expect![[
+                r#"Tuple([Array([String("prog"), String("--verbose")]), Variant(Some, Some(String("/home/gb"))), Variant(None, None)])"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn args_and_env_default_to_empty() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: ([string], Option[string]) -> (args, {env "HOME"});
+                "#,
+            )],
+            expect![[r#"Tuple([Array([]), Variant(None, None)])"#]],
+        );
+    }
+
+    #[derive(Default, Clone)]
+    struct InMemoryFileSystem(Rc<RefCell<FxHashMap<String, String>>>);
+
+    impl FileSystem for InMemoryFileSystem {
+        fn read_file(&mut self, path: &str) -> io::Result<String> {
+            self.0.borrow().get(path).cloned().ok_or_else(|| io::ErrorKind::NotFound.into())
+        }
+
+        fn write_file(&mut self, path: &str, contents: &str) -> io::Result<()> {
+            self.0.borrow_mut().insert(path.to_string(), contents.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_and_write_files() {
+        let file_system = InMemoryFileSystem::default();
+        file_system.0.borrow_mut().insert("in.txt".to_string(), "hello".to_string());
+
+        let mut evaluator = Evaluator::default();
+        evaluator.set_file_system(file_system.clone());
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc main: string -> {
+                        let contents = read_file "in.txt";
+                        write_file "out.txt", {contents.substring 1, 5};
+                        read_file "out.txt"
+                    };
+                "#,
+            )],
+            expect![[r#"String("ello")"#]],
+        );
+
+        assert_eq!(file_system.0.borrow()["out.txt"], "ello");
+    }
+
+    #[test]
+    fn read_missing_file() {
+        let mut evaluator = Evaluator::default();
+        evaluator.set_file_system(InMemoryFileSystem::default());
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc main: string -> read_file "missing.txt";
+                "#,
+            )],
+            expect![[r#"error: input/output error: entity not found"#]],
+        );
+    }
+
+    #[test]
+    fn files_without_file_system() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main -> write_file "out.txt", "hello";
+                "#,
+            )],
+            expect![[r#"error: `write_file` is not permitted in this evaluator"#]],
+        );
+    }
+
+    struct FakeClock(i64);
+
+    impl Clock for FakeClock {
+        fn now_ms(&mut self) -> i64 {
+            self.0 += 250;
+            self.0
+        }
+    }
+
+    #[test]
+    fn clock_ms() {
+        let mut evaluator = Evaluator::default();
+        evaluator.set_clock(FakeClock(1000));
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc main: (s64, s64) -> {
+                        let start = clock_ms;
+                        let end = clock_ms;
+                        (start, end - start)
+                    };
+                "#,
+            )],
+            expect![[r#"Tuple([S64(1250), S64(250)])"#]],
+        );
+    }
+
+    #[test]
+    fn clock_ms_without_clock() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s64 -> clock_ms;
+                "#,
+            )],
+            expect![[r#"error: `clock_ms` is not permitted in this evaluator"#]],
+        );
+    }
+
+    #[test]
+    fn print_and_println() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main -> {
+                        print "hello, ";
+                        println "world"
+                    };
+                "#,
+            )],
+            expect![[r#"Nil after printing "hello, world\n""#]],
+        );
+    }
+
+    #[test]
+    fn read_line_without_permission() {
+        check_with_evaluator(
+            Evaluator::new(Permissions::NONE),
+            [(
+                "main",
+                r#"
+                    fnc main: Option[string] -> read_line;
+                "#,
+            )],
+            expect![["error: `read_line` is not permitted in this evaluator"]],
+        );
+    }
+
+    #[test]
+    fn read_line_from_input() {
+        let mut evaluator = Evaluator::default();
+        evaluator.set_input(io::Cursor::new("first\r\nsecond\n"));
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc main: (Option[string], Option[string], Option[string]) ->
+                        (read_line, read_line, read_line);
+                "#,
+            )],
+            expect![[
+                r#"Tuple([Variant(Some, Some(String("first"))), Variant(Some, Some(String("second"))), Variant(None, None)])"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn string_builtins() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: (s32, string, string, bool, bool, char) -> {
+                        let s = "año 🎉!";
+                        (
+                            {len s},
+                            {substring s, 1, 5},
+                            {substring s, 6, 6},
+                            {contains s, "o 🎉"},
+                            {contains s, "x"},
+                            {char_at s, 4},
+                        )
+                    };
+                "#,
+            )],
+            expect![[
+                r#"Tuple([S32(6), String("ño 🎉"), String(""), Bool(true), Bool(false), Char('🎉')])"#
+            ]],
+        );
+    }
+
+    #[test]
+    fn method_calls() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: (s32, string, s32) -> {
+                        let s = "hello";
+                        let n = 5;
+                        ({s.len}, {s.substring 1, s.len}, {n.square.add 1})
+                    };
+                    fnc square(n: s32): s32 -> n * n;
+                    fnc add(a: s32, b: s32): s32 -> a + b;
+                "#,
+            )],
+            expect![[r#"Tuple([S32(5), String("ello"), S32(26)])"#]],
+        );
+    }
+
+    #[test]
+    fn char_at_out_of_bounds() {
+        check(
+            [("main", r#"fnc main: char -> char_at "ñ", 1;"#)],
+            expect![[r#"error: index 1 is out of bounds for a string of length 1"#]],
+        );
+    }
+
+    #[test]
+    fn substring_out_of_bounds() {
+        check(
+            [("main", r#"fnc main: string -> substring "abc", 1, 4;"#)],
+            expect![[r#"error: index 4 is out of bounds for a string of length 3"#]],
+        );
+    }
+
+    #[test]
+    fn substring_starting_after_end() {
+        check(
+            [("main", r#"fnc main: string -> substring "abc", 2, 1;"#)],
+            expect![[r#"error: cannot take the substring from 2 to 1: it starts after it ends"#]],
+        );
+    }
+
+    #[test]
+    fn passing_assert() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> {
+                        assert 1 + 1 == 2, "math is broken";
+                        3
+                    };
+                "#,
+            )],
+            expect![["S32(3)"]],
+        );
+    }
+
+    #[test]
+    fn failing_assert() {
+        let source = r#"
+            fnc main -> {
+                let n = 2;
+                assert n < 1, "n is too big"
+            };
+        "#;
+        let program = Program::new([("main", source)]);
+        let result =
+            Evaluator::default().eval(program.main, &program.bodies_map, &program.world_index);
+
+        let Err(EvalError::AssertionFailed { message, module, range }) = result else {
+            panic!("expected the assertion to fail, got {result:?}")
+        };
+        assert_eq!(message, "n is too big");
+        assert_eq!(module, program.main.module);
+
+        let assert = r#"assert n < 1, "n is too big""#;
+        let start = source.find(assert).unwrap() as u32;
+        assert_eq!(range, TextRange::at(start.into(), (assert.len() as u32).into()));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let source = r#"
+            fnc main: s64 -> {
+                let zero: s64 = 0;
+                10 / zero
+            };
+        "#;
+        let program = Program::new([("main", source)]);
+        let result =
+            Evaluator::default().eval(program.main, &program.bodies_map, &program.world_index);
+
+        let Err(error @ EvalError::DivisionByZero { module, range }) = &result else {
+            panic!("expected the division to fail, got {result:?}")
+        };
+        assert_eq!(error.display(&program.interner), "attempted to divide by zero");
+        assert_eq!(*module, program.main.module);
+
+        let division = "10 / zero";
+        let start = source.find(division).unwrap() as u32;
+        assert_eq!(*range, TextRange::at(start.into(), (division.len() as u32).into()));
+    }
+
+    #[test]
+    fn overflow() {
+        let source = r#"
+            fnc double(n: s32): s32 -> n * 2;
+            fnc main: s32 -> double 2000000000;
+        "#;
+        let program = Program::new([("main", source)]);
+        let result =
+            Evaluator::default().eval(program.main, &program.bodies_map, &program.world_index);
+
+        let Err(error @ EvalError::Overflow { module, range }) = &result else {
+            panic!("expected the multiplication to overflow, got {result:?}")
+        };
+        assert_eq!(error.display(&program.interner), "arithmetic overflowed");
+        assert_eq!(*module, program.main.module);
+
+        let multiplication = "n * 2";
+        let start = source.find(multiplication).unwrap() as u32;
+        assert_eq!(*range, TextRange::at(start.into(), (multiplication.len() as u32).into()));
+    }
+
+    #[test]
+    fn overflow_of_each_operation() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc min: s32 -> -2147483647 - 1;
+                    fnc main: s32 -> min / -1;
+                "#,
+            )],
+            expect![["error: arithmetic overflowed"]],
+        );
+        check(
+            [(
+                "main",
+                r#"
+                    fnc zero: u32 -> 0;
+                    fnc main: u32 -> zero - 1;
+                "#,
+            )],
+            expect![["error: arithmetic overflowed"]],
+        );
+        check(
+            [(
+                "main",
+                r#"
+                    fnc min: s64 -> {0 - 9223372036854775807} - 1;
+                    fnc main: s64 -> -min;
+                "#,
+            )],
+            expect![["error: arithmetic overflowed"]],
+        );
+    }
+
+    #[test]
+    fn most_negative_literals() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: (s32, s64) -> {
+                        let x: s64 = -9223372036854775808;
+                        (-2147483648, x)
+                    };
+                "#,
+            )],
+            expect![["Tuple([S32(-2147483648), S64(-9223372036854775808)])"]],
+        );
+    }
+
+    #[test]
+    fn wrapping_overflow() {
+        let mut evaluator = Evaluator::default();
+        evaluator.set_overflow_mode(OverflowMode::Wrapping);
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc max: s32 -> 2147483647;
+                    fnc main: (s32, s32) -> ({max} + 1, {max} * 2);
+                "#,
+            )],
+            expect![["Tuple([S32(-2147483648), S32(-2)])"]],
+        );
+    }
+
+    #[test]
+    fn infinite_loop_runs_out_of_fuel() {
+        let mut evaluator = Evaluator::default();
+        evaluator.set_fuel(10_000);
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc main -> loop {};
+                "#,
+            )],
+            expect![["error: evaluation ran out of fuel"]],
+        );
+    }
+
+    #[test]
+    fn allocating_forever_runs_out_of_heap() {
+        let mut evaluator = Evaluator::default();
+        evaluator.set_heap_limit(10_000);
+
+        check_with_evaluator(
+            evaluator,
+            [(
+                "main",
+                r#"
+                    fnc main -> loop {
+                        let numbers = [1, 2, 3];
+                        let s = to_string numbers[0];
+                    };
+                "#,
+            )],
+            expect![["error: evaluation ran out of memory"]],
+        );
+    }
+
+    #[test]
+    fn heap_left_after_evaluation() {
+        let program = Program::new([(
+            "main",
+            r#"
+                fnc main: string -> {
+                    let numbers = [1, 2, 3];
+                    let literal = "not counted";
+                    to_string {numbers[0] + 122}
+                };
+            "#,
+        )]);
+
+        for backend in [Backend::Bytecode, Backend::TreeWalker] {
+            let mut evaluator = Evaluator::default();
+            evaluator.set_backend(backend);
+            evaluator.set_heap_limit(1000);
+
+            let result = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+            assert_eq!(result, Ok(Val::String("123".into())));
+            let used = 3 * std::mem::size_of::<Val>() + "123".len();
+            assert_eq!(evaluator.heap_limit(), Some(1000 - used as u64));
+        }
+    }
+
+    #[test]
+    fn fuel_left_after_evaluation() {
+        let program = Program::new([("main", "fnc main: s32 -> 1 + 2;")]);
+        let mut evaluator = Evaluator::default();
+        evaluator.set_fuel(10);
+
+        let result = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+        assert_eq!(result, Ok(Val::S32(3)));
+        let left = evaluator.fuel().unwrap();
+        assert!(left < 10);
+
+        evaluator.set_fuel(1);
+        let result = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+        assert_eq!(result, Err(EvalError::FuelExhausted));
+        assert_eq!(evaluator.fuel(), Some(0));
+    }
+
+    #[test]
+    fn unbounded_recursion_overflows_stack() {
+        let source = r#"
+            fnc forever(n: s32): s32 -> 1 + forever n;
+            fnc main: s32 -> forever 0;
+        "#;
+        let program = Program::new([("main", source)]);
+        let result =
+            Evaluator::default().eval(program.main, &program.bodies_map, &program.world_index);
+
+        let Err(error @ EvalError::StackOverflow { module, range }) = &result else {
+            panic!("expected the stack to overflow, got {result:?}")
+        };
+        assert_eq!(error.display(&program.interner), "too many nested calls");
+        assert_eq!(*module, program.main.module);
+
+        let call = "forever n";
+        let start = source.find(call).unwrap() as u32;
+        assert_eq!(*range, TextRange::at(start.into(), (call.len() as u32).into()));
+    }
+
+    #[test]
+    fn deep_tail_recursion() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc count(n: s32, total: s32 = 0): s32 -> {
+                        n == 0 && { return total; };
+                        count n - 1, total + 1
+                    };
+                    fnc is_even(n: s32): bool -> match n {
+                        0 -> true;
+                        _ -> is_odd n - 1;
+                    };
+                    fnc is_odd(n: s32): bool -> n != 0 && { return is_even n - 1; };
+                    fnc main: (s32, bool) -> {
+                        let total = 'done: loop {
+                            break 'done count 100000;
+                        };
+                        (total, is_even 100001)
+                    };
+                "#,
+            )],
+            expect![["Tuple([S32(100000), Bool(false)])"]],
+        );
+    }
+
+    #[test]
+    fn deferring_prevents_tail_calls() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc count(n: s32): s32 -> {
+                        defer n;
+                        n == 0 && { return 0; };
+                        count n - 1
+                    };
+                    fnc main: s32 -> count 100000;
+                "#,
+            )],
+            expect![["error: too many nested calls"]],
+        );
+    }
+
+    #[test]
+    fn backtrace_of_failed_evaluation() {
+        let main = r#"
+            use math.average;
+            fnc main: s32 -> {
+                let n = average 4, 0;
+                n
+            };
+        "#;
+        let math = r#"
+            pub fnc average(total: s32, count: s32): s32 -> {
+                let result = divide total, count;
+                result
+            };
+            fnc divide(a: s32, b: s32): s32 -> a / b;
+        "#;
+        let program = Program::new([("main", main), ("math", math)]);
+        let main_index = LineIndex::new(main);
+        let math_index = LineIndex::new(math);
+        let main_module = program.main.module;
+
+        let backtrace = |backend| {
+            let mut evaluator = Evaluator::default();
+            evaluator.set_backend(backend);
+
+            let result = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+            assert!(matches!(result, Err(EvalError::DivisionByZero { .. })), "{result:?}");
+
+            let mut lines = String::new();
+            for frame in &evaluator.backtrace().unwrap().frames {
+                let function = program.interner.lookup(frame.function.function.0);
+                lines.push_str(function);
+                if let Some(call_site) = frame.call_site {
+                    let index =
+                        if call_site.module == main_module { &main_index } else { &math_index };
+                    let (line, col) = index.line_col(call_site.range.start());
+                    let module = program.interner.lookup(call_site.module.0);
+                    lines.push_str(&format!(" called at {module}:{}:{}", line.0 + 1, col.0 + 1));
+                }
+                lines.push('\n');
+            }
+            lines
+        };
+
+        let actual = backtrace(Backend::Bytecode);
+        assert_eq!(actual, backtrace(Backend::TreeWalker));
+        expect![[r#"
+            divide called at math:3:30
+            average called at main:4:25
+            main
+        "#]]
+        .assert_eq(&actual);
+
+        // it only lasts until the next evaluation
+        let mut evaluator = Evaluator::default();
+        let _ = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+        assert!(evaluator.backtrace().is_some());
+        let program = Program::new([("main", "fnc main: s32 -> 1;")]);
+        let _ = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+        assert_eq!(evaluator.backtrace(), None);
+    }
+
+    #[test]
+    fn max_call_depth() {
+        let source = r#"
+            fnc count_down(n: s32): s32 -> {
+                n == 0 && { return 0; };
+                let result = count_down n - 1;
+                result
+            };
+            fnc main: s32 -> {
+                let result = count_down 9;
+                result
+            };
+        "#;
+        let program = Program::new([("main", source)]);
+
+        // `main` takes up one call as well
+        let mut evaluator = Evaluator::default();
+        evaluator.set_max_call_depth(11);
+        let result = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+        assert_eq!(result, Ok(Val::S32(0)));
+
+        evaluator.set_max_call_depth(10);
+        let result = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+        assert!(matches!(result, Err(EvalError::StackOverflow { .. })), "{result:?}");
+    }
+
+    #[test]
+    fn int_to_string() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: string -> to_string {0 - 42};
+                "#,
+            )],
+            expect![[r#"String("-42")"#]],
+        );
+    }
+
+    #[test]
+    fn shadow_builtin() {
+        check(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> rand_int 1, 6;
+                    fnc rand_int(lo: s32, hi: s32): s32 -> 4;
+                "#,
+            )],
+            expect![["S32(4)"]],
+        );
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let program = Program::new([(
+            "main",
+            r#"
+                fnc main: s32 -> add {1 + 2}, {3 * 4};
+                fnc add(a: s32, b: s32): s32 -> a + b;
+            "#,
+        )]);
+        let mut evaluator = Evaluator::default();
+
+        let mut pauses = 0;
+        let mut progress = evaluator
+            .eval_with_budget(program.main, &program.bodies_map, &program.world_index, 3)
+            .unwrap();
+
+        let val = loop {
+            match progress {
+                Progress::Finished(val) => break val,
+                Progress::Paused(paused) => {
+                    pauses += 1;
+                    progress = evaluator
+                        .resume(paused, &program.bodies_map, &program.world_index, 3)
+                        .unwrap();
+                }
+                Progress::Pending(_) => unreachable!(),
+            }
+        };
+
+        expect![["S32(15) after 3 pauses"]].assert_eq(&format!("{val:?} after {pauses} pauses"));
+    }
+
+    #[test]
+    fn zero_budget_pauses_before_first_step() {
+        let program = Program::new([(
+            "main",
+            r#"
+                fnc main: s32 -> 42;
+            "#,
+        )]);
+        let mut evaluator = Evaluator::default();
+
+        let progress = evaluator
+            .eval_with_budget(program.main, &program.bodies_map, &program.world_index, 0)
+            .unwrap();
+        let Progress::Paused(paused) = progress else { panic!("evaluation should have paused") };
+
+        let progress =
+            evaluator.resume(paused, &program.bodies_map, &program.world_index, 10).unwrap();
+        let Progress::Finished(val) = progress else { panic!("evaluation should have finished") };
+        expect![["S32(42)"]].assert_eq(&format!("{val:?}"));
+    }
+
+    #[test]
+    fn call_host_function() {
+        let mut program = Program::new([(
+            "main",
+            r#"
+                fnc main: s32 -> add_one 41;
+                extern fnc add_one(n: s32): s32;
+            "#,
+        )]);
+        let add_one = program.fqn("main", "add_one");
+
+        let mut evaluator = Evaluator::default();
+        evaluator.register_host_function(add_one, |args| match args[..] {
+            [Val::S32(n)] => Poll::Ready(Val::S32(n + 1)),
+            _ => unreachable!(),
+        });
+
+        let val = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+        expect![["S32(42)"]].assert_eq(&format!("{:?}", val.unwrap()));
+    }
+
+    #[test]
+    fn call_native_function() {
+        let mut evaluator = Evaluator::default();
+        let program = Program::with_setup(
+            [(
+                "main",
+                r#"
+                    fnc main: s32 -> double {double 10} + 1;
+                "#,
+            )],
+            |world_index, interner| {
+                let fqn = hir::Fqn {
+                    module: hir::Name(interner.intern("host")),
+                    function: hir::Name(interner.intern("double")),
+                };
+                let signature = hir::Function {
+                    ty_params: Vec::new(),
+                    params: vec![hir::Param { name: None, ty: hir::Ty::S32, has_default: false }],
+                    return_ty: hir::Ty::S32,
+                    infers_return_ty: false,
+                    is_pub: true,
+                    is_extern: true,
+                    attrs: Vec::new(),
+                };
+
+                evaluator.register_fnc(world_index, fqn, signature, |args| match args[..] {
+                    [Val::S32(n)] => Poll::Ready(Val::S32(n * 2)),
+                    _ => unreachable!(),
+                });
+            },
+        );
+
+        let val = evaluator.eval(program.main, &program.bodies_map, &program.world_index);
+        expect![["S32(42)"]].assert_eq(&format!("{:?}", val.unwrap()));
+    }
+
+    #[test]
+    fn pending_host_function_without_resumable_evaluation() {
+        let mut program = Program::new([(
+            "main",
+            r#"
+                fnc main: s32 -> fetch 1;
+                extern fnc fetch(n: s32): s32;
+            "#,
+        )]);
+        let fetch = program.fqn("main", "fetch");
+
+        let mut evaluator = Evaluator::default();
+        evaluator.register_host_function(fetch, |_| Poll::Pending);
+
+        let error =
+            evaluator.eval(program.main, &program.bodies_map, &program.world_index).unwrap_err();
+        expect![[
+            "`main.fetch` did not return right away: only resumable evaluations can wait for it"
+        ]]
+        .assert_eq(&error.display(&program.interner));
+    }
+
+    #[tokio::test]
+    async fn drive_pending_host_function_from_tokio() {
+        let mut program = Program::new([(
+            "main",
+            r#"
+                fnc main: s32 -> {fetch 10} + {fetch 20};
+                extern fnc fetch(n: s32): s32;
+            "#,
+        )]);
+        let fetch = program.fqn("main", "fetch");
+
+        // The host function only records its argument:
+        // the actual work happens in a tokio task once evaluation is suspended.
+        let request = Rc::new(Cell::new(None));
+        let mut evaluator = Evaluator::default();
+        evaluator.register_host_function(fetch, {
+            let request = Rc::clone(&request);
+            move |args| match args[..] {
+                [Val::S32(n)] => {
+                    request.set(Some(n));
+                    Poll::Pending
+                }
+                _ => unreachable!(),
+            }
+        });
+
+        let mut progress = evaluator
+            .eval_with_budget(program.main, &program.bodies_map, &program.world_index, u64::MAX)
+            .unwrap();
+
+        let val = loop {
+            match progress {
+                Progress::Finished(val) => break val,
+                Progress::Paused(_) => unreachable!(),
+                Progress::Pending(call) => {
+                    let n = request.take().unwrap();
+                    let result = tokio::spawn(async move {
+                        tokio::task::yield_now().await;
+                        n * 2
+                    })
+                    .await
+                    .unwrap();
+
+                    let paused = call.complete(Val::S32(result));
+                    progress = evaluator
+                        .resume(paused, &program.bodies_map, &program.world_index, u64::MAX)
+                        .unwrap();
+                }
+            }
+        };
+
+        expect![["S32(60)"]].assert_eq(&format!("{val:?}"));
+    }
+
+    #[test]
+    fn display_values() {
+        let vals = [
+            Val::Nil,
+            Val::S32(-5),
+            Val::Bool(true),
+            Val::String("say \"hi\"\n".into()),
+            Val::String("a".repeat(2000).into()),
+            Val::Char('x'),
+            Val::Char('\''),
+            Val::Char('"'),
+            Val::Tuple(vec![Val::S32(1), Val::String("a".into())]),
+            Val::Tuple(Vec::new()),
+            Val::Tuple((0..20).map(Val::S32).collect()),
+            Val::Tuple(vec![Val::Tuple(vec![Val::Tuple(vec![Val::Bool(true)])]), Val::Nil]),
+            Val::Array(vec![Val::Char('a'), Val::Char('b')]),
+            Val::Array((0..20).map(Val::S32).collect()),
+            Val::Array(vec![Val::Array(vec![Val::Tuple(Vec::new())]), Val::Array(Vec::new())]),
+            Val::Variant(hir::Variant::None, None),
+            Val::Variant(
+                hir::Variant::Ok,
+                Some(Box::new(Val::Variant(hir::Variant::Some, Some(Box::new(Val::S32(1)))))),
+            ),
+        ];
+        let limits = PrintLimits { max_string_len: 10, max_elements: 5, max_depth: 2 };
+
+        let displayed: Vec<_> = vals.iter().map(|val| val.display(limits)).collect();
+        expect![[r#"
+            {}
+            -5
+            true
+            "say \"hi\"\n"
+            "aaaaaaaaaa…"
+            'x'
+            '\''
+            '"'
+            (1, "a")
+            ()
+            (0, 1, 2, 3, 4, …)
+            (((…)), {})
+            ['a', 'b']
+            [0, 1, 2, 3, 4, …]
+            [[(…)], []]
+            None
+            Ok Some 1"#]]
+        .assert_eq(&displayed.join("\n"));
+    }
+
+    #[test]
+    fn compare_and_print_result() {
+        let program = Program::new([(
+            "main",
+            r#"
+                fnc main -> (1 + 2, ["a"], Some 'b');
+            "#,
+        )]);
+        let val = eval(program.main, &program.bodies_map, &program.world_index).unwrap();
+
+        assert_eq!(
+            val,
+            Val::Tuple(vec![
+                Val::S32(3),
+                Val::Array(vec![Val::String("a".into())]),
+                Val::Variant(hir::Variant::Some, Some(Box::new(Val::Char('b')))),
+            ])
+        );
+        assert_eq!(val.to_string(), r#"(3, ["a"], Some 'b')"#);
+    }
+
+    #[test]
+    fn display_unlimited_string() {
+        let val = Val::String("a".repeat(2000).into());
+        assert_eq!(val.display(PrintLimits::UNLIMITED).len(), 2002);
+    }
 }
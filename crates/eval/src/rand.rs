@@ -0,0 +1,32 @@
+// A SplitMix64 generator: tiny, fast and good enough for example programs.
+// We only need the sequence to be reproducible for a given seed,
+// so there’s no reason to pull in a dependency for it.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `lo..=hi`.
+    pub(crate) fn range_inclusive(&mut self, lo: i32, hi: i32) -> i32 {
+        assert!(lo <= hi);
+
+        let span = (i64::from(hi) - i64::from(lo) + 1) as u64;
+        let offset = (self.next_u64() % span) as i64;
+
+        (i64::from(lo) + offset) as i32
+    }
+}
@@ -0,0 +1,374 @@
+use crate::evaluator::{compare_with, index_array, s32_arg};
+use crate::{EvalError, EvalPaused, Evaluator, PendingHostCall, Progress, Val};
+use codegen_bytecode::{Instruction, Pattern, Program};
+use hir::{Backtrace, BacktraceFrame, CallSite};
+use std::rc::Rc;
+use std::task::Poll;
+
+// Runs the bytecode `codegen_bytecode` lowers programs to.
+// Every call gets a frame with its own range of `slots`
+// for its parameters and locals,
+// while all calls share the value stack.
+pub(crate) struct Vm<'a> {
+    evaluator: &'a mut Evaluator,
+    values: Vec<Val>,
+    state: State,
+    waiting_on: Option<hir::Fqn>,
+}
+
+/// Everything about a running program besides its value stack.
+pub(crate) struct State {
+    program: Rc<Program>,
+    // the strings of the program, which every value made from the same literal shares
+    strings: Vec<Rc<str>>,
+    frames: Vec<Frame>,
+    slots: Vec<Val>,
+}
+
+struct Frame {
+    function: u32,
+    // the next instruction to run
+    ip: u32,
+    // where the slots of the function start in `State::slots`
+    slots_start: usize,
+    // the height of the value stack once the arguments have been taken off it
+    values_len: usize,
+    arg_count: u32,
+    call_site: Option<CallSite>,
+}
+
+impl<'a> Vm<'a> {
+    pub(crate) fn new(evaluator: &'a mut Evaluator, program: Rc<Program>) -> Self {
+        let strings = program.strings.iter().map(|s| Rc::from(s.as_str())).collect();
+        let mut vm = Self {
+            evaluator,
+            values: Vec::new(),
+            state: State { program, strings, frames: Vec::new(), slots: Vec::new() },
+            waiting_on: None,
+        };
+
+        // the entry point comes first
+        vm.call(0, 0, None);
+        vm
+    }
+
+    pub(crate) fn resume(evaluator: &'a mut Evaluator, values: Vec<Val>, state: State) -> Self {
+        Self { evaluator, values, state, waiting_on: None }
+    }
+
+    pub(crate) fn run(mut self, budget: Option<u64>) -> Result<Progress, EvalError> {
+        let program = Rc::clone(&self.state.program);
+        let mut steps = 0;
+
+        loop {
+            if let Some(function) = self.waiting_on.take() {
+                let paused = EvalPaused::bytecode(self.values, self.state);
+                return Ok(Progress::Pending(PendingHostCall { function, paused }));
+            }
+
+            if self.state.frames.is_empty() {
+                break;
+            }
+
+            if budget == Some(steps) {
+                return Ok(Progress::Paused(EvalPaused::bytecode(self.values, self.state)));
+            }
+
+            if let Err(error) = self.evaluator.burn_fuel().and_then(|()| self.step(&program)) {
+                self.evaluator.failed_with(self.backtrace());
+                return Err(error);
+            }
+            steps += 1;
+        }
+
+        assert_eq!(self.values.len(), 1);
+        Ok(Progress::Finished(self.values.pop().unwrap()))
+    }
+
+    fn step(&mut self, program: &Program) -> Result<(), EvalError> {
+        let frame = self.state.frames.last_mut().unwrap();
+        let function = &program.functions[frame.function as usize];
+        let instruction = &function.code[frame.ip as usize];
+        frame.ip += 1;
+
+        let slots_start = frame.slots_start;
+        let arg_count = frame.arg_count;
+        let module = function.fqn.module;
+
+        match instruction {
+            Instruction::PushS32(n) => self.values.push(Val::S32(*n)),
+            Instruction::PushS64(n) => self.values.push(Val::S64(*n)),
+            Instruction::PushU32(n) => self.values.push(Val::U32(*n)),
+            Instruction::PushBool(b) => self.values.push(Val::Bool(*b)),
+            Instruction::PushChar(c) => self.values.push(Val::Char(*c)),
+            Instruction::PushString(idx) => {
+                let s = Rc::clone(&self.state.strings[*idx as usize]);
+                self.values.push(Val::String(s));
+            }
+            Instruction::PushNil => self.values.push(Val::Nil),
+            Instruction::PushVariant(variant) => self.values.push(Val::Variant(*variant, None)),
+
+            Instruction::Pop(count) => {
+                self.values.truncate(self.values.len() - *count as usize);
+            }
+
+            Instruction::Slide(count) => {
+                let top = self.values.pop().unwrap();
+                self.values.truncate(self.values.len() - *count as usize);
+                self.values.push(top);
+            }
+
+            Instruction::Load(slot) => {
+                let value = self.state.slots[slots_start + *slot as usize].clone();
+                self.values.push(value);
+            }
+
+            Instruction::Store(slot) => {
+                self.state.slots[slots_start + *slot as usize] = self.values.pop().unwrap();
+            }
+
+            Instruction::Unary { operator, range } => {
+                let operand = self.values.pop().unwrap();
+                match self.evaluator.unary(*operator, operand) {
+                    Ok(result) => self.values.push(result),
+                    Err(error) => return Err(error.at(module, *range)),
+                }
+            }
+
+            Instruction::Arithmetic { operator, range } => {
+                let rhs = self.values.pop().unwrap();
+                let lhs = self.values.pop().unwrap();
+                match self.evaluator.arithmetic(*operator, lhs, rhs) {
+                    Ok(result) => self.values.push(result),
+                    Err(error) => return Err(error.at(module, *range)),
+                }
+            }
+
+            Instruction::Compare(operator) => {
+                let rhs = self.values.pop().unwrap();
+                let lhs = self.values.pop().unwrap();
+                self.values.push(Val::Bool(compare_with(*operator, &lhs, &rhs)));
+            }
+
+            Instruction::Cast(ty) => {
+                let val = self.evaluator.cast(self.values.pop().unwrap(), ty.clone())?;
+                self.values.push(val);
+            }
+
+            Instruction::Tuple(len) => {
+                let elements = self.values.split_off(self.values.len() - *len as usize);
+                self.values.push(Val::Tuple(elements));
+            }
+
+            Instruction::Field(idx) => {
+                let Val::Tuple(mut elements) = self.values.pop().unwrap() else {
+                    unreachable!("type checking guarantees this is a tuple")
+                };
+                self.values.push(elements.swap_remove(*idx as usize));
+            }
+
+            Instruction::Array(len) => {
+                let elements = self.values.split_off(self.values.len() - *len as usize);
+                let array = self.evaluator.array(elements)?;
+                self.values.push(array);
+            }
+
+            Instruction::Variant(variant) => {
+                let field = self.values.pop().unwrap();
+                self.values.push(Val::Variant(*variant, Some(Box::new(field))));
+            }
+
+            Instruction::Index => {
+                let index = self.values.pop().unwrap();
+                let array = self.values.pop().unwrap();
+                self.values.push(index_array(array, index)?);
+            }
+
+            Instruction::Destructure(slots) => {
+                let Val::Tuple(elements) = self.values.pop().unwrap() else {
+                    unreachable!("type checking guarantees this is a tuple")
+                };
+
+                for (slot, element) in slots.iter().zip(elements) {
+                    if let Some(slot) = slot {
+                        self.state.slots[slots_start + *slot as usize] = element;
+                    }
+                }
+            }
+
+            Instruction::Jump(target) => self.jump(*target),
+
+            Instruction::JumpIfKeep { value, target } => match self.values.last() {
+                Some(Val::Bool(b)) if b == value => self.jump(*target),
+                Some(Val::Bool(_)) => {
+                    self.values.pop();
+                }
+                _ => unreachable!("type checking guarantees this is a bool"),
+            },
+
+            Instruction::ForNext { counter, end, local, exit } => {
+                let counter = slots_start + *counter as usize;
+                let next = s32_arg(&self.state.slots[counter]);
+                let end = s32_arg(&self.state.slots[slots_start + *end as usize]);
+
+                if next >= end {
+                    self.jump(*exit);
+                } else {
+                    self.state.slots[slots_start + *local as usize] = Val::S32(next);
+                    self.state.slots[counter] = Val::S32(next + 1);
+                }
+            }
+
+            Instruction::Match { pattern, otherwise } => {
+                let mut bindings = Vec::new();
+                let value = self.values.last().unwrap();
+
+                if match_pattern(&program.patterns[*pattern as usize], value, &mut bindings) {
+                    self.values.pop();
+                    for (slot, val) in bindings {
+                        self.state.slots[slots_start + slot as usize] = val;
+                    }
+                } else {
+                    self.jump(*otherwise);
+                }
+            }
+
+            Instruction::SkipIfPassed { param, target } => {
+                if *param < arg_count {
+                    self.jump(*target);
+                }
+            }
+
+            Instruction::Call { range, .. } | Instruction::CallExtern { range, .. }
+                if self.state.frames.len() >= self.evaluator.max_call_depth() =>
+            {
+                return Err(EvalError::StackOverflow { module, range: *range });
+            }
+
+            Instruction::Call { function, arg_count, range } => {
+                let call_site = CallSite { module, range: *range };
+                self.call(*function, *arg_count, Some(call_site));
+            }
+
+            Instruction::TailCall { function, arg_count, range } => {
+                let call_site = CallSite { module, range: *range };
+                let args_start = self.values.len() - *arg_count as usize;
+                let frame = self.state.frames.pop().unwrap();
+                self.values.drain(frame.values_len..args_start);
+                self.state.slots.truncate(frame.slots_start);
+                self.call(*function, *arg_count, Some(call_site));
+            }
+
+            Instruction::CallExtern { fqn, arg_count, .. } => {
+                let args = self.values.split_off(self.values.len() - *arg_count as usize);
+
+                match self.evaluator.call_host_function(*fqn, args)? {
+                    Poll::Ready(value) => self.values.push(value),
+                    Poll::Pending => self.waiting_on = Some(*fqn),
+                }
+            }
+
+            Instruction::CallBuiltin { builtin, arg_count } => {
+                let args = self.values.split_off(self.values.len() - *arg_count as usize);
+                let value = self.evaluator.call_builtin(*builtin, args)?;
+                self.values.push(value);
+            }
+
+            Instruction::Assert { range } => {
+                let Some(Val::String(message)) = self.values.pop() else { unreachable!() };
+                let Some(Val::Bool(condition)) = self.values.pop() else { unreachable!() };
+
+                if !condition {
+                    let message = message.to_string();
+                    return Err(EvalError::AssertionFailed { message, module, range: *range });
+                }
+
+                self.values.push(Val::Nil);
+            }
+
+            Instruction::Trace { range } => {
+                self.evaluator.trace(module, *range, self.values.last().unwrap());
+            }
+
+            Instruction::Return => {
+                let value = self.values.pop().unwrap();
+                let frame = self.state.frames.pop().unwrap();
+                self.values.truncate(frame.values_len);
+                self.state.slots.truncate(frame.slots_start);
+                self.values.push(value);
+            }
+        }
+
+        Ok(())
+    }
+
+    // the arguments move from the value stack into the first slots of the new frame
+    fn call(&mut self, function: u32, arg_count: u32, call_site: Option<CallSite>) {
+        let slot_count = self.state.program.functions[function as usize].slot_count;
+        let values_len = self.values.len() - arg_count as usize;
+        let slots_start = self.state.slots.len();
+
+        self.state.slots.extend(self.values.drain(values_len..));
+        self.state.slots.resize(slots_start + slot_count as usize, Val::Nil);
+
+        self.state.frames.push(Frame {
+            function,
+            ip: 0,
+            slots_start,
+            values_len,
+            arg_count,
+            call_site,
+        });
+    }
+
+    fn backtrace(&self) -> Backtrace {
+        let frames = self.state.frames.iter().rev().map(|frame| BacktraceFrame {
+            function: self.state.program.functions[frame.function as usize].fqn,
+            call_site: frame.call_site,
+        });
+        Backtrace { frames: frames.collect() }
+    }
+
+    fn jump(&mut self, target: u32) {
+        self.state.frames.last_mut().unwrap().ip = target;
+    }
+}
+
+/// Returns whether the pattern matches `val`,
+/// collecting the values of the slots it binds into `bindings`.
+fn match_pattern(pattern: &Pattern, val: &Val, bindings: &mut Vec<(u32, Val)>) -> bool {
+    match (pattern, val) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Bind(slot), _) => {
+            bindings.push((*slot, val.clone()));
+            true
+        }
+        (Pattern::Int(expected), _) => {
+            let n = match val {
+                Val::S32(n) => i128::from(*n),
+                Val::S64(n) => i128::from(*n),
+                Val::U32(n) => i128::from(*n),
+                _ => unreachable!("type checking guarantees this is an integer"),
+            };
+            n == *expected
+        }
+        (Pattern::Bool(expected), Val::Bool(b)) => b == expected,
+        (Pattern::String(expected), Val::String(s)) => **s == **expected,
+        (Pattern::Char(expected), Val::Char(c)) => c == expected,
+        (Pattern::Tuple(patterns), Val::Tuple(elements)) => patterns
+            .iter()
+            .zip(elements)
+            .all(|(pattern, element)| match_pattern(pattern, element, bindings)),
+        (Pattern::Variant { variant, field }, Val::Variant(val_variant, val_field)) => {
+            if variant != val_variant {
+                return false;
+            }
+
+            match (field, val_field) {
+                (Some(field), Some(val_field)) => match_pattern(field, val_field, bindings),
+                _ => true,
+            }
+        }
+        _ => unreachable!("type checking guarantees the pattern fits the value"),
+    }
+}
@@ -43,6 +43,9 @@ fn token_types() -> Vec<SemanticTokenType> {
             HighlightKind::Function => SemanticTokenType::FUNCTION,
             HighlightKind::Module => SemanticTokenType::NAMESPACE,
             HighlightKind::Ty => SemanticTokenType::new("builtinType"),
+            HighlightKind::Variant => SemanticTokenType::ENUM_MEMBER,
+            HighlightKind::Constant => SemanticTokenType::new("constant"),
+            HighlightKind::Label => SemanticTokenType::new("label"),
             HighlightKind::Number => SemanticTokenType::NUMBER,
             HighlightKind::Quote => SemanticTokenType::new("quote"),
             HighlightKind::Escape => SemanticTokenType::new("escapeSequence"),
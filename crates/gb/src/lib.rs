@@ -8,11 +8,11 @@ use lsp::proto::WriteMsgError;
 use lsp_types::notification::{PublishDiagnostics, ShowMessage};
 use lsp_types::request::SemanticTokensRefesh as SemanticTokensRefresh;
 use lsp_types::{
-    Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams, DidOpenTextDocumentParams,
-    GotoDefinitionParams, GotoDefinitionResponse, Location, LocationLink, MessageType, Position,
-    PublishDiagnosticsParams, Range, SelectionRange, SelectionRangeParams, SemanticToken,
-    SemanticTokens, SemanticTokensParams, SemanticTokensResult, ShowMessageParams,
-    SymbolInformation, SymbolKind, WorkspaceSymbolParams,
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, Location,
+    LocationLink, MessageType, NumberOrString, Position, PublishDiagnosticsParams, Range,
+    SelectionRange, SelectionRangeParams, SemanticToken, SemanticTokens, SemanticTokensParams,
+    SemanticTokensResult, ShowMessageParams, SymbolInformation, SymbolKind, WorkspaceSymbolParams,
 };
 use text_size::{TextRange, TextSize};
 
@@ -185,6 +185,7 @@ pub fn change_text_document(
             line_index = LineIndex::new(content);
         }
     });
+    global_state.check();
 
     publish_all_diagnostics(global_state, connection)?;
     connection.make_request::<SemanticTokensRefresh>(())?;
@@ -207,14 +208,56 @@ fn publish_all_diagnostics(
                     diagnostics::Severity::Error => DiagnosticSeverity::ERROR,
                 };
 
+                let mut related_information: Vec<_> = diagnostic
+                    .labels()
+                    .into_iter()
+                    .map(|label| DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: uri.clone(),
+                            range: convert_text_range(label.range, line_index),
+                        },
+                        message: label.message,
+                    })
+                    .collect();
+
+                let related = diagnostic.related_location(global_state.interner());
+                if let Some(related) = related {
+                    let related_uri = match related.module {
+                        Some(module) => global_state.module_uri(module),
+                        None => Some(uri.clone()),
+                    };
+                    if let Some(related_uri) = related_uri {
+                        let range = convert_text_range(
+                            related.range,
+                            global_state.line_index(&related_uri),
+                        );
+                        related_information.push(DiagnosticRelatedInformation {
+                            location: Location { uri: related_uri, range },
+                            message: related.message,
+                        });
+                    }
+                }
+
+                let related_information =
+                    (!related_information.is_empty()).then_some(related_information);
+
+                let mut message = diagnostic.message(global_state.interner());
+                for trailer in diagnostic.trailers(global_state.interner()) {
+                    let (kind, trailer) = match trailer {
+                        diagnostics::Trailer::Note(trailer) => ("note", trailer),
+                        diagnostics::Trailer::Help(trailer) => ("help", trailer),
+                    };
+                    message.push_str(&format!("\n{kind}: {trailer}"));
+                }
+
                 Diagnostic {
                     range: convert_text_range(diagnostic.range(), line_index),
                     severity: Some(severity),
-                    code: None,
+                    code: Some(NumberOrString::String(diagnostic.code().to_string())),
                     code_description: None,
                     source: Some("gb".to_string()),
-                    message: diagnostic.message(global_state.interner()),
-                    related_information: None,
+                    message,
+                    related_information,
                     tags: None,
                     data: None,
                 }
@@ -239,7 +282,7 @@ fn convert_lsp_range(range: Range, line_index: &LineIndex) -> TextRange {
 }
 
 fn convert_lsp_position(position: Position, line_index: &LineIndex) -> TextSize {
-    line_index[LineNr(position.line)] + TextSize::from(position.character)
+    line_index.offset(LineNr(position.line), ColNr(position.character))
 }
 
 fn convert_text_range(range: TextRange, line_index: &LineIndex) -> Range {
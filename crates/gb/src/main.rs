@@ -1,19 +1,21 @@
+use anyhow::{anyhow, bail, Context};
 use interner::Interner;
 use lsp_types::notification::{DidChangeTextDocument, DidOpenTextDocument};
 use lsp_types::request::{
     GotoDefinition, SelectionRangeRequest, SemanticTokensFullRequest, Shutdown, WorkspaceSymbol,
 };
-use lsp_types::InitializeResult;
+use lsp_types::{InitializeResult, Url};
 use owo_colors::Style;
-use std::env;
 use std::io::Read;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use std::{env, fs};
 
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 fn main() -> anyhow::Result<()> {
     match env::args().nth(1).as_deref() {
+        Some("check") => check()?,
         Some("highlight") => highlight()?,
         Some("server") => server()?,
         Some(subcommand) => eprintln!("`{subcommand}` is not a valid subcommand"),
@@ -23,6 +25,142 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn check() -> anyhow::Result<()> {
+    let mut path = None;
+    let mut lint_levels = diagnostics::LintLevels::default();
+    let mut color = io::stdout().is_terminal();
+    let mut display_limit = diagnostics::DisplayLimit::default();
+    let mut format = diagnostics::Format::Full;
+
+    let mut args = env::args().skip(2);
+    while let Some(arg) = args.next() {
+        let level = match arg.as_str() {
+            "--deny-warnings" => {
+                lint_levels.deny_warnings = true;
+                continue;
+            }
+            "--color" => {
+                color = match args.next().as_deref() {
+                    Some("always") => true,
+                    Some("never") => false,
+                    Some("auto") => io::stdout().is_terminal(),
+                    _ => bail!("`--color` needs one of `always`, `never` or `auto`"),
+                };
+                continue;
+            }
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("full") => diagnostics::Format::Full,
+                    Some("short") => diagnostics::Format::Short,
+                    _ => bail!("`--format` needs one of `full` or `short`"),
+                };
+                continue;
+            }
+            // warnings count towards the limit too
+            "--max-errors" => {
+                display_limit = match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => diagnostics::DisplayLimit::new(n),
+                    None => bail!("`--max-errors` needs a number"),
+                };
+                continue;
+            }
+            "--allow" => diagnostics::Level::Allow,
+            "--warn" => diagnostics::Level::Warn,
+            "--deny" => diagnostics::Level::Deny,
+            _ => {
+                path = Some(arg);
+                continue;
+            }
+        };
+
+        let name = args.next().with_context(|| format!("`{arg}` needs the name of a lint"))?;
+        let lint =
+            diagnostics::Lint::from_name(&name).ok_or_else(|| anyhow!("unknown lint `{name}`"))?;
+        lint_levels.set(lint, level);
+    }
+
+    let display_config = diagnostics::DisplayConfig { color, format };
+    let path = fs::canonicalize(path.context("please provide a file to check")?)?;
+    let uri = Url::from_file_path(&path).map_err(|()| anyhow!("invalid path"))?;
+
+    let mut global_state = ide::GlobalState::default();
+    global_state.set_lint_levels(lint_levels);
+    if global_state.open_file(uri)?.is_err() {
+        bail!("file is not part of a project");
+    }
+
+    let mut all_diagnostics: Vec<_> = global_state.diagnostics().collect();
+    all_diagnostics.sort_by_key(|(uri, _)| *uri);
+
+    let mut errors = 0;
+
+    for (uri, diagnostics) in all_diagnostics {
+        let content = global_state.content(uri);
+        let line_index = global_state.line_index(uri);
+
+        for diagnostic in diagnostics {
+            if diagnostic.severity() == diagnostics::Severity::Error {
+                errors += 1;
+            }
+            if !display_limit.allows(&diagnostic) {
+                continue;
+            }
+
+            let lines = diagnostic.display_with(
+                content,
+                global_state.interner(),
+                line_index,
+                display_config,
+            );
+
+            if format == diagnostics::Format::Short {
+                for line in lines {
+                    println!("{}:{line}", uri.path());
+                }
+                continue;
+            }
+
+            println!("{}:", uri.path());
+            for line in lines {
+                println!("{line}");
+            }
+
+            if let Some(related) = diagnostic.related_location(global_state.interner()) {
+                let related_uri = match related.module {
+                    Some(module) => global_state.module_uri(module),
+                    None => Some(uri.clone()),
+                };
+                if let Some(related_uri) = related_uri {
+                    let line_index = global_state.line_index(&related_uri);
+                    let (line, col) = line_index.line_col(related.range.start());
+                    println!(
+                        "note: {} at {}:{}:{}",
+                        related.message,
+                        related_uri.path(),
+                        line.0 + 1,
+                        col.0 + 1
+                    );
+                }
+            }
+
+            if let Some(suggestion) = diagnostic.suggestion() {
+                let (line, col) = line_index.line_col(suggestion.range.start());
+                println!("help: {} at {}:{}", suggestion.message, line.0 + 1, col.0 + 1);
+            }
+        }
+    }
+
+    if let Some(summary) = display_limit.summary() {
+        println!("{summary}");
+    }
+
+    if errors > 0 {
+        bail!("found {errors} {}", if errors == 1 { "error" } else { "errors" });
+    }
+
+    Ok(())
+}
+
 fn highlight() -> anyhow::Result<()> {
     let mut content = String::new();
     io::stdin().read_to_string(&mut content)?;
@@ -58,6 +196,9 @@ fn highlight() -> anyhow::Result<()> {
             ide::HighlightKind::Function => style.blue(),
             ide::HighlightKind::Module => style.yellow(),
             ide::HighlightKind::Ty => style.cyan(),
+            ide::HighlightKind::Variant => style.cyan(),
+            ide::HighlightKind::Constant => style.yellow(),
+            ide::HighlightKind::Label => style.yellow(),
             ide::HighlightKind::Number => style.green(),
             ide::HighlightKind::Quote => style.green(),
             ide::HighlightKind::Escape => style,
@@ -75,7 +216,7 @@ fn highlight() -> anyhow::Result<()> {
 
         last_range = range;
     }
-    bytes.extend_from_slice(content[last_range.end..].as_bytes());
+    bytes.extend_from_slice(&content.as_bytes()[last_range.end..]);
 
     io::stdout().write_all(&bytes)?;
 
@@ -0,0 +1,70 @@
+//! Everything needed to embed the language, in one crate.
+//!
+//! Lexing, parsing, lowering and type checking are always available.
+//! Everything else sits behind a feature (all enabled by default),
+//! so that an embedder which only validates programs
+//! can turn off default features and compile just the front end:
+//!
+//! - `diagnostics`: rendering errors as human-readable messages
+//!   (including runtime errors, when `eval` is enabled too)
+//! - `ide`: the analysis behind the language server
+//! - `eval`: the interpreter
+//! - `wasm`: compiling to WebAssembly (implies `eval`)
+
+pub use ast;
+pub use hir;
+pub use hir_ty;
+pub use interner;
+pub use lexer;
+pub use parser;
+pub use syntax;
+
+#[cfg(feature = "diagnostics")]
+pub use diagnostics;
+
+#[cfg(feature = "eval")]
+pub use eval;
+
+#[cfg(feature = "ide")]
+pub use ide;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+
+    // run with `--no-default-features` too,
+    // to check the front end still builds on its own
+    fn check(input: &str) -> usize {
+        let mut interner = interner::Interner::default();
+        let world_index = hir::WorldIndex::default();
+
+        let tokens = lexer::lex(input);
+        let parse = parser::parse_source_file(&tokens, input);
+        let syntax_errors = parse.errors().len();
+        let tree = parse.into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let validation_diagnostics = ast::validation::validate(root, &tree);
+
+        let (index, indexing_diagnostics) = hir::index(root, &tree, &world_index, &mut interner);
+        let (bodies, lowering_diagnostics) =
+            hir::lower(root, &tree, &index, &world_index, &mut interner);
+        let (_, ty_diagnostics) = hir_ty::infer_all(&bodies, &index, &world_index);
+
+        syntax_errors
+            + validation_diagnostics.len()
+            + indexing_diagnostics.len()
+            + lowering_diagnostics.len()
+            + ty_diagnostics.len()
+    }
+
+    #[test]
+    fn validate_valid_program() {
+        assert_eq!(check("fnc port: s32 -> 8080;"), 0);
+    }
+
+    #[test]
+    fn validate_invalid_program() {
+        assert_eq!(check("fnc port: s32 -> \"8080\";"), 1);
+    }
+}
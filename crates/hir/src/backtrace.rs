@@ -0,0 +1,29 @@
+use text_size::TextRange;
+
+use crate::{Fqn, Name};
+
+/// The calls in progress when a program failed at runtime,
+/// innermost first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Backtrace {
+    pub frames: Vec<BacktraceFrame>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BacktraceFrame {
+    pub function: Fqn,
+    /// Where the function was called,
+    /// which the function the program started with doesn’t have.
+    pub call_site: Option<CallSite>,
+}
+
+/// The call expression which entered a function.
+///
+/// This is usually in the function of the next frame,
+/// but a call in tail position replaces the frame of the function it’s in,
+/// so the call site can be in a function the backtrace no longer shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallSite {
+    pub module: Name,
+    pub range: TextRange,
+}
@@ -1,64 +1,296 @@
-use crate::{Fqn, Function, GetFunctionError, Index, Name, WorldIndex};
+use crate::prelude::GenericTy;
+use crate::{
+    Builtin, Fqn, Function, GetFunctionError, Index, Name, ParamsLocation, Ty, Variant, WorldIndex,
+};
 use arena::{Arena, ArenaMap, Id};
 use ast::{AstNode, AstToken};
 use interner::{Interner, Key};
 use rustc_hash::{FxHashMap, FxHashSet};
+use std::borrow::Cow;
 use syntax::SyntaxTree;
-use text_size::TextRange;
+use text_size::{TextRange, TextSize};
 
 #[derive(Clone)]
 pub struct Bodies {
-    local_defs: Arena<LocalDef>,
-    statements: Arena<Statement>,
-    exprs: Arena<Expr>,
-    expr_ranges: ArenaMap<Id<Expr>, TextRange>,
-    function_bodies: FxHashMap<Name, Id<Expr>>,
+    pub(crate) local_defs: Arena<LocalDef>,
+    pub(crate) statements: Arena<Statement>,
+    pub(crate) statement_ranges: ArenaMap<Id<Statement>, TextRange>,
+    pub(crate) exprs: Arena<Expr>,
+    pub(crate) expr_ranges: ArenaMap<Id<Expr>, TextRange>,
+    pub(crate) patterns: Arena<Pattern>,
+    pub(crate) pattern_ranges: ArenaMap<Id<Pattern>, TextRange>,
+    // the type annotations of locals, constants and parameters,
+    // keyed by the value which is checked against them
+    pub(crate) annotation_ranges: ArenaMap<Id<Expr>, TextRange>,
+    // the names of locals, for those which have one in the source
+    pub(crate) local_def_ranges: ArenaMap<Id<LocalDef>, TextRange>,
+    pub(crate) function_bodies: FxHashMap<Name, Id<Expr>>,
+    // only functions with at least one default value are in here
+    pub(crate) param_defaults: FxHashMap<Name, Vec<Option<Id<Expr>>>>,
+    pub(crate) consts: FxHashMap<Name, Const>,
     other_module_references: FxHashSet<Fqn>,
     symbol_map: FxHashMap<ast::Ident, Symbol>,
+    tys: FxHashSet<ast::Ident>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Expr {
     Missing,
-    IntLiteral(u32),
+    IntLiteral(u64),
+    BoolLiteral(bool),
     StringLiteral(String),
-    Binary { lhs: Id<Expr>, rhs: Id<Expr>, operator: BinaryOperator },
-    Block { statements: Vec<Id<Statement>>, tail_expr: Option<Id<Expr>> },
+    CharLiteral(char),
+    /// The only value of type `unit`, written `unit`.
+    Unit,
+    Binary {
+        lhs: Id<Expr>,
+        rhs: Id<Expr>,
+        operator: BinaryOperator,
+    },
+    Unary {
+        expr: Id<Expr>,
+        operator: UnaryOperator,
+    },
+    Cast {
+        expr: Id<Expr>,
+        ty: Ty,
+    },
+    Tuple(Vec<Id<Expr>>),
+    Field {
+        tuple: Id<Expr>,
+        idx: u32,
+    },
+    Array(Vec<Id<Expr>>),
+    Index {
+        array: Id<Expr>,
+        index: Id<Expr>,
+    },
+    Block {
+        statements: Vec<Id<Statement>>,
+        tail_expr: Option<Id<Expr>>,
+    },
     Local(Id<LocalDef>),
-    Param { idx: u32 },
-    Call { path: Path, args: Vec<Id<Expr>> },
+    Param {
+        idx: u32,
+    },
+    Const(Name),
+    Loop {
+        label: Option<Name>,
+        body: Id<Expr>,
+    },
+    /// Runs `body` once for every number from `start` up to (but not including) `end`,
+    /// with `local_def` bound to that number.
+    For {
+        label: Option<Name>,
+        local_def: Id<LocalDef>,
+        start: Id<Expr>,
+        end: Id<Expr>,
+        body: Id<Expr>,
+    },
+    /// `'label: { ... }`, a block which `break 'label` can leave early.
+    LabeledBlock {
+        label: Name,
+        body: Id<Expr>,
+    },
+    Call {
+        path: Path,
+        args: Vec<Id<Expr>>,
+    },
+    /// `Some x`, `None`, `Ok x` or `Err e`,
+    /// where `value` is present exactly when the variant has a field.
+    Variant {
+        variant: Variant,
+        value: Option<Id<Expr>>,
+    },
+    /// Evaluates the body of the first arm whose pattern matches `value`.
+    Match {
+        value: Id<Expr>,
+        arms: Vec<MatchArm>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Id<Pattern>,
+    pub body: Id<Expr>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Missing,
+    /// `_`, which matches anything without binding it.
+    Wildcard,
+    Bind(Id<LocalDef>),
+    IntLiteral {
+        value: u64,
+        is_negated: bool,
+    },
+    BoolLiteral(bool),
+    StringLiteral(String),
+    CharLiteral(char),
+    Tuple(Vec<Id<Pattern>>),
+    /// `Some x` or `None`,
+    /// where `field` is present exactly when the variant has a field.
+    Variant {
+        variant: Variant,
+        field: Option<Id<Pattern>>,
+    },
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Path {
     ThisModule(Name),
     OtherModule(Fqn),
+    Builtin(Builtin),
+}
+
+impl Path {
+    /// Where the parameters of the function are defined,
+    /// which builtins and native functions have nowhere.
+    pub fn params_location(
+        self,
+        index: &Index,
+        world_index: &WorldIndex,
+    ) -> Option<ParamsLocation> {
+        match self {
+            Self::ThisModule(name) => {
+                Some(ParamsLocation { module: None, range: index.range_info(name).params })
+            }
+            Self::OtherModule(fqn) if world_index.is_native_function(fqn) => None,
+            Self::OtherModule(fqn) => Some(ParamsLocation {
+                module: Some(fqn.module),
+                range: world_index.range_info(fqn).params,
+            }),
+            Self::Builtin(_) => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Statement {
     Expr(Id<Expr>),
     LocalDef(Id<LocalDef>),
+    /// `let (a, b) = value;`,
+    /// which binds every element of the tuple `value` to a local of its own
+    /// (or to nothing, for `_`).
+    Destructure {
+        value: Id<Expr>,
+        ty: Option<Ty>,
+        local_defs: Vec<Option<Id<LocalDef>>>,
+    },
+    Assign {
+        local_def: Id<LocalDef>,
+        value: Id<Expr>,
+    },
+    /// Leaves the loop or block named by `label`,
+    /// or the innermost loop without one.
+    Break {
+        label: Option<Name>,
+        value: Option<Id<Expr>>,
+    },
+    Continue {
+        label: Option<Name>,
+    },
+    Return {
+        value: Option<Id<Expr>>,
+    },
+    /// `defer expr;`, which evaluates `expr` once the enclosing block is left,
+    /// after anything deferred later on in it.
+    Defer(Id<Expr>),
+}
+
+/// A top-level `const`,
+/// whose value type checking works out before the program runs.
+#[derive(Clone)]
+pub struct Const {
+    pub value: Id<Expr>,
+    /// The type the constant was annotated with, if any.
+    pub ty: Option<Ty>,
+    pub ast: ast::Const,
 }
 
 #[derive(Clone)]
 pub struct LocalDef {
     pub value: Id<Expr>,
-    pub ast: ast::LocalDef,
+    pub is_mutable: bool,
+    /// The type the local was annotated with, if any.
+    pub ty: Option<Ty>,
+    pub ast: LocalDefAst,
+}
+
+/// Where a local was defined:
+/// by a `let`, as one of the names in a `let` which destructures a tuple
+/// (in which case its value is the whole tuple),
+/// as the variable of a `for` loop
+/// (in which case its value is the start of the range),
+/// by a pattern in a `match` arm
+/// (in which case its value is the matched value),
+/// or by the match a `?` is lowered to, which has no name in the source.
+#[derive(Clone, Copy)]
+pub enum LocalDefAst {
+    Let(ast::LocalDef),
+    Destructured { local_def: ast::LocalDef, name: ast::Ident },
+    For(ast::For),
+    MatchArm { arm: ast::MatchArm, name: ast::Ident },
+    Try(ast::TryExpr),
+}
+
+impl LocalDefAst {
+    pub fn name(self, tree: &SyntaxTree) -> Option<ast::Ident> {
+        match self {
+            Self::Let(local_def) => local_def.name(tree),
+            Self::Destructured { name, .. } | Self::MatchArm { name, .. } => Some(name),
+            Self::For(for_expr) => for_expr.variable(tree),
+            Self::Try(_) => None,
+        }
+    }
+
+    pub fn range(self, tree: &SyntaxTree) -> TextRange {
+        match self {
+            Self::Let(local_def) | Self::Destructured { local_def, .. } => local_def.range(tree),
+            Self::For(for_expr) => for_expr.range(tree),
+            Self::MatchArm { arm, .. } => arm.range(tree),
+            Self::Try(try_expr) => try_expr.range(tree),
+        }
+    }
 }
 
 impl std::fmt::Debug for LocalDef {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("LocalDef").field("value", &self.value).finish()
+        f.debug_struct("LocalDef")
+            .field("value", &self.value)
+            .field("is_mutable", &self.is_mutable)
+            .field("ty", &self.ty)
+            .finish()
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Hash)]
 pub enum BinaryOperator {
     Add,
     Sub,
     Mul,
     Div,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum UnaryOperator {
+    Neg,
+    Not,
+}
+
+impl BinaryOperator {
+    pub fn is_comparison(self) -> bool {
+        matches!(self, Self::Eq | Self::NotEq | Self::Lt | Self::LtEq | Self::Gt | Self::GtEq)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -70,11 +302,108 @@ pub struct LoweringDiagnostic {
 #[derive(Debug, Clone, PartialEq)]
 pub enum LoweringDiagnosticKind {
     OutOfRangeIntLiteral,
-    UndefinedLocal { name: Key },
-    UndefinedModule { name: Key },
-    MismatchedArgCount { name: Key, expected: u32, got: u32 },
-    CalledLocal { name: Key },
+    UndefinedLocal {
+        name: Key,
+    },
+    UndefinedModule {
+        name: Key,
+    },
+    UndefinedFunction {
+        module: Key,
+        function: Key,
+    },
+    /// `receiver.name` where there’s no function `name` to call with `receiver`.
+    UndefinedMethod {
+        name: Key,
+    },
+    /// A function in another module which isn’t `pub`.
+    /// `definition` is the range of the function in that module.
+    PrivateFunction {
+        module: Key,
+        function: Key,
+        definition: TextRange,
+    },
+    /// `params` is where the parameters of the function are defined,
+    /// if it’s defined anywhere.
+    MismatchedArgCount {
+        name: Key,
+        expected: u32,
+        got: u32,
+        params: Option<ParamsLocation>,
+    },
+    /// A variable, parameter or constant called with arguments.
+    /// `definition` is the range of its name where it’s defined.
+    CalledLocal {
+        name: Key,
+        definition: Option<TextRange>,
+    },
+    /// `let x = a x + 1` where `a` is a variable:
+    /// rather than trying to call `a`,
+    /// the user most likely forgot the `;` after it.
+    /// `statement_end` is where the parser found the `;` missing,
+    /// which this diagnostic explains better.
+    MissingSemicolonAfterLocal {
+        name: Key,
+        statement_end: TextSize,
+    },
     InvalidEscape,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    UndefinedLabel {
+        name: Key,
+    },
+    /// `continue 'label` where `'label` names a block rather than a loop.
+    ContinueToBlock {
+        name: Key,
+    },
+    ReturnOutsideFunction,
+    /// A `break`, `continue`, `return` or `?` which would leave a deferred expression,
+    /// which runs while the block it’s in is already being left.
+    JumpOutOfDefer,
+    /// Only variables declared with `let mut` can be assigned to;
+    /// this includes parameters, which are never mutable.
+    AssignToImmutable {
+        name: Key,
+        is_param: bool,
+    },
+    InvalidAssignTarget,
+    UndefinedTy {
+        name: Key,
+    },
+    MismatchedTyArgCount {
+        name: Key,
+        expected: u32,
+        got: u32,
+    },
+    UndefinedVariant {
+        name: Key,
+    },
+    /// A pattern like `Some` without a pattern for the field, or `None x` with one.
+    MismatchedVariantPattern {
+        variant: Variant,
+    },
+    /// `?` in a function which doesn’t return an `Option` or a `Result`,
+    /// so there’s nothing to return early with.
+    TryInInfallibleFunction,
+    /// Type aliases are indexed along with functions,
+    /// so they can only be defined at the top level.
+    NestedTyAlias,
+    /// Imports are indexed along with functions,
+    /// so they can only be at the top level too.
+    NestedImport,
+    NestedConst,
+    ConstAlreadyDefined {
+        name: Key,
+    },
+    /// Part of the value of a constant which can’t be worked out before the program runs,
+    /// such as a call or a variable.
+    NonConstExpr,
+    /// A local defined with `let` which nothing refers to.
+    /// Names starting with `_` are left alone,
+    /// so that locals can be kept around on purpose.
+    UnusedLocal {
+        name: Key,
+    },
 }
 
 #[derive(Clone, Copy)]
@@ -82,7 +411,9 @@ pub enum Symbol {
     Local(Id<LocalDef>),
     Param(ast::Param),
     Function(Path),
+    Const(Name),
     Module(Name),
+    Variant(Variant),
     Unknown,
 }
 
@@ -95,17 +426,63 @@ pub fn lower(
 ) -> (Bodies, Vec<LoweringDiagnostic>) {
     let mut ctx = Ctx::new(index, world_index, interner, tree);
 
+    // like functions, constants can be used before they’re defined
+    for def in root.defs(tree) {
+        let ast::Def::Const(constant) = def else { continue };
+        let Some(name_token) = constant.name(tree) else { continue };
+        let name = ctx.interner.intern(name_token.text(tree));
+
+        if ctx.const_defs.contains_key(&name) || index.get_function(Name(name)).is_some() {
+            ctx.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::ConstAlreadyDefined { name },
+                range: constant.range(tree),
+            });
+            continue;
+        }
+
+        ctx.const_defs.insert(name, constant);
+    }
+
     for def in root.defs(tree) {
         match def {
             ast::Def::Function(function) => ctx.lower_function(function),
+            // indexing has already lowered the type
+            ast::Def::TyAlias(_) => {}
+            ast::Def::Import(import) => ctx.lower_import(import),
+            ast::Def::Const(constant) => ctx.lower_const(constant),
+        }
+    }
+
+    // nested functions inside of code which lowering gave up on
+    // never had their bodies lowered,
+    // but type checking expects a body for every function in the index
+    for &name in index.nested_functions.values() {
+        let is_extern = index.get_function(name).unwrap().is_extern;
+        if !is_extern && !ctx.bodies.function_bodies.contains_key(&name) {
+            let body = ctx.bodies.exprs.alloc(Expr::Missing);
+            ctx.bodies.function_bodies.insert(name, body);
         }
     }
 
+    ctx.report_unused_local_defs();
     ctx.bodies.shrink_to_fit();
 
+    if cfg!(debug_assertions) {
+        let errors = crate::validate(&ctx.bodies, index);
+        assert!(errors.is_empty(), "lowering produced invalid bodies: {:?}", errors);
+    }
+
     (ctx.bodies, ctx.diagnostics)
 }
 
+enum FunctionLookup<'a> {
+    Found(Path, Cow<'a, Function>),
+    // an import of a function which doesn’t exist,
+    // which has already been reported
+    BrokenImport,
+    NotFound,
+}
+
 struct Ctx<'a> {
     bodies: Bodies,
     index: &'a Index,
@@ -114,7 +491,33 @@ struct Ctx<'a> {
     tree: &'a SyntaxTree,
     diagnostics: Vec<LoweringDiagnostic>,
     scopes: Vec<FxHashMap<Key, Id<LocalDef>>>,
+    // the scopes of the functions the one being lowered is nested in,
+    // whose locals it can’t refer to
+    enclosing_scopes: Vec<FxHashMap<Key, Id<LocalDef>>>,
+    // locals which a nested function tried to refer to,
+    // which already get an error there and shouldn’t also be reported as unused
+    referenced_from_nested: FxHashSet<Id<LocalDef>>,
+    // functions defined in each enclosing block,
+    // which stay visible inside of the functions nested in them
+    function_scopes: Vec<FxHashMap<Key, Name>>,
     params: FxHashMap<Key, (u32, ast::Param)>,
+    loop_depth: u32,
+    // the labels of the loops and blocks around what’s being lowered,
+    // along with whether each names a loop
+    labels: Vec<(Key, bool)>,
+    // how many of `labels` are outside of the innermost `defer`
+    labels_outside_defer: usize,
+    in_function: bool,
+    in_defer: bool,
+    // the return type of the function being lowered,
+    // which decides what a `?` returns early with
+    return_ty: Option<Ty>,
+    // the type parameters of the function being lowered
+    ty_params: Vec<Name>,
+    // the value of the local definition being lowered
+    // if that definition isn’t terminated by a `;`
+    unterminated_local_def_value: Option<ast::Call>,
+    const_defs: FxHashMap<Key, ast::Const>,
 }
 
 impl<'a> Ctx<'a> {
@@ -128,11 +531,19 @@ impl<'a> Ctx<'a> {
             bodies: Bodies {
                 local_defs: Arena::new(),
                 statements: Arena::new(),
+                statement_ranges: ArenaMap::default(),
                 exprs: Arena::new(),
                 expr_ranges: ArenaMap::default(),
+                patterns: Arena::new(),
+                pattern_ranges: ArenaMap::default(),
+                annotation_ranges: ArenaMap::default(),
+                local_def_ranges: ArenaMap::default(),
                 function_bodies: FxHashMap::default(),
+                param_defaults: FxHashMap::default(),
+                consts: FxHashMap::default(),
                 other_module_references: FxHashSet::default(),
                 symbol_map: FxHashMap::default(),
+                tys: FxHashSet::default(),
             },
             index,
             world_index,
@@ -140,7 +551,168 @@ impl<'a> Ctx<'a> {
             tree,
             diagnostics: Vec::new(),
             scopes: vec![FxHashMap::default()],
+            enclosing_scopes: Vec::new(),
+            referenced_from_nested: FxHashSet::default(),
+            function_scopes: Vec::new(),
             params: FxHashMap::default(),
+            loop_depth: 0,
+            labels: Vec::new(),
+            labels_outside_defer: 0,
+            in_function: false,
+            in_defer: false,
+            return_ty: None,
+            ty_params: Vec::new(),
+            unterminated_local_def_value: None,
+            const_defs: FxHashMap::default(),
+        }
+    }
+
+    fn lower_import(&mut self, import: ast::Import) {
+        let (Some(module_name_token), Some(function_name_token)) =
+            (import.module_name(self.tree), import.function_name(self.tree))
+        else {
+            return;
+        };
+
+        let module_name = self.interner.intern(module_name_token.text(self.tree));
+        let function_name = self.interner.intern(function_name_token.text(self.tree));
+        let fqn = Fqn { module: Name(module_name), function: Name(function_name) };
+
+        match self.world_index.get_function(fqn) {
+            Ok(function) => {
+                self.check_visibility(fqn, function, function_name_token);
+                self.bodies.symbol_map.insert(module_name_token, Symbol::Module(fqn.module));
+                self.bodies
+                    .symbol_map
+                    .insert(function_name_token, Symbol::Function(Path::OtherModule(fqn)));
+            }
+
+            Err(GetFunctionError::UnknownModule) => {
+                self.diagnostics.push(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::UndefinedModule { name: module_name },
+                    range: module_name_token.range(self.tree),
+                });
+
+                self.bodies.symbol_map.insert(module_name_token, Symbol::Unknown);
+                self.bodies.symbol_map.insert(function_name_token, Symbol::Unknown);
+            }
+
+            Err(GetFunctionError::UnknownFunction) => {
+                self.diagnostics.push(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::UndefinedFunction {
+                        module: module_name,
+                        function: function_name,
+                    },
+                    range: function_name_token.range(self.tree),
+                });
+
+                self.bodies.symbol_map.insert(module_name_token, Symbol::Module(fqn.module));
+                self.bodies.symbol_map.insert(function_name_token, Symbol::Unknown);
+            }
+        }
+    }
+
+    fn lower_const(&mut self, constant: ast::Const) {
+        let Some(name_token) = constant.name(self.tree) else { return };
+        let name = self.interner.intern(name_token.text(self.tree));
+
+        // duplicates have already been reported
+        if self.const_defs.get(&name) != Some(&constant) {
+            return;
+        }
+
+        let ty = constant.ty(self.tree).map(|ty| self.lower_ty(Some(ty)));
+        let value = self.lower_expr(constant.value(self.tree));
+        self.check_const_expr(value);
+        if let Some(ty) = constant.ty(self.tree) {
+            self.bodies.annotation_ranges.insert(value, ty.range(self.tree));
+        }
+
+        self.bodies.consts.insert(Name(name), Const { value, ty, ast: constant });
+    }
+
+    // only literals, other constants and arithmetic on them
+    // can be worked out before the program runs
+    fn check_const_expr(&mut self, expr: Id<Expr>) {
+        match self.bodies[expr].clone() {
+            Expr::Missing
+            | Expr::IntLiteral(_)
+            | Expr::BoolLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::CharLiteral(_)
+            | Expr::Const(_) => {}
+            Expr::Binary {
+                lhs,
+                rhs,
+                operator:
+                    BinaryOperator::Add
+                    | BinaryOperator::Sub
+                    | BinaryOperator::Mul
+                    | BinaryOperator::Div,
+            } => {
+                self.check_const_expr(lhs);
+                self.check_const_expr(rhs);
+            }
+            Expr::Unary { expr, operator: UnaryOperator::Neg } => self.check_const_expr(expr),
+            Expr::Block { statements, tail_expr: Some(tail_expr) } if statements.is_empty() => {
+                self.check_const_expr(tail_expr)
+            }
+            _ => self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::NonConstExpr,
+                range: self.bodies.range_for_expr(expr),
+            }),
+        }
+    }
+
+    fn check_visibility(&mut self, fqn: Fqn, function: &Function, function_name: ast::Ident) {
+        if function.is_pub {
+            return;
+        }
+
+        self.diagnostics.push(LoweringDiagnostic {
+            kind: LoweringDiagnosticKind::PrivateFunction {
+                module: fqn.module.0,
+                function: fqn.function.0,
+                definition: self.world_index.range_info(fqn).whole,
+            },
+            range: function_name.range(self.tree),
+        });
+    }
+
+    fn report_unused_local_defs(&mut self) {
+        let used: FxHashSet<_> = self
+            .bodies
+            .exprs
+            .iter()
+            .filter_map(|(_, expr)| match expr {
+                Expr::Local(local_def) => Some(*local_def),
+                _ => None,
+            })
+            .collect();
+
+        for (id, local_def) in self.bodies.local_defs.iter() {
+            if used.contains(&id) || self.referenced_from_nested.contains(&id) {
+                continue;
+            }
+
+            let ident = match local_def.ast {
+                LocalDefAst::Let(local_def) => local_def.name(self.tree),
+                LocalDefAst::Destructured { name, .. } => Some(name),
+                LocalDefAst::For(_) | LocalDefAst::MatchArm { .. } | LocalDefAst::Try(_) => None,
+            };
+            let (Some(ident), Some(range)) = (ident, self.bodies.range_for_local_def(id)) else {
+                continue;
+            };
+            if ident.text(self.tree).starts_with('_') {
+                continue;
+            }
+
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::UnusedLocal {
+                    name: self.interner.intern(ident.text(self.tree)),
+                },
+                range,
+            });
         }
     }
 
@@ -151,14 +723,61 @@ impl<'a> Ctx<'a> {
         };
 
         // if we’ve already seen a function with this name,
+        // or it has the name of an import,
         // we ignore all other functions with that name
         //
         // we don’t have to worry about emitting a diagnostic here
         // because indexing already handles this
-        if self.bodies.function_bodies.contains_key(&name) {
+        if self.bodies.function_bodies.contains_key(&name) || self.index.get_import(name).is_some()
+        {
+            return;
+        }
+
+        // extern functions have no body to lower
+        if function.extern_kw(self.tree).is_some() {
             return;
         }
 
+        self.lower_param_defaults(function, name);
+        self.lower_params(function);
+
+        self.in_function = true;
+        self.return_ty = self.index.get_function(name).map(|function| function.return_ty.clone());
+        self.ty_params = self.ty_params_of(name);
+        let body = self.lower_expr(function.body(self.tree));
+        self.in_function = false;
+        self.return_ty = None;
+        self.ty_params.clear();
+        self.params.clear();
+        self.bodies.function_bodies.insert(name, body);
+    }
+
+    // defaults are worked out before the function is entered,
+    // so they can’t refer to any of its parameters
+    fn lower_param_defaults(&mut self, function: ast::Function, name: Name) {
+        let Some(param_list) = function.param_list(self.tree) else { return };
+
+        let in_function = std::mem::take(&mut self.in_function);
+
+        let defaults: Vec<_> = param_list
+            .params(self.tree)
+            .map(|param| {
+                let value = self.lower_expr(Some(param.default_value(self.tree)?));
+                if let Some(ty) = param.ty(self.tree) {
+                    self.bodies.annotation_ranges.insert(value, ty.range(self.tree));
+                }
+                Some(value)
+            })
+            .collect();
+
+        self.in_function = in_function;
+
+        if defaults.iter().any(Option::is_some) {
+            self.bodies.param_defaults.insert(name, defaults);
+        }
+    }
+
+    fn lower_params(&mut self, function: ast::Function) {
         if let Some(param_list) = function.param_list(self.tree) {
             for (idx, param) in param_list.params(self.tree).enumerate() {
                 if let Some(ident) = param.name(self.tree) {
@@ -167,10 +786,6 @@ impl<'a> Ctx<'a> {
                 }
             }
         }
-
-        let body = self.lower_expr(function.body(self.tree));
-        self.params.clear();
-        self.bodies.function_bodies.insert(name, body);
     }
 
     fn lower_statement(&mut self, statement: ast::Statement) -> Statement {
@@ -180,21 +795,249 @@ impl<'a> Ctx<'a> {
                 let expr = self.lower_expr(expr_statement.expr(self.tree));
                 Statement::Expr(expr)
             }
+            ast::Statement::Assign(assign) => self.lower_assign(assign),
+            ast::Statement::Break(break_statement) => {
+                let label = match break_statement.label(self.tree) {
+                    Some(label) => Some(self.lower_jump_label(label, false)),
+                    None => {
+                        if self.loop_depth == 0 {
+                            let kind = if self.in_defer {
+                                LoweringDiagnosticKind::JumpOutOfDefer
+                            } else {
+                                LoweringDiagnosticKind::BreakOutsideLoop
+                            };
+                            self.diagnostics.push(LoweringDiagnostic {
+                                kind,
+                                range: break_statement.range(self.tree),
+                            });
+                        }
+                        None
+                    }
+                };
+
+                let value =
+                    break_statement.value(self.tree).map(|value| self.lower_expr(Some(value)));
+                Statement::Break { label, value }
+            }
+            ast::Statement::Continue(continue_statement) => {
+                if let Some(label) = continue_statement.label(self.tree) {
+                    let label = self.lower_jump_label(label, true);
+                    return Statement::Continue { label: Some(label) };
+                }
+
+                if self.loop_depth == 0 {
+                    let kind = if self.in_defer {
+                        LoweringDiagnosticKind::JumpOutOfDefer
+                    } else {
+                        LoweringDiagnosticKind::ContinueOutsideLoop
+                    };
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind,
+                        range: continue_statement.range(self.tree),
+                    });
+                }
+                Statement::Continue { label: None }
+            }
+            ast::Statement::Return(return_statement) => {
+                let kind = if self.in_defer {
+                    Some(LoweringDiagnosticKind::JumpOutOfDefer)
+                } else if !self.in_function {
+                    Some(LoweringDiagnosticKind::ReturnOutsideFunction)
+                } else {
+                    None
+                };
+
+                if let Some(kind) = kind {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind,
+                        range: return_statement.range(self.tree),
+                    });
+                }
+
+                let value =
+                    return_statement.value(self.tree).map(|value| self.lower_expr(Some(value)));
+                Statement::Return { value }
+            }
+            ast::Statement::Defer(defer) => {
+                // loops around the `defer` can’t be jumped out of from inside it
+                let loop_depth = std::mem::take(&mut self.loop_depth);
+                let labels_outside_defer =
+                    std::mem::replace(&mut self.labels_outside_defer, self.labels.len());
+                let in_defer = std::mem::replace(&mut self.in_defer, true);
+                let expr = self.lower_expr(defer.expr(self.tree));
+                self.loop_depth = loop_depth;
+                self.labels_outside_defer = labels_outside_defer;
+                self.in_defer = in_defer;
+
+                Statement::Defer(expr)
+            }
+        }
+    }
+
+    /// Checks that `label` names a loop or block around the jump to it,
+    /// which for a `continue` has to be a loop.
+    fn lower_jump_label(&mut self, label: ast::Label, is_continue: bool) -> Name {
+        let name = self.interner.intern(label.name(self.tree));
+
+        let kind = match self.labels.iter().rposition(|(label_name, _)| *label_name == name) {
+            None => Some(LoweringDiagnosticKind::UndefinedLabel { name }),
+            Some(idx) if idx < self.labels_outside_defer => {
+                Some(LoweringDiagnosticKind::JumpOutOfDefer)
+            }
+            Some(idx) if is_continue && !self.labels[idx].1 => {
+                Some(LoweringDiagnosticKind::ContinueToBlock { name })
+            }
+            Some(_) => None,
+        };
+
+        if let Some(kind) = kind {
+            self.diagnostics.push(LoweringDiagnostic { kind, range: label.range(self.tree) });
         }
+
+        Name(name)
+    }
+
+    // makes the label visible to the jumps in whatever `f` lowers
+    fn with_label<T>(
+        &mut self,
+        label: Option<ast::Label>,
+        is_loop: bool,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> (Option<Name>, T) {
+        let Some(label) = label else {
+            return (None, f(self));
+        };
+
+        let name = self.interner.intern(label.name(self.tree));
+        self.labels.push((name, is_loop));
+        let result = f(self);
+        self.labels.pop();
+
+        (Some(Name(name)), result)
     }
 
     fn lower_local_def(&mut self, local_def: ast::LocalDef) -> Statement {
+        if local_def.semicolon(self.tree).is_none() {
+            if let Some(ast::Expr::Call(call)) = local_def.value(self.tree) {
+                self.unterminated_local_def_value = Some(call);
+            }
+        }
+
         let value = self.lower_expr(local_def.value(self.tree));
-        let id = self.bodies.local_defs.alloc(LocalDef { value, ast: local_def });
+        self.unterminated_local_def_value = None;
+        let is_mutable = local_def.mut_kw(self.tree).is_some();
+        let ty = local_def.ty(self.tree).map(|ty| self.lower_ty(Some(ty)));
+        if let Some(ty) = local_def.ty(self.tree) {
+            self.bodies.annotation_ranges.insert(value, ty.range(self.tree));
+        }
+
+        if let Some(tuple_pattern) = local_def.tuple_pattern(self.tree) {
+            // the parser only allows plain variables in here
+            let names = tuple_pattern.patterns(self.tree).filter_map(|pattern| match pattern {
+                ast::Pattern::Binding(binding_pattern) => binding_pattern.name(self.tree),
+                _ => None,
+            });
+
+            let local_defs = names
+                .map(|ident| {
+                    if ident.text(self.tree) == "_" {
+                        return None;
+                    }
+
+                    let id = self.bodies.local_defs.alloc(LocalDef {
+                        value,
+                        is_mutable,
+                        ty: None,
+                        ast: LocalDefAst::Destructured { local_def, name: ident },
+                    });
+                    self.bodies.local_def_ranges.insert(id, ident.range(self.tree));
+
+                    let name = self.interner.intern(ident.text(self.tree));
+                    self.insert_into_current_scope(name, id);
 
-        if let Some(ident) = local_def.name(self.tree) {
+                    Some(id)
+                })
+                .collect();
+
+            return Statement::Destructure { value, ty, local_defs };
+        }
+
+        let id = self.bodies.local_defs.alloc(LocalDef {
+            value,
+            is_mutable,
+            ty,
+            ast: LocalDefAst::Let(local_def),
+        });
+
+        // `let _ = ...` evaluates the value without introducing a local
+        if let Some(ident) = local_def.name(self.tree).filter(|i| i.text(self.tree) != "_") {
             let name = self.interner.intern(ident.text(self.tree));
             self.insert_into_current_scope(name, id);
+            self.bodies.local_def_ranges.insert(id, ident.range(self.tree));
         }
 
         Statement::LocalDef(id)
     }
 
+    // an assignment which can’t be carried out
+    // is lowered to just its value, so that the value is still checked
+    fn lower_assign(&mut self, assign: ast::Assign) -> Statement {
+        let value = self.lower_expr(assign.value(self.tree));
+
+        let target = match assign.target(self.tree) {
+            Some(target) => target,
+            None => return Statement::Expr(value),
+        };
+
+        let ident = match target {
+            ast::Expr::Call(call)
+                if call.nested_name(self.tree).is_none() && call.arg_list(self.tree).is_none() =>
+            {
+                call.top_level_name(self.tree)
+            }
+            _ => {
+                self.diagnostics.push(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::InvalidAssignTarget,
+                    range: target.range(self.tree),
+                });
+                return Statement::Expr(value);
+            }
+        };
+
+        let ident = match ident {
+            Some(ident) => ident,
+            None => return Statement::Expr(value),
+        };
+
+        let name = self.interner.intern(ident.text(self.tree));
+
+        if let Some(local_def) = self.look_up_in_current_scope(name) {
+            self.bodies.symbol_map.insert(ident, Symbol::Local(local_def));
+
+            if !self.bodies.local_defs[local_def].is_mutable {
+                self.diagnostics.push(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::AssignToImmutable { name, is_param: false },
+                    range: ident.range(self.tree),
+                });
+            }
+
+            return Statement::Assign { local_def, value };
+        }
+
+        let kind = if let Some((_, ast)) = self.look_up_param(name) {
+            self.bodies.symbol_map.insert(ident, Symbol::Param(ast));
+            LoweringDiagnosticKind::AssignToImmutable { name, is_param: true }
+        } else {
+            self.bodies.symbol_map.insert(ident, Symbol::Unknown);
+            self.note_reference_from_nested(name);
+            LoweringDiagnosticKind::UndefinedLocal { name }
+        };
+
+        self.diagnostics.push(LoweringDiagnostic { kind, range: ident.range(self.tree) });
+
+        Statement::Expr(value)
+    }
+
     fn lower_expr(&mut self, expr: Option<ast::Expr>) -> Id<Expr> {
         let expr_ast = match expr {
             Some(expr) => expr,
@@ -205,10 +1048,26 @@ impl<'a> Ctx<'a> {
 
         let expr = match expr_ast {
             ast::Expr::Binary(binary_expr) => self.lower_binary_expr(binary_expr),
-            ast::Expr::Block(block) => self.lower_block(block),
+            ast::Expr::Unary(unary_expr) => self.lower_unary_expr(unary_expr),
+            ast::Expr::Cast(cast_expr) => self.lower_cast_expr(cast_expr),
+            ast::Expr::Tuple(tuple_expr) => self.lower_tuple_expr(tuple_expr),
+            ast::Expr::Field(field_expr) => self.lower_field_expr(field_expr),
+            ast::Expr::MethodCall(method_call) => self.lower_method_call(method_call),
+            ast::Expr::Array(array_expr) => self.lower_array_expr(array_expr),
+            ast::Expr::Index(index_expr) => self.lower_index_expr(index_expr),
+            ast::Expr::Try(try_expr) => self.lower_try_expr(try_expr),
+            ast::Expr::Block(block) => match block.label(self.tree) {
+                Some(label) => self.lower_labeled_block(block, label, range),
+                None => self.lower_block(block),
+            },
             ast::Expr::Call(call) => self.lower_local_or_call(call),
             ast::Expr::IntLiteral(int_literal) => self.lower_int_literal(int_literal),
+            ast::Expr::BoolLiteral(bool_literal) => self.lower_bool_literal(bool_literal),
             ast::Expr::StringLiteral(string_literal) => self.lower_string_literal(string_literal),
+            ast::Expr::CharLiteral(char_literal) => self.lower_char_literal(char_literal),
+            ast::Expr::Loop(loop_expr) => self.lower_loop(loop_expr),
+            ast::Expr::For(for_expr) => self.lower_for(for_expr),
+            ast::Expr::Match(match_expr) => self.lower_match(match_expr),
         };
 
         let id = self.bodies.exprs.alloc(expr);
@@ -226,702 +1085,3591 @@ impl<'a> Ctx<'a> {
             Some(ast::BinaryOperator::Sub(_)) => BinaryOperator::Sub,
             Some(ast::BinaryOperator::Mul(_)) => BinaryOperator::Mul,
             Some(ast::BinaryOperator::Div(_)) => BinaryOperator::Div,
+            Some(ast::BinaryOperator::Eq(_)) => BinaryOperator::Eq,
+            Some(ast::BinaryOperator::NotEq(_)) => BinaryOperator::NotEq,
+            Some(ast::BinaryOperator::Lt(_)) => BinaryOperator::Lt,
+            Some(ast::BinaryOperator::LtEq(_)) => BinaryOperator::LtEq,
+            Some(ast::BinaryOperator::Gt(_)) => BinaryOperator::Gt,
+            Some(ast::BinaryOperator::GtEq(_)) => BinaryOperator::GtEq,
+            Some(ast::BinaryOperator::And(_)) => BinaryOperator::And,
+            Some(ast::BinaryOperator::Or(_)) => BinaryOperator::Or,
             None => return Expr::Missing,
         };
 
         Expr::Binary { lhs, rhs, operator }
     }
 
-    fn lower_block(&mut self, block: ast::Block) -> Expr {
-        self.create_new_child_scope();
-
-        let mut statements = Vec::new();
+    fn lower_unary_expr(&mut self, unary_expr: ast::UnaryExpr) -> Expr {
+        let expr = self.lower_expr(unary_expr.expr(self.tree));
 
-        for statement in block.statements(self.tree) {
-            let statement = self.lower_statement(statement);
-            statements.push(self.bodies.statements.alloc(statement));
-        }
+        let operator = match unary_expr.operator(self.tree) {
+            Some(ast::UnaryOperator::Neg(_)) => UnaryOperator::Neg,
+            Some(ast::UnaryOperator::Not(_)) => UnaryOperator::Not,
+            None => return Expr::Missing,
+        };
 
-        let tail_expr =
-            block.tail_expr(self.tree).map(|tail_expr| self.lower_expr(Some(tail_expr)));
+        Expr::Unary { expr, operator }
+    }
 
-        self.destroy_current_scope();
+    fn lower_cast_expr(&mut self, cast_expr: ast::CastExpr) -> Expr {
+        let expr = self.lower_expr(cast_expr.expr(self.tree));
+        let ty = self.lower_ty(cast_expr.ty(self.tree));
 
-        Expr::Block { statements, tail_expr }
+        Expr::Cast { expr, ty }
     }
 
-    fn lower_local_or_call(&mut self, call: ast::Call) -> Expr {
-        let ident = match call.top_level_name(self.tree) {
-            Some(ident) => ident,
-            None => return Expr::Missing,
-        };
+    fn lower_tuple_expr(&mut self, tuple_expr: ast::TupleExpr) -> Expr {
+        let elements = tuple_expr.elements(self.tree).map(|element| self.lower_expr(Some(element)));
+        Expr::Tuple(elements.collect())
+    }
 
-        if let Some(function_name_token) = call.nested_name(self.tree) {
-            let module_name_token = ident;
+    fn lower_field_expr(&mut self, field_expr: ast::FieldExpr) -> Expr {
+        let tuple = self.lower_expr(field_expr.tuple(self.tree));
 
-            let module_name = self.interner.intern(module_name_token.text(self.tree));
-            let function_name = self.interner.intern(function_name_token.text(self.tree));
+        // validation has already complained about indices that aren’t plain numbers
+        let idx = field_expr.index(self.tree).and_then(|idx| idx.text(self.tree).parse().ok());
+        let Some(idx) = idx else { return Expr::Missing };
 
-            let fqn = Fqn { module: Name(module_name), function: Name(function_name) };
+        Expr::Field { tuple, idx }
+    }
 
-            match self.world_index.get_function(fqn) {
-                Ok(function) => {
-                    let path = Path::OtherModule(fqn);
+    fn lower_array_expr(&mut self, array_expr: ast::ArrayExpr) -> Expr {
+        let elements = array_expr.elements(self.tree).map(|element| self.lower_expr(Some(element)));
+        Expr::Array(elements.collect())
+    }
 
-                    self.bodies.other_module_references.insert(fqn);
+    fn lower_index_expr(&mut self, index_expr: ast::IndexExpr) -> Expr {
+        let array = self.lower_expr(index_expr.array(self.tree));
+        let index = self.lower_expr(index_expr.index(self.tree));
 
-                    self.bodies
-                        .symbol_map
-                        .insert(module_name_token, Symbol::Module(Name(module_name)));
-                    self.bodies.symbol_map.insert(function_name_token, Symbol::Function(path));
+        Expr::Index { array, index }
+    }
 
-                    return self.lower_call(call, function, path, function_name_token);
-                }
+    // `value?` is lowered to a match which returns early from the function
+    // with anything that isn’t a success:
+    // `match value { Some x -> x; None -> { return None; } }` for options
+    // and `match value { Ok x -> x; Err e -> { return Err e; } }` for results
+    fn lower_try_expr(&mut self, try_expr: ast::TryExpr) -> Expr {
+        let value = self.lower_expr(try_expr.expr(self.tree));
+        let range = try_expr.range(self.tree);
+
+        if self.in_defer {
+            self.diagnostics
+                .push(LoweringDiagnostic { kind: LoweringDiagnosticKind::JumpOutOfDefer, range });
+        }
 
-                Err(GetFunctionError::UnknownModule) => {
+        let (success, failure) = match &self.return_ty {
+            Some(Ty::Option(_)) => (Variant::Some, Variant::None),
+            Some(Ty::Result { .. }) => (Variant::Ok, Variant::Err),
+            return_ty => {
+                // an unknown return type has already been reported
+                if return_ty != &Some(Ty::Unknown) {
                     self.diagnostics.push(LoweringDiagnostic {
-                        kind: LoweringDiagnosticKind::UndefinedModule { name: module_name },
-                        range: module_name_token.range(self.tree),
+                        kind: LoweringDiagnosticKind::TryInInfallibleFunction,
+                        range,
                     });
+                }
 
-                    self.bodies.symbol_map.insert(module_name_token, Symbol::Unknown);
-                    self.bodies.symbol_map.insert(function_name_token, Symbol::Unknown);
+                // the operand is still checked,
+                // but what the `?` evaluates to is left unknown
+                let statement = self.alloc_statement(Statement::Expr(value), range);
+                let tail_expr = self.bodies.exprs.alloc(Expr::Missing);
+                return Expr::Block { statements: vec![statement], tail_expr: Some(tail_expr) };
+            }
+        };
 
-                    return Expr::Missing;
-                }
+        let success_arm = {
+            let local_def = self.alloc_try_local_def(try_expr, value);
+            let field = self.alloc_synthesized_pattern(Pattern::Bind(local_def), range);
+            let pattern = Pattern::Variant { variant: success, field: Some(field) };
+            let pattern = self.alloc_synthesized_pattern(pattern, range);
+            let body = self.alloc_synthesized_expr(Expr::Local(local_def), range);
 
-                Err(GetFunctionError::UnknownFunction) => {
-                    self.diagnostics.push(LoweringDiagnostic {
-                        kind: LoweringDiagnosticKind::UndefinedLocal { name: function_name },
-                        range: function_name_token.range(self.tree),
-                    });
+            MatchArm { pattern, body }
+        };
 
-                    self.bodies
-                        .symbol_map
-                        .insert(module_name_token, Symbol::Module(Name(module_name)));
-                    self.bodies.symbol_map.insert(function_name_token, Symbol::Unknown);
+        let failure_arm = {
+            let (field, returned_value) = if failure.has_field() {
+                let local_def = self.alloc_try_local_def(try_expr, value);
+                let field = self.alloc_synthesized_pattern(Pattern::Bind(local_def), range);
+                let returned_value = self.alloc_synthesized_expr(Expr::Local(local_def), range);
+                (Some(field), Some(returned_value))
+            } else {
+                (None, None)
+            };
 
-                    return Expr::Missing;
-                }
-            }
-        }
+            let pattern = Pattern::Variant { variant: failure, field };
+            let pattern = self.alloc_synthesized_pattern(pattern, range);
 
-        let name = self.interner.intern(ident.text(self.tree));
+            let returned = Expr::Variant { variant: failure, value: returned_value };
+            let returned = self.alloc_synthesized_expr(returned, range);
+            let statement =
+                self.alloc_statement(Statement::Return { value: Some(returned) }, range);
+            let body = Expr::Block { statements: vec![statement], tail_expr: None };
+            let body = self.alloc_synthesized_expr(body, range);
 
-        if let Some(def) = self.look_up_in_current_scope(name) {
-            check_args_for_local(call, ident, self.tree, name, &mut self.diagnostics);
-            self.bodies.symbol_map.insert(ident, Symbol::Local(def));
-            return Expr::Local(def);
-        }
+            MatchArm { pattern, body }
+        };
 
-        if let Some((idx, ast)) = self.look_up_param(name) {
-            check_args_for_local(call, ident, self.tree, name, &mut self.diagnostics);
-            self.bodies.symbol_map.insert(ident, Symbol::Param(ast));
-            return Expr::Param { idx };
-        }
+        Expr::Match { value, arms: vec![success_arm, failure_arm] }
+    }
 
-        let name = Name(name);
-        if let Some(function) = self.index.get_function(name) {
-            let path = Path::ThisModule(name);
-            self.bodies.symbol_map.insert(ident, Symbol::Function(path));
-            return self.lower_call(call, function, path, ident);
-        }
+    fn alloc_try_local_def(&mut self, try_expr: ast::TryExpr, value: Id<Expr>) -> Id<LocalDef> {
+        self.bodies.local_defs.alloc(LocalDef {
+            value,
+            is_mutable: false,
+            ty: None,
+            ast: LocalDefAst::Try(try_expr),
+        })
+    }
 
-        self.diagnostics.push(LoweringDiagnostic {
-            kind: LoweringDiagnosticKind::UndefinedLocal { name: name.0 },
-            range: ident.range(self.tree),
-        });
+    fn alloc_synthesized_expr(&mut self, expr: Expr, range: TextRange) -> Id<Expr> {
+        let id = self.bodies.exprs.alloc(expr);
+        self.bodies.expr_ranges.insert(id, range);
+        id
+    }
 
-        self.bodies.symbol_map.insert(ident, Symbol::Unknown);
+    fn alloc_statement(&mut self, statement: Statement, range: TextRange) -> Id<Statement> {
+        let id = self.bodies.statements.alloc(statement);
+        self.bodies.statement_ranges.insert(id, range);
+        id
+    }
 
-        return Expr::Missing;
+    fn alloc_synthesized_pattern(&mut self, pattern: Pattern, range: TextRange) -> Id<Pattern> {
+        let id = self.bodies.patterns.alloc(pattern);
+        self.bodies.pattern_ranges.insert(id, range);
+        id
+    }
 
-        fn check_args_for_local(
-            call: ast::Call,
-            ident: ast::Ident,
-            tree: &SyntaxTree,
-            name: Key,
-            diagnostics: &mut Vec<LoweringDiagnostic>,
-        ) {
-            if let Some(arg_list) = call.arg_list(tree) {
-                if arg_list.args(tree).count() != 0 {
-                    diagnostics.push(LoweringDiagnostic {
-                        kind: LoweringDiagnosticKind::CalledLocal { name },
-                        range: ident.range(tree),
-                    });
-                }
-            }
+    fn lower_ty(&mut self, ty: Option<ast::Ty>) -> Ty {
+        let Some(ty) = ty else { return Ty::Unknown };
+
+        if ty.l_paren(self.tree).is_some() {
+            let elements = ty.elements(self.tree).map(|element| self.lower_ty(Some(element)));
+            return Ty::Tuple(elements.collect());
         }
-    }
 
-    fn lower_call(
-        &mut self,
-        call: ast::Call,
-        function: &Function,
-        path: Path,
-        ident: ast::Ident,
-    ) -> Expr {
-        let arg_list = call.arg_list(self.tree);
+        if ty.l_bracket(self.tree).is_some() {
+            let element = self.lower_ty(ty.elements(self.tree).next());
+            return Ty::Array(Box::new(element));
+        }
 
-        let expected = function.params.len() as u32;
-        let got = match &arg_list {
-            Some(al) => al.args(self.tree).count() as u32,
-            None => 0,
-        };
+        let Some(ident) = ty.name(self.tree) else { return Ty::Unknown };
+        let name = Name(self.interner.intern(ident.text(self.tree)));
 
-        if expected != got {
-            let name = match path {
-                Path::ThisModule(function) => function.0,
-                Path::OtherModule(fqn) => fqn.function.0,
+        let args: Vec<_> = match ty.arg_list(self.tree) {
+            Some(arg_list) => arg_list.tys(self.tree).map(|arg| self.lower_ty(Some(arg))).collect(),
+            None => Vec::new(),
+        };
+        let got = args.len() as u32;
+
+        let (lowered, expected) = if let Some(idx) = self.ty_params.iter().position(|p| *p == name)
+        {
+            (Ty::Param { idx: idx as u32, name: ident.text(self.tree).to_string() }, 0)
+        } else if let Some(generic_ty) = GenericTy::from_name(ident.text(self.tree)) {
+            (generic_ty.apply(args), generic_ty.arity())
+        } else if let Some(ty) = self.world_index.get_ty(name) {
+            (ty, 0)
+        } else if let Some(ty) = self.index.get_ty_alias(name) {
+            // an alias which refers to itself has already been reported
+            let ty = match ty {
+                Ty::Unknown => Ty::Unknown,
+                ty => {
+                    Ty::Alias { name: ident.text(self.tree).to_string(), ty: Box::new(ty.clone()) }
+                }
             };
-
+            (ty, 0)
+        } else {
             self.diagnostics.push(LoweringDiagnostic {
-                kind: LoweringDiagnosticKind::MismatchedArgCount { name, expected, got },
+                kind: LoweringDiagnosticKind::UndefinedTy { name: name.0 },
                 range: ident.range(self.tree),
             });
+            return Ty::Unknown;
+        };
 
-            return Expr::Missing;
-        }
-
-        let mut args = Vec::new();
+        self.bodies.tys.insert(ident);
 
-        if let Some(arg_list) = arg_list {
-            for arg in arg_list.args(self.tree) {
-                let expr = self.lower_expr(arg.value(self.tree));
-                args.push(expr);
-            }
+        if got != expected {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::MismatchedTyArgCount { name: name.0, expected, got },
+                range: ty.range(self.tree),
+            });
         }
 
-        Expr::Call { path, args }
+        lowered
     }
 
-    fn lower_int_literal(&mut self, int_literal: ast::IntLiteral) -> Expr {
-        let value = int_literal.value(self.tree).and_then(|int| int.text(self.tree).parse().ok());
-
-        if let Some(value) = value {
-            return Expr::IntLiteral(value);
-        }
+    fn lower_labeled_block(
+        &mut self,
+        block: ast::Block,
+        label: ast::Label,
+        range: TextRange,
+    ) -> Expr {
+        let (label, body) = self.with_label(Some(label), false, |this| this.lower_block(block));
 
-        self.diagnostics.push(LoweringDiagnostic {
-            kind: LoweringDiagnosticKind::OutOfRangeIntLiteral,
-            range: int_literal.range(self.tree),
-        });
+        let body = self.bodies.exprs.alloc(body);
+        self.bodies.expr_ranges.insert(body, range);
 
-        Expr::Missing
+        Expr::LabeledBlock { label: label.unwrap(), body }
     }
 
-    fn lower_string_literal(&mut self, string_literal: ast::StringLiteral) -> Expr {
-        let mut text = String::new();
-
-        for component in string_literal.components(self.tree) {
-            match component {
-                ast::StringComponent::Escape(escape) => {
-                    let escape_text = escape.text(self.tree);
-                    let mut chars = escape_text.chars();
-                    if cfg!(debug_assertions) {
-                        assert_eq!(chars.next(), Some('\\'));
-                    } else {
-                        chars.next();
-                    }
-
-                    let escape_char = chars.next().unwrap();
-                    debug_assert!(chars.next().is_none());
+    fn lower_block(&mut self, block: ast::Block) -> Expr {
+        self.create_new_child_scope();
 
-                    match escape_char {
-                        '"' => text.push('"'),
-                        '\\' => text.push('\\'),
-                        'n' => text.push('\n'),
-                        'r' => text.push('\r'),
-                        't' => text.push('\t'),
-                        _ => self.diagnostics.push(LoweringDiagnostic {
-                            kind: LoweringDiagnosticKind::InvalidEscape,
-                            range: escape.range(self.tree),
-                        }),
-                    }
+        // like top-level functions,
+        // functions in a block can be called before they’re defined
+        let mut functions = Vec::new();
+        let mut function_scope = FxHashMap::default();
+        for def in block.defs(self.tree) {
+            let function = match def {
+                ast::Def::Function(function) => function,
+                ast::Def::TyAlias(ty_alias) => {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::NestedTyAlias,
+                        range: ty_alias.range(self.tree),
+                    });
+                    continue;
                 }
-                ast::StringComponent::Contents(contents) => {
-                    text.push_str(contents.text(self.tree));
+                ast::Def::Import(import) => {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::NestedImport,
+                        range: import.range(self.tree),
+                    });
+                    continue;
+                }
+                ast::Def::Const(constant) => {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::NestedConst,
+                        range: constant.range(self.tree),
+                    });
+                    continue;
                 }
+            };
+            if let Some(&name) = self.index.nested_functions.get(&function) {
+                let ident = function.name(self.tree).unwrap();
+                function_scope.insert(self.interner.intern(ident.text(self.tree)), name);
+                functions.push((function, name));
             }
         }
+        self.function_scopes.push(function_scope);
 
-        Expr::StringLiteral(text)
-    }
-
-    fn insert_into_current_scope(&mut self, name: Key, id: Id<LocalDef>) {
-        self.scopes.last_mut().unwrap().insert(name, id);
-    }
+        let mut statements = Vec::new();
 
-    fn look_up_in_current_scope(&mut self, name: Key) -> Option<Id<LocalDef>> {
-        for scope in self.scopes.iter().rev() {
-            if let Some(def) = scope.get(&name) {
-                return Some(*def);
-            }
+        for statement in block.statements(self.tree) {
+            let range = statement.range(self.tree);
+            let statement = self.lower_statement(statement);
+            statements.push(self.alloc_statement(statement, range));
         }
 
-        None
-    }
-
-    fn look_up_param(&mut self, name: Key) -> Option<(u32, ast::Param)> {
-        self.params.get(&name).copied()
-    }
-
-    fn create_new_child_scope(&mut self) {
-        self.scopes.push(FxHashMap::default());
-    }
+        let tail_expr =
+            block.tail_expr(self.tree).map(|tail_expr| self.lower_expr(Some(tail_expr)));
 
-    fn destroy_current_scope(&mut self) {
-        self.scopes.pop();
-    }
-}
+        for (function, name) in functions {
+            self.lower_nested_function(function, name);
+        }
 
-impl Bodies {
-    pub fn function_body(&self, name: Name) -> Id<Expr> {
-        self.function_bodies[&name]
-    }
+        self.function_scopes.pop();
+        self.destroy_current_scope();
 
-    pub fn range_for_expr(&self, expr: Id<Expr>) -> TextRange {
-        self.expr_ranges[expr]
+        Expr::Block { statements, tail_expr }
     }
 
-    pub fn other_module_references(&self) -> &FxHashSet<Fqn> {
-        &self.other_module_references
-    }
+    // nested functions can’t see the locals and parameters around them,
+    // and jumps inside of them can’t leave them
+    fn lower_nested_function(&mut self, function: ast::Function, name: Name) {
+        if function.extern_kw(self.tree).is_some() {
+            return;
+        }
 
-    pub fn symbol(&self, ident: ast::Ident) -> Option<Symbol> {
-        self.symbol_map.get(&ident).copied()
-    }
+        let scopes = std::mem::replace(&mut self.scopes, vec![FxHashMap::default()]);
+        let enclosing_scopes_len = self.enclosing_scopes.len();
+        self.enclosing_scopes.extend(scopes.iter().cloned());
+        let params = std::mem::take(&mut self.params);
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let labels = std::mem::take(&mut self.labels);
+        let labels_outside_defer = std::mem::take(&mut self.labels_outside_defer);
+        let in_defer = std::mem::take(&mut self.in_defer);
+        let unterminated_local_def_value = self.unterminated_local_def_value.take();
+        let return_ty = std::mem::replace(
+            &mut self.return_ty,
+            self.index.get_function(name).map(|function| function.return_ty.clone()),
+        );
+        let ty_params = self.ty_params_of(name);
+        let ty_params = std::mem::replace(&mut self.ty_params, ty_params);
 
-    fn shrink_to_fit(&mut self) {
-        let Self {
-            local_defs,
-            statements,
-            exprs,
-            expr_ranges,
-            function_bodies,
-            other_module_references,
-            symbol_map,
-        } = self;
+        self.lower_param_defaults(function, name);
+        self.lower_params(function);
+        let body = self.lower_expr(function.body(self.tree));
+        self.bodies.function_bodies.insert(name, body);
 
-        local_defs.shrink_to_fit();
-        statements.shrink_to_fit();
-        exprs.shrink_to_fit();
-        expr_ranges.shrink_to_fit();
-        function_bodies.shrink_to_fit();
-        other_module_references.shrink_to_fit();
-        symbol_map.shrink_to_fit();
+        self.scopes = scopes;
+        self.enclosing_scopes.truncate(enclosing_scopes_len);
+        self.params = params;
+        self.loop_depth = loop_depth;
+        self.labels = labels;
+        self.labels_outside_defer = labels_outside_defer;
+        self.in_defer = in_defer;
+        self.unterminated_local_def_value = unterminated_local_def_value;
+        self.return_ty = return_ty;
+        self.ty_params = ty_params;
     }
-}
-
-impl std::ops::Index<Id<LocalDef>> for Bodies {
-    type Output = LocalDef;
 
-    fn index(&self, id: Id<LocalDef>) -> &Self::Output {
-        &self.local_defs[id]
+    fn ty_params_of(&self, function: Name) -> Vec<Name> {
+        self.index.get_function(function).map_or_else(Vec::new, |f| f.ty_params.clone())
     }
-}
 
-impl std::ops::Index<Id<Statement>> for Bodies {
-    type Output = Statement;
+    fn lower_loop(&mut self, loop_expr: ast::Loop) -> Expr {
+        self.loop_depth += 1;
+        let (label, body) = self.with_label(loop_expr.label(self.tree), true, |this| {
+            this.lower_expr(loop_expr.body(this.tree).map(ast::Expr::Block))
+        });
+        self.loop_depth -= 1;
 
-    fn index(&self, id: Id<Statement>) -> &Self::Output {
-        &self.statements[id]
+        Expr::Loop { label, body }
     }
-}
-
-impl std::ops::Index<Id<Expr>> for Bodies {
-    type Output = Expr;
 
-    fn index(&self, id: Id<Expr>) -> &Self::Output {
-        &self.exprs[id]
-    }
-}
+    fn lower_for(&mut self, for_expr: ast::For) -> Expr {
+        let range_expr = for_expr.range_expr(self.tree);
+        let start = self.lower_expr(range_expr.and_then(|r| r.start(self.tree)));
+        let end = self.lower_expr(range_expr.and_then(|r| r.end(self.tree)));
 
-impl Bodies {
-    pub fn debug(&self, interner: &Interner) -> String {
-        let mut s = String::new();
+        // the loop variable is only visible inside the body
+        self.create_new_child_scope();
 
-        let mut function_bodies: Vec<_> = self.function_bodies.iter().collect();
-        function_bodies.sort_unstable_by_key(|(name, _)| *name);
+        let local_def = self.bodies.local_defs.alloc(LocalDef {
+            value: start,
+            is_mutable: false,
+            ty: None,
+            ast: LocalDefAst::For(for_expr),
+        });
 
-        for (name, expr_id) in function_bodies {
-            s.push_str(&format!("fnc {} -> ", interner.lookup(name.0)));
-            write_expr(*expr_id, self, &mut s, interner, 0);
-            s.push_str(";\n");
+        if let Some(ident) = for_expr.variable(self.tree) {
+            let name = self.interner.intern(ident.text(self.tree));
+            self.insert_into_current_scope(name, local_def);
+            self.bodies.local_def_ranges.insert(local_def, ident.range(self.tree));
         }
 
-        if !self.other_module_references.is_empty() {
-            let mut other_module_references: Vec<_> = self.other_module_references.iter().collect();
-            other_module_references.sort_unstable();
-
-            s.push_str("\nReferences to other modules:\n");
-            for fqn in &other_module_references {
-                s.push_str(&format!(
-                    "- {}.{}\n",
-                    interner.lookup(fqn.module.0),
-                    interner.lookup(fqn.function.0)
-                ));
-            }
-        }
+        self.loop_depth += 1;
+        let (label, body) = self.with_label(for_expr.label(self.tree), true, |this| {
+            this.lower_expr(for_expr.body(this.tree).map(ast::Expr::Block))
+        });
+        self.loop_depth -= 1;
 
-        return s;
+        self.destroy_current_scope();
 
-        fn write_expr(
-            id: Id<Expr>,
-            bodies: &Bodies,
-            s: &mut String,
-            interner: &Interner,
-            mut indentation: usize,
-        ) {
-            match &bodies[id] {
-                Expr::Missing => s.push_str("<missing>"),
+        Expr::For { label, local_def, start, end, body }
+    }
 
-                Expr::IntLiteral(n) => s.push_str(&format!("{}", n)),
+    fn lower_match(&mut self, match_expr: ast::MatchExpr) -> Expr {
+        let value = self.lower_expr(match_expr.value(self.tree));
 
-                Expr::StringLiteral(content) => s.push_str(&format!("{content:?}")),
+        let arms = match_expr
+            .arms(self.tree)
+            .map(|arm| {
+                // the locals bound by the pattern are only visible inside the arm
+                self.create_new_child_scope();
+                let pattern = self.lower_pattern(arm.pattern(self.tree), arm, value);
+                let body = self.lower_expr(arm.body(self.tree));
+                self.destroy_current_scope();
 
-                Expr::Binary { lhs, rhs, operator } => {
-                    write_expr(*lhs, bodies, s, interner, indentation);
+                MatchArm { pattern, body }
+            })
+            .collect();
 
-                    s.push(' ');
+        Expr::Match { value, arms }
+    }
 
-                    match operator {
-                        BinaryOperator::Add => s.push('+'),
-                        BinaryOperator::Sub => s.push('-'),
-                        BinaryOperator::Mul => s.push('*'),
-                        BinaryOperator::Div => s.push('/'),
-                    }
+    fn lower_pattern(
+        &mut self,
+        pattern: Option<ast::Pattern>,
+        arm: ast::MatchArm,
+        value: Id<Expr>,
+    ) -> Id<Pattern> {
+        let pattern_ast = match pattern {
+            Some(pattern) => pattern,
+            None => return self.bodies.patterns.alloc(Pattern::Missing),
+        };
 
-                    s.push(' ');
+        let range = pattern_ast.range(self.tree);
 
-                    write_expr(*rhs, bodies, s, interner, indentation);
+        let pattern = match pattern_ast {
+            ast::Pattern::Binding(binding_pattern) => match binding_pattern.name(self.tree) {
+                Some(ident) if ident.text(self.tree) == "_" => Pattern::Wildcard,
+                Some(ident) if Variant::from_name(ident.text(self.tree)).is_some() => {
+                    self.lower_variant_pattern(ident, None, arm, value)
                 }
+                Some(ident) => {
+                    let local_def = self.bodies.local_defs.alloc(LocalDef {
+                        value,
+                        is_mutable: false,
+                        ty: None,
+                        ast: LocalDefAst::MatchArm { arm, name: ident },
+                    });
+                    self.bodies.local_def_ranges.insert(local_def, ident.range(self.tree));
 
-                Expr::Block { statements, tail_expr: None } if statements.is_empty() => {
-                    s.push_str("{}");
-                }
+                    let name = self.interner.intern(ident.text(self.tree));
+                    self.insert_into_current_scope(name, local_def);
 
-                Expr::Block { statements, tail_expr: Some(tail_expr) } if statements.is_empty() => {
-                    s.push_str("{ ");
-                    write_expr(*tail_expr, bodies, s, interner, indentation + 4);
-                    s.push_str(" }");
+                    Pattern::Bind(local_def)
                 }
+                None => Pattern::Missing,
+            },
+            ast::Pattern::Literal(literal_pattern) => {
+                let is_negated = literal_pattern.hyphen(self.tree).is_some();
+
+                // literal patterns are lowered like the literal expressions they contain
+                let literal = match literal_pattern.literal(self.tree) {
+                    Some(ast::Expr::IntLiteral(int_literal)) => self.lower_int_literal(int_literal),
+                    Some(ast::Expr::BoolLiteral(bool_literal)) => {
+                        self.lower_bool_literal(bool_literal)
+                    }
+                    Some(ast::Expr::StringLiteral(string_literal)) => {
+                        self.lower_string_literal(string_literal)
+                    }
+                    Some(ast::Expr::CharLiteral(char_literal)) => {
+                        self.lower_char_literal(char_literal)
+                    }
+                    _ => Expr::Missing,
+                };
 
-                Expr::Block { statements, tail_expr } => {
-                    indentation += 4;
+                match literal {
+                    Expr::IntLiteral(value) => Pattern::IntLiteral { value, is_negated },
+                    Expr::BoolLiteral(value) => Pattern::BoolLiteral(value),
+                    Expr::StringLiteral(value) => Pattern::StringLiteral(value),
+                    Expr::CharLiteral(value) => Pattern::CharLiteral(value),
+                    _ => Pattern::Missing,
+                }
+            }
+            ast::Pattern::Tuple(tuple_pattern) => {
+                let patterns = tuple_pattern
+                    .patterns(self.tree)
+                    .map(|pattern| self.lower_pattern(Some(pattern), arm, value));
+                Pattern::Tuple(patterns.collect())
+            }
+            ast::Pattern::Variant(variant_pattern) => match variant_pattern.name(self.tree) {
+                Some(ident) => {
+                    let field = variant_pattern.pattern(self.tree);
+                    self.lower_variant_pattern(ident, Some(field), arm, value)
+                }
+                None => Pattern::Missing,
+            },
+        };
 
-                    s.push_str("{\n");
+        let id = self.bodies.patterns.alloc(pattern);
+        self.bodies.pattern_ranges.insert(id, range);
 
-                    for statement in statements.clone() {
-                        s.push_str(&" ".repeat(indentation));
-                        write_statement(statement, bodies, s, interner, indentation);
-                        s.push('\n');
-                    }
+        id
+    }
 
-                    if let Some(tail_expr) = tail_expr {
-                        s.push_str(&" ".repeat(indentation));
-                        write_expr(*tail_expr, bodies, s, interner, indentation);
-                        s.push('\n');
-                    }
+    // `field` is `None` for a lone name like `None`,
+    // and `Some` for a name followed by a pattern, even if that pattern is missing
+    fn lower_variant_pattern(
+        &mut self,
+        ident: ast::Ident,
+        field: Option<Option<ast::Pattern>>,
+        arm: ast::MatchArm,
+        value: Id<Expr>,
+    ) -> Pattern {
+        let field = field.map(|field| self.lower_pattern(field, arm, value));
 
-                    indentation -= 4;
-                    s.push_str(&" ".repeat(indentation));
+        let Some(variant) = Variant::from_name(ident.text(self.tree)) else {
+            let name = self.interner.intern(ident.text(self.tree));
+            self.bodies.symbol_map.insert(ident, Symbol::Unknown);
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::UndefinedVariant { name },
+                range: ident.range(self.tree),
+            });
+            return Pattern::Missing;
+        };
 
-                    s.push('}');
+        self.bodies.symbol_map.insert(ident, Symbol::Variant(variant));
+
+        if variant.has_field() != field.is_some() {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::MismatchedVariantPattern { variant },
+                range: ident.range(self.tree),
+            });
+            return Pattern::Missing;
+        }
+
+        Pattern::Variant { variant, field }
+    }
+
+    fn lower_local_or_call(&mut self, call: ast::Call) -> Expr {
+        let is_unterminated_local_def_value =
+            self.unterminated_local_def_value.take() == Some(call);
+
+        let ident = match call.top_level_name(self.tree) {
+            Some(ident) => ident,
+            None => return Expr::Missing,
+        };
+
+        if let Some(function_name_token) = call.nested_name(self.tree) {
+            // `x.f` where `x` is a value rather than a module is a method call
+            if let Some(receiver) = self.lower_receiver(ident) {
+                let receiver = self.bodies.exprs.alloc(receiver);
+                self.bodies.expr_ranges.insert(receiver, ident.range(self.tree));
+
+                let arg_list = call.arg_list(self.tree);
+                return self.lower_method(receiver, function_name_token, arg_list);
+            }
+
+            let module_name_token = ident;
+
+            let module_name = self.interner.intern(module_name_token.text(self.tree));
+            let function_name = self.interner.intern(function_name_token.text(self.tree));
+
+            let fqn = Fqn { module: Name(module_name), function: Name(function_name) };
+
+            match self.world_index.get_function(fqn) {
+                Ok(function) => {
+                    self.check_visibility(fqn, function, function_name_token);
+
+                    let path = Path::OtherModule(fqn);
+
+                    self.bodies.other_module_references.insert(fqn);
+
+                    self.bodies
+                        .symbol_map
+                        .insert(module_name_token, Symbol::Module(Name(module_name)));
+                    self.bodies.symbol_map.insert(function_name_token, Symbol::Function(path));
+
+                    let arg_list = call.arg_list(self.tree);
+                    return self.lower_call(arg_list, None, function, path, function_name_token);
                 }
 
-                Expr::Local(id) => s.push_str(&format!("l{}", id.to_raw())),
+                Err(GetFunctionError::UnknownModule) => {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::UndefinedModule { name: module_name },
+                        range: module_name_token.range(self.tree),
+                    });
 
-                Expr::Param { idx } => s.push_str(&format!("p{}", idx)),
+                    self.bodies.symbol_map.insert(module_name_token, Symbol::Unknown);
+                    self.bodies.symbol_map.insert(function_name_token, Symbol::Unknown);
 
-                Expr::Call { path, args } => {
-                    match path {
-                        Path::ThisModule(function) => s.push_str(interner.lookup(function.0)),
-                        Path::OtherModule(fqn) => s.push_str(&format!(
-                            "{}.{}",
-                            interner.lookup(fqn.module.0),
-                            interner.lookup(fqn.function.0)
-                        )),
-                    }
+                    return Expr::Missing;
+                }
 
-                    for (idx, arg) in args.iter().enumerate() {
-                        if idx == 0 {
-                            s.push(' ');
-                        } else {
-                            s.push_str(", ");
-                        }
+                Err(GetFunctionError::UnknownFunction) => {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::UndefinedFunction {
+                            module: module_name,
+                            function: function_name,
+                        },
+                        range: function_name_token.range(self.tree),
+                    });
 
-                        write_expr(*arg, bodies, s, interner, indentation);
-                    }
+                    self.bodies
+                        .symbol_map
+                        .insert(module_name_token, Symbol::Module(Name(module_name)));
+                    self.bodies.symbol_map.insert(function_name_token, Symbol::Unknown);
+
+                    return Expr::Missing;
                 }
             }
         }
 
-        fn write_statement(
-            id: Id<Statement>,
-            bodies: &Bodies,
-            s: &mut String,
-            interner: &Interner,
-            indentation: usize,
-        ) {
-            match &bodies[id] {
-                Statement::Expr(expr_id) => {
-                    write_expr(*expr_id, bodies, s, interner, indentation);
-                    s.push(';');
-                }
-                Statement::LocalDef(local_def_id) => {
-                    s.push_str(&format!("let l{} = ", local_def_id.to_raw()));
-                    write_expr(bodies[*local_def_id].value, bodies, s, interner, indentation);
-                    s.push(';');
-                }
+        let name = self.interner.intern(ident.text(self.tree));
+
+        if let Some(def) = self.look_up_in_current_scope(name) {
+            let definition = self.bodies.range_for_local_def(def);
+            self.check_args_for_local(call, ident, definition, is_unterminated_local_def_value);
+            self.bodies.symbol_map.insert(ident, Symbol::Local(def));
+            return Expr::Local(def);
+        }
+
+        if let Some((idx, ast)) = self.look_up_param(name) {
+            let definition = ast.name(self.tree).map(|name| name.range(self.tree));
+            self.check_args_for_local(call, ident, definition, is_unterminated_local_def_value);
+            self.bodies.symbol_map.insert(ident, Symbol::Param(ast));
+            return Expr::Param { idx };
+        }
+
+        if let Some(constant) = self.const_defs.get(&name) {
+            let definition = constant.name(self.tree).map(|name| name.range(self.tree));
+            self.check_args_for_local(call, ident, definition, is_unterminated_local_def_value);
+            self.bodies.symbol_map.insert(ident, Symbol::Const(Name(name)));
+            return Expr::Const(Name(name));
+        }
+
+        match self.look_up_function(ident) {
+            FunctionLookup::Found(path, function) => {
+                return self.lower_call(call.arg_list(self.tree), None, &function, path, ident);
             }
+            FunctionLookup::BrokenImport => return Expr::Missing,
+            FunctionLookup::NotFound => {}
+        }
+
+        if let Some(variant) = Variant::from_name(ident.text(self.tree)) {
+            self.bodies.symbol_map.insert(ident, Symbol::Variant(variant));
+            return self.lower_variant(call, variant, ident);
         }
+
+        if name == Key::unit() {
+            return self.lower_unit(call, ident);
+        }
+
+        // validation has already reported the reserved keyword
+        if !ast::validation::RESERVED_KEYWORDS.contains(&ident.text(self.tree)) {
+            self.note_reference_from_nested(name);
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::UndefinedLocal { name },
+                range: ident.range(self.tree),
+            });
+        }
+
+        self.bodies.symbol_map.insert(ident, Symbol::Unknown);
+
+        Expr::Missing
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::index;
-    use ast::AstNode;
-    use expect_test::{expect, Expect};
+    fn lower_receiver(&mut self, ident: ast::Ident) -> Option<Expr> {
+        let name = self.interner.intern(ident.text(self.tree));
 
-    #[track_caller]
-    fn check<const N: usize>(
-        input: &str,
-        expect: Expect,
-        expected_diagnostics: impl Fn(
-            &mut Interner,
-        ) -> [(LoweringDiagnosticKind, std::ops::Range<u32>); N],
+        if let Some(def) = self.look_up_in_current_scope(name) {
+            self.bodies.symbol_map.insert(ident, Symbol::Local(def));
+            return Some(Expr::Local(def));
+        }
+
+        if let Some((idx, ast)) = self.look_up_param(name) {
+            self.bodies.symbol_map.insert(ident, Symbol::Param(ast));
+            return Some(Expr::Param { idx });
+        }
+
+        if self.const_defs.contains_key(&name) {
+            self.bodies.symbol_map.insert(ident, Symbol::Const(Name(name)));
+            return Some(Expr::Const(Name(name)));
+        }
+
+        None
+    }
+
+    fn check_args_for_local(
+        &mut self,
+        call: ast::Call,
+        ident: ast::Ident,
+        definition: Option<TextRange>,
+        is_unterminated_local_def_value: bool,
     ) {
-        let modules = utils::split_multi_module_test_data(input);
-        let mut interner = Interner::default();
-        let mut world_index = WorldIndex::default();
+        let Some(arg_list) = call.arg_list(self.tree) else { return };
+        if arg_list.args(self.tree).count() == 0 {
+            return;
+        }
 
-        for (name, text) in &modules {
-            if *name == "main" {
-                continue;
-            }
+        let name = self.interner.intern(ident.text(self.tree));
 
-            let tokens = lexer::lex(text);
-            let tree = parser::parse_source_file(&tokens, text).into_syntax_tree();
-            let root = ast::Root::cast(tree.root(), &tree).unwrap();
-            let (index, _) = index(root, &tree, &WorldIndex::default(), &mut interner);
+        let kind = if is_unterminated_local_def_value {
+            let statement_end = call.range(self.tree).end();
+            LoweringDiagnosticKind::MissingSemicolonAfterLocal { name, statement_end }
+        } else {
+            LoweringDiagnosticKind::CalledLocal { name, definition }
+        };
 
-            world_index.add_module(Name(interner.intern(name)), index);
+        self.diagnostics.push(LoweringDiagnostic { kind, range: ident.range(self.tree) });
+    }
+
+    /// Finds the function called `ident` in a call without a module name,
+    /// recording what it refers to.
+    fn look_up_function(&mut self, ident: ast::Ident) -> FunctionLookup<'a> {
+        let name = self.interner.intern(ident.text(self.tree));
+
+        let nested_function =
+            self.function_scopes.iter().rev().find_map(|scope| scope.get(&name)).copied();
+        let name = nested_function.unwrap_or(Name(name));
+        if let Some(function) = self.index.get_function(name) {
+            let path = Path::ThisModule(name);
+            self.bodies.symbol_map.insert(ident, Symbol::Function(path));
+            return FunctionLookup::Found(path, Cow::Borrowed(function));
         }
 
-        let text = &modules["main"];
-        let tokens = lexer::lex(text);
-        let tree = parser::parse_source_file(&tokens, text).into_syntax_tree();
-        let root = ast::Root::cast(tree.root(), &tree).unwrap();
-        let (index, _) = index(root, &tree, &WorldIndex::default(), &mut interner);
+        if let Some(fqn) = self.index.get_import(name) {
+            let Ok(function) = self.world_index.get_function(fqn) else {
+                self.bodies.symbol_map.insert(ident, Symbol::Unknown);
+                return FunctionLookup::BrokenImport;
+            };
 
-        let (bodies, actual_diagnostics) = lower(root, &tree, &index, &world_index, &mut interner);
+            let path = Path::OtherModule(fqn);
+            self.bodies.other_module_references.insert(fqn);
+            self.bodies.symbol_map.insert(ident, Symbol::Function(path));
+            return FunctionLookup::Found(path, Cow::Borrowed(function));
+        }
 
-        expect.assert_eq(&bodies.debug(&interner));
+        if let Some(builtin) = Builtin::from_name(ident.text(self.tree)) {
+            let path = Path::Builtin(builtin);
+            self.bodies.symbol_map.insert(ident, Symbol::Function(path));
+            return FunctionLookup::Found(path, Cow::Owned(builtin.signature()));
+        }
 
-        let expected_diagnostics: Vec<_> = expected_diagnostics(&mut interner)
-            .into_iter()
-            .map(|(kind, range)| LoweringDiagnostic {
-                kind,
-                range: TextRange::new(range.start.into(), range.end.into()),
-            })
-            .collect();
+        if let Some((fqn, function)) = self.world_index.get_native_function(name) {
+            let path = Path::OtherModule(fqn);
+            self.bodies.other_module_references.insert(fqn);
+            self.bodies.symbol_map.insert(ident, Symbol::Function(path));
+            return FunctionLookup::Found(path, Cow::Borrowed(function));
+        }
 
-        assert_eq!(expected_diagnostics, actual_diagnostics);
+        FunctionLookup::NotFound
     }
 
-    #[test]
-    fn empty() {
-        check("", expect![["\n"]], |_| []);
+    /// Lowers `receiver.name args` to `name receiver, args`.
+    fn lower_method_call(&mut self, method_call: ast::MethodCall) -> Expr {
+        let receiver = self.lower_expr(method_call.receiver(self.tree));
+
+        let Some(ident) = method_call.name(self.tree) else {
+            return Expr::Missing;
+        };
+
+        self.lower_method(receiver, ident, method_call.arg_list(self.tree))
     }
 
-    #[test]
-    fn int_literal() {
-        check(
-            r#"
-                fnc a: s32 -> 1;
-            "#,
-            expect![[r#"
-                fnc a -> 1;
-            "#]],
-            |_| [],
+    fn lower_method(
+        &mut self,
+        receiver: Id<Expr>,
+        ident: ast::Ident,
+        arg_list: Option<ast::ArgList>,
+    ) -> Expr {
+        match self.look_up_function(ident) {
+            FunctionLookup::Found(path, function) => {
+                self.lower_call(arg_list, Some(receiver), &function, path, ident)
+            }
+            FunctionLookup::BrokenImport => Expr::Missing,
+            FunctionLookup::NotFound => {
+                let name = self.interner.intern(ident.text(self.tree));
+                self.diagnostics.push(LoweringDiagnostic {
+                    kind: LoweringDiagnosticKind::UndefinedMethod { name },
+                    range: ident.range(self.tree),
+                });
+                self.bodies.symbol_map.insert(ident, Symbol::Unknown);
+
+                Expr::Missing
+            }
+        }
+    }
+
+    /// Lowers a call to `function`,
+    /// passing `receiver` as the first argument for a method call.
+    fn lower_call(
+        &mut self,
+        arg_list: Option<ast::ArgList>,
+        receiver: Option<Id<Expr>>,
+        function: &Function,
+        path: Path,
+        ident: ast::Ident,
+    ) -> Expr {
+        // parameters with defaults come last
+        let max = function.params.len() as u32;
+        let min =
+            function.params.iter().rposition(|param| !param.has_default).map_or(0, |idx| idx + 1)
+                as u32;
+        let got = receiver.is_some() as u32
+            + match &arg_list {
+                Some(al) => al.args(self.tree).count() as u32,
+                None => 0,
+            };
+
+        if got < min || got > max {
+            let expected = if got < min { min } else { max };
+            let name = match path {
+                // nested functions are indexed under a different name
+                // than the one they were called with
+                Path::ThisModule(_) => self.interner.intern(ident.text(self.tree)),
+                Path::OtherModule(fqn) => {
+                    let module = self.interner.lookup(fqn.module.0);
+                    let function = self.interner.lookup(fqn.function.0);
+                    self.interner.intern(&format!("{module}.{function}"))
+                }
+                Path::Builtin(builtin) => self.interner.intern(builtin.name()),
+            };
+
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::MismatchedArgCount {
+                    name,
+                    expected,
+                    got,
+                    params: path.params_location(self.index, self.world_index),
+                },
+                range: ident.range(self.tree),
+            });
+
+            return Expr::Missing;
+        }
+
+        let mut args: Vec<_> = receiver.into_iter().collect();
+
+        if let Some(arg_list) = arg_list {
+            for arg in arg_list.args(self.tree) {
+                let expr = self.lower_expr(arg.value(self.tree));
+                args.push(expr);
+            }
+        }
+
+        Expr::Call { path, args }
+    }
+
+    // a variant is constructed like a call
+    // to a function with one parameter, or none for `None`
+    fn lower_variant(&mut self, call: ast::Call, variant: Variant, ident: ast::Ident) -> Expr {
+        let args: Vec<_> = match call.arg_list(self.tree) {
+            Some(arg_list) => arg_list.args(self.tree).collect(),
+            None => Vec::new(),
+        };
+
+        let expected = variant.has_field() as u32;
+        let got = args.len() as u32;
+
+        if expected != got {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::MismatchedArgCount {
+                    name: self.interner.intern(variant.name()),
+                    expected,
+                    got,
+                    params: None,
+                },
+                range: ident.range(self.tree),
+            });
+
+            return Expr::Missing;
+        }
+
+        let value = args.first().map(|arg| self.lower_expr(arg.value(self.tree)));
+
+        Expr::Variant { variant, value }
+    }
+
+    fn lower_unit(&mut self, call: ast::Call, ident: ast::Ident) -> Expr {
+        let got = match call.arg_list(self.tree) {
+            Some(arg_list) => arg_list.args(self.tree).count() as u32,
+            None => 0,
+        };
+
+        if got != 0 {
+            self.diagnostics.push(LoweringDiagnostic {
+                kind: LoweringDiagnosticKind::MismatchedArgCount {
+                    name: Key::unit(),
+                    expected: 0,
+                    got,
+                    params: None,
+                },
+                range: ident.range(self.tree),
+            });
+
+            return Expr::Missing;
+        }
+
+        Expr::Unit
+    }
+
+    fn lower_int_literal(&mut self, int_literal: ast::IntLiteral) -> Expr {
+        let Some(int) = int_literal.value(self.tree) else { return Expr::Missing };
+        let (radix, digits) = int.radix_and_digits(self.tree);
+
+        // validation reports literals without digits or with digits that don’t fit the radix
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return Expr::Missing;
+        }
+
+        if let Ok(value) = u64::from_str_radix(digits, radix) {
+            return Expr::IntLiteral(value);
+        }
+
+        self.diagnostics.push(LoweringDiagnostic {
+            kind: LoweringDiagnosticKind::OutOfRangeIntLiteral,
+            range: int_literal.range(self.tree),
+        });
+
+        Expr::Missing
+    }
+
+    fn lower_bool_literal(&mut self, bool_literal: ast::BoolLiteral) -> Expr {
+        Expr::BoolLiteral(bool_literal.value(self.tree).unwrap())
+    }
+
+    fn lower_string_literal(&mut self, string_literal: ast::StringLiteral) -> Expr {
+        let mut text = String::new();
+
+        for component in string_literal.components(self.tree) {
+            match component {
+                ast::StringComponent::Escape(escape) => {
+                    let escape_text = escape.text(self.tree);
+                    let mut chars = escape_text.chars();
+                    if cfg!(debug_assertions) {
+                        assert_eq!(chars.next(), Some('\\'));
+                    } else {
+                        chars.next();
+                    }
+
+                    let escape_char = chars.next().unwrap();
+                    debug_assert!(chars.next().is_none());
+
+                    match unescape(escape_char) {
+                        Some(c) => text.push(c),
+                        None => self.diagnostics.push(LoweringDiagnostic {
+                            kind: LoweringDiagnosticKind::InvalidEscape,
+                            range: escape.range(self.tree),
+                        }),
+                    }
+                }
+                ast::StringComponent::Contents(contents) => {
+                    text.push_str(contents.text(self.tree));
+                }
+            }
+        }
+
+        Expr::StringLiteral(text)
+    }
+
+    fn lower_char_literal(&mut self, char_literal: ast::CharLiteral) -> Expr {
+        let text = char_literal.value(self.tree).unwrap().text(self.tree);
+
+        // validation has already reported literals
+        // which don’t contain exactly one character
+        let Some(contents) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) else {
+            return Expr::Missing;
+        };
+
+        let mut chars = contents.chars();
+        let c = match (chars.next(), chars.next(), chars.next()) {
+            (Some('\\'), Some(escape_char), None) => match unescape(escape_char) {
+                Some(c) => c,
+                None => {
+                    self.diagnostics.push(LoweringDiagnostic {
+                        kind: LoweringDiagnosticKind::InvalidEscape,
+                        range: char_literal.range(self.tree),
+                    });
+                    return Expr::Missing;
+                }
+            },
+            (Some(c), None, None) => c,
+            _ => return Expr::Missing,
+        };
+
+        Expr::CharLiteral(c)
+    }
+
+    fn insert_into_current_scope(&mut self, name: Key, id: Id<LocalDef>) {
+        self.scopes.last_mut().unwrap().insert(name, id);
+    }
+
+    fn look_up_in_current_scope(&mut self, name: Key) -> Option<Id<LocalDef>> {
+        for scope in self.scopes.iter().rev() {
+            if let Some(def) = scope.get(&name) {
+                return Some(*def);
+            }
+        }
+
+        None
+    }
+
+    // an undefined name in a nested function
+    // which is a local of a function it’s nested in
+    fn note_reference_from_nested(&mut self, name: Key) {
+        let local_def = self.enclosing_scopes.iter().rev().find_map(|scope| scope.get(&name));
+        if let Some(local_def) = local_def {
+            self.referenced_from_nested.insert(*local_def);
+        }
+    }
+
+    fn look_up_param(&mut self, name: Key) -> Option<(u32, ast::Param)> {
+        self.params.get(&name).copied()
+    }
+
+    fn create_new_child_scope(&mut self) {
+        self.scopes.push(FxHashMap::default());
+    }
+
+    fn destroy_current_scope(&mut self) {
+        self.scopes.pop();
+    }
+}
+
+// both quotes can be escaped,
+// so that either kind of literal can contain either of them
+fn unescape(escape_char: char) -> Option<char> {
+    match escape_char {
+        '"' => Some('"'),
+        '\'' => Some('\''),
+        '\\' => Some('\\'),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        _ => None,
+    }
+}
+
+impl Bodies {
+    pub fn function_body(&self, name: Name) -> Id<Expr> {
+        self.function_bodies[&name]
+    }
+
+    pub fn function_bodies(&self) -> impl Iterator<Item = (Name, Id<Expr>)> + '_ {
+        self.function_bodies.iter().map(|(name, body)| (*name, *body))
+    }
+
+    /// The value a parameter takes on when a call leaves it out.
+    pub fn param_default(&self, function: Name, idx: u32) -> Option<Id<Expr>> {
+        *self.param_defaults.get(&function)?.get(idx as usize)?
+    }
+
+    pub fn consts(&self) -> impl Iterator<Item = Name> + '_ {
+        self.consts.keys().copied()
+    }
+
+    pub fn get_const(&self, name: Name) -> Option<&Const> {
+        self.consts.get(&name)
+    }
+
+    pub fn exprs(&self) -> impl Iterator<Item = (Id<Expr>, &Expr)> {
+        self.exprs.iter()
+    }
+
+    pub fn local_defs(&self) -> impl Iterator<Item = (Id<LocalDef>, &LocalDef)> {
+        self.local_defs.iter()
+    }
+
+    pub fn range_for_expr(&self, expr: Id<Expr>) -> TextRange {
+        self.expr_ranges[expr]
+    }
+
+    pub fn range_for_pattern(&self, pattern: Id<Pattern>) -> TextRange {
+        self.pattern_ranges[pattern]
+    }
+
+    pub fn range_for_statement(&self, statement: Id<Statement>) -> TextRange {
+        self.statement_ranges[statement]
+    }
+
+    /// The name of a local,
+    /// which is missing for those lowering made up, such as the ones `?` binds.
+    pub fn range_for_local_def(&self, local_def: Id<LocalDef>) -> Option<TextRange> {
+        self.local_def_ranges.get(local_def).copied()
+    }
+
+    /// Where the type annotation `value` is checked against is,
+    /// if it’s the value of a local, constant or parameter with one.
+    pub fn annotation_range(&self, value: Id<Expr>) -> Option<TextRange> {
+        self.annotation_ranges.get(value).copied()
+    }
+
+    pub fn other_module_references(&self) -> &FxHashSet<Fqn> {
+        &self.other_module_references
+    }
+
+    pub fn symbol(&self, ident: ast::Ident) -> Option<Symbol> {
+        self.symbol_map.get(&ident).copied()
+    }
+
+    pub fn is_ident_ty(&self, ident: ast::Ident) -> bool {
+        self.tys.contains(&ident)
+    }
+
+    fn shrink_to_fit(&mut self) {
+        let Self {
+            local_defs,
+            statements,
+            statement_ranges,
+            exprs,
+            expr_ranges,
+            patterns,
+            pattern_ranges,
+            annotation_ranges,
+            local_def_ranges,
+            function_bodies,
+            param_defaults,
+            consts,
+            other_module_references,
+            symbol_map,
+            tys,
+        } = self;
+
+        local_defs.shrink_to_fit();
+        statements.shrink_to_fit();
+        statement_ranges.shrink_to_fit();
+        exprs.shrink_to_fit();
+        expr_ranges.shrink_to_fit();
+        patterns.shrink_to_fit();
+        pattern_ranges.shrink_to_fit();
+        annotation_ranges.shrink_to_fit();
+        local_def_ranges.shrink_to_fit();
+        function_bodies.shrink_to_fit();
+        param_defaults.shrink_to_fit();
+        consts.shrink_to_fit();
+        other_module_references.shrink_to_fit();
+        symbol_map.shrink_to_fit();
+        tys.shrink_to_fit();
+    }
+}
+
+impl std::ops::Index<Id<LocalDef>> for Bodies {
+    type Output = LocalDef;
+
+    fn index(&self, id: Id<LocalDef>) -> &Self::Output {
+        &self.local_defs[id]
+    }
+}
+
+impl std::ops::Index<Id<Statement>> for Bodies {
+    type Output = Statement;
+
+    fn index(&self, id: Id<Statement>) -> &Self::Output {
+        &self.statements[id]
+    }
+}
+
+impl std::ops::Index<Id<Expr>> for Bodies {
+    type Output = Expr;
+
+    fn index(&self, id: Id<Expr>) -> &Self::Output {
+        &self.exprs[id]
+    }
+}
+
+impl std::ops::Index<Id<Pattern>> for Bodies {
+    type Output = Pattern;
+
+    fn index(&self, id: Id<Pattern>) -> &Self::Output {
+        &self.patterns[id]
+    }
+}
+
+impl Bodies {
+    pub fn debug(&self, interner: &Interner) -> String {
+        let mut s = String::new();
+
+        let mut consts: Vec<_> = self.consts.iter().collect();
+        consts.sort_unstable_by_key(|(name, _)| *name);
+
+        for (name, constant) in consts {
+            s.push_str(&format!("const {}", interner.lookup(name.0)));
+            if let Some(ty) = &constant.ty {
+                s.push_str(&format!(": {ty}"));
+            }
+            s.push_str(" = ");
+            write_expr(constant.value, self, &mut s, interner, 0);
+            s.push_str(";\n");
+        }
+
+        let mut function_bodies: Vec<_> = self.function_bodies.iter().collect();
+        function_bodies.sort_unstable_by_key(|(name, _)| *name);
+
+        for (name, expr_id) in function_bodies {
+            s.push_str(&format!("fnc {}", interner.lookup(name.0)));
+
+            if let Some(defaults) = self.param_defaults.get(name) {
+                s.push('(');
+                for (idx, default) in defaults.iter().enumerate() {
+                    if idx != 0 {
+                        s.push_str(", ");
+                    }
+
+                    s.push_str(&format!("p{idx}"));
+                    if let Some(default) = default {
+                        s.push_str(" = ");
+                        write_expr(*default, self, &mut s, interner, 0);
+                    }
+                }
+                s.push(')');
+            }
+
+            s.push_str(" -> ");
+            write_expr(*expr_id, self, &mut s, interner, 0);
+            s.push_str(";\n");
+        }
+
+        if !self.other_module_references.is_empty() {
+            let mut other_module_references: Vec<_> = self.other_module_references.iter().collect();
+            other_module_references.sort_unstable();
+
+            s.push_str("\nReferences to other modules:\n");
+            for fqn in &other_module_references {
+                s.push_str(&format!(
+                    "- {}.{}\n",
+                    interner.lookup(fqn.module.0),
+                    interner.lookup(fqn.function.0)
+                ));
+            }
+        }
+
+        return s;
+
+        fn write_expr(
+            id: Id<Expr>,
+            bodies: &Bodies,
+            s: &mut String,
+            interner: &Interner,
+            mut indentation: usize,
+        ) {
+            match &bodies[id] {
+                Expr::Missing => s.push_str("<missing>"),
+
+                Expr::IntLiteral(n) => s.push_str(&format!("{}", n)),
+
+                Expr::BoolLiteral(b) => s.push_str(&format!("{}", b)),
+
+                Expr::StringLiteral(content) => s.push_str(&format!("{content:?}")),
+                Expr::CharLiteral(c) => s.push_str(&format!("{c:?}")),
+
+                Expr::Unit => s.push_str("unit"),
+
+                Expr::Unary { expr, operator } => {
+                    match operator {
+                        UnaryOperator::Neg => s.push('-'),
+                        UnaryOperator::Not => s.push('!'),
+                    }
+
+                    write_expr(*expr, bodies, s, interner, indentation);
+                }
+
+                Expr::Cast { expr, ty } => {
+                    write_expr(*expr, bodies, s, interner, indentation);
+                    s.push_str(&format!(" as {ty}"));
+                }
+
+                Expr::Tuple(elements) => {
+                    s.push('(');
+
+                    for (idx, element) in elements.iter().enumerate() {
+                        if idx != 0 {
+                            s.push_str(", ");
+                        }
+
+                        write_expr(*element, bodies, s, interner, indentation);
+                    }
+
+                    s.push(')');
+                }
+
+                Expr::Field { tuple, idx } => {
+                    write_expr(*tuple, bodies, s, interner, indentation);
+                    s.push_str(&format!(".{idx}"));
+                }
+
+                Expr::Array(elements) => {
+                    s.push('[');
+
+                    for (idx, element) in elements.iter().enumerate() {
+                        if idx != 0 {
+                            s.push_str(", ");
+                        }
+
+                        write_expr(*element, bodies, s, interner, indentation);
+                    }
+
+                    s.push(']');
+                }
+
+                Expr::Index { array, index } => {
+                    write_expr(*array, bodies, s, interner, indentation);
+                    s.push('[');
+                    write_expr(*index, bodies, s, interner, indentation);
+                    s.push(']');
+                }
+
+                Expr::Binary { lhs, rhs, operator } => {
+                    write_expr(*lhs, bodies, s, interner, indentation);
+
+                    s.push(' ');
+
+                    match operator {
+                        BinaryOperator::Add => s.push('+'),
+                        BinaryOperator::Sub => s.push('-'),
+                        BinaryOperator::Mul => s.push('*'),
+                        BinaryOperator::Div => s.push('/'),
+                        BinaryOperator::Eq => s.push_str("=="),
+                        BinaryOperator::NotEq => s.push_str("!="),
+                        BinaryOperator::Lt => s.push('<'),
+                        BinaryOperator::LtEq => s.push_str("<="),
+                        BinaryOperator::Gt => s.push('>'),
+                        BinaryOperator::GtEq => s.push_str(">="),
+                        BinaryOperator::And => s.push_str("&&"),
+                        BinaryOperator::Or => s.push_str("||"),
+                    }
+
+                    s.push(' ');
+
+                    write_expr(*rhs, bodies, s, interner, indentation);
+                }
+
+                Expr::Block { statements, tail_expr: None } if statements.is_empty() => {
+                    s.push_str("{}");
+                }
+
+                Expr::Block { statements, tail_expr: Some(tail_expr) } if statements.is_empty() => {
+                    s.push_str("{ ");
+                    write_expr(*tail_expr, bodies, s, interner, indentation + 4);
+                    s.push_str(" }");
+                }
+
+                Expr::Block { statements, tail_expr } => {
+                    indentation += 4;
+
+                    s.push_str("{\n");
+
+                    for statement in statements.clone() {
+                        s.push_str(&" ".repeat(indentation));
+                        write_statement(statement, bodies, s, interner, indentation);
+                        s.push('\n');
+                    }
+
+                    if let Some(tail_expr) = tail_expr {
+                        s.push_str(&" ".repeat(indentation));
+                        write_expr(*tail_expr, bodies, s, interner, indentation);
+                        s.push('\n');
+                    }
+
+                    indentation -= 4;
+                    s.push_str(&" ".repeat(indentation));
+
+                    s.push('}');
+                }
+
+                Expr::Local(id) => s.push_str(&format!("l{}", id.to_raw())),
+
+                Expr::Const(name) => s.push_str(interner.lookup(name.0)),
+
+                Expr::Param { idx } => s.push_str(&format!("p{}", idx)),
+
+                Expr::Loop { label, body } => {
+                    write_label(*label, s, interner);
+                    s.push_str("loop ");
+                    write_expr(*body, bodies, s, interner, indentation);
+                }
+
+                Expr::For { label, local_def, start, end, body } => {
+                    write_label(*label, s, interner);
+                    s.push_str(&format!("for l{} in ", local_def.to_raw()));
+                    write_expr(*start, bodies, s, interner, indentation);
+                    s.push_str("..");
+                    write_expr(*end, bodies, s, interner, indentation);
+                    s.push(' ');
+                    write_expr(*body, bodies, s, interner, indentation);
+                }
+
+                Expr::LabeledBlock { label, body } => {
+                    write_label(Some(*label), s, interner);
+                    write_expr(*body, bodies, s, interner, indentation);
+                }
+
+                Expr::Call { path, args } => {
+                    match path {
+                        Path::ThisModule(function) => s.push_str(interner.lookup(function.0)),
+                        Path::OtherModule(fqn) => s.push_str(&format!(
+                            "{}.{}",
+                            interner.lookup(fqn.module.0),
+                            interner.lookup(fqn.function.0)
+                        )),
+                        Path::Builtin(builtin) => s.push_str(builtin.name()),
+                    }
+
+                    for (idx, arg) in args.iter().enumerate() {
+                        if idx == 0 {
+                            s.push(' ');
+                        } else {
+                            s.push_str(", ");
+                        }
+
+                        write_expr(*arg, bodies, s, interner, indentation);
+                    }
+                }
+
+                Expr::Variant { variant, value } => {
+                    s.push_str(variant.name());
+
+                    if let Some(value) = value {
+                        s.push(' ');
+                        write_expr(*value, bodies, s, interner, indentation);
+                    }
+                }
+
+                Expr::Match { value, arms } => {
+                    s.push_str("match ");
+                    write_expr(*value, bodies, s, interner, indentation);
+                    s.push_str(" {\n");
+
+                    indentation += 4;
+
+                    for arm in arms {
+                        s.push_str(&" ".repeat(indentation));
+                        write_pattern(arm.pattern, bodies, s);
+                        s.push_str(" -> ");
+                        write_expr(arm.body, bodies, s, interner, indentation);
+                        s.push_str(";\n");
+                    }
+
+                    indentation -= 4;
+                    s.push_str(&" ".repeat(indentation));
+
+                    s.push('}');
+                }
+            }
+        }
+
+        fn write_label(label: Option<Name>, s: &mut String, interner: &Interner) {
+            if let Some(label) = label {
+                s.push_str(&format!("'{}: ", interner.lookup(label.0)));
+            }
+        }
+
+        fn write_pattern(id: Id<Pattern>, bodies: &Bodies, s: &mut String) {
+            match &bodies[id] {
+                Pattern::Missing => s.push_str("<missing>"),
+                Pattern::Wildcard => s.push('_'),
+                Pattern::Bind(local_def) => s.push_str(&format!("l{}", local_def.to_raw())),
+                Pattern::IntLiteral { value, is_negated } => {
+                    if *is_negated {
+                        s.push('-');
+                    }
+                    s.push_str(&format!("{value}"));
+                }
+                Pattern::BoolLiteral(b) => s.push_str(&format!("{b}")),
+                Pattern::StringLiteral(content) => s.push_str(&format!("{content:?}")),
+                Pattern::CharLiteral(c) => s.push_str(&format!("{c:?}")),
+                Pattern::Tuple(patterns) => {
+                    s.push('(');
+
+                    for (idx, pattern) in patterns.iter().enumerate() {
+                        if idx != 0 {
+                            s.push_str(", ");
+                        }
+
+                        write_pattern(*pattern, bodies, s);
+                    }
+
+                    s.push(')');
+                }
+                Pattern::Variant { variant, field } => {
+                    s.push_str(variant.name());
+
+                    if let Some(field) = field {
+                        s.push(' ');
+                        write_pattern(*field, bodies, s);
+                    }
+                }
+            }
+        }
+
+        fn write_statement(
+            id: Id<Statement>,
+            bodies: &Bodies,
+            s: &mut String,
+            interner: &Interner,
+            indentation: usize,
+        ) {
+            match &bodies[id] {
+                Statement::Expr(expr_id) => {
+                    write_expr(*expr_id, bodies, s, interner, indentation);
+                    s.push(';');
+                }
+                Statement::LocalDef(local_def_id) => {
+                    s.push_str("let ");
+                    if bodies[*local_def_id].is_mutable {
+                        s.push_str("mut ");
+                    }
+                    s.push_str(&format!("l{}", local_def_id.to_raw()));
+                    if let Some(ty) = &bodies[*local_def_id].ty {
+                        s.push_str(&format!(": {ty}"));
+                    }
+                    s.push_str(" = ");
+                    write_expr(bodies[*local_def_id].value, bodies, s, interner, indentation);
+                    s.push(';');
+                }
+                Statement::Destructure { value, ty, local_defs } => {
+                    s.push_str("let ");
+                    if local_defs.iter().flatten().any(|id| bodies[*id].is_mutable) {
+                        s.push_str("mut ");
+                    }
+                    s.push('(');
+                    for (idx, local_def_id) in local_defs.iter().enumerate() {
+                        if idx != 0 {
+                            s.push_str(", ");
+                        }
+                        match local_def_id {
+                            Some(id) => s.push_str(&format!("l{}", id.to_raw())),
+                            None => s.push('_'),
+                        }
+                    }
+                    s.push(')');
+                    if let Some(ty) = ty {
+                        s.push_str(&format!(": {ty}"));
+                    }
+                    s.push_str(" = ");
+                    write_expr(*value, bodies, s, interner, indentation);
+                    s.push(';');
+                }
+                Statement::Assign { local_def, value } => {
+                    s.push_str(&format!("l{} = ", local_def.to_raw()));
+                    write_expr(*value, bodies, s, interner, indentation);
+                    s.push(';');
+                }
+                Statement::Break { label, value } => {
+                    s.push_str("break");
+                    if let Some(label) = label {
+                        s.push_str(&format!(" '{}", interner.lookup(label.0)));
+                    }
+                    if let Some(value) = value {
+                        s.push(' ');
+                        write_expr(*value, bodies, s, interner, indentation);
+                    }
+                    s.push(';');
+                }
+                Statement::Continue { label: None } => s.push_str("continue;"),
+                Statement::Continue { label: Some(label) } => {
+                    s.push_str(&format!("continue '{};", interner.lookup(label.0)));
+                }
+                Statement::Return { value: None } => s.push_str("return;"),
+                Statement::Return { value: Some(value) } => {
+                    s.push_str("return ");
+                    write_expr(*value, bodies, s, interner, indentation);
+                    s.push(';');
+                }
+                Statement::Defer(expr) => {
+                    s.push_str("defer ");
+                    write_expr(*expr, bodies, s, interner, indentation);
+                    s.push(';');
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index;
+    use ast::AstNode;
+    use expect_test::{expect, Expect};
+
+    #[track_caller]
+    fn check<const N: usize>(
+        input: &str,
+        expect: Expect,
+        expected_diagnostics: impl Fn(
+            &mut Interner,
+        ) -> [(LoweringDiagnosticKind, std::ops::Range<u32>); N],
+    ) {
+        let modules = utils::split_multi_module_test_data(input);
+        let mut interner = Interner::default();
+        let mut world_index = WorldIndex::default();
+
+        for (name, text) in &modules {
+            if *name == "main" {
+                continue;
+            }
+
+            let tokens = lexer::lex(text);
+            let tree = parser::parse_source_file(&tokens, text).into_syntax_tree();
+            let root = ast::Root::cast(tree.root(), &tree).unwrap();
+            let (index, _) = index(root, &tree, &WorldIndex::default(), &mut interner);
+
+            world_index.add_module(Name(interner.intern(name)), index);
+        }
+
+        let text = &modules["main"];
+        let tokens = lexer::lex(text);
+        let tree = parser::parse_source_file(&tokens, text).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = index(root, &tree, &WorldIndex::default(), &mut interner);
+
+        let (bodies, actual_diagnostics) = lower(root, &tree, &index, &world_index, &mut interner);
+
+        expect.assert_eq(&bodies.debug(&interner));
+
+        let expected_diagnostics: Vec<_> = expected_diagnostics(&mut interner)
+            .into_iter()
+            .map(|(kind, range)| LoweringDiagnostic {
+                kind,
+                range: TextRange::new(range.start.into(), range.end.into()),
+            })
+            .collect();
+
+        assert_eq!(expected_diagnostics, actual_diagnostics);
+    }
+
+    #[test]
+    fn empty() {
+        check("", expect![["\n"]], |_| []);
+    }
+
+    #[test]
+    fn int_literal() {
+        check(
+            r#"
+                fnc a: s32 -> 1;
+            "#,
+            expect![[r#"
+                fnc a -> 1;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn bool_literals() {
+        check(
+            r#"
+                fnc a -> { true; false; };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    true;
+                    false;
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn loops() {
+        check(
+            r#"
+                fnc a -> loop {
+                    loop { break; };
+                    continue;
+                };
+            "#,
+            expect![[r#"
+                fnc a -> loop {
+                    loop {
+                        break;
+                    };
+                    continue;
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn break_with_value() {
+        check(
+            r#"
+                fnc a -> loop { break 1 + 2; };
+            "#,
+            expect![[r#"
+                fnc a -> loop {
+                    break 1 + 2;
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn break_outside_loop() {
+        check(
+            r#"
+                fnc a -> { break; };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    break;
+                };
+            "#]],
+            |_| [(LoweringDiagnosticKind::BreakOutsideLoop, 28..34)],
+        );
+    }
+
+    #[test]
+    fn continue_outside_loop() {
+        check(
+            r#"
+                fnc a -> { continue; };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    continue;
+                };
+            "#]],
+            |_| [(LoweringDiagnosticKind::ContinueOutsideLoop, 28..37)],
+        );
+    }
+
+    #[test]
+    fn labeled_loops_and_blocks() {
+        check(
+            r#"
+                fnc a -> {
+                    'outer: for i in 0..10 {
+                        'inner: loop {
+                            continue 'outer;
+                            break 'inner;
+                        };
+                    };
+                    'block: {
+                        loop { break 'block 1; };
+                        2
+                    };
+                };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    'outer: for l0 in 0..10 {
+                        'inner: loop {
+                            continue 'outer;
+                            break 'inner;
+                        };
+                    };
+                    'block: {
+                        loop {
+                            break 'block 1;
+                        };
+                        2
+                    };
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn invalid_labels() {
+        check(
+            r#"
+                fnc a -> {
+                    'a: loop {
+                        break 'b;
+                        'c: { continue 'c; };
+                        fnc f -> loop { break 'a; };
+                        defer { break 'a; };
+                    };
+                };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    'a: loop {
+                        break 'b;
+                        'c: {
+                            continue 'c;
+                        };
+                        defer {
+                            break 'a;
+                        };
+                    };
+                };
+                fnc a.f -> loop {
+                    break 'a;
+                };
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedLabel { name: i.intern("b") }, 89..91),
+                    (LoweringDiagnosticKind::ContinueToBlock { name: i.intern("c") }, 132..134),
+                    (LoweringDiagnosticKind::JumpOutOfDefer, 230..232),
+                    (LoweringDiagnosticKind::UndefinedLabel { name: i.intern("a") }, 185..187),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn defer_statements() {
+        check(
+            r#"
+                fnc a -> {
+                    let mut n = 0;
+                    defer { n = 1; };
+                    defer loop { break; };
+                };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    let mut l0 = 0;
+                    defer {
+                        l0 = 1;
+                    };
+                    defer loop {
+                        break;
+                    };
+                };
+            "#]],
+            |i| [(LoweringDiagnosticKind::UnusedLocal { name: i.intern("n") }, 56..57)],
+        );
+    }
+
+    #[test]
+    fn jump_out_of_defer() {
+        check(
+            r#"
+                fnc a: Option[s32] -> loop {
+                    defer { break; };
+                    defer { continue; };
+                    defer { return None; };
+                    defer { Some 1 }?;
+                };
+            "#,
+            expect![[r#"
+                fnc a -> loop {
+                    defer {
+                        break;
+                    };
+                    defer {
+                        continue;
+                    };
+                    defer {
+                        return None;
+                    };
+                    defer match { Some 1 } {
+                        Some l0 -> l0;
+                        None -> {
+                            return None;
+                        };
+                    };
+                };
+            "#]],
+            |_| {
+                [
+                    (LoweringDiagnosticKind::JumpOutOfDefer, 74..80),
+                    (LoweringDiagnosticKind::JumpOutOfDefer, 112..121),
+                    (LoweringDiagnosticKind::JumpOutOfDefer, 153..165),
+                    (LoweringDiagnosticKind::JumpOutOfDefer, 195..206),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn return_statements() {
+        check(
+            r#"
+                fnc a(x: s32): s32 -> {
+                    loop { return x; };
+                };
+                fnc b -> { return; };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    loop {
+                        return p0;
+                    };
+                };
+                fnc b -> {
+                    return;
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn prefixed_int_literals() {
+        check(
+            r#"
+                fnc a: s32 -> 0xff + 0o17 + 0b101;
+            "#,
+            expect![[r#"
+                fnc a -> 255 + 15 + 5;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn out_of_range_prefixed_int_literal() {
+        check(
+            r#"
+                fnc a: s32 -> 0x10000000000000000;
+            "#,
+            expect![[r#"
+                fnc a -> <missing>;
+            "#]],
+            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 31..50)],
+        );
+    }
+
+    #[test]
+    fn prefixed_int_literal_with_invalid_digits() {
+        check(
+            r#"
+                fnc a: s32 -> 0b2;
+            "#,
+            expect![[r#"
+                fnc a -> <missing>;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn out_of_range_int_literal() {
+        check(
+            r#"
+                fnc a: s32 -> 99999999999999999999;
+            "#,
+            expect![[r#"
+                fnc a -> <missing>;
+            "#]],
+            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 31..51)],
+        );
+    }
+
+    #[test]
+    fn binary_expr() {
+        check(
+            r#"
+                fnc sum: s32 -> 2 + 2;
+            "#,
+            expect![[r#"
+                fnc sum -> 2 + 2;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn comparison() {
+        check(
+            r#"
+                fnc f: bool -> 1 + 1 != 3;
+            "#,
+            expect![[r#"
+                fnc f -> 1 + 1 != 3;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn logical_operators() {
+        check(
+            r#"
+                fnc f: bool -> true || false && true;
+            "#,
+            expect![[r#"
+                fnc f -> true || false && true;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn unary_operators() {
+        check(
+            r#"
+                fnc f: bool -> -1 < 2 - -3 && !false;
+            "#,
+            expect![[r#"
+                fnc f -> -1 < 2 - -3 && !false;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn string_literal() {
+        check(
+            r#"
+                fnc crab: string -> "🦀";
+            "#,
+            expect![[r#"
+                fnc crab -> "🦀";
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn string_literal_with_escapes() {
+        check(
+            r#"
+                fnc f: string -> "\r\n\t\"\\";
+            "#,
+            expect![[r#"
+                fnc f -> "\r\n\t\"\\";
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn string_literal_with_invalid_escapes() {
+        check(
+            r#"
+                fnc f: string -> "a\*b\🤡c";
+            "#,
+            expect![[r#"
+                fnc f -> "abc";
+            "#]],
+            |_| {
+                [
+                    (LoweringDiagnosticKind::InvalidEscape, 36..38),
+                    (LoweringDiagnosticKind::InvalidEscape, 39..44),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn char_literals() {
+        check(
+            r#"
+                fnc f: bool -> 'a' < '\'' && '\n' != '🦀';
+            "#,
+            expect![[r#"
+                fnc f -> 'a' < '\'' && '\n' != '🦀';
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn char_literal_with_invalid_escape() {
+        check(
+            r#"
+                fnc f: char -> '\*';
+            "#,
+            expect![[r#"
+                fnc f -> <missing>;
+            "#]],
+            |_| [(LoweringDiagnosticKind::InvalidEscape, 32..36)],
+        );
+    }
+
+    #[test]
+    fn cast() {
+        check(
+            r#"
+                fnc f(n: s32): s64 -> n as s64 * -2 as s64;
+            "#,
+            expect![[r#"
+                fnc f -> p0 as s64 * -2 as s64;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn cast_to_undefined_ty() {
+        check(
+            r#"
+                fnc f(n: s32): s32 -> n as int;
+            "#,
+            expect![[r#"
+                fnc f -> p0 as ?;
+            "#]],
+            |i| [(LoweringDiagnosticKind::UndefinedTy { name: i.intern("int") }, 44..47)],
+        );
+    }
+
+    #[test]
+    fn nested_binary_exprs() {
+        check(
+            r#"
+                fnc a: s32 -> 1 + 2 * 3 + 4 * 5;
+            "#,
+            expect![[r#"
+                fnc a -> 1 + 2 * 3 + 4 * 5;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn empty_block() {
+        check(
+            r#"
+                fnc nil -> {};
+            "#,
+            expect![[r#"
+                fnc nil -> {};
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn unit_literal() {
+        check(
+            r#"
+                fnc nil -> unit;
+            "#,
+            expect![[r#"
+                fnc nil -> unit;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn param_shadows_unit_literal() {
+        check(
+            r#"
+                fnc id(unit: s32): s32 -> unit;
+            "#,
+            expect![[r#"
+                fnc id -> p0;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn block_with_one_expr() {
+        check(
+            r#"
+                fnc one: s32 -> { 1 };
+            "#,
+            expect![[r#"
+                fnc one -> { 1 };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn block_with_one_local_def() {
+        check(
+            r#"
+                fnc foo -> { let a = 7; };
+            "#,
+            expect![[r#"
+                fnc foo -> {
+                    let l0 = 7;
+                };
+            "#]],
+            |i| [(LoweringDiagnosticKind::UnusedLocal { name: i.intern("a") }, 34..35)],
+        );
+    }
+
+    #[test]
+    fn block_with_one_expr_statement() {
+        check(
+            r#"
+                fnc a -> { 100; };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    100;
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn block_with_local_def_and_usage() {
+        check(
+            r#"
+                fnc magic_number: s32 -> { let n = 42; n };
+            "#,
+            expect![[r#"
+                fnc magic_number -> {
+                    let l0 = 42;
+                    l0
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn block_with_multiple_local_defs() {
+        check(
+            r#"
+                fnc f -> {
+                    let a = 1;
+                    let b = 2;
+                    let c = 3;
+                    let d = 4;
+                };
+            "#,
+            expect![[r#"
+                fnc f -> {
+                    let l0 = 1;
+                    let l1 = 2;
+                    let l2 = 3;
+                    let l3 = 4;
+                };
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("a") }, 52..53),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("b") }, 83..84),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("c") }, 114..115),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("d") }, 145..146),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn multiple_functions() {
+        check(
+            r#"
+                fnc a -> {};
+                fnc d -> {};
+                fnc b -> {};
+                fnc e -> {};
+                fnc c -> {};
+            "#,
+            expect![[r#"
+                fnc a -> {};
+                fnc d -> {};
+                fnc b -> {};
+                fnc e -> {};
+                fnc c -> {};
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn local_def_with_ty() {
+        check(
+            r#"
+                fnc f -> {
+                    let a: s64 = 1;
+                    let b: text = "b";
+                };
+            "#,
+            expect![[r#"
+                fnc f -> {
+                    let l0: s64 = 1;
+                    let l1: ? = "b";
+                };
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedTy { name: i.intern("text") }, 91..95),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("a") }, 52..53),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("b") }, 88..89),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn wildcard_local_def() {
+        check(
+            r#"
+                fnc f -> {
+                    let _ = 1;
+                    let _ = 2;
+                    _
+                };
+            "#,
+            expect![[r#"
+                fnc f -> {
+                    let l0 = 1;
+                    let l1 = 2;
+                    <missing>
+                };
+            "#]],
+            |i| [(LoweringDiagnosticKind::UndefinedLocal { name: i.intern("_") }, 110..111)],
+        );
+    }
+
+    #[test]
+    fn tuples() {
+        check(
+            r#"
+                fnc f -> {
+                    let pair: (s32, (bool, string)) = (1, (true, "a"));
+                    pair.1.0;
+                    ()
+                };
+            "#,
+            expect![[r#"
+                fnc f -> {
+                    let l0: (s32, (bool, string)) = (1, (true, "a"));
+                    l0.1.0;
+                    ()
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        check(
+            r#"
+                fnc f -> {
+                    let xs: [[s32]] = [[1, 2], []];
+                    xs[0][1 + 1];
+                    [true][0]
+                };
+            "#,
+            expect![[r#"
+                fnc f -> {
+                    let l0: [[s32]] = [[1, 2], []];
+                    l0[0][1 + 1];
+                    [true][0]
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn match_expr() {
+        check(
+            r#"
+                fnc f(p: (s32, string)): s32 -> match p {
+                    (-1, "a") -> 0;
+                    (n, _) -> match n > 0 { true -> n; false -> -n };
+                };
+            "#,
+            expect![[r#"
+                fnc f -> match p0 {
+                    (-1, "a") -> 0;
+                    (l0, _) -> match l0 > 0 {
+                        true -> l0;
+                        false -> -l0;
+                    };
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn match_bindings_are_scoped_to_their_arm() {
+        check(
+            r#"
+                fnc f(p: s32): s32 -> {
+                    match p { n -> n; _ -> n };
+                    n
+                };
+            "#,
+            expect![[r#"
+                fnc f -> {
+                    match p0 {
+                        l0 -> l0;
+                        _ -> <missing>;
+                    };
+                    <missing>
+                };
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedLocal { name: i.intern("n") }, 84..85),
+                    (LoweringDiagnosticKind::UndefinedLocal { name: i.intern("n") }, 109..110),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn destructure_tuple() {
+        check(
+            r#"
+                fnc f -> {
+                    let (a, _, b) = (1, 2, 3);
+                    let mut (c, d): (s32, s32) = (b, a);
+                    a + b + c + d
+                };
+            "#,
+            expect![[r#"
+                fnc f -> {
+                    let (l0, _, l1) = (1, 2, 3);
+                    let mut (l2, l3): (s32, s32) = (l1, l0);
+                    l0 + l1 + l2 + l3
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn destructured_locals_are_not_in_scope_of_value() {
+        check(
+            r#"
+                fnc f -> {
+                    let (a, b) = (a, 1);
+                };
+            "#,
+            expect![[r#"
+                fnc f -> {
+                    let (l0, l1) = (<missing>, 1);
+                };
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedLocal { name: i.intern("a") }, 62..63),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("a") }, 53..54),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("b") }, 56..57),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn undefined_local() {
+        check(
+            r#"
+                fnc foo -> bar;
+            "#,
+            expect![[r#"
+                fnc foo -> <missing>;
+            "#]],
+            |i| [(LoweringDiagnosticKind::UndefinedLocal { name: i.intern("bar") }, 28..31)],
+        );
+    }
+
+    #[test]
+    fn scope_locals_to_blocks() {
+        check(
+            r#"
+                fnc a: s32 -> {
+                    { let foo = 5; };
+                    foo
+                }
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    {
+                        let l0 = 5;
+                    };
+                    <missing>
+                };
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedLocal { name: i.intern("foo") }, 91..94),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("foo") }, 59..62),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn reference_local_from_block() {
+        check(
+            r#"
+                fnc f -> {
+                    let a = 7;
+                    { a };
+                }
+            "#,
+            expect![[r#"
+                fnc f -> {
+                    let l0 = 7;
+                    { l0 };
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn binary_expr_with_missing_operand() {
+        check(
+            r#"
+                fnc number: s32 -> 1 +;
+            "#,
+            expect![[r#"
+                fnc number -> 1 + <missing>;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn unused_params() {
+        check(
+            r#"
+                fnc add(x: s32, y: s32) -> {};
+            "#,
+            expect![[r#"
+                fnc add -> {};
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn used_params() {
+        check(
+            r#"
+                fnc add(x: s32, y: s32): s32 -> x + y;
+            "#,
+            expect![[r#"
+                fnc add -> p0 + p1;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn call_with_no_args() {
+        check(
+            r#"
+                fnc five: s32 -> 5;
+                fnc ten: s32 -> five + five;
+            "#,
+            expect![[r#"
+                fnc five -> 5;
+                fnc ten -> five + five;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn call_with_args() {
+        check(
+            r#"
+                fnc multiply(x: s32, y: s32): s32 -> x * y;
+                fnc ten: s32 -> multiply 2, 5;
+            "#,
+            expect![[r#"
+                fnc multiply -> p0 * p1;
+                fnc ten -> multiply 2, 5;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn recursive_call() {
+        check(
+            r#"
+                fnc countdown(n: s32): s32 -> countdown n - 1;
+            "#,
+            expect![[r#"
+                fnc countdown -> countdown p0 - 1;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn mutually_recursive_calls() {
+        check(
+            r#"
+                fnc ping(n: s32): s32 -> pong n;
+                fnc pong(n: s32): s32 -> ping n;
+            "#,
+            expect![[r#"
+                fnc ping -> pong p0;
+                fnc pong -> ping p0;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn nested_function() {
+        check(
+            r#"
+                fnc outer(x: s32): s32 -> {
+                    let a = double x;
+                    fnc double(n: s32): s32 -> n * 2;
+                    a + double 1
+                };
+            "#,
+            expect![[r#"
+                fnc outer -> {
+                    let l0 = outer.double p0;
+                    l0 + outer.double 1
+                };
+                fnc outer.double -> p0 * 2;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn nested_function_shadows_outer_function() {
+        check(
+            r#"
+                fnc f: s32 -> 1;
+                fnc g: s32 -> {
+                    let a = { fnc f: s32 -> 2; f };
+                    a + f
+                };
+            "#,
+            expect![[r#"
+                fnc f -> 1;
+                fnc g -> {
+                    let l0 = { g.f };
+                    l0 + f
+                };
+                fnc g.f -> 2;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn nested_function_in_tail_block() {
+        check(
+            r#"
+                fnc f: s32 -> { { fnc h: s32 -> 2; h } };
+            "#,
+            expect![[r#"
+                fnc f -> { { f.h } };
+                fnc f.h -> 2;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn nested_function_cannot_see_enclosing_locals() {
+        check(
+            r#"
+                fnc outer(x: s32) -> {
+                    let a = 1;
+                    fnc inner: s32 -> a + x;
+                };
+            "#,
+            expect![[r#"
+                fnc outer -> {
+                    let l0 = 1;
+                };
+                fnc outer.inner -> <missing> + <missing>;
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedLocal { name: i.intern("a") }, 109..110),
+                    (LoweringDiagnosticKind::UndefinedLocal { name: i.intern("x") }, 113..114),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn nested_function_out_of_scope() {
+        check(
+            r#"
+                fnc outer -> {
+                    { fnc inner -> {}; };
+                    inner;
+                };
+            "#,
+            expect![[r#"
+                fnc outer -> {
+                    {};
+                    <missing>;
+                };
+                fnc outer.inner -> {};
+            "#]],
+            |i| [(LoweringDiagnosticKind::UndefinedLocal { name: i.intern("inner") }, 94..99)],
+        );
+    }
+
+    #[test]
+    fn break_inside_nested_function_in_loop() {
+        check(
+            r#"
+                fnc outer -> loop {
+                    fnc inner -> { break; };
+                };
+            "#,
+            expect![[r#"
+                fnc outer -> loop {};
+                fnc outer.inner -> {
+                    break;
+                };
+            "#]],
+            |_| [(LoweringDiagnosticKind::BreakOutsideLoop, 72..78)],
+        );
+    }
+
+    #[test]
+    fn nested_function_with_wrong_arg_count() {
+        check(
+            r#"
+                fnc outer -> {
+                    fnc inner(x: s32) -> {};
+                    inner;
+                };
+            "#,
+            expect![[r#"
+                fnc outer -> {
+                    <missing>;
+                };
+                fnc outer.inner -> {};
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::MismatchedArgCount {
+                        name: i.intern("inner"),
+                        expected: 1,
+                        got: 0,
+                        params: Some(ParamsLocation {
+                            module: None,
+                            range: TextRange::new(61.into(), 69.into()),
+                        }),
+                    },
+                    97..102,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn mismatched_arg_count() {
+        check(
+            r#"
+                fnc greeting: string -> id "Hello", "World";
+                fnc id(s: string): string -> s;
+            "#,
+            expect![[r#"
+                fnc greeting -> <missing>;
+                fnc id -> p0;
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::MismatchedArgCount {
+                        name: i.intern("id"),
+                        expected: 1,
+                        got: 2,
+                        params: Some(ParamsLocation {
+                            module: None,
+                            range: TextRange::new(84.into(), 95.into()),
+                        }),
+                    },
+                    41..43,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn call_with_no_args_when_some_were_expected() {
+        check(
+            r#"
+                fnc a: string -> id;
+                fnc id(s: string): string -> s;
+            "#,
+            expect![[r#"
+                fnc a -> <missing>;
+                fnc id -> p0;
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::MismatchedArgCount {
+                        name: i.intern("id"),
+                        expected: 1,
+                        got: 0,
+                        params: Some(ParamsLocation {
+                            module: None,
+                            range: TextRange::new(60.into(), 71.into()),
+                        }),
+                    },
+                    34..36,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn call_builtin() {
+        check(
+            r#"
+                fnc main: s32 -> rand_int 1, 6;
+            "#,
+            expect![[r#"
+                fnc main -> rand_int 1, 6;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn call_builtin_with_wrong_arg_count() {
+        check(
+            r#"
+                fnc main -> rand_seed;
+            "#,
+            expect![[r#"
+                fnc main -> <missing>;
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::MismatchedArgCount {
+                        name: i.intern("rand_seed"),
+                        expected: 1,
+                        got: 0,
+                        params: None,
+                    },
+                    29..38,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn call_parse_int() {
+        check(
+            r#"
+                fnc main: Option[s32] -> parse_int "42";
+            "#,
+            expect![[r#"
+                fnc main -> parse_int "42";
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn call_io_builtins() {
+        check(
+            r#"
+                fnc main -> {
+                    print "name: ";
+                    let name = read_line;
+                    println "hi"
+                };
+            "#,
+            expect![[r#"
+                fnc main -> {
+                    print "name: ";
+                    let l0 = read_line;
+                    println "hi"
+                };
+            "#]],
+            |i| [(LoweringDiagnosticKind::UnusedLocal { name: i.intern("name") }, 91..95)],
+        );
+    }
+
+    #[test]
+    fn variants() {
+        check(
+            r#"
+                fnc main -> {
+                    let a = Some 1;
+                    let b: Option[s32] = None;
+                    let c: Result[s32, string] = Ok 2;
+                    Err "e"
+                };
+            "#,
+            expect![[r#"
+                fnc main -> {
+                    let l0 = Some 1;
+                    let l1: Option[s32] = None;
+                    let l2: Result[s32, string] = Ok 2;
+                    Err "e"
+                };
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("a") }, 55..56),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("b") }, 91..92),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("c") }, 138..139),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn variant_with_wrong_arg_count() {
+        check(
+            r#"
+                fnc main -> Some;
+            "#,
+            expect![[r#"
+                fnc main -> <missing>;
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::MismatchedArgCount {
+                        name: i.intern("Some"),
+                        expected: 1,
+                        got: 0,
+                        params: None,
+                    },
+                    29..33,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn functions_take_precedence_over_variants() {
+        check(
+            r#"
+                fnc main: s32 -> Some;
+                fnc Some: s32 -> 4;
+            "#,
+            expect![[r#"
+                fnc main -> Some;
+                fnc Some -> 4;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn variant_patterns() {
+        check(
+            r#"
+                fnc main(o: Option[s32]) -> match o {
+                    Some 0 -> 0;
+                    Some n -> n;
+                    None -> 1
+                };
+            "#,
+            expect![[r#"
+                fnc main -> match p0 {
+                    Some 0 -> 0;
+                    Some l0 -> l0;
+                    None -> 1;
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn invalid_variant_patterns() {
+        check(
+            r#"
+                fnc main(o: Option[s32]) -> match o {
+                    Some -> 0;
+                    None n -> 1;
+                    Many n -> 2
+                };
+            "#,
+            expect![[r#"
+                fnc main -> match p0 {
+                    <missing> -> 0;
+                    <missing> -> 1;
+                    <missing> -> 2;
+                };
+            "#]],
+            |i| {
+                [
+                    (
+                        LoweringDiagnosticKind::MismatchedVariantPattern { variant: Variant::Some },
+                        75..79,
+                    ),
+                    (
+                        LoweringDiagnosticKind::MismatchedVariantPattern { variant: Variant::None },
+                        106..110,
+                    ),
+                    (LoweringDiagnosticKind::UndefinedVariant { name: i.intern("Many") }, 139..143),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn try_in_function_returning_option() {
+        check(
+            r#"
+                fnc main: Option[s32] -> Some {parse_int "1"}? + 1;
+            "#,
+            expect![[r#"
+                fnc main -> Some match { parse_int "1" } {
+                    Some l0 -> l0;
+                    None -> {
+                        return None;
+                    };
+                } + 1;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn try_in_function_returning_result() {
+        check(
+            r#"
+                fnc main(r: Result[s32, string]): Result[s32, string] -> Ok r?;
+            "#,
+            expect![[r#"
+                fnc main -> Ok match p0 {
+                    Ok l0 -> l0;
+                    Err l1 -> {
+                        return Err l1;
+                    };
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn try_in_nested_function() {
+        check(
+            r#"
+                fnc main: s32 -> {
+                    fnc first(r: Result[s32, string]): Result[s32, string] -> Ok r?;
+                    0
+                };
+            "#,
+            expect![[r#"
+                fnc main -> { 0 };
+                fnc main.first -> Ok match p0 {
+                    Ok l0 -> l0;
+                    Err l1 -> {
+                        return Err l1;
+                    };
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn try_in_infallible_function() {
+        check(
+            r#"
+                fnc main(o: Option[s32]): s32 -> o?;
+            "#,
+            expect![[r#"
+                fnc main -> {
+                    p0;
+                    <missing>
+                };
+            "#]],
+            |_| [(LoweringDiagnosticKind::TryInInfallibleFunction, 50..52)],
+        );
+    }
+
+    #[test]
+    fn generic_ty_with_wrong_arg_count() {
+        check(
+            r#"
+                fnc main -> {
+                    let a: Option = None;
+                    let b: Result[s32] = Ok 1;
+                    let c: s32[bool] = 1;
+                };
+            "#,
+            expect![[r#"
+                fnc main -> {
+                    let l0: Option[?] = None;
+                    let l1: Result[s32, ?] = Ok 1;
+                    let l2: s32 = 1;
+                };
+            "#]],
+            |i| {
+                [
+                    (
+                        LoweringDiagnosticKind::MismatchedTyArgCount {
+                            name: i.intern("Option"),
+                            expected: 1,
+                            got: 0,
+                        },
+                        58..64,
+                    ),
+                    (
+                        LoweringDiagnosticKind::MismatchedTyArgCount {
+                            name: i.intern("Result"),
+                            expected: 2,
+                            got: 1,
+                        },
+                        100..111,
+                    ),
+                    (
+                        LoweringDiagnosticKind::MismatchedTyArgCount {
+                            name: i.intern("s32"),
+                            expected: 0,
+                            got: 1,
+                        },
+                        147..156,
+                    ),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("a") }, 55..56),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("b") }, 97..98),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("c") }, 144..145),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn local_with_aliased_ty() {
+        check(
+            r#"
+                type Meters = s32;
+                fnc main -> {
+                    let distance: Meters = 5;
+                    let route: [Meters] = [distance];
+                };
+            "#,
+            expect![[r#"
+                fnc main -> {
+                    let l0: Meters = 5;
+                    let l1: [Meters] = [l0];
+                };
+            "#]],
+            |i| [(LoweringDiagnosticKind::UnusedLocal { name: i.intern("route") }, 136..141)],
+        );
+    }
+
+    #[test]
+    fn locals_with_ty_params() {
+        // like the locals of the function around them,
+        // its type parameters aren’t visible in nested functions
+        check(
+            r#"
+                fnc outer[T](x: T) -> {
+                    let a: T = x;
+                    fnc inner[U](y: U) -> {
+                        let b: (T, U) = (y, y);
+                    };
+                    let c: [T] = [a];
+                };
+            "#,
+            expect![[r#"
+                fnc outer -> {
+                    let l0: T = p0;
+                    let l1: [T] = [l0];
+                };
+                fnc outer.inner -> {
+                    let l2: (?, U) = (p0, p0);
+                };
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedTy { name: i.intern("T") }, 151..152),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("c") }, 214..215),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("b") }, 147..148),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn nested_ty_alias() {
+        check(
+            r#"
+                fnc main -> {
+                    type Meters = s32;
+                    let distance: Meters = 5;
+                };
+            "#,
+            expect![[r#"
+                fnc main -> {
+                    let l0: ? = 5;
+                };
+            "#]],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::NestedTyAlias, 51..69),
+                    (LoweringDiagnosticKind::UndefinedTy { name: i.intern("Meters") }, 104..110),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("distance") }, 94..102),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn functions_take_precedence_over_builtins() {
+        check(
+            r#"
+                fnc main: s32 -> rand_int;
+                fnc rand_int: s32 -> 4;
+            "#,
+            expect![[r#"
+                fnc main -> rand_int;
+                fnc rand_int -> 4;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn locals_take_precedence_over_functions() {
+        check(
+            r#"
+                fnc foo -> {};
+                fnc bar(n: s32) -> {};
+
+                fnc main -> {
+                    let foo = 0;
+                    let bar = 1;
+                    foo;
+                    bar;
+                };
+            "#,
+            expect![[r#"
+                fnc foo -> {};
+                fnc bar -> {};
+                fnc main -> {
+                    let l0 = 0;
+                    let l1 = 1;
+                    l0;
+                    l1;
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn locals_take_precedence_over_params() {
+        check(
+            r#"
+                fnc id(x: s32): s32 -> {
+                    let x = 3;
+                    x
+                };
+            "#,
+            expect![[r#"
+                fnc id -> {
+                    let l0 = 3;
+                    l0
+                };
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn local_with_args() {
+        check(
+            r#"
+                fnc a -> {
+                    let s = "foo";
+                    s 1, 2, 3
+                };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    let l0 = "foo";
+                    l0
+                };
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::CalledLocal {
+                        name: i.intern("s"),
+                        definition: Some(TextRange::new(52.into(), 53.into())),
+                    },
+                    83..84,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn param_with_args() {
+        check(
+            r#"
+                fnc a(n: s32) -> n 1;
+            "#,
+            expect![[r#"
+                fnc a -> p0;
+            "#]],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::CalledLocal {
+                        name: i.intern("n"),
+                        definition: Some(TextRange::new(23.into(), 24.into())),
+                    },
+                    34..35,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn local_def_of_local_with_args_missing_semicolon() {
+        check(
+            r#"
+                fnc a -> {
+                    let s = 1;
+                    let t = s t + 1
+                };
+            "#,
+            expect![[r#"
+                fnc a -> {
+                    let l0 = 1;
+                    let l1 = l0;
+                };
+            "#]],
+            |i| {
+                [
+                    (
+                        LoweringDiagnosticKind::MissingSemicolonAfterLocal {
+                            name: i.intern("s"),
+                            statement_end: 94.into(),
+                        },
+                        87..88,
+                    ),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("t") }, 83..84),
+                ]
+            },
         );
     }
 
     #[test]
-    fn out_of_range_int_literal() {
+    fn local_def_of_local_with_args() {
         check(
             r#"
-                fnc a: s32 -> 999999999999999;
+                fnc a -> {
+                    let s = 1;
+                    let t = s 2;
+                };
             "#,
             expect![[r#"
-                fnc a -> <missing>;
+                fnc a -> {
+                    let l0 = 1;
+                    let l1 = l0;
+                };
             "#]],
-            |_| [(LoweringDiagnosticKind::OutOfRangeIntLiteral, 31..46)],
+            |i| {
+                [
+                    (
+                        LoweringDiagnosticKind::CalledLocal {
+                            name: i.intern("s"),
+                            definition: Some(TextRange::new(52.into(), 53.into())),
+                        },
+                        87..88,
+                    ),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("t") }, 83..84),
+                ]
+            },
         );
     }
 
     #[test]
-    fn binary_expr() {
+    fn for_loop() {
         check(
             r#"
-                fnc sum: s32 -> 2 + 2;
+                fnc f -> {
+                    let n = 10;
+                    for i in 0..n {
+                        i * 2;
+                    };
+                };
             "#,
             expect![[r#"
-                fnc sum -> 2 + 2;
+                fnc f -> {
+                    let l0 = 10;
+                    for l1 in 0..l0 {
+                        l1 * 2;
+                    };
+                };
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn string_literal() {
+    fn for_loop_variable_out_of_scope() {
         check(
             r#"
-                fnc crab: string -> "🦀";
+                fnc f: s32 -> {
+                    for i in 0..10 {};
+                    i
+                };
             "#,
             expect![[r#"
-                fnc crab -> "🦀";
+                fnc f -> {
+                    for l0 in 0..10 {};
+                    <missing>
+                };
             "#]],
-            |_| [],
+            |i| [(LoweringDiagnosticKind::UndefinedLocal { name: i.intern("i") }, 92..93)],
         );
     }
 
     #[test]
-    fn string_literal_with_escapes() {
+    fn assign_to_mutable_local() {
         check(
             r#"
-                fnc f: string -> "\r\n\t\"\\";
+                fnc f: s32 -> {
+                    let mut x = 1;
+                    x = x + 1;
+                    x
+                };
             "#,
             expect![[r#"
-                fnc f -> "\r\n\t\"\\";
+                fnc f -> {
+                    let mut l0 = 1;
+                    l0 = l0 + 1;
+                    l0
+                };
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn string_literal_with_invalid_escapes() {
+    fn assign_to_immutable_local() {
         check(
             r#"
-                fnc f: string -> "a\*b\🤡c";
+                fnc f -> {
+                    let x = 1;
+                    x = 2;
+                };
             "#,
             expect![[r#"
-                fnc f -> "abc";
+                fnc f -> {
+                    let l0 = 1;
+                    l0 = 2;
+                };
             "#]],
-            |_| {
+            |i| {
                 [
-                    (LoweringDiagnosticKind::InvalidEscape, 36..38),
-                    (LoweringDiagnosticKind::InvalidEscape, 39..44),
+                    (
+                        LoweringDiagnosticKind::AssignToImmutable {
+                            name: i.intern("x"),
+                            is_param: false,
+                        },
+                        79..80,
+                    ),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("x") }, 52..53),
                 ]
             },
         );
     }
 
     #[test]
-    fn nested_binary_exprs() {
-        check(
-            r#"
-                fnc a: s32 -> 1 + 2 * 3 + 4 * 5;
-            "#,
-            expect![[r#"
-                fnc a -> 1 + 2 * 3 + 4 * 5;
-            "#]],
-            |_| [],
-        );
-    }
-
-    #[test]
-    fn empty_block() {
+    fn unused_locals() {
         check(
             r#"
-                fnc nil -> {};
+                fnc f -> {
+                    let a = 1;
+                    let mut b = 2;
+                    b = 3;
+                    let (c, _d) = (4, 5);
+                    let _e = 6;
+                    for i in 0..c {};
+                    match 7 { n -> 8 };
+                };
             "#,
             expect![[r#"
-                fnc nil -> {};
+                fnc f -> {
+                    let l0 = 1;
+                    let mut l1 = 2;
+                    l1 = 3;
+                    let (l2, l3) = (4, 5);
+                    let l4 = 6;
+                    for l5 in 0..l2 {};
+                    match 7 {
+                        l6 -> 8;
+                    };
+                };
             "#]],
-            |_| [],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("a") }, 52..53),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("b") }, 87..88),
+                ]
+            },
         );
     }
 
     #[test]
-    fn block_with_one_expr() {
+    fn locals_referred_to_by_nested_functions_are_not_unused() {
         check(
             r#"
-                fnc one: s32 -> { 1 };
+                fnc f -> {
+                    let x = 1;
+                    let y = 2;
+                    fnc g: s32 -> x;
+                    fnc h -> { y = 3; };
+                    let z = 4;
+                };
             "#,
             expect![[r#"
-                fnc one -> { 1 };
+                fnc f -> {
+                    let l0 = 1;
+                    let l1 = 2;
+                    let l2 = 4;
+                };
+                fnc f.g -> <missing>;
+                fnc f.h -> {
+                    3;
+                };
             "#]],
-            |_| [],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedLocal { name: i.intern("x") }, 124..125),
+                    (LoweringDiagnosticKind::UndefinedLocal { name: i.intern("y") }, 158..159),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("z") }, 192..193),
+                ]
+            },
         );
     }
 
     #[test]
-    fn block_with_one_local_def() {
+    fn assign_to_param() {
         check(
             r#"
-                fnc foo -> { let a = 7; };
+                fnc f(x: s32) -> {
+                    x = 2;
+                };
             "#,
             expect![[r#"
-                fnc foo -> {
-                    let l0 = 7;
+                fnc f -> {
+                    2;
                 };
             "#]],
-            |_| [],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::AssignToImmutable {
+                        name: i.intern("x"),
+                        is_param: true,
+                    },
+                    56..57,
+                )]
+            },
         );
     }
 
     #[test]
-    fn block_with_one_expr_statement() {
+    fn assign_to_undefined_local() {
         check(
             r#"
-                fnc a -> { 100; };
+                fnc f -> {
+                    x = 2;
+                };
             "#,
             expect![[r#"
-                fnc a -> {
-                    100;
+                fnc f -> {
+                    2;
                 };
             "#]],
-            |_| [],
+            |i| [(LoweringDiagnosticKind::UndefinedLocal { name: i.intern("x") }, 48..49)],
         );
     }
 
     #[test]
-    fn block_with_local_def_and_usage() {
+    fn assign_to_expr() {
         check(
             r#"
-                fnc magic_number: s32 -> { let n = 42; n };
+                fnc f -> {
+                    let mut x = 1;
+                    x + 1 = 2;
+                };
             "#,
             expect![[r#"
-                fnc magic_number -> {
-                    let l0 = 42;
-                    l0
+                fnc f -> {
+                    let mut l0 = 1;
+                    2;
                 };
             "#]],
-            |_| [],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::InvalidAssignTarget, 83..88),
+                    (LoweringDiagnosticKind::UnusedLocal { name: i.intern("x") }, 56..57),
+                ]
+            },
         );
     }
 
     #[test]
-    fn block_with_multiple_local_defs() {
+    fn nested_blocks() {
         check(
             r#"
-                fnc f -> {
-                    let a = 1;
-                    let b = 2;
-                    let c = 3;
-                    let d = 4;
+                fnc main -> {
+                    let foo = {
+                        let bar = {
+                            let baz = 9;
+                            baz * 10
+                        };
+                        bar - 1
+                    };
+                    foo + 3
                 };
             "#,
             expect![[r#"
-                fnc f -> {
-                    let l0 = 1;
-                    let l1 = 2;
-                    let l2 = 3;
-                    let l3 = 4;
+                fnc main -> {
+                    let l2 = {
+                        let l1 = {
+                            let l0 = 9;
+                            l0 * 10
+                        };
+                        l1 - 1
+                    };
+                    l2 + 3
                 };
             "#]],
             |_| [],
@@ -929,424 +4677,583 @@ mod tests {
     }
 
     #[test]
-    fn multiple_functions() {
+    fn self_recursive_function() {
         check(
             r#"
-                fnc a -> {};
-                fnc d -> {};
-                fnc b -> {};
-                fnc e -> {};
-                fnc c -> {};
+                fnc die(n: s32) -> die n;
             "#,
             expect![[r#"
-                fnc a -> {};
-                fnc d -> {};
-                fnc b -> {};
-                fnc e -> {};
-                fnc c -> {};
+                fnc die -> die p0;
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn undefined_local() {
+    fn mutually_recursive_functions() {
         check(
             r#"
-                fnc foo -> bar;
+                fnc a -> b;
+                fnc b -> a;
             "#,
             expect![[r#"
-                fnc foo -> <missing>;
+                fnc a -> b;
+                fnc b -> a;
             "#]],
-            |i| [(LoweringDiagnosticKind::UndefinedLocal { name: i.intern("bar") }, 28..31)],
+            |_| [],
         );
     }
 
     #[test]
-    fn scope_locals_to_blocks() {
+    fn functions_from_other_module() {
         check(
             r#"
-                fnc a: s32 -> {
-                    { let foo = 5; };
-                    foo
-                }
+                #- main
+                fnc a: s32 -> foo.id foo.constant;
+                #- foo
+                pub fnc id(n: s32): s32 -> n;
+                pub fnc constant: s32 -> 42;
             "#,
             expect![[r#"
-                fnc a -> {
-                    {
-                        let l0 = 5;
-                    };
-                    <missing>
-                };
+                fnc a -> foo.id foo.constant;
+
+                References to other modules:
+                - foo.id
+                - foo.constant
             "#]],
-            |i| [(LoweringDiagnosticKind::UndefinedLocal { name: i.intern("foo") }, 91..94)],
+            |_| [],
         );
     }
 
     #[test]
-    fn reference_local_from_block() {
+    fn function_from_undefined_module() {
         check(
             r#"
-                fnc f -> {
-                    let a = 7;
-                    { a };
-                }
+                fnc main -> foo.bar;
             "#,
             expect![[r#"
-                fnc f -> {
-                    let l0 = 7;
-                    { l0 };
-                };
+                fnc main -> <missing>;
             "#]],
-            |_| [],
+            |i| [(LoweringDiagnosticKind::UndefinedModule { name: i.intern("foo") }, 29..32)],
         );
     }
 
     #[test]
-    fn binary_expr_with_missing_operand() {
+    fn function_from_undefined_module_with_same_name_as_local_function() {
         check(
             r#"
-                fnc number: s32 -> 1 +;
+                fnc a -> a.foo;
             "#,
             expect![[r#"
-                fnc number -> 1 + <missing>;
+                fnc a -> <missing>;
             "#]],
-            |_| [],
+            |i| [(LoweringDiagnosticKind::UndefinedModule { name: i.intern("a") }, 26..27)],
         );
     }
 
     #[test]
-    fn unused_params() {
+    fn undefined_function_from_other_module() {
         check(
             r#"
-                fnc add(x: s32, y: s32) -> {};
+                #- main
+                fnc trim(s: string): string -> utils.strip s, " ";
+                #- utils
             "#,
             expect![[r#"
-                fnc add -> {};
+                fnc trim -> <missing>;
             "#]],
-            |_| [],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::UndefinedFunction {
+                        module: i.intern("utils"),
+                        function: i.intern("strip"),
+                    },
+                    53..58,
+                )]
+            },
         );
     }
 
     #[test]
-    fn used_params() {
+    fn mismatched_arg_count_for_function_from_other_module() {
         check(
             r#"
-                fnc add(x: s32, y: s32): s32 -> x + y;
+                #- main
+                fnc the_answer: s32 -> math.add 14, 14, 14;
+                #- math
+                pub fnc add(x: s32, y: s32): s32 -> x + y;
             "#,
             expect![[r#"
-                fnc add -> p0 + p1;
+                fnc the_answer -> <missing>;
+
+                References to other modules:
+                - math.add
             "#]],
-            |_| [],
+            |i| {
+                [(
+                    LoweringDiagnosticKind::MismatchedArgCount {
+                        name: i.intern("math.add"),
+                        expected: 2,
+                        got: 3,
+                        params: Some(ParamsLocation {
+                            module: Some(Name(i.intern("math"))),
+                            range: TextRange::new(27.into(), 43.into()),
+                        }),
+                    },
+                    44..47,
+                )]
+            },
         );
     }
 
     #[test]
-    fn call_with_no_args() {
+    fn imported_functions() {
         check(
             r#"
-                fnc five: s32 -> 5;
-                fnc ten: s32 -> five + five;
+                #- main
+                use math.add;
+                use math.double;
+                fnc a: s32 -> add {double 1}, 2;
+                #- math
+                pub fnc add(x: s32, y: s32): s32 -> x + y;
+                pub fnc double(x: s32): s32 -> x * 2;
             "#,
             expect![[r#"
-                fnc five -> 5;
-                fnc ten -> five + five;
+                fnc a -> math.add { math.double 1 }, 2;
+
+                References to other modules:
+                - math.add
+                - math.double
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn call_with_args() {
+    fn imports_of_undefined_module_and_function() {
         check(
             r#"
-                fnc multiply(x: s32, y: s32): s32 -> x * y;
-                fnc ten: s32 -> multiply 2, 5;
+                #- main
+                use io.print;
+                use math.sub;
+                fnc a -> print {sub 2, 1};
+                #- math
+                pub fnc add(x: s32, y: s32): s32 -> x + y;
             "#,
             expect![[r#"
-                fnc multiply -> p0 * p1;
-                fnc ten -> multiply 2, 5;
+                fnc a -> <missing>;
             "#]],
-            |_| [],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedModule { name: i.intern("io") }, 20..22),
+                    (
+                        LoweringDiagnosticKind::UndefinedFunction {
+                            module: i.intern("math"),
+                            function: i.intern("sub"),
+                        },
+                        55..58,
+                    ),
+                ]
+            },
         );
     }
 
     #[test]
-    fn mismatched_arg_count() {
+    fn mismatched_arg_count_for_imported_function() {
         check(
             r#"
-                fnc greeting: string -> id "Hello", "World";
-                fnc id(s: string): string -> s;
+                #- main
+                use math.add;
+                fnc a: s32 -> add 1;
+                #- math
+                pub fnc add(x: s32, y: s32): s32 -> x + y;
             "#,
             expect![[r#"
-                fnc greeting -> <missing>;
-                fnc id -> p0;
+                fnc a -> <missing>;
+
+                References to other modules:
+                - math.add
             "#]],
             |i| {
                 [(
                     LoweringDiagnosticKind::MismatchedArgCount {
-                        name: i.intern("id"),
-                        expected: 1,
-                        got: 2,
+                        name: i.intern("math.add"),
+                        expected: 2,
+                        got: 1,
+                        params: Some(ParamsLocation {
+                            module: Some(Name(i.intern("math"))),
+                            range: TextRange::new(27.into(), 43.into()),
+                        }),
                     },
-                    41..43,
+                    60..63,
                 )]
             },
         );
     }
 
     #[test]
-    fn call_with_no_args_when_some_were_expected() {
+    fn function_with_same_name_as_import() {
         check(
             r#"
-                fnc a: string -> id;
-                fnc id(s: string): string -> s;
+                #- main
+                use math.add;
+                fnc add(x: s32, y: s32): s32 -> x - y;
+                fnc a: s32 -> add 1, 2;
+                #- math
+                pub fnc add(x: s32, y: s32): s32 -> x + y;
             "#,
             expect![[r#"
-                fnc a -> <missing>;
-                fnc id -> p0;
+                fnc a -> math.add 1, 2;
+
+                References to other modules:
+                - math.add
+            "#]],
+            |_| [], // indexing already emits a diagnostic for this
+        );
+    }
+
+    #[test]
+    fn private_functions_from_other_module() {
+        check(
+            r#"
+                #- main
+                use math.add;
+                fnc a: s32 -> add {math.double 1}, 2;
+                #- math
+                fnc add(x: s32, y: s32): s32 -> x + y;
+                fnc double(x: s32): s32 -> x * 2;
+            "#,
+            expect![[r#"
+                fnc a -> math.add { math.double 1 }, 2;
+
+                References to other modules:
+                - math.add
+                - math.double
             "#]],
             |i| {
-                [(
-                    LoweringDiagnosticKind::MismatchedArgCount {
-                        name: i.intern("id"),
-                        expected: 1,
-                        got: 0,
-                    },
-                    34..36,
-                )]
+                [
+                    (
+                        LoweringDiagnosticKind::PrivateFunction {
+                            module: i.intern("math"),
+                            function: i.intern("add"),
+                            definition: TextRange::new(16.into(), 54.into()),
+                        },
+                        25..28,
+                    ),
+                    (
+                        LoweringDiagnosticKind::PrivateFunction {
+                            module: i.intern("math"),
+                            function: i.intern("double"),
+                            definition: TextRange::new(71.into(), 104.into()),
+                        },
+                        70..76,
+                    ),
+                ]
             },
         );
     }
 
     #[test]
-    fn locals_take_precedence_over_functions() {
+    fn nested_import() {
         check(
             r#"
-                fnc foo -> {};
-                fnc bar(n: s32) -> {};
-
-                fnc main -> {
-                    let foo = 0;
-                    let bar = 1;
-                    foo;
-                    bar;
+                #- main
+                fnc a -> {
+                    use math.add;
                 };
+                #- math
+                pub fnc add(x: s32, y: s32): s32 -> x + y;
             "#,
             expect![[r#"
-                fnc foo -> {};
-                fnc bar -> {};
-                fnc main -> {
-                    let l0 = 0;
-                    let l1 = 1;
-                    l0;
-                    l1;
-                };
+                fnc a -> {};
             "#]],
-            |_| [],
+            |_| [(LoweringDiagnosticKind::NestedImport, 47..60)],
         );
     }
 
     #[test]
-    fn locals_take_precedence_over_params() {
+    fn consts() {
         check(
             r#"
-                fnc id(x: s32): s32 -> {
-                    let x = 3;
-                    x
-                };
+                #- main
+                fnc area: s32 -> WIDTH * HEIGHT;
+                const WIDTH: s32 = HEIGHT * 2;
+                const HEIGHT = 10;
             "#,
             expect![[r#"
-                fnc id -> {
-                    let l0 = 3;
-                    l0
-                };
+                const WIDTH: s32 = HEIGHT * 2;
+                const HEIGHT = 10;
+                fnc area -> WIDTH * HEIGHT;
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn local_with_args() {
+    fn const_shadowed_by_local() {
         check(
             r#"
-                fnc a -> {
-                    let s = "foo";
-                    s 1, 2, 3
+                #- main
+                const X = 1;
+                fnc f -> {
+                    let X = 2;
+                    X
                 };
             "#,
             expect![[r#"
-                fnc a -> {
-                    let l0 = "foo";
+                const X = 1;
+                fnc f -> {
+                    let l0 = 2;
                     l0
                 };
             "#]],
-            |i| [(LoweringDiagnosticKind::CalledLocal { name: i.intern("s") }, 83..84)],
+            |_| [],
         );
     }
 
     #[test]
-    fn nested_blocks() {
+    fn non_const_exprs_in_const() {
         check(
             r#"
-                fnc main -> {
-                    let foo = {
-                        let bar = {
-                            let baz = 9;
-                            baz * 10
-                        };
-                        bar - 1
-                    };
-                    foo + 3
-                };
+                #- main
+                fnc two: s32 -> 2;
+                const A = 1 + two;
+                const B = -{1 * 2};
+                const C = (1, 2);
             "#,
             expect![[r#"
-                fnc main -> {
-                    let l2 = {
-                        let l1 = {
-                            let l0 = 9;
-                            l0 * 10
-                        };
-                        l1 - 1
-                    };
-                    l2 + 3
-                };
+                const A = 1 + two;
+                const B = -{ 1 * 2 };
+                const C = (1, 2);
+                fnc two -> 2;
             "#]],
-            |_| [],
+            |_| {
+                [
+                    (LoweringDiagnosticKind::NonConstExpr, 65..68),
+                    (LoweringDiagnosticKind::NonConstExpr, 132..138),
+                ]
+            },
         );
     }
 
     #[test]
-    fn self_recursive_function() {
+    fn consts_with_same_name() {
         check(
             r#"
-                fnc die(n: s32) -> die n;
+                #- main
+                fnc f -> 1;
+                const X = 1;
+                const X = 2;
+                const f = 3;
             "#,
             expect![[r#"
-                fnc die -> die p0;
+                const X = 1;
+                fnc f -> 1;
             "#]],
-            |_| [],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::ConstAlreadyDefined { name: i.intern("X") }, 73..85),
+                    (LoweringDiagnosticKind::ConstAlreadyDefined { name: i.intern("f") }, 102..114),
+                ]
+            },
         );
     }
 
     #[test]
-    fn mutually_recursive_functions() {
+    fn nested_const() {
         check(
             r#"
-                fnc a -> b;
-                fnc b -> a;
+                #- main
+                fnc a -> {
+                    const X = 1;
+                };
             "#,
             expect![[r#"
-                fnc a -> b;
-                fnc b -> a;
+                fnc a -> {};
             "#]],
-            |_| [],
+            |_| [(LoweringDiagnosticKind::NestedConst, 47..59)],
         );
     }
 
     #[test]
-    fn functions_from_other_module() {
+    fn param_default_values() {
         check(
             r#"
                 #- main
-                fnc a: s32 -> foo.id foo.constant;
-                #- foo
-                fnc id(n: s32): s32 -> n;
-                fnc constant: s32 -> 42;
+                fnc greet(greeting: string, name: string = NAME, times: s32 = 1 + 1) -> {};
+                const NAME = "world";
+                fnc main -> {
+                    greet "hi";
+                    greet "hi", "there";
+                    greet "hi", "there", 3;
+                };
             "#,
             expect![[r#"
-                fnc a -> foo.id foo.constant;
-
-                References to other modules:
-                - foo.id
-                - foo.constant
+                const NAME = "world";
+                fnc greet(p0, p1 = NAME, p2 = 1 + 1) -> {};
+                fnc main -> {
+                    greet "hi";
+                    greet "hi", "there";
+                    greet "hi", "there", 3;
+                };
             "#]],
             |_| [],
         );
     }
 
     #[test]
-    fn function_from_undefined_module() {
+    fn param_default_values_with_wrong_arg_count() {
         check(
             r#"
-                fnc main -> foo.bar;
+                #- main
+                fnc f(x: s32, y: s32 = x) -> {};
+                fnc main -> {
+                    f;
+                    f 1, 2, 3;
+                };
             "#,
             expect![[r#"
-                fnc main -> <missing>;
+                fnc f(p0, p1 = <missing>) -> {};
+                fnc main -> {
+                    <missing>;
+                    <missing>;
+                };
             "#]],
-            |i| [(LoweringDiagnosticKind::UndefinedModule { name: i.intern("foo") }, 29..32)],
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedLocal { name: i.intern("x") }, 39..40),
+                    (
+                        LoweringDiagnosticKind::MismatchedArgCount {
+                            name: i.intern("f"),
+                            expected: 1,
+                            got: 0,
+                            params: Some(ParamsLocation {
+                                module: None,
+                                range: TextRange::new(21.into(), 41.into()),
+                            }),
+                        },
+                        99..100,
+                    ),
+                    (
+                        LoweringDiagnosticKind::MismatchedArgCount {
+                            name: i.intern("f"),
+                            expected: 2,
+                            got: 3,
+                            params: Some(ParamsLocation {
+                                module: None,
+                                range: TextRange::new(21.into(), 41.into()),
+                            }),
+                        },
+                        122..123,
+                    ),
+                ]
+            },
         );
     }
 
     #[test]
-    fn function_from_undefined_module_with_same_name_as_local_function() {
+    fn functions_with_same_name() {
         check(
             r#"
-                fnc a -> a.foo;
+                #- main
+                fnc foo: s32 -> 10;
+                fnc foo: s32 -> 92;
+                fnc foo: string -> "bar";
             "#,
             expect![[r#"
-                fnc a -> <missing>;
+                fnc foo -> 10;
             "#]],
-            |i| [(LoweringDiagnosticKind::UndefinedModule { name: i.intern("a") }, 26..27)],
+            |_| [], // indexing already emits a diagnostic for this
         );
     }
 
     #[test]
-    fn undefined_function_from_other_module() {
+    fn method_calls() {
         check(
             r#"
-                #- main
-                fnc trim(s: string): string -> utils.strip s, " ";
-                #- utils
+                fnc double(n: s32): s32 -> n * 2;
+                fnc add(a: s32, b: s32): s32 -> a + b;
+                fnc f(x: s32) -> {
+                    let s = "abc";
+                    s.len;
+                    "abc".substring 1, 2;
+                    x.double.add 3;
+                    (x + 1).double;
+                };
             "#,
             expect![[r#"
-                fnc trim -> <missing>;
+                fnc double -> p0 * 2;
+                fnc add -> p0 + p1;
+                fnc f -> {
+                    let l0 = "abc";
+                    len l0;
+                    substring "abc", 1, 2;
+                    add double p0, 3;
+                    double (p0 + 1);
+                };
             "#]],
-            |i| [(LoweringDiagnosticKind::UndefinedLocal { name: i.intern("strip") }, 53..58)],
+            |_| [],
         );
     }
 
     #[test]
-    fn mismatched_arg_count_for_function_from_other_module() {
+    fn method_call_on_local_shadowing_module() {
         check(
             r#"
                 #- main
-                fnc the_answer: s32 -> math.add 14, 14, 14;
-                #- math
-                fnc add(x: s32, y: s32): s32 -> x + y;
+                fnc a -> {
+                    let foo = 1;
+                    foo.id
+                };
+                fnc id(n: s32): s32 -> n;
+                #- foo
+                pub fnc id(n: s32): s32 -> n;
             "#,
             expect![[r#"
-                fnc the_answer -> <missing>;
-
-                References to other modules:
-                - math.add
+                fnc id -> p0;
+                fnc a -> {
+                    let l0 = 1;
+                    id l0
+                };
             "#]],
-            |i| {
-                [(
-                    LoweringDiagnosticKind::MismatchedArgCount {
-                        name: i.intern("add"),
-                        expected: 2,
-                        got: 3,
-                    },
-                    44..47,
-                )]
-            },
+            |_| [],
         );
     }
 
     #[test]
-    fn functions_with_same_name() {
+    fn undefined_method_and_mismatched_arg_count() {
         check(
             r#"
-                #- main
-                fnc foo: s32 -> 10;
-                fnc foo: s32 -> 92;
-                fnc foo: string -> "bar";
+                fnc add(a: s32, b: s32): s32 -> a + b;
+                fnc f -> {
+                    1.foo;
+                    1.add;
+                };
             "#,
             expect![[r#"
-                fnc foo -> 10;
+                fnc add -> p0 + p1;
+                fnc f -> {
+                    <missing>;
+                    <missing>;
+                };
             "#]],
-            |_| [], // indexing already emits a diagnostic for this
+            |i| {
+                [
+                    (LoweringDiagnosticKind::UndefinedMethod { name: i.intern("foo") }, 105..108),
+                    (
+                        LoweringDiagnosticKind::MismatchedArgCount {
+                            name: i.intern("add"),
+                            expected: 2,
+                            got: 1,
+                            params: Some(ParamsLocation {
+                                module: None,
+                                range: TextRange::new(24.into(), 40.into()),
+                            }),
+                        },
+                        132..135,
+                    ),
+                ]
+            },
         );
     }
 }
@@ -0,0 +1,132 @@
+use crate::{Function, Param, Ty};
+
+/// A function provided by the language itself
+/// that can be called from any module without a path.
+///
+/// Functions defined in a module shadow builtins with the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    RandSeed,
+    RandInt,
+    ToString,
+    ParseInt,
+    Print,
+    Println,
+    ReadLine,
+    Assert,
+    Len,
+    ArrayLen,
+    Substring,
+    Contains,
+    CharAt,
+    Args,
+    Env,
+    ReadFile,
+    WriteFile,
+    ClockMs,
+}
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let builtin = match name {
+            "rand_seed" => Self::RandSeed,
+            "rand_int" => Self::RandInt,
+            "to_string" => Self::ToString,
+            "parse_int" => Self::ParseInt,
+            "print" => Self::Print,
+            "println" => Self::Println,
+            "read_line" => Self::ReadLine,
+            "assert" => Self::Assert,
+            "len" => Self::Len,
+            "array_len" => Self::ArrayLen,
+            "substring" => Self::Substring,
+            "contains" => Self::Contains,
+            "char_at" => Self::CharAt,
+            "args" => Self::Args,
+            "env" => Self::Env,
+            "read_file" => Self::ReadFile,
+            "write_file" => Self::WriteFile,
+            "clock_ms" => Self::ClockMs,
+            _ => return None,
+        };
+
+        Some(builtin)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RandSeed => "rand_seed",
+            Self::RandInt => "rand_int",
+            Self::ToString => "to_string",
+            Self::ParseInt => "parse_int",
+            Self::Print => "print",
+            Self::Println => "println",
+            Self::ReadLine => "read_line",
+            Self::Assert => "assert",
+            Self::Len => "len",
+            Self::ArrayLen => "array_len",
+            Self::Substring => "substring",
+            Self::Contains => "contains",
+            Self::CharAt => "char_at",
+            Self::Args => "args",
+            Self::Env => "env",
+            Self::ReadFile => "read_file",
+            Self::WriteFile => "write_file",
+            Self::ClockMs => "clock_ms",
+        }
+    }
+
+    /// How many type parameters the builtin has,
+    /// which its [`Builtin::signature`] refers to by index alone
+    /// since they have no names in any module.
+    pub fn ty_param_count(self) -> usize {
+        match self {
+            Self::ArrayLen => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn signature(self) -> Function {
+        let (params, return_ty) = match self {
+            Self::RandSeed => (vec![Ty::S32], Ty::Unit),
+            Self::RandInt => (vec![Ty::S32, Ty::S32], Ty::S32),
+            Self::ToString => (vec![Ty::S32], Ty::String),
+            Self::ParseInt => (vec![Ty::String], Ty::Option(Box::new(Ty::S32))),
+            Self::Print | Self::Println => (vec![Ty::String], Ty::Unit),
+            // `None` once there’s nothing left to read
+            Self::ReadLine => (Vec::new(), Ty::Option(Box::new(Ty::String))),
+            Self::Assert => (vec![Ty::Bool, Ty::String], Ty::Unit),
+            // strings are indexed by chars rather than bytes
+            Self::Len => (vec![Ty::String], Ty::S32),
+            Self::ArrayLen => (vec![Ty::Array(Box::new(element_ty()))], Ty::S32),
+            Self::Substring => (vec![Ty::String, Ty::S32, Ty::S32], Ty::String),
+            Self::Contains => (vec![Ty::String, Ty::String], Ty::Bool),
+            Self::CharAt => (vec![Ty::String, Ty::S32], Ty::Char),
+            Self::Args => (Vec::new(), Ty::Array(Box::new(Ty::String))),
+            // `None` if the environment variable isn’t set
+            Self::Env => (vec![Ty::String], Ty::Option(Box::new(Ty::String))),
+            Self::ReadFile => (vec![Ty::String], Ty::String),
+            Self::WriteFile => (vec![Ty::String, Ty::String], Ty::Unit),
+            // milliseconds since some point in the past which never changes
+            Self::ClockMs => (Vec::new(), Ty::S64),
+        };
+
+        Function {
+            ty_params: Vec::new(),
+            params: params
+                .into_iter()
+                .map(|ty| Param { name: None, ty, has_default: false })
+                .collect(),
+            return_ty,
+            infers_return_ty: false,
+            is_pub: true,
+            is_extern: false,
+            attrs: Vec::new(),
+        }
+    }
+}
+
+// the type parameter of builtins which work with arrays of any type
+fn element_ty() -> Ty {
+    Ty::Param { idx: 0, name: "T".to_string() }
+}
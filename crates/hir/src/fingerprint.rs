@@ -0,0 +1,313 @@
+use crate::{Bodies, Expr, Index, LocalDef, Name, Path, Pattern, Statement};
+use arena::Id;
+use rustc_hash::{FxHashMap, FxHasher};
+use std::hash::{Hash, Hasher};
+use text_size::{TextRange, TextSize};
+
+/// A hash of the contents of a function’s body.
+///
+/// It doesn’t depend on the IDs lowering happened to give the parts of the body,
+/// or on where the function is in its module,
+/// so it stays the same as long as the function itself does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint(pub u64);
+
+/// The parts of a function’s body in an order which only depends on their contents,
+/// so that what was worked out about the body before it was lowered again
+/// can be matched up with the new IDs.
+#[derive(Debug, Clone)]
+pub struct BodyLayout {
+    pub fingerprint: Fingerprint,
+    pub exprs: Vec<Id<Expr>>,
+    pub local_defs: Vec<Id<LocalDef>>,
+    /// The functions the body calls, in the order it first calls them.
+    pub callees: Vec<Path>,
+    /// The constants the body uses, in the order it first uses them.
+    pub consts: Vec<Name>,
+}
+
+impl Bodies {
+    /// Walks over the body and parameter defaults of `function`.
+    ///
+    /// Ranges are hashed relative to the start of the function,
+    /// since it’s only their positions within the function which matter.
+    pub fn layout(&self, function: Name, index: &Index) -> BodyLayout {
+        let mut ctx = Ctx {
+            bodies: self,
+            base: index.range_info(function).whole.start(),
+            hasher: FxHasher::default(),
+            exprs: Vec::new(),
+            local_defs: FxHashMap::default(),
+            local_defs_in_order: Vec::new(),
+            callees: Vec::new(),
+            consts: Vec::new(),
+        };
+
+        let param_defaults = self.param_defaults.get(&function).map_or(&[][..], Vec::as_slice);
+        param_defaults.len().hash(&mut ctx.hasher);
+        for default in param_defaults {
+            match default {
+                Some(default) => ctx.walk_expr(*default),
+                None => 0_u8.hash(&mut ctx.hasher),
+            }
+        }
+
+        ctx.walk_expr(self.function_body(function));
+
+        BodyLayout {
+            fingerprint: Fingerprint(ctx.hasher.finish()),
+            exprs: ctx.exprs,
+            local_defs: ctx.local_defs_in_order,
+            callees: ctx.callees,
+            consts: ctx.consts,
+        }
+    }
+}
+
+struct Ctx<'a> {
+    bodies: &'a Bodies,
+    base: TextSize,
+    hasher: FxHasher,
+    exprs: Vec<Id<Expr>>,
+    // where each local is in `local_defs_in_order`,
+    // which uses of it are hashed as instead of its ID
+    local_defs: FxHashMap<Id<LocalDef>, u32>,
+    local_defs_in_order: Vec<Id<LocalDef>>,
+    callees: Vec<Path>,
+    consts: Vec<Name>,
+}
+
+impl Ctx<'_> {
+    fn walk_expr(&mut self, expr: Id<Expr>) {
+        self.exprs.push(expr);
+        self.hash_range(self.bodies.expr_ranges.get(expr).copied());
+        self.hash_range(self.bodies.annotation_ranges.get(expr).copied());
+
+        let expr = &self.bodies.exprs[expr];
+        std::mem::discriminant(expr).hash(&mut self.hasher);
+
+        match expr {
+            Expr::Missing | Expr::Unit => {}
+            Expr::IntLiteral(n) => n.hash(&mut self.hasher),
+            Expr::BoolLiteral(b) => b.hash(&mut self.hasher),
+            Expr::StringLiteral(s) => s.hash(&mut self.hasher),
+            Expr::CharLiteral(c) => c.hash(&mut self.hasher),
+            Expr::Binary { lhs, rhs, operator } => {
+                operator.hash(&mut self.hasher);
+                self.walk_expr(*lhs);
+                self.walk_expr(*rhs);
+            }
+            Expr::Unary { expr, operator } => {
+                operator.hash(&mut self.hasher);
+                self.walk_expr(*expr);
+            }
+            Expr::Cast { expr, ty } => {
+                ty.hash(&mut self.hasher);
+                self.walk_expr(*expr);
+            }
+            Expr::Tuple(elements) | Expr::Array(elements) => self.walk_exprs(elements),
+            Expr::Field { tuple, idx } => {
+                idx.hash(&mut self.hasher);
+                self.walk_expr(*tuple);
+            }
+            Expr::Index { array, index } => {
+                self.walk_expr(*array);
+                self.walk_expr(*index);
+            }
+            Expr::Block { statements, tail_expr } => {
+                statements.len().hash(&mut self.hasher);
+                for statement in statements {
+                    self.walk_statement(*statement);
+                }
+                self.walk_optional_expr(*tail_expr);
+            }
+            Expr::Local(local_def) => self.hash_local_def(*local_def),
+            Expr::Param { idx } => idx.hash(&mut self.hasher),
+            Expr::Const(name) => {
+                name.hash(&mut self.hasher);
+                if !self.consts.contains(name) {
+                    self.consts.push(*name);
+                }
+            }
+            Expr::Loop { label, body } => {
+                label.hash(&mut self.hasher);
+                self.walk_expr(*body);
+            }
+            Expr::For { label, local_def, start, end, body } => {
+                label.hash(&mut self.hasher);
+                self.walk_expr(*start);
+                self.walk_expr(*end);
+                self.define_local_def(*local_def);
+                self.walk_expr(*body);
+            }
+            Expr::LabeledBlock { label, body } => {
+                label.hash(&mut self.hasher);
+                self.walk_expr(*body);
+            }
+            Expr::Call { path, args } => {
+                path.hash(&mut self.hasher);
+                if !matches!(path, Path::Builtin(_)) && !self.callees.contains(path) {
+                    self.callees.push(*path);
+                }
+                self.walk_exprs(args);
+            }
+            Expr::Variant { variant, value } => {
+                variant.hash(&mut self.hasher);
+                self.walk_optional_expr(*value);
+            }
+            Expr::Match { value, arms } => {
+                self.walk_expr(*value);
+                arms.len().hash(&mut self.hasher);
+                for arm in arms {
+                    self.walk_pattern(arm.pattern);
+                    self.walk_expr(arm.body);
+                }
+            }
+        }
+    }
+
+    fn walk_exprs(&mut self, exprs: &[Id<Expr>]) {
+        exprs.len().hash(&mut self.hasher);
+        for expr in exprs {
+            self.walk_expr(*expr);
+        }
+    }
+
+    fn walk_optional_expr(&mut self, expr: Option<Id<Expr>>) {
+        expr.is_some().hash(&mut self.hasher);
+        if let Some(expr) = expr {
+            self.walk_expr(expr);
+        }
+    }
+
+    fn walk_statement(&mut self, statement: Id<Statement>) {
+        self.hash_range(self.bodies.statement_ranges.get(statement).copied());
+
+        let statement = &self.bodies.statements[statement];
+        std::mem::discriminant(statement).hash(&mut self.hasher);
+
+        match statement {
+            Statement::Expr(expr) | Statement::Defer(expr) => self.walk_expr(*expr),
+            Statement::LocalDef(local_def) => {
+                self.walk_expr(self.bodies.local_defs[*local_def].value);
+                self.define_local_def(*local_def);
+            }
+            Statement::Destructure { value, ty, local_defs } => {
+                ty.hash(&mut self.hasher);
+                self.walk_expr(*value);
+                local_defs.len().hash(&mut self.hasher);
+                for local_def in local_defs {
+                    local_def.is_some().hash(&mut self.hasher);
+                    if let Some(local_def) = local_def {
+                        self.define_local_def(*local_def);
+                    }
+                }
+            }
+            Statement::Assign { local_def, value } => {
+                self.walk_expr(*value);
+                self.hash_local_def(*local_def);
+            }
+            Statement::Break { label, value } => {
+                label.hash(&mut self.hasher);
+                self.walk_optional_expr(*value);
+            }
+            Statement::Continue { label } => label.hash(&mut self.hasher),
+            Statement::Return { value } => self.walk_optional_expr(*value),
+        }
+    }
+
+    fn walk_pattern(&mut self, pattern: Id<Pattern>) {
+        self.hash_range(self.bodies.pattern_ranges.get(pattern).copied());
+
+        let pattern = &self.bodies.patterns[pattern];
+        std::mem::discriminant(pattern).hash(&mut self.hasher);
+
+        match pattern {
+            Pattern::Missing | Pattern::Wildcard => {}
+            Pattern::Bind(local_def) => self.define_local_def(*local_def),
+            Pattern::IntLiteral { value, is_negated } => {
+                value.hash(&mut self.hasher);
+                is_negated.hash(&mut self.hasher);
+            }
+            Pattern::BoolLiteral(b) => b.hash(&mut self.hasher),
+            Pattern::StringLiteral(s) => s.hash(&mut self.hasher),
+            Pattern::CharLiteral(c) => c.hash(&mut self.hasher),
+            Pattern::Tuple(patterns) => {
+                patterns.len().hash(&mut self.hasher);
+                for pattern in patterns {
+                    self.walk_pattern(*pattern);
+                }
+            }
+            Pattern::Variant { variant, field } => {
+                variant.hash(&mut self.hasher);
+                field.is_some().hash(&mut self.hasher);
+                if let Some(field) = field {
+                    self.walk_pattern(*field);
+                }
+            }
+        }
+    }
+
+    fn define_local_def(&mut self, local_def: Id<LocalDef>) {
+        let LocalDef { is_mutable, ty, .. } = &self.bodies.local_defs[local_def];
+        is_mutable.hash(&mut self.hasher);
+        ty.hash(&mut self.hasher);
+        self.hash_local_def(local_def);
+    }
+
+    fn hash_local_def(&mut self, local_def: Id<LocalDef>) {
+        let next_idx = self.local_defs_in_order.len() as u32;
+        let idx = *self.local_defs.entry(local_def).or_insert(next_idx);
+        if idx == next_idx {
+            self.local_defs_in_order.push(local_def);
+        }
+        idx.hash(&mut self.hasher);
+    }
+
+    fn hash_range(&mut self, range: Option<TextRange>) {
+        range.and_then(|range| range.checked_sub(self.base)).hash(&mut self.hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{index, lower, Fingerprint, Name, WorldIndex};
+    use ast::AstNode;
+    use interner::Interner;
+
+    fn fingerprint(input: &str, function: &str, interner: &mut Interner) -> Fingerprint {
+        let world_index = WorldIndex::default();
+        let tokens = lexer::lex(input);
+        let tree = parser::parse_source_file(&tokens, input).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = index(root, &tree, &world_index, interner);
+        let (bodies, _) = lower(root, &tree, &index, &world_index, interner);
+
+        bodies.layout(Name(interner.intern(function)), &index).fingerprint
+    }
+
+    #[test]
+    fn moved_function_keeps_fingerprint() {
+        let mut interner = Interner::default();
+        let body = "fnc f(x: s32): s32 -> { let y = x; y * 2 };";
+
+        let original = fingerprint(body, "f", &mut interner);
+        let moved = fingerprint(&format!("fnc g -> 1 + 2;\n\n{body}"), "f", &mut interner);
+
+        assert_eq!(original, moved);
+    }
+
+    #[test]
+    fn changed_body_changes_fingerprint() {
+        let mut interner = Interner::default();
+
+        let original =
+            fingerprint("fnc f: s32 -> { let a = 1; let b = 2; a };", "f", &mut interner);
+        let other_local =
+            fingerprint("fnc f: s32 -> { let a = 1; let b = 2; b };", "f", &mut interner);
+        let spaced = fingerprint("fnc f: s32 -> { let a = 1;  let b = 2; a };", "f", &mut interner);
+
+        assert_ne!(original, other_local);
+        assert_ne!(original, spaced);
+    }
+}
@@ -1,10 +1,11 @@
-use crate::WorldIndex;
+use crate::prelude::GenericTy;
+use crate::{Fqn, WorldIndex};
 use ast::{AstNode, AstToken};
 use interner::{Interner, Key};
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::collections::hash_map::Entry;
 use std::fmt;
-use syntax::SyntaxTree;
+use syntax::{SyntaxNode, SyntaxTree};
 use text_size::TextRange;
 
 #[derive(Clone)]
@@ -13,6 +14,9 @@ pub struct Index {
     pub(crate) range_info: FxHashMap<Name, RangeInfo>,
     docs: FxHashMap<Name, Docs>,
     tys: FxHashSet<ast::Ident>,
+    ty_aliases: FxHashMap<Name, Ty>,
+    imports: FxHashMap<Name, Fqn>,
+    pub(crate) nested_functions: FxHashMap<ast::Function, Name>,
 }
 
 impl Index {
@@ -36,46 +40,162 @@ impl Index {
         self.tys.contains(&ident)
     }
 
+    pub fn get_docs(&self, function: Name) -> Option<&Docs> {
+        self.docs.get(&function)
+    }
+
+    /// The type a `type` definition stands for.
+    /// Aliases live in their own namespace, apart from functions.
+    pub fn get_ty_alias(&self, name: Name) -> Option<&Ty> {
+        self.ty_aliases.get(&name)
+    }
+
+    /// The function in another module which a `use` brought in under `name`.
+    /// Whether that function exists is only known once every module is indexed,
+    /// so lowering is what checks it.
+    pub fn get_import(&self, name: Name) -> Option<Fqn> {
+        self.imports.get(&name).copied()
+    }
+
     fn shrink_to_fit(&mut self) {
-        let Self { functions, range_info, docs, tys } = self;
+        let Self { functions, range_info, docs, tys, ty_aliases, imports, nested_functions } = self;
         functions.shrink_to_fit();
         range_info.shrink_to_fit();
         docs.shrink_to_fit();
         tys.shrink_to_fit();
+        ty_aliases.shrink_to_fit();
+        imports.shrink_to_fit();
+        nested_functions.shrink_to_fit();
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub struct Function {
+    pub ty_params: Vec<Name>,
     pub params: Vec<Param>,
     pub return_ty: Ty,
+    /// Whether the return type was left out and is worked out from the body,
+    /// in which case `return_ty` is only a placeholder.
+    ///
+    /// Functions other modules can call, extern functions and generic functions
+    /// return `unit` when their return type is left out instead,
+    /// since they have no body which is checked alongside their callers.
+    pub infers_return_ty: bool,
+    /// Whether other modules can refer to the function.
+    pub is_pub: bool,
+    pub is_extern: bool,
+    pub attrs: Vec<Attr>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct RangeInfo {
     pub whole: TextRange,
     pub name: TextRange,
+    /// The parameter list, or the name if the function doesn’t have one.
+    pub params: TextRange,
+    pub return_ty: Option<TextRange>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Where the parameters of a function are defined,
+/// for diagnostics about calls to it to point at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamsLocation {
+    /// `None` if the function is in the same module as the call.
+    pub module: Option<Name>,
+    pub range: TextRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub struct Param {
     pub name: Option<Name>,
     pub ty: Ty,
+    /// Whether calls can leave the parameter out.
+    /// Lowering puts the value it takes on then in [`crate::Bodies`].
+    pub has_default: bool,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// An attribute like `#[allow(unneeded_parens)]`,
+/// which nothing gives a meaning to yet.
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub struct Attr {
+    pub name: Name,
+    pub args: Vec<Name>,
+}
+
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub enum Ty {
     Unknown,
     S32,
+    S64,
+    U32,
     String,
+    Bool,
+    Char,
     Unit,
+    /// The type of expressions which never finish evaluating,
+    /// such as blocks which `return` from the function.
+    /// It can’t be written down and fits wherever any other type is expected.
+    Never,
+    Tuple(Vec<Ty>),
+    Array(Box<Ty>),
+    Option(Box<Ty>),
+    Result {
+        ok: Box<Ty>,
+        err: Box<Ty>,
+    },
+    /// A type written with the name of a `type` definition,
+    /// which is kept around so that errors can use it.
+    Alias {
+        name: String,
+        ty: Box<Ty>,
+    },
+    /// A type parameter of a generic function,
+    /// which every call of the function picks a type for.
+    Param {
+        idx: u32,
+        name: String,
+    },
+    /// A type which type inference hasn’t worked out yet.
+    /// These never make it out of type inference.
+    Var(u32),
 }
 
-#[derive(Clone)]
-struct Docs {
+impl Ty {
+    pub fn is_int(&self) -> bool {
+        matches!(self, Self::S32 | Self::S64 | Self::U32)
+    }
+
+    /// Replaces every alias, however deeply nested, with the type it stands for.
+    pub fn expand_aliases(&self) -> Ty {
+        match self {
+            Self::Tuple(elements) => {
+                Self::Tuple(elements.iter().map(Self::expand_aliases).collect())
+            }
+            Self::Array(element) => Self::Array(Box::new(element.expand_aliases())),
+            Self::Option(ty) => Self::Option(Box::new(ty.expand_aliases())),
+            Self::Result { ok, err } => Self::Result {
+                ok: Box::new(ok.expand_aliases()),
+                err: Box::new(err.expand_aliases()),
+            },
+            Self::Alias { ty, .. } => ty.expand_aliases(),
+            _ => self.clone(),
+        }
+    }
+}
+
+/// The doc comments of a definition,
+/// with the lines of each paragraph joined together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Docs {
     paras: Vec<String>,
 }
 
+impl Docs {
+    pub fn paras(&self) -> &[String] {
+        &self.paras
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Name(pub Key);
 
@@ -85,124 +205,414 @@ pub fn index(
     world_index: &WorldIndex,
     interner: &mut Interner,
 ) -> (Index, Vec<IndexingDiagnostic>) {
-    let mut functions = FxHashMap::default();
-    let mut range_info = FxHashMap::default();
-    let mut docs = FxHashMap::default();
-    let mut tys = FxHashSet::default();
-    let mut diagnostics = Vec::new();
+    let mut ctx = Ctx {
+        tree,
+        world_index,
+        interner,
+        functions: FxHashMap::default(),
+        range_info: FxHashMap::default(),
+        docs: FxHashMap::default(),
+        tys: FxHashSet::default(),
+        ty_alias_defs: FxHashMap::default(),
+        ty_aliases: FxHashMap::default(),
+        ty_aliases_being_lowered: FxHashSet::default(),
+        ty_params: Vec::new(),
+        imports: FxHashMap::default(),
+        nested_functions: FxHashMap::default(),
+        diagnostics: Vec::new(),
+    };
+
+    // aliases can be used before they’re defined,
+    // so they’re all collected before anything is lowered
+    for def in root.defs(tree) {
+        let ast::Def::TyAlias(ty_alias) = def else { continue };
+        let Some(name_token) = ty_alias.name(tree) else { continue };
+        let name = Name(ctx.interner.intern(name_token.text(tree)));
+
+        match ctx.ty_alias_defs.entry(name) {
+            Entry::Occupied(_) => ctx.diagnostics.push(IndexingDiagnostic {
+                kind: IndexingDiagnosticKind::TyAliasAlreadyDefined { name: name.0 },
+                range: ty_alias.range(tree),
+            }),
+            Entry::Vacant(entry) => {
+                entry.insert(ty_alias);
+            }
+        }
+    }
+
+    // imported functions share a namespace with the functions defined here,
+    // so a function can’t have the name of an import, wherever it is
+    for def in root.defs(tree) {
+        let ast::Def::Import(import) = def else { continue };
+        let Some(module_name) = import.module_name(tree) else { continue };
+        let Some(function_name) = import.function_name(tree) else { continue };
+        let module = Name(ctx.interner.intern(module_name.text(tree)));
+        let function = Name(ctx.interner.intern(function_name.text(tree)));
+
+        match ctx.imports.entry(function) {
+            Entry::Occupied(_) => ctx.diagnostics.push(IndexingDiagnostic {
+                kind: IndexingDiagnosticKind::FunctionAlreadyDefined { name: function.0 },
+                range: import.range(tree),
+            }),
+            Entry::Vacant(entry) => {
+                entry.insert(Fqn { module, function });
+            }
+        }
+    }
 
     for def in root.defs(tree) {
         match def {
             ast::Def::Function(function) => {
-                let name_token = match function.name(tree) {
-                    Some(ident) => ident,
-                    None => continue,
-                };
+                let Some(name_token) = function.name(tree) else { continue };
+                let name = Name(ctx.interner.intern(name_token.text(tree)));
 
-                let name = Name(interner.intern(name_token.text(tree)));
+                if ctx.imports.contains_key(&name) {
+                    ctx.diagnostics.push(IndexingDiagnostic {
+                        kind: IndexingDiagnosticKind::FunctionAlreadyDefined { name: name.0 },
+                        range: function.range(tree),
+                    });
+                    continue;
+                }
 
-                let mut params = Vec::new();
+                match ctx.functions.entry(name) {
+                    Entry::Occupied(_) => ctx.diagnostics.push(IndexingDiagnostic {
+                        kind: IndexingDiagnosticKind::FunctionAlreadyDefined { name: name.0 },
+                        range: function.range(tree),
+                    }),
+                    Entry::Vacant(_) => {
+                        ctx.index_function(function, name, name_token);
+                        ctx.index_nested_functions(function.syntax(), name);
+                    }
+                }
+            }
+            ast::Def::TyAlias(ty_alias) => {
+                let Some(name_token) = ty_alias.name(tree) else { continue };
+                let name = Name(ctx.interner.intern(name_token.text(tree)));
 
-                if let Some(param_list) = function.param_list(tree) {
-                    for param in param_list.params(tree) {
-                        let name =
-                            param.name(tree).map(|ident| Name(interner.intern(ident.text(tree))));
+                // duplicates have already been reported
+                if ctx.ty_alias_defs[&name] == ty_alias {
+                    ctx.lower_ty_alias(name);
+                }
+            }
+            ast::Def::Import(_) | ast::Def::Const(_) => {}
+        }
+    }
 
-                        let ty = lower_ty(
-                            param.ty(tree),
-                            tree,
-                            world_index,
-                            &mut tys,
-                            interner,
-                            &mut diagnostics,
-                        );
+    let Ctx {
+        functions,
+        range_info,
+        docs,
+        tys,
+        ty_aliases,
+        imports,
+        nested_functions,
+        diagnostics,
+        ..
+    } = ctx;
+    let mut index =
+        Index { functions, range_info, docs, tys, ty_aliases, imports, nested_functions };
+    index.shrink_to_fit();
 
-                        params.push(Param { name, ty })
-                    }
+    (index, diagnostics)
+}
+
+struct Ctx<'a> {
+    tree: &'a SyntaxTree,
+    world_index: &'a WorldIndex,
+    interner: &'a mut Interner,
+    functions: FxHashMap<Name, Function>,
+    range_info: FxHashMap<Name, RangeInfo>,
+    docs: FxHashMap<Name, Docs>,
+    tys: FxHashSet<ast::Ident>,
+    ty_alias_defs: FxHashMap<Name, ast::TyAlias>,
+    ty_aliases: FxHashMap<Name, Ty>,
+    ty_aliases_being_lowered: FxHashSet<Name>,
+    // the type parameters of the function whose signature is being lowered
+    ty_params: Vec<Name>,
+    imports: FxHashMap<Name, Fqn>,
+    nested_functions: FxHashMap<ast::Function, Name>,
+    diagnostics: Vec<IndexingDiagnostic>,
+}
+
+impl Ctx<'_> {
+    fn index_function(&mut self, function: ast::Function, name: Name, name_token: ast::Ident) {
+        let tree = self.tree;
+        let mut ty_params = Vec::new();
+        let mut params = Vec::new();
+
+        if let Some(ty_param_list) = function.ty_param_list(tree) {
+            for ident in ty_param_list.names(tree) {
+                let name = Name(self.interner.intern(ident.text(tree)));
+
+                if ty_params.contains(&name) {
+                    self.diagnostics.push(IndexingDiagnostic {
+                        kind: IndexingDiagnosticKind::TyParamAlreadyDefined { name: name.0 },
+                        range: ident.range(tree),
+                    });
+                    continue;
                 }
 
-                let return_ty = match function.return_ty(tree) {
-                    Some(return_ty) => lower_ty(
-                        return_ty.ty(tree),
-                        tree,
-                        world_index,
-                        &mut tys,
-                        interner,
-                        &mut diagnostics,
-                    ),
-                    None => Ty::Unit,
-                };
+                ty_params.push(name);
+            }
+        }
 
-                if let Some(d) = function.docs(tree) {
-                    let mut paras = vec![String::new()];
+        self.ty_params = ty_params.clone();
 
-                    for doc_comment in d.doc_comments(tree) {
-                        match doc_comment.contents(tree) {
-                            Some(contents) => {
-                                let contents = contents.text(tree).trim();
-                                let last_para = &mut paras.last_mut().unwrap();
+        let is_extern = function.extern_kw(tree).is_some();
 
-                                if !last_para.is_empty() {
-                                    last_para.push(' ');
-                                }
+        if let Some(param_list) = function.param_list(tree) {
+            let mut seen_default = false;
 
-                                last_para.push_str(contents);
-                            }
-                            None => paras.push(String::new()),
-                        }
-                    }
+            for param in param_list.params(tree) {
+                let name =
+                    param.name(tree).map(|ident| Name(self.interner.intern(ident.text(tree))));
+                let ty = self.lower_ty(param.ty(tree));
+                let has_default = param.default_value(tree).is_some();
 
-                    docs.insert(name, Docs { paras });
+                // arguments are matched up with parameters in order,
+                // so only the last parameters can be left out
+                if seen_default && !has_default {
+                    self.diagnostics.push(IndexingDiagnostic {
+                        kind: IndexingDiagnosticKind::ParamWithoutDefaultAfterDefault,
+                        range: param.range(tree),
+                    });
                 }
 
-                match functions.entry(name) {
-                    Entry::Occupied(_) => diagnostics.push(IndexingDiagnostic {
-                        kind: IndexingDiagnosticKind::FunctionAlreadyDefined { name: name.0 },
-                        range: function.range(tree),
-                    }),
-                    Entry::Vacant(vacant_entry) => {
-                        vacant_entry.insert(Function { params, return_ty });
-                        range_info.insert(
-                            name,
-                            RangeInfo { whole: function.range(tree), name: name_token.range(tree) },
-                        );
+                if is_extern && has_default {
+                    self.diagnostics.push(IndexingDiagnostic {
+                        kind: IndexingDiagnosticKind::DefaultInExternFunction,
+                        range: param.range(tree),
+                    });
+                }
+
+                seen_default |= has_default;
+                params.push(Param { name, ty, has_default: has_default && !is_extern })
+            }
+        }
+
+        let return_ty = match function.return_ty(tree) {
+            Some(return_ty) => self.lower_ty(return_ty.ty(tree)),
+            None => Ty::Unit,
+        };
+
+        self.ty_params.clear();
+
+        if let Some(d) = function.docs(tree) {
+            let mut paras = vec![String::new()];
+
+            for doc_comment in d.doc_comments(tree) {
+                match doc_comment.contents(tree) {
+                    Some(contents) => {
+                        let contents = contents.text(tree).trim();
+                        let last_para = &mut paras.last_mut().unwrap();
+
+                        if !last_para.is_empty() {
+                            last_para.push(' ');
+                        }
+
+                        last_para.push_str(contents);
                     }
+                    None => paras.push(String::new()),
                 }
             }
+
+            self.docs.insert(name, Docs { paras });
+        }
+
+        // attributes missing their name have already been reported by the parser
+        let attrs = function
+            .attrs(tree)
+            .filter_map(|attr| {
+                let name = Name(self.interner.intern(attr.name(tree)?.text(tree)));
+                let args = match attr.arg_list(tree) {
+                    Some(arg_list) => arg_list
+                        .args(tree)
+                        .map(|arg| Name(self.interner.intern(arg.text(tree))))
+                        .collect(),
+                    None => Vec::new(),
+                };
+                Some(Attr { name, args })
+            })
+            .collect();
+
+        let is_pub = function.pub_kw(tree).is_some();
+        let infers_return_ty =
+            function.return_ty(tree).is_none() && !is_pub && !is_extern && ty_params.is_empty();
+        self.functions.insert(
+            name,
+            Function { ty_params, params, return_ty, infers_return_ty, is_pub, is_extern, attrs },
+        );
+        let name_range = name_token.range(tree);
+        let params_range = function.param_list(tree).map_or(name_range, |list| list.range(tree));
+        let return_ty_range = function
+            .return_ty(tree)
+            .and_then(|return_ty| return_ty.ty(tree))
+            .map(|ty| ty.range(tree));
+        self.range_info.insert(
+            name,
+            RangeInfo {
+                whole: function.range(tree),
+                name: name_range,
+                params: params_range,
+                return_ty: return_ty_range,
+            },
+        );
+    }
+
+    // Functions defined inside of blocks are indexed under a name
+    // made up of the functions they’re nested in, such as `outer.inner`,
+    // which no path can refer to.
+    // Lowering is in charge of making them visible to the code around them.
+    fn index_nested_functions(&mut self, node: SyntaxNode, enclosing: Name) {
+        let tree = self.tree;
+
+        // `node` can be a block itself, such as a block’s tail expression
+        let Some(block) = ast::Block::cast(node, tree) else {
+            for child in node.child_nodes(tree) {
+                self.index_nested_functions(child, enclosing);
+            }
+            return;
+        };
+
+        let mut names_in_block = FxHashSet::default();
+
+        for def in block.defs(tree) {
+            // lowering reports aliases which aren’t at the top level
+            let ast::Def::Function(function) = def else { continue };
+            let Some(name_token) = function.name(tree) else { continue };
+            let text = name_token.text(tree);
+
+            if !names_in_block.insert(text) {
+                self.diagnostics.push(IndexingDiagnostic {
+                    kind: IndexingDiagnosticKind::FunctionAlreadyDefined {
+                        name: self.interner.intern(text),
+                    },
+                    range: function.range(tree),
+                });
+                continue;
+            }
+
+            // functions with the same name in different blocks
+            // are told apart with a number
+            let mangled = format!("{}.{}", self.interner.lookup(enclosing.0), text);
+            let mut name = Name(self.interner.intern(&mangled));
+            let mut n = 2;
+            while self.functions.contains_key(&name) {
+                name = Name(self.interner.intern(&format!("{mangled}#{n}")));
+                n += 1;
+            }
+
+            self.index_function(function, name, name_token);
+            self.nested_functions.insert(function, name);
+            self.index_nested_functions(function.syntax(), name);
+        }
+
+        for statement in block.statements(tree) {
+            self.index_nested_functions(statement.syntax(), enclosing);
+        }
+
+        if let Some(tail_expr) = block.tail_expr(tree) {
+            self.index_nested_functions(tail_expr.syntax(), enclosing);
         }
     }
 
-    let mut index = Index { functions, range_info, docs, tys };
-    index.shrink_to_fit();
+    /// Returns the type the alias stands for,
+    /// lowering it first if it hasn’t been already.
+    fn lower_ty_alias(&mut self, name: Name) -> Ty {
+        if let Some(ty) = self.ty_aliases.get(&name) {
+            return ty.clone();
+        }
 
-    (index, diagnostics)
-}
+        // an alias which refers to itself has already been reported
+        if !self.ty_aliases_being_lowered.insert(name) {
+            return Ty::Unknown;
+        }
 
-fn lower_ty(
-    ty: Option<ast::Ty>,
-    tree: &SyntaxTree,
-    world_index: &WorldIndex,
-    tys: &mut FxHashSet<ast::Ident>,
-    interner: &mut Interner,
-    diagnostics: &mut Vec<IndexingDiagnostic>,
-) -> Ty {
-    let ident = match ty.and_then(|ty| ty.name(tree)) {
-        Some(ident) => ident,
-        None => return Ty::Unknown,
-    };
+        // aliases are lowered as they’re first used,
+        // which can be in the signature of a generic function
+        let ty_params = std::mem::take(&mut self.ty_params);
+        let ty_alias = self.ty_alias_defs[&name];
+        let ty = self.lower_ty(ty_alias.ty(self.tree));
+        self.ty_params = ty_params;
+
+        self.ty_aliases_being_lowered.remove(&name);
+        self.ty_aliases.insert(name, ty.clone());
 
-    let name = Name(interner.intern(ident.text(tree)));
-    if let Some(kind) = world_index.get_ty(name) {
-        tys.insert(ident);
-        return kind;
+        ty
     }
 
-    diagnostics.push(IndexingDiagnostic {
-        kind: IndexingDiagnosticKind::UndefinedTy { name: name.0 },
-        range: ident.range(tree),
-    });
+    fn lower_ty(&mut self, ty: Option<ast::Ty>) -> Ty {
+        let tree = self.tree;
+
+        let ty = match ty {
+            Some(ty) => ty,
+            None => return Ty::Unknown,
+        };
+
+        if ty.l_paren(tree).is_some() {
+            let elements = ty.elements(tree).map(|element| self.lower_ty(Some(element))).collect();
+
+            return Ty::Tuple(elements);
+        }
+
+        if ty.l_bracket(tree).is_some() {
+            let element = self.lower_ty(ty.elements(tree).next());
+
+            return Ty::Array(Box::new(element));
+        }
+
+        let ident = match ty.name(tree) {
+            Some(ident) => ident,
+            None => return Ty::Unknown,
+        };
+
+        let name = Name(self.interner.intern(ident.text(tree)));
+
+        let args: Vec<_> = match ty.arg_list(tree) {
+            Some(arg_list) => arg_list.tys(tree).map(|arg| self.lower_ty(Some(arg))).collect(),
+            None => Vec::new(),
+        };
+        let got = args.len() as u32;
+
+        let (lowered, expected) = if let Some(idx) = self.ty_params.iter().position(|p| *p == name)
+        {
+            (Ty::Param { idx: idx as u32, name: ident.text(tree).to_string() }, 0)
+        } else if let Some(generic_ty) = GenericTy::from_name(ident.text(tree)) {
+            (generic_ty.apply(args), generic_ty.arity())
+        } else if let Some(ty) = self.world_index.get_ty(name) {
+            (ty, 0)
+        } else if self.ty_alias_defs.contains_key(&name) {
+            if self.ty_aliases_being_lowered.contains(&name) {
+                self.diagnostics.push(IndexingDiagnostic {
+                    kind: IndexingDiagnosticKind::RecursiveTyAlias { name: name.0 },
+                    range: ident.range(tree),
+                });
+            }
+
+            let ty = match self.lower_ty_alias(name) {
+                Ty::Unknown => Ty::Unknown,
+                ty => Ty::Alias { name: ident.text(tree).to_string(), ty: Box::new(ty) },
+            };
+            (ty, 0)
+        } else {
+            self.diagnostics.push(IndexingDiagnostic {
+                kind: IndexingDiagnosticKind::UndefinedTy { name: name.0 },
+                range: ident.range(tree),
+            });
+            return Ty::Unknown;
+        };
+
+        self.tys.insert(ident);
+
+        if got != expected {
+            self.diagnostics.push(IndexingDiagnostic {
+                kind: IndexingDiagnosticKind::MismatchedTyArgCount { name: name.0, expected, got },
+                range: ty.range(tree),
+            });
+        }
 
-    Ty::Unknown
+        lowered
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -213,14 +623,55 @@ pub struct IndexingDiagnostic {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum IndexingDiagnosticKind {
-    FunctionAlreadyDefined { name: Key },
-    UndefinedTy { name: Key },
+    FunctionAlreadyDefined {
+        name: Key,
+    },
+    UndefinedTy {
+        name: Key,
+    },
+    MismatchedTyArgCount {
+        name: Key,
+        expected: u32,
+        got: u32,
+    },
+    TyAliasAlreadyDefined {
+        name: Key,
+    },
+    RecursiveTyAlias {
+        name: Key,
+    },
+    TyParamAlreadyDefined {
+        name: Key,
+    },
+    ParamWithoutDefaultAfterDefault,
+    /// Extern functions are called with exactly the arguments the program passes,
+    /// so there’s nowhere for defaults to be filled in.
+    DefaultInExternFunction,
 }
 
 impl Index {
+    #[cfg(test)]
     pub fn debug(&self, interner: &Interner) -> String {
         let mut s = String::new();
 
+        let mut imports: Vec<_> = self.imports.values().collect();
+        imports.sort_unstable();
+
+        for fqn in imports {
+            s.push_str(&format!(
+                "use {}.{};\n",
+                interner.lookup(fqn.module.0),
+                interner.lookup(fqn.function.0)
+            ));
+        }
+
+        let mut ty_aliases: Vec<_> = self.ty_aliases.iter().collect();
+        ty_aliases.sort_unstable_by_key(|(name, _)| *name);
+
+        for (name, ty) in ty_aliases {
+            s.push_str(&format!("type {} = {ty};\n", interner.lookup(name.0)));
+        }
+
         let mut functions: Vec<_> = self.functions.iter().collect();
         functions.sort_unstable_by_key(|(name, _)| *name);
 
@@ -239,8 +690,33 @@ impl Index {
                 }
             }
 
+            for attr in &function.attrs {
+                s.push_str(&format!("#[{}", interner.lookup(attr.name.0)));
+
+                if !attr.args.is_empty() {
+                    let args: Vec<_> = attr.args.iter().map(|arg| interner.lookup(arg.0)).collect();
+                    s.push_str(&format!("({})", args.join(", ")));
+                }
+
+                s.push_str("]\n");
+            }
+
+            if function.is_pub {
+                s.push_str("pub ");
+            }
+
+            if function.is_extern {
+                s.push_str("extern ");
+            }
+
             s.push_str(&format!("fnc {}", interner.lookup(name.0)));
 
+            if !function.ty_params.is_empty() {
+                let ty_params: Vec<_> =
+                    function.ty_params.iter().map(|name| interner.lookup(name.0)).collect();
+                s.push_str(&format!("[{}]", ty_params.join(", ")));
+            }
+
             if !function.params.is_empty() {
                 s.push('(');
 
@@ -254,6 +730,10 @@ impl Index {
                         param.name.as_ref().map_or("?", |name| interner.lookup(name.0)),
                         param.ty
                     ));
+
+                    if param.has_default {
+                        s.push_str(" = …");
+                    }
                 }
 
                 s.push(')');
@@ -273,10 +753,32 @@ impl Index {
 impl fmt::Display for Ty {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Unknown => write!(f, "?"),
+            Self::Unknown | Self::Var(_) => write!(f, "?"),
             Self::S32 => write!(f, "s32"),
+            Self::S64 => write!(f, "s64"),
+            Self::U32 => write!(f, "u32"),
             Self::String => write!(f, "string"),
+            Self::Bool => write!(f, "bool"),
+            Self::Char => write!(f, "char"),
             Self::Unit => write!(f, "unit"),
+            Self::Never => write!(f, "!"),
+            Self::Tuple(elements) => {
+                write!(f, "(")?;
+
+                for (idx, element) in elements.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{element}")?;
+                }
+
+                write!(f, ")")
+            }
+            Self::Array(element) => write!(f, "[{element}]"),
+            Self::Option(ty) => write!(f, "Option[{ty}]"),
+            Self::Result { ok, err } => write!(f, "Result[{ok}, {err}]"),
+            Self::Alias { name, .. } | Self::Param { name, .. } => write!(f, "{name}"),
         }
     }
 }
@@ -344,6 +846,262 @@ mod tests {
         );
     }
 
+    #[test]
+    fn function_with_default_param_values() {
+        check(
+            r#"
+                fnc greet(greeting: string, name: string = "world") -> {};
+            "#,
+            expect![[r#"
+                fnc greet(greeting: string, name: string = …);
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn param_without_default_after_default() {
+        check(
+            r#"
+                fnc f(x: s32 = 1, y: s32) -> {};
+                extern fnc g(x: s32 = 1);
+            "#,
+            expect![[r#"
+                fnc f(x: s32 = …, y: s32);
+                extern fnc g(x: s32);
+            "#]],
+            |_| {
+                [
+                    (IndexingDiagnosticKind::ParamWithoutDefaultAfterDefault, 35..41),
+                    (IndexingDiagnosticKind::DefaultInExternFunction, 79..89),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn function_with_tuple_tys() {
+        check(
+            r#"
+                fnc swap(pair: (s32, (bool, foo))): ((bool, string), s32) -> {};
+            "#,
+            expect![[r#"
+                fnc swap(pair: (s32, (bool, ?))): ((bool, string), s32);
+            "#]],
+            |i| [(IndexingDiagnosticKind::UndefinedTy { name: i.intern("foo") }, 45..48)],
+        );
+    }
+
+    #[test]
+    fn function_with_option_and_result_tys() {
+        check(
+            r#"
+                fnc parse(s: string, base: Option): Result[Option[s32], string] -> {};
+            "#,
+            expect![[r#"
+                fnc parse(s: string, base: Option[?]): Result[Option[s32], string];
+            "#]],
+            |i| {
+                [(
+                    IndexingDiagnosticKind::MismatchedTyArgCount {
+                        name: i.intern("Option"),
+                        expected: 1,
+                        got: 0,
+                    },
+                    44..50,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn pub_functions() {
+        check(
+            r#"
+                pub fnc add(x: s32, y: s32): s32 -> x + y;
+                pub extern fnc puts(s: string);
+                fnc helper -> {};
+            "#,
+            expect![[r#"
+                pub fnc add(x: s32, y: s32): s32;
+                pub extern fnc puts(s: string);
+                fnc helper;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn imports() {
+        check(
+            r#"
+                use io.print;
+                use math.add;
+                fnc main -> {};
+            "#,
+            expect![[r#"
+                use io.print;
+                use math.add;
+                fnc main;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn function_with_same_name_as_import() {
+        check(
+            r#"
+                fnc add(x: s32, y: s32): s32 -> x + y;
+                use math.add;
+                use ops.add;
+            "#,
+            expect![[r#"
+                use math.add;
+            "#]],
+            |i| {
+                [
+                    (
+                        IndexingDiagnosticKind::FunctionAlreadyDefined { name: i.intern("add") },
+                        102..114,
+                    ),
+                    (
+                        IndexingDiagnosticKind::FunctionAlreadyDefined { name: i.intern("add") },
+                        17..55,
+                    ),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn ty_aliases() {
+        check(
+            r#"
+                fnc walk(distance: Meters): Route -> {};
+                type Route = [(Meters, string)];
+                type Meters = s32;
+            "#,
+            expect![[r#"
+                type Route = [(Meters, string)];
+                type Meters = s32;
+                fnc walk(distance: Meters): Route;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn ty_alias_already_defined() {
+        check(
+            r#"
+                type Meters = s32;
+                type Meters = s64;
+                fnc walk(distance: Meters) -> {};
+            "#,
+            expect![[r#"
+                type Meters = s32;
+                fnc walk(distance: Meters);
+            "#]],
+            |i| {
+                [(
+                    IndexingDiagnosticKind::TyAliasAlreadyDefined { name: i.intern("Meters") },
+                    52..70,
+                )]
+            },
+        );
+    }
+
+    #[test]
+    fn recursive_ty_aliases() {
+        check(
+            r#"
+                type List = (s32, List);
+                type A = B;
+                type B = Option[A];
+            "#,
+            expect![[r#"
+                type List = (s32, ?);
+                type A = B;
+                type B = Option[?];
+            "#]],
+            |i| {
+                [
+                    (IndexingDiagnosticKind::RecursiveTyAlias { name: i.intern("List") }, 35..39),
+                    (IndexingDiagnosticKind::RecursiveTyAlias { name: i.intern("A") }, 102..103),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn generic_functions() {
+        check(
+            r#"
+                type Pair = (s32, s32);
+                fnc id[T](x: T): T -> x;
+                fnc swap[A, B](pair: (A, B)): (B, A) -> (pair.1, pair.0);
+                fnc first[T](pairs: [Pair], default: T): Result[Pair, T] -> {};
+            "#,
+            expect![[r#"
+                type Pair = (s32, s32);
+                fnc id[T](x: T): T;
+                fnc swap[A, B](pair: (A, B)): (B, A);
+                fnc first[T](pairs: [Pair], default: T): Result[Pair, T];
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn ty_params_only_exist_in_their_function() {
+        check(
+            r#"
+                fnc wrap[T, T](x: T): Wrapper -> {};
+                type Wrapper = Option[T];
+                fnc unwrap(x: Wrapper): T -> {};
+            "#,
+            expect![[r#"
+                type Wrapper = Option[?];
+                fnc wrap[T](x: T): Wrapper;
+                fnc unwrap(x: Wrapper): ?;
+            "#]],
+            |i| {
+                [
+                    (IndexingDiagnosticKind::TyParamAlreadyDefined { name: i.intern("T") }, 29..30),
+                    (IndexingDiagnosticKind::UndefinedTy { name: i.intern("T") }, 92..93),
+                    (IndexingDiagnosticKind::UndefinedTy { name: i.intern("T") }, 136..137),
+                ]
+            },
+        );
+    }
+
+    #[test]
+    fn function_with_array_tys() {
+        check(
+            r#"
+                fnc first(xs: [[s32]]): [(bool, foo)] -> {};
+            "#,
+            expect![[r#"
+                fnc first(xs: [[s32]]): [(bool, ?)];
+            "#]],
+            |i| [(IndexingDiagnosticKind::UndefinedTy { name: i.intern("foo") }, 49..52)],
+        );
+    }
+
+    #[test]
+    fn extern_function() {
+        check(
+            r#"
+                extern fnc puts(s: string): s32;
+            "#,
+            expect![[r#"
+                extern fnc puts(s: string): s32;
+            "#]],
+            |_| [],
+        );
+    }
+
     #[test]
     fn multiple_functions() {
         check(
@@ -415,6 +1173,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn function_with_unit_return_ty() {
+        check(
+            r#"
+                fnc nothing: unit -> {};
+            "#,
+            expect![[r#"
+                fnc nothing;
+            "#]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn function_with_attrs() {
+        check(
+            r#"
+                #[test]
+                #[allow(unneeded_parens, unused)]
+                fnc check -> {};
+                #[]
+                fnc f -> {};
+            "#,
+            expect![[r##"
+                #[test]
+                #[allow(unneeded_parens, unused)]
+                fnc check;
+                fnc f;
+            "##]],
+            |_| [],
+        );
+    }
+
     #[test]
     fn function_with_missing_return_ty() {
         check(
@@ -441,6 +1232,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn nested_functions() {
+        check(
+            r#"
+                fnc outer: s32 -> {
+                    fnc inner(x: s32): s32 -> {
+                        fnc innermost -> {};
+                        x
+                    };
+                    loop { fnc inner -> {}; };
+                    inner 1
+                };
+            "#,
+            expect![[r##"
+                fnc outer: s32;
+                fnc outer.inner(x: s32): s32;
+                fnc outer.inner.innermost;
+                fnc outer.inner#2;
+            "##]],
+            |_| [],
+        );
+    }
+
+    #[test]
+    fn nested_functions_with_same_name_in_one_block() {
+        check(
+            r#"
+                fnc outer -> {
+                    fnc inner -> {};
+                    fnc inner: s32 -> 1;
+                };
+            "#,
+            expect![[r#"
+                fnc outer;
+                fnc outer.inner;
+            "#]],
+            |i| {
+                [(
+                    IndexingDiagnosticKind::FunctionAlreadyDefined { name: i.intern("inner") },
+                    89..109,
+                )]
+            },
+        );
+    }
+
     #[test]
     fn functions_with_same_name() {
         check(
@@ -483,6 +1319,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn get_docs() {
+        let input =
+            "## Increments\n## a number.\n##\n## Wraps around.\nfnc inc(n: s32): s32 -> n + 1;";
+        let mut interner = Interner::default();
+        let tokens = lexer::lex(input);
+        let tree = parser::parse_source_file(&tokens, input).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = index(root, &tree, &WorldIndex::default(), &mut interner);
+
+        let docs = index.get_docs(Name(interner.intern("inc"))).unwrap();
+        assert_eq!(docs.paras(), ["Increments a number.", "Wraps around."]);
+    }
+
     #[test]
     fn join_and_trim_lines_in_docs() {
         check(
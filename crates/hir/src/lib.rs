@@ -1,11 +1,21 @@
+mod backtrace;
 mod body;
+mod builtin;
+mod fingerprint;
 mod index;
 mod index_diff;
+mod prelude;
 mod project;
+mod validate;
 mod world_index;
 
+pub use self::backtrace::*;
 pub use self::body::*;
+pub use self::builtin::*;
+pub use self::fingerprint::*;
 pub use self::index::*;
 pub use self::index_diff::*;
+pub use self::prelude::Variant;
 pub use self::project::*;
+pub use self::validate::*;
 pub use self::world_index::*;
@@ -0,0 +1,116 @@
+use crate::Ty;
+
+/// A variant of one of the enums the language provides,
+/// `Option` and `Result`,
+/// which can be used from any module without being defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variant {
+    Some,
+    None,
+    Ok,
+    Err,
+}
+
+impl Variant {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let variant = match name {
+            "Some" => Self::Some,
+            "None" => Self::None,
+            "Ok" => Self::Ok,
+            "Err" => Self::Err,
+            _ => return None,
+        };
+
+        Some(variant)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Some => "Some",
+            Self::None => "None",
+            Self::Ok => "Ok",
+            Self::Err => "Err",
+        }
+    }
+
+    /// Whether the variant holds a value, like `Some x`,
+    /// rather than standing on its own, like `None`.
+    pub fn has_field(self) -> bool {
+        self != Self::None
+    }
+
+    /// The type of the enum this variant belongs to,
+    /// with nothing known about its arguments.
+    pub fn enum_ty(self) -> Ty {
+        match self {
+            Self::Some | Self::None => Ty::Option(Box::new(Ty::Unknown)),
+            Self::Ok | Self::Err => {
+                Ty::Result { ok: Box::new(Ty::Unknown), err: Box::new(Ty::Unknown) }
+            }
+        }
+    }
+
+    /// The type of the value the variant holds,
+    /// if `ty` is the enum the variant belongs to and the variant holds a value.
+    pub fn field_ty(self, ty: &Ty) -> Option<&Ty> {
+        match (self, ty) {
+            (Self::Some, Ty::Option(ty)) => Some(ty),
+            (Self::Ok, Ty::Result { ok, .. }) => Some(ok),
+            (Self::Err, Ty::Result { err, .. }) => Some(err),
+            _ => None,
+        }
+    }
+
+    pub fn field_ty_mut(self, ty: &mut Ty) -> Option<&mut Ty> {
+        match (self, ty) {
+            (Self::Some, Ty::Option(ty)) => Some(ty),
+            (Self::Ok, Ty::Result { ok, .. }) => Some(ok),
+            (Self::Err, Ty::Result { err, .. }) => Some(err),
+            _ => None,
+        }
+    }
+
+    pub fn belongs_to(self, ty: &Ty) -> bool {
+        matches!(
+            (self, ty),
+            (Self::Some | Self::None, Ty::Option(_)) | (Self::Ok | Self::Err, Ty::Result { .. })
+        )
+    }
+}
+
+/// A type the language provides which takes other types as arguments,
+/// such as `Option[s32]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GenericTy {
+    Option,
+    Result,
+}
+
+impl GenericTy {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Option" => Some(Self::Option),
+            "Result" => Some(Self::Result),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn arity(self) -> u32 {
+        match self {
+            Self::Option => 1,
+            Self::Result => 2,
+        }
+    }
+
+    /// Applies the type to its arguments,
+    /// with any which are missing left unknown.
+    pub(crate) fn apply(self, args: Vec<Ty>) -> Ty {
+        let mut args = args.into_iter().map(Box::new);
+        let mut next_arg = || args.next().unwrap_or_else(|| Box::new(Ty::Unknown));
+
+        match self {
+            Self::Option => Ty::Option(next_arg()),
+            Self::Result => Ty::Result { ok: next_arg(), err: next_arg() },
+        }
+    }
+}
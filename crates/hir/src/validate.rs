@@ -0,0 +1,448 @@
+use crate::{Bodies, Expr, Index, LocalDef, Name, Path, Pattern, Statement};
+use arena::{Arena, Id};
+use interner::Interner;
+use rustc_hash::FxHashSet;
+
+/// Checks the invariants lowering upholds and everything downstream relies on,
+/// such as every ID pointing into the right arena
+/// and every expression belonging to exactly one function.
+///
+/// Lowering runs this itself in debug builds.
+/// Code which builds or rewrites bodies by hand should run it too,
+/// so that mistakes surface right away
+/// instead of as a confusing panic in type checking or evaluation.
+pub fn validate(bodies: &Bodies, index: &Index) -> Vec<InternalError> {
+    let mut function_bodies: Vec<_> = bodies.function_bodies.iter().collect();
+    function_bodies.sort_unstable_by_key(|(name, _)| *name);
+
+    let mut ctx = Ctx {
+        bodies,
+        index,
+        function: None,
+        seen_exprs: FxHashSet::default(),
+        locals_in_scope: FxHashSet::default(),
+        errors: Vec::new(),
+    };
+
+    for (name, body) in function_bodies {
+        if index.get_function(*name).is_none() {
+            ctx.errors.push(InternalError::BodyWithoutFunction { function: *name });
+            continue;
+        }
+
+        ctx.function = Some(*name);
+        ctx.locals_in_scope.clear();
+        ctx.validate_expr(*body);
+    }
+
+    ctx.errors
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InternalError {
+    BodyWithoutFunction { function: Name },
+    DanglingExpr { function: Name, expr: Id<Expr> },
+    DanglingStatement { function: Name, statement: Id<Statement> },
+    DanglingLocalDef { function: Name, local_def: Id<LocalDef> },
+    DanglingPattern { function: Name, pattern: Id<Pattern> },
+    SharedExpr { function: Name, expr: Id<Expr> },
+    MissingExprRange { function: Name, expr: Id<Expr> },
+    LocalNotInScope { function: Name, local_def: Id<LocalDef> },
+    ParamOutOfRange { function: Name, idx: u32 },
+    UndefinedCallee { function: Name, callee: Name },
+    UndefinedConst { function: Name, name: Name },
+}
+
+impl InternalError {
+    pub fn display(&self, interner: &Interner) -> String {
+        let function = |name: &Name| interner.lookup(name.0);
+
+        match self {
+            Self::BodyWithoutFunction { function: f } => {
+                format!("`{}` has a body but is missing from the index", function(f))
+            }
+            Self::DanglingExpr { function: f, expr } => {
+                format!("`{}` refers to {:?}, which does not exist", function(f), expr)
+            }
+            Self::DanglingStatement { function: f, statement } => {
+                format!("`{}` refers to {:?}, which does not exist", function(f), statement)
+            }
+            Self::DanglingLocalDef { function: f, local_def } => {
+                format!("`{}` refers to {:?}, which does not exist", function(f), local_def)
+            }
+            Self::DanglingPattern { function: f, pattern } => {
+                format!("`{}` refers to {:?}, which does not exist", function(f), pattern)
+            }
+            Self::SharedExpr { function: f, expr } => {
+                format!("`{}` reaches {:?} more than once", function(f), expr)
+            }
+            Self::MissingExprRange { function: f, expr } => {
+                format!("{:?} in `{}` has no text range", expr, function(f))
+            }
+            Self::LocalNotInScope { function: f, local_def } => {
+                format!(
+                    "`{}` uses {:?} before or outside of its definition",
+                    function(f),
+                    local_def
+                )
+            }
+            Self::ParamOutOfRange { function: f, idx } => {
+                format!("`{}` uses parameter {} but has fewer parameters", function(f), idx)
+            }
+            Self::UndefinedCallee { function: f, callee } => {
+                format!("`{}` calls `{}`, which is not in the index", function(f), function(callee))
+            }
+            Self::UndefinedConst { function: f, name } => {
+                format!("`{}` refers to `{}`, which is not a constant", function(f), function(name))
+            }
+        }
+    }
+}
+
+struct Ctx<'a> {
+    bodies: &'a Bodies,
+    index: &'a Index,
+    function: Option<Name>,
+    seen_exprs: FxHashSet<Id<Expr>>,
+    locals_in_scope: FxHashSet<Id<LocalDef>>,
+    errors: Vec<InternalError>,
+}
+
+impl Ctx<'_> {
+    fn validate_expr(&mut self, expr: Id<Expr>) {
+        let function = self.function.unwrap();
+
+        if !in_bounds(&self.bodies.exprs, expr) {
+            self.errors.push(InternalError::DanglingExpr { function, expr });
+            return;
+        }
+
+        if !self.seen_exprs.insert(expr) {
+            self.errors.push(InternalError::SharedExpr { function, expr });
+            return;
+        }
+
+        // exprs missing from the source have nowhere to point to
+        let is_missing = matches!(self.bodies.exprs[expr], Expr::Missing);
+        if !is_missing && self.bodies.expr_ranges.get(expr).is_none() {
+            self.errors.push(InternalError::MissingExprRange { function, expr });
+        }
+
+        match &self.bodies.exprs[expr] {
+            Expr::Missing
+            | Expr::IntLiteral(_)
+            | Expr::BoolLiteral(_)
+            | Expr::StringLiteral(_)
+            | Expr::CharLiteral(_)
+            | Expr::Unit => {}
+            Expr::Binary { lhs, rhs, .. } => {
+                self.validate_expr(*lhs);
+                self.validate_expr(*rhs);
+            }
+            Expr::Unary { expr, .. }
+            | Expr::Cast { expr, .. }
+            | Expr::Field { tuple: expr, .. } => self.validate_expr(*expr),
+            Expr::Tuple(elements) | Expr::Array(elements) => {
+                for element in elements {
+                    self.validate_expr(*element);
+                }
+            }
+            Expr::Index { array, index } => {
+                self.validate_expr(*array);
+                self.validate_expr(*index);
+            }
+            Expr::Variant { value, .. } => {
+                if let Some(value) = value {
+                    self.validate_expr(*value);
+                }
+            }
+            Expr::Block { statements, tail_expr } => {
+                for statement in statements {
+                    self.validate_statement(*statement);
+                }
+
+                if let Some(tail_expr) = tail_expr {
+                    self.validate_expr(*tail_expr);
+                }
+            }
+            Expr::Local(local_def) => {
+                if !in_bounds(&self.bodies.local_defs, *local_def) {
+                    self.errors
+                        .push(InternalError::DanglingLocalDef { function, local_def: *local_def });
+                } else if !self.locals_in_scope.contains(local_def) {
+                    self.errors
+                        .push(InternalError::LocalNotInScope { function, local_def: *local_def });
+                }
+            }
+            Expr::Param { idx } => {
+                let param_count = self.index.get_function(function).unwrap().params.len();
+                if *idx as usize >= param_count {
+                    self.errors.push(InternalError::ParamOutOfRange { function, idx: *idx });
+                }
+            }
+            Expr::Const(name) => {
+                if !self.bodies.consts.contains_key(name) {
+                    self.errors.push(InternalError::UndefinedConst { function, name: *name });
+                }
+            }
+            Expr::Loop { body, .. } | Expr::LabeledBlock { body, .. } => self.validate_expr(*body),
+            Expr::For { local_def, start, end, body, .. } => {
+                self.validate_expr(*start);
+                self.validate_expr(*end);
+
+                if !in_bounds(&self.bodies.local_defs, *local_def) {
+                    self.errors
+                        .push(InternalError::DanglingLocalDef { function, local_def: *local_def });
+                    return;
+                }
+
+                self.locals_in_scope.insert(*local_def);
+                self.validate_expr(*body);
+            }
+            Expr::Call { path, args } => {
+                if let Path::ThisModule(callee) = path {
+                    if self.index.get_function(*callee).is_none() {
+                        self.errors
+                            .push(InternalError::UndefinedCallee { function, callee: *callee });
+                    }
+                }
+
+                for arg in args {
+                    self.validate_expr(*arg);
+                }
+            }
+            Expr::Match { value, arms } => {
+                self.validate_expr(*value);
+
+                for arm in arms {
+                    self.validate_pattern(arm.pattern);
+                    self.validate_expr(arm.body);
+                }
+            }
+        }
+    }
+
+    // brings the locals bound by the pattern into scope
+    fn validate_pattern(&mut self, pattern: Id<Pattern>) {
+        let function = self.function.unwrap();
+
+        if !in_bounds(&self.bodies.patterns, pattern) {
+            self.errors.push(InternalError::DanglingPattern { function, pattern });
+            return;
+        }
+
+        match &self.bodies.patterns[pattern] {
+            Pattern::Missing
+            | Pattern::Wildcard
+            | Pattern::IntLiteral { .. }
+            | Pattern::BoolLiteral(_)
+            | Pattern::StringLiteral(_)
+            | Pattern::CharLiteral(_) => {}
+            Pattern::Bind(local_def) => {
+                if !in_bounds(&self.bodies.local_defs, *local_def) {
+                    self.errors
+                        .push(InternalError::DanglingLocalDef { function, local_def: *local_def });
+                    return;
+                }
+
+                self.locals_in_scope.insert(*local_def);
+            }
+            Pattern::Tuple(patterns) => {
+                for pattern in patterns {
+                    self.validate_pattern(*pattern);
+                }
+            }
+            Pattern::Variant { field, .. } => {
+                if let Some(field) = field {
+                    self.validate_pattern(*field);
+                }
+            }
+        }
+    }
+
+    fn validate_statement(&mut self, statement: Id<Statement>) {
+        let function = self.function.unwrap();
+
+        if !in_bounds(&self.bodies.statements, statement) {
+            self.errors.push(InternalError::DanglingStatement { function, statement });
+            return;
+        }
+
+        match &self.bodies.statements[statement] {
+            Statement::Expr(expr) => self.validate_expr(*expr),
+            Statement::LocalDef(local_def) => {
+                if !in_bounds(&self.bodies.local_defs, *local_def) {
+                    self.errors
+                        .push(InternalError::DanglingLocalDef { function, local_def: *local_def });
+                    return;
+                }
+
+                // the value can’t refer to the local itself,
+                // so it’s checked before the local comes into scope
+                self.validate_expr(self.bodies.local_defs[*local_def].value);
+                self.locals_in_scope.insert(*local_def);
+            }
+            Statement::Destructure { value, local_defs, .. } => {
+                self.validate_expr(*value);
+
+                for local_def in local_defs.iter().flatten() {
+                    if !in_bounds(&self.bodies.local_defs, *local_def) {
+                        self.errors.push(InternalError::DanglingLocalDef {
+                            function,
+                            local_def: *local_def,
+                        });
+                        continue;
+                    }
+
+                    self.locals_in_scope.insert(*local_def);
+                }
+            }
+            Statement::Assign { local_def, value } => {
+                self.validate_expr(*value);
+
+                if !in_bounds(&self.bodies.local_defs, *local_def) {
+                    self.errors
+                        .push(InternalError::DanglingLocalDef { function, local_def: *local_def });
+                } else if !self.locals_in_scope.contains(local_def) {
+                    self.errors
+                        .push(InternalError::LocalNotInScope { function, local_def: *local_def });
+                }
+            }
+            Statement::Break { value, .. } | Statement::Return { value } => {
+                if let Some(value) = value {
+                    self.validate_expr(*value);
+                }
+            }
+            Statement::Continue { .. } => {}
+            Statement::Defer(expr) => self.validate_expr(*expr),
+        }
+    }
+}
+
+fn in_bounds<T>(arena: &Arena<T>, id: Id<T>) -> bool {
+    (id.to_raw() as usize) < arena.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{index, lower, WorldIndex};
+    use ast::AstNode;
+    use expect_test::{expect, Expect};
+
+    fn check(input: &str, corrupt: impl FnOnce(&mut Bodies, &mut Interner), expect: Expect) {
+        let mut interner = Interner::default();
+        let world_index = WorldIndex::default();
+
+        let tokens = lexer::lex(input);
+        let tree = parser::parse_source_file(&tokens, input).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = index(root, &tree, &world_index, &mut interner);
+        let (mut bodies, _) = lower(root, &tree, &index, &world_index, &mut interner);
+
+        corrupt(&mut bodies, &mut interner);
+
+        let errors: Vec<_> =
+            validate(&bodies, &index).iter().map(|e| e.display(&interner) + "\n").collect();
+
+        expect.assert_eq(&errors.concat());
+    }
+
+    #[test]
+    fn lowered_bodies_are_valid() {
+        check(
+            r#"
+                fnc add(x: s32, y: s32): s32 -> x + y;
+                fnc main: s32 -> {
+                    let a = add 1, 2;
+                    let b = loop { break a; };
+                    -b
+                };
+                fnc g(p: (s32, bool)): s32 -> match p {
+                    (0, _) -> 0;
+                    (n, true) -> n;
+                    _ -> 1;
+                };
+            "#,
+            |_, _| {},
+            expect![[""]],
+        );
+    }
+
+    #[test]
+    fn dangling_expr() {
+        check(
+            "fnc f: s32 -> 1 + 2;",
+            |bodies, _| {
+                let mut exprs: Vec<_> = bodies.exprs.iter().map(|(_, e)| e.clone()).collect();
+                exprs.truncate(1);
+                bodies.exprs = exprs.into_iter().collect();
+            },
+            expect![[r#"
+                `f` refers to Id::<Expr>(3), which does not exist
+            "#]],
+        );
+    }
+
+    #[test]
+    fn shared_expr() {
+        check(
+            "fnc f: s32 -> 1 + 2;",
+            |bodies, _| {
+                let (body, _) = bodies.exprs.iter().last().unwrap();
+                let (lhs, _) = bodies.exprs.iter().next().unwrap();
+                bodies.exprs[body] =
+                    Expr::Binary { lhs, rhs: lhs, operator: crate::BinaryOperator::Add };
+            },
+            expect![[r#"
+                `f` reaches Id::<Expr>(1) more than once
+            "#]],
+        );
+    }
+
+    #[test]
+    fn local_from_other_function() {
+        check(
+            r#"
+                fnc f: s32 -> { let a = 1; a };
+                fnc g: s32 -> 2;
+            "#,
+            |bodies, interner| {
+                let local_def = bodies.local_defs.iter().next().unwrap().0;
+                let g = bodies.function_bodies[&Name(interner.intern("g"))];
+                bodies.exprs[g] = Expr::Local(local_def);
+            },
+            expect![[r#"
+                `g` uses Id::<LocalDef>(1) before or outside of its definition
+            "#]],
+        );
+    }
+
+    #[test]
+    fn param_out_of_range() {
+        check(
+            "fnc f(x: s32): s32 -> x;",
+            |bodies, interner| {
+                let f = bodies.function_bodies[&Name(interner.intern("f"))];
+                bodies.exprs[f] = Expr::Param { idx: 1 };
+            },
+            expect![[r#"
+                `f` uses parameter 1 but has fewer parameters
+            "#]],
+        );
+    }
+
+    #[test]
+    fn body_without_function() {
+        check(
+            "fnc f: s32 -> 1;",
+            |bodies, interner| {
+                let f = bodies.function_bodies[&Name(interner.intern("f"))];
+                bodies.function_bodies.insert(Name(interner.intern("g")), f);
+            },
+            expect![[r#"
+                `g` has a body but is missing from the index
+            "#]],
+        );
+    }
+}
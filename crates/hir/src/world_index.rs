@@ -1,9 +1,13 @@
-use crate::{Function, Index, Name, RangeInfo, Ty};
+use crate::{Docs, Function, Index, Name, RangeInfo, Ty};
 use interner::Key;
 use rustc_hash::FxHashMap;
 
 #[derive(Default)]
-pub struct WorldIndex(FxHashMap<Name, Index>);
+pub struct WorldIndex {
+    modules: FxHashMap<Name, Index>,
+    // keyed by their names, since programs call them without saying where they’re from
+    native_functions: FxHashMap<Name, (Fqn, Function)>,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Fqn {
@@ -13,7 +17,13 @@ pub struct Fqn {
 
 impl WorldIndex {
     pub fn get_function(&self, fqn: Fqn) -> Result<&Function, GetFunctionError> {
-        match self.0.get(&fqn.module) {
+        if let Some((native_fqn, function)) = self.native_functions.get(&fqn.function) {
+            if *native_fqn == fqn {
+                return Ok(function);
+            }
+        }
+
+        match self.modules.get(&fqn.module) {
             Some(index) => match index.get_function(fqn.function) {
                 Some(function) => Ok(function),
                 None => Err(GetFunctionError::UnknownFunction),
@@ -27,25 +37,58 @@ impl WorldIndex {
             Some(Ty::S32)
         } else if name.0 == Key::string() {
             Some(Ty::String)
+        } else if name.0 == Key::bool() {
+            Some(Ty::Bool)
+        } else if name.0 == Key::char() {
+            Some(Ty::Char)
+        } else if name.0 == Key::s64() {
+            Some(Ty::S64)
+        } else if name.0 == Key::u32() {
+            Some(Ty::U32)
+        } else if name.0 == Key::unit() {
+            Some(Ty::Unit)
         } else {
             None
         }
     }
 
+    pub fn get_docs(&self, fqn: Fqn) -> Option<&Docs> {
+        self.modules.get(&fqn.module)?.get_docs(fqn.function)
+    }
+
     pub fn range_info(&self, fqn: Fqn) -> RangeInfo {
-        self.0[&fqn.module].range_info[&fqn.function]
+        self.modules[&fqn.module].range_info[&fqn.function]
     }
 
     pub fn add_module(&mut self, module: Name, index: Index) {
-        assert!(self.0.insert(module, index).is_none());
+        assert!(self.modules.insert(module, index).is_none());
     }
 
     pub fn update_module(&mut self, module: Name, index: Index) {
-        *self.0.get_mut(&module).unwrap() = index;
+        *self.modules.get_mut(&module).unwrap() = index;
+    }
+
+    /// Declares a function the host implements,
+    /// which programs can call by its name alone
+    /// unless they define or import something with the same name.
+    ///
+    /// It has no source code, so it is always `pub` and `extern`.
+    pub fn add_native_function(&mut self, fqn: Fqn, function: Function) {
+        let function = Function { is_pub: true, is_extern: true, ..function };
+        self.native_functions.insert(fqn.function, (fqn, function));
+    }
+
+    pub fn get_native_function(&self, name: Name) -> Option<(Fqn, &Function)> {
+        let (fqn, function) = self.native_functions.get(&name)?;
+        Some((*fqn, function))
+    }
+
+    pub fn is_native_function(&self, fqn: Fqn) -> bool {
+        self.get_native_function(fqn.function).is_some_and(|(native_fqn, _)| native_fqn == fqn)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (Fqn, RangeInfo)> + '_ {
-        self.0.iter().flat_map(|(module, index)| {
+        self.modules.iter().flat_map(|(module, index)| {
             index.iter().map(|(function, range)| (Fqn { module: *module, function }, range))
         })
     }
@@ -0,0 +1,631 @@
+//! Inference which reuses what it worked out the last time a module was checked,
+//! for editors which check a module again after every change to it.
+//!
+//! Every function is keyed by the fingerprint of its body
+//! along with everything else its inference reads,
+//! such as the signatures of the functions it calls,
+//! and is only inferred again once that key changes.
+
+use std::hash::{Hash, Hasher};
+
+use rustc_hash::{FxHashMap, FxHasher};
+use text_size::{TextRange, TextSize};
+
+use crate::unify::TyVars;
+use crate::{
+    default_return_tys, infer_impl, infer_remaining_consts, signature_table, InferenceResult,
+    Signature, TyDiagnostic, TyDiagnosticKind,
+};
+
+#[derive(Default)]
+pub struct InferenceCache {
+    functions: FxHashMap<hir::Name, CachedFunction>,
+    inferred: Vec<hir::Name>,
+}
+
+struct CachedFunction {
+    key: hir::Fingerprint,
+    // where the function started, which the ranges of its diagnostics are relative to
+    start: TextSize,
+    // the types of the function’s expressions and locals,
+    // in the order of its `hir::BodyLayout`
+    expr_tys: Vec<Option<hir::Ty>>,
+    local_tys: Vec<Option<hir::Ty>>,
+    diagnostics: Vec<TyDiagnostic>,
+    // which of the functions the body calls each diagnostic points at the parameters of,
+    // since they can have moved without anything about the call changing
+    diagnostic_callees: Vec<Option<usize>>,
+}
+
+impl InferenceCache {
+    /// Like [`crate::infer_all`],
+    /// but only infers the functions which have changed since the last call.
+    ///
+    /// Functions whose return types are inferred and constants are always inferred again,
+    /// since they can be decided by the code which uses them.
+    /// The diagnostics can come in a different order than [`crate::infer_all`] gives them in.
+    pub fn infer_all(
+        &mut self,
+        bodies: &hir::Bodies,
+        index: &hir::Index,
+        world_index: &hir::WorldIndex,
+    ) -> (InferenceResult, Vec<TyDiagnostic>) {
+        self.inferred.clear();
+
+        let mut result = InferenceResult::default();
+        let mut diagnostics = Vec::new();
+        let mut ty_vars = TyVars::default();
+        let signatures = signature_table(index, &mut ty_vars);
+
+        // constants are inferred before any function uses them,
+        // so that their diagnostics don’t end up cached as part of that function
+        infer_remaining_consts(
+            bodies,
+            index,
+            world_index,
+            &mut result,
+            &mut diagnostics,
+            &mut ty_vars,
+            &signatures,
+        );
+
+        let mut function_names: Vec<_> = index.functions().collect();
+        function_names.sort_unstable();
+        let (inferred, declared): (Vec<_>, Vec<_>) = function_names
+            .into_iter()
+            .partition(|name| index.get_function(*name).unwrap().infers_return_ty);
+
+        for function_name in inferred {
+            self.inferred.push(function_name);
+            infer_impl(
+                function_name,
+                bodies,
+                index,
+                world_index,
+                &mut result,
+                &mut diagnostics,
+                &mut ty_vars,
+                &signatures,
+            );
+        }
+
+        let mut functions = FxHashMap::default();
+        let mut fresh = Vec::new();
+
+        for function_name in declared {
+            if index.get_function(function_name).unwrap().is_extern {
+                continue;
+            }
+
+            let layout = bodies.layout(function_name, index);
+            let start = index.range_info(function_name).whole.start();
+            let key = function_key(
+                function_name,
+                &layout,
+                index,
+                world_index,
+                &result,
+                &ty_vars,
+                &signatures,
+            );
+
+            if let Some(key) = key {
+                if let Some(mut cached) = self.functions.remove(&function_name) {
+                    if cached.key == key {
+                        cached.replay(&layout, start, index, world_index, &mut result);
+                        diagnostics.extend(cached.diagnostics.iter().cloned());
+                        functions.insert(function_name, cached);
+                        continue;
+                    }
+                }
+            }
+
+            self.inferred.push(function_name);
+            let mut function_diagnostics = Vec::new();
+            infer_impl(
+                function_name,
+                bodies,
+                index,
+                world_index,
+                &mut result,
+                &mut function_diagnostics,
+                &mut ty_vars,
+                &signatures,
+            );
+            fresh.push((function_name, layout, start, key, function_diagnostics));
+        }
+
+        default_return_tys(&signatures, &mut ty_vars);
+        result.signatures = signatures;
+        result.resolve_ty_vars(&ty_vars, &mut diagnostics);
+
+        for (function_name, layout, start, key, mut function_diagnostics) in fresh {
+            for diagnostic in &mut function_diagnostics {
+                diagnostic.kind.resolve_ty_vars(&ty_vars);
+            }
+
+            if let Some(key) = key {
+                let cached = CachedFunction::new(
+                    key,
+                    start,
+                    &layout,
+                    &result,
+                    function_diagnostics.clone(),
+                    index,
+                    world_index,
+                );
+                functions.insert(function_name, cached);
+            }
+
+            diagnostics.extend(function_diagnostics);
+        }
+
+        self.functions = functions;
+        result.shrink_to_fit();
+
+        (result, diagnostics)
+    }
+
+    /// The diagnostics of a single function,
+    /// which is only inferred again if it has changed since it was cached.
+    ///
+    /// Like [`InferenceCache::infer_all`],
+    /// constants and functions whose return types are inferred are always inferred again,
+    /// but the module’s other functions are left alone,
+    /// so this is quick enough to run on every change to the function.
+    /// Returns `None` if the module has no such function.
+    pub fn infer_function(
+        &mut self,
+        function_name: hir::Name,
+        bodies: &hir::Bodies,
+        index: &hir::Index,
+        world_index: &hir::WorldIndex,
+    ) -> Option<Vec<TyDiagnostic>> {
+        self.inferred.clear();
+        let function = index.get_function(function_name)?;
+
+        let mut result = InferenceResult::default();
+        let mut diagnostics = Vec::new();
+        let mut ty_vars = TyVars::default();
+        let signatures = signature_table(index, &mut ty_vars);
+
+        // the diagnostics of constants and other functions belong to those
+        infer_remaining_consts(
+            bodies,
+            index,
+            world_index,
+            &mut result,
+            &mut Vec::new(),
+            &mut ty_vars,
+            &signatures,
+        );
+
+        let mut inferred: Vec<_> = index
+            .functions()
+            .filter(|name| index.get_function(*name).unwrap().infers_return_ty)
+            .collect();
+        inferred.sort_unstable();
+
+        for name in inferred {
+            self.inferred.push(name);
+            let mut other_diagnostics = Vec::new();
+            infer_impl(
+                name,
+                bodies,
+                index,
+                world_index,
+                &mut result,
+                if name == function_name { &mut diagnostics } else { &mut other_diagnostics },
+                &mut ty_vars,
+                &signatures,
+            );
+        }
+
+        let key = if function.infers_return_ty || function.is_extern {
+            None
+        } else {
+            let layout = bodies.layout(function_name, index);
+            let start = index.range_info(function_name).whole.start();
+            let key = function_key(
+                function_name,
+                &layout,
+                index,
+                world_index,
+                &result,
+                &ty_vars,
+                &signatures,
+            );
+
+            if let (Some(key), Some(cached)) = (key, self.functions.get_mut(&function_name)) {
+                if cached.key == key {
+                    cached.replay(&layout, start, index, world_index, &mut result);
+                    return Some(cached.diagnostics.clone());
+                }
+            }
+
+            self.inferred.push(function_name);
+            infer_impl(
+                function_name,
+                bodies,
+                index,
+                world_index,
+                &mut result,
+                &mut diagnostics,
+                &mut ty_vars,
+                &signatures,
+            );
+
+            key.map(|key| (key, layout, start))
+        };
+
+        default_return_tys(&signatures, &mut ty_vars);
+        result.resolve_ty_vars(&ty_vars, &mut diagnostics);
+
+        if let Some((key, layout, start)) = key {
+            let cached = CachedFunction::new(
+                key,
+                start,
+                &layout,
+                &result,
+                diagnostics.clone(),
+                index,
+                world_index,
+            );
+            self.functions.insert(function_name, cached);
+        }
+
+        Some(diagnostics)
+    }
+
+    /// The functions the last call to [`InferenceCache::infer_all`]
+    /// or [`InferenceCache::infer_function`] inferred
+    /// rather than reusing what it had cached.
+    pub fn inferred(&self) -> &[hir::Name] {
+        &self.inferred
+    }
+}
+
+impl CachedFunction {
+    fn new(
+        key: hir::Fingerprint,
+        start: TextSize,
+        layout: &hir::BodyLayout,
+        result: &InferenceResult,
+        diagnostics: Vec<TyDiagnostic>,
+        index: &hir::Index,
+        world_index: &hir::WorldIndex,
+    ) -> Self {
+        let params_locations: Vec<_> = layout
+            .callees
+            .iter()
+            .map(|callee| callee.params_location(index, world_index))
+            .collect();
+        let diagnostic_callees = diagnostics
+            .iter()
+            .map(|diagnostic| match diagnostic.kind {
+                TyDiagnosticKind::ArgMismatch { params: Some(params), .. } => {
+                    params_locations.iter().position(|location| *location == Some(params))
+                }
+                _ => None,
+            })
+            .collect();
+
+        Self {
+            key,
+            start,
+            expr_tys: layout.exprs.iter().map(|expr| result.expr_tys.get(*expr).cloned()).collect(),
+            local_tys: layout
+                .local_defs
+                .iter()
+                .map(|local_def| result.local_tys.get(*local_def).cloned())
+                .collect(),
+            diagnostics,
+            diagnostic_callees,
+        }
+    }
+
+    fn replay(
+        &mut self,
+        layout: &hir::BodyLayout,
+        start: TextSize,
+        index: &hir::Index,
+        world_index: &hir::WorldIndex,
+        result: &mut InferenceResult,
+    ) {
+        for (expr, ty) in layout.exprs.iter().zip(&self.expr_tys) {
+            if let Some(ty) = ty {
+                result.expr_tys.insert(*expr, ty.clone());
+            }
+        }
+
+        for (local_def, ty) in layout.local_defs.iter().zip(&self.local_tys) {
+            if let Some(ty) = ty {
+                result.local_tys.insert(*local_def, ty.clone());
+            }
+        }
+
+        let offset = i64::from(u32::from(start)) - i64::from(u32::from(self.start));
+        for (diagnostic, callee) in self.diagnostics.iter_mut().zip(&self.diagnostic_callees) {
+            diagnostic.range = shift(diagnostic.range, offset);
+
+            match (&mut diagnostic.kind, callee) {
+                (TyDiagnosticKind::ArgMismatch { params, .. }, Some(callee)) => {
+                    *params = layout.callees[*callee].params_location(index, world_index);
+                }
+                (TyDiagnosticKind::AnnotationMismatch { annotation: Some(range), .. }, _)
+                | (TyDiagnosticKind::ReturnTyMismatch { return_ty: Some(range), .. }, _) => {
+                    *range = shift(*range, offset);
+                }
+                _ => {}
+            }
+        }
+        self.start = start;
+    }
+}
+
+/// Hashes everything the inference of a function depends on,
+/// or returns `None` if the function can decide types other functions see,
+/// in which case it has to be inferred every time.
+fn function_key(
+    function_name: hir::Name,
+    layout: &hir::BodyLayout,
+    index: &hir::Index,
+    world_index: &hir::WorldIndex,
+    result: &InferenceResult,
+    ty_vars: &TyVars,
+    signatures: &FxHashMap<hir::Name, Signature>,
+) -> Option<hir::Fingerprint> {
+    let mut hasher = FxHasher::default();
+
+    layout.fingerprint.hash(&mut hasher);
+    index.get_function(function_name).hash(&mut hasher);
+
+    // mismatches with the return type point at it
+    let range_info = index.range_info(function_name);
+    let return_ty =
+        range_info.return_ty.and_then(|range| range.checked_sub(range_info.whole.start()));
+    return_ty.hash(&mut hasher);
+
+    for callee in &layout.callees {
+        match callee {
+            hir::Path::ThisModule(name) => {
+                index.get_function(*name).hash(&mut hasher);
+
+                let return_ty = &signatures[name].return_ty;
+                if ty_vars.has_unbound_vars(return_ty) {
+                    return None;
+                }
+                ty_vars.resolve(return_ty).hash(&mut hasher);
+            }
+            hir::Path::OtherModule(fqn) => world_index.get_function(*fqn).ok().hash(&mut hasher),
+            hir::Path::Builtin(_) => {}
+        }
+    }
+
+    for name in &layout.consts {
+        let ty = &result.consts[name].ty;
+        if ty_vars.has_unbound_vars(ty) {
+            return None;
+        }
+        ty_vars.resolve(ty).hash(&mut hasher);
+    }
+
+    Some(hir::Fingerprint(hasher.finish()))
+}
+
+fn shift(range: TextRange, offset: i64) -> TextRange {
+    let start = i64::from(u32::from(range.start())) + offset;
+    TextRange::at(TextSize::from(start as u32), range.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+    use expect_test::{expect, Expect};
+    use interner::Interner;
+
+    // checks every version of the module in turn with the same cache,
+    // making sure the result is the same as inferring it from scratch
+    fn check(versions: &[&str], expect: Expect) {
+        let mut interner = Interner::default();
+        let world_index = hir::WorldIndex::default();
+        let mut cache = InferenceCache::default();
+        let mut inferred = String::new();
+
+        for text in versions {
+            let tokens = lexer::lex(text);
+            let tree = parser::parse_source_file(&tokens, text).into_syntax_tree();
+            let root = ast::Root::cast(tree.root(), &tree).unwrap();
+            let (index, _) = hir::index(root, &tree, &world_index, &mut interner);
+            let (bodies, _) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+
+            let (result, mut diagnostics) = cache.infer_all(&bodies, &index, &world_index);
+            let (expected_result, mut expected_diagnostics) =
+                crate::infer_all(&bodies, &index, &world_index);
+
+            assert_eq!(result.debug(&interner), expected_result.debug(&interner));
+            let sort_key = |d: &TyDiagnostic| (d.range.start(), d.range.end(), format!("{d:?}"));
+            diagnostics.sort_by_key(sort_key);
+            expected_diagnostics.sort_by_key(sort_key);
+            assert_eq!(diagnostics, expected_diagnostics);
+
+            let mut names: Vec<_> =
+                cache.inferred().iter().map(|name| interner.lookup(name.0)).collect();
+            names.sort_unstable();
+            inferred.push_str(&names.join(", "));
+            inferred.push('\n');
+        }
+
+        expect.assert_eq(&inferred);
+    }
+
+    #[test]
+    fn reuse_unchanged_functions() {
+        check(
+            &[
+                r#"
+                    fnc a: s32 -> 1;
+                    fnc b: string -> a;
+                    fnc c: s32 -> { let x = [1, 2]; x[0] };
+                "#,
+                r#"
+                    fnc a: s32 -> 1 + 1;
+                    fnc b: string -> a;
+                    fnc c: s32 -> { let x = [1, 2]; x[0] };
+                "#,
+                r#"
+                    fnc a: s32 -> 1 + 1;
+
+                    fnc b: string -> a;
+                    fnc c: s32 -> { let x = [1, 2]; x[0] };
+                "#,
+            ],
+            expect![[r#"
+                a, b, c
+                a
+                
+            "#]],
+        );
+    }
+
+    #[test]
+    fn infer_callers_of_changed_signatures() {
+        check(
+            &[
+                r#"
+                    fnc a: s32 -> 1;
+                    fnc b: string -> a;
+                    fnc c: s32 -> 2;
+                "#,
+                r#"
+                    fnc a: string -> "1";
+                    fnc b: string -> a;
+                    fnc c: s32 -> 2;
+                "#,
+            ],
+            expect![[r#"
+                a, b, c
+                a, b
+            "#]],
+        );
+    }
+
+    #[test]
+    fn always_infer_functions_with_inferred_return_tys() {
+        check(
+            &[
+                r#"
+                    fnc a -> 1;
+                    fnc b: string -> a;
+                "#,
+                r#"
+                    fnc a -> 1;
+                    fnc b: string -> a;
+                "#,
+                r#"
+                    fnc a -> "1";
+                    fnc b: string -> a;
+                "#,
+            ],
+            expect![[r#"
+                a, b
+                a
+                a, b
+            "#]],
+        );
+    }
+
+    #[test]
+    fn move_params_of_callee() {
+        check(
+            &[
+                r#"
+                    fnc f(x: s32): unit -> {};
+                    fnc g: unit -> f "x";
+                "#,
+                r#"
+                    fnc h: unit -> {};
+                    fnc f(x: s32): unit -> {};
+                    fnc g: unit -> f "x";
+                "#,
+            ],
+            expect![[r#"
+                f, g
+                h
+            "#]],
+        );
+    }
+
+    #[test]
+    fn move_function_with_mismatches() {
+        check(
+            &[
+                r#"
+                    fnc f: string -> { let a: string = 1; 2 };
+                "#,
+                r#"
+                    fnc h: unit -> {};
+                    fnc f: string -> { let a: string = 1; 2 };
+                "#,
+            ],
+            expect![[r#"
+                f
+                h
+            "#]],
+        );
+    }
+
+    #[test]
+    fn infer_single_function() {
+        let mut interner = Interner::default();
+        let world_index = hir::WorldIndex::default();
+        let mut cache = InferenceCache::default();
+
+        let mut check_function = |text: &str, function: &str, expect: Expect| {
+            let tokens = lexer::lex(text);
+            let tree = parser::parse_source_file(&tokens, text).into_syntax_tree();
+            let root = ast::Root::cast(tree.root(), &tree).unwrap();
+            let (index, _) = hir::index(root, &tree, &world_index, &mut interner);
+            let (bodies, _) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+
+            let function = hir::Name(interner.intern(function));
+            let diagnostics = cache.infer_function(function, &bodies, &index, &world_index);
+
+            let mut names: Vec<_> =
+                cache.inferred().iter().map(|name| interner.lookup(name.0)).collect();
+            names.sort_unstable();
+            expect.assert_eq(&format!("{diagnostics:?}\ninferred: {}\n", names.join(", ")));
+        };
+
+        let text = r#"
+            fnc a -> 1;
+            fnc b: string -> a;
+            fnc c: s32 -> "c";
+        "#;
+        check_function(
+            text,
+            "b",
+            expect![[r#"
+                Some([TyDiagnostic { kind: ReturnTyMismatch { expected: String, found: S32, return_ty: Some(44..50) }, range: 54..55 }])
+                inferred: a, b
+            "#]],
+        );
+        check_function(
+            text,
+            "b",
+            expect![[r#"
+                Some([TyDiagnostic { kind: ReturnTyMismatch { expected: String, found: S32, return_ty: Some(44..50) }, range: 54..55 }])
+                inferred: a
+            "#]],
+        );
+        check_function(
+            text,
+            "d",
+            expect![[r#"
+                None
+                inferred: 
+            "#]],
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Finds the values a `match` doesn’t cover and the arms it never reaches,
+//! using the usefulness algorithm from Maranget’s
+//! “Warnings for pattern matching”.
+//!
+//! Bools, tuples, options and results are the only types whose values can all be listed,
+//! so a match on an integer, string or char needs a catch-all arm to be exhaustive.
+
+/// A value which none of the arms of a match cover,
+/// with `_` standing in for any value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Witness {
+    Wildcard,
+    Bool(bool),
+    Tuple(Vec<Witness>),
+    Variant(hir::Variant, Option<Box<Witness>>),
+}
+
+#[cfg(feature = "diagnostics")]
+impl std::fmt::Display for Witness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Wildcard => write!(f, "_"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Tuple(elements) => {
+                write!(f, "(")?;
+                for (idx, element) in elements.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Variant(variant, None) => write!(f, "{}", variant.name()),
+            Self::Variant(variant, Some(field)) => write!(f, "{} {field}", variant.name()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Ctor {
+    Bool(bool),
+    Int(i128),
+    String(String),
+    Char(char),
+    Tuple,
+    Variant(hir::Variant),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+impl Pat {
+    pub(crate) fn lower(pattern: arena::Id<hir::Pattern>, bodies: &hir::Bodies) -> Self {
+        let ctor = match &bodies[pattern] {
+            hir::Pattern::Missing | hir::Pattern::Wildcard | hir::Pattern::Bind(_) => {
+                return Self::Wild
+            }
+            hir::Pattern::IntLiteral { value, is_negated } => {
+                let value = *value as i128;
+                Ctor::Int(if *is_negated { -value } else { value })
+            }
+            hir::Pattern::BoolLiteral(b) => Ctor::Bool(*b),
+            hir::Pattern::StringLiteral(s) => Ctor::String(s.clone()),
+            hir::Pattern::CharLiteral(c) => Ctor::Char(*c),
+            hir::Pattern::Tuple(patterns) => {
+                let fields = patterns.iter().map(|pattern| Self::lower(*pattern, bodies));
+                return Self::Ctor(Ctor::Tuple, fields.collect());
+            }
+            hir::Pattern::Variant { variant, field } => {
+                let fields = field.iter().map(|field| Self::lower(*field, bodies));
+                return Self::Ctor(Ctor::Variant(*variant), fields.collect());
+            }
+        };
+
+        Self::Ctor(ctor, Vec::new())
+    }
+}
+
+/// Returns the values `row` matches which none of the rows in `matrix` do,
+/// with one witness per column.
+/// Every pattern has to fit the type of its column,
+/// which has to be known wherever a tuple pattern is used.
+/// `row` is useful (and so reachable after `matrix`) if there are any.
+pub(crate) fn usefulness(matrix: &[Vec<Pat>], row: &[Pat], tys: &[hir::Ty]) -> Vec<Vec<Witness>> {
+    let Some((head, rest)) = row.split_first() else {
+        return if matrix.is_empty() { vec![Vec::new()] } else { Vec::new() };
+    };
+
+    let ty = &tys[0];
+
+    if let Pat::Ctor(ctor, fields) = head {
+        let mut specialized_row = fields.clone();
+        specialized_row.extend_from_slice(rest);
+        return specialize_and_recurse(matrix, &specialized_row, ctor, ty, tys);
+    }
+
+    let head_ctors: Vec<&Ctor> = matrix
+        .iter()
+        .filter_map(|matrix_row| match &matrix_row[0] {
+            Pat::Ctor(ctor, _) => Some(ctor),
+            Pat::Wild => None,
+        })
+        .collect();
+
+    // if the matrix mentions every value of the type,
+    // `_` is only useful if one of them is
+    if let Some(all_ctors) = all_ctors(ty) {
+        if all_ctors.iter().all(|ctor| head_ctors.contains(&ctor)) {
+            let mut witnesses = Vec::new();
+            for ctor in &all_ctors {
+                let mut specialized_row = vec![Pat::Wild; arity(ctor, ty)];
+                specialized_row.extend_from_slice(rest);
+                witnesses.extend(specialize_and_recurse(matrix, &specialized_row, ctor, ty, tys));
+            }
+            return witnesses;
+        }
+    }
+
+    // otherwise only the rows which match anything in this column matter
+    let default_matrix: Vec<_> = matrix
+        .iter()
+        .filter(|matrix_row| matches!(matrix_row[0], Pat::Wild))
+        .map(|matrix_row| matrix_row[1..].to_vec())
+        .collect();
+
+    // the witness for this column is a value none of the rows mention
+    let missing_head = match all_ctors(ty) {
+        Some(all_ctors) if !head_ctors.is_empty() => {
+            let missing = all_ctors.into_iter().find(|ctor| !head_ctors.contains(&ctor));
+            match missing {
+                Some(Ctor::Bool(b)) => Witness::Bool(b),
+                Some(Ctor::Variant(variant)) => Witness::Variant(
+                    variant,
+                    variant.has_field().then(|| Box::new(Witness::Wildcard)),
+                ),
+                _ => Witness::Wildcard,
+            }
+        }
+        _ => Witness::Wildcard,
+    };
+
+    let mut witnesses = usefulness(&default_matrix, rest, &tys[1..]);
+    for witness in &mut witnesses {
+        witness.insert(0, missing_head.clone());
+    }
+    witnesses
+}
+
+fn specialize_and_recurse(
+    matrix: &[Vec<Pat>],
+    specialized_row: &[Pat],
+    ctor: &Ctor,
+    ty: &hir::Ty,
+    tys: &[hir::Ty],
+) -> Vec<Vec<Witness>> {
+    let arity = arity(ctor, ty);
+
+    let specialized_matrix: Vec<_> = matrix
+        .iter()
+        .filter_map(|matrix_row| {
+            let mut specialized = match &matrix_row[0] {
+                Pat::Ctor(c, fields) if c == ctor => fields.clone(),
+                Pat::Ctor(..) => return None,
+                Pat::Wild => vec![Pat::Wild; arity],
+            };
+            specialized.extend_from_slice(&matrix_row[1..]);
+            Some(specialized)
+        })
+        .collect();
+
+    let mut specialized_tys = field_tys(ctor, ty);
+    specialized_tys.extend_from_slice(&tys[1..]);
+
+    let mut witnesses = usefulness(&specialized_matrix, specialized_row, &specialized_tys);
+
+    for witness in &mut witnesses {
+        let mut fields: Vec<_> = witness.drain(..arity).collect();
+        let head = match ctor {
+            Ctor::Bool(b) => Witness::Bool(*b),
+            Ctor::Tuple => Witness::Tuple(fields),
+            Ctor::Variant(variant) => Witness::Variant(*variant, fields.pop().map(Box::new)),
+            // literals of infinite types only show up in the witnesses of rows
+            // which are checked for reachability, which doesn’t look at them
+            Ctor::Int(_) | Ctor::String(_) | Ctor::Char(_) => Witness::Wildcard,
+        };
+        witness.insert(0, head);
+    }
+
+    witnesses
+}
+
+/// Every constructor of `ty`, if there are few enough to list.
+fn all_ctors(ty: &hir::Ty) -> Option<Vec<Ctor>> {
+    match ty {
+        hir::Ty::Bool => Some(vec![Ctor::Bool(true), Ctor::Bool(false)]),
+        hir::Ty::Tuple(_) => Some(vec![Ctor::Tuple]),
+        hir::Ty::Option(_) => {
+            Some(vec![Ctor::Variant(hir::Variant::Some), Ctor::Variant(hir::Variant::None)])
+        }
+        hir::Ty::Result { .. } => {
+            Some(vec![Ctor::Variant(hir::Variant::Ok), Ctor::Variant(hir::Variant::Err)])
+        }
+        _ => None,
+    }
+}
+
+fn arity(ctor: &Ctor, ty: &hir::Ty) -> usize {
+    match (ctor, ty) {
+        (Ctor::Tuple, hir::Ty::Tuple(element_tys)) => element_tys.len(),
+        (Ctor::Variant(variant), _) => variant.has_field() as usize,
+        _ => 0,
+    }
+}
+
+fn field_tys(ctor: &Ctor, ty: &hir::Ty) -> Vec<hir::Ty> {
+    match (ctor, ty) {
+        (Ctor::Tuple, hir::Ty::Tuple(element_tys)) => element_tys.clone(),
+        (Ctor::Variant(variant), ty) => variant.field_ty(ty).cloned().into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
@@ -1,20 +1,33 @@
+mod cache;
+mod exhaustiveness;
+mod substitution;
+pub mod thir;
+mod unify;
+
 use arena::{ArenaMap, Id};
+use exhaustiveness::Pat;
 use interner::Interner;
 use rustc_hash::FxHashMap;
+use substitution::Substitution;
 use text_size::TextRange;
+use unify::TyVars;
 
-#[derive(Clone)]
+pub use cache::InferenceCache;
+pub use exhaustiveness::Witness;
+
+#[derive(Clone, Default)]
 pub struct InferenceResult {
     signatures: FxHashMap<hir::Name, Signature>,
     expr_tys: ArenaMap<Id<hir::Expr>, hir::Ty>,
     local_tys: ArenaMap<Id<hir::LocalDef>, hir::Ty>,
+    consts: FxHashMap<hir::Name, InferredConst>,
 }
 
 impl std::ops::Index<Id<hir::Expr>> for InferenceResult {
     type Output = hir::Ty;
 
     fn index(&self, expr: Id<hir::Expr>) -> &Self::Output {
-        &self.expr_tys[expr]
+        self.type_of(expr)
     }
 }
 
@@ -22,16 +35,45 @@ impl std::ops::Index<Id<hir::LocalDef>> for InferenceResult {
     type Output = hir::Ty;
 
     fn index(&self, local_def: Id<hir::LocalDef>) -> &Self::Output {
-        &self.local_tys[local_def]
+        self.type_of_local(local_def)
     }
 }
 
+/// The value of a constant, worked out while type checking.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstValue {
+    Int(i64),
+    Bool(bool),
+    String(String),
+    Char(char),
+}
+
 #[derive(Clone)]
-struct Signature {
+struct InferredConst {
+    ty: hir::Ty,
+    // missing if working out the value ran into an error
+    value: Option<ConstValue>,
+}
+
+/// The types a function takes and returns,
+/// with those which are omitted from its definition filled in.
+#[derive(Clone)]
+pub struct Signature {
+    ty_param_count: usize,
     return_ty: hir::Ty,
     param_tys: Vec<hir::Ty>,
 }
 
+impl Signature {
+    pub fn param_tys(&self) -> &[hir::Ty] {
+        &self.param_tys
+    }
+
+    pub fn return_ty(&self) -> &hir::Ty {
+        &self.return_ty
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TyDiagnostic {
     pub kind: TyDiagnosticKind,
@@ -40,7 +82,102 @@ pub struct TyDiagnostic {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TyDiagnosticKind {
-    Mismatch { expected: hir::Ty, found: hir::Ty },
+    Mismatch {
+        expected: hir::Ty,
+        found: hir::Ty,
+    },
+    /// A value doesn’t match the type annotation of the local, constant or parameter
+    /// it’s given to.
+    /// `annotation` is where the annotation is.
+    AnnotationMismatch {
+        expected: hir::Ty,
+        found: hir::Ty,
+        annotation: Option<TextRange>,
+    },
+    /// An argument doesn’t match the type of the parameter it’s passed to.
+    /// `params` is where the parameters of the function are defined,
+    /// if it’s defined anywhere.
+    ArgMismatch {
+        expected: hir::Ty,
+        found: hir::Ty,
+        params: Option<hir::ParamsLocation>,
+    },
+    /// What a function returns doesn’t match its return type.
+    /// `return_ty` is where the return type is.
+    ReturnTyMismatch {
+        expected: hir::Ty,
+        found: hir::Ty,
+        return_ty: Option<TextRange>,
+    },
+    /// What a function without a return type returns
+    /// doesn’t match the type its callers or its other `return`s decided on.
+    InferredReturnTyMismatch {
+        expected: hir::Ty,
+        found: hir::Ty,
+    },
+    OutOfRangeIntLiteral {
+        ty: hir::Ty,
+    },
+    InvalidCast {
+        from: hir::Ty,
+        to: hir::Ty,
+    },
+    NoSuchField {
+        ty: hir::Ty,
+        idx: u32,
+    },
+    /// `let (a, b) = value;` where `value` isn’t a tuple of two elements.
+    MismatchedTuplePattern {
+        len: u32,
+        found: hir::Ty,
+    },
+    NotIndexable {
+        ty: hir::Ty,
+    },
+    NonExhaustiveMatch {
+        missing: Vec<Witness>,
+    },
+    /// A match arm whose pattern only matches values the arms before it already cover.
+    UnreachableArm,
+    /// Statements or a tail expression after a statement which never finishes,
+    /// such as a `return`.
+    UnreachableCode,
+    /// An expression whose type has to be known where it’s used,
+    /// but can only be worked out from later uses.
+    AnnotationNeeded,
+    /// A constant whose value depends on itself.
+    RecursiveConst,
+    ConstOverflow {
+        ty: hir::Ty,
+    },
+    ConstDivisionByZero,
+}
+
+impl TyDiagnosticKind {
+    fn resolve_ty_vars(&mut self, ty_vars: &TyVars) {
+        match self {
+            Self::Mismatch { expected, found }
+            | Self::AnnotationMismatch { expected, found, .. }
+            | Self::ArgMismatch { expected, found, .. }
+            | Self::ReturnTyMismatch { expected, found, .. }
+            | Self::InferredReturnTyMismatch { expected, found } => {
+                *expected = ty_vars.resolve_fully(expected);
+                *found = ty_vars.resolve_fully(found);
+            }
+            Self::InvalidCast { from: ty, .. }
+            | Self::OutOfRangeIntLiteral { ty }
+            | Self::NoSuchField { ty, .. }
+            | Self::MismatchedTuplePattern { found: ty, .. }
+            | Self::NotIndexable { ty }
+            | Self::ConstOverflow { ty } => *ty = ty_vars.resolve_fully(ty),
+            Self::NonExhaustiveMatch { .. }
+            | Self::UnreachableArm
+            | Self::UnreachableCode
+            | Self::AnnotationNeeded
+            | Self::RecursiveConst
+            | Self::ConstDivisionByZero => {}
+        }
+    }
 }
 
 pub fn infer_all(
@@ -48,27 +185,44 @@ pub fn infer_all(
     index: &hir::Index,
     world_index: &hir::WorldIndex,
 ) -> (InferenceResult, Vec<TyDiagnostic>) {
-    let mut expr_tys = ArenaMap::default();
-    let mut local_tys = ArenaMap::default();
+    let mut result = InferenceResult::default();
     let mut diagnostics = Vec::new();
-    let mut signatures = FxHashMap::default();
+    let mut ty_vars = TyVars::default();
+    let signatures = signature_table(index, &mut ty_vars);
+
+    // functions whose return types are inferred are checked before the others,
+    // so that it’s their bodies rather than their callers which decide them
+    let mut function_names: Vec<_> = index.functions().collect();
+    function_names
+        .sort_unstable_by_key(|name| (!index.get_function(*name).unwrap().infers_return_ty, *name));
 
-    for function_name in index.functions() {
-        signatures.insert(
+    for function_name in function_names {
+        infer_impl(
             function_name,
-            infer_impl(
-                function_name,
-                bodies,
-                index,
-                world_index,
-                &mut expr_tys,
-                &mut local_tys,
-                &mut diagnostics,
-            ),
+            bodies,
+            index,
+            world_index,
+            &mut result,
+            &mut diagnostics,
+            &mut ty_vars,
+            &signatures,
         );
     }
 
-    let mut result = InferenceResult { signatures, expr_tys, local_tys };
+    // constants no function uses still have to be checked
+    infer_remaining_consts(
+        bodies,
+        index,
+        world_index,
+        &mut result,
+        &mut diagnostics,
+        &mut ty_vars,
+        &signatures,
+    );
+
+    default_return_tys(&signatures, &mut ty_vars);
+    result.signatures = signatures;
+    result.resolve_ty_vars(&ty_vars, &mut diagnostics);
     result.shrink_to_fit();
 
     (result, diagnostics)
@@ -80,210 +234,1452 @@ pub fn infer(
     index: &hir::Index,
     world_index: &hir::WorldIndex,
 ) -> (InferenceResult, Vec<TyDiagnostic>) {
-    let mut expr_tys = ArenaMap::default();
-    let mut local_tys = ArenaMap::default();
+    let mut result = InferenceResult::default();
     let mut diagnostics = Vec::new();
-    let signature = infer_impl(
+    let mut ty_vars = TyVars::default();
+    let signatures = signature_table(index, &mut ty_vars);
+
+    // the functions whose return types are inferred are checked too,
+    // so that calls to them have types,
+    // but nothing else about them is kept
+    let mut others: Vec<_> = index
+        .functions()
+        .filter(|name| {
+            *name != function_name && index.get_function(*name).unwrap().infers_return_ty
+        })
+        .collect();
+    others.sort_unstable();
+    for other in others {
+        infer_impl(
+            other,
+            bodies,
+            index,
+            world_index,
+            &mut InferenceResult::default(),
+            &mut Vec::new(),
+            &mut ty_vars,
+            &signatures,
+        );
+    }
+
+    infer_impl(
         function_name,
         bodies,
         index,
         world_index,
-        &mut expr_tys,
-        &mut local_tys,
+        &mut result,
         &mut diagnostics,
+        &mut ty_vars,
+        &signatures,
     );
 
-    let mut signatures = FxHashMap::default();
-    signatures.insert(function_name, signature);
-
-    let mut result = InferenceResult { signatures, expr_tys, local_tys };
+    default_return_tys(&signatures, &mut ty_vars);
+    result.signatures.insert(function_name, signatures[&function_name].clone());
+    result.resolve_ty_vars(&ty_vars, &mut diagnostics);
     result.shrink_to_fit();
 
     (result, diagnostics)
 }
 
+/// The signatures of every function in the module,
+/// which are worked out from their definitions before any body is inferred
+/// so that calls are checked against them whatever order functions come in.
+///
+/// A function whose return type is inferred gets a variable for it,
+/// which its body and its callers both decide.
+fn signature_table(index: &hir::Index, ty_vars: &mut TyVars) -> FxHashMap<hir::Name, Signature> {
+    index
+        .functions()
+        .map(|name| {
+            let function = index.get_function(name).unwrap();
+            let mut signature = get_signature(function);
+            if function.infers_return_ty {
+                signature.return_ty = ty_vars.new_var();
+            }
+            (name, signature)
+        })
+        .collect()
+}
+
+/// Infers the constants which haven’t been inferred yet.
+#[allow(clippy::too_many_arguments)]
+fn infer_remaining_consts(
+    bodies: &hir::Bodies,
+    index: &hir::Index,
+    world_index: &hir::WorldIndex,
+    result: &mut InferenceResult,
+    diagnostics: &mut Vec<TyDiagnostic>,
+    ty_vars: &mut TyVars,
+    signatures: &FxHashMap<hir::Name, Signature>,
+) {
+    for const_name in bodies.consts() {
+        if result.consts.contains_key(&const_name) {
+            continue;
+        }
+
+        let const_value = bodies.get_const(const_name).unwrap().value;
+        let mut ctx = Ctx {
+            expr_tys: &mut result.expr_tys,
+            local_tys: &mut result.local_tys,
+            consts: &mut result.consts,
+            param_tys: &[],
+            bodies,
+            index,
+            world_index,
+            diagnostics,
+            ty_vars,
+            signatures,
+            loop_tys: Vec::new(),
+            consts_in_progress: Vec::new(),
+            return_ty: hir::Ty::Unknown,
+            return_ty_range: None,
+            infers_return_ty: false,
+            function_body: const_value,
+        };
+        ctx.infer_const(const_name);
+    }
+}
+
+/// A function whose body never finishes
+/// returns `unit` unless its callers decide otherwise.
+fn default_return_tys(signatures: &FxHashMap<hir::Name, Signature>, ty_vars: &mut TyVars) {
+    for signature in signatures.values() {
+        if ty_vars.is_unbound_var(&signature.return_ty) {
+            ty_vars.unify(&signature.return_ty, &hir::Ty::Unit);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn infer_impl(
     function_name: hir::Name,
     bodies: &hir::Bodies,
     index: &hir::Index,
     world_index: &hir::WorldIndex,
-    expr_tys: &mut ArenaMap<Id<hir::Expr>, hir::Ty>,
-    local_tys: &mut ArenaMap<Id<hir::LocalDef>, hir::Ty>,
+    result: &mut InferenceResult,
     diagnostics: &mut Vec<TyDiagnostic>,
-) -> Signature {
-    let signature = get_signature(index.get_function(function_name).unwrap());
+    ty_vars: &mut TyVars,
+    signatures: &FxHashMap<hir::Name, Signature>,
+) {
+    let function = index.get_function(function_name).unwrap();
+    let signature = &signatures[&function_name];
+
+    if function.is_extern {
+        return;
+    }
 
     let mut ctx = Ctx {
-        expr_tys,
-        local_tys,
+        expr_tys: &mut result.expr_tys,
+        local_tys: &mut result.local_tys,
+        consts: &mut result.consts,
         param_tys: &signature.param_tys,
         bodies,
         index,
         world_index,
         diagnostics,
+        ty_vars,
+        signatures,
+        loop_tys: Vec::new(),
+        consts_in_progress: Vec::new(),
+        return_ty: signature.return_ty.clone(),
+        return_ty_range: index.range_info(function_name).return_ty,
+        infers_return_ty: function.infers_return_ty,
+        function_body: bodies.function_body(function_name),
     };
 
-    let function_body = bodies.function_body(function_name);
-
-    let actual_return_ty = ctx.infer_expr(function_body);
-    ctx.expect_match(actual_return_ty, signature.return_ty, function_body);
+    for (idx, param_ty) in signature.param_tys.iter().enumerate() {
+        let Some(default) = bodies.param_default(function_name, idx as u32) else { continue };
+        let origin = Origin::Annotation(bodies.annotation_range(default));
+        ctx.check_expr(default, param_ty.clone(), origin);
+    }
 
-    signature
+    let function_body = bodies.function_body(function_name);
+    ctx.check_expr(function_body, signature.return_ty.clone(), ctx.return_origin());
 }
 
 struct Ctx<'a> {
     expr_tys: &'a mut ArenaMap<Id<hir::Expr>, hir::Ty>,
     local_tys: &'a mut ArenaMap<Id<hir::LocalDef>, hir::Ty>,
+    consts: &'a mut FxHashMap<hir::Name, InferredConst>,
     param_tys: &'a [hir::Ty],
     bodies: &'a hir::Bodies,
     index: &'a hir::Index,
     world_index: &'a hir::WorldIndex,
     diagnostics: &'a mut Vec<TyDiagnostic>,
+    // shared by every function in the module,
+    // since the types of constants can contain variables
+    ty_vars: &'a mut TyVars,
+    // the signatures of the functions in this module
+    signatures: &'a FxHashMap<hir::Name, Signature>,
+    // the type of each enclosing loop and labeled block,
+    // which is only known once one of its `break`s has been seen
+    loop_tys: Vec<(Id<hir::Expr>, Option<hir::Ty>)>,
+    // constants are inferred the first time they’re used,
+    // so these are the ones whose values are being inferred right now
+    consts_in_progress: Vec<hir::Name>,
+    return_ty: hir::Ty,
+    return_ty_range: Option<TextRange>,
+    infers_return_ty: bool,
+    function_body: Id<hir::Expr>,
+}
+
+/// Where the type an expression is checked against comes from,
+/// which mismatches with it mention.
+#[derive(Clone, Copy)]
+enum Origin {
+    Annotation(Option<TextRange>),
+    Param(Option<hir::ParamsLocation>),
+    ReturnTy(Option<TextRange>),
+    InferredReturnTy,
+    // anything else, such as the operands of an operator
+    Other,
 }
 
 impl Ctx<'_> {
-    fn infer_statement(&mut self, statement_id: Id<hir::Statement>) {
+    /// Returns whether the statement never finishes,
+    /// in which case the rest of its block is never reached.
+    fn infer_statement(&mut self, statement_id: Id<hir::Statement>) -> bool {
         match &self.bodies[statement_id] {
-            hir::Statement::Expr(expr) => {
-                self.infer_expr(*expr);
-            }
+            hir::Statement::Expr(expr) => self.infer_expr(*expr) == hir::Ty::Never,
 
             hir::Statement::LocalDef(local_def) => {
-                let ty = self.infer_expr(self.bodies[*local_def].value);
+                let hir::LocalDef { value, ty: annotation, .. } = &self.bodies[*local_def];
+                let (ty, diverges) = self.infer_local_def_value(*value, annotation.clone());
+
+                // the type variables left in a local’s type aren’t generalized,
+                // so there’s no `let f = id;` which keeps `f` generic:
+                // functions aren’t values, so a local only ever holds
+                // what one call of a generic function returned,
+                // and it can be assigned to later,
+                // which has to fit whatever its uses decided
                 self.local_tys.insert(*local_def, ty);
+                diverges
+            }
+
+            hir::Statement::Destructure { value, ty: annotation, local_defs } => {
+                let (mut ty, diverges) = self.infer_local_def_value(*value, annotation.clone());
+
+                // the value has to be a tuple of as many elements as there are locals
+                if self.ty_vars.is_unbound_var(&ty) {
+                    let tuple = self.new_tuple_ty(local_defs.len());
+                    self.ty_vars.unify(&ty, &tuple);
+                    ty = tuple;
+                }
+
+                let element_tys = match ty {
+                    hir::Ty::Tuple(element_tys) if element_tys.len() == local_defs.len() => {
+                        element_tys
+                    }
+                    hir::Ty::Unknown | hir::Ty::Never => vec![hir::Ty::Unknown; local_defs.len()],
+                    found => {
+                        self.diagnostics.push(TyDiagnostic {
+                            kind: TyDiagnosticKind::MismatchedTuplePattern {
+                                len: local_defs.len() as u32,
+                                found,
+                            },
+                            range: self.bodies.range_for_expr(*value),
+                        });
+                        vec![hir::Ty::Unknown; local_defs.len()]
+                    }
+                };
+
+                for (local_def, ty) in local_defs.iter().zip(element_tys) {
+                    if let Some(local_def) = local_def {
+                        self.local_tys.insert(*local_def, ty);
+                    }
+                }
+
+                diverges
+            }
+
+            hir::Statement::Assign { local_def, value } => {
+                let local_ty = self.ty_vars.resolve(&self.local_tys[*local_def]);
+                self.check_expr(*value, local_ty, Origin::Other) == hir::Ty::Never
+            }
+
+            // the deferred expression only runs once the block is left,
+            // so the rest of the block is still reached
+            hir::Statement::Defer(expr) => {
+                // lowering has already reported jumps out of it to the loops around it
+                let loop_tys = std::mem::take(&mut self.loop_tys);
+                self.infer_expr(*expr);
+                self.loop_tys = loop_tys;
+                false
+            }
+
+            hir::Statement::Break { label, value } => {
+                let target = self.break_target(*label);
+                let hint = target.and_then(|idx| self.loop_tys[idx].1.clone());
+                let ty = match value {
+                    Some(value) => self.infer_expr_with_hint(*value, hint),
+                    None => hir::Ty::Unit,
+                };
+
+                // lowering has already reported `break`s outside of loops
+                // and to undefined labels
+                let Some(target) = target else {
+                    return true;
+                };
+                let (loop_expr, loop_ty) = self.loop_tys[target].clone();
+
+                match loop_ty {
+                    // a `break` without a value has nothing to point at,
+                    // so mismatches are reported on the whole loop
+                    Some(loop_ty) => self.expect_match(ty, loop_ty, value.unwrap_or(loop_expr)),
+                    None => self.loop_tys[target].1 = Some(ty),
+                }
+
+                true
+            }
+
+            hir::Statement::Continue { .. } => true,
+
+            hir::Statement::Return { value } => {
+                let return_ty = self.return_ty.clone();
+                match value {
+                    Some(value) => {
+                        self.check_expr(*value, return_ty, self.return_origin());
+                    }
+                    // like with `break`, a bare `return` is reported on the whole body
+                    None => self.expect_match_because(
+                        hir::Ty::Unit,
+                        return_ty,
+                        self.function_body,
+                        self.return_origin(),
+                    ),
+                }
+
+                true
+            }
+        }
+    }
+
+    /// The index in `loop_tys` of what a `break` with `label` leaves:
+    /// the innermost loop or block with that label,
+    /// or the innermost loop if there isn’t one.
+    fn break_target(&self, label: Option<hir::Name>) -> Option<usize> {
+        self.loop_tys.iter().rposition(|(expr, _)| {
+            let (expr_label, is_loop) = match &self.bodies[*expr] {
+                hir::Expr::Loop { label, .. } | hir::Expr::For { label, .. } => (*label, true),
+                hir::Expr::LabeledBlock { label, .. } => (Some(*label), false),
+                _ => unreachable!("only loops and labeled blocks can be broken out of"),
+            };
+
+            match label {
+                Some(label) => expr_label == Some(label),
+                None => is_loop,
+            }
+        })
+    }
+
+    /// Infers the value of a `let`, checking it against the annotation if there is one.
+    /// Returns the type of the local and whether the value never finishes.
+    fn infer_local_def_value(
+        &mut self,
+        value: Id<hir::Expr>,
+        annotation: Option<hir::Ty>,
+    ) -> (hir::Ty, bool) {
+        match annotation {
+            Some(ty) => {
+                let origin = Origin::Annotation(self.bodies.annotation_range(value));
+                let value_ty = self.check_expr(value, ty.clone(), origin);
+                (ty.expand_aliases(), value_ty == hir::Ty::Never)
+            }
+            None => {
+                let value_ty = self.infer_expr(value);
+                let diverges = value_ty == hir::Ty::Never;
+                (value_ty, diverges)
             }
         }
     }
 
+    /// Infers the type of `expr` and checks that it matches `expected`.
+    ///
+    /// The expected type is pushed down into tuples, arrays, variants,
+    /// the tails of blocks and the arms of matches,
+    /// so that a mismatch is reported on the innermost expression it’s in.
+    fn check_expr(&mut self, expr: Id<hir::Expr>, expected: hir::Ty, origin: Origin) -> hir::Ty {
+        let ty = match (&self.bodies[expr], strip_aliases(self.ty_vars.resolve(&expected))) {
+            (hir::Expr::Tuple(elements), hir::Ty::Tuple(element_tys))
+                if elements.len() == element_tys.len() =>
+            {
+                let element_tys = elements
+                    .iter()
+                    .zip(element_tys)
+                    .map(|(element, ty)| self.check_expr(*element, ty, origin))
+                    .collect();
+
+                hir::Ty::Tuple(element_tys)
+            }
+            (hir::Expr::Array(elements), hir::Ty::Array(element_ty)) => {
+                for element in elements {
+                    self.check_expr(*element, (*element_ty).clone(), origin);
+                }
+
+                hir::Ty::Array(Box::new(element_ty.expand_aliases()))
+            }
+            (hir::Expr::Variant { variant, value: Some(value) }, ty) if variant.belongs_to(&ty) => {
+                let field_ty = variant.field_ty(&ty).unwrap().clone();
+                let value_ty = self.check_expr(*value, field_ty, origin);
+
+                let mut ty = ty.expand_aliases();
+                *variant.field_ty_mut(&mut ty).unwrap() = value_ty;
+                ty
+            }
+            (hir::Expr::Block { statements, tail_expr: Some(tail), .. }, _) => {
+                self.infer_statements(statements, Some(*tail));
+                self.check_expr(*tail, expected, origin)
+            }
+            (hir::Expr::Match { value, arms }, _) => {
+                let diverges = self.infer_match(*value, arms, |ctx, body| {
+                    ctx.check_expr(body, expected.clone(), origin)
+                });
+
+                if diverges {
+                    hir::Ty::Never
+                } else {
+                    expected.expand_aliases()
+                }
+            }
+            _ => {
+                let ty = self.infer_expr_with_hint(expr, Some(expected.clone()));
+                self.expect_match_because(ty.clone(), expected, expr, origin);
+                return ty;
+            }
+        };
+
+        let ty = self.ty_vars.resolve(&ty);
+        self.expr_tys.insert(expr, ty.clone());
+
+        ty
+    }
+
     fn infer_expr(&mut self, expr: Id<hir::Expr>) -> hir::Ty {
+        self.infer_expr_with_hint(expr, None)
+    }
+
+    /// Like [`Ctx::infer_expr`],
+    /// but gives integer literals the type `hint` if it’s an integer type.
+    /// Whether the expression actually ends up with that type
+    /// still has to be checked by the caller.
+    ///
+    /// The types this infers never contain aliases:
+    /// only the types errors expect keep them, so that they can be shown.
+    fn infer_expr_with_hint(&mut self, expr: Id<hir::Expr>, hint: Option<hir::Ty>) -> hir::Ty {
+        let hint = hint.map(|hint| self.ty_vars.resolve(&hint.expand_aliases()));
+
         let ty = match &self.bodies[expr] {
             hir::Expr::Missing => hir::Ty::Unknown,
-            hir::Expr::IntLiteral(_) => hir::Ty::S32,
+            hir::Expr::IntLiteral(value) => self.infer_int_literal(expr, *value, hint, false),
+            hir::Expr::BoolLiteral(_) => hir::Ty::Bool,
             hir::Expr::StringLiteral(_) => hir::Ty::String,
-            hir::Expr::Binary { lhs, rhs, .. } => {
+            hir::Expr::CharLiteral(_) => hir::Ty::Char,
+            hir::Expr::Unit => hir::Ty::Unit,
+            hir::Expr::Unary { expr: operand, operator: hir::UnaryOperator::Neg } => {
+                // unsigned types can’t be negated,
+                // so a literal only takes on signed types
+                let hint = hint.filter(|ty| matches!(ty, hir::Ty::S32 | hir::Ty::S64));
+
+                // `-2147483648` is a valid `s32`, even though `2147483648` isn’t
+                let ty = match self.bodies[*operand] {
+                    hir::Expr::IntLiteral(value) => {
+                        let ty = self.infer_int_literal(*operand, value, hint, true);
+                        self.expr_tys.insert(*operand, ty.clone());
+                        ty
+                    }
+                    _ => self.infer_expr_with_hint(*operand, hint),
+                };
+
+                if !matches!(ty, hir::Ty::S32 | hir::Ty::S64) {
+                    self.expect_match(ty.clone(), hir::Ty::S32, *operand);
+                }
+
+                // an operand of unknown type has already been reported,
+                // and guessing a type for the result would only lead to more errors
+                if ty.is_int() || ty == hir::Ty::Unknown {
+                    ty
+                } else {
+                    hir::Ty::S32
+                }
+            }
+            hir::Expr::Cast { expr: operand, ty } => {
+                let to = ty.expand_aliases();
+                let hint = if to.is_int() { Some(to.clone()) } else { None };
+                let operand_ty = self.infer_expr_with_hint(*operand, hint);
+
+                if !is_valid_cast(&operand_ty, &to) {
+                    self.diagnostics.push(TyDiagnostic {
+                        kind: TyDiagnosticKind::InvalidCast { from: operand_ty, to: ty.clone() },
+                        range: self.bodies.range_for_expr(expr),
+                    });
+                }
+
+                to
+            }
+            hir::Expr::Tuple(elements) => {
+                // each element takes on the type of the matching element of the hint
+                let element_hints = match hint {
+                    Some(hir::Ty::Tuple(hints)) if hints.len() == elements.len() => {
+                        hints.into_iter().map(Some).collect()
+                    }
+                    _ => vec![None; elements.len()],
+                };
+
+                let element_tys = elements
+                    .iter()
+                    .zip(element_hints)
+                    .map(|(element, hint)| self.infer_expr_with_hint(*element, hint))
+                    .collect();
+
+                hir::Ty::Tuple(element_tys)
+            }
+            hir::Expr::Field { tuple, idx } => match self.infer_expr(*tuple) {
+                hir::Ty::Tuple(element_tys) if (*idx as usize) < element_tys.len() => {
+                    element_tys[*idx as usize].clone()
+                }
+                ty @ (hir::Ty::Unknown | hir::Ty::Never) => ty,
+                // there’s no telling how many elements the tuple will have
+                hir::Ty::Var(_) => {
+                    self.diagnostics.push(TyDiagnostic {
+                        kind: TyDiagnosticKind::AnnotationNeeded,
+                        range: self.bodies.range_for_expr(*tuple),
+                    });
+                    hir::Ty::Unknown
+                }
+                ty => {
+                    self.diagnostics.push(TyDiagnostic {
+                        kind: TyDiagnosticKind::NoSuchField { ty, idx: *idx },
+                        range: self.bodies.range_for_expr(expr),
+                    });
+                    hir::Ty::Unknown
+                }
+            },
+            hir::Expr::Array(elements) => {
+                let element_hint = match hint {
+                    Some(hir::Ty::Array(element_ty)) => Some(*element_ty),
+                    _ => None,
+                };
+
+                // the first element with a known type decides the type of the others,
+                // and an empty array’s is decided by how it’s used
+                let element_ty = self.ty_vars.new_var();
+                for element in elements {
+                    let hint = match self.ty_vars.resolve(&element_ty) {
+                        hir::Ty::Var(_) => element_hint.clone(),
+                        ty => Some(ty),
+                    };
+                    let ty = self.infer_expr_with_hint(*element, hint);
+                    self.expect_match(ty, element_ty.clone(), *element);
+                }
+
+                hir::Ty::Array(Box::new(element_ty))
+            }
+            hir::Expr::Index { array, index } => {
+                let array_ty = self.infer_expr(*array);
+
+                let index_ty = self.infer_expr_with_hint(*index, Some(hir::Ty::S32));
+                self.expect_match(index_ty, hir::Ty::S32, *index);
+
+                match array_ty {
+                    hir::Ty::Array(element_ty) => *element_ty,
+                    ty @ (hir::Ty::Unknown | hir::Ty::Never) => ty,
+                    hir::Ty::Var(_) => {
+                        let element_ty = self.ty_vars.new_var();
+                        let ty = hir::Ty::Array(Box::new(element_ty.clone()));
+                        self.ty_vars.unify(&array_ty, &ty);
+                        element_ty
+                    }
+                    ty => {
+                        self.diagnostics.push(TyDiagnostic {
+                            kind: TyDiagnosticKind::NotIndexable { ty },
+                            range: self.bodies.range_for_expr(*array),
+                        });
+                        hir::Ty::Unknown
+                    }
+                }
+            }
+            hir::Expr::Variant { variant, value } => {
+                // the enum takes on the type of the hint,
+                // except for the field, which takes on the type of the value
+                let mut ty = match hint {
+                    Some(hint) if variant.belongs_to(&hint) => hint,
+                    _ => self.new_enum_ty(*variant),
+                };
+
+                if let Some(value) = value {
+                    let field_hint = variant.field_ty(&ty).cloned();
+                    let value_ty = self.infer_expr_with_hint(*value, field_hint);
+                    *variant.field_ty_mut(&mut ty).unwrap() = value_ty;
+                }
+
+                ty
+            }
+            hir::Expr::Unary { expr, operator: hir::UnaryOperator::Not } => {
+                let expr_ty = self.infer_expr(*expr);
+                self.expect_match(expr_ty, hir::Ty::Bool, *expr);
+
+                hir::Ty::Bool
+            }
+            hir::Expr::Binary {
+                lhs,
+                rhs,
+                operator: hir::BinaryOperator::And | hir::BinaryOperator::Or,
+            } => {
                 let lhs_ty = self.infer_expr(*lhs);
                 let rhs_ty = self.infer_expr(*rhs);
 
-                self.expect_match(lhs_ty, hir::Ty::S32, *lhs);
-                self.expect_match(rhs_ty, hir::Ty::S32, *rhs);
+                self.expect_match(lhs_ty, hir::Ty::Bool, *lhs);
+                self.expect_match(rhs_ty, hir::Ty::Bool, *rhs);
 
-                hir::Ty::S32
+                hir::Ty::Bool
             }
-            hir::Expr::Block { statements, tail_expr, .. } => {
-                for statement in statements {
-                    self.infer_statement(*statement);
+            hir::Expr::Binary { lhs, rhs, operator } if operator.is_comparison() => {
+                let (lhs_ty, rhs_ty) = self.infer_operands(*lhs, *rhs, None);
+
+                match lhs_ty {
+                    hir::Ty::S32
+                    | hir::Ty::S64
+                    | hir::Ty::U32
+                    | hir::Ty::Bool
+                    | hir::Ty::String
+                    | hir::Ty::Char => self.expect_match(rhs_ty, lhs_ty, *rhs),
+                    hir::Ty::Var(_) if is_comparable(&rhs_ty) => {
+                        self.expect_match(lhs_ty, rhs_ty, *lhs)
+                    }
+                    _ => self.expect_match(lhs_ty, hir::Ty::S32, *lhs),
+                }
+
+                hir::Ty::Bool
+            }
+            hir::Expr::Binary { lhs, rhs, .. } => {
+                let (lhs_ty, rhs_ty) = self.infer_operands(*lhs, *rhs, hint);
+
+                // like with negation, an operand of unknown type makes the result unknown,
+                // though the other operand still has to be a number
+                if lhs_ty == hir::Ty::Unknown || rhs_ty == hir::Ty::Unknown {
+                    for (ty, operand) in [(lhs_ty, *lhs), (rhs_ty, *rhs)] {
+                        if !ty.is_int() {
+                            self.expect_match(ty, hir::Ty::S32, operand);
+                        }
+                    }
+                    hir::Ty::Unknown
+                } else if lhs_ty.is_int() {
+                    self.expect_match(rhs_ty, lhs_ty.clone(), *rhs);
+                    lhs_ty
+                } else {
+                    self.expect_match(lhs_ty, hir::Ty::S32, *lhs);
+                    self.expect_match(rhs_ty, hir::Ty::S32, *rhs);
+                    hir::Ty::S32
                 }
+            }
+            hir::Expr::Block { statements, tail_expr, .. } => {
+                let diverges = self.infer_statements(statements, *tail_expr);
 
                 match tail_expr {
-                    Some(tail) => self.infer_expr(*tail),
+                    Some(tail) => self.infer_expr_with_hint(*tail, hint),
+                    None if diverges => hir::Ty::Never,
                     None => hir::Ty::Unit,
                 }
             }
-            hir::Expr::Local(local_def) => self.local_tys[*local_def],
-            hir::Expr::Param { idx } => self.param_tys[*idx as usize],
+            hir::Expr::Local(local_def) => self.local_tys[*local_def].clone(),
+            hir::Expr::Param { idx } => self.param_tys[*idx as usize].expand_aliases(),
+            hir::Expr::Const(name) => {
+                if self.consts_in_progress.contains(name) {
+                    self.diagnostics.push(TyDiagnostic {
+                        kind: TyDiagnosticKind::RecursiveConst,
+                        range: self.bodies.range_for_expr(expr),
+                    });
+                    hir::Ty::Unknown
+                } else {
+                    self.infer_const(*name).ty.expand_aliases()
+                }
+            }
+            hir::Expr::Loop { body, .. } => {
+                self.loop_tys.push((expr, None));
+                self.infer_expr(*body);
+                let (_, ty) = self.loop_tys.pop().unwrap();
+
+                // the first `break` decides the type of the loop,
+                // and a loop without one can only be left through a `return`
+                ty.unwrap_or(hir::Ty::Never)
+            }
+            hir::Expr::For { local_def, start, end, body, .. } => {
+                for bound in [*start, *end] {
+                    let ty = self.infer_expr_with_hint(bound, Some(hir::Ty::S32));
+                    self.expect_match(ty, hir::Ty::S32, bound);
+                }
+
+                self.local_tys.insert(*local_def, hir::Ty::S32);
+
+                // a `for` loop can run zero times,
+                // so it can’t produce a value through a `break`
+                self.loop_tys.push((expr, Some(hir::Ty::Unit)));
+                self.infer_expr(*body);
+                self.loop_tys.pop();
+
+                hir::Ty::Unit
+            }
+            hir::Expr::LabeledBlock { body, .. } => {
+                self.loop_tys.push((expr, None));
+                let body_ty = self.infer_expr_with_hint(*body, hint);
+                let (_, ty) = self.loop_tys.pop().unwrap();
+
+                // like with a loop, the first `break` decides the type of the block,
+                // which its end has to match unless it’s never reached
+                match ty {
+                    Some(ty) if body_ty != hir::Ty::Never => {
+                        self.expect_match(body_ty, ty.clone(), *body);
+                        ty
+                    }
+                    Some(ty) => ty,
+                    None => body_ty,
+                }
+            }
+            hir::Expr::Match { value, arms } => {
+                // like with arrays, the first arm with a known type decides the type of the others
+                let match_ty = self.ty_vars.new_var();
+                let diverges = self.infer_match(*value, arms, |ctx, body| {
+                    let hint = match ctx.ty_vars.resolve(&match_ty) {
+                        hir::Ty::Var(_) => hint.clone(),
+                        ty => Some(ty),
+                    };
+                    let ty = ctx.infer_expr_with_hint(body, hint);
+                    ctx.expect_match(ty.clone(), match_ty.clone(), body);
+                    ty
+                });
+
+                match self.ty_vars.resolve(&match_ty) {
+                    // a match without arms can only be on a value which doesn’t exist
+                    hir::Ty::Var(_) if diverges => hir::Ty::Never,
+                    ty => ty,
+                }
+            }
             hir::Expr::Call { path, args } => {
-                let function = match *path {
-                    hir::Path::ThisModule(function) => self.index.get_function(function).unwrap(),
-                    hir::Path::OtherModule(fqn) => self.world_index.get_function(fqn).unwrap(),
+                let signature = match *path {
+                    hir::Path::ThisModule(function) => self.signatures[&function].clone(),
+                    hir::Path::OtherModule(fqn) => {
+                        get_signature(self.world_index.get_function(fqn).unwrap())
+                    }
+                    hir::Path::Builtin(builtin) => Signature {
+                        ty_param_count: builtin.ty_param_count(),
+                        ..get_signature(&builtin.signature())
+                    },
                 };
 
-                let signature = get_signature(function);
+                // every call gets its own variables for the type parameters
+                let vars: Vec<_> =
+                    (0..signature.ty_param_count).map(|_| self.ty_vars.new_var()).collect();
+                let instantiated = Substitution::with_tys(vars.clone());
+
+                // the arguments decide what the type parameters stand for,
+                // and the hint only gives types to the literals among them
+                // for those the arguments before them haven’t decided
+                let mut hinted = Substitution::new(signature.ty_param_count);
+                if let Some(hint) = &hint {
+                    hinted.unify(&signature.return_ty, hint);
+                }
+
+                let mut unknown = Substitution::new(signature.ty_param_count);
+
+                let origin = Origin::Param(path.params_location(self.index, self.world_index));
+                for (arg, param_ty) in args.iter().zip(&signature.param_tys) {
+                    // without type parameters, there’s nothing for the hint to add
+                    if signature.ty_param_count == 0 {
+                        self.check_expr(*arg, param_ty.clone(), origin);
+                        continue;
+                    }
 
-                for (idx, arg) in args.iter().enumerate() {
-                    let arg_ty = self.infer_expr(*arg);
-                    self.expect_match(arg_ty, signature.param_tys[idx], *arg);
+                    let decided = vars.iter().map(|var| match self.ty_vars.resolve(var) {
+                        hir::Ty::Var(_) => None,
+                        ty => Some(ty),
+                    });
+                    let mut arg_hint = Substitution::with_picks(decided.collect());
+                    arg_hint.fill_from(&hinted);
+
+                    let arg_ty = self.infer_expr_with_hint(*arg, Some(arg_hint.apply(param_ty)));
+                    unknown.pick_unknown(param_ty, &arg_ty);
+                    self.expect_match_because(arg_ty, instantiated.apply(param_ty), *arg, origin);
                 }
 
-                signature.return_ty
+                // type parameters which only arguments of unknown type could have decided
+                // are unknown too, rather than needing an annotation wherever they end up
+                let tys =
+                    vars.iter().enumerate().map(|(idx, var)| match self.ty_vars.resolve(var) {
+                        hir::Ty::Var(_) if unknown.is_picked(idx) => hir::Ty::Unknown,
+                        _ => var.clone(),
+                    });
+                let instantiated = Substitution::with_tys(tys.collect());
+
+                instantiated.apply(&signature.return_ty).expand_aliases()
             }
         };
 
-        self.expr_tys.insert(expr, ty);
+        // the variables which are bound by now are resolved right away,
+        // so that the type can be matched on
+        let ty = self.ty_vars.resolve(&ty);
+        self.expr_tys.insert(expr, ty.clone());
 
         ty
     }
 
-    fn expect_match(&mut self, found: hir::Ty, expected: hir::Ty, expr: Id<hir::Expr>) {
-        if found == hir::Ty::Unknown || expected == hir::Ty::Unknown {
-            return;
+    /// Infers the statements of a block, reporting whatever comes after one that never finishes.
+    /// Returns whether any of them never finishes.
+    fn infer_statements(
+        &mut self,
+        statements: &[Id<hir::Statement>],
+        tail_expr: Option<Id<hir::Expr>>,
+    ) -> bool {
+        let mut diverges = false;
+        let mut unreachable = None;
+        for statement in statements {
+            if diverges && unreachable.is_none() {
+                unreachable = Some(self.bodies.range_for_statement(*statement));
+            }
+            diverges |= self.infer_statement(*statement);
         }
 
-        if found != expected {
-            // if the erroneous expression is a block with a tail expression,
-            // attach the error to the tail instead of the whole block
-            let expr = match self.bodies[expr] {
-                hir::Expr::Block { tail_expr: Some(tail_expr), .. } => tail_expr,
-                _ => expr,
-            };
+        // missing tail expressions have nowhere to point to
+        let tail_range = tail_expr
+            .filter(|tail| !matches!(self.bodies[*tail], hir::Expr::Missing))
+            .map(|tail| self.bodies.range_for_expr(tail));
 
-            self.diagnostics.push(TyDiagnostic {
-                kind: TyDiagnosticKind::Mismatch { expected, found },
-                range: self.bodies.range_for_expr(expr),
-            });
+        let end = tail_range.or_else(|| Some(self.bodies.range_for_statement(*statements.last()?)));
+        let range = match (unreachable, tail_range) {
+            (Some(start), _) => Some(start.cover(end.unwrap())),
+            (None, Some(tail_range)) if diverges => Some(tail_range),
+            _ => None,
+        };
+
+        if let Some(range) = range {
+            self.diagnostics.push(TyDiagnostic { kind: TyDiagnosticKind::UnreachableCode, range });
         }
+
+        diverges
     }
-}
 
-fn get_signature(function: &hir::Function) -> Signature {
-    let return_ty = function.return_ty;
-    let param_tys: Vec<_> = function.params.iter().map(|param| param.ty).collect();
+    /// Infers the value of a match and checks its patterns against it,
+    /// leaving the arms’ bodies to `infer_arm`.
+    /// Returns whether none of the arms ever finish.
+    fn infer_match(
+        &mut self,
+        value: Id<hir::Expr>,
+        arms: &[hir::MatchArm],
+        mut infer_arm: impl FnMut(&mut Self, Id<hir::Expr>) -> hir::Ty,
+    ) -> bool {
+        let value_ty = self.infer_expr(value);
 
-    Signature { return_ty, param_tys }
-}
+        let mut diverges = true;
+        let mut patterns_are_valid = true;
+        for arm in arms {
+            patterns_are_valid &= self.check_pattern(arm.pattern, value_ty.clone());
+            diverges &= infer_arm(self, arm.body) == hir::Ty::Never;
+        }
 
-impl InferenceResult {
-    fn shrink_to_fit(&mut self) {
-        let Self { signatures, expr_tys, local_tys } = self;
-        signatures.shrink_to_fit();
-        expr_tys.shrink_to_fit();
-        local_tys.shrink_to_fit();
+        // the patterns can have worked out the type of the value
+        let value_ty = self.ty_vars.resolve(&value_ty);
+
+        // patterns which don’t fit the value have already been reported,
+        // and would only confuse the analysis
+        let value_ty_is_known =
+            !matches!(value_ty, hir::Ty::Unknown | hir::Ty::Never | hir::Ty::Var(_));
+        if patterns_are_valid && value_ty_is_known {
+            self.check_exhaustiveness(value, arms, value_ty);
+        }
+
+        diverges
     }
-}
 
-impl InferenceResult {
-    pub fn debug(&self, interner: &Interner) -> String {
-        let mut s = String::new();
+    /// Infers both operands of a binary expression,
+    /// letting an integer literal on either side take on the type of the other.
+    fn infer_operands(
+        &mut self,
+        lhs: Id<hir::Expr>,
+        rhs: Id<hir::Expr>,
+        hint: Option<hir::Ty>,
+    ) -> (hir::Ty, hir::Ty) {
+        if self.is_int_literal(lhs) && !self.is_int_literal(rhs) {
+            let rhs_ty = self.infer_expr_with_hint(rhs, hint);
+            let lhs_ty = self.infer_expr_with_hint(lhs, Some(rhs_ty.clone()));
+            return (lhs_ty, rhs_ty);
+        }
+
+        let lhs_ty = self.infer_expr_with_hint(lhs, hint);
+        let rhs_ty = self.infer_expr_with_hint(rhs, Some(lhs_ty.clone()));
+        (lhs_ty, rhs_ty)
+    }
+
+    fn is_int_literal(&self, expr: Id<hir::Expr>) -> bool {
+        match self.bodies[expr] {
+            hir::Expr::IntLiteral(_) => true,
+            hir::Expr::Unary { expr, operator: hir::UnaryOperator::Neg } => {
+                self.is_int_literal(expr)
+            }
+            _ => false,
+        }
+    }
+
+    fn infer_const(&mut self, name: hir::Name) -> InferredConst {
+        if let Some(inferred) = self.consts.get(&name) {
+            return inferred.clone();
+        }
+
+        let constant = self.bodies.get_const(name).unwrap();
+
+        // the value of a constant can’t `break` out of the loops
+        // of the function which happened to use it first
+        let loop_tys = std::mem::take(&mut self.loop_tys);
+        self.consts_in_progress.push(name);
+
+        let (ty, _) = self.infer_local_def_value(constant.value, constant.ty.clone());
 
-        let display_ty = |ty| match ty {
-            hir::Ty::Unknown => "<unknown>",
-            hir::Ty::Unit => "unit",
-            hir::Ty::S32 => "s32",
-            hir::Ty::String => "string",
+        self.consts_in_progress.pop();
+        self.loop_tys = loop_tys;
+
+        // the value is only any use if it has the type the constant claims to have
+        let value = if self.expr_tys[constant.value] == ty {
+            self.eval_const(constant.value)
+        } else {
+            None
         };
+        let inferred = InferredConst { ty, value };
+        self.consts.insert(name, inferred.clone());
 
-        for (name, signature) in &self.signatures {
-            s.push_str(&format!("{}(", interner.lookup(name.0)));
-            for (idx, param_ty) in signature.param_tys.iter().enumerate() {
-                if idx != 0 {
-                    s.push_str(", ");
+        inferred
+    }
+
+    /// Works out the value of an expression lowering has checked is constant,
+    /// or returns `None` if it can’t be worked out because of an error.
+    fn eval_const(&mut self, expr: Id<hir::Expr>) -> Option<ConstValue> {
+        let value = match &self.bodies[expr] {
+            // literals which don’t fit their type have already been reported
+            hir::Expr::IntLiteral(n) => {
+                let n = *n as i128;
+                if !int_range(self.expr_tys.get(expr)?)?.contains(&n) {
+                    return None;
                 }
-                s.push_str(display_ty(*param_ty));
+                ConstValue::Int(n as i64)
             }
-            s.push(')');
+            hir::Expr::BoolLiteral(b) => ConstValue::Bool(*b),
+            hir::Expr::StringLiteral(s) => ConstValue::String(s.clone()),
+            hir::Expr::CharLiteral(c) => ConstValue::Char(*c),
+            hir::Expr::Const(name) => self.consts.get(name)?.value.clone()?,
+            hir::Expr::Block { tail_expr: Some(tail_expr), .. } => self.eval_const(*tail_expr)?,
+            hir::Expr::Unary { expr: operand, operator: hir::UnaryOperator::Neg } => {
+                // `-2147483648` is a valid `s32`, even though `2147483648` isn’t
+                let n = match self.bodies[*operand] {
+                    hir::Expr::IntLiteral(n) => n as i128,
+                    _ => {
+                        let ConstValue::Int(n) = self.eval_const(*operand)? else { return None };
+                        n as i128
+                    }
+                };
+                self.check_const_int(expr, -n)?
+            }
+            hir::Expr::Binary { lhs, rhs, operator } => {
+                let ConstValue::Int(lhs) = self.eval_const(*lhs)? else { return None };
+                let ConstValue::Int(rhs) = self.eval_const(*rhs)? else { return None };
+                let (lhs, rhs) = (lhs as i128, rhs as i128);
 
-            s.push_str(&format!(": {}\n", display_ty(signature.return_ty)));
-        }
+                let result = match operator {
+                    hir::BinaryOperator::Add => lhs + rhs,
+                    hir::BinaryOperator::Sub => lhs - rhs,
+                    hir::BinaryOperator::Mul => lhs * rhs,
+                    hir::BinaryOperator::Div if rhs == 0 => {
+                        self.diagnostics.push(TyDiagnostic {
+                            kind: TyDiagnosticKind::ConstDivisionByZero,
+                            range: self.bodies.range_for_expr(expr),
+                        });
+                        return None;
+                    }
+                    hir::BinaryOperator::Div => lhs / rhs,
+                    _ => return None,
+                };
 
-        s.push('\n');
-        for (expr_id, ty) in self.expr_tys.iter() {
-            s.push_str(&format!("{}: {}\n", expr_id.to_raw(), display_ty(*ty)));
-        }
+                self.check_const_int(expr, result)?
+            }
+            _ => return None,
+        };
 
-        if self.local_tys.is_empty() {
-            return s;
-        }
+        Some(value)
+    }
 
-        s.push('\n');
-        for (local_def_id, ty) in self.local_tys.iter() {
-            s.push_str(&format!("l{}: {}\n", local_def_id.to_raw(), display_ty(*ty)));
+    // values of both operands fit in an `i64`,
+    // so the result of any operation on them fits in an `i128`
+    fn check_const_int(&mut self, expr: Id<hir::Expr>, n: i128) -> Option<ConstValue> {
+        let ty = self.expr_tys.get(expr)?.clone();
+
+        if !int_range(&ty)?.contains(&n) {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::ConstOverflow { ty },
+                range: self.bodies.range_for_expr(expr),
+            });
+            return None;
         }
 
-        s
+        Some(ConstValue::Int(n as i64))
     }
-}
 
-#[cfg(test)]
-mod tests {
+    fn infer_int_literal(
+        &mut self,
+        expr: Id<hir::Expr>,
+        value: u64,
+        hint: Option<hir::Ty>,
+        is_negated: bool,
+    ) -> hir::Ty {
+        let ty = match hint {
+            Some(ty) if ty.is_int() => ty,
+            _ => hir::Ty::S32,
+        };
+
+        // the most negative value of a signed type is one further from zero than the largest
+        let max = match ty {
+            hir::Ty::S32 => i32::MAX as u64 + is_negated as u64,
+            hir::Ty::S64 => i64::MAX as u64 + is_negated as u64,
+            hir::Ty::U32 => u32::MAX as u64,
+            _ => unreachable!(),
+        };
+
+        if value > max {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::OutOfRangeIntLiteral { ty: ty.clone() },
+                range: self.bodies.range_for_expr(expr),
+            });
+        }
+
+        ty
+    }
+
+    /// Checks that the pattern can match values of type `ty`,
+    /// giving the locals it binds their types.
+    /// Returns whether the pattern is fit for exhaustiveness checking.
+    fn check_pattern(&mut self, pattern: Id<hir::Pattern>, ty: hir::Ty) -> bool {
+        let ty = self.ty_vars.resolve(&ty);
+
+        let pattern_ty = match &self.bodies[pattern] {
+            hir::Pattern::Missing => return false,
+            hir::Pattern::Wildcard => return true,
+            hir::Pattern::Bind(local_def) => {
+                self.local_tys.insert(*local_def, ty);
+                return true;
+            }
+            hir::Pattern::IntLiteral { value, is_negated } => {
+                // like literal expressions, the pattern takes on the type of the value,
+                // as long as it’s an integer type which can hold the pattern’s sign
+                let pattern_ty = if ty.is_int() && !(*is_negated && ty == hir::Ty::U32) {
+                    ty.clone()
+                } else {
+                    hir::Ty::S32
+                };
+
+                let max = match pattern_ty {
+                    hir::Ty::S32 => i32::MAX as u64 + *is_negated as u64,
+                    hir::Ty::S64 => i64::MAX as u64 + *is_negated as u64,
+                    hir::Ty::U32 => u32::MAX as u64,
+                    _ => unreachable!(),
+                };
+
+                if *value > max {
+                    self.diagnostics.push(TyDiagnostic {
+                        kind: TyDiagnosticKind::OutOfRangeIntLiteral { ty: pattern_ty },
+                        range: self.bodies.range_for_pattern(pattern),
+                    });
+                    return false;
+                }
+
+                pattern_ty
+            }
+            hir::Pattern::BoolLiteral(_) => hir::Ty::Bool,
+            hir::Pattern::StringLiteral(_) => hir::Ty::String,
+            hir::Pattern::CharLiteral(_) => hir::Ty::Char,
+            hir::Pattern::Tuple(patterns) => {
+                let mut ty = ty;
+                if let hir::Ty::Var(_) = ty {
+                    let tuple = self.new_tuple_ty(patterns.len());
+                    self.ty_vars.unify(&ty, &tuple);
+                    ty = tuple;
+                }
+
+                let element_tys = match ty {
+                    hir::Ty::Tuple(element_tys) if element_tys.len() == patterns.len() => {
+                        element_tys
+                    }
+                    hir::Ty::Unknown | hir::Ty::Never => {
+                        for pattern in patterns {
+                            self.check_pattern(*pattern, hir::Ty::Unknown);
+                        }
+                        return false;
+                    }
+                    found => {
+                        self.diagnostics.push(TyDiagnostic {
+                            kind: TyDiagnosticKind::MismatchedTuplePattern {
+                                len: patterns.len() as u32,
+                                found,
+                            },
+                            range: self.bodies.range_for_pattern(pattern),
+                        });
+                        for pattern in patterns {
+                            self.check_pattern(*pattern, hir::Ty::Unknown);
+                        }
+                        return false;
+                    }
+                };
+
+                let mut is_valid = true;
+                for (pattern, ty) in patterns.iter().zip(element_tys) {
+                    is_valid &= self.check_pattern(*pattern, ty);
+                }
+                return is_valid;
+            }
+            hir::Pattern::Variant { variant, field } => {
+                let mut ty = ty;
+                if let hir::Ty::Var(_) = ty {
+                    let enum_ty = self.new_enum_ty(*variant);
+                    self.ty_vars.unify(&ty, &enum_ty);
+                    ty = enum_ty;
+                }
+
+                if variant.belongs_to(&ty) {
+                    return match (field, variant.field_ty(&ty)) {
+                        (Some(field), Some(field_ty)) => {
+                            self.check_pattern(*field, field_ty.clone())
+                        }
+                        _ => true,
+                    };
+                }
+
+                if !matches!(ty, hir::Ty::Unknown | hir::Ty::Never) {
+                    let diagnostic = TyDiagnostic {
+                        kind: TyDiagnosticKind::Mismatch { expected: ty, found: variant.enum_ty() },
+                        range: self.bodies.range_for_pattern(pattern),
+                    };
+
+                    // both patterns of the match a `?` is lowered to
+                    // have the range of the `?` expression
+                    if self.diagnostics.last() != Some(&diagnostic) {
+                        self.diagnostics.push(diagnostic);
+                    }
+                }
+
+                if let Some(field) = field {
+                    self.check_pattern(*field, hir::Ty::Unknown);
+                }
+                return false;
+            }
+        };
+
+        if !self.ty_vars.unify(&pattern_ty, &ty) {
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::Mismatch { expected: ty, found: pattern_ty },
+                range: self.bodies.range_for_pattern(pattern),
+            });
+            return false;
+        }
+
+        true
+    }
+
+    /// Reports the arms of a match which can never be reached
+    /// and the values none of its arms cover.
+    fn check_exhaustiveness(&mut self, value: Id<hir::Expr>, arms: &[hir::MatchArm], ty: hir::Ty) {
+        let tys = [ty];
+        let mut matrix = Vec::new();
+
+        for arm in arms {
+            let row = vec![Pat::lower(arm.pattern, self.bodies)];
+
+            if exhaustiveness::usefulness(&matrix, &row, &tys).is_empty() {
+                self.diagnostics.push(TyDiagnostic {
+                    kind: TyDiagnosticKind::UnreachableArm,
+                    range: self.bodies.range_for_pattern(arm.pattern),
+                });
+            }
+
+            matrix.push(row);
+        }
+
+        let missing = exhaustiveness::usefulness(&matrix, &[Pat::Wild], &tys);
+        if !missing.is_empty() {
+            let missing = missing.into_iter().map(|mut witness| witness.remove(0)).collect();
+            self.diagnostics.push(TyDiagnostic {
+                kind: TyDiagnosticKind::NonExhaustiveMatch { missing },
+                range: self.bodies.range_for_expr(value),
+            });
+        }
+    }
+
+    fn expect_match(&mut self, found: hir::Ty, expected: hir::Ty, expr: Id<hir::Expr>) {
+        self.expect_match_because(found, expected, expr, Origin::Other);
+    }
+
+    fn expect_match_because(
+        &mut self,
+        found: hir::Ty,
+        expected: hir::Ty,
+        expr: Id<hir::Expr>,
+        origin: Origin,
+    ) {
+        if self.ty_vars.unify(&found, &expected) {
+            return;
+        }
+
+        let kind = match origin {
+            Origin::Annotation(annotation) => {
+                TyDiagnosticKind::AnnotationMismatch { expected, found, annotation }
+            }
+            Origin::Param(params) => TyDiagnosticKind::ArgMismatch { expected, found, params },
+            Origin::ReturnTy(return_ty) => {
+                TyDiagnosticKind::ReturnTyMismatch { expected, found, return_ty }
+            }
+            Origin::InferredReturnTy => {
+                TyDiagnosticKind::InferredReturnTyMismatch { expected, found }
+            }
+            Origin::Other => TyDiagnosticKind::Mismatch { expected, found },
+        };
+
+        self.diagnostics
+            .push(TyDiagnostic { kind, range: self.bodies.range_for_expr(self.tail_of(expr)) });
+    }
+
+    // what the function returns also decides its return type if that’s inferred
+    fn return_origin(&self) -> Origin {
+        if self.infers_return_ty {
+            Origin::InferredReturnTy
+        } else {
+            Origin::ReturnTy(self.return_ty_range)
+        }
+    }
+
+    // if the erroneous expression is a block with a tail expression,
+    // errors are attached to the tail instead of the whole block
+    fn tail_of(&self, expr: Id<hir::Expr>) -> Id<hir::Expr> {
+        match self.bodies[expr] {
+            hir::Expr::Block { tail_expr: Some(tail_expr), .. } => tail_expr,
+            _ => expr,
+        }
+    }
+}
+
+impl Ctx<'_> {
+    fn new_tuple_ty(&mut self, len: usize) -> hir::Ty {
+        hir::Ty::Tuple((0..len).map(|_| self.ty_vars.new_var()).collect())
+    }
+
+    /// The type of the enum `variant` belongs to,
+    /// with variables for its arguments.
+    fn new_enum_ty(&mut self, variant: hir::Variant) -> hir::Ty {
+        match variant.enum_ty() {
+            hir::Ty::Option(_) => hir::Ty::Option(Box::new(self.ty_vars.new_var())),
+            hir::Ty::Result { .. } => hir::Ty::Result {
+                ok: Box::new(self.ty_vars.new_var()),
+                err: Box::new(self.ty_vars.new_var()),
+            },
+            _ => unreachable!(),
+        }
+    }
+}
+
+// the aliases around a type don’t change what it’s made of
+fn strip_aliases(ty: hir::Ty) -> hir::Ty {
+    match ty {
+        hir::Ty::Alias { ty, .. } => strip_aliases(*ty),
+        ty => ty,
+    }
+}
+
+fn is_comparable(ty: &hir::Ty) -> bool {
+    matches!(
+        ty,
+        hir::Ty::S32
+            | hir::Ty::S64
+            | hir::Ty::U32
+            | hir::Ty::Bool
+            | hir::Ty::String
+            | hir::Ty::Char
+    )
+}
+
+fn int_range(ty: &hir::Ty) -> Option<std::ops::RangeInclusive<i128>> {
+    match ty {
+        hir::Ty::S32 => Some(i32::MIN as i128..=i32::MAX as i128),
+        hir::Ty::S64 => Some(i64::MIN as i128..=i64::MAX as i128),
+        hir::Ty::U32 => Some(0..=u32::MAX as i128),
+        _ => None,
+    }
+}
+
+/// Unknown types have already been reported,
+/// and an expression which never finishes can stand in for any type,
+/// so both are compatible with anything, even inside of other types.
+fn is_compatible(found: &hir::Ty, expected: &hir::Ty) -> bool {
+    match (found, expected) {
+        (hir::Ty::Unknown | hir::Ty::Never, _) | (_, hir::Ty::Unknown) => true,
+        // only unification can tell whether a variable fits
+        (hir::Ty::Var(_), _) | (_, hir::Ty::Var(_)) => true,
+        (hir::Ty::Alias { ty: found, .. }, expected) => is_compatible(found, expected),
+        (found, hir::Ty::Alias { ty: expected, .. }) => is_compatible(found, expected),
+        (hir::Ty::Tuple(found), hir::Ty::Tuple(expected)) => {
+            found.len() == expected.len()
+                && found
+                    .iter()
+                    .zip(expected)
+                    .all(|(found, expected)| is_compatible(found, expected))
+        }
+        (hir::Ty::Array(found), hir::Ty::Array(expected))
+        | (hir::Ty::Option(found), hir::Ty::Option(expected)) => is_compatible(found, expected),
+        (
+            hir::Ty::Result { ok: found_ok, err: found_err },
+            hir::Ty::Result { ok: expected_ok, err: expected_err },
+        ) => is_compatible(found_ok, expected_ok) && is_compatible(found_err, expected_err),
+        _ => found == expected,
+    }
+}
+
+/// Casts convert between integer types,
+/// turn bools and chars into integers,
+/// and turn any of those into strings.
+fn is_valid_cast(from: &hir::Ty, to: &hir::Ty) -> bool {
+    if is_compatible(from, to) {
+        return true;
+    }
+
+    let from_is_numeric = from.is_int() || matches!(from, hir::Ty::Bool | hir::Ty::Char);
+
+    match to {
+        hir::Ty::String => from_is_numeric,
+        _ if to.is_int() => from_is_numeric,
+        _ => false,
+    }
+}
+
+fn get_signature(function: &hir::Function) -> Signature {
+    let ty_param_count = function.ty_params.len();
+    let return_ty = function.return_ty.clone();
+    let param_tys: Vec<_> = function.params.iter().map(|param| param.ty.clone()).collect();
+
+    Signature { ty_param_count, return_ty, param_tys }
+}
+
+impl InferenceResult {
+    fn shrink_to_fit(&mut self) {
+        let Self { signatures, expr_tys, local_tys, consts } = self;
+        signatures.shrink_to_fit();
+        expr_tys.shrink_to_fit();
+        local_tys.shrink_to_fit();
+        consts.shrink_to_fit();
+    }
+
+    fn resolve_ty_vars(&mut self, ty_vars: &TyVars, diagnostics: &mut [TyDiagnostic]) {
+        let Self { signatures, expr_tys, local_tys, consts } = self;
+
+        for signature in signatures.values_mut() {
+            signature.return_ty = ty_vars.resolve_fully(&signature.return_ty);
+        }
+
+        for ty in expr_tys.values_mut().chain(local_tys.values_mut()) {
+            *ty = ty_vars.resolve_fully(ty);
+        }
+
+        for inferred in consts.values_mut() {
+            inferred.ty = ty_vars.resolve_fully(&inferred.ty);
+        }
+
+        for diagnostic in diagnostics {
+            diagnostic.kind.resolve_ty_vars(ty_vars);
+        }
+    }
+
+    /// The type an expression was inferred to have.
+    pub fn type_of(&self, expr: Id<hir::Expr>) -> &hir::Ty {
+        &self.expr_tys[expr]
+    }
+
+    /// The type a local was inferred to have.
+    pub fn type_of_local(&self, local_def: Id<hir::LocalDef>) -> &hir::Ty {
+        &self.local_tys[local_def]
+    }
+
+    /// The signature of a function in this module.
+    pub fn signature(&self, function: hir::Name) -> Option<&Signature> {
+        self.signatures.get(&function)
+    }
+
+    /// The type a function returns,
+    /// which is only known once its body has been inferred if it isn’t written down.
+    pub fn return_ty(&self, function: hir::Name) -> &hir::Ty {
+        &self.signatures[&function].return_ty
+    }
+
+    /// The value of a constant,
+    /// which is missing if type checking reported an error while working it out.
+    pub fn const_value(&self, name: hir::Name) -> Option<&ConstValue> {
+        self.consts.get(&name)?.value.as_ref()
+    }
+}
+
+impl InferenceResult {
+    pub fn debug(&self, interner: &Interner) -> String {
+        let mut s = String::new();
+
+        fn display_ty(ty: &hir::Ty) -> String {
+            match ty {
+                hir::Ty::Unknown => "<unknown>".to_string(),
+                hir::Ty::Tuple(element_tys) => {
+                    let element_tys: Vec<_> = element_tys.iter().map(display_ty).collect();
+                    format!("({})", element_tys.join(", "))
+                }
+                hir::Ty::Array(element_ty) => format!("[{}]", display_ty(element_ty)),
+                hir::Ty::Option(ty) => format!("Option[{}]", display_ty(ty)),
+                hir::Ty::Result { ok, err } => {
+                    format!("Result[{}, {}]", display_ty(ok), display_ty(err))
+                }
+                _ => ty.to_string(),
+            }
+        }
+
+        for (name, signature) in &self.signatures {
+            s.push_str(&format!("{}(", interner.lookup(name.0)));
+            for (idx, param_ty) in signature.param_tys.iter().enumerate() {
+                if idx != 0 {
+                    s.push_str(", ");
+                }
+                s.push_str(&display_ty(param_ty));
+            }
+            s.push(')');
+
+            s.push_str(&format!(": {}\n", display_ty(&signature.return_ty)));
+        }
+
+        let mut consts: Vec<_> = self.consts.iter().collect();
+        consts.sort_unstable_by_key(|(name, _)| *name);
+
+        for (name, inferred) in consts {
+            s.push_str(&format!("{}: {}", interner.lookup(name.0), display_ty(&inferred.ty)));
+            match &inferred.value {
+                Some(value) => s.push_str(&format!(" = {value:?}\n")),
+                None => s.push('\n'),
+            }
+        }
+
+        s.push('\n');
+        for (expr_id, ty) in self.expr_tys.iter() {
+            s.push_str(&format!("{}: {}\n", expr_id.to_raw(), display_ty(ty)));
+        }
+
+        if self.local_tys.is_empty() {
+            return s;
+        }
+
+        s.push('\n');
+        for (local_def_id, ty) in self.local_tys.iter() {
+            s.push_str(&format!("l{}: {}\n", local_def_id.to_raw(), display_ty(ty)));
+        }
+
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
     use ast::AstNode;
     use expect_test::{expect, Expect};
@@ -333,344 +1729,3543 @@ mod tests {
             })
             .collect();
 
-        assert_eq!(expected_diagnostics, actual_diagnostics);
+        assert_eq!(expected_diagnostics, actual_diagnostics);
+    }
+
+    #[test]
+    fn unit_function() {
+        check(
+            r#"
+                fnc foo -> {};
+            "#,
+            "foo",
+            expect![[r#"
+                foo(): unit
+
+                0: unit
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn unit_literal() {
+        check(
+            r#"
+                fnc foo: unit -> unit;
+            "#,
+            "foo",
+            expect![[r#"
+                foo(): unit
+
+                0: unit
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn unit_literal_in_non_unit_function() {
+        check(
+            r#"
+                fnc foo: s32 -> unit;
+            "#,
+            "foo",
+            expect![[r#"
+                foo(): s32
+
+                0: unit
+            "#]],
+            [(
+                TyDiagnosticKind::ReturnTyMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::Unit,
+                    return_ty: Some(TextRange::new(26.into(), 29.into())),
+                },
+                33..37,
+            )],
+        );
+    }
+
+    #[test]
+    fn function_with_return_ty() {
+        check(
+            r#"
+                fnc one: s32 -> 1;
+            "#,
+            "one",
+            expect![[r#"
+                one(): s32
+
+                0: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn function_with_undefined_return_ty() {
+        check(
+            r#"
+                fnc one: foo -> 1;
+            "#,
+            "one",
+            expect![[r#"
+                one(): <unknown>
+
+                0: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn function_with_missing_return_ty() {
+        check(
+            r#"
+                fnc one: -> 1;
+            "#,
+            "one",
+            expect![[r#"
+                one(): <unknown>
+
+                0: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn binary_expr() {
+        check(
+            r#"
+                fnc twenty: s32 -> 10 + 10;
+            "#,
+            "twenty",
+            expect![[r#"
+                twenty(): s32
+
+                0: s32
+                1: s32
+                2: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn comparison() {
+        check(
+            r#"
+                fnc f: bool -> 1 + 1 >= 2;
+            "#,
+            "f",
+            expect![[r#"
+                f(): bool
+
+                0: s32
+                1: s32
+                2: s32
+                3: s32
+                4: bool
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn comparison_of_mismatched_operands() {
+        check(
+            r#"
+                fnc f: bool -> 1 == true;
+            "#,
+            "f",
+            expect![[r#"
+                f(): bool
+
+                0: s32
+                1: bool
+                2: bool
+            "#]],
+            [(TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Bool }, 37..41)],
+        );
+    }
+
+    #[test]
+    fn comparison_of_strings() {
+        check(
+            r#"
+                fnc f: bool -> "a" < "b";
+            "#,
+            "f",
+            expect![[r#"
+                f(): bool
+
+                0: string
+                1: string
+                2: bool
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn int_literals_take_expected_type() {
+        check(
+            r#"
+                fnc f(x: s64, y: u32): s64 -> x * 2 - {1 + x};
+            "#,
+            "f",
+            expect![[r#"
+                f(s64, u32): s64
+
+                0: s64
+                1: s64
+                2: s64
+                3: s64
+                4: s64
+                5: s64
+                6: s64
+                7: s64
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn int_literal_args() {
+        check(
+            r#"
+                fnc f(x: s64, y: u32) -> {};
+                fnc g -> f 5000000000, 4000000000;
+            "#,
+            "g",
+            expect![[r#"
+                g(): unit
+
+                1: s64
+                2: u32
+                3: unit
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn mismatched_int_types() {
+        check(
+            r#"
+                fnc f(x: s32, y: s64): bool -> x < y;
+            "#,
+            "f",
+            expect![[r#"
+                f(s32, s64): bool
+
+                0: s32
+                1: s64
+                2: bool
+            "#]],
+            [(TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::S64 }, 52..53)],
+        );
+    }
+
+    #[test]
+    fn out_of_range_int_literals() {
+        check(
+            r#"
+                fnc f: s32 -> 2147483648;
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: s32
+            "#]],
+            [(TyDiagnosticKind::OutOfRangeIntLiteral { ty: hir::Ty::S32 }, 31..41)],
+        );
+    }
+
+    #[test]
+    fn min_int_literals() {
+        check(
+            r#"
+                fnc f: s32 -> -2147483648;
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: s32
+                1: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn negate_u32() {
+        check(
+            r#"
+                fnc f(x: u32): u32 -> -x;
+            "#,
+            "f",
+            expect![[r#"
+                f(u32): u32
+
+                0: u32
+                1: u32
+            "#]],
+            [(TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::U32 }, 40..41)],
+        );
+    }
+
+    #[test]
+    fn casts() {
+        check(
+            r#"
+                fnc f(n: s32, c: char, b: bool): string -> {
+                    let wide = n as s64 + c as s64 + b as s64;
+                    let big = 3000000000 as u32;
+                    wide as string
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(s32, char, bool): string
+
+                0: s32
+                1: s64
+                2: char
+                3: s64
+                4: s64
+                5: bool
+                6: s64
+                7: s64
+                8: u32
+                9: u32
+                10: s64
+                11: string
+                12: string
+
+                l0: s64
+                l1: u32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn invalid_casts() {
+        check(
+            r#"
+                fnc f: s32 -> {
+                    let n = "42" as s32;
+                    let c = 97 as char;
+                    n
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: string
+                1: s32
+                2: s32
+                3: char
+                4: s32
+                5: s32
+
+                l0: s32
+                l1: char
+            "#]],
+            [
+                (TyDiagnosticKind::InvalidCast { from: hir::Ty::String, to: hir::Ty::S32 }, 61..72),
+                (TyDiagnosticKind::InvalidCast { from: hir::Ty::S32, to: hir::Ty::Char }, 102..112),
+            ],
+        );
+    }
+
+    #[test]
+    fn local_def_with_ty() {
+        check(
+            r#"
+                fnc f: s64 -> {
+                    let big: s64 = 5000000000;
+                    let n: u32 = {1 + 2};
+                    big
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s64
+
+                0: s64
+                1: u32
+                2: u32
+                3: u32
+                4: u32
+                5: s64
+                6: s64
+
+                l0: s64
+                l1: u32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn local_def_with_mismatched_ty() {
+        check(
+            r#"
+                fnc f -> {
+                    let s: string = 5;
+                    let t = s;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: string
+                2: unit
+
+                l0: string
+                l1: string
+            "#]],
+            [(
+                TyDiagnosticKind::AnnotationMismatch {
+                    expected: hir::Ty::String,
+                    found: hir::Ty::S32,
+                    annotation: Some(TextRange::new(55.into(), 61.into())),
+                },
+                64..65,
+            )],
+        );
+    }
+
+    #[test]
+    fn ty_aliases() {
+        check(
+            r#"
+                type Meters = s64;
+                type Point = (Meters, Meters);
+                fnc f(p: Point): Meters -> {
+                    let (x, y) = p;
+                    let sum: Meters = x + y + 1;
+                    sum
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(Point): Meters
+
+                0: (s64, s64)
+                1: s64
+                2: s64
+                3: s64
+                4: s64
+                5: s64
+                6: s64
+                7: s64
+
+                l0: s64
+                l1: s64
+                l2: s64
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn mismatch_with_ty_alias() {
+        check(
+            r#"
+                type Name = string;
+                fnc greet(name: Name): Name -> {
+                    let n: Name = 5;
+                    true
+                };
+            "#,
+            "greet",
+            expect![[r#"
+                greet(Name): Name
+
+                0: s32
+                1: bool
+                2: bool
+
+                l0: string
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::AnnotationMismatch {
+                        expected: hir::Ty::Alias {
+                            name: "Name".to_string(),
+                            ty: Box::new(hir::Ty::String),
+                        },
+                        found: hir::Ty::S32,
+                        annotation: Some(TextRange::new(113.into(), 117.into())),
+                    },
+                    120..121,
+                ),
+                (
+                    TyDiagnosticKind::ReturnTyMismatch {
+                        expected: hir::Ty::Alias {
+                            name: "Name".to_string(),
+                            ty: Box::new(hir::Ty::String),
+                        },
+                        found: hir::Ty::Bool,
+                        return_ty: Some(TextRange::new(76.into(), 80.into())),
+                    },
+                    143..147,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn generic_function() {
+        check(
+            r#"
+                fnc id[T](x: T): T -> {
+                    let y: T = x;
+                    y
+                };
+            "#,
+            "id",
+            expect![[r#"
+                id(T): T
+
+                0: T
+                1: T
+                2: T
+
+                l0: T
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn type_params_are_opaque() {
+        check(
+            r#"
+                fnc add_one[T](x: T): T -> x + 1;
+            "#,
+            "add_one",
+            expect![[r#"
+                add_one(T): T
+
+                0: T
+                1: s32
+                2: s32
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::Mismatch {
+                        expected: hir::Ty::S32,
+                        found: hir::Ty::Param { idx: 0, name: "T".to_string() },
+                    },
+                    44..45,
+                ),
+                (
+                    TyDiagnosticKind::ReturnTyMismatch {
+                        expected: hir::Ty::Param { idx: 0, name: "T".to_string() },
+                        found: hir::Ty::S32,
+                        return_ty: Some(TextRange::new(39.into(), 40.into())),
+                    },
+                    44..49,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn generic_calls() {
+        check(
+            r#"
+                fnc id[T](x: T): T -> x;
+                fnc pair[A, B](a: A, b: B): (A, B) -> (a, b);
+                fnc first[T](xs: [T]): Option[T] -> None;
+                fnc none[T]: Option[T] -> None;
+                fnc f -> {
+                    let a = id true;
+                    let b: s64 = id 5;
+                    let c = pair "x", id 'y';
+                    let d = first [1, 2];
+                    let e: Option[string] = none;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                6: bool
+                7: bool
+                8: s64
+                9: s64
+                10: string
+                11: char
+                12: char
+                13: (string, char)
+                14: s32
+                15: s32
+                16: [s32]
+                17: Option[s32]
+                18: Option[string]
+                19: unit
+
+                l0: bool
+                l1: s64
+                l2: (string, char)
+                l3: Option[s32]
+                l4: Option[string]
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn mismatched_generic_call() {
+        check(
+            r#"
+                fnc same[T](a: T, b: T): T -> a;
+                fnc f: s32 -> same "a", 1;
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                1: string
+                2: s32
+                3: string
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::ArgMismatch {
+                        expected: hir::Ty::String,
+                        found: hir::Ty::S32,
+                        params: Some(hir::ParamsLocation {
+                            module: None,
+                            range: TextRange::new(28.into(), 40.into()),
+                        }),
+                    },
+                    90..91,
+                ),
+                (
+                    TyDiagnosticKind::ReturnTyMismatch {
+                        expected: hir::Ty::S32,
+                        found: hir::Ty::String,
+                        return_ty: Some(TextRange::new(73.into(), 76.into())),
+                    },
+                    80..91,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn tuples() {
+        check(
+            r#"
+                fnc f(pair: (s32, bool)): (bool, s64) -> {
+                    let swapped = (pair.1, pair.0);
+                    (swapped.0, 5)
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f((s32, bool)): (bool, s64)
+
+                0: (s32, bool)
+                1: bool
+                2: (s32, bool)
+                3: s32
+                4: (bool, s32)
+                5: (bool, s32)
+                6: bool
+                7: s64
+                8: (bool, s64)
+                9: (bool, s64)
+
+                l0: (bool, s32)
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn nested_tuple_with_annotation() {
+        check(
+            r#"
+                fnc f -> {
+                    let t: (u32, (s64, char)) = (1, (2, 'a'));
+                    let n = t.1.0;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: u32
+                1: s64
+                2: char
+                3: (s64, char)
+                4: (u32, (s64, char))
+                5: (u32, (s64, char))
+                6: (s64, char)
+                7: s64
+                8: unit
+
+                l0: (u32, (s64, char))
+                l1: s64
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        check(
+            r#"
+                fnc f(xs: [s64]): [[u32]] -> {
+                    let first = xs[0];
+                    let empty = [];
+                    [[1, 2], [first as u32], []]
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f([s64]): [[u32]]
+
+                0: [s64]
+                1: s32
+                2: s64
+                3: [<unknown>]
+                4: u32
+                5: u32
+                6: [u32]
+                7: s64
+                8: u32
+                9: [u32]
+                10: [u32]
+                11: [[u32]]
+                12: [[u32]]
+
+                l0: s64
+                l1: [<unknown>]
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn array_element_mismatch() {
+        check(
+            r#"
+                fnc f -> {
+                    let xs = [1, true, 'a'];
+                    let ys: [bool] = [1];
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: bool
+                2: char
+                3: [s32]
+                4: s32
+                5: [bool]
+                6: unit
+
+                l0: [s32]
+                l1: [bool]
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Bool },
+                    61..65,
+                ),
+                (
+                    TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Char },
+                    67..70,
+                ),
+                (
+                    TyDiagnosticKind::AnnotationMismatch {
+                        expected: hir::Ty::Bool,
+                        found: hir::Ty::S32,
+                        annotation: Some(TextRange::new(101.into(), 107.into())),
+                    },
+                    111..112,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn index_non_array() {
+        check(
+            r#"
+                fnc f(xs: [bool]) -> {
+                    let a = 1[0];
+                    let b = xs[true];
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f([bool]): unit
+
+                0: s32
+                1: s32
+                2: <unknown>
+                3: [bool]
+                4: bool
+                5: bool
+                6: unit
+
+                l0: <unknown>
+                l1: bool
+            "#]],
+            [
+                (TyDiagnosticKind::NotIndexable { ty: hir::Ty::S32 }, 68..69),
+                (
+                    TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Bool },
+                    105..109,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn no_such_field() {
+        check(
+            r#"
+                fnc f -> {
+                    let pair = (1, 2);
+                    let a = pair.2;
+                    let b = a.0;
+                    let c = 1.0;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: s32
+                2: (s32, s32)
+                3: (s32, s32)
+                4: <unknown>
+                5: <unknown>
+                6: <unknown>
+                7: s32
+                8: <unknown>
+                9: unit
+
+                l0: (s32, s32)
+                l1: <unknown>
+                l2: <unknown>
+                l3: <unknown>
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::NoSuchField {
+                        ty: hir::Ty::Tuple(vec![hir::Ty::S32, hir::Ty::S32]),
+                        idx: 2,
+                    },
+                    95..101,
+                ),
+                (TyDiagnosticKind::NoSuchField { ty: hir::Ty::S32, idx: 0 }, 164..167),
+            ],
+        );
+    }
+
+    #[test]
+    fn mismatched_tuples() {
+        check(
+            r#"
+                fnc f -> {
+                    let a: (s32, bool) = (1, 2);
+                    let b: (s32, bool) = (1, true, 'c');
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: s32
+                2: (s32, s32)
+                3: s32
+                4: bool
+                5: char
+                6: (s32, bool, char)
+                7: unit
+
+                l0: (s32, bool)
+                l1: (s32, bool)
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::AnnotationMismatch {
+                        expected: hir::Ty::Bool,
+                        found: hir::Ty::S32,
+                        annotation: Some(TextRange::new(55.into(), 66.into())),
+                    },
+                    73..74,
+                ),
+                (
+                    TyDiagnosticKind::AnnotationMismatch {
+                        expected: hir::Ty::Tuple(vec![hir::Ty::S32, hir::Ty::Bool]),
+                        found: hir::Ty::Tuple(vec![hir::Ty::S32, hir::Ty::Bool, hir::Ty::Char]),
+                        annotation: Some(TextRange::new(104.into(), 115.into())),
+                    },
+                    118..132,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn destructure_tuple() {
+        check(
+            r#"
+                fnc f -> {
+                    let (a, _, c): (s64, bool, string) = (1, true, "c");
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s64
+                1: bool
+                2: string
+                3: (s64, bool, string)
+                4: unit
+
+                l0: s64
+                l1: string
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn destructure_mismatched_tuple() {
+        check(
+            r#"
+                fnc f -> {
+                    let (a, b) = (1, 2, 3);
+                    let (c, d) = 4;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: s32
+                2: s32
+                3: (s32, s32, s32)
+                4: s32
+                5: unit
+
+                l0: <unknown>
+                l1: <unknown>
+                l2: <unknown>
+                l3: <unknown>
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::MismatchedTuplePattern {
+                        len: 2,
+                        found: hir::Ty::Tuple(vec![hir::Ty::S32, hir::Ty::S32, hir::Ty::S32]),
+                    },
+                    61..70,
+                ),
+                (
+                    TyDiagnosticKind::MismatchedTuplePattern { len: 2, found: hir::Ty::S32 },
+                    105..106,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn match_expr() {
+        check(
+            r#"
+                fnc f(n: s64, p: (bool, string)): s64 -> match n {
+                    -1 -> 0;
+                    0 -> match p { (true, s) -> 1; (false, _) -> 2 };
+                    m -> m;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(s64, (bool, string)): s64
+
+                0: s64
+                1: s64
+                2: (bool, string)
+                3: s64
+                4: s64
+                5: s64
+                6: s64
+                7: s64
+
+                l0: string
+                l1: s64
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn match_arms_of_different_types() {
+        check(
+            r#"
+                fnc f(b: bool): s32 -> match b {
+                    true -> 1;
+                    false -> "two";
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(bool): s32
+
+                0: bool
+                1: s32
+                2: string
+                3: s32
+            "#]],
+            [(
+                TyDiagnosticKind::ReturnTyMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::String,
+                    return_ty: Some(TextRange::new(33.into(), 36.into())),
+                },
+                110..115,
+            )],
+        );
+    }
+
+    #[test]
+    fn mismatched_patterns() {
+        check(
+            r#"
+                fnc f(n: u32): s32 -> match n {
+                    true -> 1;
+                    (a, b) -> 2;
+                    -1 -> 3;
+                    4294967296 -> 4;
+                    _ -> 5;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(u32): s32
+
+                0: u32
+                1: s32
+                2: s32
+                3: s32
+                4: s32
+                5: s32
+                6: s32
+
+                l0: <unknown>
+                l1: <unknown>
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::Mismatch { expected: hir::Ty::U32, found: hir::Ty::Bool },
+                    69..73,
+                ),
+                (
+                    TyDiagnosticKind::MismatchedTuplePattern { len: 2, found: hir::Ty::U32 },
+                    100..106,
+                ),
+                (
+                    TyDiagnosticKind::Mismatch { expected: hir::Ty::U32, found: hir::Ty::S32 },
+                    133..135,
+                ),
+                (TyDiagnosticKind::OutOfRangeIntLiteral { ty: hir::Ty::U32 }, 162..172),
+            ],
+        );
+    }
+
+    #[test]
+    fn non_exhaustive_bool_match() {
+        check(
+            r#"
+                fnc f(b: bool): s32 -> match b { true -> 1 };
+            "#,
+            "f",
+            expect![[r#"
+                f(bool): s32
+
+                0: bool
+                1: s32
+                2: s32
+            "#]],
+            [(
+                TyDiagnosticKind::NonExhaustiveMatch { missing: vec![Witness::Bool(false)] },
+                46..47,
+            )],
+        );
+    }
+
+    #[test]
+    fn non_exhaustive_tuple_match() {
+        check(
+            r#"
+                fnc f(p: (bool, (bool, s32))): s32 -> match p {
+                    (true, (false, _)) -> 1;
+                    (false, (_, 0)) -> 2;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f((bool, (bool, s32))): s32
+
+                0: (bool, (bool, s32))
+                1: s32
+                2: s32
+                3: s32
+            "#]],
+            [(
+                TyDiagnosticKind::NonExhaustiveMatch {
+                    missing: vec![
+                        Witness::Tuple(vec![
+                            Witness::Bool(true),
+                            Witness::Tuple(vec![Witness::Bool(true), Witness::Wildcard]),
+                        ]),
+                        Witness::Tuple(vec![
+                            Witness::Bool(false),
+                            Witness::Tuple(vec![Witness::Wildcard, Witness::Wildcard]),
+                        ]),
+                    ],
+                },
+                61..62,
+            )],
+        );
+    }
+
+    #[test]
+    fn match_on_int_needs_catch_all() {
+        check(
+            r#"
+                fnc f(n: s32): s32 -> match n { 0 -> 1; 1 -> 2 };
+            "#,
+            "f",
+            expect![[r#"
+                f(s32): s32
+
+                0: s32
+                1: s32
+                2: s32
+                3: s32
+            "#]],
+            [(TyDiagnosticKind::NonExhaustiveMatch { missing: vec![Witness::Wildcard] }, 45..46)],
+        );
+    }
+
+    #[test]
+    fn match_without_arms() {
+        check(
+            r#"
+                fnc f(n: s32): s32 -> match n {};
+            "#,
+            "f",
+            expect![[r#"
+                f(s32): s32
+
+                0: s32
+                1: !
+            "#]],
+            [(TyDiagnosticKind::NonExhaustiveMatch { missing: vec![Witness::Wildcard] }, 45..46)],
+        );
+    }
+
+    #[test]
+    fn unreachable_arms() {
+        check(
+            r#"
+                fnc f(p: (bool, string)): s32 -> match p {
+                    (true, "a") -> 1;
+                    (true, "a") -> 2;
+                    (b, _) -> 3;
+                    (false, "b") -> 4;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f((bool, string)): s32
+
+                0: (bool, string)
+                1: s32
+                2: s32
+                3: s32
+                4: s32
+                5: s32
+
+                l0: bool
+            "#]],
+            [
+                (TyDiagnosticKind::UnreachableArm, 118..129),
+                (TyDiagnosticKind::UnreachableArm, 189..201),
+            ],
+        );
+    }
+
+    #[test]
+    fn variants_take_expected_type() {
+        check(
+            r#"
+                fnc f -> {
+                    let a = Some 1;
+                    let b: Option[s64] = Some 1;
+                    let c: Option[string] = None;
+                    let d = None;
+                    let e: Result[u32, string] = Ok 1;
+                    let g: Result[u32, string] = Err "e";
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: Option[s32]
+                2: s64
+                3: Option[s64]
+                4: Option[string]
+                5: Option[<unknown>]
+                6: u32
+                7: Result[u32, string]
+                8: string
+                9: Result[u32, string]
+                10: unit
+
+                l0: Option[s32]
+                l1: Option[s64]
+                l2: Option[string]
+                l3: Option[<unknown>]
+                l4: Result[u32, string]
+                l5: Result[u32, string]
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn mismatched_variant_field() {
+        check(
+            r#"
+                fnc f: Option[s32] -> Some "one";
+            "#,
+            "f",
+            expect![[r#"
+                f(): Option[s32]
+
+                0: string
+                1: Option[string]
+            "#]],
+            [(
+                TyDiagnosticKind::ReturnTyMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::String,
+                    return_ty: Some(TextRange::new(24.into(), 35.into())),
+                },
+                44..49,
+            )],
+        );
+    }
+
+    #[test]
+    fn match_on_option() {
+        check(
+            r#"
+                fnc f(o: Option[(bool, s32)]): s32 -> match o {
+                    Some (true, n) -> n;
+                    Some (false, _) -> 0;
+                    None -> -1
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(Option[(bool, s32)]): s32
+
+                0: Option[(bool, s32)]
+                1: s32
+                2: s32
+                3: s32
+                4: s32
+                5: s32
+
+                l0: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn non_exhaustive_option_match() {
+        check(
+            r#"
+                fnc f(o: Option[Option[bool]]): s32 -> match o {
+                    Some Some true -> 1;
+                    None -> 0;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(Option[Option[bool]]): s32
+
+                0: Option[Option[bool]]
+                1: s32
+                2: s32
+                3: s32
+            "#]],
+            [(
+                TyDiagnosticKind::NonExhaustiveMatch {
+                    missing: vec![Witness::Variant(
+                        hir::Variant::Some,
+                        Some(Box::new(Witness::Variant(hir::Variant::None, None))),
+                    )],
+                },
+                62..63,
+            )],
+        );
+    }
+
+    #[test]
+    fn non_exhaustive_result_match() {
+        check(
+            r#"
+                fnc f(r: Result[s32, string]): s32 -> match r { Ok n -> n };
+            "#,
+            "f",
+            expect![[r#"
+                f(Result[s32, string]): s32
+
+                0: Result[s32, string]
+                1: s32
+                2: s32
+
+                l0: s32
+            "#]],
+            [(
+                TyDiagnosticKind::NonExhaustiveMatch {
+                    missing: vec![Witness::Variant(
+                        hir::Variant::Err,
+                        Some(Box::new(Witness::Wildcard)),
+                    )],
+                },
+                61..62,
+            )],
+        );
+    }
+
+    #[test]
+    fn variant_pattern_of_wrong_enum() {
+        check(
+            r#"
+                fnc f(n: s32): s32 -> match n { Ok m -> m; _ -> 0 };
+            "#,
+            "f",
+            expect![[r#"
+                f(s32): s32
+
+                0: s32
+                1: <unknown>
+                2: s32
+                3: s32
+
+                l0: <unknown>
+            "#]],
+            [(
+                TyDiagnosticKind::Mismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::Result {
+                        ok: Box::new(hir::Ty::Unknown),
+                        err: Box::new(hir::Ty::Unknown),
+                    },
+                },
+                49..53,
+            )],
+        );
+    }
+
+    #[test]
+    fn try_operator() {
+        check(
+            r#"
+                fnc f(s: string): Result[s32, string] -> {
+                    let n = {check s}?;
+                    Ok n + 1
+                };
+
+                fnc check(s: string): Result[s32, string] -> Err s;
+            "#,
+            "f",
+            expect![[r#"
+                f(string): Result[s32, string]
+
+                0: string
+                1: Result[s32, string]
+                2: Result[s32, string]
+                3: s32
+                4: string
+                5: Result[s32, string]
+                6: !
+                7: s32
+                8: s32
+                9: s32
+                10: s32
+                11: Result[s32, string]
+                12: Result[s32, string]
+
+                l0: s32
+                l1: string
+                l2: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn try_on_mismatched_enum() {
+        check(
+            r#"
+                fnc f: Option[s32] -> Some {parse_int "1"}? + {g}?;
+
+                fnc g: Result[s32, string] -> Ok 1;
+            "#,
+            "f",
+            expect![[r#"
+                f(): Option[s32]
+
+                0: string
+                1: Option[s32]
+                2: Option[s32]
+                3: s32
+                4: Option[s32]
+                5: !
+                6: s32
+                7: Result[s32, string]
+                8: Result[s32, string]
+                9: <unknown>
+                10: Option[s32]
+                11: !
+                12: s32
+                13: s32
+                14: Option[s32]
+
+                l0: s32
+                l1: <unknown>
+            "#]],
+            [(
+                TyDiagnosticKind::Mismatch {
+                    expected: hir::Ty::Result {
+                        ok: Box::new(hir::Ty::S32),
+                        err: Box::new(hir::Ty::String),
+                    },
+                    found: hir::Ty::Option(Box::new(hir::Ty::Unknown)),
+                },
+                63..67,
+            )],
+        );
+    }
+
+    #[test]
+    fn call_parse_int() {
+        check(
+            r#"
+                fnc f: Option[s32] -> parse_int "12";
+            "#,
+            "f",
+            expect![[r#"
+                f(): Option[s32]
+
+                0: string
+                1: Option[s32]
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn call_io_builtins() {
+        check(
+            r#"
+                fnc f: Option[string] -> {
+                    println "reading";
+                    read_line
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): Option[string]
+
+                0: string
+                1: unit
+                2: Option[string]
+                3: Option[string]
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn method_call_with_mismatched_receiver() {
+        check(
+            r#"
+                fnc double(n: s32): s32 -> n * 2;
+                fnc f: s32 -> "abc".double;
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                3: string
+                4: s32
+            "#]],
+            [(
+                TyDiagnosticKind::ArgMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::String,
+                    params: Some(hir::ParamsLocation {
+                        module: None,
+                        range: TextRange::new(27.into(), 35.into()),
+                    }),
+                },
+                81..86,
+            )],
+        );
+    }
+
+    #[test]
+    fn call_args_and_env() {
+        check(
+            r#"
+                fnc f: ([string], Option[string]) -> (args, {env "HOME"});
+            "#,
+            "f",
+            expect![[r#"
+                f(): ([string], Option[string])
+
+                0: [string]
+                1: string
+                2: Option[string]
+                3: Option[string]
+                4: ([string], Option[string])
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn call_string_builtins() {
+        check(
+            r#"
+                fnc f: (s32, string, bool, char) -> {
+                    let s = "hello";
+                    ({len s}, {substring s, 1, 3}, {contains s, "ell"}, {char_at s, 0})
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): (s32, string, bool, char)
+
+                0: string
+                1: string
+                2: s32
+                3: s32
+                4: string
+                5: s32
+                6: s32
+                7: string
+                8: string
+                9: string
+                10: string
+                11: bool
+                12: bool
+                13: string
+                14: s32
+                15: char
+                16: char
+                17: (s32, string, bool, char)
+                18: (s32, string, bool, char)
+
+                l0: string
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn call_array_len_on_any_array() {
+        check(
+            r#"
+                fnc f: s32 -> {array_len [1, 2]} + {array_len ["a"]} + {array_len 5};
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: s32
+                1: s32
+                2: [s32]
+                3: s32
+                4: s32
+                5: string
+                6: [string]
+                7: s32
+                8: s32
+                9: s32
+                10: s32
+                11: s32
+                12: s32
+                13: s32
+            "#]],
+            [(
+                TyDiagnosticKind::ArgMismatch {
+                    expected: hir::Ty::Array(Box::new(hir::Ty::Unknown)),
+                    found: hir::Ty::S32,
+                    params: None,
+                },
+                83..84,
+            )],
+        );
+    }
+
+    #[test]
+    fn assert_with_non_bool_condition() {
+        check(
+            r#"
+                fnc f -> assert 1, "one";
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: string
+                2: unit
+            "#]],
+            [(
+                TyDiagnosticKind::ArgMismatch {
+                    expected: hir::Ty::Bool,
+                    found: hir::Ty::S32,
+                    params: None,
+                },
+                33..34,
+            )],
+        );
+    }
+
+    #[test]
+    fn comparison_of_chars() {
+        check(
+            r#"
+                fnc is_digit(c: char): bool -> c >= '0' && c <= '9';
+            "#,
+            "is_digit",
+            expect![[r#"
+                is_digit(char): bool
+
+                0: char
+                1: char
+                2: bool
+                3: char
+                4: char
+                5: bool
+                6: bool
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn char_is_not_string() {
+        check(
+            r#"
+                fnc f: string -> 'a';
+            "#,
+            "f",
+            expect![[r#"
+                f(): string
+
+                0: char
+            "#]],
+            [(
+                TyDiagnosticKind::ReturnTyMismatch {
+                    expected: hir::Ty::String,
+                    found: hir::Ty::Char,
+                    return_ty: Some(TextRange::new(24.into(), 30.into())),
+                },
+                34..37,
+            )],
+        );
+    }
+
+    #[test]
+    fn comparison_of_string_and_s32() {
+        check(
+            r#"
+                fnc f: bool -> "a" == 1;
+            "#,
+            "f",
+            expect![[r#"
+                f(): bool
+
+                0: string
+                1: s32
+                2: bool
+            "#]],
+            [(
+                TyDiagnosticKind::Mismatch { expected: hir::Ty::String, found: hir::Ty::S32 },
+                39..40,
+            )],
+        );
+    }
+
+    #[test]
+    fn logical_operators() {
+        check(
+            r#"
+                fnc f: bool -> 1 < 2 && false;
+            "#,
+            "f",
+            expect![[r#"
+                f(): bool
+
+                0: s32
+                1: s32
+                2: bool
+                3: bool
+                4: bool
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn logical_operator_on_s32() {
+        check(
+            r#"
+                fnc f: bool -> true || 1;
+            "#,
+            "f",
+            expect![[r#"
+                f(): bool
+
+                0: bool
+                1: s32
+                2: bool
+            "#]],
+            [(TyDiagnosticKind::Mismatch { expected: hir::Ty::Bool, found: hir::Ty::S32 }, 40..41)],
+        );
+    }
+
+    #[test]
+    fn unary_operators() {
+        check(
+            r#"
+                fnc f: bool -> -1 < 2 && !false;
+            "#,
+            "f",
+            expect![[r#"
+                f(): bool
+
+                0: s32
+                1: s32
+                2: s32
+                3: bool
+                4: bool
+                5: bool
+                6: bool
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn negation_of_bool() {
+        check(
+            r#"
+                fnc f: s32 -> -true;
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: bool
+                1: s32
+            "#]],
+            [(TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Bool }, 32..36)],
+        );
+    }
+
+    #[test]
+    fn assign_of_mismatched_ty() {
+        check(
+            r#"
+                fnc f -> {
+                    let mut x = 1;
+                    x = true;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: bool
+                2: unit
+
+                l0: s32
+            "#]],
+            [(TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Bool }, 87..91)],
+        );
+    }
+
+    #[test]
+    fn for_loop() {
+        check(
+            r#"
+                fnc f -> for i in 0..10 { i + 1; };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: s32
+                2: s32
+                3: s32
+                4: s32
+                5: unit
+                6: unit
+
+                l0: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn for_loop_with_bool_bound() {
+        check(
+            r#"
+                fnc f -> for i in 0..true {};
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: bool
+                2: unit
+                3: unit
+
+                l0: s32
+            "#]],
+            [(TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Bool }, 38..42)],
+        );
+    }
+
+    #[test]
+    fn for_loop_with_break_value() {
+        check(
+            r#"
+                fnc f -> for i in 0..10 { break i; };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: s32
+                2: s32
+                3: !
+                4: unit
+
+                l0: s32
+            "#]],
+            [(TyDiagnosticKind::Mismatch { expected: hir::Ty::Unit, found: hir::Ty::S32 }, 49..50)],
+        );
+    }
+
+    #[test]
+    fn function_with_params() {
+        check(
+            r#"
+                fnc add(x: s32, y: s32): s32 -> x + y;
+            "#,
+            "add",
+            expect![[r#"
+                add(s32, s32): s32
+
+                0: s32
+                1: s32
+                2: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn local_definition_and_usage() {
+        check(
+            r#"
+                fnc main -> {
+                    let a = 10;
+                    a;
+                };
+            "#,
+            "main",
+            expect![[r#"
+                main(): unit
+
+                0: s32
+                1: s32
+                2: unit
+
+                l0: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn local_shadowing() {
+        check(
+            r#"
+                fnc foo -> {
+                    let a = 10;
+                    let a = "10";
+                    a;
+                };
+            "#,
+            "foo",
+            expect![[r#"
+                foo(): unit
+
+                0: s32
+                1: string
+                2: string
+                3: unit
+
+                l0: s32
+                l1: string
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn non_s32_binary_expr() {
+        check(
+            r#"
+                fnc sum: s32 -> "foo" + 1;
+            "#,
+            "sum",
+            expect![[r#"
+                sum(): s32
+
+                0: string
+                1: s32
+                2: s32
+            "#]],
+            [(
+                TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
+                33..38,
+            )],
+        );
+    }
+
+    #[test]
+    fn bool_literals() {
+        check(
+            r#"
+                fnc f(b: bool): bool -> { true; false; b };
+            "#,
+            "f",
+            expect![[r#"
+                f(bool): bool
+
+                0: bool
+                1: bool
+                2: bool
+                3: bool
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn bool_binary_expr() {
+        check(
+            r#"
+                fnc f: s32 -> true + 1;
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: bool
+                1: s32
+                2: s32
+            "#]],
+            [(TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Bool }, 31..35)],
+        );
+    }
+
+    #[test]
+    fn loop_expr() {
+        check(
+            r#"
+                fnc f -> loop { 1; break; };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: !
+                2: unit
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn loop_with_break_value() {
+        check(
+            r#"
+                fnc f: s32 -> loop { break 92; };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: s32
+                1: !
+                2: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn defer_does_not_affect_block_ty() {
+        check(
+            r#"
+                fnc f: s32 -> { defer 1 + 1; 2 };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: s32
+                1: s32
+                2: s32
+                3: s32
+                4: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn labeled_break_values() {
+        check(
+            r#"
+                fnc f: s32 -> 'outer: loop {
+                    let x = 'block: {
+                        loop { break 'block "a"; };
+                        "b"
+                    };
+                    loop { break 'outer 1; };
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: string
+                1: !
+                2: !
+                3: string
+                4: string
+                5: string
+                6: s32
+                7: !
+                8: !
+                9: !
+                10: s32
+
+                l0: string
+            "#]],
+            [(TyDiagnosticKind::UnreachableCode, 160..163)],
+        );
+    }
+
+    #[test]
+    fn labeled_block_with_mismatched_break_value() {
+        check(
+            r#"
+                fnc f: s32 -> 'a: { break 'a "a"; 1 };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: string
+                1: s32
+                2: s32
+                3: string
+            "#]],
+            [
+                (TyDiagnosticKind::UnreachableCode, 51..52),
+                (
+                    TyDiagnosticKind::Mismatch { expected: hir::Ty::String, found: hir::Ty::S32 },
+                    51..52,
+                ),
+                (
+                    TyDiagnosticKind::ReturnTyMismatch {
+                        expected: hir::Ty::S32,
+                        found: hir::Ty::String,
+                        return_ty: Some(TextRange::new(24.into(), 27.into())),
+                    },
+                    31..54,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn loop_with_mismatched_break_values() {
+        check(
+            r#"
+                fnc f: s32 -> loop { break 1; break "a"; break; };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: s32
+                1: string
+                2: !
+                3: s32
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
+                    53..56,
+                ),
+                (
+                    TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Unit },
+                    31..66,
+                ),
+                (TyDiagnosticKind::UnreachableCode, 47..64),
+            ],
+        );
+    }
+
+    #[test]
+    fn return_from_loop() {
+        check(
+            r#"
+                fnc f(x: s32): s32 -> loop { return x; };
+            "#,
+            "f",
+            expect![[r#"
+                f(s32): s32
+
+                0: s32
+                1: !
+                2: !
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn return_with_mismatched_value() {
+        check(
+            r#"
+                fnc f: s32 -> { return true; };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: bool
+                1: !
+            "#]],
+            [(
+                TyDiagnosticKind::ReturnTyMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::Bool,
+                    return_ty: Some(TextRange::new(24.into(), 27.into())),
+                },
+                40..44,
+            )],
+        );
+    }
+
+    #[test]
+    fn return_without_value_from_non_unit_function() {
+        check(
+            r#"
+                fnc f: s32 -> { return; };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: !
+            "#]],
+            [(
+                TyDiagnosticKind::ReturnTyMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::Unit,
+                    return_ty: Some(TextRange::new(24.into(), 27.into())),
+                },
+                31..42,
+            )],
+        );
+    }
+
+    #[test]
+    fn diverging_local_value() {
+        check(
+            r#"
+                fnc f: s32 -> {
+                    let a = { return 1; };
+                    a + 1
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: s32
+                1: !
+                2: !
+                3: s32
+                4: s32
+                5: s32
+
+                l0: !
+            "#]],
+            [(TyDiagnosticKind::UnreachableCode, 96..101)],
+        );
+    }
+
+    #[test]
+    fn break_value_outside_loop() {
+        check(
+            r#"
+                fnc f -> { break 1; };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: s32
+                1: !
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn binary_expr_with_missing_operand() {
+        check(
+            r#"
+                fnc f: s32 -> 5 +;
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                0: s32
+                1: <unknown>
+                2: <unknown>
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn unknown_operands_dont_cascade() {
+        check(
+            r#"
+                fnc f -> {
+                    let a: string = z + 1;
+                    let b: string = 1 * -z;
+                    let c = z - "a";
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: <unknown>
+                1: s32
+                2: <unknown>
+                3: s32
+                4: <unknown>
+                5: <unknown>
+                6: <unknown>
+                7: <unknown>
+                8: string
+                9: <unknown>
+                10: unit
+
+                l0: string
+                l1: string
+                l2: <unknown>
+            "#]],
+            [(
+                TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
+                147..150,
+            )],
+        );
+    }
+
+    #[test]
+    fn generic_call_with_unknown_arg() {
+        check(
+            r#"
+                fnc id[T](x: T): T -> x;
+                fnc pair[T, U](x: T): (T, U) -> (x, x);
+                fnc f -> {
+                    let a = id z;
+                    let b: string = a.0 + 1;
+                    let c = pair z;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                4: <unknown>
+                5: <unknown>
+                6: <unknown>
+                7: <unknown>
+                8: s32
+                9: <unknown>
+                10: <unknown>
+                11: (<unknown>, <unknown>)
+                12: unit
+
+                l0: <unknown>
+                l1: string
+                l2: (<unknown>, <unknown>)
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn mismatched_function_body() {
+        check(
+            r#"
+                fnc s: string -> 92;
+            "#,
+            "s",
+            expect![[r#"
+                s(): string
+
+                0: s32
+            "#]],
+            [(
+                TyDiagnosticKind::ReturnTyMismatch {
+                    expected: hir::Ty::String,
+                    found: hir::Ty::S32,
+                    return_ty: Some(TextRange::new(24.into(), 30.into())),
+                },
+                34..36,
+            )],
+        );
+    }
+
+    #[test]
+    fn call_unit_function() {
+        check(
+            r#"
+                fnc main -> nothing;
+                fnc nothing -> {};
+            "#,
+            "main",
+            expect![[r#"
+                main(): unit
+
+                0: unit
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn call_function_with_return_ty() {
+        check(
+            r#"
+                fnc main: s32 -> number;
+                fnc number: s32 -> 5;
+            "#,
+            "main",
+            expect![[r#"
+                main(): s32
+
+                0: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn call_function_with_params() {
+        check(
+            r#"
+                fnc main: s32 -> id 10;
+                fnc id(n: s32): s32 -> n;
+            "#,
+            "main",
+            expect![[r#"
+                main(): s32
+
+                0: s32
+                1: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn recursive_call() {
+        check(
+            r#"
+                fnc factorial(n: s32): s32 -> {
+                    n == 0 && { return 1; };
+                    n * factorial {n - 1}
+                };
+            "#,
+            "factorial",
+            expect![[r#"
+                factorial(s32): s32
+
+                0: s32
+                1: s32
+                2: bool
+                3: s32
+                4: !
+                5: bool
+                6: s32
+                7: s32
+                8: s32
+                9: s32
+                10: s32
+                11: s32
+                12: s32
+                13: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn mutually_recursive_calls() {
+        check(
+            r#"
+                fnc is_even(n: s32): bool -> n == 0 || is_odd n - 1;
+                fnc is_odd(n: s32): bool -> n != 0 && is_even n - 1;
+            "#,
+            "is_even",
+            expect![[r#"
+                is_even(s32): bool
+
+                0: s32
+                1: s32
+                2: bool
+                3: s32
+                4: s32
+                5: s32
+                6: bool
+                7: bool
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn mutually_recursive_call_with_mismatched_arg() {
+        check(
+            r#"
+                fnc is_even(n: s32): bool -> n == 0 || is_odd "1";
+                fnc is_odd(n: s32): bool -> n != 0 && is_even n - 1;
+            "#,
+            "is_even",
+            expect![[r#"
+                is_even(s32): bool
+
+                0: s32
+                1: s32
+                2: bool
+                3: string
+                4: bool
+                5: bool
+            "#]],
+            [(
+                TyDiagnosticKind::ArgMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::String,
+                    params: Some(hir::ParamsLocation {
+                        module: None,
+                        range: TextRange::new(94.into(), 102.into()),
+                    }),
+                },
+                63..66,
+            )],
+        );
+    }
+
+    #[test]
+    fn call_builtin() {
+        check(
+            r#"
+                fnc main: string -> rand_int "a", 6;
+            "#,
+            "main",
+            expect![[r#"
+                main(): string
+
+                0: string
+                1: s32
+                2: s32
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::ArgMismatch {
+                        expected: hir::Ty::S32,
+                        found: hir::Ty::String,
+                        params: None,
+                    },
+                    46..49,
+                ),
+                (
+                    TyDiagnosticKind::ReturnTyMismatch {
+                        expected: hir::Ty::String,
+                        found: hir::Ty::S32,
+                        return_ty: Some(TextRange::new(27.into(), 33.into())),
+                    },
+                    37..52,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn call_to_string() {
+        check(
+            r#"
+                fnc main: s32 -> to_string 1;
+            "#,
+            "main",
+            expect![[r#"
+                main(): s32
+
+                0: s32
+                1: string
+            "#]],
+            [(
+                TyDiagnosticKind::ReturnTyMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::String,
+                    return_ty: Some(TextRange::new(27.into(), 30.into())),
+                },
+                34..45,
+            )],
+        );
+    }
+
+    #[test]
+    fn mismatched_param_tys() {
+        check(
+            r#"
+                fnc main: s32 -> multiply {}, "a";
+                fnc multiply(x: s32, y: s32): s32 -> x * y;
+            "#,
+            "main",
+            expect![[r#"
+                main(): s32
+
+                0: unit
+                1: string
+                2: s32
+            "#]],
+            [
+                (
+                    TyDiagnosticKind::ArgMismatch {
+                        expected: hir::Ty::S32,
+                        found: hir::Ty::Unit,
+                        params: Some(hir::ParamsLocation {
+                            module: None,
+                            range: TextRange::new(80.into(), 96.into()),
+                        }),
+                    },
+                    43..45,
+                ),
+                (
+                    TyDiagnosticKind::ArgMismatch {
+                        expected: hir::Ty::S32,
+                        found: hir::Ty::String,
+                        params: Some(hir::ParamsLocation {
+                            module: None,
+                            range: TextRange::new(80.into(), 96.into()),
+                        }),
+                    },
+                    47..50,
+                ),
+            ],
+        );
+    }
+
+    #[test]
+    fn call_function_from_other_module() {
+        check(
+            r#"
+                #- main
+                fnc a: string -> greetings.informal 10;
+                #- greetings
+                pub fnc informal(n: s32): string -> "Hello!";
+            "#,
+            "a",
+            expect![[r#"
+                a(): string
+
+                0: s32
+                1: string
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn call_extern_function() {
+        check(
+            r#"
+                fnc main: s32 -> puts "hello";
+                extern fnc puts(s: string): s32;
+            "#,
+            "main",
+            expect![[r#"
+                main(): s32
+
+                0: string
+                1: s32
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn attach_mismatch_diagnostics_to_block_tail_expr() {
+        check(
+            r#"
+                fnc main -> take_s32 {
+                    let a = 10 + 10;
+                    "foo"
+                };
+
+                fnc take_s32(n: s32) -> {};
+            "#,
+            "main",
+            expect![[r#"
+                main(): unit
+
+                0: s32
+                1: s32
+                2: s32
+                3: string
+                4: string
+                5: unit
+
+                l0: s32
+            "#]],
+            [(
+                TyDiagnosticKind::ArgMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::String,
+                    params: Some(hir::ParamsLocation {
+                        module: None,
+                        range: TextRange::new(151.into(), 159.into()),
+                    }),
+                },
+                97..102,
+            )],
+        );
+    }
+
+    #[test]
+    fn consts() {
+        check(
+            r#"
+                const WIDTH: s64 = HEIGHT * 2;
+                const HEIGHT: s64 = 10;
+                const GREETING = "hello";
+                fnc area: s64 -> WIDTH * HEIGHT;
+            "#,
+            "area",
+            expect![[r#"
+                area(): s64
+                WIDTH: s64 = Int(20)
+                HEIGHT: s64 = Int(10)
+
+                0: s64
+                1: s64
+                2: s64
+                3: s64
+                5: s64
+                6: s64
+                7: s64
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn const_annotation_mismatch() {
+        check(
+            r#"
+                const FLAG: bool = 1;
+                fnc f: bool -> FLAG;
+            "#,
+            "f",
+            expect![[r#"
+                f(): bool
+                FLAG: bool
+
+                0: s32
+                1: bool
+            "#]],
+            [(
+                TyDiagnosticKind::AnnotationMismatch {
+                    expected: hir::Ty::Bool,
+                    found: hir::Ty::S32,
+                    annotation: Some(TextRange::new(29.into(), 33.into())),
+                },
+                36..37,
+            )],
+        );
+    }
+
+    #[test]
+    fn recursive_consts() {
+        check(
+            r#"
+                const A = B + 1;
+                const B = A * 2;
+                fnc f: s32 -> A;
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+                A: <unknown>
+                B: <unknown>
+
+                0: <unknown>
+                1: s32
+                2: <unknown>
+                3: <unknown>
+                4: s32
+                5: <unknown>
+                6: <unknown>
+            "#]],
+            [(TyDiagnosticKind::RecursiveConst, 60..61)],
+        );
+    }
+
+    #[test]
+    fn const_overflow_and_division_by_zero() {
+        check(
+            r#"
+                const BIG = 2147483647 + 1;
+                const SMALL: u32 = 1 - 2;
+                const MIN = -2147483648;
+                const ZERO = 10 / {5 - 5};
+                fnc f: s32 -> BIG + SMALL as s32 + MIN + ZERO;
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+                BIG: s32
+                SMALL: u32
+                MIN: s32 = Int(-2147483648)
+                ZERO: s32
+
+                0: s32
+                1: s32
+                2: s32
+                3: u32
+                4: u32
+                5: u32
+                6: s32
+                7: s32
+                8: s32
+                9: s32
+                10: s32
+                11: s32
+                12: s32
+                13: s32
+                14: s32
+                15: u32
+                16: s32
+                17: s32
+                18: s32
+                19: s32
+                20: s32
+                21: s32
+            "#]],
+            [
+                (TyDiagnosticKind::ConstOverflow { ty: hir::Ty::S32 }, 29..43),
+                (TyDiagnosticKind::ConstOverflow { ty: hir::Ty::U32 }, 80..85),
+                (TyDiagnosticKind::ConstDivisionByZero, 157..169),
+            ],
+        );
+    }
+
+    #[test]
+    fn param_default_values() {
+        check(
+            r#"
+                fnc repeat(s: string, times: s64 = 2, separator: string = 0): string -> s;
+            "#,
+            "repeat",
+            expect![[r#"
+                repeat(string, s64, string): string
+
+                0: s64
+                1: s32
+                2: string
+            "#]],
+            [(
+                TyDiagnosticKind::AnnotationMismatch {
+                    expected: hir::Ty::String,
+                    found: hir::Ty::S32,
+                    annotation: Some(TextRange::new(66.into(), 72.into())),
+                },
+                75..76,
+            )],
+        );
+    }
+
+    #[test]
+    fn empty_array_takes_type_from_later_use() {
+        check(
+            r#"
+                fnc sum(xs: [s32]): s32 -> 0;
+                fnc f: s32 -> {
+                    let xs = [];
+                    sum xs
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): s32
+
+                1: [s32]
+                2: [s32]
+                3: s32
+                4: s32
+
+                l0: [s32]
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn none_takes_type_from_later_assignment() {
+        check(
+            r#"
+                fnc f: Option[string] -> {
+                    let mut x = None;
+                    x = Some "a";
+                    x
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): Option[string]
+
+                0: Option[string]
+                1: string
+                2: Option[string]
+                3: Option[string]
+                4: Option[string]
+
+                l0: Option[string]
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn generic_call_decided_by_later_use() {
+        check(
+            r#"
+                fnc none[T]: Option[T] -> None;
+                fnc f -> {
+                    let x = none;
+                    let y: Option[bool] = x;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                1: Option[bool]
+                2: Option[bool]
+                3: unit
+
+                l0: Option[bool]
+                l1: Option[bool]
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn generic_calls_instantiated_independently() {
+        check(
+            r#"
+                fnc none[T]: Option[T] -> None;
+                fnc f -> {
+                    let a = none;
+                    let b = none;
+                    let x: Option[s32] = a;
+                    let y: Option[string] = b;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                1: Option[s32]
+                2: Option[string]
+                3: Option[s32]
+                4: Option[string]
+                5: unit
+
+                l0: Option[s32]
+                l1: Option[string]
+                l2: Option[s32]
+                l3: Option[string]
+            "#]],
+            [],
+        );
+    }
+
+    #[test]
+    fn locals_not_generalized() {
+        check(
+            r#"
+                fnc none[T]: Option[T] -> None;
+                fnc f -> {
+                    let a = none;
+                    let x: Option[s32] = a;
+                    let y: Option[string] = a;
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                1: Option[s32]
+                2: Option[s32]
+                3: Option[s32]
+                4: unit
+
+                l0: Option[s32]
+                l1: Option[s32]
+                l2: Option[string]
+            "#]],
+            [(
+                TyDiagnosticKind::AnnotationMismatch {
+                    expected: hir::Ty::Option(Box::new(hir::Ty::String)),
+                    found: hir::Ty::Option(Box::new(hir::Ty::S32)),
+                    annotation: Some(TextRange::new(181.into(), 195.into())),
+                },
+                198..199,
+            )],
+        );
+    }
+
+    #[test]
+    fn index_decides_array_ty() {
+        check(
+            r#"
+                fnc f -> {
+                    let xs = [];
+                    let s: string = xs[0];
+                };
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
+
+                0: [string]
+                1: [string]
+                2: s32
+                3: string
+                4: unit
+
+                l0: [string]
+                l1: string
+            "#]],
+            [],
+        );
     }
 
     #[test]
-    fn unit_function() {
+    fn patterns_decide_value_ty() {
         check(
             r#"
-                fnc foo -> {};
+                fnc f: s32 -> {
+                    let x = None;
+                    match x {
+                        Some (a, b) -> a + b;
+                        None -> 0;
+                    }
+                };
             "#,
-            "foo",
+            "f",
             expect![[r#"
-                foo(): unit
+                f(): s32
 
-                0: unit
+                0: Option[(s32, s32)]
+                1: Option[(s32, s32)]
+                2: s32
+                3: s32
+                4: s32
+                5: s32
+                6: s32
+                7: s32
+
+                l0: Option[(s32, s32)]
+                l1: s32
+                l2: s32
             "#]],
             [],
         );
     }
 
     #[test]
-    fn function_with_return_ty() {
+    fn destructure_decides_value_ty() {
         check(
             r#"
-                fnc one: s32 -> 1;
+                fnc f: bool -> {
+                    let xs = [];
+                    let (a, b) = xs[0];
+                    a && b
+                };
             "#,
-            "one",
+            "f",
             expect![[r#"
-                one(): s32
+                f(): bool
 
-                0: s32
+                0: [(bool, bool)]
+                1: [(bool, bool)]
+                2: s32
+                3: (bool, bool)
+                4: bool
+                5: bool
+                6: bool
+                7: bool
+
+                l0: [(bool, bool)]
+                l1: bool
+                l2: bool
             "#]],
             [],
         );
     }
 
     #[test]
-    fn function_with_undefined_return_ty() {
+    fn comparison_decides_operand_ty() {
         check(
             r#"
-                fnc one: foo -> 1;
+                fnc f: bool -> {
+                    let xs = [];
+                    xs[0] == "a"
+                };
             "#,
-            "one",
+            "f",
             expect![[r#"
-                one(): <unknown>
+                f(): bool
 
-                0: s32
+                0: [string]
+                1: [string]
+                2: s32
+                3: string
+                4: string
+                5: bool
+                6: bool
+
+                l0: [string]
             "#]],
             [],
         );
     }
 
     #[test]
-    fn function_with_missing_return_ty() {
+    fn mismatch_after_ty_is_decided() {
         check(
             r#"
-                fnc one: -> 1;
+                fnc f -> {
+                    let mut x = None;
+                    x = Some 1;
+                    x = Some "a";
+                };
             "#,
-            "one",
+            "f",
             expect![[r#"
-                one(): <unknown>
+                f(): unit
 
-                0: s32
+                0: Option[s32]
+                1: s32
+                2: Option[s32]
+                3: string
+                4: Option[string]
+                5: unit
+
+                l0: Option[s32]
             "#]],
-            [],
+            [(
+                TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
+                127..130,
+            )],
         );
     }
 
     #[test]
-    fn binary_expr() {
+    fn field_of_undecided_ty() {
         check(
             r#"
-                fnc twenty: s32 -> 10 + 10;
+                fnc f -> {
+                    let xs = [];
+                    xs[0].0;
+                };
             "#,
-            "twenty",
+            "f",
             expect![[r#"
-                twenty(): s32
+                f(): unit
 
-                0: s32
-                1: s32
+                0: [<unknown>]
+                1: [<unknown>]
                 2: s32
+                3: <unknown>
+                4: <unknown>
+                5: unit
+
+                l0: [<unknown>]
             "#]],
-            [],
+            [(TyDiagnosticKind::AnnotationNeeded, 81..86)],
         );
     }
 
     #[test]
-    fn function_with_params() {
+    fn array_containing_itself() {
         check(
             r#"
-                fnc add(x: s32, y: s32): s32 -> x + y;
+                fnc f -> {
+                    let mut xs = [];
+                    xs = [xs];
+                };
             "#,
-            "add",
+            "f",
             expect![[r#"
-                add(s32, s32): s32
+                f(): unit
 
-                0: s32
-                1: s32
-                2: s32
+                0: [<unknown>]
+                1: [<unknown>]
+                2: [<unknown>]
+                3: unit
+
+                l0: [<unknown>]
             "#]],
-            [],
+            [(
+                TyDiagnosticKind::Mismatch {
+                    expected: hir::Ty::Unknown,
+                    found: hir::Ty::Array(Box::new(hir::Ty::Unknown)),
+                },
+                91..93,
+            )],
         );
     }
 
     #[test]
-    fn local_definition_and_usage() {
+    fn undecided_ty_becomes_unknown() {
         check(
             r#"
-                fnc main -> {
-                    let a = 10;
-                    a;
+                fnc f -> {
+                    let xs = [];
+                    let x = None;
                 };
             "#,
-            "main",
+            "f",
             expect![[r#"
-                main(): unit
+                f(): unit
 
-                0: s32
-                1: s32
+                0: [<unknown>]
+                1: Option[<unknown>]
                 2: unit
 
-                l0: s32
+                l0: [<unknown>]
+                l1: Option[<unknown>]
             "#]],
             [],
         );
     }
 
     #[test]
-    fn local_shadowing() {
+    fn infer_return_ty_from_body() {
         check(
             r#"
-                fnc foo -> {
-                    let a = 10;
-                    let a = "10";
-                    a;
-                };
+                fnc double(n: s32) -> n * 2;
+                fnc f: s32 -> double 2;
             "#,
-            "foo",
+            "double",
             expect![[r#"
-                foo(): unit
+                double(s32): s32
 
                 0: s32
-                1: string
-                2: string
-                3: unit
-
-                l0: s32
-                l1: string
+                1: s32
+                2: s32
             "#]],
             [],
         );
     }
 
     #[test]
-    fn non_s32_binary_expr() {
+    fn call_function_with_inferred_return_ty() {
         check(
             r#"
-                fnc sum: s32 -> "foo" + 1;
+                fnc double(n: s32) -> n * 2;
+                fnc f: s32 -> double 2;
             "#,
-            "sum",
+            "f",
             expect![[r#"
-                sum(): s32
+                f(): s32
 
-                0: string
-                1: s32
-                2: s32
+                3: s32
+                4: s32
             "#]],
-            [(
-                TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
-                33..38,
-            )],
+            [],
         );
     }
 
     #[test]
-    fn binary_expr_with_missing_operand() {
+    fn infer_return_ty_from_returns() {
         check(
             r#"
-                fnc f: s32 -> 5 +;
+                fnc f(b: bool) -> {
+                    b && { return "a"; };
+                    "b"
+                };
             "#,
             "f",
             expect![[r#"
-                f(): s32
+                f(bool): string
 
-                0: s32
-                1: <unknown>
-                2: s32
+                0: bool
+                1: string
+                2: !
+                3: bool
+                4: string
+                5: string
             "#]],
             [],
         );
     }
 
     #[test]
-    fn mismatched_function_body() {
+    fn recursive_function_with_inferred_return_ty() {
         check(
             r#"
-                fnc s: string -> 92;
+                fnc count(n: s32) -> match n {
+                    0 -> 0;
+                    _ -> count n - 1;
+                };
             "#,
-            "s",
+            "count",
             expect![[r#"
-                s(): string
+                count(s32): s32
 
                 0: s32
+                1: s32
+                2: s32
+                3: s32
+                4: s32
+                5: s32
+                6: s32
             "#]],
-            [(
-                TyDiagnosticKind::Mismatch { expected: hir::Ty::String, found: hir::Ty::S32 },
-                34..36,
-            )],
+            [],
         );
     }
 
     #[test]
-    fn call_unit_function() {
+    fn diverging_function_with_inferred_return_ty() {
         check(
             r#"
-                fnc main -> nothing;
-                fnc nothing -> {};
+                fnc f -> loop {};
             "#,
-            "main",
+            "f",
             expect![[r#"
-                main(): unit
+                f(): unit
 
                 0: unit
+                1: !
             "#]],
             [],
         );
     }
 
     #[test]
-    fn call_function_with_return_ty() {
+    fn conflicting_returns_with_inferred_return_ty() {
         check(
             r#"
-                fnc main: s32 -> number;
-                fnc number: s32 -> 5;
+                fnc f(b: bool) -> {
+                    b && { return 1; };
+                    "b"
+                };
             "#,
-            "main",
+            "f",
             expect![[r#"
-                main(): s32
+                f(bool): s32
+
+                0: bool
+                1: s32
+                2: !
+                3: bool
+                4: string
+                5: string
+            "#]],
+            [(
+                TyDiagnosticKind::InferredReturnTyMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::String,
+                },
+                97..100,
+            )],
+        );
+    }
+
+    #[test]
+    fn pub_function_without_return_ty_returns_unit() {
+        check(
+            r#"
+                pub fnc f -> 5;
+            "#,
+            "f",
+            expect![[r#"
+                f(): unit
 
                 0: s32
             "#]],
-            [],
+            [(
+                TyDiagnosticKind::ReturnTyMismatch {
+                    expected: hir::Ty::Unit,
+                    found: hir::Ty::S32,
+                    return_ty: None,
+                },
+                30..31,
+            )],
         );
     }
 
     #[test]
-    fn call_function_with_params() {
+    fn expected_ty_reaches_nested_arg() {
         check(
             r#"
-                fnc main: s32 -> id 10;
-                fnc id(n: s32): s32 -> n;
+                fnc f: s32 -> g (1, [Some "a"]);
+                fnc g(x: (s32, [Option[s32]])): s32 -> x.0;
             "#,
-            "main",
+            "f",
             expect![[r#"
-                main(): s32
+                f(): s32
 
                 0: s32
-                1: s32
+                1: string
+                2: Option[string]
+                3: [Option[s32]]
+                4: (s32, [Option[s32]])
+                5: s32
             "#]],
-            [],
+            [(
+                TyDiagnosticKind::ArgMismatch {
+                    expected: hir::Ty::S32,
+                    found: hir::Ty::String,
+                    params: Some(hir::ParamsLocation {
+                        module: None,
+                        range: TextRange::new(71.into(), 96.into()),
+                    }),
+                },
+                43..46,
+            )],
         );
     }
 
     #[test]
-    fn mismatched_param_tys() {
+    fn expected_ty_reaches_match_arms() {
         check(
             r#"
-                fnc main: s32 -> multiply {}, "a";
-                fnc multiply(x: s32, y: s32): s32 -> x * y;
+                fnc f(b: bool) -> {
+                    let s: string = match b {
+                        true -> "yes";
+                        false -> { let n = 1; n };
+                    };
+                };
             "#,
-            "main",
+            "f",
             expect![[r#"
-                main(): s32
+                f(bool): unit
 
-                0: unit
+                0: bool
                 1: string
                 2: s32
+                3: s32
+                4: s32
+                5: string
+                6: unit
+
+                l0: s32
+                l1: string
             "#]],
-            [
-                (
-                    TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::Unit },
-                    43..45,
-                ),
-                (
-                    TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
-                    47..50,
-                ),
-            ],
+            [(
+                TyDiagnosticKind::AnnotationMismatch {
+                    expected: hir::Ty::String,
+                    found: hir::Ty::S32,
+                    annotation: Some(TextRange::new(64.into(), 70.into())),
+                },
+                168..169,
+            )],
         );
     }
 
     #[test]
-    fn call_function_from_other_module() {
+    fn diverging_arm_fits_any_ty() {
         check(
             r#"
-                #- main
-                fnc a: string -> greetings.informal 10;
-                #- greetings
-                fnc informal(n: s32): string -> "Hello!";
+                fnc f(b: bool): s32 -> match b {
+                    true -> { return 1; };
+                    false -> 2;
+                };
             "#,
-            "a",
+            "f",
             expect![[r#"
-                a(): string
+                f(bool): s32
 
-                0: s32
-                1: string
+                0: bool
+                1: s32
+                2: !
+                3: s32
+                4: s32
             "#]],
             [],
         );
     }
 
     #[test]
-    fn attach_mismatch_diagnostics_to_block_tail_expr() {
+    fn code_after_diverging_statement() {
         check(
             r#"
-                fnc main -> take_s32 {
-                    let a = 10 + 10;
-                    "foo"
+                fnc f: s32 -> {
+                    return 1;
+                    let a = 2;
+                    a
                 };
-
-                fnc take_s32(n: s32) -> {};
             "#,
-            "main",
+            "f",
             expect![[r#"
-                main(): unit
+                f(): s32
 
                 0: s32
                 1: s32
                 2: s32
-                3: string
-                4: string
-                5: unit
+                3: s32
 
                 l0: s32
             "#]],
-            [(
-                TyDiagnosticKind::Mismatch { expected: hir::Ty::S32, found: hir::Ty::String },
-                97..102,
-            )],
+            [(TyDiagnosticKind::UnreachableCode, 83..115)],
         );
     }
+
+    #[test]
+    fn query_tys_of_exprs_and_signatures() {
+        let text = "fnc double(n: s32) -> n * 2; fnc f: s32 -> double 2;";
+        let mut interner = Interner::default();
+        let world_index = hir::WorldIndex::default();
+        let tokens = lexer::lex(text);
+        let tree = parser::parse_source_file(&tokens, text).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = hir::index(root, &tree, &world_index, &mut interner);
+        let (bodies, _) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+        let (result, diagnostics) = infer_all(&bodies, &index, &world_index);
+        assert!(diagnostics.is_empty());
+
+        let double = hir::Name(interner.intern("double"));
+        let signature = result.signature(double).unwrap();
+        assert_eq!(signature.param_tys(), [hir::Ty::S32]);
+        assert_eq!(signature.return_ty(), &hir::Ty::S32);
+        assert_eq!(result.type_of(bodies.function_body(double)), &hir::Ty::S32);
+
+        let f = hir::Name(interner.intern("f"));
+        assert_eq!(result.type_of(bodies.function_body(f)), &hir::Ty::S32);
+        assert!(result.signature(hir::Name(interner.intern("g"))).is_none());
+    }
+
+    #[test]
+    fn lower_to_thir() {
+        let text = "fnc f -> { let x: s64 = 1; }; const C: bool = true;";
+        let mut interner = Interner::default();
+        let world_index = hir::WorldIndex::default();
+        let tokens = lexer::lex(text);
+        let tree = parser::parse_source_file(&tokens, text).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = hir::index(root, &tree, &world_index, &mut interner);
+        let (bodies, _) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+        let (result, _) = infer_all(&bodies, &index, &world_index);
+
+        let f = hir::Name(interner.intern("f"));
+        let body = bodies.function_body(f);
+        let thir = thir::lower(bodies, result);
+
+        let thir::Expr { kind: hir::Expr::Block { statements, .. }, ty } = &thir[body] else {
+            panic!()
+        };
+        assert_eq!(ty, &hir::Ty::Unit);
+        assert_eq!(thir.return_ty(f), &hir::Ty::Unit);
+
+        let hir::Statement::LocalDef(x) = thir[statements[0]] else { panic!() };
+        let x = &thir[x];
+        assert_eq!(x.ty, hir::Ty::S64);
+        assert!(matches!(thir[x.value].kind, hir::Expr::IntLiteral(1)));
+        assert_eq!(thir[x.value].ty, hir::Ty::S64);
+
+        let c = hir::Name(interner.intern("C"));
+        assert_eq!(thir.const_value(c), Some(&ConstValue::Bool(true)));
+    }
 }
@@ -0,0 +1,131 @@
+//! Instantiates generic functions.
+//! Each call picks its own types for the type parameters of the function it calls,
+//! by unifying the types of the function’s parameters with those of the arguments.
+
+/// The types picked for the type parameters of a function,
+/// with `None` standing for those which haven’t been picked yet.
+#[derive(Debug, Clone)]
+pub(crate) struct Substitution(Vec<Option<hir::Ty>>);
+
+impl Substitution {
+    pub(crate) fn new(len: usize) -> Self {
+        Self(vec![None; len])
+    }
+
+    pub(crate) fn with_picks(picks: Vec<Option<hir::Ty>>) -> Self {
+        Self(picks)
+    }
+
+    /// Picks `tys` for the type parameters, in order.
+    pub(crate) fn with_tys(tys: Vec<hir::Ty>) -> Self {
+        Self(tys.into_iter().map(Some).collect())
+    }
+
+    /// Picks types for the type parameters in `generic` which make it match `concrete`.
+    /// Type parameters which already have a type keep it,
+    /// and whether `concrete` actually fits is left to the caller to check.
+    pub(crate) fn unify(&mut self, generic: &hir::Ty, concrete: &hir::Ty) {
+        match (generic, concrete) {
+            // these fit any type, so they can’t decide what a type parameter stands for
+            (_, hir::Ty::Unknown | hir::Ty::Never) => {}
+            (hir::Ty::Param { idx, .. }, concrete) => {
+                let picked = &mut self.0[*idx as usize];
+                if picked.is_none() {
+                    *picked = Some(concrete.expand_aliases());
+                }
+            }
+            (hir::Ty::Alias { ty, .. }, concrete) => self.unify(ty, concrete),
+            (generic, hir::Ty::Alias { ty, .. }) => self.unify(generic, ty),
+            (hir::Ty::Tuple(generic), hir::Ty::Tuple(concrete)) => {
+                for (generic, concrete) in generic.iter().zip(concrete) {
+                    self.unify(generic, concrete);
+                }
+            }
+            (hir::Ty::Array(generic), hir::Ty::Array(concrete))
+            | (hir::Ty::Option(generic), hir::Ty::Option(concrete)) => {
+                self.unify(generic, concrete)
+            }
+            (
+                hir::Ty::Result { ok: generic_ok, err: generic_err },
+                hir::Ty::Result { ok: concrete_ok, err: concrete_err },
+            ) => {
+                self.unify(generic_ok, concrete_ok);
+                self.unify(generic_err, concrete_err);
+            }
+            _ => {}
+        }
+    }
+
+    /// Picks unknown types for the type parameters in `generic`
+    /// which line up with the unknown parts of `concrete` and haven’t been picked yet.
+    pub(crate) fn pick_unknown(&mut self, generic: &hir::Ty, concrete: &hir::Ty) {
+        match (generic, concrete) {
+            (hir::Ty::Param { idx, .. }, hir::Ty::Unknown) => {
+                let picked = &mut self.0[*idx as usize];
+                if picked.is_none() {
+                    *picked = Some(hir::Ty::Unknown);
+                }
+            }
+            (hir::Ty::Alias { ty, .. }, concrete) => self.pick_unknown(ty, concrete),
+            (hir::Ty::Tuple(generic), hir::Ty::Unknown) => {
+                for generic in generic {
+                    self.pick_unknown(generic, &hir::Ty::Unknown);
+                }
+            }
+            (hir::Ty::Tuple(generic), hir::Ty::Tuple(concrete)) => {
+                for (generic, concrete) in generic.iter().zip(concrete) {
+                    self.pick_unknown(generic, concrete);
+                }
+            }
+            (hir::Ty::Array(generic) | hir::Ty::Option(generic), hir::Ty::Unknown) => {
+                self.pick_unknown(generic, &hir::Ty::Unknown)
+            }
+            (hir::Ty::Array(generic), hir::Ty::Array(concrete))
+            | (hir::Ty::Option(generic), hir::Ty::Option(concrete)) => {
+                self.pick_unknown(generic, concrete)
+            }
+            (hir::Ty::Result { ok, err }, hir::Ty::Unknown) => {
+                self.pick_unknown(ok, &hir::Ty::Unknown);
+                self.pick_unknown(err, &hir::Ty::Unknown);
+            }
+            (
+                hir::Ty::Result { ok: generic_ok, err: generic_err },
+                hir::Ty::Result { ok: concrete_ok, err: concrete_err },
+            ) => {
+                self.pick_unknown(generic_ok, concrete_ok);
+                self.pick_unknown(generic_err, concrete_err);
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn is_picked(&self, idx: usize) -> bool {
+        self.0[idx].is_some()
+    }
+
+    /// Picks the types `other` has picked for the type parameters this hasn’t.
+    pub(crate) fn fill_from(&mut self, other: &Self) {
+        for (picked, other) in self.0.iter_mut().zip(&other.0) {
+            if picked.is_none() {
+                *picked = other.clone();
+            }
+        }
+    }
+
+    /// Replaces the type parameters in `ty` with the types picked for them,
+    /// and with unknown types if none have been.
+    pub(crate) fn apply(&self, ty: &hir::Ty) -> hir::Ty {
+        match ty {
+            hir::Ty::Param { idx, .. } => self.0[*idx as usize].clone().unwrap_or(hir::Ty::Unknown),
+            hir::Ty::Tuple(elements) => {
+                hir::Ty::Tuple(elements.iter().map(|element| self.apply(element)).collect())
+            }
+            hir::Ty::Array(element) => hir::Ty::Array(Box::new(self.apply(element))),
+            hir::Ty::Option(ty) => hir::Ty::Option(Box::new(self.apply(ty))),
+            hir::Ty::Result { ok, err } => {
+                hir::Ty::Result { ok: Box::new(self.apply(ok)), err: Box::new(self.apply(err)) }
+            }
+            _ => ty.clone(),
+        }
+    }
+}
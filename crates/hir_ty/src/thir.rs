@@ -0,0 +1,301 @@
+//! The typed HIR, in which every expression and local carries the type inference gave it.
+//!
+//! Backends work from this rather than from HIR bodies
+//! and an [`InferenceResult`] kept alongside them,
+//! since the two are combined once and can’t be paired up wrongly later.
+
+use arena::{ArenaMap, Id};
+use rustc_hash::{FxHashMap, FxHashSet};
+use text_size::TextRange;
+
+use crate::{ConstValue, InferenceResult, Signature};
+
+/// The bodies of a module’s functions and constants along with their types.
+#[derive(Clone)]
+pub struct Bodies {
+    bodies: hir::Bodies,
+    exprs: ArenaMap<Id<hir::Expr>, Expr>,
+    local_defs: ArenaMap<Id<hir::LocalDef>, LocalDef>,
+    signatures: FxHashMap<hir::Name, Signature>,
+    const_values: FxHashMap<hir::Name, ConstValue>,
+    tail_calls: FxHashSet<Id<hir::Expr>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: hir::Expr,
+    pub ty: hir::Ty,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalDef {
+    pub value: Id<hir::Expr>,
+    pub is_mutable: bool,
+    pub ty: hir::Ty,
+}
+
+/// Combines a module’s bodies with the result of inferring them.
+///
+/// Expressions which inference never reached,
+/// such as those in bodies it gave up on, have an unknown type.
+pub fn lower(bodies: hir::Bodies, inference: InferenceResult) -> Bodies {
+    let InferenceResult { signatures, expr_tys, local_tys, consts } = inference;
+
+    let mut exprs = ArenaMap::default();
+    for (id, expr) in bodies.exprs() {
+        let ty = expr_tys.get(id).cloned().unwrap_or(hir::Ty::Unknown);
+        exprs.insert(id, Expr { kind: expr.clone(), ty });
+    }
+
+    let mut local_defs = ArenaMap::default();
+    for (id, local_def) in bodies.local_defs() {
+        let ty = local_tys.get(id).cloned().unwrap_or(hir::Ty::Unknown);
+        local_defs
+            .insert(id, LocalDef { value: local_def.value, is_mutable: local_def.is_mutable, ty });
+    }
+
+    let const_values =
+        consts.into_iter().filter_map(|(name, inferred)| Some((name, inferred.value?))).collect();
+
+    let mut tail_calls = FxHashSet::default();
+    for (_, body) in bodies.function_bodies() {
+        let mut finder = TailCallFinder { bodies: &bodies, targets: Vec::new(), tail_calls };
+        finder.walk_expr(body, true, false);
+        tail_calls = finder.tail_calls;
+    }
+
+    Bodies { bodies, exprs, local_defs, signatures, const_values, tail_calls }
+}
+
+// Finds the calls whose value their function returns without doing anything else,
+// which is the case when nothing has been deferred on the way out of the function.
+struct TailCallFinder<'a> {
+    bodies: &'a hir::Bodies,
+    // the loops and labeled blocks `break` can leave,
+    // and whether their value is returned right away
+    targets: Vec<BreakTarget>,
+    tail_calls: FxHashSet<Id<hir::Expr>>,
+}
+
+struct BreakTarget {
+    label: Option<hir::Name>,
+    is_loop: bool,
+    is_tail: bool,
+}
+
+impl TailCallFinder<'_> {
+    // `deferred` is whether a block around `expr` has deferred something by this point
+    fn walk_expr(&mut self, expr: Id<hir::Expr>, is_tail: bool, deferred: bool) {
+        match &self.bodies[expr] {
+            hir::Expr::Missing
+            | hir::Expr::IntLiteral(_)
+            | hir::Expr::BoolLiteral(_)
+            | hir::Expr::StringLiteral(_)
+            | hir::Expr::CharLiteral(_)
+            | hir::Expr::Unit
+            | hir::Expr::Local(_)
+            | hir::Expr::Param { .. }
+            | hir::Expr::Const(_)
+            | hir::Expr::Variant { value: None, .. } => {}
+
+            hir::Expr::Binary {
+                lhs,
+                rhs,
+                operator: hir::BinaryOperator::And | hir::BinaryOperator::Or,
+            } => {
+                self.walk_expr(*lhs, false, deferred);
+                self.walk_expr(*rhs, is_tail, deferred);
+            }
+
+            hir::Expr::Binary { lhs, rhs, .. } | hir::Expr::Index { array: lhs, index: rhs } => {
+                self.walk_expr(*lhs, false, deferred);
+                self.walk_expr(*rhs, false, deferred);
+            }
+
+            hir::Expr::Unary { expr, .. }
+            | hir::Expr::Cast { expr, .. }
+            | hir::Expr::Field { tuple: expr, .. }
+            | hir::Expr::Variant { value: Some(expr), .. } => {
+                self.walk_expr(*expr, false, deferred);
+            }
+
+            hir::Expr::Tuple(elements) | hir::Expr::Array(elements) => {
+                for element in elements {
+                    self.walk_expr(*element, false, deferred);
+                }
+            }
+
+            hir::Expr::Block { statements, tail_expr } => {
+                let mut deferred = deferred;
+                for statement in statements {
+                    self.walk_statement(*statement, &mut deferred);
+                }
+
+                if let Some(tail_expr) = tail_expr {
+                    self.walk_expr(*tail_expr, is_tail && !deferred, deferred);
+                }
+            }
+
+            hir::Expr::Loop { label, body } => {
+                self.targets.push(BreakTarget { label: *label, is_loop: true, is_tail });
+                self.walk_expr(*body, false, deferred);
+                self.targets.pop();
+            }
+
+            hir::Expr::For { label, start, end, body, .. } => {
+                self.walk_expr(*start, false, deferred);
+                self.walk_expr(*end, false, deferred);
+
+                self.targets.push(BreakTarget { label: *label, is_loop: true, is_tail });
+                self.walk_expr(*body, false, deferred);
+                self.targets.pop();
+            }
+
+            hir::Expr::LabeledBlock { label, body } => {
+                let label = Some(*label);
+                self.targets.push(BreakTarget { label, is_loop: false, is_tail });
+                self.walk_expr(*body, is_tail, deferred);
+                self.targets.pop();
+            }
+
+            hir::Expr::Call { path, args } => {
+                for arg in args {
+                    self.walk_expr(*arg, false, deferred);
+                }
+
+                if is_tail && !matches!(path, hir::Path::Builtin(_)) {
+                    self.tail_calls.insert(expr);
+                }
+            }
+
+            hir::Expr::Match { value, arms } => {
+                self.walk_expr(*value, false, deferred);
+                for arm in arms {
+                    self.walk_expr(arm.body, is_tail, deferred);
+                }
+            }
+        }
+    }
+
+    fn walk_statement(&mut self, statement: Id<hir::Statement>, deferred: &mut bool) {
+        match &self.bodies[statement] {
+            hir::Statement::Expr(value)
+            | hir::Statement::Destructure { value, .. }
+            | hir::Statement::Assign { value, .. } => self.walk_expr(*value, false, *deferred),
+            hir::Statement::LocalDef(local_def) => {
+                self.walk_expr(self.bodies[*local_def].value, false, *deferred);
+            }
+            hir::Statement::Break { label, value } => {
+                let is_tail = self
+                    .targets
+                    .iter()
+                    .rev()
+                    .find(|target| match label {
+                        Some(label) => target.label == Some(*label),
+                        None => target.is_loop,
+                    })
+                    .is_some_and(|target| target.is_tail);
+
+                if let Some(value) = value {
+                    self.walk_expr(*value, is_tail && !*deferred, *deferred);
+                }
+            }
+            hir::Statement::Continue { .. } => {}
+            hir::Statement::Return { value } => {
+                if let Some(value) = value {
+                    self.walk_expr(*value, !*deferred, *deferred);
+                }
+            }
+            hir::Statement::Defer(expr) => {
+                self.walk_expr(*expr, false, true);
+                *deferred = true;
+            }
+        }
+    }
+}
+
+impl Bodies {
+    pub fn function_body(&self, name: hir::Name) -> Id<hir::Expr> {
+        self.bodies.function_body(name)
+    }
+
+    /// The value a parameter takes on when a call leaves it out.
+    pub fn param_default(&self, function: hir::Name, idx: u32) -> Option<Id<hir::Expr>> {
+        self.bodies.param_default(function, idx)
+    }
+
+    /// The type a function returns,
+    /// including those whose return types were inferred.
+    pub fn return_ty(&self, function: hir::Name) -> &hir::Ty {
+        self.signatures[&function].return_ty()
+    }
+
+    /// The value of a constant,
+    /// which is missing if type checking reported an error while working it out.
+    pub fn const_value(&self, name: hir::Name) -> Option<&ConstValue> {
+        self.const_values.get(&name)
+    }
+
+    /// Whether the function of `call` returns its value as soon as it has it,
+    /// so that its frame can make way for the one of the function being called.
+    pub fn is_tail_call(&self, call: Id<hir::Expr>) -> bool {
+        self.tail_calls.contains(&call)
+    }
+
+    /// The value of an integer literal or of a negated one.
+    ///
+    /// A negated literal is a literal of its own rather than a negation at runtime,
+    /// since the most negative value of a type has no positive counterpart to negate.
+    pub fn int_literal(&self, expr: Id<hir::Expr>) -> Option<i128> {
+        match self.exprs[expr].kind {
+            hir::Expr::IntLiteral(n) => Some(i128::from(n)),
+            hir::Expr::Unary { expr, operator: hir::UnaryOperator::Neg } => {
+                match self.exprs[expr].kind {
+                    hir::Expr::IntLiteral(n) => Some(-i128::from(n)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn range_for_expr(&self, expr: Id<hir::Expr>) -> TextRange {
+        self.bodies.range_for_expr(expr)
+    }
+
+    pub fn range_for_statement(&self, statement: Id<hir::Statement>) -> TextRange {
+        self.bodies.range_for_statement(statement)
+    }
+}
+
+impl std::ops::Index<Id<hir::Expr>> for Bodies {
+    type Output = Expr;
+
+    fn index(&self, id: Id<hir::Expr>) -> &Self::Output {
+        &self.exprs[id]
+    }
+}
+
+impl std::ops::Index<Id<hir::LocalDef>> for Bodies {
+    type Output = LocalDef;
+
+    fn index(&self, id: Id<hir::LocalDef>) -> &Self::Output {
+        &self.local_defs[id]
+    }
+}
+
+impl std::ops::Index<Id<hir::Statement>> for Bodies {
+    type Output = hir::Statement;
+
+    fn index(&self, id: Id<hir::Statement>) -> &Self::Output {
+        &self.bodies[id]
+    }
+}
+
+impl std::ops::Index<Id<hir::Pattern>> for Bodies {
+    type Output = hir::Pattern;
+
+    fn index(&self, id: Id<hir::Pattern>) -> &Self::Output {
+        &self.bodies[id]
+    }
+}
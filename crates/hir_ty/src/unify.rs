@@ -0,0 +1,143 @@
+//! Type variables stand for the types inference hasn’t worked out yet,
+//! such as the element type of an empty array,
+//! and are bound to a type by unifying them with it
+//! the first time they’re used somewhere that has one.
+//!
+//! Variables which never get bound become unknown types
+//! once a whole module has been inferred.
+
+/// The types type variables have been bound to,
+/// indexed by the variables’ IDs.
+#[derive(Debug, Default)]
+pub(crate) struct TyVars(Vec<Option<hir::Ty>>);
+
+impl TyVars {
+    pub(crate) fn new_var(&mut self) -> hir::Ty {
+        self.0.push(None);
+        hir::Ty::Var(self.0.len() as u32 - 1)
+    }
+
+    /// Replaces every bound type variable in `ty`, however deeply nested,
+    /// with the type it’s bound to.
+    pub(crate) fn resolve(&self, ty: &hir::Ty) -> hir::Ty {
+        self.map_vars(ty, &|var| hir::Ty::Var(var))
+    }
+
+    /// Like [`TyVars::resolve`],
+    /// but also replaces the variables which aren’t bound with unknown types.
+    pub(crate) fn resolve_fully(&self, ty: &hir::Ty) -> hir::Ty {
+        self.map_vars(ty, &|_| hir::Ty::Unknown)
+    }
+
+    pub(crate) fn is_unbound_var(&self, ty: &hir::Ty) -> bool {
+        matches!(self.resolve(ty), hir::Ty::Var(_))
+    }
+
+    /// Whether any variable in `ty`, however deeply nested, isn’t bound yet.
+    pub(crate) fn has_unbound_vars(&self, ty: &hir::Ty) -> bool {
+        let found = std::cell::Cell::new(false);
+        self.map_vars(ty, &|var| {
+            found.set(true);
+            hir::Ty::Var(var)
+        });
+        found.get()
+    }
+
+    fn map_vars(&self, ty: &hir::Ty, unbound: &impl Fn(u32) -> hir::Ty) -> hir::Ty {
+        match ty {
+            hir::Ty::Var(var) => match &self.0[*var as usize] {
+                Some(bound) => self.map_vars(bound, unbound),
+                None => unbound(*var),
+            },
+            hir::Ty::Tuple(elements) => hir::Ty::Tuple(
+                elements.iter().map(|element| self.map_vars(element, unbound)).collect(),
+            ),
+            hir::Ty::Array(element) => hir::Ty::Array(Box::new(self.map_vars(element, unbound))),
+            hir::Ty::Option(ty) => hir::Ty::Option(Box::new(self.map_vars(ty, unbound))),
+            hir::Ty::Result { ok, err } => hir::Ty::Result {
+                ok: Box::new(self.map_vars(ok, unbound)),
+                err: Box::new(self.map_vars(err, unbound)),
+            },
+            hir::Ty::Alias { name, ty } => {
+                hir::Ty::Alias { name: name.clone(), ty: Box::new(self.map_vars(ty, unbound)) }
+            }
+            _ => ty.clone(),
+        }
+    }
+
+    /// Binds the type variables in `found` and `expected`
+    /// so that the two types match, if they can.
+    ///
+    /// Like [`crate::is_compatible`],
+    /// unknown types and the never type fit anything;
+    /// they don’t say anything about what a variable stands for, though,
+    /// so variables are never bound to them.
+    pub(crate) fn unify(&mut self, found: &hir::Ty, expected: &hir::Ty) -> bool {
+        let found = self.resolve_shallow(found);
+        let expected = self.resolve_shallow(expected);
+
+        match (&found, &expected) {
+            (hir::Ty::Var(found), hir::Ty::Var(expected)) if found == expected => true,
+            (hir::Ty::Unknown | hir::Ty::Never, _) | (_, hir::Ty::Unknown) => true,
+            (hir::Ty::Var(_), hir::Ty::Never) => true,
+            (hir::Ty::Var(var), ty) | (ty, hir::Ty::Var(var)) => self.bind(*var, ty),
+            (hir::Ty::Alias { ty: found, .. }, expected) => self.unify(found, expected),
+            (found, hir::Ty::Alias { ty: expected, .. }) => self.unify(found, expected),
+            (hir::Ty::Tuple(found), hir::Ty::Tuple(expected)) => {
+                if found.len() != expected.len() {
+                    return false;
+                }
+
+                // every element is unified, even after one has failed to,
+                // so that what can be worked out still is
+                let mut unified = true;
+                for (found, expected) in found.iter().zip(expected) {
+                    unified &= self.unify(found, expected);
+                }
+                unified
+            }
+            (hir::Ty::Array(found), hir::Ty::Array(expected))
+            | (hir::Ty::Option(found), hir::Ty::Option(expected)) => self.unify(found, expected),
+            (
+                hir::Ty::Result { ok: found_ok, err: found_err },
+                hir::Ty::Result { ok: expected_ok, err: expected_err },
+            ) => {
+                let ok_unified = self.unify(found_ok, expected_ok);
+                self.unify(found_err, expected_err) && ok_unified
+            }
+            _ => found == expected,
+        }
+    }
+
+    fn bind(&mut self, var: u32, ty: &hir::Ty) -> bool {
+        // a type can’t contain itself
+        if self.occurs(var, ty) {
+            return false;
+        }
+
+        self.0[var as usize] = Some(ty.expand_aliases());
+        true
+    }
+
+    fn occurs(&self, var: u32, ty: &hir::Ty) -> bool {
+        match self.resolve_shallow(ty) {
+            hir::Ty::Var(other) => other == var,
+            hir::Ty::Tuple(elements) => elements.iter().any(|element| self.occurs(var, element)),
+            hir::Ty::Array(ty) | hir::Ty::Option(ty) | hir::Ty::Alias { ty, .. } => {
+                self.occurs(var, &ty)
+            }
+            hir::Ty::Result { ok, err } => self.occurs(var, &ok) || self.occurs(var, &err),
+            _ => false,
+        }
+    }
+
+    fn resolve_shallow(&self, ty: &hir::Ty) -> hir::Ty {
+        match ty {
+            hir::Ty::Var(var) => match &self.0[*var as usize] {
+                Some(bound) => self.resolve_shallow(bound),
+                None => ty.clone(),
+            },
+            _ => ty.clone(),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Comments like `# allow(unused_variables)` on the line before a statement or item,
+//! which silence the warnings of the lints they name inside it.
+
+use diagnostics::{Diagnostic, Lint, Severity};
+use syntax::{NodeKind, SyntaxElement, SyntaxNode, SyntaxTree, TokenKind};
+use text_size::TextSize;
+
+pub(crate) fn is_allowed(diagnostic: &Diagnostic, tree: &SyntaxTree) -> bool {
+    if diagnostic.severity() != Severity::Warning {
+        return false;
+    }
+
+    match diagnostic.lint() {
+        Some(lint) => allowed_lints(tree, diagnostic.range().start()).contains(&lint),
+        None => false,
+    }
+}
+
+/// The lints allowed by the comments in front of every statement and item containing `offset`.
+fn allowed_lints(tree: &SyntaxTree, offset: TextSize) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let mut node = tree.root();
+    let mut at_line_start = true;
+
+    'descend: loop {
+        // the comment on the line just before the current child, if any
+        let mut comment = None;
+
+        for child in node.children(tree) {
+            match child {
+                SyntaxElement::Token(token) if token.kind(tree) == TokenKind::Whitespace => {
+                    let newlines = token.text(tree).matches('\n').count();
+                    if newlines != 1 {
+                        comment = None;
+                    }
+                    at_line_start = newlines > 0;
+                }
+                SyntaxElement::Node(child) if child.kind(tree) == NodeKind::Comment => {
+                    // a comment after some code on the same line is about that code
+                    comment = if at_line_start { Some(child) } else { None };
+                    at_line_start = false;
+                }
+                SyntaxElement::Node(child) if child.range(tree).contains(offset) => {
+                    if is_statement_or_item(child.kind(tree)) {
+                        if let Some(comment) = comment {
+                            lints.extend(parse_allow(comment, tree));
+                        }
+                    }
+                    node = child;
+                    at_line_start = false;
+                    continue 'descend;
+                }
+                SyntaxElement::Node(_) | SyntaxElement::Token(_) => {
+                    comment = None;
+                    at_line_start = false;
+                }
+            }
+        }
+
+        return lints;
+    }
+}
+
+fn is_statement_or_item(kind: NodeKind) -> bool {
+    matches!(
+        kind,
+        NodeKind::LocalDef
+            | NodeKind::ExprStatement
+            | NodeKind::Assign
+            | NodeKind::Break
+            | NodeKind::Continue
+            | NodeKind::Return
+            | NodeKind::Defer
+            | NodeKind::Function
+            | NodeKind::TyAlias
+            | NodeKind::Import
+            | NodeKind::Const
+    )
+}
+
+fn parse_allow(comment: SyntaxNode, tree: &SyntaxTree) -> Vec<Lint> {
+    let contents = comment
+        .child_tokens(tree)
+        .find(|token| token.kind(tree) == TokenKind::CommentContents)
+        .map_or("", |token| token.text(tree));
+
+    let names = contents.trim().strip_prefix("allow(").and_then(|rest| rest.strip_suffix(')'));
+
+    match names {
+        Some(names) => names.split(',').filter_map(|name| Lint::from_name(name.trim())).collect(),
+        None => Vec::new(),
+    }
+}
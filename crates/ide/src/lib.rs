@@ -1,3 +1,7 @@
+mod allow;
+#[cfg(test)]
+mod tests;
+
 use ast::validation::ValidationDiagnostic;
 use ast::{AstNode, AstToken};
 use diagnostics::Diagnostic;
@@ -8,7 +12,7 @@ use rustc_hash::FxHashMap;
 use std::ops::BitOrAssign;
 use std::path::Path;
 use std::{fs, io, mem};
-use syntax::{Event, NodeKind, SyntaxElement, SyntaxNode, SyntaxTree, TokenKind};
+use syntax::{Event, NodeKind, SyntaxElement, SyntaxNode, SyntaxToken, SyntaxTree, TokenKind};
 use text_size::{TextRange, TextSize};
 use url::Url;
 
@@ -18,6 +22,7 @@ pub struct GlobalState {
     world_index: hir::WorldIndex,
     analyses: FxHashMap<Url, Analysis>,
     project: Option<hir::Project>,
+    lint_levels: diagnostics::LintLevels,
 }
 
 pub struct Analysis {
@@ -34,6 +39,12 @@ pub struct Analysis {
     lowering_diagnostics: Vec<hir::LoweringDiagnostic>,
     inference_result: hir_ty::InferenceResult,
     ty_diagnostics: Vec<hir_ty::TyDiagnostic>,
+    inference_cache: hir_ty::InferenceCache,
+    // whether the bodies and the types inferred for them are up to date
+    // with this module and the modules it uses,
+    // which they aren’t between an edit and the next check
+    is_lowered: bool,
+    is_inferred: bool,
 }
 
 impl GlobalState {
@@ -52,6 +63,11 @@ impl GlobalState {
 
         let project = hir::Project::new(path.parent().unwrap(), &mut self.interner)?;
 
+        let std_name = hir::Name(self.interner.intern(stdlib::MODULE_NAME));
+        if project.module_path(std_name).is_none() {
+            self.index_std(std_name);
+        }
+
         for module in project.modules() {
             let uri = path_to_uri(module);
             let content = fs::read_to_string(module)?;
@@ -70,15 +86,34 @@ impl GlobalState {
             self.analyses.insert(uri, analysis);
         }
 
+        // every module has to be indexed before any can be lowered
         for analysis in self.analyses.values_mut() {
-            analysis.recheck(&mut self.world_index, &mut self.interner);
+            analysis.is_lowered = false;
+            analysis.is_inferred = false;
         }
+        self.check();
 
         self.project = Some(project);
 
         Ok(Ok(()))
     }
 
+    fn index_std(&mut self, std_name: hir::Name) {
+        let tokens = lexer::lex(stdlib::SOURCE);
+        let tree = parser::parse_source_file(&tokens, stdlib::SOURCE).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = hir::index(root, &tree, &self.world_index, &mut self.interner);
+
+        self.world_index.add_module(std_name, index);
+    }
+
+    /// Applies an edit to a file without checking anything the edit affects,
+    /// which is left to [`GlobalState::check`]
+    /// or to [`GlobalState::function_diagnostics`] for a single function.
+    ///
+    /// Until [`GlobalState::check`] is called,
+    /// [`GlobalState::diagnostics`] still returns the diagnostics from before the edit,
+    /// so callers have to call it after every edit, or after a batch of edits.
     pub fn update_contents(&mut self, uri: &Url, f: impl FnOnce(&mut String, &LineIndex)) {
         self.analyses.get_mut(uri).unwrap().update_contents(
             f,
@@ -86,12 +121,16 @@ impl GlobalState {
             &mut self.world_index,
         );
 
-        for (analysis_uri, analysis) in &mut self.analyses {
-            if analysis_uri == uri {
-                continue;
-            }
+        for analysis in self.analyses.values_mut() {
+            analysis.is_lowered = false;
+            analysis.is_inferred = false;
+        }
+    }
 
-            analysis.recheck(&mut self.world_index, &mut self.interner);
+    /// Checks every file again that has been affected by an edit since the last check.
+    pub fn check(&mut self) {
+        for analysis in self.analyses.values_mut() {
+            analysis.check(&self.world_index, &mut self.interner);
         }
     }
 
@@ -111,14 +150,17 @@ impl GlobalState {
         let project = self.project.as_ref().unwrap();
         self.world_index
             .iter()
-            .map(|(fqn, range_info)| {
+            .filter_map(|(fqn, range_info)| {
+                // the standard library has no file on disk to point at
+                let path = project.module_path(fqn.module)?;
+
                 let module_name = self.interner.lookup(fqn.module.0);
                 let function_name = self.interner.lookup(fqn.function.0);
-                Symbol {
+                Some(Symbol {
                     name: format!("{module_name}.{function_name}"),
-                    file: path_to_uri(project.module_path(fqn.module).unwrap()),
+                    file: path_to_uri(path),
                     range: range_info.whole,
-                }
+                })
             })
             .collect()
     }
@@ -127,8 +169,59 @@ impl GlobalState {
         self.analyses[uri].highlight()
     }
 
+    pub fn set_lint_levels(&mut self, lint_levels: diagnostics::LintLevels) {
+        self.lint_levels = lint_levels;
+    }
+
+    /// The diagnostics of every file as of the last [`GlobalState::check`].
     pub fn diagnostics(&self) -> impl Iterator<Item = (&Url, Vec<Diagnostic>)> {
-        self.analyses.iter().map(|(uri, analysis)| (uri, analysis.diagnostics()))
+        self.analyses
+            .iter()
+            .map(|(uri, analysis)| (uri, self.apply_lint_levels(analysis.diagnostics())))
+    }
+
+    /// The diagnostics of a single function, for quick feedback while it’s being edited,
+    /// which only checks the function again rather than everything an edit affected.
+    ///
+    /// Returns `None` if there is no such function in any open file.
+    pub fn function_diagnostics(&mut self, fqn: hir::Fqn) -> Option<Vec<Diagnostic>> {
+        let analysis =
+            self.analyses.values_mut().find(|analysis| analysis.module_name == fqn.module)?;
+        let diagnostics =
+            analysis.function_diagnostics(fqn.function, &self.world_index, &mut self.interner)?;
+
+        Some(self.apply_lint_levels(diagnostics))
+    }
+
+    /// Like [`GlobalState::function_diagnostics`],
+    /// but for the diagnostics in `range`, including any touching it,
+    /// of every function which overlaps it.
+    ///
+    /// Returns `None` if the file isn’t open.
+    pub fn diagnostics_in_range(&mut self, uri: &Url, range: TextRange) -> Option<Vec<Diagnostic>> {
+        let analysis = self.analyses.get_mut(uri)?;
+        let diagnostics =
+            analysis.diagnostics_in_range(range, &self.world_index, &mut self.interner);
+
+        Some(self.apply_lint_levels(diagnostics))
+    }
+
+    fn apply_lint_levels(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .filter_map(|diagnostic| self.lint_levels.apply(diagnostic))
+            .collect()
+    }
+
+    pub fn content(&self, uri: &Url) -> &str {
+        &self.analyses[uri].content
+    }
+
+    /// The file a module was loaded from,
+    /// which the standard library doesn’t have.
+    pub fn module_uri(&self, module: hir::Name) -> Option<Url> {
+        let project = self.project.as_ref()?;
+        project.module_path(module).map(path_to_uri)
     }
 
     pub fn line_index(&self, uri: &Url) -> &LineIndex {
@@ -160,7 +253,9 @@ impl Analysis {
         let ast = ast::Root::cast(tree.root(), tree).unwrap();
         let (index, indexing_diagnostics) = hir::index(ast, tree, world_index, interner);
         let (bodies, lowering_diagnostics) = hir::lower(ast, tree, &index, world_index, interner);
-        let (inference_result, ty_diagnostics) = hir_ty::infer_all(&bodies, &index, world_index);
+        let mut inference_cache = hir_ty::InferenceCache::default();
+        let (inference_result, ty_diagnostics) =
+            inference_cache.infer_all(&bodies, &index, world_index);
 
         world_index.add_module(module_name, index.clone());
 
@@ -178,6 +273,9 @@ impl Analysis {
             lowering_diagnostics,
             inference_result,
             ty_diagnostics,
+            inference_cache,
+            is_lowered: true,
+            is_inferred: true,
         };
 
         analysis.update_line_index();
@@ -197,14 +295,19 @@ impl Analysis {
         self.reparse();
         self.validate();
         self.index(world_index, interner);
-        self.recheck(world_index, interner);
+        world_index.update_module(self.module_name, self.index.clone());
+
+        self.is_lowered = false;
+        self.is_inferred = false;
     }
 
-    pub fn recheck(&mut self, world_index: &mut hir::WorldIndex, interner: &mut Interner) {
-        self.lower(world_index, interner);
-        world_index.update_module(self.module_name, self.index.clone());
+    fn check(&mut self, world_index: &hir::WorldIndex, interner: &mut Interner) {
+        self.lower_if_needed(world_index, interner);
 
-        self.infer(world_index);
+        if !self.is_inferred {
+            self.infer(world_index);
+            self.is_inferred = true;
+        }
     }
 
     pub fn parent_ranges(&self, offset: TextSize) -> Vec<TextRange> {
@@ -268,14 +371,20 @@ impl Analysis {
                     (
                         range_info.whole,
                         range_info.name,
-                        path_to_uri(project.module_path(fqn.module).unwrap()),
+                        path_to_uri(project.module_path(fqn.module)?),
                     )
                 }
+                hir::Path::Builtin(_) => return None,
             },
+            hir::Symbol::Const(name) => {
+                let constant = self.bodies.get_const(name)?.ast;
+                (constant.range(tree), constant.name(tree)?.range(tree), self.uri.clone())
+            }
+            hir::Symbol::Variant(_) => return None,
             hir::Symbol::Module(name) => (
                 TextRange::default(),
                 TextRange::default(),
-                path_to_uri(project.module_path(name).unwrap()),
+                path_to_uri(project.module_path(name)?),
             ),
             hir::Symbol::Unknown => return None,
         };
@@ -303,21 +412,54 @@ impl Analysis {
             let mut modifiers = HighlightModifiers(0);
 
             let kind = match token.kind(self.parse.syntax_tree()) {
-                TokenKind::LetKw | TokenKind::FncKw => HighlightKind::Keyword,
+                TokenKind::LetKw
+                | TokenKind::FncKw
+                | TokenKind::ExternKw
+                | TokenKind::TrueKw
+                | TokenKind::FalseKw
+                | TokenKind::LoopKw
+                | TokenKind::BreakKw
+                | TokenKind::ContinueKw
+                | TokenKind::MutKw
+                | TokenKind::ForKw
+                | TokenKind::InKw
+                | TokenKind::ReturnKw
+                | TokenKind::AsKw
+                | TokenKind::MatchKw
+                | TokenKind::TypeKw
+                | TokenKind::UseKw
+                | TokenKind::PubKw
+                | TokenKind::ConstKw
+                | TokenKind::DeferKw => HighlightKind::Keyword,
                 TokenKind::Int => HighlightKind::Number,
                 TokenKind::Quote => HighlightKind::Quote,
                 TokenKind::Escape => HighlightKind::Escape,
-                TokenKind::StringContents => HighlightKind::String,
-                TokenKind::Plus | TokenKind::Hyphen | TokenKind::Asterisk | TokenKind::Slash => {
-                    HighlightKind::Operator
-                }
+                TokenKind::StringContents | TokenKind::Char => HighlightKind::String,
+                TokenKind::Label => HighlightKind::Label,
+                TokenKind::Plus
+                | TokenKind::Hyphen
+                | TokenKind::Asterisk
+                | TokenKind::Slash
+                | TokenKind::EqEq
+                | TokenKind::BangEq
+                | TokenKind::Less
+                | TokenKind::LessEq
+                | TokenKind::Greater
+                | TokenKind::GreaterEq
+                | TokenKind::AmpAmp
+                | TokenKind::PipePipe
+                | TokenKind::Bang
+                | TokenKind::Question
+                | TokenKind::DotDot => HighlightKind::Operator,
                 TokenKind::CommentContents => HighlightKind::CommentContents,
                 TokenKind::CommentLeader => HighlightKind::CommentLeader,
                 TokenKind::DocCommentContents => HighlightKind::DocCommentContents,
                 TokenKind::DocCommentLeader => HighlightKind::DocCommentLeader,
 
                 TokenKind::Ident => match parent_node_kinds[parent_node_kinds.len() - 1] {
-                    NodeKind::LocalDef => {
+                    // a lone `None` is parsed like a binding
+                    NodeKind::BindingPattern if self.is_variant(token) => HighlightKind::Variant,
+                    NodeKind::LocalDef | NodeKind::BindingPattern | NodeKind::For => {
                         modifiers |= HighlightModifier::Declaration;
                         HighlightKind::Local
                     }
@@ -329,6 +471,14 @@ impl Analysis {
                         modifiers |= HighlightModifier::Declaration;
                         HighlightKind::Function
                     }
+                    NodeKind::TyParamList | NodeKind::TyAlias => {
+                        modifiers |= HighlightModifier::Declaration;
+                        HighlightKind::Ty
+                    }
+                    NodeKind::Const => {
+                        modifiers |= HighlightModifier::Declaration;
+                        HighlightKind::Constant
+                    }
                     _ => {
                         let ident = ast::Ident::cast(token, self.parse.syntax_tree()).unwrap();
                         match self.bodies.symbol(ident) {
@@ -336,8 +486,14 @@ impl Analysis {
                             Some(hir::Symbol::Param(_)) => HighlightKind::Param,
                             Some(hir::Symbol::Function(_)) => HighlightKind::Function,
                             Some(hir::Symbol::Module(_)) => HighlightKind::Module,
+                            Some(hir::Symbol::Variant(_)) => HighlightKind::Variant,
+                            Some(hir::Symbol::Const(_)) => HighlightKind::Constant,
                             Some(hir::Symbol::Unknown) => HighlightKind::UnresolvedReference,
-                            None if self.index.is_ident_ty(ident) => HighlightKind::Ty,
+                            None if self.index.is_ident_ty(ident)
+                                || self.bodies.is_ident_ty(ident) =>
+                            {
+                                HighlightKind::Ty
+                            }
                             None => continue,
                         }
                     }
@@ -354,9 +510,105 @@ impl Analysis {
         tokens
     }
 
+    /// The diagnostics of the module as of the last check.
     pub fn diagnostics(&self) -> Vec<Diagnostic> {
-        let syntax_errors =
-            self.parse.errors().iter().copied().map(diagnostics::Diagnostic::from_syntax);
+        self.diagnostics_with(&self.ty_diagnostics, |_| true)
+    }
+
+    /// The diagnostics of a single function,
+    /// which only infers the function again if it has changed.
+    pub fn function_diagnostics(
+        &mut self,
+        function: hir::Name,
+        world_index: &hir::WorldIndex,
+        interner: &mut Interner,
+    ) -> Option<Vec<Diagnostic>> {
+        self.index.get_function(function)?;
+        self.lower_if_needed(world_index, interner);
+        let range = self.index.range_info(function).whole;
+        let ty_diagnostics = self.function_ty_diagnostics(function, world_index)?;
+
+        Some(self.diagnostics_with(&ty_diagnostics, |r| range.contains_range(r)))
+    }
+
+    pub fn diagnostics_in_range(
+        &mut self,
+        range: TextRange,
+        world_index: &hir::WorldIndex,
+        interner: &mut Interner,
+    ) -> Vec<Diagnostic> {
+        self.lower_if_needed(world_index, interner);
+        let mut functions: Vec<_> = self
+            .index
+            .functions()
+            .filter(|function| self.index.range_info(*function).whole.intersect(range).is_some())
+            .collect();
+        functions.sort_unstable();
+
+        let mut ty_diagnostics = Vec::new();
+        for function in functions {
+            ty_diagnostics
+                .extend(self.function_ty_diagnostics(function, world_index).unwrap_or_default());
+        }
+
+        self.diagnostics_with(&ty_diagnostics, |r| r.intersect(range).is_some())
+    }
+
+    // served from the last check if nothing has changed since,
+    // and otherwise from the inference cache;
+    // the bodies have to be lowered already
+    fn function_ty_diagnostics(
+        &mut self,
+        function: hir::Name,
+        world_index: &hir::WorldIndex,
+    ) -> Option<Vec<hir_ty::TyDiagnostic>> {
+        if self.is_inferred {
+            let range = self.index.range_info(function).whole;
+            return Some(
+                self.ty_diagnostics
+                    .iter()
+                    .filter(|diagnostic| range.contains_range(diagnostic.range))
+                    .cloned()
+                    .collect(),
+            );
+        }
+
+        self.inference_cache.infer_function(function, &self.bodies, &self.index, world_index)
+    }
+
+    // the diagnostics of every stage along with `ty_diagnostics`,
+    // keeping those whose range passes `in_range`
+    fn diagnostics_with(
+        &self,
+        ty_diagnostics: &[hir_ty::TyDiagnostic],
+        in_range: impl Fn(TextRange) -> bool,
+    ) -> Vec<Diagnostic> {
+        // lowering explains some missing `;`s better than the parser can,
+        // so the parser’s errors for those are left out
+        let explained_missing_semicolons: Vec<_> = self
+            .lowering_diagnostics
+            .iter()
+            .filter_map(|d| match d.kind {
+                hir::LoweringDiagnosticKind::MissingSemicolonAfterLocal {
+                    statement_end, ..
+                } => Some(statement_end),
+                _ => None,
+            })
+            .collect();
+
+        let syntax_errors = self
+            .parse
+            .errors()
+            .iter()
+            .filter(|e| {
+                !matches!(
+                    e.kind,
+                    parser::SyntaxErrorKind::Missing { offset }
+                        if explained_missing_semicolons.contains(&offset)
+                )
+            })
+            .cloned()
+            .map(diagnostics::Diagnostic::from_syntax);
 
         let validation_diagnostics = self
             .validation_diagnostics
@@ -370,14 +622,17 @@ impl Analysis {
         let lowering_diagnostics =
             self.lowering_diagnostics.iter().cloned().map(diagnostics::Diagnostic::from_lowering);
 
-        let ty_diagnostics =
-            self.ty_diagnostics.iter().cloned().map(diagnostics::Diagnostic::from_ty);
+        let ty_diagnostics = ty_diagnostics.iter().cloned().map(diagnostics::Diagnostic::from_ty);
 
         syntax_errors
             .chain(validation_diagnostics)
             .chain(indexing_diagnostics)
             .chain(lowering_diagnostics)
             .chain(ty_diagnostics)
+            .collect::<diagnostics::Diagnostics>()
+            .into_iter()
+            .filter(|diagnostic| in_range(diagnostic.range()))
+            .filter(|diagnostic| !allow::is_allowed(diagnostic, self.parse.syntax_tree()))
             .collect()
     }
 
@@ -403,18 +658,29 @@ impl Analysis {
         self.indexing_diagnostics = diagnostics;
     }
 
-    fn lower(&mut self, world_index: &hir::WorldIndex, interner: &mut Interner) {
+    fn lower_if_needed(&mut self, world_index: &hir::WorldIndex, interner: &mut Interner) {
+        if self.is_lowered {
+            return;
+        }
+
         let (bodies, diagnostics) =
             hir::lower(self.ast, self.parse.syntax_tree(), &self.index, world_index, interner);
         self.bodies = bodies;
         self.lowering_diagnostics = diagnostics;
+        self.is_lowered = true;
     }
 
     fn infer(&mut self, world_index: &hir::WorldIndex) {
-        let (results, diagnostics) = hir_ty::infer_all(&self.bodies, &self.index, world_index);
+        let (results, diagnostics) =
+            self.inference_cache.infer_all(&self.bodies, &self.index, world_index);
         self.inference_result = results;
         self.ty_diagnostics = diagnostics;
     }
+
+    fn is_variant(&self, token: SyntaxToken) -> bool {
+        let ident = ast::Ident::cast(token, self.parse.syntax_tree()).unwrap();
+        matches!(self.bodies.symbol(ident), Some(hir::Symbol::Variant(_)))
+    }
 }
 
 pub struct Definition {
@@ -444,6 +710,9 @@ pub enum HighlightKind {
     Function,
     Module,
     Ty,
+    Variant,
+    Constant,
+    Label,
     Number,
     Quote,
     Escape,
@@ -0,0 +1,270 @@
+//! Runs the `.gb` fixtures in `src/tests/ui` through the whole front end
+//! and compares the diagnostics with the annotations in the fixtures.
+//!
+//! An annotation is a comment of the form `#~ ERROR: <message>`
+//! (or `#~ WARNING: <message>`). It expects a diagnostic
+//! with that severity whose message contains `<message>`
+//! and which starts on the annotation’s line.
+//! Each `^` after the `#~` moves the expected line one line up,
+//! so `#~^ ERROR: ...` refers to the line above.
+//!
+//! Every diagnostic has to be annotated,
+//! and every annotation has to match a diagnostic.
+
+use crate::{path_to_uri, Analysis, GlobalState};
+use diagnostics::{Diagnostic, Severity};
+use line_index::LineIndex;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::{env, fs};
+use text_size::{TextRange, TextSize};
+use url::Url;
+
+#[test]
+fn ui() {
+    let tests_dir = env::current_dir().unwrap().join("src/tests/ui");
+
+    let mut did_any_test_fail = false;
+
+    for file in fs::read_dir(tests_dir).unwrap() {
+        let path = file.unwrap().path();
+
+        if path.extension() != Some(OsStr::new("gb")) {
+            continue;
+        }
+
+        let did_panic = std::panic::catch_unwind(|| check_fixture(&path)).is_err();
+
+        if did_panic {
+            did_any_test_fail = true;
+        }
+    }
+
+    if did_any_test_fail {
+        panic!("At least one UI test failed");
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Expected {
+    line: usize,
+    severity: Severity,
+    message: String,
+}
+
+fn check_fixture(path: &Path) {
+    let content = fs::read_to_string(path).unwrap();
+    let mut expected = parse_annotations(&content);
+
+    let mut state = GlobalState::default();
+    let std_name = hir::Name(state.interner.intern(stdlib::MODULE_NAME));
+    state.index_std(std_name);
+
+    let module_name = hir::Name(state.interner.intern("main"));
+    let analysis = Analysis::new(
+        path_to_uri(path),
+        content.clone(),
+        module_name,
+        &mut state.interner,
+        &mut state.world_index,
+    );
+
+    let line_index = LineIndex::new(&content);
+    let mut unexpected = Vec::new();
+
+    for diagnostic in analysis.diagnostics() {
+        let (line, _) = line_index.line_col(diagnostic.range().start());
+        let line = line.0 as usize;
+        let severity = diagnostic.severity();
+        let message = diagnostic.message(&state.interner);
+
+        let matching_annotation = expected
+            .iter()
+            .position(|e| e.line == line && e.severity == severity && message.contains(&e.message));
+
+        match matching_annotation {
+            Some(idx) => {
+                expected.remove(idx);
+            }
+            None => unexpected.push(diagnostic.display(&content, &state.interner, &line_index)),
+        }
+    }
+
+    if unexpected.is_empty() && expected.is_empty() {
+        return;
+    }
+
+    let mut report = format!("{}:\n", path.display());
+
+    for lines in unexpected {
+        report.push_str("  unexpected diagnostic:\n");
+        for line in lines {
+            report.push_str(&format!("    {}\n", line));
+        }
+    }
+
+    for e in expected {
+        report.push_str(&format!(
+            "  no diagnostic matched annotation on line {}: {:?}: {}\n",
+            e.line + 1,
+            e.severity,
+            e.message
+        ));
+    }
+
+    panic!("{}", report);
+}
+
+fn parse_annotations(content: &str) -> Vec<Expected> {
+    let mut annotations = Vec::new();
+
+    for (idx, line) in content.lines().enumerate() {
+        let annotation = match line.find("#~") {
+            Some(start) => &line[start + 2..],
+            None => continue,
+        };
+
+        let lines_up = annotation.len() - annotation.trim_start_matches('^').len();
+        let annotation = annotation[lines_up..].trim_start();
+
+        let (severity, message) = if let Some(message) = annotation.strip_prefix("ERROR:") {
+            (Severity::Error, message)
+        } else if let Some(message) = annotation.strip_prefix("WARNING:") {
+            (Severity::Warning, message)
+        } else {
+            panic!("malformed annotation on line {}: `{}`", idx + 1, line);
+        };
+
+        let line = match idx.checked_sub(lines_up) {
+            Some(line) => line,
+            None => panic!("annotation on line {} points above the file", idx + 1),
+        };
+
+        annotations.push(Expected { line, severity, message: message.trim().to_string() });
+    }
+
+    annotations
+}
+
+#[test]
+fn parse_annotation_on_same_line() {
+    assert_eq!(
+        parse_annotations("fnc f: s32 -> x; #~ ERROR: undefined variable `x`"),
+        [Expected {
+            line: 0,
+            severity: Severity::Error,
+            message: "undefined variable `x`".to_string()
+        }]
+    );
+}
+
+#[test]
+fn parse_annotation_pointing_up() {
+    assert_eq!(
+        parse_annotations("fnc f() -> {};\n\n#~^^ WARNING: unneeded parentheses"),
+        [Expected {
+            line: 0,
+            severity: Severity::Warning,
+            message: "unneeded parentheses".to_string()
+        }]
+    );
+}
+
+// a project of one file, `main`, which isn't on disk
+fn state_with_main(content: &str) -> (GlobalState, Url) {
+    let mut state = GlobalState::default();
+    let uri = Url::parse("file:///project/main.gb").unwrap();
+    let module_name = hir::Name(state.interner.intern("main"));
+    let analysis = Analysis::new(
+        uri.clone(),
+        content.to_string(),
+        module_name,
+        &mut state.interner,
+        &mut state.world_index,
+    );
+    state.analyses.insert(uri.clone(), analysis);
+
+    (state, uri)
+}
+
+fn messages(state: &GlobalState, diagnostics: Option<Vec<Diagnostic>>) -> Option<Vec<String>> {
+    let diagnostics = diagnostics?;
+    Some(diagnostics.iter().map(|diagnostic| diagnostic.message(&state.interner)).collect())
+}
+
+#[test]
+fn function_diagnostics() {
+    let (mut state, uri) = state_with_main(
+        r#"
+fnc a: s32 -> "a";
+fnc b: s32 -> 2;
+"#,
+    );
+    let main = hir::Name(state.interner.intern("main"));
+    let fqn = |state: &mut GlobalState, name| hir::Fqn {
+        module: main,
+        function: hir::Name(state.interner.intern(name)),
+    };
+
+    // editing `b` only infers `b` again, not `a`
+    state.update_contents(&uri, |content, _| *content = content.replace("-> 2", "-> true"));
+    let b = fqn(&mut state, "b");
+    let diagnostics = state.function_diagnostics(b);
+    assert_eq!(
+        messages(&state, diagnostics).unwrap(),
+        ["expected `s32` because of the return type but found `bool`"]
+    );
+    let inferred = state.analyses[&uri].inference_cache.inferred();
+    assert_eq!(inferred, [b.function]);
+
+    let a = fqn(&mut state, "a");
+    let diagnostics = state.function_diagnostics(a);
+    assert_eq!(
+        messages(&state, diagnostics).unwrap(),
+        ["expected `s32` because of the return type but found `string`"]
+    );
+    assert!(state.analyses[&uri].inference_cache.inferred().is_empty());
+
+    let missing = fqn(&mut state, "missing");
+    assert!(state.function_diagnostics(missing).is_none());
+    let other_module = hir::Fqn { module: a.function, function: a.function };
+    assert!(state.function_diagnostics(other_module).is_none());
+
+    // checking the whole project catches up on everything
+    state.check();
+    let (_, diagnostics) = state.diagnostics().next().unwrap();
+    assert_eq!(diagnostics.len(), 2);
+}
+
+#[test]
+fn diagnostics_in_range() {
+    let content = r#"
+fnc a: s32 -> "a";
+fnc b: s32 -> {
+    let _x: s32 = true;
+    1
+};
+"#;
+    let (mut state, uri) = state_with_main(content);
+    state.update_contents(&uri, |_, _| {});
+
+    // an empty range, like a cursor, still finds what it touches
+    let offset = TextSize::from(content.find("true").unwrap() as u32);
+    let diagnostics = state.diagnostics_in_range(&uri, TextRange::empty(offset));
+    assert_eq!(
+        messages(&state, diagnostics).unwrap(),
+        ["expected `s32` because of the type annotation but found `bool`"]
+    );
+
+    let diagnostics = state.diagnostics_in_range(&uri, TextRange::up_to(offset));
+    assert_eq!(
+        messages(&state, diagnostics).unwrap(),
+        [
+            "expected `s32` because of the return type but found `string`",
+            "expected `s32` because of the type annotation but found `bool`"
+        ]
+    );
+
+    let other = Url::parse("file:///project/other.gb").unwrap();
+    assert!(state.diagnostics_in_range(&other, TextRange::empty(offset)).is_none());
+}
@@ -7,6 +7,11 @@ impl Default for Interner {
         let mut interner = Self(lasso::Rodeo::default());
         interner.intern("s32");
         interner.intern("string");
+        interner.intern("bool");
+        interner.intern("char");
+        interner.intern("s64");
+        interner.intern("u32");
+        interner.intern("unit");
         interner
     }
 }
@@ -31,12 +36,27 @@ impl Key {
     pub fn string() -> Self {
         Self::from_raw(2)
     }
+    pub fn bool() -> Self {
+        Self::from_raw(3)
+    }
+    pub fn char() -> Self {
+        Self::from_raw(4)
+    }
+    pub fn s64() -> Self {
+        Self::from_raw(5)
+    }
+    pub fn u32() -> Self {
+        Self::from_raw(6)
+    }
+    pub fn unit() -> Self {
+        Self::from_raw(7)
+    }
 
     pub fn from_raw(raw: u32) -> Self {
-        unsafe { Self(mem::transmute(raw)) }
+        unsafe { Self(mem::transmute::<u32, lasso::Spur>(raw)) }
     }
 
     pub fn to_raw(self) -> u32 {
-        unsafe { mem::transmute(self.0) }
+        unsafe { mem::transmute::<lasso::Spur, u32>(self.0) }
     }
 }
@@ -22,7 +22,11 @@ pub fn lex(text: &str) -> Tokens {
             LexerTokenKind::__InternalString => lex_string(lexer.slice(), start, handler),
             LexerTokenKind::__InternalComment => lex_comment(start, range.len(), handler),
             LexerTokenKind::__InternalDocComment => lex_doc_comment(start, range.len(), handler),
-            _ => handler(unsafe { mem::transmute(kind) }, start),
+            LexerTokenKind::__InternalAttrStart => {
+                handler(TokenKind::Hash, start);
+                handler(TokenKind::LBracket, start + TextSize::from(1));
+            }
+            _ => handler(unsafe { mem::transmute::<LexerTokenKind, TokenKind>(kind) }, start),
         }
     }
 
@@ -91,12 +95,77 @@ enum LexerTokenKind {
     #[token("fnc")]
     FncKw,
 
+    #[token("extern")]
+    ExternKw,
+
+    #[token("true")]
+    TrueKw,
+
+    #[token("false")]
+    FalseKw,
+
+    #[token("loop")]
+    LoopKw,
+
+    #[token("break")]
+    BreakKw,
+
+    #[token("continue")]
+    ContinueKw,
+
+    #[token("mut")]
+    MutKw,
+
+    #[token("for")]
+    ForKw,
+
+    #[token("in")]
+    InKw,
+
+    #[token("return")]
+    ReturnKw,
+
+    #[token("as")]
+    AsKw,
+
+    #[token("match")]
+    MatchKw,
+
+    #[token("type")]
+    TypeKw,
+
+    #[token("use")]
+    UseKw,
+
+    #[token("pub")]
+    PubKw,
+
+    #[token("const")]
+    ConstKw,
+
+    #[token("defer")]
+    DeferKw,
+
     #[regex("[a-zA-Z_]+[a-zA-Z0-9_]*")]
     Ident,
 
-    #[regex("[0-9]+")]
+    // prefixed literals take in any alphanumerics,
+    // so that validation can point out digits which don’t fit the radix
+    #[regex("[0-9]+|0[xob][0-9a-zA-Z]*")]
     Int,
 
+    // like with strings, the closing quote is optional,
+    // and validation reports literals without exactly one character;
+    // an unclosed literal starting like an identifier is a label instead,
+    // so that `'a: loop` isn’t read as one long literal
+    #[regex(r"'[a-zA-Z_][a-zA-Z0-9_]*'")]
+    #[regex(r"'([^'\\\na-zA-Z_]|\\.)([^'\\\n]|\\.)*'?")]
+    #[regex(r"''?")]
+    Char,
+
+    #[regex("'[a-zA-Z_][a-zA-Z0-9_]*")]
+    Label,
+
     _Quote,
 
     _Escape,
@@ -115,12 +184,45 @@ enum LexerTokenKind {
     #[token("/")]
     Slash,
 
+    #[token("==")]
+    EqEq,
+
+    #[token("!=")]
+    BangEq,
+
+    #[token("<")]
+    Less,
+
+    #[token("<=")]
+    LessEq,
+
+    #[token(">")]
+    Greater,
+
+    #[token(">=")]
+    GreaterEq,
+
+    #[token("&&")]
+    AmpAmp,
+
+    #[token("||")]
+    PipePipe,
+
+    #[token("!")]
+    Bang,
+
+    #[token("?")]
+    Question,
+
     #[token("=")]
     Eq,
 
     #[token(".")]
     Dot,
 
+    #[token("..")]
+    DotDot,
+
     #[token(":")]
     Colon,
 
@@ -139,13 +241,23 @@ enum LexerTokenKind {
     #[token(")")]
     RParen,
 
+    #[token("[")]
+    LBracket,
+
+    #[token("]")]
+    RBracket,
+
     #[token("{")]
     LBrace,
 
     #[token("}")]
     RBrace,
 
-    #[regex("[ \n]+")]
+    _Hash,
+
+    // `\r` only counts as whitespace before `\n`,
+    // so that files with Windows line endings lex the same as any other
+    #[regex("([ \t\n]|\r\n)+")]
     Whitespace,
 
     _CommentContents,
@@ -164,11 +276,15 @@ enum LexerTokenKind {
     #[regex(r#""([^"\\\n]|\\.)*"?"#)]
     __InternalString,
 
-    #[regex("#.*")]
+    // `#[` starts an attribute rather than a comment
+    #[regex(r"#([^\[\r\n][^\r\n]*)?")]
     __InternalComment,
 
-    #[regex("##.*")]
+    #[regex(r"##[^\r\n]*")]
     __InternalDocComment,
+
+    #[token("#[")]
+    __InternalAttrStart,
 }
 
 #[cfg(test)]
@@ -191,6 +307,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lex_tabs() {
+        check(
+            "\t1\t\n",
+            expect![[r#"
+                Whitespace@0..1
+                Int@1..2
+                Whitespace@2..4
+            "#]],
+        );
+    }
+
     #[test]
     fn lex_comment() {
         check(
@@ -212,6 +340,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lex_crlf() {
+        check(
+            "1\r\n2 \r\n",
+            expect![[r#"
+                Int@0..1
+                Whitespace@1..3
+                Int@3..4
+                Whitespace@4..7
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lone_cr_is_not_whitespace() {
+        check(
+            "1\r2",
+            expect![[r#"
+                Int@0..1
+                Error@1..2
+                Int@2..3
+            "#]],
+        );
+    }
+
+    #[test]
+    fn comments_go_to_end_of_crlf_line() {
+        check(
+            "# foo\r\n## bar\r\n",
+            expect![[r#"
+                CommentLeader@0..1
+                CommentContents@1..5
+                Whitespace@5..7
+                DocCommentLeader@7..9
+                DocCommentContents@9..13
+                Whitespace@13..15
+            "#]],
+        );
+    }
+
     #[test]
     fn comments_go_to_end_of_line() {
         check(
@@ -225,6 +393,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lex_shebang() {
+        check(
+            "#!/usr/bin/env gb\n1",
+            expect![[r#"
+                CommentLeader@0..1
+                CommentContents@1..17
+                Whitespace@17..18
+                Int@18..19
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_attr_start() {
+        check(
+            "#[test]",
+            expect![[r#"
+                Hash@0..1
+                LBracket@1..2
+                Ident@2..6
+                RBracket@6..7
+            "#]],
+        );
+    }
+
     #[test]
     fn lex_let_keyword() {
         check(
@@ -245,6 +439,166 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lex_extern_keyword() {
+        check(
+            "extern",
+            expect![[r#"
+                ExternKw@0..6
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_true_keyword() {
+        check(
+            "true",
+            expect![[r#"
+                TrueKw@0..4
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_false_keyword() {
+        check(
+            "false",
+            expect![[r#"
+                FalseKw@0..5
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_loop_keyword() {
+        check(
+            "loop",
+            expect![[r#"
+                LoopKw@0..4
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_break_keyword() {
+        check(
+            "break",
+            expect![[r#"
+                BreakKw@0..5
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_continue_keyword() {
+        check(
+            "continue",
+            expect![[r#"
+                ContinueKw@0..8
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_mut_keyword() {
+        check(
+            "mut",
+            expect![[r#"
+                MutKw@0..3
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_for_keyword() {
+        check(
+            "for",
+            expect![[r#"
+                ForKw@0..3
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_in_keyword() {
+        check(
+            "in",
+            expect![[r#"
+                InKw@0..2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_return_keyword() {
+        check(
+            "return",
+            expect![[r#"
+                ReturnKw@0..6
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_as_keyword() {
+        check(
+            "as",
+            expect![[r#"
+                AsKw@0..2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_match_keyword() {
+        check(
+            "match",
+            expect![[r#"
+                MatchKw@0..5
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_type_keyword() {
+        check(
+            "type",
+            expect![[r#"
+                TypeKw@0..4
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_use_keyword() {
+        check(
+            "use",
+            expect![[r#"
+                UseKw@0..3
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_pub_keyword() {
+        check(
+            "pub",
+            expect![[r#"
+                PubKw@0..3
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_const_keyword() {
+        check(
+            "const",
+            expect![[r#"
+                ConstKw@0..5
+            "#]],
+        );
+    }
+
     #[test]
     fn lex_lowercase_alphabetic_ident() {
         check(
@@ -315,6 +669,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn lex_prefixed_ints() {
+        check(
+            "0xFF 0o17 0b101",
+            expect![[r#"
+                Int@0..4
+                Whitespace@4..5
+                Int@5..9
+                Whitespace@9..10
+                Int@10..15
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_prefixed_int_with_invalid_digits() {
+        check(
+            "0b102",
+            expect![[r#"
+                Int@0..5
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_char() {
+        check(
+            "'a'",
+            expect![[r#"
+                Char@0..3
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_char_with_escaped_quote() {
+        check(
+            r"'\''",
+            expect![[r#"
+                Char@0..4
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_label() {
+        check(
+            "'outer 'a'",
+            expect![[r#"
+                Label@0..6
+                Whitespace@6..7
+                Char@7..10
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_unclosed_char() {
+        check(
+            "'1 b",
+            expect![[r#"
+                Char@0..4
+            "#]],
+        );
+    }
+
     #[test]
     fn dont_lex_ident_starting_with_int() {
         check(
@@ -470,6 +890,96 @@ baz",
         );
     }
 
+    #[test]
+    fn lex_eq_eq() {
+        check(
+            "==",
+            expect![[r#"
+                EqEq@0..2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_bang_eq() {
+        check(
+            "!=",
+            expect![[r#"
+                BangEq@0..2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_less() {
+        check(
+            "<",
+            expect![[r#"
+                Less@0..1
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_less_eq() {
+        check(
+            "<=",
+            expect![[r#"
+                LessEq@0..2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_greater() {
+        check(
+            ">",
+            expect![[r#"
+                Greater@0..1
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_greater_eq() {
+        check(
+            ">=",
+            expect![[r#"
+                GreaterEq@0..2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_amp_amp() {
+        check(
+            "&&",
+            expect![[r#"
+                AmpAmp@0..2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_pipe_pipe() {
+        check(
+            "||",
+            expect![[r#"
+                PipePipe@0..2
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_bang() {
+        check(
+            "!",
+            expect![[r#"
+                Bang@0..1
+            "#]],
+        );
+    }
+
     #[test]
     fn lex_eq() {
         check(
@@ -490,6 +1000,26 @@ baz",
         );
     }
 
+    #[test]
+    fn lex_question() {
+        check(
+            "?",
+            expect![[r#"
+                Question@0..1
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_dot_dot() {
+        check(
+            "..",
+            expect![[r#"
+                DotDot@0..2
+            "#]],
+        );
+    }
+
     #[test]
     fn lex_colon() {
         check(
@@ -550,6 +1080,26 @@ baz",
         );
     }
 
+    #[test]
+    fn lex_l_bracket() {
+        check(
+            "[",
+            expect![[r#"
+                LBracket@0..1
+            "#]],
+        );
+    }
+
+    #[test]
+    fn lex_r_bracket() {
+        check(
+            "]",
+            expect![[r#"
+                RBracket@0..1
+            "#]],
+        );
+    }
+
     #[test]
     fn lex_l_brace() {
         check(
@@ -2,9 +2,16 @@ use std::iter;
 use std::ops::{Index, Sub};
 use text_size::TextSize;
 
+/// Converts between offsets into a text and the lines and columns they’re at.
+///
+/// It’s built once per text, after which every lookup is a binary search.
+/// Lines and columns start at zero, and columns are in bytes.
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct LineIndex {
     line_starts: Vec<TextSize>,
+    // where the contents of each line end,
+    // which is before the `\r` of lines ending with `\r\n`
+    line_ends: Vec<TextSize>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -15,22 +22,45 @@ pub struct ColNr(pub u32);
 
 impl LineIndex {
     pub fn new(text: &str) -> Self {
-        Self {
-            line_starts: iter::once(TextSize::from(0))
-                .chain(text.match_indices('\n').map(|(idx, _)| TextSize::from(idx as u32 + 1)))
-                .collect(),
-        }
+        let newlines = text.match_indices('\n').map(|(idx, _)| idx);
+
+        let line_starts = iter::once(TextSize::from(0))
+            .chain(newlines.clone().map(|idx| TextSize::from(idx as u32 + 1)))
+            .collect();
+
+        let line_ends = newlines
+            .map(|idx| if text[..idx].ends_with('\r') { idx - 1 } else { idx })
+            .chain(iter::once(text.len()))
+            .map(|idx| TextSize::from(idx as u32))
+            .collect();
+
+        Self { line_starts, line_ends }
     }
 
+    /// Offsets in a line’s terminator are at the end of the line,
+    /// so that `\r\n` is one column wide like `\n` is.
     pub fn line_col(&self, offset: TextSize) -> (LineNr, ColNr) {
         let line = self.line_starts.partition_point(|&it| it <= offset) - 1;
+        let line_end = self.line_ends[line];
         let line = LineNr(line as u32);
 
         let line_start_offset = self[line];
-        let col = ColNr(u32::from(offset - line_start_offset));
+        let col = ColNr(u32::from(offset.min(line_end) - line_start_offset));
 
         (line, col)
     }
+
+    /// The inverse of [`LineIndex::line_col`].
+    /// Positions past the end of a line, or past the last line,
+    /// are moved back to where it ends.
+    pub fn offset(&self, line: LineNr, col: ColNr) -> TextSize {
+        let Some(&line_start) = self.line_starts.get(line.0 as usize) else {
+            return *self.line_ends.last().unwrap();
+        };
+        let line_end = self.line_ends[line.0 as usize];
+
+        (line_start + TextSize::from(col.0)).min(line_end)
+    }
 }
 
 impl Index<LineNr> for LineIndex {
@@ -63,11 +93,16 @@ mod tests {
 
     fn check<const LEN: usize>(text: &str, line_starts: [u32; LEN]) {
         assert_eq!(
-            LineIndex::new(text),
-            LineIndex { line_starts: line_starts.into_iter().map(TextSize::from).collect() }
+            LineIndex::new(text).line_starts,
+            line_starts.into_iter().map(TextSize::from).collect::<Vec<_>>()
         );
     }
 
+    fn check_line_col(text: &str, offset: u32, line: u32, col: u32) {
+        let (LineNr(actual_line), ColNr(actual_col)) = LineIndex::new(text).line_col(offset.into());
+        assert_eq!((actual_line, actual_col), (line, col));
+    }
+
     #[test]
     fn empty() {
         check("", [0]);
@@ -87,4 +122,46 @@ mod tests {
     fn two() {
         check("foo\nbar", [0, 4]);
     }
+
+    #[test]
+    fn crlf() {
+        check("foo\r\nbar\r\n", [0, 5, 10]);
+    }
+
+    #[test]
+    fn line_col() {
+        check_line_col("foo\nbar", 5, 1, 1);
+    }
+
+    #[test]
+    fn line_col_after_crlf() {
+        check_line_col("foo\r\nbar", 6, 1, 1);
+    }
+
+    #[test]
+    fn line_col_in_crlf() {
+        check_line_col("foo\r\nbar", 3, 0, 3);
+        check_line_col("foo\r\nbar", 4, 0, 3);
+    }
+
+    #[test]
+    fn offset_round_trips() {
+        let text = "foo\r\nbar\nbaz";
+        let line_index = LineIndex::new(text);
+
+        for offset in 0..text.len() as u32 {
+            if text[..offset as usize].ends_with('\r') {
+                continue;
+            }
+            let (line, col) = line_index.line_col(offset.into());
+            assert_eq!(line_index.offset(line, col), TextSize::from(offset));
+        }
+    }
+
+    #[test]
+    fn offset_past_end_of_line() {
+        let line_index = LineIndex::new("foo\r\nbar");
+        assert_eq!(line_index.offset(LineNr(0), ColNr(10)), TextSize::from(3));
+        assert_eq!(line_index.offset(LineNr(5), ColNr(0)), TextSize::from(8));
+    }
 }
@@ -2,7 +2,7 @@ use std::fmt;
 use syntax::TokenKind;
 use text_size::{TextRange, TextSize};
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct SyntaxError {
     pub expected_syntax: ExpectedSyntax,
     pub kind: SyntaxErrorKind,
@@ -25,9 +25,22 @@ impl fmt::Debug for SyntaxError {
         };
         write!(f, ": ")?;
 
-        let format_expected_syntax = |f: &mut fmt::Formatter<'_>| match self.expected_syntax {
-            ExpectedSyntax::Named(name) => write!(f, "{}", name),
-            ExpectedSyntax::Unnamed(kind) => write!(f, "{:?}", kind),
+        let format_expected_syntax = |f: &mut fmt::Formatter<'_>| {
+            let alternatives = self.expected_syntax.alternatives();
+            for (idx, expected_syntax) in alternatives.iter().enumerate() {
+                match idx {
+                    0 => {}
+                    _ if idx == alternatives.len() - 1 => write!(f, " or ")?,
+                    _ => write!(f, ", ")?,
+                }
+                match expected_syntax {
+                    ExpectedSyntax::Named(name) => write!(f, "{}", name)?,
+                    ExpectedSyntax::Unnamed(kind) => write!(f, "{:?}", kind)?,
+                    ExpectedSyntax::OneOf(_) => unreachable!(),
+                }
+            }
+
+            Ok(())
         };
 
         match self.kind {
@@ -46,8 +59,26 @@ impl fmt::Debug for SyntaxError {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ExpectedSyntax {
     Named(&'static str),
     Unnamed(TokenKind),
+    /// Several alternatives which were all checked for at the same position,
+    /// in the order they were checked for.
+    /// None of them are `OneOf`s themselves.
+    OneOf(Vec<ExpectedSyntax>),
+}
+
+impl ExpectedSyntax {
+    /// Every alternative on its own, of which there is one unless this is a `OneOf`.
+    pub fn alternatives(&self) -> &[ExpectedSyntax] {
+        match self {
+            Self::OneOf(alternatives) => alternatives,
+            _ => std::slice::from_ref(self),
+        }
+    }
+
+    pub fn allows(&self, kind: TokenKind) -> bool {
+        self.alternatives().contains(&Self::Unnamed(kind))
+    }
 }
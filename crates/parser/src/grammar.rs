@@ -1,5 +1,6 @@
 mod def;
 mod expr;
+mod pattern;
 mod statement;
 mod ty;
 
@@ -1,19 +1,39 @@
+mod constant;
 mod function;
+mod import;
+mod ty_alias;
 
+use self::constant::parse_const;
 use self::function::parse_function;
+use self::import::parse_import;
+use self::ty_alias::parse_ty_alias;
 use crate::parser::{CompletedMarker, Parser};
 use crate::token_set::TokenSet;
 use syntax::{NodeKind, TokenKind};
 
-pub(super) const DEF_FIRST: TokenSet =
-    TokenSet::new([TokenKind::FncKw, TokenKind::DocCommentLeader]);
+pub(super) const DEF_FIRST: TokenSet = TokenSet::new([
+    TokenKind::FncKw,
+    TokenKind::ExternKw,
+    TokenKind::TypeKw,
+    TokenKind::UseKw,
+    TokenKind::PubKw,
+    TokenKind::ConstKw,
+    TokenKind::DocCommentLeader,
+    TokenKind::Hash,
+]);
 
 pub(super) fn parse_def(p: &mut Parser<'_>) -> Option<CompletedMarker> {
-    let docs_cm = if p.at(TokenKind::DocCommentLeader) { Some(parse_docs(p)) } else { None };
+    let mut docs_cm = if p.at(TokenKind::DocCommentLeader) { Some(parse_docs(p)) } else { None };
+
+    // attributes are only allowed on functions, which validation checks
+    while p.at(TokenKind::Hash) {
+        let attr_cm = parse_attr(p);
+        docs_cm = docs_cm.or(Some(attr_cm));
+    }
 
     let _guard = p.expected_syntax_name("definition");
 
-    if p.at(TokenKind::FncKw) {
+    if p.at(TokenKind::PubKw) || p.at(TokenKind::FncKw) || p.at(TokenKind::ExternKw) {
         let m = match docs_cm {
             Some(cm) => cm.precede(p),
             None => p.start(),
@@ -21,6 +41,30 @@ pub(super) fn parse_def(p: &mut Parser<'_>) -> Option<CompletedMarker> {
         return Some(parse_function(p, m));
     }
 
+    if p.at(TokenKind::TypeKw) {
+        let m = match docs_cm {
+            Some(cm) => cm.precede(p),
+            None => p.start(),
+        };
+        return Some(parse_ty_alias(p, m));
+    }
+
+    if p.at(TokenKind::UseKw) {
+        let m = match docs_cm {
+            Some(cm) => cm.precede(p),
+            None => p.start(),
+        };
+        return Some(parse_import(p, m));
+    }
+
+    if p.at(TokenKind::ConstKw) {
+        let m = match docs_cm {
+            Some(cm) => cm.precede(p),
+            None => p.start(),
+        };
+        return Some(parse_const(p, m));
+    }
+
     p.error_with_recovery_set_no_default(TokenSet::default())
 }
 
@@ -39,3 +83,51 @@ fn parse_docs(p: &mut Parser<'_>) -> CompletedMarker {
 
     m.complete(p, NodeKind::Docs)
 }
+
+// `#[name]` or `#[name(arg, arg)]`, where the arguments are plain names
+fn parse_attr(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::Hash));
+    let m = p.start();
+    p.bump();
+    p.expect(TokenKind::LBracket);
+
+    {
+        let _guard = p.expected_syntax_name("attribute name");
+        p.expect_with_recovery_set(
+            TokenKind::Ident,
+            TokenSet::new([TokenKind::LParen, TokenKind::RBracket]),
+        );
+    }
+
+    if p.at(TokenKind::LParen) {
+        parse_attr_arg_list(p);
+    }
+
+    p.expect(TokenKind::RBracket);
+
+    m.complete(p, NodeKind::Attr)
+}
+
+fn parse_attr_arg_list(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::LParen));
+    let m = p.start();
+    p.bump();
+
+    while !p.at(TokenKind::RParen) && !p.at_eof() {
+        let _guard = p.expected_syntax_name("attribute argument");
+        p.expect_with_recovery_set(
+            TokenKind::Ident,
+            TokenSet::new([TokenKind::Comma, TokenKind::RParen, TokenKind::RBracket]),
+        );
+
+        if p.at(TokenKind::Comma) {
+            p.bump();
+        } else {
+            break;
+        }
+    }
+
+    p.expect_with_recovery_set(TokenKind::RParen, TokenSet::new([TokenKind::RBracket]));
+
+    m.complete(p, NodeKind::AttrArgList)
+}
@@ -0,0 +1,32 @@
+use crate::grammar::expr::parse_expr;
+use crate::grammar::ty::parse_ty;
+use crate::parser::{CompletedMarker, Marker, Parser};
+use crate::token_set::TokenSet;
+use syntax::{NodeKind, TokenKind};
+
+pub(super) fn parse_const(p: &mut Parser<'_>, m: Marker) -> CompletedMarker {
+    assert!(p.at(TokenKind::ConstKw));
+    p.bump();
+
+    {
+        let _guard = p.expected_syntax_name("constant name");
+        p.expect_with_recovery_set(
+            TokenKind::Ident,
+            TokenSet::new([TokenKind::Colon, TokenKind::Eq]),
+        );
+    }
+
+    if p.at(TokenKind::Colon) {
+        p.bump();
+
+        let _guard = p.expected_syntax_name("constant type");
+        parse_ty(p, TokenSet::new([TokenKind::Eq]));
+    }
+
+    p.expect(TokenKind::Eq);
+    parse_expr(p, "constant value");
+
+    p.expect(TokenKind::Semicolon);
+
+    m.complete(p, NodeKind::Const)
+}
@@ -1,16 +1,33 @@
-use crate::grammar::expr::{parse_expr, EXPR_FIRST};
+use crate::grammar::expr::{parse_expr, parse_expr_with_recovery_set, EXPR_FIRST};
 use crate::grammar::ty::parse_ty;
 use crate::parser::{CompletedMarker, Marker, Parser};
 use crate::token_set::TokenSet;
 use syntax::{NodeKind, TokenKind};
 
 pub(super) fn parse_function(p: &mut Parser<'_>, m: Marker) -> CompletedMarker {
-    assert!(p.at(TokenKind::FncKw));
-    p.bump();
+    assert!(p.at(TokenKind::PubKw) || p.at(TokenKind::FncKw) || p.at(TokenKind::ExternKw));
+
+    if p.at(TokenKind::PubKw) {
+        p.bump();
+    }
+
+    let is_extern = p.at(TokenKind::ExternKw);
+    if is_extern {
+        p.bump();
+    }
+
+    p.expect_with_recovery_set(TokenKind::FncKw, TokenSet::new([TokenKind::Ident]));
 
     {
         let _guard = p.expected_syntax_name("function name");
-        p.expect_with_recovery_set(TokenKind::Ident, TokenSet::new([TokenKind::LParen]));
+        p.expect_with_recovery_set(
+            TokenKind::Ident,
+            TokenSet::new([TokenKind::LBracket, TokenKind::LParen]),
+        );
+    }
+
+    if p.at(TokenKind::LBracket) {
+        parse_ty_param_list(p);
     }
 
     if p.at(TokenKind::LParen) {
@@ -21,13 +38,46 @@ pub(super) fn parse_function(p: &mut Parser<'_>, m: Marker) -> CompletedMarker {
         parse_return_ty(p);
     }
 
-    p.expect_with_recovery_set(TokenKind::Arrow, EXPR_FIRST);
-    parse_expr(p, "function body");
+    // extern functions are defined outside of gingerbread,
+    // so they only have a signature
+    if !is_extern {
+        p.expect_with_recovery_set(TokenKind::Arrow, EXPR_FIRST);
+        parse_expr(p, "function body");
+    }
+
     p.expect(TokenKind::Semicolon);
 
     m.complete(p, NodeKind::Function)
 }
 
+// the type parameters of a generic function, like the `[T]` in `fnc id[T](x: T): T`
+fn parse_ty_param_list(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::LBracket));
+    let m = p.start();
+    p.bump();
+
+    while !p.at(TokenKind::RBracket) && !p.at_eof() {
+        let _guard = p.expected_syntax_name("type parameter");
+        p.expect_with_recovery_set(
+            TokenKind::Ident,
+            TokenSet::new([TokenKind::Comma, TokenKind::RBracket, TokenKind::LParen]),
+        );
+
+        if p.at(TokenKind::Comma) {
+            p.bump();
+        } else {
+            break;
+        }
+    }
+
+    p.expect_with_recovery_set(
+        TokenKind::RBracket,
+        TokenSet::new([TokenKind::LParen, TokenKind::Colon, TokenKind::Arrow]),
+    );
+
+    m.complete(p, NodeKind::TyParamList)
+}
+
 fn parse_param_list(p: &mut Parser<'_>) -> CompletedMarker {
     assert!(p.at(TokenKind::LParen));
     let m = p.start();
@@ -91,7 +141,16 @@ fn parse_param(p: &mut Parser<'_>) -> CompletedMarker {
 
     {
         let _guard = p.expected_syntax_name("parameter type");
-        parse_ty(p, TokenSet::new([TokenKind::Comma, TokenKind::RParen]));
+        parse_ty(p, TokenSet::new([TokenKind::Eq, TokenKind::Comma, TokenKind::RParen]));
+    }
+
+    if p.at(TokenKind::Eq) {
+        p.bump();
+        parse_expr_with_recovery_set(
+            p,
+            TokenSet::new([TokenKind::Comma, TokenKind::RParen]),
+            "default value",
+        );
     }
 
     m.complete(p, NodeKind::Param)
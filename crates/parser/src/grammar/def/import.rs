@@ -0,0 +1,24 @@
+use crate::parser::{CompletedMarker, Marker, Parser};
+use crate::token_set::TokenSet;
+use syntax::{NodeKind, TokenKind};
+
+pub(super) fn parse_import(p: &mut Parser<'_>, m: Marker) -> CompletedMarker {
+    assert!(p.at(TokenKind::UseKw));
+    p.bump();
+
+    {
+        let _guard = p.expected_syntax_name("module name");
+        p.expect_with_recovery_set(TokenKind::Ident, TokenSet::new([TokenKind::Dot]));
+    }
+
+    p.expect_with_recovery_set(TokenKind::Dot, TokenSet::new([TokenKind::Ident]));
+
+    {
+        let _guard = p.expected_syntax_name("function name");
+        p.expect(TokenKind::Ident);
+    }
+
+    p.expect(TokenKind::Semicolon);
+
+    m.complete(p, NodeKind::Import)
+}
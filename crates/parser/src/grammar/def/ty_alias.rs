@@ -0,0 +1,28 @@
+use crate::grammar::ty::parse_ty;
+use crate::parser::{CompletedMarker, Marker, Parser};
+use crate::token_set::TokenSet;
+use syntax::{NodeKind, TokenKind};
+
+pub(super) fn parse_ty_alias(p: &mut Parser<'_>, m: Marker) -> CompletedMarker {
+    assert!(p.at(TokenKind::TypeKw));
+    p.bump();
+
+    {
+        let _guard = p.expected_syntax_name("type alias name");
+        p.expect_with_recovery_set(TokenKind::Ident, TokenSet::new([TokenKind::Eq]));
+    }
+
+    p.expect_with_recovery_set(
+        TokenKind::Eq,
+        TokenSet::new([TokenKind::Ident, TokenKind::LParen, TokenKind::LBracket]),
+    );
+
+    {
+        let _guard = p.expected_syntax_name("type");
+        parse_ty(p, TokenSet::default());
+    }
+
+    p.expect(TokenKind::Semicolon);
+
+    m.complete(p, NodeKind::TyAlias)
+}
@@ -1,11 +1,46 @@
-use crate::grammar::def::DEF_FIRST;
+use crate::grammar::def::{parse_def, DEF_FIRST};
+use crate::grammar::pattern::{parse_pattern, PATTERN_FIRST};
 use crate::grammar::statement::parse_statement;
-use crate::parser::{CompletedMarker, Parser};
+use crate::grammar::ty::parse_ty;
+use crate::parser::{CompletedMarker, Marker, Parser};
 use crate::token_set::TokenSet;
 use syntax::{NodeKind, TokenKind};
 
-pub(super) const EXPR_FIRST: TokenSet =
-    TokenSet::new([TokenKind::Ident, TokenKind::LBrace, TokenKind::Int, TokenKind::Quote]);
+pub(super) const EXPR_FIRST: TokenSet = ARG_FIRST.union(TokenSet::new([TokenKind::Hyphen]));
+
+// `f -1` subtracts one from `f`
+// rather than calling `f` with a negative argument
+const ARG_FIRST: TokenSet = TokenSet::new([
+    TokenKind::Ident,
+    TokenKind::LBrace,
+    TokenKind::LParen,
+    TokenKind::LBracket,
+    TokenKind::Int,
+    TokenKind::Char,
+    TokenKind::TrueKw,
+    TokenKind::FalseKw,
+    TokenKind::Quote,
+    TokenKind::LoopKw,
+    TokenKind::ForKw,
+    TokenKind::Label,
+    TokenKind::MatchKw,
+    TokenKind::Bang,
+]);
+
+// `as` binds tighter than any infix operator,
+// and prefix operators bind tighter still,
+// so `-x as s64` casts the negated `x`
+const CAST_BP: u8 = 11;
+const PREFIX_BP: u8 = 12;
+
+const COMPARISON_OPERATORS: TokenSet = TokenSet::new([
+    TokenKind::EqEq,
+    TokenKind::BangEq,
+    TokenKind::Less,
+    TokenKind::LessEq,
+    TokenKind::Greater,
+    TokenKind::GreaterEq,
+]);
 
 pub(super) fn parse_expr(
     p: &mut Parser<'_>,
@@ -14,7 +49,7 @@ pub(super) fn parse_expr(
     parse_expr_with_recovery_set(p, TokenSet::default(), expected_syntax_name)
 }
 
-fn parse_expr_with_recovery_set(
+pub(super) fn parse_expr_with_recovery_set(
     p: &mut Parser<'_>,
     recovery_set: TokenSet,
     expected_syntax_name: &'static str,
@@ -31,10 +66,29 @@ fn parse_expr_bp(
     let mut lhs = parse_lhs(p, recovery_set, expected_syntax_name)?;
 
     loop {
-        let (left_bp, right_bp) = if p.at(TokenKind::Plus) || p.at(TokenKind::Hyphen) {
+        if p.at_operator(TokenKind::AsKw) {
+            if CAST_BP < min_bp {
+                break;
+            }
+
+            let m = lhs.precede(p);
+            p.bump();
+            parse_ty(p, recovery_set);
+            lhs = m.complete(p, NodeKind::CastExpr);
+
+            continue;
+        }
+
+        let (left_bp, right_bp) = if p.at_operator(TokenKind::PipePipe) {
             (1, 2)
-        } else if p.at(TokenKind::Asterisk) || p.at(TokenKind::Slash) {
+        } else if p.at_operator(TokenKind::AmpAmp) {
             (3, 4)
+        } else if p.at_set(COMPARISON_OPERATORS) {
+            (5, 6)
+        } else if p.at_operator(TokenKind::Plus) || p.at_operator(TokenKind::Hyphen) {
+            (7, 8)
+        } else if p.at_operator(TokenKind::Asterisk) || p.at_operator(TokenKind::Slash) {
+            (9, 10)
         } else {
             break;
         };
@@ -66,13 +120,131 @@ fn parse_lhs(
         parse_block(p)
     } else if p.at(TokenKind::Int) {
         parse_int_literal(p)
+    } else if p.at(TokenKind::TrueKw) || p.at(TokenKind::FalseKw) {
+        parse_bool_literal(p)
     } else if p.at(TokenKind::Quote) {
         parse_string_literal(p)
+    } else if p.at(TokenKind::Char) {
+        parse_char_literal(p)
+    } else if p.at(TokenKind::LoopKw) {
+        parse_loop(p)
+    } else if p.at(TokenKind::ForKw) {
+        parse_for(p)
+    } else if p.at(TokenKind::Label) {
+        parse_labeled(p)
+    } else if p.at(TokenKind::MatchKw) {
+        parse_match(p)
+    } else if p.at(TokenKind::LParen) {
+        parse_tuple_expr(p)
+    } else if p.at(TokenKind::LBracket) {
+        parse_array_expr(p)
+    } else if p.at(TokenKind::Hyphen) || p.at(TokenKind::Bang) {
+        // the operand takes in any fields and indexes after it,
+        // so `-pair.0` negates the field rather than the tuple
+        return Some(parse_unary_expr(p, recovery_set));
     } else {
         return p.error_with_recovery_set(recovery_set);
     };
 
-    Some(completed_marker)
+    Some(parse_postfix_exprs(p, completed_marker))
+}
+
+// only a `[` right after an expression indexes it:
+// `f [1]` calls `f` with an array, while `f[1]` indexes `f`
+fn parse_postfix_exprs(p: &mut Parser<'_>, mut lhs: CompletedMarker) -> CompletedMarker {
+    loop {
+        if p.at_operator(TokenKind::Dot) && p.next_at(TokenKind::Ident) {
+            let m = lhs.precede(p);
+            p.bump();
+            p.bump();
+
+            if at_args(p) {
+                parse_arg_list(p);
+            }
+
+            lhs = m.complete(p, NodeKind::MethodCall);
+        } else if p.at_operator(TokenKind::Dot) {
+            let m = lhs.precede(p);
+            p.bump();
+
+            let _guard = p.expected_syntax_name("tuple index");
+            p.expect_with_no_skip(TokenKind::Int);
+
+            lhs = m.complete(p, NodeKind::FieldExpr);
+        } else if p.at_immediately(TokenKind::LBracket) {
+            let m = lhs.precede(p);
+            p.bump();
+
+            p.with_block_args_allowed(true, |p| {
+                parse_expr_with_recovery_set(p, TokenSet::new([TokenKind::RBracket]), "index")
+            });
+            p.expect(TokenKind::RBracket);
+
+            lhs = m.complete(p, NodeKind::IndexExpr);
+        } else if p.at_operator(TokenKind::Question) {
+            let m = lhs.precede(p);
+            p.bump();
+            lhs = m.complete(p, NodeKind::TryExpr);
+        } else {
+            break;
+        }
+    }
+
+    lhs
+}
+
+fn parse_tuple_expr(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::LParen));
+    let m = p.start();
+    p.bump();
+
+    p.with_block_args_allowed(true, |p| {
+        while p.at_set(EXPR_FIRST) {
+            parse_expr(p, "tuple element");
+
+            if p.at(TokenKind::Comma) {
+                p.bump();
+            } else {
+                break;
+            }
+        }
+    });
+
+    p.expect(TokenKind::RParen);
+
+    m.complete(p, NodeKind::TupleExpr)
+}
+
+fn parse_array_expr(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::LBracket));
+    let m = p.start();
+    p.bump();
+
+    p.with_block_args_allowed(true, |p| {
+        while p.at_set(EXPR_FIRST) {
+            parse_expr(p, "array element");
+
+            if p.at(TokenKind::Comma) {
+                p.bump();
+            } else {
+                break;
+            }
+        }
+    });
+
+    p.expect(TokenKind::RBracket);
+
+    m.complete(p, NodeKind::ArrayExpr)
+}
+
+fn parse_unary_expr(p: &mut Parser<'_>, recovery_set: TokenSet) -> CompletedMarker {
+    assert!(p.at(TokenKind::Hyphen) || p.at(TokenKind::Bang));
+    let m = p.start();
+    p.bump();
+
+    parse_expr_bp(p, PREFIX_BP, recovery_set, "operand");
+
+    m.complete(p, NodeKind::UnaryExpr)
 }
 
 fn parse_call(p: &mut Parser<'_>) -> CompletedMarker {
@@ -80,18 +252,25 @@ fn parse_call(p: &mut Parser<'_>) -> CompletedMarker {
     let m = p.start();
     p.bump();
 
-    if p.at(TokenKind::Dot) {
+    // a `.` directly followed by a number is a field of the call’s result instead
+    if p.at_operator(TokenKind::Dot) && !p.next_at(TokenKind::Int) {
         p.bump();
         p.expect_with_no_skip(TokenKind::Ident);
     }
 
-    if p.at_set(EXPR_FIRST) {
+    if at_args(p) {
         parse_arg_list(p);
     }
 
     m.complete(p, NodeKind::Call)
 }
 
+fn at_args(p: &mut Parser<'_>) -> bool {
+    p.at_set(ARG_FIRST)
+        && (p.block_args_allowed() || !p.at(TokenKind::LBrace))
+        && !p.at_immediately(TokenKind::LBracket)
+}
+
 fn parse_arg_list(p: &mut Parser<'_>) -> CompletedMarker {
     assert!(p.at_set(EXPR_FIRST));
     let m = p.start();
@@ -119,32 +298,187 @@ fn parse_arg(p: &mut Parser<'_>) -> CompletedMarker {
 }
 
 fn parse_block(p: &mut Parser<'_>) -> CompletedMarker {
-    assert!(p.at(TokenKind::LBrace));
     let m = p.start();
+    finish_block(p, m)
+}
+
+fn finish_block(p: &mut Parser<'_>, m: Marker) -> CompletedMarker {
+    assert!(p.at(TokenKind::LBrace));
     p.bump();
 
-    while !p.at(TokenKind::RBrace) && !p.at_set(DEF_FIRST) && !p.at_eof() {
-        parse_statement(p);
-    }
+    p.with_block_args_allowed(true, |p| {
+        while !p.at(TokenKind::RBrace) && !p.at_eof() {
+            if p.at_set(DEF_FIRST) {
+                parse_def(p);
+            } else {
+                parse_statement(p);
+            }
+        }
+    });
 
     p.expect(TokenKind::RBrace);
 
     m.complete(p, NodeKind::Block)
 }
 
-fn parse_int_literal(p: &mut Parser<'_>) -> CompletedMarker {
+fn parse_loop(p: &mut Parser<'_>) -> CompletedMarker {
+    let m = p.start();
+    finish_loop(p, m)
+}
+
+fn finish_loop(p: &mut Parser<'_>, m: Marker) -> CompletedMarker {
+    assert!(p.at(TokenKind::LoopKw));
+    p.bump();
+
+    if p.at(TokenKind::LBrace) {
+        parse_block(p);
+    } else {
+        let _guard = p.expected_syntax_name("loop body");
+        p.error_with_recovery_set(TokenSet::default());
+    }
+
+    m.complete(p, NodeKind::Loop)
+}
+
+fn parse_for(p: &mut Parser<'_>) -> CompletedMarker {
+    let m = p.start();
+    finish_for(p, m)
+}
+
+fn finish_for(p: &mut Parser<'_>, m: Marker) -> CompletedMarker {
+    assert!(p.at(TokenKind::ForKw));
+    p.bump();
+
+    {
+        let _guard = p.expected_syntax_name("loop variable");
+        p.expect_with_recovery_set(TokenKind::Ident, TokenSet::new([TokenKind::InKw]));
+    }
+
+    p.expect(TokenKind::InKw);
+    parse_range_expr(p);
+
+    if p.at(TokenKind::LBrace) {
+        parse_block(p);
+    } else {
+        let _guard = p.expected_syntax_name("loop body");
+        p.error_with_recovery_set(TokenSet::default());
+    }
+
+    m.complete(p, NodeKind::For)
+}
+
+// the label goes inside of the loop or block it names,
+// so that `break 'label` can find what it leaves
+fn parse_labeled(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::Label));
+    let m = p.start();
+    p.bump();
+
+    p.expect_with_no_skip(TokenKind::Colon);
+
+    if p.at(TokenKind::LoopKw) {
+        finish_loop(p, m)
+    } else if p.at(TokenKind::ForKw) {
+        finish_for(p, m)
+    } else if p.at(TokenKind::LBrace) {
+        finish_block(p, m)
+    } else {
+        let _guard = p.expected_syntax_name("loop or block");
+        p.error_with_no_skip();
+        m.complete(p, NodeKind::Error)
+    }
+}
+
+fn parse_match(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::MatchKw));
+    let m = p.start();
+    p.bump();
+
+    // like with `for` loops, the `{` after the value starts the arms
+    p.with_block_args_allowed(false, |p| parse_expr(p, "matched value"));
+
+    if p.at(TokenKind::LBrace) {
+        p.bump();
+
+        p.with_block_args_allowed(true, |p| {
+            while !p.at(TokenKind::RBrace) && !p.at_eof() {
+                if p.at_set(PATTERN_FIRST) || p.at(TokenKind::Arrow) {
+                    parse_match_arm(p);
+                } else if p.at_default_recovery_set() {
+                    break;
+                } else {
+                    let _guard = p.expected_syntax_name("match arm");
+                    p.error_with_recovery_set(TokenSet::default());
+                }
+            }
+        });
+
+        p.expect(TokenKind::RBrace);
+    } else {
+        let _guard = p.expected_syntax_name("match arms");
+        p.error_with_recovery_set(TokenSet::default());
+    }
+
+    m.complete(p, NodeKind::MatchExpr)
+}
+
+// the `;` after the last arm is optional
+fn parse_match_arm(p: &mut Parser<'_>) -> CompletedMarker {
+    let m = p.start();
+
+    parse_pattern(p, TokenSet::new([TokenKind::Arrow]));
+    p.expect(TokenKind::Arrow);
+    parse_expr(p, "match arm body");
+
+    if !p.at(TokenKind::RBrace) {
+        p.expect_with_no_skip(TokenKind::Semicolon);
+    }
+
+    m.complete(p, NodeKind::MatchArm)
+}
+
+// the `{` after the range starts the body of the loop,
+// so it can’t be an argument to a call at the end of the range
+fn parse_range_expr(p: &mut Parser<'_>) -> CompletedMarker {
+    let m = p.start();
+
+    p.with_block_args_allowed(false, |p| {
+        parse_expr(p, "range start");
+        p.expect(TokenKind::DotDot);
+        parse_expr(p, "range end");
+    });
+
+    m.complete(p, NodeKind::RangeExpr)
+}
+
+pub(super) fn parse_int_literal(p: &mut Parser<'_>) -> CompletedMarker {
     assert!(p.at(TokenKind::Int));
     let m = p.start();
     p.bump();
     m.complete(p, NodeKind::IntLiteral)
 }
 
-fn parse_string_literal(p: &mut Parser<'_>) -> CompletedMarker {
+pub(super) fn parse_bool_literal(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::TrueKw) || p.at(TokenKind::FalseKw));
+    let m = p.start();
+    p.bump();
+    m.complete(p, NodeKind::BoolLiteral)
+}
+
+pub(super) fn parse_char_literal(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::Char));
+    let m = p.start();
+    p.bump();
+    m.complete(p, NodeKind::CharLiteral)
+}
+
+pub(super) fn parse_string_literal(p: &mut Parser<'_>) -> CompletedMarker {
     assert!(p.at(TokenKind::Quote));
     let m = p.start();
     p.bump();
 
-    while p.at(TokenKind::StringContents) || p.at(TokenKind::Escape) {
+    // only a missing closing quote is worth reporting
+    while p.at_set(TokenSet::new([TokenKind::StringContents, TokenKind::Escape])) {
         p.bump();
     }
 
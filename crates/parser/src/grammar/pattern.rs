@@ -0,0 +1,107 @@
+use crate::grammar::expr::{
+    parse_bool_literal, parse_char_literal, parse_int_literal, parse_string_literal,
+};
+use crate::parser::{CompletedMarker, Parser};
+use crate::token_set::TokenSet;
+use syntax::{NodeKind, TokenKind};
+
+pub(super) const PATTERN_FIRST: TokenSet = TokenSet::new([
+    TokenKind::Ident,
+    TokenKind::Int,
+    TokenKind::Hyphen,
+    TokenKind::TrueKw,
+    TokenKind::FalseKw,
+    TokenKind::Quote,
+    TokenKind::Char,
+    TokenKind::LParen,
+]);
+
+pub(super) fn parse_pattern(p: &mut Parser<'_>, recovery_set: TokenSet) -> Option<CompletedMarker> {
+    let _guard = p.expected_syntax_name("pattern");
+
+    if p.at(TokenKind::Ident) {
+        Some(parse_binding_or_variant_pattern(p, recovery_set))
+    } else if p.at(TokenKind::LParen) {
+        Some(parse_tuple_pattern(p, recovery_set, parse_pattern))
+    } else if p.at_set(PATTERN_FIRST) {
+        Some(parse_literal_pattern(p))
+    } else {
+        p.error_with_recovery_set(recovery_set)
+    }
+}
+
+// `_` is an ordinary binding to the parser,
+// and only lowering treats it as a wildcard
+pub(super) fn parse_binding_pattern(p: &mut Parser<'_>) -> CompletedMarker {
+    assert!(p.at(TokenKind::Ident));
+    let m = p.start();
+    p.bump();
+    m.complete(p, NodeKind::BindingPattern)
+}
+
+// `Some x` is a variant with a field,
+// while a lone `None` is parsed like a binding and left for lowering to tell apart
+fn parse_binding_or_variant_pattern(p: &mut Parser<'_>, recovery_set: TokenSet) -> CompletedMarker {
+    let m = p.start();
+    p.bump();
+
+    if p.at_set(PATTERN_FIRST) {
+        parse_pattern(p, recovery_set);
+        return m.complete(p, NodeKind::VariantPattern);
+    }
+
+    m.complete(p, NodeKind::BindingPattern)
+}
+
+/// Parses `(a, b)`, with `parse_element` deciding what can go between the parentheses.
+pub(super) fn parse_tuple_pattern(
+    p: &mut Parser<'_>,
+    recovery_set: TokenSet,
+    parse_element: fn(&mut Parser<'_>, TokenSet) -> Option<CompletedMarker>,
+) -> CompletedMarker {
+    assert!(p.at(TokenKind::LParen));
+    let m = p.start();
+    p.bump();
+
+    let element_recovery_set =
+        recovery_set.union(TokenSet::new([TokenKind::Comma, TokenKind::RParen]));
+
+    while !p.at(TokenKind::RParen) && !p.at_eof() {
+        parse_element(p, element_recovery_set);
+
+        if p.at(TokenKind::Comma) {
+            p.bump();
+        } else {
+            break;
+        }
+    }
+
+    p.expect_with_recovery_set(TokenKind::RParen, recovery_set);
+
+    m.complete(p, NodeKind::TuplePattern)
+}
+
+fn parse_literal_pattern(p: &mut Parser<'_>) -> CompletedMarker {
+    let m = p.start();
+
+    if p.at(TokenKind::Hyphen) {
+        p.bump();
+
+        let _guard = p.expected_syntax_name("integer");
+        if p.at(TokenKind::Int) {
+            parse_int_literal(p);
+        } else {
+            p.error_with_no_skip();
+        }
+    } else if p.at(TokenKind::Int) {
+        parse_int_literal(p);
+    } else if p.at(TokenKind::TrueKw) || p.at(TokenKind::FalseKw) {
+        parse_bool_literal(p);
+    } else if p.at(TokenKind::Quote) {
+        parse_string_literal(p);
+    } else {
+        parse_char_literal(p);
+    }
+
+    m.complete(p, NodeKind::LiteralPattern)
+}
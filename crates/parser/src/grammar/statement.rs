@@ -1,9 +1,25 @@
-use crate::grammar::expr::parse_expr;
+use crate::grammar::def::DEF_FIRST;
+use crate::grammar::expr::{parse_expr, EXPR_FIRST};
+use crate::grammar::pattern::{parse_binding_pattern, parse_tuple_pattern};
+use crate::grammar::ty::parse_ty;
 use crate::parser::{CompletedMarker, Parser};
 use crate::token_set::TokenSet;
 use syntax::{NodeKind, TokenKind};
 
+const STATEMENT_FIRST: TokenSet = EXPR_FIRST.union(TokenSet::new([
+    TokenKind::LetKw,
+    TokenKind::BreakKw,
+    TokenKind::ContinueKw,
+    TokenKind::ReturnKw,
+    TokenKind::DeferKw,
+]));
+
+// where the next statement starts, or where the block ends
+const STATEMENT_RECOVERY_SET: TokenSet =
+    DEF_FIRST.union(TokenSet::new([TokenKind::LetKw, TokenKind::Semicolon, TokenKind::RBrace]));
+
 pub(super) fn parse_statement(p: &mut Parser<'_>) -> Option<CompletedMarker> {
+    let errors = p.error_count();
     let _guard = p.expected_syntax_name("statement");
 
     if p.at(TokenKind::Semicolon) {
@@ -11,35 +27,170 @@ pub(super) fn parse_statement(p: &mut Parser<'_>) -> Option<CompletedMarker> {
     }
 
     if p.at(TokenKind::LetKw) {
-        return Some(parse_local_def(p));
+        return Some(parse_local_def(p, errors));
+    }
+
+    if p.at(TokenKind::BreakKw) {
+        return Some(parse_jump(p, NodeKind::Break, errors));
+    }
+
+    if p.at(TokenKind::ContinueKw) {
+        return Some(parse_jump(p, NodeKind::Continue, errors));
+    }
+
+    if p.at(TokenKind::ReturnKw) {
+        return Some(parse_jump(p, NodeKind::Return, errors));
+    }
+
+    if p.at(TokenKind::DeferKw) {
+        return Some(parse_defer(p, errors));
     }
 
     let cm = parse_expr(p, "statement")?;
 
+    if p.at_operator(TokenKind::Eq) {
+        return Some(parse_assign(p, cm, errors));
+    }
+
     if p.at(TokenKind::RBrace) || p.at_eof() {
         return Some(cm);
     }
 
     let m = cm.precede(p);
-    p.expect_with_no_skip(TokenKind::Semicolon);
+    expect_statement_end(p, errors);
 
     Some(m.complete(p, NodeKind::ExprStatement))
 }
 
-fn parse_local_def(p: &mut Parser<'_>) -> CompletedMarker {
+// A statement with an error in it
+// is skipped up to where the next one starts,
+// so that the rest of it can’t set off errors of its own.
+// The same goes for one followed by something that can’t start a statement,
+// while one which is only missing its `;` is left alone,
+// since what comes next is probably meant as the next statement.
+//
+// `errors` is how many errors there were before the statement,
+// since one that already has an error doesn’t need another about its end.
+fn expect_statement_end(p: &mut Parser<'_>, errors: usize) {
+    if p.at(TokenKind::Semicolon) {
+        p.bump();
+        return;
+    }
+
+    let is_garbled = p.error_count() > errors;
+    if !is_garbled {
+        p.error_with_no_skip();
+
+        if p.at_set(STATEMENT_FIRST) {
+            return;
+        }
+    }
+
+    p.skip_to(STATEMENT_RECOVERY_SET);
+    if p.at(TokenKind::Semicolon) {
+        p.bump();
+    }
+}
+
+// `continue` only consists of its keyword and an optional label,
+// while `break` can also carry the value of the loop or block it exits
+// and `return` that of the function it exits
+fn parse_jump(p: &mut Parser<'_>, kind: NodeKind, errors: usize) -> CompletedMarker {
+    assert!(p.at(TokenKind::BreakKw) || p.at(TokenKind::ContinueKw) || p.at(TokenKind::ReturnKw));
+    let m = p.start();
+    p.bump();
+
+    if kind != NodeKind::Return && p.at(TokenKind::Label) {
+        p.bump();
+    }
+
+    let value_name = match kind {
+        NodeKind::Break => Some("break value"),
+        NodeKind::Return => Some("return value"),
+        _ => None,
+    };
+
+    if let Some(value_name) = value_name {
+        if p.at_set(EXPR_FIRST) {
+            parse_expr(p, value_name);
+        }
+    }
+
+    expect_statement_end(p, errors);
+
+    m.complete(p, kind)
+}
+
+fn parse_defer(p: &mut Parser<'_>, errors: usize) -> CompletedMarker {
+    assert!(p.at(TokenKind::DeferKw));
+    let m = p.start();
+    p.bump();
+
+    parse_expr(p, "deferred expression");
+
+    expect_statement_end(p, errors);
+
+    m.complete(p, NodeKind::Defer)
+}
+
+fn parse_local_def(p: &mut Parser<'_>, errors: usize) -> CompletedMarker {
     assert!(p.at(TokenKind::LetKw));
     let m = p.start();
     p.bump();
 
-    {
+    if p.at(TokenKind::MutKw) {
+        p.bump();
+    }
+
+    if p.at(TokenKind::LParen) {
+        parse_tuple_pattern(
+            p,
+            TokenSet::new([TokenKind::Colon, TokenKind::Eq]),
+            parse_variable_name,
+        );
+    } else {
         let _guard = p.expected_syntax_name("variable name");
-        p.expect_with_recovery_set(TokenKind::Ident, TokenSet::new([TokenKind::Eq]));
+        p.expect_with_recovery_set(
+            TokenKind::Ident,
+            TokenSet::new([TokenKind::Colon, TokenKind::Eq]),
+        );
+    }
+
+    if p.at(TokenKind::Colon) {
+        p.bump();
+
+        let _guard = p.expected_syntax_name("variable type");
+        parse_ty(p, TokenSet::new([TokenKind::Eq]));
     }
 
     p.expect(TokenKind::Eq);
     parse_expr(p, "variable value");
 
-    p.expect_with_no_skip(TokenKind::Semicolon);
+    expect_statement_end(p, errors);
 
     m.complete(p, NodeKind::LocalDef)
 }
+
+// `let (x, y) = pair;` binds every element of a tuple to a variable of its own
+fn parse_variable_name(p: &mut Parser<'_>, recovery_set: TokenSet) -> Option<CompletedMarker> {
+    if p.at(TokenKind::Ident) {
+        return Some(parse_binding_pattern(p));
+    }
+
+    let _guard = p.expected_syntax_name("variable name");
+    p.error_with_recovery_set(recovery_set)
+}
+
+// the target is parsed as an expression
+// and checked to be a variable during lowering
+fn parse_assign(p: &mut Parser<'_>, target: CompletedMarker, errors: usize) -> CompletedMarker {
+    assert!(p.at(TokenKind::Eq));
+    let m = target.precede(p);
+    p.bump();
+
+    parse_expr(p, "assigned value");
+
+    expect_statement_end(p, errors);
+
+    m.complete(p, NodeKind::Assign)
+}
@@ -4,6 +4,59 @@ use syntax::{NodeKind, TokenKind};
 
 pub(super) fn parse_ty(p: &mut Parser<'_>, recovery_set: TokenSet) -> CompletedMarker {
     let m = p.start();
-    p.expect_with_recovery_set(TokenKind::Ident, recovery_set);
+
+    if p.at(TokenKind::LParen) {
+        p.bump();
+
+        let element_recovery_set =
+            recovery_set.union(TokenSet::new([TokenKind::Comma, TokenKind::RParen]));
+
+        while p.at(TokenKind::Ident) || p.at(TokenKind::LParen) || p.at(TokenKind::LBracket) {
+            parse_ty(p, element_recovery_set);
+
+            if p.at(TokenKind::Comma) {
+                p.bump();
+            } else {
+                break;
+            }
+        }
+
+        p.expect_with_recovery_set(TokenKind::RParen, recovery_set);
+    } else if p.at(TokenKind::LBracket) {
+        p.bump();
+        parse_ty(p, recovery_set.union(TokenSet::new([TokenKind::RBracket])));
+        p.expect_with_recovery_set(TokenKind::RBracket, recovery_set);
+    } else {
+        p.expect_with_recovery_set(TokenKind::Ident, recovery_set);
+
+        if p.at(TokenKind::LBracket) {
+            parse_ty_arg_list(p, recovery_set);
+        }
+    }
+
     m.complete(p, NodeKind::Ty)
 }
+
+// the arguments of a generic type, like the `[s32]` in `Option[s32]`
+fn parse_ty_arg_list(p: &mut Parser<'_>, recovery_set: TokenSet) {
+    assert!(p.at(TokenKind::LBracket));
+    let m = p.start();
+    p.bump();
+
+    let arg_recovery_set =
+        recovery_set.union(TokenSet::new([TokenKind::Comma, TokenKind::RBracket]));
+
+    while p.at(TokenKind::Ident) || p.at(TokenKind::LParen) || p.at(TokenKind::LBracket) {
+        parse_ty(p, arg_recovery_set);
+
+        if p.at(TokenKind::Comma) {
+            p.bump();
+        } else {
+            break;
+        }
+    }
+
+    p.expect_with_recovery_set(TokenKind::RBracket, recovery_set);
+
+    m.complete(p, NodeKind::TyArgList);
+}
@@ -14,6 +14,11 @@ use token::Tokens;
 const DEFAULT_RECOVERY_SET: TokenSet = TokenSet::new([
     TokenKind::LetKw,
     TokenKind::FncKw,
+    TokenKind::ExternKw,
+    TokenKind::TypeKw,
+    TokenKind::UseKw,
+    TokenKind::PubKw,
+    TokenKind::ConstKw,
     TokenKind::LBrace,
     TokenKind::RBrace,
     TokenKind::Semicolon,
@@ -26,8 +31,13 @@ pub(crate) struct Parser<'tokens> {
     token_idx: usize,
     events: Vec<Option<Event>>,
     errors: Vec<SyntaxError>,
-    expected_syntax: Option<ExpectedSyntax>,
+    // everything checked for since the last token was bumped,
+    // all of which would have been fine in place of the current token
+    expected_syntaxes: Vec<ExpectedSyntax>,
     expected_syntax_tracking_state: Rc<Cell<ExpectedSyntaxTrackingState>>,
+    // whether a `{` can start an argument to a call;
+    // it can’t where it starts the body of a `for` loop instead
+    block_args_allowed: bool,
 }
 
 impl<'tokens> Parser<'tokens> {
@@ -37,10 +47,11 @@ impl<'tokens> Parser<'tokens> {
             token_idx: 0,
             events: Vec::new(),
             errors: Vec::new(),
-            expected_syntax: None,
+            expected_syntaxes: Vec::new(),
             expected_syntax_tracking_state: Rc::new(Cell::new(
                 ExpectedSyntaxTrackingState::Unnamed,
             )),
+            block_args_allowed: true,
         }
     }
 
@@ -91,13 +102,19 @@ impl<'tokens> Parser<'tokens> {
         recovery_set: TokenSet,
     ) -> Option<CompletedMarker> {
         // we must have been expecting something if there was an error
-        let expected_syntax = self.expected_syntax.take().unwrap();
+        let mut expected_syntaxes = mem::take(&mut self.expected_syntaxes);
+        let expected_syntax = if expected_syntaxes.len() == 1 {
+            expected_syntaxes.pop().unwrap()
+        } else {
+            assert!(!expected_syntaxes.is_empty());
+            ExpectedSyntax::OneOf(expected_syntaxes)
+        };
         self.expected_syntax_tracking_state.set(ExpectedSyntaxTrackingState::Unnamed);
 
         if self.at_eof() || self.at_set(recovery_set) {
             let range = self.previous_token_range();
             self.errors.push(SyntaxError {
-                expected_syntax,
+                expected_syntax: expected_syntax.clone(),
                 kind: SyntaxErrorKind::Missing { offset: range.end() },
             });
 
@@ -117,14 +134,64 @@ impl<'tokens> Parser<'tokens> {
         Some(m.complete(self, NodeKind::Error))
     }
 
+    pub(crate) fn error_count(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Puts everything up to the next token in `set` into an error node
+    /// without reporting another error,
+    /// skipping over anything in braces as a whole.
+    pub(crate) fn skip_to(&mut self, set: TokenSet) {
+        self.clear_expected_syntaxes();
+        if self.at_eof() || self.at_set(set) {
+            return;
+        }
+
+        let m = self.start();
+        let mut depth = 0_u32;
+
+        while !self.at_eof() {
+            if depth == 0 && self.at_set(set) {
+                break;
+            }
+
+            match self.peek() {
+                Some(TokenKind::LBrace) => depth += 1,
+                Some(TokenKind::RBrace) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.bump();
+        }
+
+        m.complete(self, NodeKind::Error);
+    }
+
     #[must_use]
     pub(crate) fn expected_syntax_name(&mut self, name: &'static str) -> ExpectedSyntaxGuard {
         self.expected_syntax_tracking_state.set(ExpectedSyntaxTrackingState::Named);
-        self.expected_syntax = Some(ExpectedSyntax::Named(name));
+        // the name covers anything checked for here before it
+        self.expected_syntaxes.clear();
+        self.expected_syntaxes.push(ExpectedSyntax::Named(name));
 
         ExpectedSyntaxGuard::new(Rc::clone(&self.expected_syntax_tracking_state))
     }
 
+    pub(crate) fn with_block_args_allowed<T>(
+        &mut self,
+        allowed: bool,
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let previous = mem::replace(&mut self.block_args_allowed, allowed);
+        let result = f(self);
+        self.block_args_allowed = previous;
+
+        result
+    }
+
+    pub(crate) fn block_args_allowed(&self) -> bool {
+        self.block_args_allowed
+    }
+
     pub(crate) fn start(&mut self) -> Marker {
         let pos = self.events.len();
         self.events.push(None);
@@ -134,13 +201,40 @@ impl<'tokens> Parser<'tokens> {
 
     pub(crate) fn at(&mut self, kind: TokenKind) -> bool {
         if let ExpectedSyntaxTrackingState::Unnamed = self.expected_syntax_tracking_state.get() {
-            self.expected_syntax = Some(ExpectedSyntax::Unnamed(kind));
+            self.add_expected_syntax(ExpectedSyntax::Unnamed(kind));
         }
 
         self.skip_trivia();
         self.at_raw(kind)
     }
 
+    /// Like [`Parser::at`], but for an operator,
+    /// which isn’t listed among what was expected if it isn’t there:
+    /// one can follow almost any expression,
+    /// so listing them all would bury what the error is about.
+    pub(crate) fn at_operator(&mut self, kind: TokenKind) -> bool {
+        self.skip_trivia();
+        self.at_raw(kind)
+    }
+
+    /// Whether the token right after the current one,
+    /// without any whitespace in between, is of the given kind.
+    pub(crate) fn next_at(&mut self, kind: TokenKind) -> bool {
+        self.skip_trivia();
+        self.tokens.get_kind(self.token_idx + 1) == Some(kind)
+    }
+
+    /// Whether the current token is of the given kind
+    /// and directly follows the previous one, without any whitespace in between,
+    /// as an operator.
+    pub(crate) fn at_immediately(&mut self, kind: TokenKind) -> bool {
+        self.at_operator(kind)
+            && !matches!(
+                self.tokens.get_kind(self.token_idx.wrapping_sub(1)),
+                Some(TokenKind::Whitespace | TokenKind::CommentLeader | TokenKind::CommentContents)
+            )
+    }
+
     pub(crate) fn at_eof(&mut self) -> bool {
         self.skip_trivia();
         self.token_idx >= self.tokens.len()
@@ -152,7 +246,7 @@ impl<'tokens> Parser<'tokens> {
 
     pub(crate) fn at_set(&mut self, set: TokenSet) -> bool {
         self.skip_trivia();
-        self.peek().map_or(false, |kind| set.contains(kind))
+        self.peek().is_some_and(|kind| set.contains(kind))
     }
 
     pub(crate) fn bump(&mut self) {
@@ -161,8 +255,14 @@ impl<'tokens> Parser<'tokens> {
         self.token_idx += 1;
     }
 
+    fn add_expected_syntax(&mut self, expected_syntax: ExpectedSyntax) {
+        if !self.expected_syntaxes.contains(&expected_syntax) {
+            self.expected_syntaxes.push(expected_syntax);
+        }
+    }
+
     fn clear_expected_syntaxes(&mut self) {
-        self.expected_syntax = None;
+        self.expected_syntaxes.clear();
         self.expected_syntax_tracking_state.set(ExpectedSyntaxTrackingState::Unnamed);
     }
 
@@ -187,7 +287,7 @@ impl<'tokens> Parser<'tokens> {
     }
 
     fn at_raw(&self, kind: TokenKind) -> bool {
-        self.peek().map_or(false, |k| k == kind)
+        self.peek() == Some(kind)
     }
 
     fn peek(&self) -> Option<TokenKind> {
@@ -0,0 +1,54 @@
+//! The standard library, written in gingerbread itself.
+//!
+//! It is loaded alongside the modules of every project under the name `std`,
+//! so its functions can be called with `std.square 5` and the like.
+
+pub const MODULE_NAME: &str = "std";
+
+pub const SOURCE: &str = include_str!("std.gb");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::AstNode;
+    use interner::Interner;
+
+    #[test]
+    fn type_checks() {
+        let mut interner = Interner::default();
+        let mut world_index = hir::WorldIndex::default();
+
+        let tokens = lexer::lex(SOURCE);
+        let parse = parser::parse_source_file(&tokens, SOURCE);
+        assert_eq!(parse.errors(), []);
+
+        let tree = parse.into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        assert_eq!(ast::validation::validate(root, &tree), []);
+
+        let (index, d) = hir::index(root, &tree, &world_index, &mut interner);
+        assert_eq!(d, []);
+        world_index.add_module(hir::Name(interner.intern(MODULE_NAME)), index.clone());
+
+        let (bodies, d) = hir::lower(root, &tree, &index, &world_index, &mut interner);
+        assert_eq!(d, []);
+
+        let (_, d) = hir_ty::infer_all(&bodies, &index, &world_index);
+        assert_eq!(d, []);
+    }
+
+    #[test]
+    fn every_function_is_pub() {
+        let mut interner = Interner::default();
+
+        let tokens = lexer::lex(SOURCE);
+        let tree = parser::parse_source_file(&tokens, SOURCE).into_syntax_tree();
+        let root = ast::Root::cast(tree.root(), &tree).unwrap();
+        let (index, _) = hir::index(root, &tree, &hir::WorldIndex::default(), &mut interner);
+
+        for name in index.functions() {
+            let function = index.get_function(name).unwrap();
+            assert!(function.is_pub, "`{}` isn’t pub", interner.lookup(name.0));
+        }
+    }
+}
@@ -19,8 +19,27 @@ impl eventree::TreeConfig for TreeConfig {
 pub enum TokenKind {
     LetKw,
     FncKw,
+    ExternKw,
+    TrueKw,
+    FalseKw,
+    LoopKw,
+    BreakKw,
+    ContinueKw,
+    MutKw,
+    ForKw,
+    InKw,
+    ReturnKw,
+    AsKw,
+    MatchKw,
+    TypeKw,
+    UseKw,
+    PubKw,
+    ConstKw,
+    DeferKw,
     Ident,
     Int,
+    Char,
+    Label,
     Quote,
     Escape,
     StringContents,
@@ -28,16 +47,30 @@ pub enum TokenKind {
     Hyphen,
     Asterisk,
     Slash,
+    EqEq,
+    BangEq,
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    AmpAmp,
+    PipePipe,
+    Bang,
+    Question,
     Eq,
     Dot,
+    DotDot,
     Colon,
     Comma,
     Semicolon,
     Arrow,
     LParen,
     RParen,
+    LBracket,
+    RBracket,
     LBrace,
     RBrace,
+    Hash,
     Whitespace,
     CommentContents,
     CommentLeader,
@@ -54,15 +87,46 @@ pub enum NodeKind {
     Arg,
     Block,
     IntLiteral,
+    BoolLiteral,
     StringLiteral,
+    CharLiteral,
     BinaryExpr,
+    UnaryExpr,
+    CastExpr,
+    TupleExpr,
+    FieldExpr,
+    MethodCall,
+    ArrayExpr,
+    IndexExpr,
+    TryExpr,
+    MatchExpr,
+    MatchArm,
     LocalDef,
+    TuplePattern,
+    BindingPattern,
+    LiteralPattern,
+    VariantPattern,
     ExprStatement,
+    Assign,
+    Loop,
+    For,
+    RangeExpr,
+    Break,
+    Continue,
+    Return,
+    Defer,
     Function,
+    TyAlias,
+    Import,
+    Const,
+    TyParamList,
     ParamList,
     Param,
     ReturnTy,
     Ty,
+    TyArgList,
+    Attr,
+    AttrArgList,
     Comment,
     DocComment,
     Docs,